@@ -0,0 +1,74 @@
+//! Loading of the device recording described by `apps/fixtures/manifest.json`, shared by the
+//! benchmarks here and the tests, which include this file with `#[path]`.
+
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+
+use appattest_rs::assertion::Assertion;
+use appattest_rs::attestation::{Attestation, Environment};
+use base64::{engine::general_purpose, Engine};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    pub app_id: String,
+    pub recorded_at: i64,
+    pub environment: Environment,
+    pub attestation: RecordedAttestation,
+    pub assertions: Vec<RecordedAssertion>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordedAttestation {
+    pub file: String,
+    pub challenge: String,
+    pub key_id: String,
+    pub public_key: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordedAssertion {
+    pub file: String,
+    pub client_data: String,
+    pub counter: u32,
+}
+
+pub fn fixtures() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../apps/fixtures")
+}
+
+/// The trimmed contents of the fixture `name`.
+pub fn fixture(name: &str) -> String {
+    let path = fixtures().join(name);
+    std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {e}", path.display())).trim().to_string()
+}
+
+/// The fixture `name`, decoded from base64.
+pub fn decoded(name: &str) -> Vec<u8> {
+    general_purpose::STANDARD.decode(fixture(name)).unwrap()
+}
+
+pub fn manifest() -> Manifest {
+    serde_json::from_str(&fixture("manifest.json")).unwrap()
+}
+
+impl Manifest {
+    pub fn app_id_hash(&self) -> [u8; 32] {
+        Sha256::digest(&self.app_id).into()
+    }
+
+    pub fn attestation(&self) -> Attestation {
+        Attestation::from_base64(&fixture(&self.attestation.file)).unwrap()
+    }
+}
+
+impl RecordedAssertion {
+    pub fn assertion(&self) -> Assertion {
+        Assertion::from_base64(&fixture(&self.file)).unwrap()
+    }
+}
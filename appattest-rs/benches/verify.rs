@@ -0,0 +1,123 @@
+//! Decoding and verification of the recorded device objects, see `baseline.md` for the
+//! numbers to compare against.
+//!
+//! `verify_raw` allocates a fresh object for each iteration, since verifying consumes it;
+//! the `parse` benchmarks show what that costs.
+
+use appattest_rs::assertion::Assertion;
+use appattest_rs::attestation::{Attestation, AttestationRef};
+use appattest_rs::key::PublicKey;
+use base64::{engine::general_purpose, Engine};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+
+mod bench_utils;
+
+use bench_utils::{decoded, fixture, manifest};
+
+fn attestation(c: &mut Criterion) {
+    let manifest = manifest();
+    let text = fixture(&manifest.attestation.file);
+    let cbor = decoded(&manifest.attestation.file);
+    let challenge = decoded(&manifest.attestation.challenge);
+    let key_id = decoded(&manifest.attestation.key_id);
+    let app_id_hash = manifest.app_id_hash();
+    let public_key = manifest.attestation().verify_chain(&challenge, manifest.recorded_at).unwrap().public_key;
+
+    let mut group = c.benchmark_group("attestation");
+    group.bench_function("parse_cbor", |b| b.iter(|| Attestation::from_cbor(black_box(&cbor)).unwrap()));
+    group.bench_function("parse_base64", |b| b.iter(|| Attestation::from_base64(black_box(&text)).unwrap()));
+    // What `parse_base64` did before streaming: the whole object decoded, then parsed.
+    group.bench_function("parse_base64_buffered", |b| {
+        b.iter(|| Attestation::from_cbor(&general_purpose::STANDARD.decode(black_box(&text)).unwrap()).unwrap())
+    });
+    // The chain crypto alone: certificate signatures, validity and the nonce.
+    group.bench_function("verify_chain", |b| {
+        let attestation = manifest.attestation();
+        b.iter(|| attestation.verify_chain(black_box(&challenge), manifest.recorded_at).unwrap())
+    });
+    // Everything but the chain, for a key already taken from a verified chain.
+    group.bench_function("verify_auth_data", |b| {
+        b.iter_batched(
+            || manifest.attestation(),
+            |attestation| attestation.verify_auth_data(&app_id_hash, &key_id, &public_key).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("verify_raw", |b| {
+        b.iter_batched(
+            || manifest.attestation(),
+            |attestation| attestation.verify_raw(&challenge, &app_id_hash, &key_id, manifest.recorded_at).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+    // The non-cryptographic checks alone, from a fresh copy since the report is kept.
+    group.bench_function("validate_structure", |b| {
+        b.iter_batched(|| manifest.attestation(), |attestation| attestation.validate_structure().unwrap(), BatchSize::SmallInput)
+    });
+    // `verify_raw` after `validate_structure`: the leaf's key, nonce and expiry come from the
+    // report, so the chain walk is the only certificate parsing left.
+    group.bench_function("verify_raw_validated", |b| {
+        b.iter_batched(
+            || {
+                let attestation = manifest.attestation();
+                attestation.validate_structure().unwrap();
+                attestation
+            },
+            |attestation| attestation.verify_raw(&challenge, &app_id_hash, &key_id, manifest.recorded_at).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+    // The borrowed object: parsing copies no byte strings, and verifying takes no fresh copy.
+    group.bench_function("parse_cbor_borrowed", |b| b.iter(|| AttestationRef::from_cbor(black_box(&cbor)).unwrap()));
+    group.bench_function("verify_raw_borrowed", |b| {
+        b.iter(|| {
+            let attestation = AttestationRef::from_cbor(black_box(&cbor)).unwrap();
+            attestation.verify_raw(&challenge, &app_id_hash, &key_id, manifest.recorded_at).unwrap()
+        })
+    });
+    group.finish();
+}
+
+fn assertion(c: &mut Criterion) {
+    let manifest = manifest();
+    let recorded = &manifest.assertions[0];
+    let cbor = decoded(&recorded.file);
+    let client_data = decoded(&recorded.client_data);
+    let text_client_data = fixture(&recorded.client_data);
+    let app_id_hash = manifest.app_id_hash();
+    let pem = fixture(&manifest.attestation.public_key);
+    let public_key = PublicKey::from_pem(&pem).unwrap().to_uncompressed();
+
+    let mut group = c.benchmark_group("assertion");
+    group.bench_function("parse_cbor", |b| b.iter(|| Assertion::from_cbor(black_box(&cbor)).unwrap()));
+    group.bench_function("verify_raw", |b| {
+        b.iter_batched(
+            || recorded.assertion(),
+            |assertion| assertion.verify_raw(&client_data, &app_id_hash, &public_key, None).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+    // The text API, which decodes the client data and builds the key from PEM each time.
+    group.bench_function("verify_pem", |b| {
+        b.iter_batched(
+            || recorded.assertion(),
+            |assertion| assertion.verify(&text_client_data, &manifest.app_id, &pem, None, None).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+fn public_key(c: &mut Criterion) {
+    let manifest = manifest();
+    let pem = fixture(&manifest.attestation.public_key);
+    let sec1 = PublicKey::from_pem(&pem).unwrap().to_uncompressed();
+
+    let mut group = c.benchmark_group("public_key");
+    group.bench_function("from_pem", |b| b.iter(|| PublicKey::from_pem(black_box(&pem)).unwrap()));
+    group.bench_function("from_sec1", |b| b.iter(|| PublicKey::from_uncompressed(black_box(&sec1)).unwrap()));
+    group.finish();
+}
+
+criterion_group!(benches, attestation, assertion, public_key);
+criterion_main!(benches);
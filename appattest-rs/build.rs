@@ -1,75 +1,113 @@
-//! build.rs – downloads Apple’s App Attest root **PEM**, converts to **DER**, and
-//! stores both in `certificates/`.
+//! build.rs – compiles every **PEM** / **DER** root in `certificates/` into the store the
+//! crate embeds, after checking Apple's App Attest root against its pinned fingerprint.
+//!
+//! The build is offline. Only with the `fetch-root` feature is a missing Apple PEM
+//! downloaded, and the download must match the pin too. The checks live in `src/root.rs`,
+//! where they are unit tested.
 
-use std::{fs, path::Path, process::exit, time::Duration};
-use base64::Engine;
-use base64::engine::general_purpose;
+use std::{env, fmt::Write, fs, path::Path, process::exit};
 
-/// Remote Apple PEM (never changes).
-const PEM_URL: &str =
-    "https://www.apple.com/certificateauthority/Apple_App_Attestation_Root_CA.pem";
+#[path = "src/root.rs"]
+#[allow(dead_code)]
+mod root;
 
-/// Local paths.
+/// Checked-in roots, Apple's vendored copy among them.
 const CERT_DIR: &str = "certificates";
-const PEM_PATH: &str = "certificates/Apple_App_Attestation_Root_CA.pem";
-const DER_PATH: &str = "certificates/Apple_App_Attestation_Root_CA.der";
 
 fn main() {
-    // Re-run when this script or the generated DER changes
     println!("cargo:rerun-if-changed=build.rs");
-    println!("cargo:rerun-if-changed={DER_PATH}");
+    println!("cargo:rerun-if-changed=src/root.rs");
+    println!("cargo:rerun-if-changed={CERT_DIR}");
+    println!("cargo:rerun-if-env-changed={}", root::ALLOW_STALE_ENV);
 
-    // Fetch + convert only if DER is missing
-    if Path::new(DER_PATH).exists() {
-        return;
+    let dir = Path::new(CERT_DIR);
+    root::ensure_apple_root(dir, fetch::fetcher()).unwrap_or_else(|e| fail(&e));
+
+    // Every root parses, none is repeated, and Apple's is the pinned one.
+    let roots = root::collect(dir).unwrap_or_else(|e| fail(&e));
+    let allow_stale = env::var(root::ALLOW_STALE_ENV).as_deref() == Ok("1");
+    if let Some(warning) = root::check_apple_pin(&roots, allow_stale).unwrap_or_else(|e| fail(&e)) {
+        println!("cargo:warning=⚠️  {warning}");
     }
 
-    // Ensure the target directory exists
-    fs::create_dir_all(CERT_DIR).expect("failed to create certificates/ directory");
+    // Rebuilt from `certificates/` on every run, so no earlier DER can outlive its source.
+    let out_dir = Path::new(&env::var("OUT_DIR").expect("OUT_DIR is set by cargo")).join("roots");
+    let _ = fs::remove_dir_all(&out_dir);
+    write_store(&out_dir, &roots);
 
-    // Download PEM
-    let pem_bytes = match fetch_pem(PEM_URL) {
-        Ok(b) => b,
-        Err(e) => {
-            eprintln!("❌  {e}");
-            exit(1);
-        }
+    // Google's roots, for `androidattest`; not vendored, so an empty store only warns.
+    let android_dir = dir.join(root::ANDROID_ROOT_DIR);
+    println!("cargo:rerun-if-changed={}", android_dir.display());
+    let android = if android_dir.is_dir() {
+        root::collect(&android_dir).unwrap_or_else(|e| fail(&e))
+    } else {
+        Vec::new()
     };
+    if android.is_empty() && env::var_os("CARGO_FEATURE_ANDROID").is_some() {
+        println!(
+            "cargo:warning=⚠️  {} holds no Google hardware attestation root, so androidattest trusts none",
+            android_dir.display()
+        );
+    }
+    write_store(&out_dir.join(root::ANDROID_ROOT_DIR), &android);
+}
 
-    // Save PEM for reference/debugging
-    fs::write(PEM_PATH, &pem_bytes).expect("unable to write PEM file");
+/// Writes `roots` to `out_dir` as DER files and `roots.rs`, a `&[(name, DER)]` included by
+/// `attestation::EMBEDDED_ROOTS` and `androidattest::EMBEDDED_ROOTS`.
+fn write_store(out_dir: &Path, roots: &[(String, Vec<u8>)]) {
+    fs::create_dir_all(out_dir).expect("unable to create roots/ in OUT_DIR");
+    let mut store = String::from("&[\n");
+    for (name, der) in roots {
+        let path = out_dir.join(format!("{name}.der"));
+        fs::write(&path, der).expect("unable to write DER file");
+        writeln!(store, "    ({name:?}, include_bytes!({:?})),", path.display().to_string()).unwrap();
+    }
+    store.push_str("]\n");
+    fs::write(out_dir.join("roots.rs"), store).expect("unable to write root store");
+}
 
-    // Convert to DER and save
-    let der_bytes = match pem_to_der(&pem_bytes) {
-        Ok(b) => b,
-        Err(e) => {
-            eprintln!("❌  PEM → DER conversion failed: {e}");
-            exit(1);
-        }
-    };
-    fs::write(DER_PATH, der_bytes).expect("unable to write DER file");
+/// Prints `error` with what to do about it, and fails the build.
+fn fail(error: &root::RootCertError) -> ! {
+    eprintln!("❌  {CERT_DIR}: {error}\n\n{}", error.remediation());
+    exit(1);
 }
 
-/// Download the PEM as raw bytes.
-fn fetch_pem(url: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-    let client = reqwest::blocking::Client::builder()
-        .timeout(Duration::from_secs(15))
-        .build()?;
+#[cfg(feature = "fetch-root")]
+mod fetch {
+    use std::time::Duration;
 
-    let resp = client.get(url).send()?;
-    if !resp.status().is_success() {
-        return Err(format!("HTTP error: {}", resp.status()).into());
+    use super::root::{Fetch, FetchError};
+
+    /// Downloads over HTTPS, telling "no response" apart from an error status.
+    struct Http;
+
+    impl Fetch for Http {
+        fn fetch(&self, url: &str) -> Result<Vec<u8>, FetchError> {
+            let offline = |e: reqwest::Error| FetchError::Offline(e.to_string());
+            let client = reqwest::blocking::Client::builder()
+                .timeout(Duration::from_secs(15))
+                .build()
+                .map_err(offline)?;
+
+            let resp = client.get(url).send().map_err(offline)?;
+            if !resp.status().is_success() {
+                return Err(FetchError::Status(resp.status().as_u16()));
+            }
+
+            Ok(resp.bytes().map_err(offline)?.to_vec())
+        }
     }
 
-    Ok(resp.bytes()?.to_vec())
+    pub fn fetcher() -> Option<&'static dyn Fetch> {
+        Some(&Http)
+    }
 }
 
-/// Strip PEM headers/footers and base64-decode the body.
-fn pem_to_der(pem: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-    let pem_str = std::str::from_utf8(pem)?;
-    let b64: String = pem_str
-        .lines()
-        .filter(|l| !l.starts_with("-----")) // skip BEGIN/END lines
-        .collect();
-    Ok(general_purpose::STANDARD.decode(b64.trim())?)
+#[cfg(not(feature = "fetch-root"))]
+mod fetch {
+    use super::root::Fetch;
+
+    pub fn fetcher() -> Option<&'static dyn Fetch> {
+        None
+    }
 }
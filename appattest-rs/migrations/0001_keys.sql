@@ -0,0 +1,10 @@
+-- Attested keys, see store::sqlite::SqliteKeyStore. Times are Unix seconds.
+CREATE TABLE keys (
+    key_id       TEXT PRIMARY KEY NOT NULL,
+    public_key   BLOB NOT NULL,
+    counter      INTEGER NOT NULL,
+    environment  TEXT CHECK (environment IN ('production', 'development')),
+    receipt      BLOB NOT NULL,
+    created_at   INTEGER NOT NULL,
+    last_used_at INTEGER
+);
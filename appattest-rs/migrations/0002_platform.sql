@@ -0,0 +1,2 @@
+-- The platform that attested each key; every key stored before this was Apple's.
+ALTER TABLE keys ADD COLUMN platform TEXT NOT NULL DEFAULT 'apple' CHECK (platform IN ('apple', 'android'));
@@ -0,0 +1,16 @@
+-- A key's counter is NULL until its first assertion, which may then be 0. SQLite cannot drop
+-- NOT NULL in place, so the table is rebuilt; keys stored before this keep their counter.
+CREATE TABLE keys_new (
+    key_id       TEXT PRIMARY KEY NOT NULL,
+    public_key   BLOB NOT NULL,
+    counter      INTEGER,
+    environment  TEXT CHECK (environment IN ('production', 'development')),
+    receipt      BLOB NOT NULL,
+    created_at   INTEGER NOT NULL,
+    last_used_at INTEGER,
+    platform     TEXT NOT NULL DEFAULT 'apple' CHECK (platform IN ('apple', 'android'))
+);
+INSERT INTO keys_new (key_id, public_key, counter, environment, receipt, created_at, last_used_at, platform)
+    SELECT key_id, public_key, counter, environment, receipt, created_at, last_used_at, platform FROM keys;
+DROP TABLE keys;
+ALTER TABLE keys_new RENAME TO keys;
@@ -0,0 +1,3 @@
+-- When the certificate that attested each key expires, in Unix seconds; NULL for keys stored
+-- before this, or attested without a chain.
+ALTER TABLE keys ADD COLUMN leaf_not_after INTEGER;
@@ -0,0 +1,15 @@
+-- Keys replaced by a re-attestation, see KeyStore::archive: the row as it was in keys, and
+-- when it was moved here. A key may be archived more than once.
+CREATE TABLE archived_keys (
+    key_id         TEXT NOT NULL,
+    public_key     BLOB NOT NULL,
+    counter        INTEGER,
+    environment    TEXT,
+    receipt        BLOB NOT NULL,
+    created_at     INTEGER NOT NULL,
+    last_used_at   INTEGER,
+    platform       TEXT NOT NULL,
+    leaf_not_after INTEGER,
+    archived_at    INTEGER NOT NULL
+);
+CREATE INDEX archived_keys_key_id ON archived_keys (key_id);
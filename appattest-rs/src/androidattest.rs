@@ -0,0 +1,686 @@
+//! Android hardware key attestation: the certificate chain `KeyStore.getCertificateChain`
+//! returns for a key generated with an attestation challenge. Its leaf certifies the key
+//! and carries the key description extension, which says where the key lives and which app
+//! asked for it; the chain has to end at one of Google's hardware attestation roots.
+//!
+//! The Android counterpart of [`crate::attestation`]: same error type, same root store
+//! shape, and a [`VerifiedKeyAttestation`] where Apple's path returns a
+//! [`crate::attestation::VerifiedAttestation`]. Revocation is not checked here, since
+//! Google's status list is fetched over the network.
+
+use std::borrow::Cow;
+
+use sha2::{Digest, Sha256};
+use x509_parser::prelude::*;
+
+use crate::attestation::{strip_trusted_root, Attestation, TrustedRoot, VerifyOptions};
+use crate::error::AppAttestError;
+use crate::root::{self, RootCertError};
+
+/// OID of the key description extension.
+pub const KEY_DESCRIPTION_OID: &str = "1.3.6.1.4.1.11129.2.1.17";
+
+/// `(file stem, DER)` of every root in `certificates/android/`, compiled in by build.rs.
+/// Kept apart from Apple's store, so neither vendor's root vouches for the other's chains.
+const EMBEDDED_ROOTS: &[(&str, &[u8])] = include!(concat!(env!("OUT_DIR"), "/roots/android/roots.rs"));
+
+/// Most certificates a key attestation chain may hold below the root: the leaf, the
+/// KeyMint batch or remotely provisioned key, and up to two intermediates.
+const MAX_CHAIN_LENGTH: usize = 4;
+
+/// Where an attested key, or the KeyMint implementation that attested it, lives.
+/// Ordered from weakest to strongest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SecurityLevel {
+    Software,
+    /// A trusted execution environment, e.g. TrustZone.
+    TrustedEnvironment,
+    /// A separate secure element.
+    StrongBox,
+}
+
+impl SecurityLevel {
+    fn from_der(value: u64) -> Result<Self, AppAttestError> {
+        match value {
+            0 => Ok(SecurityLevel::Software),
+            1 => Ok(SecurityLevel::TrustedEnvironment),
+            2 => Ok(SecurityLevel::StrongBox),
+            _ => Err(AppAttestError::InvalidKeyDescription),
+        }
+    }
+}
+
+/// The device's verified boot state when the key was attested.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerifiedBootState {
+    /// Booted an image signed by the device maker's key.
+    Verified,
+    /// Booted an image signed by a user-installed key.
+    SelfSigned,
+    /// The bootloader is unlocked.
+    Unverified,
+    Failed,
+}
+
+/// What the bootloader reported about the running system.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RootOfTrust {
+    pub verified_boot_key: Vec<u8>,
+    pub device_locked: bool,
+    pub verified_boot_state: VerifiedBootState,
+    /// Digest of the verified images, from attestation version 3 on.
+    pub verified_boot_hash: Option<Vec<u8>>,
+}
+
+/// One package sharing the attested key's UID.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PackageInfo {
+    pub name: String,
+    pub version: u64,
+}
+
+/// The app that generated the key, as the Android framework saw it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AttestationApplicationId {
+    pub packages: Vec<PackageInfo>,
+    /// SHA-256 of each of the app's signing certificates.
+    pub signature_digests: Vec<Vec<u8>>,
+}
+
+/// The tags of an authorization list this module reads; the rest are skipped.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AuthorizationList {
+    /// Tag 709, normally in the software-enforced list.
+    pub attestation_application_id: Option<AttestationApplicationId>,
+    /// Tag 704, in the hardware-enforced list of keys in secure hardware.
+    pub root_of_trust: Option<RootOfTrust>,
+}
+
+/// The key description extension, [`KEY_DESCRIPTION_OID`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyDescription {
+    pub attestation_version: u64,
+    pub attestation_security_level: SecurityLevel,
+    pub keymint_version: u64,
+    pub keymint_security_level: SecurityLevel,
+    pub attestation_challenge: Vec<u8>,
+    pub unique_id: Vec<u8>,
+    pub software_enforced: AuthorizationList,
+    pub hardware_enforced: AuthorizationList,
+}
+
+impl KeyDescription {
+    /// Parses the DER value of the extension. Fields later attestation versions append
+    /// after the two authorization lists are ignored.
+    pub fn from_der(bytes: &[u8]) -> Result<Self, AppAttestError> {
+        let mut outer = Der::new(bytes);
+        let mut seq = outer.sequence()?;
+        let description = KeyDescription {
+            attestation_version: seq.integer()?,
+            attestation_security_level: SecurityLevel::from_der(seq.enumerated()?)?,
+            keymint_version: seq.integer()?,
+            keymint_security_level: SecurityLevel::from_der(seq.enumerated()?)?,
+            attestation_challenge: seq.octets()?.to_vec(),
+            unique_id: seq.octets()?.to_vec(),
+            software_enforced: AuthorizationList::from_der(seq.sequence()?)?,
+            hardware_enforced: AuthorizationList::from_der(seq.sequence()?)?,
+        };
+        if !outer.is_empty() {
+            return Err(AppAttestError::InvalidKeyDescription);
+        }
+        Ok(description)
+    }
+
+    /// The attestation application ID, from whichever list has it.
+    pub fn application_id(&self) -> Option<&AttestationApplicationId> {
+        self.hardware_enforced
+            .attestation_application_id
+            .as_ref()
+            .or(self.software_enforced.attestation_application_id.as_ref())
+    }
+}
+
+impl AuthorizationList {
+    const ROOT_OF_TRUST: u32 = 704;
+    const ATTESTATION_APPLICATION_ID: u32 = 709;
+
+    fn from_der(mut list: Der) -> Result<Self, AppAttestError> {
+        let mut parsed = AuthorizationList::default();
+        while !list.is_empty() {
+            // Every entry is `[tag] EXPLICIT value`.
+            let (tag, content) = list.any()?;
+            if tag.class != Tag::CONTEXT || !tag.constructed {
+                return Err(AppAttestError::InvalidKeyDescription);
+            }
+            let mut value = Der::new(content);
+            match tag.number {
+                Self::ATTESTATION_APPLICATION_ID => {
+                    // An OCTET STRING holding the DER of the ID, not the ID itself.
+                    let id = AttestationApplicationId::from_der(value.octets()?)?;
+                    set_once(&mut parsed.attestation_application_id, id)?;
+                }
+                Self::ROOT_OF_TRUST => set_once(&mut parsed.root_of_trust, RootOfTrust::from_der(value.sequence()?)?)?,
+                _ => continue,
+            }
+            if !value.is_empty() {
+                return Err(AppAttestError::InvalidKeyDescription);
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+impl AttestationApplicationId {
+    fn from_der(bytes: &[u8]) -> Result<Self, AppAttestError> {
+        let mut seq = Der::new(bytes).sequence()?;
+        let mut infos = seq.set()?;
+        let mut packages = Vec::new();
+        while !infos.is_empty() {
+            let mut info = infos.sequence()?;
+            let name = String::from_utf8(info.octets()?.to_vec()).map_err(|_| AppAttestError::InvalidKeyDescription)?;
+            packages.push(PackageInfo { name, version: info.integer()? });
+        }
+        let mut digests = seq.set()?;
+        let mut signature_digests = Vec::new();
+        while !digests.is_empty() {
+            signature_digests.push(digests.octets()?.to_vec());
+        }
+        Ok(AttestationApplicationId { packages, signature_digests })
+    }
+}
+
+impl RootOfTrust {
+    fn from_der(mut seq: Der) -> Result<Self, AppAttestError> {
+        Ok(RootOfTrust {
+            verified_boot_key: seq.octets()?.to_vec(),
+            device_locked: seq.boolean()?,
+            verified_boot_state: match seq.enumerated()? {
+                0 => VerifiedBootState::Verified,
+                1 => VerifiedBootState::SelfSigned,
+                2 => VerifiedBootState::Unverified,
+                3 => VerifiedBootState::Failed,
+                _ => return Err(AppAttestError::InvalidKeyDescription),
+            },
+            verified_boot_hash: if seq.is_empty() { None } else { Some(seq.octets()?.to_vec()) },
+        })
+    }
+}
+
+fn set_once<T>(slot: &mut Option<T>, value: T) -> Result<(), AppAttestError> {
+    match slot.replace(value) {
+        None => Ok(()),
+        Some(_) => Err(AppAttestError::InvalidKeyDescription),
+    }
+}
+
+/// The app a key attestation has to be for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AndroidApp {
+    pub package_name: String,
+    /// SHA-256 of each certificate the app may be signed with. Every digest the attestation
+    /// states has to be one of them.
+    pub signature_digests: Vec<[u8; 32]>,
+}
+
+/// What a successful [`AndroidAttestationVerifier::verify`] establishes about the key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerifiedKeyAttestation {
+    /// The leaf certificate's public key: uncompressed SEC1 for an EC key.
+    pub public_key: Vec<u8>,
+    /// `SHA256` of the leaf certificate's DER.
+    pub leaf_fingerprint: [u8; 32],
+    /// Name of the trusted root the chain ends at, see [`TrustedRoot::name`].
+    pub root: String,
+    pub attestation_version: u64,
+    pub security_level: SecurityLevel,
+    pub keymint_security_level: SecurityLevel,
+    pub package: PackageInfo,
+    pub signature_digests: Vec<[u8; 32]>,
+    pub root_of_trust: Option<RootOfTrust>,
+}
+
+/// Verifies key attestation chains against a store of trusted roots: those embedded at
+/// build time from `certificates/android/`, or ones the caller supplies, as
+/// [`crate::attestation::AttestationVerifier`] does for Apple's.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AndroidAttestationVerifier {
+    roots: Vec<TrustedRoot>,
+}
+
+impl Default for AndroidAttestationVerifier {
+    fn default() -> Self {
+        Self::embedded()
+    }
+}
+
+impl AndroidAttestationVerifier {
+    /// Trusts the embedded Google roots.
+    pub fn embedded() -> Self {
+        let roots = EMBEDDED_ROOTS
+            .iter()
+            .map(|(name, der)| TrustedRoot { name: Cow::Borrowed(*name), der: Cow::Borrowed(*der) })
+            .collect();
+        AndroidAttestationVerifier { roots }
+    }
+
+    /// Trusts only the DER certificate `der`, reported as `name`.
+    pub fn with_root_der(name: impl Into<String>, der: Vec<u8>) -> Result<Self, RootCertError> {
+        Self::with_roots(vec![TrustedRoot { name: Cow::Owned(name.into()), der: Cow::Owned(der) }])
+    }
+
+    /// Trusts only `roots`, e.g. from [`root::collect`].
+    pub fn with_roots(roots: Vec<TrustedRoot>) -> Result<Self, RootCertError> {
+        for root in &roots {
+            root::check_certificate(&root.der)
+                .map_err(|error| RootCertError::File { file: root.name.to_string(), error: Box::new(error) })?;
+        }
+        Ok(AndroidAttestationVerifier { roots })
+    }
+
+    /// The roots this verifier trusts.
+    pub fn roots(&self) -> &[TrustedRoot] {
+        &self.roots
+    }
+
+    /// Verifies `chain`, leaf first, at `time`: that it ends at a trusted root, and that its
+    /// leaf attests a key for `challenge`, in at least `min_security_level`, generated by
+    /// `app`. The root itself may be the chain's last certificate, as devices send it.
+    pub fn verify(
+        &self,
+        chain: &[Vec<u8>],
+        challenge: &[u8],
+        app: &AndroidApp,
+        min_security_level: SecurityLevel,
+        time: i64,
+    ) -> Result<VerifiedKeyAttestation, AppAttestError> {
+        let anchor = self.verify_chain(chain, time)?;
+        let leaf_der = chain.first().ok_or_else(|| AppAttestError::Message("certificate list is empty".into()))?;
+
+        let (_, leaf) = parse_x509_certificate(leaf_der)
+            .map_err(|_| AppAttestError::Message("invalid attestation certificate DER".into()))?;
+        let extension = leaf
+            .extensions()
+            .iter()
+            .find(|ext| ext.oid.to_id_string() == KEY_DESCRIPTION_OID)
+            .ok_or(AppAttestError::InvalidKeyDescription)?;
+        let description = KeyDescription::from_der(extension.value)?;
+
+        if description.attestation_challenge != challenge {
+            return Err(AppAttestError::InvalidChallenge);
+        }
+        if description.attestation_security_level < min_security_level
+            || description.keymint_security_level < min_security_level
+        {
+            return Err(AppAttestError::InvalidSecurityLevel);
+        }
+
+        let id = description.application_id().ok_or(AppAttestError::InvalidKeyDescription)?;
+        let package =
+            id.packages.iter().find(|p| p.name == app.package_name).ok_or(AppAttestError::InvalidPackageName)?;
+        let signature_digests = id
+            .signature_digests
+            .iter()
+            .map(|digest| <[u8; 32]>::try_from(digest.as_slice()).ok().filter(|d| app.signature_digests.contains(d)))
+            .collect::<Option<Vec<_>>>()
+            .filter(|digests| !digests.is_empty())
+            .ok_or(AppAttestError::InvalidSignatureDigest)?;
+
+        Ok(VerifiedKeyAttestation {
+            public_key: leaf.public_key().subject_public_key.data.to_vec(),
+            leaf_fingerprint: Sha256::digest(leaf_der).into(),
+            root: self.roots[anchor].name.to_string(),
+            attestation_version: description.attestation_version,
+            security_level: description.attestation_security_level,
+            keymint_security_level: description.keymint_security_level,
+            package: package.clone(),
+            signature_digests,
+            root_of_trust: description.hardware_enforced.root_of_trust.clone(),
+        })
+    }
+
+    /// Checks `chain` back to one of the roots at `time`, returning the root's index.
+    fn verify_chain(&self, chain: &[Vec<u8>], time: i64) -> Result<usize, AppAttestError> {
+        if self.roots.is_empty() {
+            return Err(AppAttestError::Message(
+                "no Google hardware attestation root is trusted; see certificates/android/README.md".into(),
+            ));
+        }
+        let mut root_certs = Vec::with_capacity(self.roots.len());
+        for root in &self.roots {
+            let (_, cert) =
+                parse_x509_certificate(&root.der).map_err(|_| AppAttestError::Message("invalid root DER".into()))?;
+            root_certs.push(cert);
+        }
+        // Devices append the root, which the walk must not anchor at itself.
+        let chain = strip_trusted_root(chain, &self.roots);
+        let options = VerifyOptions { max_chain_length: MAX_CHAIN_LENGTH, ..VerifyOptions::default() };
+        Attestation::verify_certificates(chain, &root_certs, time, &options)
+    }
+}
+
+/// A reader over DER, enough for the key description: definite lengths, and tag numbers in
+/// the high-tag form the authorization list uses.
+struct Der<'a> {
+    bytes: &'a [u8],
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Tag {
+    class: u8,
+    constructed: bool,
+    number: u32,
+}
+
+impl Tag {
+    const UNIVERSAL: u8 = 0;
+    const CONTEXT: u8 = 2;
+
+    const BOOLEAN: Tag = Tag::universal(1, false);
+    const INTEGER: Tag = Tag::universal(2, false);
+    const OCTET_STRING: Tag = Tag::universal(4, false);
+    const ENUMERATED: Tag = Tag::universal(10, false);
+    const SEQUENCE: Tag = Tag::universal(16, true);
+    const SET: Tag = Tag::universal(17, true);
+
+    const fn universal(number: u32, constructed: bool) -> Tag {
+        Tag { class: Tag::UNIVERSAL, constructed, number }
+    }
+}
+
+impl<'a> Der<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Der { bytes }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    fn byte(&mut self) -> Result<u8, AppAttestError> {
+        let (&byte, rest) = self.bytes.split_first().ok_or(AppAttestError::InvalidKeyDescription)?;
+        self.bytes = rest;
+        Ok(byte)
+    }
+
+    /// The next element's tag and contents.
+    fn any(&mut self) -> Result<(Tag, &'a [u8]), AppAttestError> {
+        let invalid = || AppAttestError::InvalidKeyDescription;
+        let first = self.byte()?;
+        let mut number = u32::from(first & 0x1f);
+        if number == 0x1f {
+            number = 0;
+            loop {
+                let byte = self.byte()?;
+                number = number.checked_mul(128).ok_or_else(invalid)? | u32::from(byte & 0x7f);
+                if byte & 0x80 == 0 {
+                    break;
+                }
+            }
+        }
+
+        let len = match self.byte()? {
+            short @ 0..=0x7f => usize::from(short),
+            long => {
+                let digits = usize::from(long & 0x7f);
+                if digits == 0 || digits > 4 {
+                    return Err(invalid());
+                }
+                (0..digits).try_fold(0usize, |len, _| Ok::<_, AppAttestError>((len << 8) | usize::from(self.byte()?)))?
+            }
+        };
+        if self.bytes.len() < len {
+            return Err(invalid());
+        }
+        let (content, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok((Tag { class: first >> 6, constructed: first & 0x20 != 0, number }, content))
+    }
+
+    fn expect(&mut self, tag: Tag) -> Result<&'a [u8], AppAttestError> {
+        match self.any()? {
+            (found, content) if found == tag => Ok(content),
+            _ => Err(AppAttestError::InvalidKeyDescription),
+        }
+    }
+
+    fn sequence(&mut self) -> Result<Der<'a>, AppAttestError> {
+        self.expect(Tag::SEQUENCE).map(Der::new)
+    }
+
+    fn set(&mut self) -> Result<Der<'a>, AppAttestError> {
+        self.expect(Tag::SET).map(Der::new)
+    }
+
+    fn octets(&mut self) -> Result<&'a [u8], AppAttestError> {
+        self.expect(Tag::OCTET_STRING)
+    }
+
+    fn boolean(&mut self) -> Result<bool, AppAttestError> {
+        match self.expect(Tag::BOOLEAN)? {
+            [0x00] => Ok(false),
+            [0xff] => Ok(true),
+            _ => Err(AppAttestError::InvalidKeyDescription),
+        }
+    }
+
+    fn integer(&mut self) -> Result<u64, AppAttestError> {
+        unsigned(self.expect(Tag::INTEGER)?)
+    }
+
+    fn enumerated(&mut self) -> Result<u64, AppAttestError> {
+        unsigned(self.expect(Tag::ENUMERATED)?)
+    }
+}
+
+/// A non-negative DER integer that fits a `u64`.
+fn unsigned(content: &[u8]) -> Result<u64, AppAttestError> {
+    let digits = match content {
+        [] => return Err(AppAttestError::InvalidKeyDescription),
+        [first, ..] if first & 0x80 != 0 => return Err(AppAttestError::InvalidKeyDescription),
+        [0x00, rest @ ..] if !rest.is_empty() => rest,
+        _ => content,
+    };
+    if digits.len() > 8 {
+        return Err(AppAttestError::InvalidKeyDescription);
+    }
+    Ok(digits.iter().fold(0, |value, &digit| (value << 8) | u64::from(digit)))
+}
+
+#[cfg(test)]
+mod tests {
+    use rcgen::{BasicConstraints, CertificateParams, CustomExtension, DnType, IsCa, KeyPair, PublicKeyData};
+
+    use super::*;
+
+    const KEY_DESCRIPTION: [u64; 10] = [1, 3, 6, 1, 4, 1, 11129, 2, 1, 17];
+    const NOW: i64 = 1_760_000_000;
+    const PACKAGE: &str = "xyz.elus.aegis.attester";
+    const CHALLENGE: &[u8] = b"server challenge";
+    const DIGEST: [u8; 32] = [0x5a; 32];
+
+    fn tlv(tag: &[u8], content: &[u8]) -> Vec<u8> {
+        let len = content.len();
+        let len = match len {
+            0..=0x7f => vec![len as u8],
+            0x80..=0xff => vec![0x81, len as u8],
+            _ => vec![0x82, (len >> 8) as u8, len as u8],
+        };
+        [tag, &len, content].concat()
+    }
+
+    fn seq(parts: &[Vec<u8>]) -> Vec<u8> {
+        tlv(&[0x30], &parts.concat())
+    }
+
+    fn set(parts: &[Vec<u8>]) -> Vec<u8> {
+        tlv(&[0x31], &parts.concat())
+    }
+
+    fn octets(bytes: &[u8]) -> Vec<u8> {
+        tlv(&[0x04], bytes)
+    }
+
+    /// A minimal two's complement INTEGER, or ENUMERATED with `tag` 0x0a.
+    fn number(tag: u8, value: u64) -> Vec<u8> {
+        let bytes = value.to_be_bytes();
+        let start = bytes.iter().position(|&b| b != 0).unwrap_or(7);
+        let digits = if bytes[start] & 0x80 != 0 { [&[0], &bytes[start..]].concat() } else { bytes[start..].to_vec() };
+        tlv(&[tag], &digits)
+    }
+
+    /// `[tag] EXPLICIT inner`, in the high-tag form tags over 30 need.
+    fn explicit(tag: u32, inner: Vec<u8>) -> Vec<u8> {
+        tlv(&[0xbf, 0x80 | (tag >> 7) as u8, (tag & 0x7f) as u8], &inner)
+    }
+
+    /// What a key generated by [`PACKAGE`] in `level` for `challenge` is described as.
+    fn key_description(challenge: &[u8], level: u64, package: &str, digest: &[u8]) -> Vec<u8> {
+        let application_id =
+            seq(&[set(&[seq(&[octets(package.as_bytes()), number(0x02, 7)])]), set(&[octets(digest)])]);
+        let root_of_trust = seq(&[octets(&[0x11; 32]), tlv(&[0x01], &[0xff]), number(0x0a, 0), octets(&[0x22; 32])]);
+        seq(&[
+            number(0x02, 200),
+            number(0x0a, level),
+            number(0x02, 200),
+            number(0x0a, level),
+            octets(challenge),
+            octets(&[]),
+            // An origin (702) the parser skips, then the application ID.
+            seq(&[explicit(702, number(0x02, 0)), explicit(709, octets(&application_id))]),
+            seq(&[explicit(704, root_of_trust)]),
+        ])
+    }
+
+    /// A root, and a chain as a device sends it: leaf, intermediate and the root itself.
+    /// The leaf carries `description`; its public key is returned too.
+    fn chain(description: Vec<u8>) -> (rcgen::Certificate, Vec<Vec<u8>>, Vec<u8>) {
+        let ca = |name: &str| {
+            let mut params = CertificateParams::new(Vec::new()).unwrap();
+            params.distinguished_name.push(DnType::CommonName, name);
+            params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            params
+        };
+        let root_key = KeyPair::generate().unwrap();
+        let root = ca("Test Hardware Attestation Root").self_signed(&root_key).unwrap();
+        let intermediate_key = KeyPair::generate().unwrap();
+        let intermediate = ca("Test StrongBox Intermediate").signed_by(&intermediate_key, &root, &root_key).unwrap();
+
+        let leaf_key = KeyPair::generate().unwrap();
+        let mut leaf = CertificateParams::new(Vec::new()).unwrap();
+        leaf.distinguished_name.push(DnType::CommonName, "Android Keystore Key");
+        leaf.custom_extensions.push(CustomExtension::from_oid_content(&KEY_DESCRIPTION, description));
+        let leaf = leaf.signed_by(&leaf_key, &intermediate, &intermediate_key).unwrap();
+
+        let chain = vec![leaf.der().to_vec(), intermediate.der().to_vec(), root.der().to_vec()];
+        (root, chain, leaf_key.public_key_raw().to_vec())
+    }
+
+    fn app() -> AndroidApp {
+        AndroidApp { package_name: PACKAGE.into(), signature_digests: vec![[0x01; 32], DIGEST] }
+    }
+
+    fn verify(
+        description: Vec<u8>,
+        min_security_level: SecurityLevel,
+    ) -> Result<VerifiedKeyAttestation, AppAttestError> {
+        let (root, chain, _) = chain(description);
+        let verifier = AndroidAttestationVerifier::with_root_der("test", root.der().to_vec()).unwrap();
+        verifier.verify(&chain, CHALLENGE, &app(), min_security_level, NOW)
+    }
+
+    #[test]
+    fn parses_a_key_description() {
+        let description = KeyDescription::from_der(&key_description(CHALLENGE, 2, PACKAGE, &DIGEST)).unwrap();
+        assert_eq!(description.attestation_version, 200);
+        assert_eq!(description.attestation_security_level, SecurityLevel::StrongBox);
+        assert_eq!(description.attestation_challenge, CHALLENGE);
+        let id = description.application_id().unwrap();
+        assert_eq!(id.packages, [PackageInfo { name: PACKAGE.into(), version: 7 }]);
+        assert_eq!(id.signature_digests, [DIGEST.to_vec()]);
+        let root_of_trust = description.hardware_enforced.root_of_trust.unwrap();
+        assert!(root_of_trust.device_locked);
+        assert_eq!(root_of_trust.verified_boot_state, VerifiedBootState::Verified);
+        assert_eq!(root_of_trust.verified_boot_hash, Some(vec![0x22; 32]));
+    }
+
+    #[test]
+    fn malformed_descriptions_are_errors_not_panics() {
+        let der = key_description(CHALLENGE, 1, PACKAGE, &DIGEST);
+        for len in 0..der.len() {
+            assert_eq!(KeyDescription::from_der(&der[..len]), Err(AppAttestError::InvalidKeyDescription), "{len}");
+        }
+        assert!(KeyDescription::from_der(&[der.as_slice(), &[0x05, 0x00]].concat()).is_err(), "trailing data");
+        // Security level 3 does not exist.
+        assert!(KeyDescription::from_der(&key_description(CHALLENGE, 3, PACKAGE, &DIGEST)).is_err());
+        assert_eq!(unsigned(&[0xff]), Err(AppAttestError::InvalidKeyDescription), "negative");
+        assert_eq!(unsigned(&[0x00, 0x80]), Ok(0x80));
+    }
+
+    #[test]
+    fn verifies_a_chain_to_a_trusted_root() {
+        let (root, chain, public_key) = chain(key_description(CHALLENGE, 1, PACKAGE, &DIGEST));
+        let verifier = AndroidAttestationVerifier::with_root_der("test", root.der().to_vec()).unwrap();
+        let verified = verifier.verify(&chain, CHALLENGE, &app(), SecurityLevel::TrustedEnvironment, NOW).unwrap();
+        assert_eq!(verified.public_key, public_key);
+        assert_eq!(verified.leaf_fingerprint, <[u8; 32]>::from(Sha256::digest(&chain[0])));
+        assert_eq!(verified.root, "test");
+        assert_eq!(verified.security_level, SecurityLevel::TrustedEnvironment);
+        assert_eq!(verified.package, PackageInfo { name: PACKAGE.into(), version: 7 });
+        assert_eq!(verified.signature_digests, [DIGEST]);
+        assert_eq!(verified.root_of_trust.unwrap().verified_boot_state, VerifiedBootState::Verified);
+
+        // Without the root at the end too.
+        let without_root = verifier.verify(&chain[..2], CHALLENGE, &app(), SecurityLevel::TrustedEnvironment, NOW);
+        assert_eq!(without_root.unwrap().leaf_fingerprint, verified.leaf_fingerprint);
+    }
+
+    #[test]
+    fn rejects_what_the_app_did_not_ask_for() {
+        let strongbox = SecurityLevel::StrongBox;
+        assert_eq!(
+            verify(key_description(b"another", 2, PACKAGE, &DIGEST), strongbox),
+            Err(AppAttestError::InvalidChallenge)
+        );
+        assert_eq!(
+            verify(key_description(CHALLENGE, 1, PACKAGE, &DIGEST), strongbox),
+            Err(AppAttestError::InvalidSecurityLevel)
+        );
+        let software = key_description(CHALLENGE, 0, PACKAGE, &DIGEST);
+        assert_eq!(verify(software, SecurityLevel::TrustedEnvironment), Err(AppAttestError::InvalidSecurityLevel));
+        assert_eq!(
+            verify(key_description(CHALLENGE, 2, "com.example.other", &DIGEST), strongbox),
+            Err(AppAttestError::InvalidPackageName)
+        );
+        assert_eq!(
+            verify(key_description(CHALLENGE, 2, PACKAGE, &[0x02; 32]), strongbox),
+            Err(AppAttestError::InvalidSignatureDigest)
+        );
+        assert_eq!(
+            verify(key_description(CHALLENGE, 2, PACKAGE, &[0x5a; 20]), strongbox),
+            Err(AppAttestError::InvalidSignatureDigest)
+        );
+        assert!(verify(key_description(CHALLENGE, 2, PACKAGE, &DIGEST), strongbox).is_ok());
+    }
+
+    #[test]
+    fn rejects_chains_from_other_roots() {
+        let (_, device_chain, _) = chain(key_description(CHALLENGE, 1, PACKAGE, &DIGEST));
+        let (other_root, ..) = chain(key_description(CHALLENGE, 1, PACKAGE, &DIGEST));
+        let verifier = AndroidAttestationVerifier::with_root_der("other", other_root.der().to_vec()).unwrap();
+        assert!(verifier.verify(&device_chain, CHALLENGE, &app(), SecurityLevel::Software, NOW).is_err());
+
+        let none = AndroidAttestationVerifier::with_roots(Vec::new()).unwrap();
+        let error = none.verify(&device_chain, CHALLENGE, &app(), SecurityLevel::Software, NOW).unwrap_err();
+        assert!(error.to_string().contains("certificates/android"), "{error}");
+    }
+
+    #[test]
+    fn leaf_without_a_key_description_is_rejected() {
+        let (root, mut chain, _) = chain(key_description(CHALLENGE, 1, PACKAGE, &DIGEST));
+        // The intermediate as the leaf: a valid chain, but nothing attested.
+        chain.remove(0);
+        let verifier = AndroidAttestationVerifier::with_root_der("test", root.der().to_vec()).unwrap();
+        let verified = verifier.verify(&chain, CHALLENGE, &app(), SecurityLevel::Software, NOW);
+        assert_eq!(verified, Err(AppAttestError::InvalidKeyDescription));
+    }
+}
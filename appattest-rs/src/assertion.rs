@@ -6,15 +6,21 @@ use std::io::Cursor;
 use serde::{Deserialize, Serialize};
 use std::error::Error;
 use p256::pkcs8::DecodePublicKey;
-use crate::{authenticator::AuthenticatorData, error::AppAttestError};
+use std::sync::Arc;
+use crate::{authenticator::AuthenticatorData, cbor, error::AppAttestError, metrics::{MetricsSink, Sink}};
+use crate::audit::{Audit, AuditEvent, AuditKind, AuditSink};
+use crate::key::KeyId;
+use crate::keypolicy::{KeyPolicy, Policy};
+use crate::sensitive::{base64_problem, Sensitive};
+use crate::timings::{Timer, VerificationStep, VerificationTimings};
 
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct Assertion {
     #[serde(rename = "authenticatorData")]
     raw_authenticator_data: Vec<u8>,
     #[serde(rename = "signature")]
-    signature: Vec<u8>,
+    signature: Sensitive<Vec<u8>>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -22,6 +28,22 @@ struct ClientData {
     challenge: String,
 }
 
+/// Returns the challenge of client data that follows the JSON convention, an object with a
+/// string `challenge` field, or `None` for any other client data.
+pub fn client_data_challenge(client_data: &[u8]) -> Option<String> {
+    serde_json::from_slice::<ClientData>(client_data)
+        .ok()
+        .map(|data| data.challenge)
+}
+
+/// [`Assertion::nonce`] of the authenticator data `auth_data`.
+fn nonce(auth_data: &[u8], client_data: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(auth_data);
+    hasher.update(Sha256::digest(client_data));
+    hasher.finalize().to_vec()
+}
+
 impl Assertion {
 
     /// Creates a new `Assertion` from a Base64-encoded CBOR string.
@@ -31,94 +53,278 @@ impl Assertion {
     ///
     /// # Errors
     /// Returns `AppAttestError` if decoding or deserialization fails.
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        name = "assertion.decode", level = "debug", skip_all,
+        fields(len = base64_assertion.len()), err(level = "debug"),
+    ))]
     pub fn from_base64(base64_assertion: &str) -> Result<Self, AppAttestError> {
+        if !cfg!(feature = "minimal-cbor") {
+            return cbor::from_base64(base64_assertion, "unable to parse assertion");
+        }
         let decoded_bytes = general_purpose::STANDARD
             .decode(base64_assertion)
-            .map_err(|e| AppAttestError::Message(format!("Failed to decode Base64: {}", e)))?;
+            .map_err(|e| AppAttestError::Message(format!("Failed to decode Base64: {}", base64_problem(&e))))?;
+
+        Self::from_cbor(&decoded_bytes)
+    }
 
-        let cursor = Cursor::new(decoded_bytes);
-        let assertion_result: Result<Assertion, _> = from_reader(cursor);  
+    /// Creates a new `Assertion` from the raw CBOR bytes the device produced.
+    ///
+    /// With the `minimal-cbor` feature this uses the crate's own reader instead of ciborium.
+    ///
+    /// # Errors
+    /// Returns `AppAttestError` if deserialization fails.
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        name = "assertion.parse", level = "debug", skip_all, fields(len = cbor.len()), err(level = "debug"),
+    ))]
+    pub fn from_cbor(cbor: &[u8]) -> Result<Self, AppAttestError> {
+        if cfg!(feature = "minimal-cbor") {
+            return Self::from_minimal_cbor(cbor);
+        }
+        let assertion_result: Result<Assertion, _> = from_reader(Cursor::new(cbor));
         if let Ok(assertion) = assertion_result {
             return  Ok(assertion)
         }
         Err(AppAttestError::Message("unable to parse assertion".to_string()))
     }
 
+    fn from_minimal_cbor(bytes: &[u8]) -> Result<Self, AppAttestError> {
+        let mut reader = cbor::Reader::new(bytes);
+        let (mut raw_authenticator_data, mut signature) = (None, None);
+        for _ in 0..reader.map()? {
+            match reader.text()? {
+                key @ "authenticatorData" => {
+                    cbor::set_once(&mut raw_authenticator_data, reader.bytes()?.to_vec(), key)?
+                }
+                key @ "signature" => cbor::set_once(&mut signature, reader.bytes()?.to_vec(), key)?,
+                _ => reader.skip()?,
+            }
+        }
+        Ok(Assertion {
+            raw_authenticator_data: cbor::required(raw_authenticator_data, "authenticatorData")?,
+            signature: Sensitive(cbor::required(signature, "signature")?),
+        })
+    }
+
+    /// Returns the counter from the authenticator data without verifying anything.
+    ///
+    /// Servers read it before `verify` (which consumes the assertion) to store the new value.
+    pub fn counter(&self) -> Result<u32, AppAttestError> {
+        match self.raw_authenticator_data.get(33..37).and_then(|counter| <[u8; 4]>::try_from(counter).ok()) {
+            Some(counter) => Ok(u32::from_be_bytes(counter)),
+            None => Err(AppAttestError::Message("Authenticator data is too short".to_string())),
+        }
+    }
+
+    /// Computes the nonce the assertion signs: `SHA256(authenticatorData || SHA256(client_data))`.
+    ///
+    /// ECDSA hashes its message once more, so the digest actually signed is `SHA256(nonce)`.
+    pub fn nonce(&self, client_data: &[u8]) -> Vec<u8> {
+        nonce(&self.raw_authenticator_data, client_data)
+    }
+
     /// Verifies the authenticity of an assertion using provided data and cryptographic checks.
     /// # Arguments
-    /// * `client_data_byte` - A vector of bytes representing serialized client data.
+    /// * `base64_client_data` - The base64 client data the assertion signs.
     /// * `app_id` - A string slice representing the application identifier.
-    /// * `public_key_byte` - A vector of bytes representing the public key used for verifying the signature.
-    /// * `previous_counter` - The counter value from the last successful verification.
-    /// * `stored_challenge` - A string slice representing the challenge previously issued to the client.
+    /// * `public_key` - The PEM public key of the attested key.
+    /// * `previous_counter` - The counter value from the last successful verification, or `None` before the first.
+    /// * `verify_signature` - Whether to check the signature; `None` checks it.
     ///
     /// # Example
     /// ```
     /// use appattest_rs::assertion::Assertion;
-    /// use base64::{engine::general_purpose, Engine};
     ///
-    /// let base64_client_data = "eyJjaGFsbGVuZ2UiOiAiY2hhbGxlbmdlMTIzIn0=";
-    /// let app_id = "com.example.app";
-    /// let public_key_pem = "BLROJkpk8NoHVHAnkLOKWUrc4MhyMkATpDyDwjEk82o+uf+KCQiDoHZdlcJ1ff5HPgK7Jd/pTA3cyKOq5MYM6Gs=";
-    /// let previous_counter = 5;
-    /// let stored_challenge = "challenge123";
+    /// let base64_client_data = include_str!("../examples/fixtures/client_data.b64").trim();
+    /// let app_id = include_str!("../examples/fixtures/app_id.txt").trim();
+    /// let public_key_pem = include_str!("../examples/fixtures/public_key.pem");
+    /// let previous_counter = None; // the key's first assertion
     ///
-    /// let assertion = Assertion::from_base64("omlzaWduYXR1cmVYRzBFAiEA3P1gbUuJK9dipE03PXibJgDMDJ3BeFp3NDtSL9U5sXECIHn4VZOXWpHpTP8WEXdqiqDQXGYpmEzmFwjlAa2Z7FkScWF1dGhlbnRpY2F0b3JEYXRhWCWkXNlsBrqJ4mRKrRfpKJTm48XHMxXQ8W64SUf50/HoU0AAAAAB").unwrap();
+    /// let assertion = Assertion::from_base64(include_str!("../examples/fixtures/assertion.b64").trim()).unwrap();
+    /// assert_eq!(assertion.counter().unwrap(), 1);
     ///
-    /// match assertion.verify(base64_client_data, app_id, public_key_pem, previous_counter, None) {
-    ///     Ok(_) => println!("Verification successful!"),
-    ///     Err(e) => println!("Verification failed: {}", e),
-    /// }
+    /// // The signature's `r` and `s`, and the key's `x` and `y`.
+    /// let parts = assertion
+    ///     .verify(base64_client_data, app_id, public_key_pem, previous_counter, None)
+    ///     .expect("Verification failed");
+    /// assert!(parts.iter().all(|part| part.len() == 32));
     /// ```
-    pub fn verify(self, base64_client_data: &str, app_id: &str, public_key: &str, previous_counter: u32, verify_signature: Option<bool>) -> Result<[Vec<u8>; 4], Box<dyn Error>> {
+    pub fn verify(self, base64_client_data: &str, app_id: &str, public_key: &str, previous_counter: Option<u32>, verify_signature: Option<bool>) -> Result<[Vec<u8>; 4], Box<dyn Error>> {
 
         let client_data_byte = general_purpose::STANDARD
             .decode(base64_client_data)
             .map_err(|_| AppAttestError::Message("failed to decode client data".to_string()))?;
-        
-        let auth_data = AuthenticatorData::new(self.raw_authenticator_data)?;
-
-        // 1. Compute clientDataHash as the SHA256 hash of clientData.
-        let client_data_hash = Sha256::digest(client_data_byte).to_vec();
 
         let verifying_key = VerifyingKey::from_public_key_pem(&public_key)
             .map_err(|_| AppAttestError::Message("failed to parse the public key".to_string()))?;
 
-        // 2. Concatenate authenticatorData and clientDataHash, and apply a SHA256 hash over the result to form nonce.
-        let mut hasher = Sha256::new();
-        hasher.update(auth_data.bytes.as_slice());
-        hasher.update(client_data_hash.as_slice());
-        let nonce_hash = hasher.finalize();
+        let app_id_hash: [u8; 32] = Sha256::digest(app_id.as_bytes()).into();
+
+        let timer = &mut Timer::new(false);
+        Ok(self.verify_with_key(&client_data_byte, &app_id_hash, &verifying_key, previous_counter, verify_signature.unwrap_or(true), timer)?)
+    }
+
+    /// Verifies an assertion from raw inputs, without any text decoding.
+    ///
+    /// This is the form the zkVM guest uses: `client_data` is the raw client data,
+    /// `app_id_hash` is `SHA256(app_id)` and `public_key` is the uncompressed SEC1 key
+    /// returned by attestation verification. The signature is always checked.
+    ///
+    /// The counter must be greater than `previous_counter`, the counter of the key's last
+    /// accepted assertion. On the first assertion, `None`, any counter is accepted, `0`
+    /// included, as some devices start at `0` after attestation.
+    ///
+    /// Returns the signature `r` and `s` and the public key `x` and `y` coordinates.
+    pub fn verify_raw(self, client_data: &[u8], app_id_hash: &[u8; 32], public_key: &[u8], previous_counter: Option<u32>) -> Result<[Vec<u8>; 4], AppAttestError> {
+        self.verify_timed(client_data, app_id_hash, public_key, previous_counter, &mut Timer::new(false))
+    }
+
+    /// [`Assertion::verify_raw`], timing its steps with `timer`.
+    fn verify_timed(
+        self,
+        client_data: &[u8],
+        app_id_hash: &[u8; 32],
+        public_key: &[u8],
+        previous_counter: Option<u32>,
+        timer: &mut Timer,
+    ) -> Result<[Vec<u8>; 4], AppAttestError> {
+        timer.step(VerificationStep::Decode);
+        let verifying_key = VerifyingKey::from_sec1_bytes(public_key)
+            .map_err(|_| AppAttestError::InvalidPublicKey)?;
+
+        self.verify_with_key(client_data, app_id_hash, &verifying_key, previous_counter, true, timer)
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        name = "assertion.verify", skip_all,
+        fields(previous_counter = previous_counter, counter = tracing::field::Empty), err(level = "info"),
+    ))]
+    fn verify_with_key(self, client_data: &[u8], app_id_hash: &[u8; 32], verifying_key: &VerifyingKey, previous_counter: Option<u32>, verify_signature: bool, timer: &mut Timer) -> Result<[Vec<u8>; 4], AppAttestError> {
+        // Decoding goes on from the key, timed by the caller.
+        let auth_data = AuthenticatorData::new(self.raw_authenticator_data)?;
+        record!(counter = auth_data.counter);
 
         let signature = ecdsa::Signature::from_der(&self.signature)
             .map_err(|_| AppAttestError::Message("invalid signature format".to_string()))?;
 
+        // 1. Compute clientDataHash as the SHA256 hash of clientData.
+        // 2. Concatenate authenticatorData and clientDataHash, and apply a SHA256 hash over the result to form nonce.
+        timer.step(VerificationStep::Nonce);
+        let nonce_hash = nonce(&auth_data.bytes, client_data);
+
         // 3. Use the public key that you store from the attestation object to verify that the assertion’s signature is valid for nonce.
-        if verify_signature.unwrap_or(true) {
-            if verifying_key.verify(nonce_hash.as_slice(), &signature).is_err() {
-                return Err(Box::new(AppAttestError::InvalidSignature));
-            }
+        timer.step(VerificationStep::Signature);
+        if verify_signature && verifying_key.verify(nonce_hash.as_slice(), &signature).is_err() {
+            return Err(AppAttestError::InvalidSignature);
         }
 
         // 4. Compute the SHA256 hash of the client’s App ID, and verify that it matches the RP ID in the authenticator data.
-        auth_data.verify_app_id(app_id)?;
+        timer.step(VerificationStep::AuthData);
+        auth_data.verify_app_id_hash(app_id_hash)?;
 
         // 5. Verify that the authenticator data’s counter value is greater than the value from the previous assertion, or greater than 0 on the first assertion.
-        if auth_data.counter <= previous_counter {
-            return Err(Box::new(AppAttestError::InvalidCounter));
+        // Devices do emit 0 on the first assertion, so without a previous one any counter goes.
+        if previous_counter.is_some_and(|previous| auth_data.counter <= previous) {
+            return Err(AppAttestError::InvalidCounter);
+        }
+        // The last counter value is refused, so a key that reaches it fails loudly rather than
+        // being stuck replaying it.
+        if auth_data.counter == u32::MAX {
+            return Err(AppAttestError::CounterExhausted);
         }
-        
+
+        let public_key = verifying_key.to_encoded_point(false);
         let verification_data = [
-           signature.r().to_bytes().to_vec(),
+            signature.r().to_bytes().to_vec(),
             signature.s().to_bytes().to_vec(),
-            verifying_key.to_encoded_point(false).x().unwrap().to_vec(),
-            verifying_key.to_encoded_point(false).y().unwrap().to_vec(),
+            public_key.x().ok_or(AppAttestError::InvalidPublicKey)?.to_vec(),
+            public_key.y().ok_or(AppAttestError::InvalidPublicKey)?.to_vec(),
         ];
 
         Ok(verification_data)
     }
 }
 
+/// Verifies assertions as [`Assertion::verify_raw`] does, reporting each to a
+/// [`MetricsSink`] with `kind` `assertion`, see [`crate::metrics`], and recording each
+/// decision to an [`AuditSink`] if it has one.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AssertionVerifier {
+    metrics: Sink,
+    audit: Audit,
+    timings: bool,
+    key_policy: Policy,
+}
+
+impl AssertionVerifier {
+    pub fn with_metrics(mut self, sink: Arc<dyn MetricsSink>) -> Self {
+        self.metrics = Sink(sink);
+        self
+    }
+
+    /// Records each decision to `sink`, see [`crate::audit`]: the claimed counter, the key
+    /// ID of `public_key`, and the digests of the authenticator data, the client data and
+    /// the signature.
+    pub fn with_audit(mut self, sink: Arc<dyn AuditSink>) -> Self {
+        self.audit = Audit(Some(sink));
+        self
+    }
+
+    /// Consults `policy` on the key of each assertion, see [`crate::keypolicy`], before
+    /// anything else is checked.
+    pub fn with_key_policy(mut self, policy: Arc<dyn KeyPolicy>) -> Self {
+        self.key_policy = Policy(Some(policy));
+        self
+    }
+
+    /// Times the steps of each verification, see [`crate::timings`], for
+    /// [`AssertionVerifier::verify_timed`] to return. Without it, verifying reads no clock
+    /// for them.
+    pub fn with_timings(mut self) -> Self {
+        self.timings = true;
+        self
+    }
+
+    /// [`Assertion::verify_raw`], reported to this verifier's sinks.
+    pub fn verify_raw(
+        &self,
+        assertion: Assertion,
+        client_data: &[u8],
+        app_id_hash: &[u8; 32],
+        public_key: &[u8],
+        previous_counter: Option<u32>,
+    ) -> Result<[Vec<u8>; 4], AppAttestError> {
+        self.verify_timed(assertion, client_data, app_id_hash, public_key, previous_counter).0
+    }
+
+    /// [`AssertionVerifier::verify_raw`], returning the timings of the steps too, up to the
+    /// one that failed if one did. `None` without [`AssertionVerifier::with_timings`].
+    pub fn verify_timed(
+        &self,
+        assertion: Assertion,
+        client_data: &[u8],
+        app_id_hash: &[u8; 32],
+        public_key: &[u8],
+        previous_counter: Option<u32>,
+    ) -> (Result<[Vec<u8>; 4], AppAttestError>, Option<VerificationTimings>) {
+        let event = self.audit.start(|| {
+            AuditEvent::new(AuditKind::Assertion)
+                .key_id(KeyId::for_public_key(public_key).ok())
+                .counter(assertion.counter().ok())
+                .digest("authenticatorData", &assertion.raw_authenticator_data)
+                .digest("clientData", client_data)
+                .digest("signature", &assertion.signature)
+        });
+        let mut timer = Timer::new(self.timings);
+        let result = self.metrics.verification("assertion", || {
+            self.key_policy.check(KeyId::for_public_key(public_key), Some(public_key))?;
+            assertion.verify_timed(client_data, app_id_hash, public_key, previous_counter, &mut timer)
+        });
+        self.audit.finish(event, &result);
+        (result, timer.finish())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -129,4 +335,228 @@ mod tests {
         let result = Assertion::from_base64(valid_cbor_base64);
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_counter() {
+        let valid_cbor_base64 = "omlzaWduYXR1cmVYRjBEAiAImFuY4+UbGZ5/ZbjAJpjQ3bd8GxaKFpMEo58WMEUGbwIgaqdDJnVS8/3oJCz16O5Zp4Qga5g6zrFF7eoiYEWkdtNxYXV0aGVudGljYXRvckRhdGFYJaRc2WwGuoniZEqtF+kolObjxcczFdDxbrhJR/nT8ehTQAAAAAI=";
+        let assertion = Assertion::from_base64(valid_cbor_base64).unwrap();
+        assert_eq!(assertion.counter().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_nonce() {
+        let assertion = Assertion {
+            raw_authenticator_data: b"auth".to_vec(),
+            signature: Sensitive(Vec::new()),
+        };
+        let mut expected = b"auth".to_vec();
+        expected.extend_from_slice(&Sha256::digest(b"client"));
+        assert_eq!(assertion.nonce(b"client"), Sha256::digest(&expected).to_vec());
+    }
+
+    #[test]
+    fn test_client_data_challenge() {
+        let json = br#"{"challenge": "c2VydmVy", "action": "transfer"}"#;
+        assert_eq!(client_data_challenge(json).as_deref(), Some("c2VydmVy"));
+        assert_eq!(client_data_challenge(b"x = 15"), None);
+        assert_eq!(client_data_challenge(br#"{"challenge": 1}"#), None);
+        assert_eq!(client_data_challenge(br#"["challenge"]"#), None);
+    }
+
+    #[test]
+    fn test_from_cbor_matches_from_base64() {
+        let valid_cbor_base64 = "omlzaWduYXR1cmVYRjBEAiAImFuY4+UbGZ5/ZbjAJpjQ3bd8GxaKFpMEo58WMEUGbwIgaqdDJnVS8/3oJCz16O5Zp4Qga5g6zrFF7eoiYEWkdtNxYXV0aGVudGljYXRvckRhdGFYJaRc2WwGuoniZEqtF+kolObjxcczFdDxbrhJR/nT8ehTQAAAAAI=";
+        let cbor = general_purpose::STANDARD.decode(valid_cbor_base64).unwrap();
+        let from_cbor = Assertion::from_cbor(&cbor).unwrap();
+        let from_base64 = Assertion::from_base64(valid_cbor_base64).unwrap();
+        assert_eq!(from_cbor.raw_authenticator_data, from_base64.raw_authenticator_data);
+        assert_eq!(from_cbor.signature, from_base64.signature);
+    }
+
+    #[test]
+    fn test_minimal_cbor_matches_ciborium() {
+        let valid_cbor_base64 = "omlzaWduYXR1cmVYRjBEAiAImFuY4+UbGZ5/ZbjAJpjQ3bd8GxaKFpMEo58WMEUGbwIgaqdDJnVS8/3oJCz16O5Zp4Qga5g6zrFF7eoiYEWkdtNxYXV0aGVudGljYXRvckRhdGFYJaRc2WwGuoniZEqtF+kolObjxcczFdDxbrhJR/nT8ehTQAAAAAI=";
+        let cbor = general_purpose::STANDARD.decode(valid_cbor_base64).unwrap();
+        let expected: Assertion = from_reader(Cursor::new(&cbor)).unwrap();
+        assert_eq!(Assertion::from_minimal_cbor(&cbor).unwrap(), expected);
+
+        for len in 0..cbor.len() {
+            assert!(Assertion::from_minimal_cbor(&cbor[..len]).is_err(), "prefix of {} bytes", len);
+        }
+        // The same map with the signature twice.
+        let mut duplicated = cbor.clone();
+        duplicated[0] = 0xa3;
+        duplicated.extend_from_slice(&cbor[1..1 + 10 + 2 + 70]);
+        assert!(Assertion::from_minimal_cbor(&duplicated).is_err());
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_verification_spans() {
+        let output = crate::traced(|| {
+            let assertion = Assertion::from_base64(include_str!("../../apps/fixtures/assertion.b64").trim()).unwrap();
+            let client_data = include_str!("../../apps/fixtures/client_data.b64").trim();
+            let app_id = include_str!("../../apps/fixtures/app_id.txt").trim();
+            let public_key = include_str!("../../apps/fixtures/public_key.pem");
+            assertion.verify(client_data, app_id, public_key, Some(0), None).unwrap();
+        });
+        // Base64 is parsed as it is decoded, without a separate `assertion.parse`.
+        for span in ["assertion.decode{len=", "assertion.verify{previous_counter=0 counter=1}"] {
+            assert!(output.contains(span), "no {span} in\n{output}");
+        }
+    }
+
+    #[test]
+    fn test_verifier_reports_outcomes() {
+        let sink = Arc::new(crate::metrics::RecordingSink::default());
+        let verifier = AssertionVerifier::default().with_metrics(sink.clone());
+        let assertion = || Assertion::from_base64(include_str!("../../apps/fixtures/assertion.b64").trim()).unwrap();
+        let client_data = include_str!("../../apps/fixtures/client_data.b64").trim();
+        let client_data = general_purpose::STANDARD.decode(client_data).unwrap();
+        let app_id_hash: [u8; 32] = Sha256::digest(include_str!("../../apps/fixtures/app_id.txt").trim()).into();
+        let public_key = VerifyingKey::from_public_key_pem(include_str!("../../apps/fixtures/public_key.pem")).unwrap();
+        let public_key = public_key.to_encoded_point(false);
+
+        assert!(verifier.verify_raw(assertion(), &client_data, &app_id_hash, public_key.as_bytes(), Some(0)).is_ok());
+        let replayed = verifier.verify_raw(assertion(), &client_data, &app_id_hash, public_key.as_bytes(), Some(1));
+        assert_eq!(replayed, Err(AppAttestError::InvalidCounter));
+        assert_eq!(
+            sink.names(),
+            [
+                "appattest_verifications_total{kind=assertion,outcome=success}",
+                "appattest_verify_duration_seconds{kind=assertion}",
+                "appattest_verifications_total{kind=assertion,outcome=InvalidCounter}",
+                "appattest_verify_duration_seconds{kind=assertion}",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_verifier_times_its_steps() {
+        use crate::timings::VerificationStep::*;
+        let verifier = AssertionVerifier::default().with_timings();
+        let assertion = || Assertion::from_base64(include_str!("../../apps/fixtures/assertion.b64").trim()).unwrap();
+        let client_data = include_str!("../../apps/fixtures/client_data.b64").trim();
+        let client_data = general_purpose::STANDARD.decode(client_data).unwrap();
+        let app_id_hash: [u8; 32] = Sha256::digest(include_str!("../../apps/fixtures/app_id.txt").trim()).into();
+        let public_key = VerifyingKey::from_public_key_pem(include_str!("../../apps/fixtures/public_key.pem")).unwrap();
+        let public_key = public_key.to_encoded_point(false);
+        let verify = |client_data: &[u8], public_key: &[u8], previous_counter| {
+            let (result, timings) = verifier.verify_timed(assertion(), client_data, &app_id_hash, public_key, previous_counter);
+            let timings = timings.unwrap();
+            assert!(timings.sum() <= timings.total);
+            assert!(timings.total - timings.sum() < std::time::Duration::from_millis(5), "{timings:?}");
+            (result.map(|_| ()), timings.steps.iter().map(|timing| timing.step).collect::<Vec<_>>())
+        };
+
+        assert_eq!(verify(&client_data, public_key.as_bytes(), Some(0)), (Ok(()), vec![Decode, Nonce, Signature, AuthData]));
+        let replayed = verify(&client_data, public_key.as_bytes(), Some(1));
+        assert_eq!(replayed, (Err(AppAttestError::InvalidCounter), vec![Decode, Nonce, Signature, AuthData]));
+        let forged = verify(b"{}", public_key.as_bytes(), Some(0));
+        assert_eq!(forged, (Err(AppAttestError::InvalidSignature), vec![Decode, Nonce, Signature]));
+        assert_eq!(verify(&client_data, &[4; 12], Some(0)), (Err(AppAttestError::InvalidPublicKey), vec![Decode]));
+
+        let untimed = AssertionVerifier::default();
+        let (result, timings) = untimed.verify_timed(assertion(), &client_data, &app_id_hash, public_key.as_bytes(), Some(0));
+        assert!(result.is_ok() && timings.is_none());
+    }
+
+    #[test]
+    fn test_verifier_denies_keys_before_the_signature() {
+        let deny_list = Arc::new(crate::keypolicy::InMemoryKeyList::deny());
+        let verifier = AssertionVerifier::default().with_timings().with_key_policy(deny_list.clone());
+        let assertion = || Assertion::from_base64(include_str!("../../apps/fixtures/assertion.b64").trim()).unwrap();
+        let client_data = include_str!("../../apps/fixtures/client_data.b64").trim();
+        let client_data = general_purpose::STANDARD.decode(client_data).unwrap();
+        let app_id_hash: [u8; 32] = Sha256::digest(include_str!("../../apps/fixtures/app_id.txt").trim()).into();
+        let public_key = VerifyingKey::from_public_key_pem(include_str!("../../apps/fixtures/public_key.pem")).unwrap();
+        let public_key = public_key.to_encoded_point(false);
+
+        deny_list.insert_key_id(KeyId::for_public_key(public_key.as_bytes()).unwrap());
+        let (result, timings) = verifier.verify_timed(assertion(), &client_data, &app_id_hash, public_key.as_bytes(), Some(0));
+        assert_eq!(result, Err(AppAttestError::KeyDenied { reason: "key on the deny list".into() }));
+        // Not a step was run, the signature least of all.
+        assert!(timings.unwrap().steps.is_empty());
+
+        deny_list.remove_key_id(&KeyId::for_public_key(public_key.as_bytes()).unwrap());
+        assert!(verifier.verify_raw(assertion(), &client_data, &app_id_hash, public_key.as_bytes(), Some(0)).is_ok());
+    }
+
+    #[test]
+    fn test_verifier_audits_decisions() {
+        let dir = tempfile::tempdir().unwrap();
+        let sink = Arc::new(crate::audit::JsonlAuditSink::open(dir.path().join("audit.jsonl"), u64::MAX).unwrap());
+        let verifier = AssertionVerifier::default().with_audit(sink.clone());
+        let assertion = || Assertion::from_base64(include_str!("../../apps/fixtures/assertion.b64").trim()).unwrap();
+        let client_data = include_str!("../../apps/fixtures/client_data.b64").trim();
+        let client_data = general_purpose::STANDARD.decode(client_data).unwrap();
+        let app_id_hash: [u8; 32] = Sha256::digest(include_str!("../../apps/fixtures/app_id.txt").trim()).into();
+        let public_key = VerifyingKey::from_public_key_pem(include_str!("../../apps/fixtures/public_key.pem")).unwrap();
+        let public_key = public_key.to_encoded_point(false);
+
+        assert!(verifier.verify_raw(assertion(), &client_data, &app_id_hash, public_key.as_bytes(), Some(0)).is_ok());
+        let replayed = verifier.verify_raw(assertion(), &client_data, &app_id_hash, public_key.as_bytes(), Some(1));
+        assert_eq!(replayed, Err(AppAttestError::InvalidCounter));
+        let forged = verifier.verify_raw(assertion(), b"{}", &app_id_hash, public_key.as_bytes(), Some(0));
+        assert_eq!(forged, Err(AppAttestError::InvalidSignature));
+        sink.flush().unwrap();
+
+        let log = std::fs::read_to_string(sink.path()).unwrap();
+        let events: Vec<crate::audit::AuditEvent> = log.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        let reasons: Vec<_> = events.iter().map(|event| event.reason.as_ref().map(|reason| reason.code)).collect();
+        assert_eq!(reasons, [None, Some(1004), Some(1007)]);
+        let key_id = KeyId::for_public_key(public_key.as_bytes()).unwrap();
+        assert!(events.iter().all(|event| event.key_id == Some(key_id) && event.counter == Some(1)));
+        let inputs: Vec<_> = events[0].digests.iter().map(|digest| digest.input.as_str()).collect();
+        assert_eq!(inputs, ["authenticatorData", "clientData", "signature"]);
+        assert_eq!(events[2].digests[1].sha256, <[u8; 32]>::from(Sha256::digest(b"{}")));
+        assert!(!log.contains(&general_purpose::STANDARD.encode(&assertion().signature)));
+    }
+
+    #[test]
+    fn test_counter_exhausts_at_u32_max() {
+        let public_key = VerifyingKey::from_public_key_pem(include_str!("../../apps/fixtures/public_key.pem")).unwrap();
+        let app_id_hash: [u8; 32] = Sha256::digest(include_str!("../../apps/fixtures/app_id.txt").trim()).into();
+        let with_counter = |counter: u32| {
+            let mut assertion = Assertion::from_base64(include_str!("../../apps/fixtures/assertion.b64").trim()).unwrap();
+            assertion.raw_authenticator_data[33..37].copy_from_slice(&counter.to_be_bytes());
+            assertion
+        };
+        // The signature no longer covers the counter, so it is not checked.
+        let verify = |counter, previous_counter| {
+            with_counter(counter).verify_with_key(b"", &app_id_hash, &public_key, previous_counter, false, &mut Timer::new(false)).map(|_| ())
+        };
+        assert_eq!(verify(u32::MAX - 1, Some(u32::MAX - 2)), Ok(()));
+        assert_eq!(verify(u32::MAX, Some(u32::MAX - 1)), Err(AppAttestError::CounterExhausted));
+        assert_eq!(verify(u32::MAX, Some(0)), Err(AppAttestError::CounterExhausted));
+        assert_eq!(verify(u32::MAX, None), Err(AppAttestError::CounterExhausted));
+        assert_eq!(verify(u32::MAX, Some(u32::MAX)), Err(AppAttestError::InvalidCounter));
+    }
+
+    #[test]
+    fn test_first_assertion_accepts_any_counter() {
+        let public_key = VerifyingKey::from_public_key_pem(include_str!("../../apps/fixtures/public_key.pem")).unwrap();
+        let app_id_hash: [u8; 32] = Sha256::digest(include_str!("../../apps/fixtures/app_id.txt").trim()).into();
+        let verify = |counter: u32, previous_counter| {
+            let mut assertion = Assertion::from_base64(include_str!("../../apps/fixtures/assertion.b64").trim()).unwrap();
+            assertion.raw_authenticator_data[33..37].copy_from_slice(&counter.to_be_bytes());
+            assertion.verify_with_key(b"", &app_id_hash, &public_key, previous_counter, false, &mut Timer::new(false)).map(|_| ())
+        };
+        assert_eq!(verify(0, None), Ok(()));
+        assert_eq!(verify(1, None), Ok(()));
+        // Once there is a previous assertion, its counter is spent, 0 included.
+        assert_eq!(verify(0, Some(0)), Err(AppAttestError::InvalidCounter));
+        assert_eq!(verify(1, Some(1)), Err(AppAttestError::InvalidCounter));
+        assert_eq!(verify(2, Some(1)), Ok(()));
+    }
+
+    #[test]
+    fn test_verify_raw_rejects_invalid_public_key() {
+        let assertion = Assertion {
+            raw_authenticator_data: vec![0; 37],
+            signature: Sensitive(Vec::new()),
+        };
+        let result = assertion.verify_raw(b"client", &[0; 32], &[4; 10], None);
+        assert!(matches!(result, Err(AppAttestError::InvalidPublicKey)));
+    }
 }
\ No newline at end of file
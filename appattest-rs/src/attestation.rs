@@ -1,22 +1,257 @@
+use std::borrow::Cow;
+use std::fmt;
 use std::io::Cursor;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
 use base64::{engine::general_purpose, Engine};
 use ciborium::from_reader;
 use serde::{Deserialize, Serialize};
-use crate::{authenticator::AuthenticatorData, error::AppAttestError};
+use crate::{authenticator::AuthenticatorData, cbor, error::AppAttestError, root::{self, RootCertError}};
+use crate::key::{key_id_for_public_key, KeyId, PublicKey};
+use crate::keypolicy::{KeyPolicy, Policy};
+use crate::audit::{Audit, AuditEvent, AuditKind, AuditSink};
+use crate::metrics::{MetricsSink, Sink};
+use crate::receipt::{Receipt, ReceiptPolicy};
+use crate::decode::rfc3339;
+use crate::sensitive::{base64_problem, Sensitive};
+use crate::timings::{Timer, VerificationStep, VerificationTimings};
 use std::error::Error;
 use x509_parser::prelude::*;
 use der_parser::{ber::BerObjectContent, oid::Oid, parse_ber};
-use sha2::{Digest, Sha256};
+use sha2::{Digest, Sha256, Sha384};
+use p256::ecdsa::signature::hazmat::PrehashVerifier;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, PartialEq)]
 pub struct Attestation {
+    /// The statement format, [`FORMAT`] for App Attest; only
+    /// [`Attestation::validate_structure`] checks it.
+    #[serde(default)]
+    fmt: String,
     #[serde(rename = "attStmt")]
     statement: Statement,
     #[serde(rename="authData")]
     auth_data: Vec<u8>,
+    #[serde(skip)]
+    validated: Validated,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Shows the format and, as [`Sensitive`], the byte strings, never the bytes themselves.
+impl fmt::Debug for Attestation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Attestation")
+            .field("fmt", &self.fmt)
+            .field("certificates", &self.statement.certificates.iter().map(Sensitive).collect::<Vec<_>>())
+            .field("receipt", &Sensitive(&self.statement.receipt))
+            .field("auth_data", &Sensitive(&self.auth_data))
+            .finish()
+    }
+}
+
+/// The `fmt` of an App Attest attestation object.
+pub const FORMAT: &str = "apple-appattest";
+
+/// The [`StructureReport`] of an [`Attestation`] once [`Attestation::validate_structure`]
+/// made it, for the verification after to reuse. Not part of the attestation's value: a
+/// validated attestation equals the same one unvalidated.
+#[derive(Debug, Default)]
+struct Validated(OnceLock<StructureReport>);
+
+impl PartialEq for Validated {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+/// What [`Attestation::validate_structure`] found: everything about an attestation that is
+/// checked without a signature, a root, the time, or the server's challenge, app ID and key
+/// ID.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StructureReport {
+    /// Certificates in `x5c`, the leaf first, not counting an embedded root after them.
+    pub certificate_count: usize,
+    /// Uncompressed SEC1 public key the leaf certifies.
+    #[serde(with = "crate::json::bytes")]
+    pub leaf_public_key: Vec<u8>,
+    /// The nonce the leaf's credCert extension certifies.
+    #[serde(with = "crate::json::bytes")]
+    pub certificate_nonce: Vec<u8>,
+    /// Unix time the leaf's validity ends, its `notAfter`.
+    pub leaf_not_after: i64,
+    /// The App Attest environment the AAGUID names.
+    pub environment: Environment,
+    /// The credential ID from the authenticator data, which verification compares with the
+    /// key ID.
+    #[serde(with = "crate::json::bytes")]
+    pub credential_id: Vec<u8>,
+}
+
+/// What verification takes from the leaf certificate, parsed once.
+struct Leaf {
+    public_key: Vec<u8>,
+    nonce: Vec<u8>,
+    not_after: i64,
+}
+
+impl Leaf {
+    fn from_certificate(cert: &X509Certificate) -> Result<Self, AppAttestError> {
+        // The leaf certifies a P-256 key as an uncompressed point, or the key ID cannot match.
+        let public_key = PublicKey::from_spki_der(cert.public_key().raw)?.to_uncompressed().to_vec();
+        let nonce = Attestation::extract_nonce_from_cert(cert)?;
+        Ok(Leaf { public_key, nonce, not_after: cert.validity().not_after.timestamp() })
+    }
+
+    fn from_report(report: &StructureReport) -> Self {
+        Leaf {
+            public_key: report.leaf_public_key.clone(),
+            nonce: report.certificate_nonce.clone(),
+            not_after: report.leaf_not_after,
+        }
+    }
+}
+
+/// App Attest environment a key was attested in, from the AAGUID. In JSON, `"production"`
+/// or `"development"`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum Environment {
+    Production,
+    Development,
+}
+
+/// What a successful [`Attestation::verify_raw`] establishes about the key.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct VerifiedAttestation {
+    /// Uncompressed SEC1 public key of the attested key.
+    #[serde(with = "crate::json::bytes")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+    pub public_key: Vec<u8>,
+    /// Receipt to exchange with Apple for fraud metrics.
+    #[serde(with = "crate::json::bytes")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+    pub receipt: Vec<u8>,
+    /// RP ID hash from the authenticator data, equal to the expected app ID hash.
+    #[serde(with = "crate::json::bytes")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+    pub rp_id_hash: [u8; 32],
+    pub environment: Environment,
+    /// Unix time the leaf certificate expires, from [`VerifiedChain::leaf_not_after`];
+    /// `None` from [`Attestation::verify_auth_data`] alone, until
+    /// [`VerifiedAttestation::with_chain`]. Assertions keep verifying after it, but a policy
+    /// may want the key attested again before.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub leaf_not_after: Option<i64>,
+    /// The app ID the RP ID hash was checked against, taken from the leaf certificate with
+    /// [`VerifyOptions::infer_app_id`]; `None` when the caller gave its hash.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub app_id: Option<String>,
+    /// How long each step took, from a verifier [`AttestationVerifier::with_timings`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timings: Option<VerificationTimings>,
+}
+
+impl VerifiedAttestation {
+    /// Completes the result of [`Attestation::verify_auth_data`] with what only the chain
+    /// establishes, as [`Attestation::verify_raw`] does.
+    pub fn with_chain(mut self, chain: &VerifiedChain) -> Self {
+        self.leaf_not_after = Some(chain.leaf_not_after);
+        self
+    }
+}
+
+/// What a successful [`Attestation::verify_chain`] establishes: a trusted root, normally
+/// Apple's, certified a key for this authenticator data and challenge.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct VerifiedChain {
+    /// Uncompressed SEC1 public key of the leaf certificate.
+    #[serde(with = "crate::json::bytes")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+    pub public_key: Vec<u8>,
+    /// `SHA256` of the leaf certificate's DER.
+    #[serde(with = "crate::json::bytes")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+    pub leaf_fingerprint: [u8; 32],
+    /// `SHA256` of the authenticator data the certified nonce covers.
+    #[serde(with = "crate::json::bytes")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+    pub auth_data_hash: [u8; 32],
+    /// Name of the trusted root the chain ends at, see [`TrustedRoot::name`].
+    pub root: String,
+    /// Unix time the leaf certificate's validity ends, its `notAfter`.
+    pub leaf_not_after: i64,
+}
+
+/// The intermediate values of an attestation verification, as hex, from
+/// [`AttestationVerifier::verify_debug`], to tell a client bug from a server bug.
+///
+/// For the server's own logs only: never send it back to a client. It tells whoever holds
+/// it which check failed and the value that would have passed. Values are `None` for the
+/// steps the verification did not reach.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationTrace {
+    /// The step of Apple's "Validating apps that connect to your server", 1 to 9, that
+    /// failed. `None` if all of them passed, even when the receipt policy failed after.
+    pub failed_step: Option<u8>,
+    /// `SHA256` of the challenge (step 2).
+    pub client_data_hash: Option<String>,
+    /// `SHA256(authData || clientDataHash)`, the nonce the leaf should certify (step 3).
+    pub nonce: Option<String>,
+    /// The nonce the leaf's credCert extension certifies (step 4).
+    pub certificate_nonce: Option<String>,
+    /// `SHA256` of the leaf's public key (step 5).
+    pub public_key_hash: Option<String>,
+    /// The key identifier compared with it, and with the credential ID (steps 5 and 9).
+    pub key_id: Option<String>,
+    /// RP ID hash from the authenticator data (step 6).
+    pub rp_id_hash: Option<String>,
+    /// `SHA256` of the app ID it is compared with (step 6).
+    pub expected_rp_id_hash: Option<String>,
+    /// Counter from the authenticator data (step 7).
+    pub counter: Option<u32>,
+    /// AAGUID from the authenticator data (step 8).
+    pub aaguid: Option<String>,
+}
+
+/// Where a verification records its steps; only [`AttestationVerifier::verify_debug`] passes
+/// a trace, and only a verifier [`AttestationVerifier::with_timings`] a timer, so the
+/// others, and the guests, compute nothing extra.
+#[derive(Default)]
+struct Tracer<'a> {
+    trace: Option<&'a mut VerificationTrace>,
+    timer: Option<&'a mut Timer>,
+}
+
+impl Tracer<'_> {
+    /// Step `step` starts; it is the failed one unless a later one starts.
+    fn step(&mut self, step: u8) {
+        self.record(|trace| trace.failed_step = Some(step));
+    }
+
+    fn record(&mut self, record: impl FnOnce(&mut VerificationTrace)) {
+        if let Some(trace) = self.trace.as_deref_mut() {
+            record(trace);
+        }
+    }
+
+    /// Timed step `step` starts, and the one before ends.
+    fn time(&mut self, step: VerificationStep) {
+        if let Some(timer) = self.timer.as_deref_mut() {
+            timer.step(step);
+        }
+    }
+
+    fn reborrow(&mut self) -> Tracer<'_> {
+        Tracer { trace: self.trace.as_deref_mut(), timer: self.timer.as_deref_mut() }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
 struct Statement {
     #[serde(rename = "x5c")]
     certificates: Vec<Vec<u8>>,
@@ -24,6 +259,210 @@ struct Statement {
     receipt: Vec<u8>,
 }
 
+/// An attestation object borrowing its certificates, receipt and authenticator data from
+/// the CBOR it was parsed from, for servers verifying many: parsing allocates only the list
+/// of certificates, where [`Attestation`] copies every byte string out of the CBOR.
+///
+/// Always parsed with the crate's own reader, see [`crate::cbor`], as ciborium cannot lend
+/// byte strings out of its input. It verifies exactly as the [`Attestation`] from the same
+/// bytes does; keep that one for FFI and the zkVM guest, which need an owned value.
+#[derive(Clone, PartialEq)]
+pub struct AttestationRef<'a> {
+    fmt: &'a str,
+    certificates: Vec<&'a [u8]>,
+    receipt: &'a [u8],
+    auth_data: &'a [u8],
+}
+
+/// As [`Attestation`]'s, shows the byte strings only as [`Sensitive`].
+impl fmt::Debug for AttestationRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AttestationRef")
+            .field("fmt", &self.fmt)
+            .field("certificates", &self.certificates.iter().map(Sensitive).collect::<Vec<_>>())
+            .field("receipt", &Sensitive(self.receipt))
+            .field("auth_data", &Sensitive(self.auth_data))
+            .finish()
+    }
+}
+
+impl<'a> AttestationRef<'a> {
+    /// Parses the raw CBOR bytes the device produced, without copying them.
+    ///
+    /// # Errors
+    /// Returns `AppAttestError` if deserialization fails.
+    pub fn from_cbor(cbor: &'a [u8]) -> Result<Self, AppAttestError> {
+        let mut reader = cbor::Reader::new(cbor);
+        let (mut fmt, mut statement, mut auth_data) = (None, None, None);
+        for _ in 0..reader.map()? {
+            match reader.text()? {
+                key @ "fmt" => cbor::set_once(&mut fmt, reader.text()?, key)?,
+                key @ "attStmt" => {
+                    let value = Self::statement_from_cbor(&mut reader)?;
+                    cbor::set_once(&mut statement, value, key)?
+                }
+                key @ "authData" => cbor::set_once(&mut auth_data, reader.bytes()?, key)?,
+                _ => reader.skip()?,
+            }
+        }
+        let (certificates, receipt) = cbor::required(statement, "attStmt")?;
+        Ok(AttestationRef {
+            fmt: fmt.unwrap_or_default(),
+            certificates,
+            receipt,
+            auth_data: cbor::required(auth_data, "authData")?,
+        })
+    }
+
+    fn statement_from_cbor(reader: &mut cbor::Reader<'a>) -> Result<(Vec<&'a [u8]>, &'a [u8]), AppAttestError> {
+        let (mut certificates, mut receipt) = (None, None);
+        for _ in 0..reader.map()? {
+            match reader.text()? {
+                key @ "x5c" => cbor::set_once(&mut certificates, reader.byte_strings()?, key)?,
+                key @ "receipt" => cbor::set_once(&mut receipt, reader.bytes()?, key)?,
+                _ => reader.skip()?,
+            }
+        }
+        Ok((cbor::required(certificates, "x5c")?, cbor::required(receipt, "receipt")?))
+    }
+
+    /// The authenticator data the attestation carries.
+    pub fn auth_data(&self) -> &'a [u8] {
+        self.auth_data
+    }
+
+    /// The receipt the attestation carries, see [`crate::receipt::Receipt::from_ber`].
+    pub fn receipt(&self) -> &'a [u8] {
+        self.receipt
+    }
+
+    /// [`Attestation::extract_app_id`].
+    pub fn extract_app_id(&self) -> Result<String, AppAttestError> {
+        leaf_app_id(self.certificates.first().copied())
+    }
+
+    /// Copies the borrowed bytes into an [`Attestation`].
+    pub fn to_attestation(&self) -> Attestation {
+        Attestation {
+            fmt: self.fmt.to_string(),
+            statement: Statement {
+                certificates: self.certificates.iter().map(|der| der.to_vec()).collect(),
+                receipt: self.receipt.to_vec(),
+            },
+            auth_data: self.auth_data.to_vec(),
+            validated: Validated::default(),
+        }
+    }
+
+    /// [`Attestation::validate_structure`]. The report is not kept: verifying this
+    /// attestation parses its leaf again.
+    pub fn validate_structure(&self) -> Result<StructureReport, AppAttestError> {
+        if self.fmt != FORMAT {
+            return Err(AppAttestError::UnsupportedFormat { fmt: self.fmt.to_string() });
+        }
+        let chain = strip_trusted_root(&self.certificates, &AttestationVerifier::embedded().roots);
+        let parsed = parse_chain(chain, VerifyOptions::DEFAULT_MAX_CHAIN_LENGTH)?;
+        let leaf = Leaf::from_certificate(&parsed[0])?;
+        let auth_data = AuthenticatorData::new(self.auth_data.to_vec())?;
+        let environment = environment(&auth_data)?;
+        let credential_id = match auth_data.credential_id {
+            Some(credential_id) if credential_id.len() == 32 => credential_id,
+            _ => return Err(AppAttestError::InvalidCredentialID),
+        };
+        Ok(StructureReport {
+            certificate_count: chain.len(),
+            leaf_public_key: leaf.public_key,
+            certificate_nonce: leaf.nonce,
+            leaf_not_after: leaf.not_after,
+            environment,
+            credential_id,
+        })
+    }
+
+    /// [`Attestation::verify_raw`] without taking the attestation.
+    pub fn verify_raw(&self, challenge: &[u8], app_id_hash: &[u8; 32], key_id: &[u8], time: i64) -> Result<VerifiedAttestation, AppAttestError> {
+        let chain = self.verify_chain(challenge, time)?;
+        Ok(self.verify_auth_data(app_id_hash, key_id, &chain.public_key)?.with_chain(&chain))
+    }
+
+    /// [`Attestation::verify_chain`].
+    pub fn verify_chain(&self, challenge: &[u8], time: i64) -> Result<VerifiedChain, AppAttestError> {
+        let verifier = AttestationVerifier::embedded();
+        self.verify_chain_to(None, &verifier.roots, &verifier.options, challenge, time, Tracer::default())
+    }
+
+    /// [`Attestation::verify_auth_data`]. The authenticator data and receipt are copied, the
+    /// latter into the result.
+    pub fn verify_auth_data(
+        &self,
+        app_id_hash: &[u8; 32],
+        key_id: &[u8],
+        public_key: &[u8],
+    ) -> Result<VerifiedAttestation, AppAttestError> {
+        verify_auth_data(self.auth_data.to_vec(), self.receipt.to_vec(), app_id_hash, key_id, public_key, Tracer::default())
+    }
+
+    /// Verifies the chain, taking the leaf's key, nonce and expiry from `validated`, this
+    /// attestation's report, if it has one.
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        name = "attestation.verify_chain", level = "debug", skip_all,
+        fields(cert_count = self.certificates.len(), root = tracing::field::Empty), err(level = "debug"),
+    ))]
+    fn verify_chain_to(
+        &self,
+        validated: Option<&StructureReport>,
+        roots: &[TrustedRoot],
+        options: &VerifyOptions,
+        challenge: &[u8],
+        time: i64,
+        mut trace: Tracer<'_>,
+    ) -> Result<VerifiedChain, AppAttestError> {
+        // Step 1: Verify Certificates
+        trace.step(1);
+        trace.time(VerificationStep::Decode);
+        let mut root_certs = Vec::with_capacity(roots.len());
+        for root in roots {
+            let (_, cert) = parse_x509_certificate(&root.der)
+                .map_err(|_| AppAttestError::Message("invalid root DER".into()))?;
+            root_certs.push(cert);
+        }
+        let chain = strip_trusted_root(&self.certificates, roots);
+        let parsed = parse_chain(chain, options.max_chain_length)?;
+        trace.time(VerificationStep::Chain);
+        let anchor = Attestation::verify_parsed_certificates(&parsed, &root_certs, time, options)?;
+        record!(root = &*roots[anchor].name);
+        let leaf = (self.certificates.first())
+            .ok_or_else(|| AppAttestError::Message("certificate list is empty".into()))?;
+
+        // Step 3: Create and Verify Nonce
+        trace.step(2);
+        trace.time(VerificationStep::Nonce);
+        let client_data_hash = Sha256::digest(challenge);
+        trace.record(|trace| trace.client_data_hash = Some(hex::encode(client_data_hash)));
+        trace.step(3);
+        let nonce = Attestation::nonce_hash(self.auth_data, &client_data_hash);
+        trace.record(|trace| trace.nonce = Some(hex::encode(&nonce)));
+        trace.step(4);
+        // The walk above parsed the leaf; `validated` already holds what is needed from it.
+        let cred_cert = match validated {
+            Some(report) => Leaf::from_report(report),
+            None => Leaf::from_certificate(&parsed[0])?,
+        };
+        trace.record(|trace| trace.certificate_nonce = Some(hex::encode(&cred_cert.nonce)));
+        if cred_cert.nonce.as_slice() != nonce.as_slice() {
+            return Err(AppAttestError::InvalidNonce);
+        }
+
+        Ok(VerifiedChain {
+            public_key: cred_cert.public_key,
+            leaf_fingerprint: Sha256::digest(leaf).into(),
+            auth_data_hash: Sha256::digest(self.auth_data).into(),
+            root: roots[anchor].name.to_string(),
+            leaf_not_after: cred_cert.not_after,
+        })
+    }
+}
+
 impl Attestation {
     /// Creates a new `Attestation` from a Base64-encoded CBOR string.
     /// 
@@ -32,12 +471,57 @@ impl Attestation {
     ///
     /// # Errors
     /// Returns `AppAttestError` if decoding or deserialization fails.
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        name = "attestation.decode", level = "debug", skip_all,
+        fields(len = base64_attestation.len()), err(level = "debug"),
+    ))]
     pub fn from_base64(base64_attestation: &str) -> Result<Self, AppAttestError> {
+        if !cfg!(feature = "minimal-cbor") {
+            // Streamed, so the decoded bytes are never held in full next to the text.
+            return cbor::from_base64(base64_attestation, "unable to parse base64 attestation");
+        }
         let decoded_bytes = general_purpose::STANDARD
         .decode(base64_attestation)
-        .map_err(|e| AppAttestError::Message(format!("Failed to decode Base64: {}", e)))?;
+        .map_err(|e| AppAttestError::Message(format!("Failed to decode Base64: {}", base64_problem(&e))))?;
+
+        Self::from_cbor(&decoded_bytes)
+    }
+
+    /// The authenticator data the attestation carries.
+    pub fn auth_data(&self) -> &[u8] {
+        &self.auth_data
+    }
 
-        let cursor = Cursor::new(decoded_bytes);
+    /// The receipt the attestation carries, see [`crate::receipt::Receipt::from_ber`].
+    pub fn receipt(&self) -> &[u8] {
+        &self.statement.receipt
+    }
+
+    /// Returns the app ID, `<team id>.<bundle id>`, the leaf certificate was issued for,
+    /// without verifying anything. Apple puts it in the leaf's `1.2.840.113635.100.8.5`
+    /// extension, as an octet string under context tag [`APP_ID_TAG`]. Verifying with it
+    /// instead of a given app ID is [`VerifyOptions::infer_app_id`].
+    ///
+    /// # Errors
+    /// Fails if there is no leaf, it does not parse, or it lacks the extension or the tag.
+    pub fn extract_app_id(&self) -> Result<String, AppAttestError> {
+        leaf_app_id(self.statement.certificates.first().map(Vec::as_slice))
+    }
+
+    /// Creates a new `Attestation` from the raw CBOR bytes the device produced.
+    ///
+    /// With the `minimal-cbor` feature this uses the crate's own reader instead of ciborium.
+    ///
+    /// # Errors
+    /// Returns `AppAttestError` if deserialization fails.
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        name = "attestation.parse", level = "debug", skip_all, fields(len = cbor.len()), err(level = "debug"),
+    ))]
+    pub fn from_cbor(cbor: &[u8]) -> Result<Self, AppAttestError> {
+        if cfg!(feature = "minimal-cbor") {
+            return Self::from_minimal_cbor(cbor);
+        }
+        let cursor = Cursor::new(cbor);
         let assertion_result: Result<Attestation, _> = from_reader(cursor);  
         if let Ok(assertion) = assertion_result {
             return  Ok(assertion)
@@ -45,24 +529,52 @@ impl Attestation {
         Err(AppAttestError::Message("unable to parse base64 attestation".to_string()))
     }
 
-    /// Verifies `cert_chain` back to `apple_root_der` at `now`.
+    fn from_minimal_cbor(bytes: &[u8]) -> Result<Self, AppAttestError> {
+        AttestationRef::from_cbor(bytes).map(|attestation| attestation.to_attestation())
+    }
+
+    /// Borrows the attestation's bytes as an [`AttestationRef`], which verifies the same.
+    pub fn borrowed(&self) -> AttestationRef<'_> {
+        AttestationRef {
+            fmt: &self.fmt,
+            certificates: self.statement.certificates.iter().map(Vec::as_slice).collect(),
+            receipt: &self.statement.receipt,
+            auth_data: &self.auth_data,
+        }
+    }
+
+    /// Verifies `cert_chain` back to one of `roots` at `now`, and returns the index of the
+    /// root that issued its last certificate.
     /// *All* certs must be ECDSA-P256 / SHA-256 (true for Apple’s App Attest).
+    ///
+    /// A chain longer than `options` allow is rejected before any certificate is parsed, and
+    /// one repeating a certificate or containing a root before it is walked. A chain that
+    /// ends at a root drops it first, see [`strip_trusted_root`].
     pub fn verify_certificates(
-        cert_chain: &[Vec<u8>],          // leaf first, root last (leaf + ⟨intermediates⟩)
-        root_cert: &X509Certificate,           // trusted Apple root in **DER**
+        cert_chain: &[impl AsRef<[u8]>], // leaf first, without the root (leaf + ⟨intermediates⟩)
+        roots: &[X509Certificate],             // trusted roots, e.g. Apple's
         time: i64,
-    ) -> Result<(), AppAttestError> {
-        // 1. Basic sanity
-        if cert_chain.is_empty() {
-            return Err(AppAttestError::Message("certificate list is empty".into()));
-        }
+        options: &VerifyOptions,
+    ) -> Result<usize, AppAttestError> {
+        let parsed = parse_chain(cert_chain, options.max_chain_length)?;
+        Self::verify_parsed_certificates(&parsed, roots, time, options)
+    }
 
-        // 3. Parse the supplied chain
-        let mut parsed: Vec<X509Certificate<'_>> = Vec::with_capacity(cert_chain.len());
-        for der in cert_chain {
-            let (_, cert) = parse_x509_certificate(der)
-                .map_err(|_| AppAttestError::Message("failed to parse certificate".into()))?;
-            parsed.push(cert);
+    /// [`Attestation::verify_certificates`] from step 3 on, for a chain [`parse_chain`]
+    /// parsed.
+    fn verify_parsed_certificates(
+        parsed: &[X509Certificate],
+        roots: &[X509Certificate],
+        time: i64,
+        options: &VerifyOptions,
+    ) -> Result<usize, AppAttestError> {
+        // A root in the chain would be anchored at itself, its self-signature standing in
+        // for the issuer's.
+        let is_root = |cert: &X509Certificate| {
+            roots.iter().any(|root| root.tbs_certificate.as_ref() == cert.tbs_certificate.as_ref())
+        };
+        if let Some(index) = parsed.iter().position(is_root) {
+            return Err(AppAttestError::RootInCertificateChain { index });
         }
 
         // 4. Convert `now` to ASN.1 time for validity checks
@@ -73,38 +585,122 @@ impl Attestation {
         for (idx, cert) in parsed.iter().enumerate() {
             // 5-a. Check notBefore / notAfter
             if !cert.validity().is_valid_at(now_asn1) {
-                return Err(AppAttestError::Message("certificate expired / not yet valid".into()));
+                return Err(Attestation::validity_error(idx, cert, time));
             }
 
-            // 5-b. Pick issuer: next cert in vector or hard-coded Apple root
-            let issuer = if idx + 1 < parsed.len() { &parsed[idx + 1] } else { &root_cert };
+            // 5-b. Pick issuer: next cert in vector, or whichever trusted root has its name
+            if idx + 1 == parsed.len() {
+                break;
+            }
+            let issuer = &parsed[idx + 1];
 
             // 5-c. Subject / issuer DN match
             if cert.issuer() != issuer.subject() {
                 return Err(AppAttestError::Message("issuer DN mismatch".into()));
             }
 
-            // Bytes we are about to feed to ring
-            let _spki = issuer.subject_pki.raw;
-            let _tbs  = cert.tbs_certificate.as_ref();   // raw DER of TBSCertificate
-            let _sig  = &cert.signature_value.data;      // ASN.1 DER ECDSA sig
+            // 5-d. Issuer signature over the TBSCertificate
+            if !Attestation::is_signed_by(cert, issuer)? {
+                return Err(AppAttestError::Message(format!("signature verification failed for {}", idx)));
+            }
+        }
 
-            // Choose the ring algo from OID
+        // 6. Anchor the last certificate at a root. Roots may share a name, e.g. across a
+        // rotation, so the signature decides. A self-issued certificate is an untrusted root:
+        // its self-signature proves nothing.
+        let last = parsed.len() - 1;
+        if parsed[last].issuer() == parsed[last].subject() {
+            return Err(AppAttestError::UntrustedSelfSignedCertificate { index: last });
+        }
+        let mut named = roots.iter().enumerate().filter(|(_, root)| parsed[last].issuer() == root.subject()).peekable();
+        if named.peek().is_none() {
+            return Err(AppAttestError::Message("issuer DN mismatch".into()));
+        }
+        for (idx, root) in named {
+            if Attestation::is_signed_by(&parsed[last], root)? {
+                // 7. The leaf's issuer, the next certificate or else the root, against the pins
+                Attestation::check_issuer_pin(parsed.get(1).unwrap_or(root), options)?;
+                return Ok(idx);
+            }
+        }
+        Err(AppAttestError::Message(format!("signature verification failed for {}", last)))
+    }
 
-            // TODO - fix certificate verification
-            // UnparsedPublicKey::new(&ECDSA_P256_SHA256_ASN1, spki)
-            //     .verify(tbs, sig)
-            //     .map_err(|_| AppAttestError::Message(format!("signature verification failed for {}", idx)))?;
+    /// Which bound of its validity the certificate at `index` is outside of at `time`.
+    fn validity_error(index: usize, cert: &X509Certificate, time: i64) -> AppAttestError {
+        let subject = cert.subject();
+        let subject_cn = match subject.iter_common_name().next().and_then(|cn| cn.as_str().ok()) {
+            Some(cn) => cn.to_string(),
+            None => subject.to_string(),
+        };
+        let validity = cert.validity();
+        let not_before = rfc3339(validity.not_before.timestamp());
+        let not_after = rfc3339(validity.not_after.timestamp());
+        let checked_at = rfc3339(time);
+        if time < validity.not_before.timestamp() {
+            AppAttestError::CertificateNotYetValid { index, subject_cn, not_before, not_after, checked_at }
+        } else {
+            AppAttestError::CertificateExpired { index, subject_cn, not_before, not_after, checked_at }
         }
+    }
 
+    fn check_issuer_pin(issuer: &X509Certificate, options: &VerifyOptions) -> Result<(), AppAttestError> {
+        let Some(pins) = &options.pinned_intermediate_spki_sha256 else {
+            return Ok(());
+        };
+        let digest: [u8; 32] = Sha256::digest(issuer.public_key().raw).into();
+        if !pins.contains(&digest) {
+            return Err(AppAttestError::IntermediateNotPinned { spki_sha256: hex::encode(digest) });
+        }
         Ok(())
     }
 
+    /// Checks `issuer`'s ECDSA signature on `cert`. Apple's chain signs with SHA-256 or
+    /// SHA-384 using P-256 or P-384 keys. With the `android` feature, SHA-256 RSA signatures,
+    /// which Google's attestation roots make, are accepted too; anything else is rejected.
+    fn is_signed_by(cert: &X509Certificate, issuer: &X509Certificate) -> Result<bool, AppAttestError> {
+        let tbs = cert.tbs_certificate.as_ref();
+        let prehash = match cert.signature_algorithm.algorithm.to_id_string().as_str() {
+            #[cfg(feature = "android")]
+            "1.2.840.113549.1.1.11" => return Ok(Attestation::is_rsa_signed_by(cert, issuer)),
+            "1.2.840.10045.4.3.2" => Sha256::digest(tbs).to_vec(),
+            "1.2.840.10045.4.3.3" => Sha384::digest(tbs).to_vec(),
+            other => return Err(AppAttestError::Message(format!("unsupported certificate signature algorithm {}", other))),
+        };
+
+        let key = issuer.public_key().subject_public_key.data.as_ref();
+        let signature = cert.signature_value.data.as_ref();
+        let valid = match key.len() {
+            65 => match (p256::ecdsa::VerifyingKey::from_sec1_bytes(key), p256::ecdsa::Signature::from_der(signature)) {
+                (Ok(key), Ok(signature)) => key.verify_prehash(&prehash, &signature).is_ok(),
+                _ => false,
+            },
+            97 => match (p384::ecdsa::VerifyingKey::from_sec1_bytes(key), p384::ecdsa::Signature::from_der(signature)) {
+                (Ok(key), Ok(signature)) => key.verify_prehash(&prehash, &signature).is_ok(),
+                _ => false,
+            },
+            _ => return Err(AppAttestError::Message("unsupported certificate public key".into())),
+        };
+        Ok(valid)
+    }
+
+    /// `sha256WithRSAEncryption` for [`Attestation::is_signed_by`].
+    #[cfg(feature = "android")]
+    fn is_rsa_signed_by(cert: &X509Certificate, issuer: &X509Certificate) -> bool {
+        use rsa::{pkcs1::DecodeRsaPublicKey, pkcs1v15, signature::Verifier, RsaPublicKey};
+
+        let key = RsaPublicKey::from_pkcs1_der(issuer.public_key().subject_public_key.data.as_ref());
+        let signature = pkcs1v15::Signature::try_from(cert.signature_value.data.as_ref());
+        match (key, signature) {
+            (Ok(key), Ok(signature)) => pkcs1v15::VerifyingKey::<Sha256>::new(key)
+                .verify(cert.tbs_certificate.as_ref(), &signature)
+                .is_ok(),
+            _ => false,
+        }
+    }
+
     // extract_nonce_from_cert extracts the nonce from the certificate
-    fn extract_nonce_from_cert(cert_der: &[u8]) -> Result<Vec<u8>, AppAttestError> {
-        let (_, cert) = parse_x509_certificate(cert_der)
-            .map_err(|_| AppAttestError::Message("Failed to parse certificate".to_string()))?;
-    
+    fn extract_nonce_from_cert(cert: &X509Certificate) -> Result<Vec<u8>, AppAttestError> {
         let cred_cert_oid = Oid::from(&[1, 2, 840, 113635, 100, 8, 2])
             .map_err(|_| AppAttestError::Message("Failed to parse OID".to_string()))?;
     
@@ -123,7 +719,11 @@ impl Attestation {
                     BerObjectContent::Unknown(unknown_obj) => {
                         // Ref: https://cs.opensource.google/go/go/+/refs/tags/go1.22.4:src/encoding/asn1/asn1.go;l=530
                         let offset: usize = 2; 
-                        return Ok(unknown_obj.data[offset..].to_vec());
+                        return unknown_obj
+                            .data
+                            .get(offset..)
+                            .map(<[u8]>::to_vec)
+                            .ok_or(AppAttestError::FailedToExtractValueFromASN1Node);
                     },
                     _ => continue, 
                 }
@@ -134,131 +734,644 @@ impl Attestation {
         }
     }
 
+    // extract_app_id_from_cert extracts the app ID from the certificate
+    fn extract_app_id_from_cert(cert: &X509Certificate) -> Result<String, AppAttestError> {
+        let app_id_oid = Oid::from(&[1, 2, 840, 113635, 100, 8, 5])
+            .map_err(|_| AppAttestError::Message("Failed to parse OID".to_string()))?;
+
+        let extension_value = cert
+            .extensions()
+            .iter()
+            .find(|ext| ext.oid == app_id_oid)
+            .ok_or(AppAttestError::Message("Certificate did not contain the app ID extension".to_string()))?
+            .value;
+
+        let (_, raw_value) = parse_ber(extension_value).map_err(|_| AppAttestError::ExpectedASN1Node)?;
+        let BerObjectContent::Sequence(seq) = &raw_value.content else {
+            return Err(AppAttestError::ExpectedASN1Node);
+        };
+        let tagged = seq
+            .iter()
+            .find(|obj| obj.header.tag().0 == APP_ID_TAG)
+            .ok_or(AppAttestError::FailedToExtractValueFromASN1Node)?;
+        let BerObjectContent::Unknown(unknown_obj) = &tagged.content else {
+            return Err(AppAttestError::FailedToExtractValueFromASN1Node);
+        };
+        let (_, app_id) = parse_ber(unknown_obj.data).map_err(|_| AppAttestError::ExpectedOctetStringInsideASN1Node)?;
+        let BerObjectContent::OctetString(app_id) = app_id.content else {
+            return Err(AppAttestError::ExpectedOctetStringInsideASN1Node);
+        };
+        String::from_utf8(app_id.to_vec()).map_err(|_| AppAttestError::Message("app ID is not UTF-8".to_string()))
+    }
+
     // nonce_hash creates a new SHA256 hash of the composite item
-    fn nonce_hash(auth_data: &Vec<u8>, client_data_hash: Vec<u8>) -> Vec<u8> {
+    fn nonce_hash(auth_data: &[u8], client_data_hash: &[u8]) -> Vec<u8> {
         let mut hasher = Sha256::new();
         hasher.update(auth_data);
         
-        hasher.update(&client_data_hash);
+        hasher.update(client_data_hash);
         hasher.finalize().to_vec()
     }
 
-    fn verify_public_key_hash(cert: &X509Certificate, key_identifier: &Vec<u8>) -> Result<(Vec<u8>, bool), Box<dyn Error>> {
-        // 1. Extract the SubjectPublicKeyInfo
-        let spki = cert.public_key();
-
-        // 2. Get the raw EC point bytes (BitString MUST have 0 unused bits)
-        let pub_key_bytes = spki
-            .subject_public_key
-            .data
-            .to_vec();
-
-        // 3. SHA-256 over the raw key bytes
-        let hash = Sha256::digest(&pub_key_bytes).to_vec();
-
-        Ok((pub_key_bytes, &hash == key_identifier))
-    }
-
     /// Verify performs the complete attestation verification
     ///
     /// # Arguments
-    /// * `challenge` - A reference to the challenge string provided by the verifier.
-    /// * `app_id` - A reference to the application identifier.
-    /// * `key_id` - A reference to the key identifier expected to match the public key.
+    /// * `base64_challenge` - The base64 challenge the server issued for this attestation.
+    /// * `app_id` - The application identifier, `<team id>.<bundle id>`.
+    /// * `key_id` - The base64 key identifier expected to match the public key.
+    /// * `time` - Unix time the certificates must be valid at.
+    /// * `dev_env` - Whether keys from the development environment are accepted.
     ///
     /// # Returns
-    /// This method returns `Ok(())` if all verification steps are successful. If any step fails,
-    /// it returns `Err` with an appropriate error encapsulated in a `Box<dyn Error>`.
+    /// The attested public key and the receipt if all verification steps are successful. If
+    /// any step fails, it returns `Err` with an appropriate error encapsulated in a
+    /// `Box<dyn Error>`.
     ///
     /// # Example
-    /// ```no_run
+    /// ```
     /// use appattest_rs::attestation::Attestation;
-    /// 
-    /// let challenge = "example_challenge";
-    /// let app_id = "com.example.app";
-    /// let key_id = "base64encodedkeyid==";
-    /// let unix_time = 1700000000; // Example timestamp
     ///
-    /// let base64_cbor_data = "o2NmbXR....";
+    /// let challenge = include_str!("../examples/fixtures/challenge.b64").trim();
+    /// let app_id = include_str!("../examples/fixtures/app_id.txt").trim();
+    /// let key_id = include_str!("../examples/fixtures/key_id.b64").trim();
+    /// // When the fixture was recorded; its leaf certificate has since expired.
+    /// let unix_time = include_str!("../examples/fixtures/recorded_at.txt").trim().parse().unwrap();
+    ///
+    /// let base64_cbor_data = include_str!("../examples/fixtures/attestation.b64").trim();
     /// let attestation = Attestation::from_base64(base64_cbor_data).expect("unable to convert from base64");
     ///
-    /// attestation.verify(challenge, app_id, key_id, unix_time, Some(true)).expect("Verification failed");
+    /// // Recorded by a development build, so development keys must be allowed.
+    /// let (public_key, _receipt) =
+    ///     attestation.verify(challenge, app_id, key_id, unix_time, Some(true)).expect("Verification failed");
+    /// assert_eq!(public_key.len(), 65);
     /// ```
-    #[allow(unused_variables)]
     pub fn verify(self, base64_challenge: &str, app_id: &str, key_id: &str, time: i64, dev_env: Option<bool>) -> Result<(Vec<u8>, Vec<u8>),  Box<dyn Error>> {
 
         let challenge = general_purpose::STANDARD
             .decode(base64_challenge)
-            .map_err(|e| AppAttestError::Message(format!("Failed to decode Base64 challenge: {}", e)))?;
+            .map_err(|e| AppAttestError::Message(format!("Failed to decode Base64 challenge: {}", base64_problem(&e))))?;
+        let key_id = KeyId::from_base64(key_id)?;
+        let app_id_hash: [u8; 32] = Sha256::digest(app_id.as_bytes()).into();
 
-        // Step 1: Verify Certificates
-        // Read the apple root certificate from byte:
-        let apple_root_der = include_bytes!("../certificates/Apple_App_Attestation_Root_CA.der");
-        let (_, apple_root_cert) = parse_x509_certificate(apple_root_der)
-            .map_err(|_| AppAttestError::Message("invalid Apple root DER".into()))?;
-        Attestation::verify_certificates(&self.statement.certificates, &apple_root_cert, time)?;
+        let verified = self.verify_raw(&challenge, &app_id_hash, key_id.as_bytes(), time)?;
+        if verified.environment == Environment::Development && !dev_env.unwrap_or(false) {
+            return Err(AppAttestError::InvalidAAGUID.into());
+        }
+
+        Ok((verified.public_key, verified.receipt))
+    }
 
-        // Step 2: Parse Authenticator Data
-        let auth_data = AuthenticatorData::new(self.auth_data)?;
+    /// Verifies an attestation from raw inputs, without any text decoding.
+    ///
+    /// This is the form the zkVM guest uses: `challenge` is the raw challenge, `app_id_hash`
+    /// is `SHA256(app_id)` and `key_id` is the raw key identifier. Keys from both App Attest
+    /// environments are accepted; the caller decides whether
+    /// [`VerifiedAttestation::environment`] is acceptable.
+    ///
+    /// Equivalent to [`Attestation::verify_chain`] followed by [`Attestation::verify_auth_data`].
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "attestation.verify", skip_all, err(level = "info")))]
+    pub fn verify_raw(self, challenge: &[u8], app_id_hash: &[u8; 32], key_id: &[u8], time: i64) -> Result<VerifiedAttestation, AppAttestError> {
+        let chain = self.verify_chain(challenge, time)?;
+        Ok(self.verify_auth_data(app_id_hash, key_id, &chain.public_key)?.with_chain(&chain))
+    }
 
-        // Step 3: Create and Verify Nonce
-        let client_data_hash = Sha256::digest(&challenge).to_vec();
-        let nonce = Attestation::nonce_hash(&auth_data.bytes, client_data_hash);
-        let (_, cred_cert) = parse_x509_certificate(&self.statement.certificates[0])
-            .map_err(|_| AppAttestError::Message("invalid Cred certificate DER".into()))?;
-        let key_id_decoded_bytes = general_purpose::STANDARD
-            .decode(key_id).map_err(|e| AppAttestError::Message(e.to_string()))?;
-        
-        // Step 4: Verify Public Key Hash
-        let public_key_bytes = Attestation::verify_public_key_hash(&cred_cert, &key_id_decoded_bytes)?;
-        if !public_key_bytes.1 {
-            return Err(AppAttestError::InvalidPublicKey.into());
+    /// The certificate half of [`Attestation::verify_raw`]: checks the chain back to an
+    /// embedded root at `time` and that the leaf certifies the nonce of this authenticator
+    /// data and `challenge`. This is the expensive half, and needs doing only once per key.
+    pub fn verify_chain(&self, challenge: &[u8], time: i64) -> Result<VerifiedChain, AppAttestError> {
+        let verifier = AttestationVerifier::embedded();
+        self.verify_chain_to(&verifier.roots, &verifier.options, challenge, time, Tracer::default())
+    }
+
+    fn verify_chain_to(
+        &self,
+        roots: &[TrustedRoot],
+        options: &VerifyOptions,
+        challenge: &[u8],
+        time: i64,
+        trace: Tracer<'_>,
+    ) -> Result<VerifiedChain, AppAttestError> {
+        self.borrowed().verify_chain_to(self.validated.0.get(), roots, options, challenge, time, trace)
+    }
+
+    /// Checks everything about the attestation that needs no signature, root, time or
+    /// server-side value: `fmt` is [`FORMAT`]; `x5c` holds at most
+    /// [`VerifyOptions::DEFAULT_MAX_CHAIN_LENGTH`] certificates besides an embedded root,
+    /// none repeated and all parseable; the leaf certifies a P-256 key and a nonce; and the
+    /// authenticator data parses, with a known AAGUID and a 32-byte credential ID.
+    ///
+    /// For rejecting malformed submissions cheaply, e.g. before queuing them for
+    /// verification. The report is kept, and verifying this attestation afterwards takes
+    /// the leaf's key, nonce and expiry from it rather than from the certificate. Verifying
+    /// does not need it: it checks all of the above but `fmt` itself.
+    pub fn validate_structure(&self) -> Result<StructureReport, AppAttestError> {
+        if let Some(report) = self.validated.0.get() {
+            return Ok(report.clone());
         }
-        let extracted_nonce= Attestation::extract_nonce_from_cert(&self.statement.certificates[0])?;
-        if extracted_nonce.as_slice() != nonce.as_slice() {
-            return Err(AppAttestError::InvalidNonce.into());
+        let report = self.borrowed().validate_structure()?;
+        Ok(self.validated.0.get_or_init(|| report).clone())
+    }
+
+    /// The authenticator data half of [`Attestation::verify_raw`], for `public_key` taken
+    /// from a [`VerifiedChain`] of this attestation: checks the key ID, the COSE credential
+    /// key, RP ID, counter, AAGUID and credential ID.
+    pub fn verify_auth_data(
+        self,
+        app_id_hash: &[u8; 32],
+        key_id: &[u8],
+        public_key: &[u8],
+    ) -> Result<VerifiedAttestation, AppAttestError> {
+        verify_auth_data(self.auth_data, self.statement.receipt, app_id_hash, key_id, public_key, Tracer::default())
+    }
+}
+
+/// The authenticator data half of the verification, on the bytes of either attestation type.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    name = "attestation.verify_auth_data", level = "debug", skip_all,
+    fields(environment = tracing::field::Empty), err(level = "debug"),
+))]
+fn verify_auth_data(
+    auth_data: Vec<u8>,
+    receipt: Vec<u8>,
+    app_id_hash: &[u8; 32],
+    key_id: &[u8],
+    public_key: &[u8],
+    mut trace: Tracer<'_>,
+) -> Result<VerifiedAttestation, AppAttestError> {
+    // Step 2: Parse Authenticator Data
+    trace.step(5);
+    let auth_data = AuthenticatorData::new(auth_data)?;
+
+    // Step 4: Verify Public Key Hash
+    let public_key_hash = key_id_for_public_key(public_key)?;
+    trace.record(|trace| {
+        trace.public_key_hash = Some(hex::encode(public_key_hash.as_slice()));
+        trace.key_id = Some(hex::encode(key_id));
+    });
+    if public_key_hash.as_slice() != key_id {
+        return Err(AppAttestError::InvalidPublicKey);
+    }
+    // The credential key in the authenticator data must be there, and the certified one.
+    // Assertions set the attested data flag without any, so `AuthenticatorData` does not
+    // insist on it.
+    match &auth_data.credential_public_key {
+        Some(credential_public_key) if credential_public_key.public_key().to_uncompressed() == public_key => {}
+        _ => return Err(AppAttestError::InvalidPublicKey),
+    }
+    let public_key = public_key.to_vec();
+
+    // Step 5: Verify App ID Hash
+    trace.step(6);
+    trace.record(|trace| {
+        trace.rp_id_hash = Some(hex::encode(auth_data.rp_id_hash));
+        trace.expected_rp_id_hash = Some(hex::encode(app_id_hash));
+    });
+    auth_data.verify_app_id_hash(app_id_hash)?;
+
+    // Step 6: Verify Counter
+    trace.step(7);
+    trace.record(|trace| trace.counter = Some(auth_data.counter));
+    auth_data.verify_counter()?;
+
+    // Step 7: Verify AAGUID
+    trace.step(8);
+    trace.record(|trace| trace.aaguid = auth_data.aaguid.as_ref().map(|aaguid| hex::encode(aaguid.bytes())));
+    let environment = environment(&auth_data)?;
+    record!(environment = tracing::field::debug(environment));
+
+    // Step 8: Verify Credential ID
+    trace.step(9);
+    auth_data.verify_key_id(key_id)?;
+
+    Ok(VerifiedAttestation {
+        public_key,
+        receipt,
+        rp_id_hash: *app_id_hash,
+        environment,
+        leaf_not_after: None,
+        app_id: None,
+        timings: None,
+    })
+}
+
+/// The App Attest environment `auth_data`'s AAGUID names.
+fn environment(auth_data: &AuthenticatorData) -> Result<Environment, AppAttestError> {
+    if auth_data.is_valid_aaguid(false) {
+        Ok(Environment::Production)
+    } else if auth_data.is_valid_aaguid(true) {
+        Ok(Environment::Development)
+    } else {
+        Err(AppAttestError::InvalidAAGUID)
+    }
+}
+
+/// Context tag of the app ID in the leaf's `1.2.840.113635.100.8.5` extension, see
+/// [`Attestation::extract_app_id`].
+pub const APP_ID_TAG: u32 = 1204;
+
+/// [`Attestation::extract_app_id`] of the leaf `der`, if the chain has one.
+fn leaf_app_id(der: Option<&[u8]>) -> Result<String, AppAttestError> {
+    let der = der.ok_or_else(|| AppAttestError::Message("certificate list is empty".into()))?;
+    let (_, cert) =
+        parse_x509_certificate(der).map_err(|_| AppAttestError::Message("failed to parse certificate".into()))?;
+    Attestation::extract_app_id_from_cert(&cert)
+}
+
+/// Steps 1 to 3 of [`Attestation::verify_certificates`]: `cert_chain` is not empty, holds
+/// at most `max_length` certificates and none twice, and parses.
+fn parse_chain<C: AsRef<[u8]>>(cert_chain: &[C], max_length: usize) -> Result<Vec<X509Certificate<'_>>, AppAttestError> {
+    // 1. Basic sanity
+    if cert_chain.is_empty() {
+        return Err(AppAttestError::Message("certificate list is empty".into()));
+    }
+
+    // 2. Bound the work an attacker-supplied chain costs
+    if cert_chain.len() > max_length {
+        return Err(AppAttestError::CertificateChainTooLong { max_length, length: cert_chain.len() });
+    }
+    let repeats = |index: usize| cert_chain[..index].iter().any(|der| der.as_ref() == cert_chain[index].as_ref());
+    if let Some(index) = (1..cert_chain.len()).find(|&index| repeats(index)) {
+        return Err(AppAttestError::DuplicateCertificate { index });
+    }
+
+    // 3. Parse the supplied chain
+    let mut parsed: Vec<X509Certificate<'_>> = Vec::with_capacity(cert_chain.len());
+    for der in cert_chain {
+        let (_, cert) = parse_x509_certificate(der.as_ref())
+            .map_err(|_| AppAttestError::Message("failed to parse certificate".into()))?;
+        parsed.push(cert);
+    }
+    Ok(parsed)
+}
+
+/// `(file stem, DER)` of every root in `certificates/`, compiled in by build.rs. Apple's,
+/// [`root::APPLE_ROOT_NAME`], is checked against [`root::APPLE_ROOT_SHA256`] first.
+const EMBEDDED_ROOTS: &[(&str, &[u8])] = include!(concat!(env!("OUT_DIR"), "/roots/roots.rs"));
+
+/// A root certificate an [`AttestationVerifier`] accepts chains up to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrustedRoot {
+    /// The file stem it was loaded from, reported in [`VerifiedChain::root`].
+    pub name: Cow<'static, str>,
+    pub der: Cow<'static, [u8]>,
+}
+
+/// `chain` without its last certificate if that is byte for byte one of `roots`, as some
+/// clients append the root to `x5c` and others do not. A chain of the root alone is kept,
+/// and rejected by [`Attestation::verify_certificates`].
+pub(crate) fn strip_trusted_root<'a, C: AsRef<[u8]>>(chain: &'a [C], roots: &[TrustedRoot]) -> &'a [C] {
+    match chain.split_last() {
+        Some((last, rest)) if !rest.is_empty() && roots.iter().any(|root| *root.der == *last.as_ref()) => rest,
+        _ => chain,
+    }
+}
+
+/// Limits on the certificate chains [`Attestation::verify_certificates`] accepts.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerifyOptions {
+    /// Most certificates a chain may hold, leaf included. The root is supplied separately and
+    /// does not count.
+    pub max_chain_length: usize,
+    /// If set, the certificate that signs the leaf, "Apple App Attestation CA 1" in Apple's
+    /// chain, must have one of these [`root::spki_sha256`] digests, so that no other
+    /// intermediate under a trusted root can issue acceptable leaves. An empty list accepts
+    /// no chain.
+    pub pinned_intermediate_spki_sha256: Option<Vec<[u8; 32]>>,
+    /// If set, the verifier checks the RP ID hash against the app ID in the leaf
+    /// certificate, see [`Attestation::extract_app_id`], instead of the `app_id_hash` it is
+    /// given, which it ignores, and returns that app ID in [`VerifiedAttestation::app_id`].
+    /// For services verifying for many apps: any app's attestation then passes, and the
+    /// caller must look the returned ID up itself.
+    pub infer_app_id: bool,
+}
+
+impl VerifyOptions {
+    /// Apple's chain is the leaf and one intermediate; one more leaves room for Apple
+    /// adding a level.
+    pub const DEFAULT_MAX_CHAIN_LENGTH: usize = 3;
+}
+
+impl Default for VerifyOptions {
+    fn default() -> Self {
+        VerifyOptions {
+            max_chain_length: Self::DEFAULT_MAX_CHAIN_LENGTH,
+            pinned_intermediate_spki_sha256: None,
+            infer_app_id: false,
         }
+    }
+}
+
+/// Verifies attestation chains against a store of trusted roots: those embedded at build
+/// time, Apple's among them, or ones the operator supplies at run time, e.g. a staging CA
+/// or a root Apple rotated to.
+///
+/// A supplied root is parsed when the verifier is built, so a bad file fails at startup
+/// rather than on the first attestation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AttestationVerifier {
+    roots: Vec<TrustedRoot>,
+    metrics: Sink,
+    audit: Audit,
+    receipt_policy: Option<ReceiptPolicy>,
+    options: VerifyOptions,
+    timings: bool,
+    key_policy: Policy,
+}
 
-        // Step 5: Verify App ID Hash
-        auth_data.verify_app_id(&app_id)?;
+impl Default for AttestationVerifier {
+    fn default() -> Self {
+        Self::embedded()
+    }
+}
 
-        // Step 6: Verify Counter
-        auth_data.verify_counter()?;
+impl AttestationVerifier {
+    /// Trusts the embedded roots, as [`Attestation::verify_chain`] does.
+    pub fn embedded() -> Self {
+        let roots = EMBEDDED_ROOTS
+            .iter()
+            .map(|(name, der)| TrustedRoot { name: Cow::Borrowed(*name), der: Cow::Borrowed(*der) })
+            .collect();
+        AttestationVerifier {
+            roots,
+            metrics: Sink::default(),
+            audit: Audit::default(),
+            receipt_policy: None,
+            options: VerifyOptions::default(),
+            timings: false,
+            key_policy: Policy::default(),
+        }
+    }
 
-        // Step 7: Verify AAGUID
-        if !auth_data.is_valid_aaguid(dev_env.unwrap_or(false)) {
-            return Err(AppAttestError::InvalidAAGUID.into())
+    /// Trusts the PEM or DER file named by `APPATTEST_ROOT_CA` ([`root::ROOT_ENV`]), or the
+    /// embedded roots when the variable is unset or empty.
+    pub fn from_env() -> Result<Self, RootCertError> {
+        match std::env::var_os(root::ROOT_ENV) {
+            Some(path) if !path.is_empty() => Self::with_root_file(path),
+            _ => Ok(Self::embedded()),
         }
+    }
+
+    /// Trusts only the PEM or DER certificate at `path`, named by its file stem.
+    pub fn with_root_file(path: impl AsRef<Path>) -> Result<Self, RootCertError> {
+        let path = path.as_ref();
+        let name = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+        Self::with_root_der(name, root::load(path)?)
+    }
+
+    /// Trusts only the DER certificate `der`, reported as `name`.
+    pub fn with_root_der(name: impl Into<String>, der: Vec<u8>) -> Result<Self, RootCertError> {
+        Self::with_roots(vec![TrustedRoot { name: Cow::Owned(name.into()), der: Cow::Owned(der) }])
+    }
+
+    /// Trusts only `roots`, e.g. from [`root::collect`].
+    pub fn with_roots(roots: Vec<TrustedRoot>) -> Result<Self, RootCertError> {
+        for root in &roots {
+            root::check_certificate(&root.der)
+                .map_err(|error| RootCertError::File { file: root.name.to_string(), error: Box::new(error) })?;
+        }
+        Ok(AttestationVerifier {
+            roots,
+            metrics: Sink::default(),
+            audit: Audit::default(),
+            receipt_policy: None,
+            options: VerifyOptions::default(),
+            timings: false,
+            key_policy: Policy::default(),
+        })
+    }
+
+    /// Reports each verification to `sink`, see [`crate::metrics`]; `kind` is `attestation`,
+    /// or `attestation_chain` for [`AttestationVerifier::verify_chain`].
+    pub fn with_metrics(mut self, sink: Arc<dyn MetricsSink>) -> Self {
+        self.metrics = Sink(sink);
+        self
+    }
+
+    /// Records each decision to `sink`, see [`crate::audit`]: the digests of the
+    /// authenticator data and the challenge, and the key ID if it is well formed.
+    pub fn with_audit(mut self, sink: Arc<dyn AuditSink>) -> Self {
+        self.audit = Audit(Some(sink));
+        self
+    }
+
+    /// Also requires the receipt of each attestation [`AttestationVerifier::verify_raw`]
+    /// accepts to pass `policy`, at the verification time.
+    pub fn with_receipt_policy(mut self, policy: ReceiptPolicy) -> Self {
+        self.receipt_policy = Some(policy);
+        self
+    }
 
-        // Step 8: Verify Credential ID
-        auth_data.verify_key_id(&key_id_decoded_bytes)?;
+    /// Limits the chains this verifier accepts to `options` instead of the defaults.
+    pub fn with_options(mut self, options: VerifyOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Consults `policy` on the key of each [`AttestationVerifier::verify_raw`], see
+    /// [`crate::keypolicy`]: with its ID before the chain is verified, and with the public
+    /// key the chain certifies before the authenticator data is.
+    pub fn with_key_policy(mut self, policy: Arc<dyn KeyPolicy>) -> Self {
+        self.key_policy = Policy(Some(policy));
+        self
+    }
+
+    /// Times the steps of each [`AttestationVerifier::verify_raw`], see [`crate::timings`].
+    /// Without it, verifying reads no clock for them.
+    pub fn with_timings(mut self) -> Self {
+        self.timings = true;
+        self
+    }
+
+    /// The roots this verifier trusts.
+    pub fn roots(&self) -> &[TrustedRoot] {
+        &self.roots
+    }
+
+    /// [`Attestation::verify_chain`] against this verifier's roots.
+    pub fn verify_chain(
+        &self,
+        attestation: &Attestation,
+        challenge: &[u8],
+        time: i64,
+    ) -> Result<VerifiedChain, AppAttestError> {
+        let event = self.audit.start(|| audit_event(&attestation.auth_data, AuditKind::AttestationChain, challenge, time));
+        let result = self.metrics.verification("attestation_chain", || {
+            attestation.verify_chain_to(&self.roots, &self.options, challenge, time, Tracer::default())
+        });
+        self.audit.finish(event, &result);
+        result
+    }
+
+    /// [`Attestation::verify_raw`] against this verifier's roots, and its receipt policy if it
+    /// has one. With [`AttestationVerifier::with_timings`], the result holds the timings.
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "attestation.verify", skip_all, err(level = "info")))]
+    pub fn verify_raw(
+        &self,
+        attestation: Attestation,
+        challenge: &[u8],
+        app_id_hash: &[u8; 32],
+        key_id: &[u8],
+        time: i64,
+    ) -> Result<VerifiedAttestation, AppAttestError> {
+        let (result, timings) = self.verify_timed(attestation, challenge, app_id_hash, key_id, time);
+        result.map(|verified| VerifiedAttestation { timings, ..verified })
+    }
+
+    /// [`AttestationVerifier::verify_raw`], returning the timings beside the result rather
+    /// than in it, so that a failed verification has them too: those of the steps up to the
+    /// one that failed. `None` without [`AttestationVerifier::with_timings`].
+    pub fn verify_timed(
+        &self,
+        attestation: Attestation,
+        challenge: &[u8],
+        app_id_hash: &[u8; 32],
+        key_id: &[u8],
+        time: i64,
+    ) -> (Result<VerifiedAttestation, AppAttestError>, Option<VerificationTimings>) {
+        let event = self
+            .audit
+            .start(|| audit_event(&attestation.auth_data, AuditKind::Attestation, challenge, time).key_id(KeyId::from_bytes(key_id).ok()));
+        let mut timer = Timer::new(self.timings);
+        let result = self.metrics.verification("attestation", || {
+            let tracer = Tracer { trace: None, timer: Some(&mut timer) };
+            self.verify_traced(attestation, challenge, app_id_hash, key_id, time, tracer)
+        });
+        self.audit.finish(event, &result);
+        (result, timer.finish())
+    }
+
+    /// [`AttestationVerifier::verify_raw`] on an [`AttestationRef`], for servers parsing
+    /// without copying; the result, metrics and audit records are the same.
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "attestation.verify", skip_all, err(level = "info")))]
+    pub fn verify_ref(
+        &self,
+        attestation: &AttestationRef<'_>,
+        challenge: &[u8],
+        app_id_hash: &[u8; 32],
+        key_id: &[u8],
+        time: i64,
+    ) -> Result<VerifiedAttestation, AppAttestError> {
+        let event = self
+            .audit
+            .start(|| audit_event(attestation.auth_data, AuditKind::Attestation, challenge, time).key_id(KeyId::from_bytes(key_id).ok()));
+        let mut timer = Timer::new(self.timings);
+        let result = self.metrics.verification("attestation", || {
+            let mut tracer = Tracer { trace: None, timer: Some(&mut timer) };
+            self.key_policy.check(KeyId::from_bytes(key_id), None)?;
+            let chain = attestation.verify_chain_to(None, &self.roots, &self.options, challenge, time, tracer.reborrow())?;
+            self.key_policy.check(KeyId::from_bytes(key_id), Some(&chain.public_key))?;
+            tracer.time(VerificationStep::AuthData);
+            let (app_id_hash, app_id) = self.app_id(|| attestation.extract_app_id(), app_id_hash)?;
+            let (auth_data, receipt) = (attestation.auth_data.to_vec(), attestation.receipt.to_vec());
+            let verified = verify_auth_data(auth_data, receipt, &app_id_hash, key_id, &chain.public_key, tracer.reborrow())?;
+            let verified = VerifiedAttestation { app_id, ..verified.with_chain(&chain) };
+            self.check_receipt(&verified, time, tracer)?;
+            Ok(verified)
+        });
+        self.audit.finish(event, &result);
+        let timings = timer.finish();
+        result.map(|verified| VerifiedAttestation { timings, ..verified })
+    }
+
+    /// [`AttestationVerifier::verify_raw`] without metrics or audit, returning the
+    /// [`VerificationTrace`] of the intermediate values too, for debugging a rejected
+    /// attestation. Keep the trace in the server's logs; see its warning.
+    pub fn verify_debug(
+        &self,
+        attestation: Attestation,
+        challenge: &[u8],
+        app_id_hash: &[u8; 32],
+        key_id: &[u8],
+        time: i64,
+    ) -> (Result<VerifiedAttestation, AppAttestError>, VerificationTrace) {
+        let mut trace = VerificationTrace::default();
+        let tracer = Tracer { trace: Some(&mut trace), timer: None };
+        let result = self.verify_traced(attestation, challenge, app_id_hash, key_id, time, tracer);
+        (result, trace)
+    }
+
+    /// The verification of [`AttestationVerifier::verify_raw`], recording to `tracer`.
+    fn verify_traced(
+        &self,
+        attestation: Attestation,
+        challenge: &[u8],
+        app_id_hash: &[u8; 32],
+        key_id: &[u8],
+        time: i64,
+        mut tracer: Tracer<'_>,
+    ) -> Result<VerifiedAttestation, AppAttestError> {
+        self.key_policy.check(KeyId::from_bytes(key_id), None)?;
+        let chain = attestation.verify_chain_to(&self.roots, &self.options, challenge, time, tracer.reborrow())?;
+        self.key_policy.check(KeyId::from_bytes(key_id), Some(&chain.public_key))?;
+        tracer.time(VerificationStep::AuthData);
+        let (app_id_hash, app_id) = self.app_id(|| attestation.extract_app_id(), app_id_hash)?;
+        let Attestation { statement, auth_data, .. } = attestation;
+        let verified = verify_auth_data(auth_data, statement.receipt, &app_id_hash, key_id, &chain.public_key, tracer.reborrow())?;
+        let verified = VerifiedAttestation { app_id, ..verified.with_chain(&chain) };
+        self.check_receipt(&verified, time, tracer)?;
+        Ok(verified)
+    }
+
+    /// Checks the receipt of `verified` against this verifier's receipt policy, if it has
+    /// one. Every one of Apple's steps passed by then, so the trace shows none failed.
+    fn check_receipt(&self, verified: &VerifiedAttestation, time: i64, mut tracer: Tracer<'_>) -> Result<(), AppAttestError> {
+        tracer.record(|trace| trace.failed_step = None);
+        if let Some(policy) = &self.receipt_policy {
+            tracer.time(VerificationStep::Receipt);
+            policy.check(&Receipt::from_ber(&verified.receipt)?, time)?;
+        }
+        Ok(())
+    }
 
-        Ok((public_key_bytes.0.clone(), self.statement.receipt))
+    /// The app ID hash to check the RP ID hash against: `app_id_hash`, or with
+    /// [`VerifyOptions::infer_app_id`] that of the app ID `extract` returns, with the ID.
+    fn app_id(
+        &self,
+        extract: impl FnOnce() -> Result<String, AppAttestError>,
+        app_id_hash: &[u8; 32],
+    ) -> Result<([u8; 32], Option<String>), AppAttestError> {
+        if !self.options.infer_app_id {
+            return Ok((*app_id_hash, None));
+        }
+        let app_id = extract()?;
+        Ok((Sha256::digest(app_id.as_bytes()).into(), Some(app_id)))
     }
 }
 
+fn audit_event(auth_data: &[u8], kind: AuditKind, challenge: &[u8], time: i64) -> AuditEvent {
+    AuditEvent::new(kind).verification_time(time).digest("authData", auth_data).digest("challenge", challenge)
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::root::pem_to_der;
+
+    const ATTESTATION_B64: &str = "o2NmbXRvYXBwbGUtYXBwYXR0ZXN0Z2F0dFN0bXSiY3g1Y4JZAzEwggMtMIICs6ADAgECAgYBkGqxbE8wCgYIKoZIzj0EAwIwTzEjMCEGA1UEAwwaQXBwbGUgQXBwIEF0dGVzdGF0aW9uIENBIDExEzARBgNVBAoMCkFwcGxlIEluYy4xEzARBgNVBAgMCkNhbGlmb3JuaWEwHhcNMjQwNjI5MTk0ODUwWhcNMjUwMTI0MDcyNzUwWjCBkTFJMEcGA1UEAwxAMWI3NzlmZjY5MWVkZjRkZTAzYzU0OGU4ZmUxOTYyZjZkNTc5ODA2MGNhNjgzZGQ0N2JiMmJjNzJhNzhkZmViZjEaMBgGA1UECwwRQUFBIENlcnRpZmljYXRpb24xEzARBgNVBAoMCkFwcGxlIEluYy4xEzARBgNVBAgMCkNhbGlmb3JuaWEwWTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAATVrgv9TJ/pAmgUQYA0gtXDRV9vw3TRJv8C1qtpFZ4POMIBHcByLUsDZSFPJQQxM3nRmKD1ELEfd0RXzKZrhhXno4IBNjCCATIwDAYDVR0TAQH/BAIwADAOBgNVHQ8BAf8EBAMCBPAwgYMGCSqGSIb3Y2QIBQR2MHSkAwIBCr+JMAMCAQG/iTEDAgEAv4kyAwIBAb+JMwMCAQG/iTQkBCI3NjJVNUc3MjM2Lm5ldHdvcmsuZ2FuZGFsZi5jb25uZWN0pQYEBHNrcyC/iTYDAgEFv4k3AwIBAL+JOQMCAQC/iToDAgEAv4k7AwIBADBXBgkqhkiG92NkCAcESjBIv4p4CAQGMTcuNS4xv4hQBwIFAP////+/insHBAUyMUY5ML+KfQgEBjE3LjUuMb+KfgMCAQC/iwwPBA0yMS42LjkwLjAuMCwwMDMGCSqGSIb3Y2QIAgQmMCShIgQgFsrz55cr5FuBWoLw3/BtAxUNXVwuG1+YrqHb3a4nl38wCgYIKoZIzj0EAwIDaAAwZQIwMXgjaRv1XCpl2b47xoScDqeR8uwsKpG5gPsQVr7Am3rXNxPyWbN/QHSuv4xWARI8AjEAvXdy8jQvyX1RVZCg2acUw31ptSOee3CDEWMcSmv24iRETKo96TdMPYNN864cpUHpWQJHMIICQzCCAcigAwIBAgIQCbrF4bxAGtnUU5W8OBoIVDAKBggqhkjOPQQDAzBSMSYwJAYDVQQDDB1BcHBsZSBBcHAgQXR0ZXN0YXRpb24gUm9vdCBDQTETMBEGA1UECgwKQXBwbGUgSW5jLjETMBEGA1UECAwKQ2FsaWZvcm5pYTAeFw0yMDAzMTgxODM5NTVaFw0zMDAzMTMwMDAwMDBaME8xIzAhBgNVBAMMGkFwcGxlIEFwcCBBdHRlc3RhdGlvbiBDQSAxMRMwEQYDVQQKDApBcHBsZSBJbmMuMRMwEQYDVQQIDApDYWxpZm9ybmlhMHYwEAYHKoZIzj0CAQYFK4EEACIDYgAErls3oHdNebI1j0Dn0fImJvHCX+8XgC3qs4JqWYdP+NKtFSV4mqJmBBkSSLY8uWcGnpjTY71eNw+/oI4ynoBzqYXndG6jWaL2bynbMq9FXiEWWNVnr54mfrJhTcIaZs6Zo2YwZDASBgNVHRMBAf8ECDAGAQH/AgEAMB8GA1UdIwQYMBaAFKyREFMzvb5oQf+nDKnl+url5YqhMB0GA1UdDgQWBBQ+410cBBmpybQx+IR01uHhV3LjmzAOBgNVHQ8BAf8EBAMCAQYwCgYIKoZIzj0EAwMDaQAwZgIxALu+iI1zjQUCz7z9Zm0JV1A1vNaHLD+EMEkmKe3R+RToeZkcmui1rvjTqFQz97YNBgIxAKs47dDMge0ApFLDukT5k2NlU/7MKX8utN+fXr5aSsq2mVxLgg35BDhveAe7WJQ5t2dyZWNlaXB0WQ6lMIAGCSqGSIb3DQEHAqCAMIACAQExDzANBglghkgBZQMEAgEFADCABgkqhkiG9w0BBwGggCSABIID6DGCBF8wKgIBAgIBAQQiNzYyVTVHNzIzNi5uZXR3b3JrLmdhbmRhbGYuY29ubmVjdDCCAzsCAQMCAQEEggMxMIIDLTCCArOgAwIBAgIGAZBqsWxPMAoGCCqGSM49BAMCME8xIzAhBgNVBAMMGkFwcGxlIEFwcCBBdHRlc3RhdGlvbiBDQSAxMRMwEQYDVQQKDApBcHBsZSBJbmMuMRMwEQYDVQQIDApDYWxpZm9ybmlhMB4XDTI0MDYyOTE5NDg1MFoXDTI1MDEyNDA3Mjc1MFowgZExSTBHBgNVBAMMQDFiNzc5ZmY2OTFlZGY0ZGUwM2M1NDhlOGZlMTk2MmY2ZDU3OTgwNjBjYTY4M2RkNDdiYjJiYzcyYTc4ZGZlYmYxGjAYBgNVBAsMEUFBQSBDZXJ0aWZpY2F0aW9uMRMwEQYDVQQKDApBcHBsZSBJbmMuMRMwEQYDVQQIDApDYWxpZm9ybmlhMFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAE1a4L/Uyf6QJoFEGANILVw0Vfb8N00Sb/AtaraRWeDzjCAR3Aci1LA2UhTyUEMTN50Zig9RCxH3dEV8yma4YV56OCATYwggEyMAwGA1UdEwEB/wQCMAAwDgYDVR0PAQH/BAQDAgTwMIGDBgkqhkiG92NkCAUEdjB0pAMCAQq/iTADAgEBv4kxAwIBAL+JMgMCAQG/iTMDAgEBv4k0JAQiNzYyVTVHNzIzNi5uZXR3b3JrLmdhbmRhbGYuY29ubmVjdKUGBARza3Mgv4k2AwIBBb+JNwMCAQC/iTkDAgEAv4k6AwIBAL+JOwMCAQAwVwYJKoZIhvdjZAgHBEowSL+KeAgEBjE3LjUuMb+IUAcCBQD/////v4p7BwQFMjFGOTC/in0IBAYxNy41LjG/in4DAgEAv4sMDwQNMjEuNi45MC4wLjAsMDAzBgkqhkiG92NkCAIEJjAkoSIEIBbK8+eXK+RbgVqC8N/wbQMVDV1cLhtfmK6h292uJ5d/MAoGCCqGSM49BAMCA2gAMGUCMDF4I2kb9VwqZdm+O8aEnA6nkfLsLCqRuYD7EFa+wJt61zcT8lmzf0B0rr+MVgESPAIxAL13cvI0L8l9UVWQoNmnFMN9abUjnntwgxFjHEpr9uIkREyqPek3TD2DTfOuHKVB6TAoAgEEAgEBBCBHxKY1WEfoCPE422InvhV7p1EScBHkMnbFOIPiq0iieDBgAgEFAgEBBFhXdDhMSmp4aFVFdnBzREhCOU5zQU9KUkpsTVBuc3BQMTBBcGdWNkwvcDBlRXJwZGRYL0t5bDYwdUpheTdtb2VYODZ0cTUEe2dLTjROOW9haGtCWjlhQ0VBPT0wDgIBBgIBAQQGQVRURVNUMBICAQcCAQEECnByb2R1Y3Rpb24wIAIBDAIBAQQYMjAyNC0wNi0zMFQxOTo0ODo1MC45MzRaMCACARUCAQEEGDIwMjQtMDktMjhUMTk6NDg6NTAuOTM0WgAAAAAAAKCAMIIDrjCCA1SgAwIBAgIQfgISYNjOd6typZ3waCe+/TAKBggqhkjOPQQDAjB8MTAwLgYDVQQDDCdBcHBsZSBBcHBsaWNhdGlvbiBJbnRlZ3JhdGlvbiBDQSA1IC0gRzExJjAkBgNVBAsMHUFwcGxlIENlcnRpZmljYXRpb24gQXV0aG9yaXR5MRMwEQYDVQQKDApBcHBsZSBJbmMuMQswCQYDVQQGEwJVUzAeFw0yNDAyMjcxODM5NTJaFw0yNTAzMjgxODM5NTFaMFoxNjA0BgNVBAMMLUFwcGxpY2F0aW9uIEF0dGVzdGF0aW9uIEZyYXVkIFJlY2VpcHQgU2lnbmluZzETMBEGA1UECgwKQXBwbGUgSW5jLjELMAkGA1UEBhMCVVMwWTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAARUN7iCxk/FE+l6UecSdFXhSxqQC5mL19QWh2k/C9iTyos16j1YI8lqda38TLd/kswpmZCT2cbcLRgAyQMg9HtEo4IB2DCCAdQwDAYDVR0TAQH/BAIwADAfBgNVHSMEGDAWgBTZF/5LZ5A4S5L0287VV4AUC489yTBDBggrBgEFBQcBAQQ3MDUwMwYIKwYBBQUHMAGGJ2h0dHA6Ly9vY3NwLmFwcGxlLmNvbS9vY3NwMDMtYWFpY2E1ZzEwMTCCARwGA1UdIASCARMwggEPMIIBCwYJKoZIhvdjZAUBMIH9MIHDBggrBgEFBQcCAjCBtgyBs1JlbGlhbmNlIG9uIHRoaXMgY2VydGlmaWNhdGUgYnkgYW55IHBhcnR5IGFzc3VtZXMgYWNjZXB0YW5jZSBvZiB0aGUgdGhlbiBhcHBsaWNhYmxlIHN0YW5kYXJkIHRlcm1zIGFuZCBjb25kaXRpb25zIG9mIHVzZSwgY2VydGlmaWNhdGUgcG9saWN5IGFuZCBjZXJ0aWZpY2F0aW9uIHByYWN0aWNlIHN0YXRlbWVudHMuMDUGCCsGAQUFBwIBFilodHRwOi8vd3d3LmFwcGxlLmNvbS9jZXJ0aWZpY2F0ZWF1dGhvcml0eTAdBgNVHQ4EFgQUK89JHvvPG3kO8K8CKRO1ARbheTQwDgYDVR0PAQH/BAQDAgeAMA8GCSqGSIb3Y2QMDwQCBQAwCgYIKoZIzj0EAwIDSAAwRQIhAIeoCSt0X5hAxTqUIUEaXYuqCYDUhpLV1tKZmdB4x8q1AiA/ZVOMEyzPiDA0sEd16JdTz8/T90SDVbqXVlx9igaBHDCCAvkwggJ/oAMCAQICEFb7g9Qr/43DN5kjtVqubr0wCgYIKoZIzj0EAwMwZzEbMBkGA1UEAwwSQXBwbGUgUm9vdCBDQSAtIEczMSYwJAYDVQQLDB1BcHBsZSBDZXJ0aWZpY2F0aW9uIEF1dGhvcml0eTETMBEGA1UECgwKQXBwbGUgSW5jLjELMAkGA1UEBhMCVVMwHhcNMTkwMzIyMTc1MzMzWhcNMzQwMzIyMDAwMDAwWjB8MTAwLgYDVQQDDCdBcHBsZSBBcHBsaWNhdGlvbiBJbnRlZ3JhdGlvbiBDQSA1IC0gRzExJjAkBgNVBAsMHUFwcGxlIENlcnRpZmljYXRpb24gQXV0aG9yaXR5MRMwEQYDVQQKDApBcHBsZSBJbmMuMQswCQYDVQQGEwJVUzBZMBMGByqGSM49AgEGCCqGSM49AwEHA0IABJLOY719hrGrKAo7HOGv+wSUgJGs9jHfpssoNW9ES+Eh5VfdEo2NuoJ8lb5J+r4zyq7NBBnxL0Ml+vS+s8uDfrqjgfcwgfQwDwYDVR0TAQH/BAUwAwEB/zAfBgNVHSMEGDAWgBS7sN6hWDOImqSKmd6+veuv2sskqzBGBggrBgEFBQcBAQQ6MDgwNgYIKwYBBQUHMAGGKmh0dHA6Ly9vY3NwLmFwcGxlLmNvbS9vY3NwMDMtYXBwbGVyb290Y2FnMzA3BgNVHR8EMDAuMCygKqAohiZodHRwOi8vY3JsLmFwcGxlLmNvbS9hcHBsZXJvb3RjYWczLmNybDAdBgNVHQ4EFgQU2Rf+S2eQOEuS9NvO1VeAFAuPPckwDgYDVR0PAQH/BAQDAgEGMBAGCiqGSIb3Y2QGAgMEAgUAMAoGCCqGSM49BAMDA2gAMGUCMQCNb6afoeDk7FtOc4qSfz14U5iP9NofWB7DdUr+OKhMKoMaGqoNpmRt4bmT6NFVTO0CMGc7LLTh6DcHd8vV7HaoGjpVOz81asjF5pKw4WG+gElp5F8rqWzhEQKqzGHZOLdzSjCCAkMwggHJoAMCAQICCC3F/IjSxUuVMAoGCCqGSM49BAMDMGcxGzAZBgNVBAMMEkFwcGxlIFJvb3QgQ0EgLSBHMzEmMCQGA1UECwwdQXBwbGUgQ2VydGlmaWNhdGlvbiBBdXRob3JpdHkxEzARBgNVBAoMCkFwcGxlIEluYy4xCzAJBgNVBAYTAlVTMB4XDTE0MDQzMDE4MTkwNloXDTM5MDQzMDE4MTkwNlowZzEbMBkGA1UEAwwSQXBwbGUgUm9vdCBDQSAtIEczMSYwJAYDVQQLDB1BcHBsZSBDZXJ0aWZpY2F0aW9uIEF1dGhvcml0eTETMBEGA1UECgwKQXBwbGUgSW5jLjELMAkGA1UEBhMCVVMwdjAQBgcqhkjOPQIBBgUrgQQAIgNiAASY6S89QHKk7ZMicoETHN0QlfHFo05x3BQW2Q7lpgUqd2R7X04407scRLV/9R+2MmJdyemEW08wTxFaAP1YWAyl9Q8sTQdHE3Xal5eXbzFc7SudeyA72LlU2V6ZpDpRCjGjQjBAMB0GA1UdDgQWBBS7sN6hWDOImqSKmd6+veuv2sskqzAPBgNVHRMBAf8EBTADAQH/MA4GA1UdDwEB/wQEAwIBBjAKBggqhkjOPQQDAwNoADBlAjEAg+nBxBZeGl00GNnt7/RsDgBGS7jfskYRxQ/95nqMoaZrzsID1Jz1k8Z0uGrfqiMVAjBtZooQytQN1E/NjUM+tIpjpTNu423aF7dkH8hTJvmIYnQ5Cxdby1GoDOgYA+eisigAADGB/TCB+gIBATCBkDB8MTAwLgYDVQQDDCdBcHBsZSBBcHBsaWNhdGlvbiBJbnRlZ3JhdGlvbiBDQSA1IC0gRzExJjAkBgNVBAsMHUFwcGxlIENlcnRpZmljYXRpb24gQXV0aG9yaXR5MRMwEQYDVQQKDApBcHBsZSBJbmMuMQswCQYDVQQGEwJVUwIQfgISYNjOd6typZ3waCe+/TANBglghkgBZQMEAgEFADAKBggqhkjOPQQDAgRHMEUCIDzodg4szIkkk6IxaqaR/NcsLQO3LtXn9DDBt/yoESUYAiEApRtfQvovTtktiicXHCiBke0Dzlyk14nuYQUnNNumVR0AAAAAAABoYXV0aERhdGFYpKRc2WwGuoniZEqtF+kolObjxcczFdDxbrhJR/nT8ehTQAAAAABhcHBhdHRlc3QAAAAAAAAAACAbd5/2ke303gPFSOj+GWL21XmAYMpoPdR7srxyp43+v6UBAgMmIAEhWCDVrgv9TJ/pAmgUQYA0gtXDRV9vw3TRJv8C1qtpFZ4POCJYIMIBHcByLUsDZSFPJQQxM3nRmKD1ELEfd0RXzKZrhhXn";
 
-    /// Strip PEM headers/footers and base64-decode the body.
-    fn pem_to_der(pem: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
-        let pem_str = std::str::from_utf8(pem)?;
-        let b64: String = pem_str
-            .lines()
-            .filter(|l| !l.starts_with("-----")) // skip BEGIN/END lines
-            .collect();
-        Ok(general_purpose::STANDARD.decode(b64.trim())?)
-    }
-   
     #[test]
     fn test_from_base64_valid() {
-        let valid_cbor_base64 = "o2NmbXRvYXBwbGUtYXBwYXR0ZXN0Z2F0dFN0bXSiY3g1Y4JZAzEwggMtMIICs6ADAgECAgYBkGqxbE8wCgYIKoZIzj0EAwIwTzEjMCEGA1UEAwwaQXBwbGUgQXBwIEF0dGVzdGF0aW9uIENBIDExEzARBgNVBAoMCkFwcGxlIEluYy4xEzARBgNVBAgMCkNhbGlmb3JuaWEwHhcNMjQwNjI5MTk0ODUwWhcNMjUwMTI0MDcyNzUwWjCBkTFJMEcGA1UEAwxAMWI3NzlmZjY5MWVkZjRkZTAzYzU0OGU4ZmUxOTYyZjZkNTc5ODA2MGNhNjgzZGQ0N2JiMmJjNzJhNzhkZmViZjEaMBgGA1UECwwRQUFBIENlcnRpZmljYXRpb24xEzARBgNVBAoMCkFwcGxlIEluYy4xEzARBgNVBAgMCkNhbGlmb3JuaWEwWTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAATVrgv9TJ/pAmgUQYA0gtXDRV9vw3TRJv8C1qtpFZ4POMIBHcByLUsDZSFPJQQxM3nRmKD1ELEfd0RXzKZrhhXno4IBNjCCATIwDAYDVR0TAQH/BAIwADAOBgNVHQ8BAf8EBAMCBPAwgYMGCSqGSIb3Y2QIBQR2MHSkAwIBCr+JMAMCAQG/iTEDAgEAv4kyAwIBAb+JMwMCAQG/iTQkBCI3NjJVNUc3MjM2Lm5ldHdvcmsuZ2FuZGFsZi5jb25uZWN0pQYEBHNrcyC/iTYDAgEFv4k3AwIBAL+JOQMCAQC/iToDAgEAv4k7AwIBADBXBgkqhkiG92NkCAcESjBIv4p4CAQGMTcuNS4xv4hQBwIFAP////+/insHBAUyMUY5ML+KfQgEBjE3LjUuMb+KfgMCAQC/iwwPBA0yMS42LjkwLjAuMCwwMDMGCSqGSIb3Y2QIAgQmMCShIgQgFsrz55cr5FuBWoLw3/BtAxUNXVwuG1+YrqHb3a4nl38wCgYIKoZIzj0EAwIDaAAwZQIwMXgjaRv1XCpl2b47xoScDqeR8uwsKpG5gPsQVr7Am3rXNxPyWbN/QHSuv4xWARI8AjEAvXdy8jQvyX1RVZCg2acUw31ptSOee3CDEWMcSmv24iRETKo96TdMPYNN864cpUHpWQJHMIICQzCCAcigAwIBAgIQCbrF4bxAGtnUU5W8OBoIVDAKBggqhkjOPQQDAzBSMSYwJAYDVQQDDB1BcHBsZSBBcHAgQXR0ZXN0YXRpb24gUm9vdCBDQTETMBEGA1UECgwKQXBwbGUgSW5jLjETMBEGA1UECAwKQ2FsaWZvcm5pYTAeFw0yMDAzMTgxODM5NTVaFw0zMDAzMTMwMDAwMDBaME8xIzAhBgNVBAMMGkFwcGxlIEFwcCBBdHRlc3RhdGlvbiBDQSAxMRMwEQYDVQQKDApBcHBsZSBJbmMuMRMwEQYDVQQIDApDYWxpZm9ybmlhMHYwEAYHKoZIzj0CAQYFK4EEACIDYgAErls3oHdNebI1j0Dn0fImJvHCX+8XgC3qs4JqWYdP+NKtFSV4mqJmBBkSSLY8uWcGnpjTY71eNw+/oI4ynoBzqYXndG6jWaL2bynbMq9FXiEWWNVnr54mfrJhTcIaZs6Zo2YwZDASBgNVHRMBAf8ECDAGAQH/AgEAMB8GA1UdIwQYMBaAFKyREFMzvb5oQf+nDKnl+url5YqhMB0GA1UdDgQWBBQ+410cBBmpybQx+IR01uHhV3LjmzAOBgNVHQ8BAf8EBAMCAQYwCgYIKoZIzj0EAwMDaQAwZgIxALu+iI1zjQUCz7z9Zm0JV1A1vNaHLD+EMEkmKe3R+RToeZkcmui1rvjTqFQz97YNBgIxAKs47dDMge0ApFLDukT5k2NlU/7MKX8utN+fXr5aSsq2mVxLgg35BDhveAe7WJQ5t2dyZWNlaXB0WQ6lMIAGCSqGSIb3DQEHAqCAMIACAQExDzANBglghkgBZQMEAgEFADCABgkqhkiG9w0BBwGggCSABIID6DGCBF8wKgIBAgIBAQQiNzYyVTVHNzIzNi5uZXR3b3JrLmdhbmRhbGYuY29ubmVjdDCCAzsCAQMCAQEEggMxMIIDLTCCArOgAwIBAgIGAZBqsWxPMAoGCCqGSM49BAMCME8xIzAhBgNVBAMMGkFwcGxlIEFwcCBBdHRlc3RhdGlvbiBDQSAxMRMwEQYDVQQKDApBcHBsZSBJbmMuMRMwEQYDVQQIDApDYWxpZm9ybmlhMB4XDTI0MDYyOTE5NDg1MFoXDTI1MDEyNDA3Mjc1MFowgZExSTBHBgNVBAMMQDFiNzc5ZmY2OTFlZGY0ZGUwM2M1NDhlOGZlMTk2MmY2ZDU3OTgwNjBjYTY4M2RkNDdiYjJiYzcyYTc4ZGZlYmYxGjAYBgNVBAsMEUFBQSBDZXJ0aWZpY2F0aW9uMRMwEQYDVQQKDApBcHBsZSBJbmMuMRMwEQYDVQQIDApDYWxpZm9ybmlhMFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAE1a4L/Uyf6QJoFEGANILVw0Vfb8N00Sb/AtaraRWeDzjCAR3Aci1LA2UhTyUEMTN50Zig9RCxH3dEV8yma4YV56OCATYwggEyMAwGA1UdEwEB/wQCMAAwDgYDVR0PAQH/BAQDAgTwMIGDBgkqhkiG92NkCAUEdjB0pAMCAQq/iTADAgEBv4kxAwIBAL+JMgMCAQG/iTMDAgEBv4k0JAQiNzYyVTVHNzIzNi5uZXR3b3JrLmdhbmRhbGYuY29ubmVjdKUGBARza3Mgv4k2AwIBBb+JNwMCAQC/iTkDAgEAv4k6AwIBAL+JOwMCAQAwVwYJKoZIhvdjZAgHBEowSL+KeAgEBjE3LjUuMb+IUAcCBQD/////v4p7BwQFMjFGOTC/in0IBAYxNy41LjG/in4DAgEAv4sMDwQNMjEuNi45MC4wLjAsMDAzBgkqhkiG92NkCAIEJjAkoSIEIBbK8+eXK+RbgVqC8N/wbQMVDV1cLhtfmK6h292uJ5d/MAoGCCqGSM49BAMCA2gAMGUCMDF4I2kb9VwqZdm+O8aEnA6nkfLsLCqRuYD7EFa+wJt61zcT8lmzf0B0rr+MVgESPAIxAL13cvI0L8l9UVWQoNmnFMN9abUjnntwgxFjHEpr9uIkREyqPek3TD2DTfOuHKVB6TAoAgEEAgEBBCBHxKY1WEfoCPE422InvhV7p1EScBHkMnbFOIPiq0iieDBgAgEFAgEBBFhXdDhMSmp4aFVFdnBzREhCOU5zQU9KUkpsTVBuc3BQMTBBcGdWNkwvcDBlRXJwZGRYL0t5bDYwdUpheTdtb2VYODZ0cTUEe2dLTjROOW9haGtCWjlhQ0VBPT0wDgIBBgIBAQQGQVRURVNUMBICAQcCAQEECnByb2R1Y3Rpb24wIAIBDAIBAQQYMjAyNC0wNi0zMFQxOTo0ODo1MC45MzRaMCACARUCAQEEGDIwMjQtMDktMjhUMTk6NDg6NTAuOTM0WgAAAAAAAKCAMIIDrjCCA1SgAwIBAgIQfgISYNjOd6typZ3waCe+/TAKBggqhkjOPQQDAjB8MTAwLgYDVQQDDCdBcHBsZSBBcHBsaWNhdGlvbiBJbnRlZ3JhdGlvbiBDQSA1IC0gRzExJjAkBgNVBAsMHUFwcGxlIENlcnRpZmljYXRpb24gQXV0aG9yaXR5MRMwEQYDVQQKDApBcHBsZSBJbmMuMQswCQYDVQQGEwJVUzAeFw0yNDAyMjcxODM5NTJaFw0yNTAzMjgxODM5NTFaMFoxNjA0BgNVBAMMLUFwcGxpY2F0aW9uIEF0dGVzdGF0aW9uIEZyYXVkIFJlY2VpcHQgU2lnbmluZzETMBEGA1UECgwKQXBwbGUgSW5jLjELMAkGA1UEBhMCVVMwWTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAARUN7iCxk/FE+l6UecSdFXhSxqQC5mL19QWh2k/C9iTyos16j1YI8lqda38TLd/kswpmZCT2cbcLRgAyQMg9HtEo4IB2DCCAdQwDAYDVR0TAQH/BAIwADAfBgNVHSMEGDAWgBTZF/5LZ5A4S5L0287VV4AUC489yTBDBggrBgEFBQcBAQQ3MDUwMwYIKwYBBQUHMAGGJ2h0dHA6Ly9vY3NwLmFwcGxlLmNvbS9vY3NwMDMtYWFpY2E1ZzEwMTCCARwGA1UdIASCARMwggEPMIIBCwYJKoZIhvdjZAUBMIH9MIHDBggrBgEFBQcCAjCBtgyBs1JlbGlhbmNlIG9uIHRoaXMgY2VydGlmaWNhdGUgYnkgYW55IHBhcnR5IGFzc3VtZXMgYWNjZXB0YW5jZSBvZiB0aGUgdGhlbiBhcHBsaWNhYmxlIHN0YW5kYXJkIHRlcm1zIGFuZCBjb25kaXRpb25zIG9mIHVzZSwgY2VydGlmaWNhdGUgcG9saWN5IGFuZCBjZXJ0aWZpY2F0aW9uIHByYWN0aWNlIHN0YXRlbWVudHMuMDUGCCsGAQUFBwIBFilodHRwOi8vd3d3LmFwcGxlLmNvbS9jZXJ0aWZpY2F0ZWF1dGhvcml0eTAdBgNVHQ4EFgQUK89JHvvPG3kO8K8CKRO1ARbheTQwDgYDVR0PAQH/BAQDAgeAMA8GCSqGSIb3Y2QMDwQCBQAwCgYIKoZIzj0EAwIDSAAwRQIhAIeoCSt0X5hAxTqUIUEaXYuqCYDUhpLV1tKZmdB4x8q1AiA/ZVOMEyzPiDA0sEd16JdTz8/T90SDVbqXVlx9igaBHDCCAvkwggJ/oAMCAQICEFb7g9Qr/43DN5kjtVqubr0wCgYIKoZIzj0EAwMwZzEbMBkGA1UEAwwSQXBwbGUgUm9vdCBDQSAtIEczMSYwJAYDVQQLDB1BcHBsZSBDZXJ0aWZpY2F0aW9uIEF1dGhvcml0eTETMBEGA1UECgwKQXBwbGUgSW5jLjELMAkGA1UEBhMCVVMwHhcNMTkwMzIyMTc1MzMzWhcNMzQwMzIyMDAwMDAwWjB8MTAwLgYDVQQDDCdBcHBsZSBBcHBsaWNhdGlvbiBJbnRlZ3JhdGlvbiBDQSA1IC0gRzExJjAkBgNVBAsMHUFwcGxlIENlcnRpZmljYXRpb24gQXV0aG9yaXR5MRMwEQYDVQQKDApBcHBsZSBJbmMuMQswCQYDVQQGEwJVUzBZMBMGByqGSM49AgEGCCqGSM49AwEHA0IABJLOY719hrGrKAo7HOGv+wSUgJGs9jHfpssoNW9ES+Eh5VfdEo2NuoJ8lb5J+r4zyq7NBBnxL0Ml+vS+s8uDfrqjgfcwgfQwDwYDVR0TAQH/BAUwAwEB/zAfBgNVHSMEGDAWgBS7sN6hWDOImqSKmd6+veuv2sskqzBGBggrBgEFBQcBAQQ6MDgwNgYIKwYBBQUHMAGGKmh0dHA6Ly9vY3NwLmFwcGxlLmNvbS9vY3NwMDMtYXBwbGVyb290Y2FnMzA3BgNVHR8EMDAuMCygKqAohiZodHRwOi8vY3JsLmFwcGxlLmNvbS9hcHBsZXJvb3RjYWczLmNybDAdBgNVHQ4EFgQU2Rf+S2eQOEuS9NvO1VeAFAuPPckwDgYDVR0PAQH/BAQDAgEGMBAGCiqGSIb3Y2QGAgMEAgUAMAoGCCqGSM49BAMDA2gAMGUCMQCNb6afoeDk7FtOc4qSfz14U5iP9NofWB7DdUr+OKhMKoMaGqoNpmRt4bmT6NFVTO0CMGc7LLTh6DcHd8vV7HaoGjpVOz81asjF5pKw4WG+gElp5F8rqWzhEQKqzGHZOLdzSjCCAkMwggHJoAMCAQICCC3F/IjSxUuVMAoGCCqGSM49BAMDMGcxGzAZBgNVBAMMEkFwcGxlIFJvb3QgQ0EgLSBHMzEmMCQGA1UECwwdQXBwbGUgQ2VydGlmaWNhdGlvbiBBdXRob3JpdHkxEzARBgNVBAoMCkFwcGxlIEluYy4xCzAJBgNVBAYTAlVTMB4XDTE0MDQzMDE4MTkwNloXDTM5MDQzMDE4MTkwNlowZzEbMBkGA1UEAwwSQXBwbGUgUm9vdCBDQSAtIEczMSYwJAYDVQQLDB1BcHBsZSBDZXJ0aWZpY2F0aW9uIEF1dGhvcml0eTETMBEGA1UECgwKQXBwbGUgSW5jLjELMAkGA1UEBhMCVVMwdjAQBgcqhkjOPQIBBgUrgQQAIgNiAASY6S89QHKk7ZMicoETHN0QlfHFo05x3BQW2Q7lpgUqd2R7X04407scRLV/9R+2MmJdyemEW08wTxFaAP1YWAyl9Q8sTQdHE3Xal5eXbzFc7SudeyA72LlU2V6ZpDpRCjGjQjBAMB0GA1UdDgQWBBS7sN6hWDOImqSKmd6+veuv2sskqzAPBgNVHRMBAf8EBTADAQH/MA4GA1UdDwEB/wQEAwIBBjAKBggqhkjOPQQDAwNoADBlAjEAg+nBxBZeGl00GNnt7/RsDgBGS7jfskYRxQ/95nqMoaZrzsID1Jz1k8Z0uGrfqiMVAjBtZooQytQN1E/NjUM+tIpjpTNu423aF7dkH8hTJvmIYnQ5Cxdby1GoDOgYA+eisigAADGB/TCB+gIBATCBkDB8MTAwLgYDVQQDDCdBcHBsZSBBcHBsaWNhdGlvbiBJbnRlZ3JhdGlvbiBDQSA1IC0gRzExJjAkBgNVBAsMHUFwcGxlIENlcnRpZmljYXRpb24gQXV0aG9yaXR5MRMwEQYDVQQKDApBcHBsZSBJbmMuMQswCQYDVQQGEwJVUwIQfgISYNjOd6typZ3waCe+/TANBglghkgBZQMEAgEFADAKBggqhkjOPQQDAgRHMEUCIDzodg4szIkkk6IxaqaR/NcsLQO3LtXn9DDBt/yoESUYAiEApRtfQvovTtktiicXHCiBke0Dzlyk14nuYQUnNNumVR0AAAAAAABoYXV0aERhdGFYpKRc2WwGuoniZEqtF+kolObjxcczFdDxbrhJR/nT8ehTQAAAAABhcHBhdHRlc3QAAAAAAAAAACAbd5/2ke303gPFSOj+GWL21XmAYMpoPdR7srxyp43+v6UBAgMmIAEhWCDVrgv9TJ/pAmgUQYA0gtXDRV9vw3TRJv8C1qtpFZ4POCJYIMIBHcByLUsDZSFPJQQxM3nRmKD1ELEfd0RXzKZrhhXn";
-        let result = Attestation::from_base64(valid_cbor_base64);
+        let result = Attestation::from_base64(ATTESTATION_B64);
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_minimal_cbor_matches_ciborium() {
+        let cbor = general_purpose::STANDARD.decode(ATTESTATION_B64).unwrap();
+        let expected: Attestation = from_reader(Cursor::new(&cbor)).unwrap();
+        assert_eq!(Attestation::from_minimal_cbor(&cbor).unwrap(), expected);
+
+        // Every strict prefix is missing part of the map; none may panic.
+        for len in 0..cbor.len() {
+            assert!(Attestation::from_minimal_cbor(&cbor[..len]).is_err(), "prefix of {} bytes", len);
+        }
+        // `x5c` holding a text string instead of byte strings.
+        let x5c = cbor.windows(4).position(|w| w == b"x5c\x82").unwrap() + 3;
+        let mut wrong_type = cbor.clone();
+        wrong_type[x5c + 1] = 0x60;
+        assert!(Attestation::from_minimal_cbor(&wrong_type).is_err());
+    }
+
     #[test]
     fn test_verify_certificates_empty() {
         let empty_certs = Vec::new();
@@ -280,8 +1393,845 @@ mod tests {
         let time = 1700000000; // Example timestamp
         let root_cert_der = pem_to_der(root_cert_pem).unwrap();
         let (_, root_cert) = parse_x509_certificate(&root_cert_der).unwrap();
-        let result = Attestation::verify_certificates(&empty_certs, &root_cert, time);
+        let result = Attestation::verify_certificates(&empty_certs, &[root_cert], time, &VerifyOptions::default());
         assert!(result.is_err());
     }
 
-}
\ No newline at end of file
+    /// The attestation recorded in apps/fixtures, with its challenge, app ID hash and key ID.
+    fn recorded() -> (Attestation, Vec<u8>, [u8; 32], Vec<u8>) {
+        let attestation = Attestation::from_base64(include_str!("../../apps/fixtures/attestation.b64").trim()).unwrap();
+        let challenge = general_purpose::STANDARD.decode("ExYiYJ4jA3rGL9oQN8ihDP4seccnxwHSEU28u8aCQaU=").unwrap();
+        let app_id_hash = Sha256::digest("LMRM26A744.xyz.elus.aegis.app-attester").into();
+        let key_id = general_purpose::STANDARD.decode("HD8zVqwgesSBwPaYj5icRg1GI/b6SOoa4TqieTEBA0g=").unwrap();
+        (attestation, challenge, app_id_hash, key_id)
+    }
+
+    const RECORDED_AT: i64 = 1751800000;
+    /// `notAfter` of the recorded leaf, 2026-01-22T05:05:02Z.
+    const RECORDED_LEAF_NOT_AFTER: i64 = 1769058302;
+
+    #[test]
+    fn test_split_verification_matches_verify_raw() {
+        let (attestation, challenge, app_id_hash, key_id) = recorded();
+        let chain = attestation.verify_chain(&challenge, RECORDED_AT).unwrap();
+        assert_eq!(chain.auth_data_hash, <[u8; 32]>::from(Sha256::digest(attestation.auth_data())));
+        assert_eq!(chain.leaf_not_after, RECORDED_LEAF_NOT_AFTER);
+        let split = attestation.verify_auth_data(&app_id_hash, &key_id, &chain.public_key).unwrap();
+        assert_eq!(split.leaf_not_after, None);
+        let split = split.with_chain(&chain);
+
+        let (attestation, ..) = recorded();
+        let verified = attestation.verify_raw(&challenge, &app_id_hash, &key_id, RECORDED_AT).unwrap();
+        assert_eq!(verified.leaf_not_after, Some(RECORDED_LEAF_NOT_AFTER));
+        assert_eq!(verified, split);
+    }
+
+    #[test]
+    fn test_leaf_not_after_is_the_last_valid_second() {
+        let (attestation, challenge, ..) = recorded();
+        let chain = attestation.verify_chain(&challenge, RECORDED_LEAF_NOT_AFTER).unwrap();
+        assert_eq!(chain.leaf_not_after, RECORDED_LEAF_NOT_AFTER);
+        assert!(matches!(
+            attestation.verify_chain(&challenge, RECORDED_LEAF_NOT_AFTER + 1),
+            Err(AppAttestError::CertificateExpired { index: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn test_validity_failures_name_the_certificate_and_bound() {
+        use rcgen::{date_time_ymd, BasicConstraints, CertificateParams, DnType, IsCa, KeyPair};
+
+        // A root, an intermediate valid in July 2025 and a leaf valid in 2025.
+        let ca = |name: &str| {
+            let mut params = CertificateParams::new(Vec::new()).unwrap();
+            params.distinguished_name.push(DnType::CommonName, name);
+            params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            params
+        };
+        let root_key = KeyPair::generate().unwrap();
+        let root = ca("Test Root").self_signed(&root_key).unwrap();
+        let intermediate_key = KeyPair::generate().unwrap();
+        let mut intermediate = ca("Test Intermediate");
+        intermediate.not_before = date_time_ymd(2025, 7, 1);
+        intermediate.not_after = date_time_ymd(2025, 8, 1);
+        let intermediate = intermediate.signed_by(&intermediate_key, &root, &root_key).unwrap();
+        let mut leaf = CertificateParams::new(Vec::new()).unwrap();
+        leaf.distinguished_name.push(DnType::CommonName, "Test Leaf");
+        leaf.not_before = date_time_ymd(2025, 1, 1);
+        leaf.not_after = date_time_ymd(2026, 1, 1);
+        let leaf = leaf.signed_by(&KeyPair::generate().unwrap(), &intermediate, &intermediate_key).unwrap();
+
+        let chain = [leaf.der().to_vec(), intermediate.der().to_vec()];
+        let roots = [X509Certificate::from_der(root.der()).unwrap().1];
+        let verify = |time| Attestation::verify_certificates(&chain, &roots, time, &VerifyOptions::default());
+
+        let july_2 = 1_751_414_400;
+        assert_eq!(verify(july_2), Ok(0));
+        let june_30 = 1_751_241_600;
+        let not_yet_valid = verify(june_30).unwrap_err();
+        assert_eq!(
+            not_yet_valid,
+            AppAttestError::CertificateNotYetValid {
+                index: 1,
+                subject_cn: "Test Intermediate".into(),
+                not_before: "2025-07-01T00:00:00Z".into(),
+                not_after: "2025-08-01T00:00:00Z".into(),
+                checked_at: "2025-06-30T00:00:00Z".into(),
+            }
+        );
+        assert_eq!(
+            not_yet_valid.to_string(),
+            "certificate 1 (Test Intermediate) is valid from 2025-07-01T00:00:00Z to 2025-08-01T00:00:00Z, \
+             not yet at 2025-06-30T00:00:00Z"
+        );
+        let august_2 = 1_754_092_800;
+        assert_eq!(
+            verify(august_2),
+            Err(AppAttestError::CertificateExpired {
+                index: 1,
+                subject_cn: "Test Intermediate".into(),
+                not_before: "2025-07-01T00:00:00Z".into(),
+                not_after: "2025-08-01T00:00:00Z".into(),
+                checked_at: "2025-08-02T00:00:00Z".into(),
+            })
+        );
+        // The leaf is checked first.
+        let january_2_2026 = 1_767_312_000;
+        assert!(matches!(
+            verify(january_2_2026),
+            Err(AppAttestError::CertificateExpired { index: 0, subject_cn, .. }) if subject_cn == "Test Leaf"
+        ));
+    }
+
+    #[test]
+    fn test_app_id_is_extracted_from_the_leaf() {
+        let (attestation, ..) = recorded();
+        let app_id = attestation.extract_app_id().unwrap();
+        assert_eq!(app_id, include_str!("../../apps/fixtures/app_id.txt").trim());
+        assert_eq!(attestation.borrowed().extract_app_id(), Ok(app_id.clone()));
+        // The RP ID hash of the authenticator data is the hash of the same app ID.
+        assert_eq!(attestation.auth_data()[..32], Sha256::digest(&app_id)[..]);
+
+        let older = Attestation::from_base64(ATTESTATION_B64).unwrap();
+        let app_id = older.extract_app_id().unwrap();
+        assert_eq!(app_id, "762U5G7236.network.gandalf.connect");
+        assert_eq!(older.auth_data()[..32], Sha256::digest(&app_id)[..]);
+
+        // The intermediate has no such extension, and an empty chain no leaf.
+        let (without_leaf, _) = with_chain(|chain| chain[1..].to_vec());
+        assert_eq!(
+            without_leaf.extract_app_id(),
+            Err(AppAttestError::Message("Certificate did not contain the app ID extension".into()))
+        );
+        let (empty, _) = with_chain(|_| Vec::new());
+        assert_eq!(empty.extract_app_id(), Err(AppAttestError::Message("certificate list is empty".into())));
+    }
+
+    #[test]
+    fn test_inferred_app_ids_are_checked_against_the_rp_id_hash() {
+        let (_, challenge, app_id_hash, key_id) = recorded();
+        let attestation = || recorded().0;
+        let inferring = AttestationVerifier::embedded()
+            .with_options(VerifyOptions { infer_app_id: true, ..VerifyOptions::default() });
+
+        // The given hash is ignored, so a wrong one does not matter.
+        let verified = inferring.verify_raw(attestation(), &challenge, &[0; 32], &key_id, RECORDED_AT).unwrap();
+        assert_eq!(verified.app_id.as_deref(), Some(include_str!("../../apps/fixtures/app_id.txt").trim()));
+        assert_eq!(verified.rp_id_hash, app_id_hash);
+        let borrowed = inferring.verify_ref(&attestation().borrowed(), &challenge, &[0; 32], &key_id, RECORDED_AT);
+        assert_eq!(borrowed, Ok(verified));
+        let (debugged, _) = inferring.verify_debug(attestation(), &challenge, &[0; 32], &key_id, RECORDED_AT);
+        assert!(debugged.unwrap().app_id.is_some());
+
+        // Without the option the given hash is checked, and no app ID is returned.
+        let verifier = AttestationVerifier::embedded();
+        let result = verifier.verify_raw(attestation(), &challenge, &[0; 32], &key_id, RECORDED_AT);
+        assert_eq!(result, Err(AppAttestError::InvalidAppID));
+        let verified = verifier.verify_raw(attestation(), &challenge, &app_id_hash, &key_id, RECORDED_AT).unwrap();
+        assert_eq!(verified.app_id, None);
+    }
+
+    #[test]
+    fn test_structure_of_the_recorded_attestation() {
+        let (attestation, challenge, app_id_hash, key_id) = recorded();
+        let report = attestation.validate_structure().unwrap();
+        let chain = attestation.verify_chain(&challenge, RECORDED_AT).unwrap();
+        assert_eq!(report.certificate_count, 2);
+        assert_eq!(report.leaf_public_key, chain.public_key);
+        assert_eq!(report.leaf_not_after, RECORDED_LEAF_NOT_AFTER);
+        assert_eq!(report.environment, Environment::Development);
+        assert_eq!(report.credential_id, key_id);
+        assert_eq!(attestation.borrowed().validate_structure(), Ok(report.clone()));
+
+        // Validating again returns the kept report, which is not part of the value.
+        assert_eq!(attestation.validate_structure(), Ok(report));
+        assert_eq!(attestation, recorded().0);
+
+        // Verification takes the leaf from the report, and comes to the same result.
+        let expected = recorded().0.verify_raw(&challenge, &app_id_hash, &key_id, RECORDED_AT);
+        assert_eq!(attestation.verify_raw(&challenge, &app_id_hash, &key_id, RECORDED_AT), expected);
+    }
+
+    #[test]
+    fn test_forged_attestations_pass_validation_and_fail_verification() {
+        let (attestation, challenge, app_id_hash, key_id) = recorded();
+        // The last byte of the leaf is the last of its signature's `s`: still DER, no longer
+        // the intermediate's signature.
+        let (forged, _) = with_chain(|chain| {
+            let mut leaf = chain[0].clone();
+            *leaf.last_mut().unwrap() ^= 1;
+            vec![leaf, chain[1].clone()]
+        });
+        assert_eq!(forged.validate_structure(), attestation.validate_structure());
+        assert_eq!(
+            forged.verify_raw(&challenge, &app_id_hash, &key_id, RECORDED_AT),
+            Err(AppAttestError::Message("signature verification failed for 0".into()))
+        );
+
+        // Over another challenge: the structure is the same, the nonce is not.
+        let (attestation, ..) = recorded();
+        attestation.validate_structure().unwrap();
+        assert_eq!(
+            attestation.verify_raw(b"another challenge", &app_id_hash, &key_id, RECORDED_AT),
+            Err(AppAttestError::InvalidNonce)
+        );
+    }
+
+    #[test]
+    fn test_malformed_attestations_fail_validation() {
+        let (mut other_format, ..) = recorded();
+        other_format.fmt = "packed".into();
+        assert_eq!(other_format.validate_structure(), Err(AppAttestError::UnsupportedFormat { fmt: "packed".into() }));
+
+        let (empty, _) = with_chain(|_| Vec::new());
+        assert_eq!(empty.validate_structure(), Err(AppAttestError::Message("certificate list is empty".into())));
+        let (overlong, _) = with_chain(|chain| [chain, chain].concat());
+        assert_eq!(overlong.validate_structure(), Err(AppAttestError::CertificateChainTooLong { max_length: 3, length: 4 }));
+        let (repeated, _) = with_chain(|chain| vec![chain[0].clone(), chain[0].clone()]);
+        assert_eq!(repeated.validate_structure(), Err(AppAttestError::DuplicateCertificate { index: 1 }));
+        let (garbage, _) = with_chain(|chain| vec![chain[0].clone(), b"not a certificate".to_vec()]);
+        assert_eq!(garbage.validate_structure(), Err(AppAttestError::Message("failed to parse certificate".into())));
+        // The intermediate certifies a P-384 key and no nonce.
+        let (no_leaf, _) = with_chain(|chain| vec![chain[1].clone()]);
+        assert!(no_leaf.validate_structure().is_err());
+
+        let (mut unknown_aaguid, ..) = recorded();
+        unknown_aaguid.auth_data[37] ^= 1;
+        assert_eq!(unknown_aaguid.validate_structure(), Err(AppAttestError::InvalidAAGUID));
+        let (mut truncated, ..) = recorded();
+        truncated.auth_data.truncate(36);
+        assert!(truncated.validate_structure().is_err());
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_verification_spans() {
+        let output = crate::traced(|| {
+            let (attestation, challenge, app_id_hash, key_id) = recorded();
+            attestation.verify_raw(&challenge, &app_id_hash, &key_id, RECORDED_AT).unwrap();
+        });
+        for span in [
+            "attestation.decode{len=",
+            "attestation.verify:attestation.verify_chain{cert_count=2 root=\"Apple_App_Attestation_Root_CA\"}",
+            "attestation.verify:attestation.verify_auth_data{environment=Development}",
+        ] {
+            assert!(output.contains(span), "no {span} in\n{output}");
+        }
+    }
+
+    #[test]
+    fn test_verifier_reports_outcomes() {
+        let sink = Arc::new(crate::metrics::RecordingSink::default());
+        let verifier = AttestationVerifier::embedded().with_metrics(sink.clone());
+        let (attestation, mut challenge, app_id_hash, key_id) = recorded();
+        assert!(verifier.verify_raw(attestation, &challenge, &app_id_hash, &key_id, RECORDED_AT).is_ok());
+
+        let (attestation, ..) = recorded();
+        challenge[0] ^= 0x01;
+        let result = verifier.verify_raw(attestation, &challenge, &app_id_hash, &key_id, RECORDED_AT);
+        assert_eq!(result, Err(AppAttestError::InvalidNonce));
+        assert_eq!(
+            sink.names(),
+            [
+                "appattest_verifications_total{kind=attestation,outcome=success}",
+                "appattest_verify_duration_seconds{kind=attestation}",
+                "appattest_verifications_total{kind=attestation,outcome=InvalidNonce}",
+                "appattest_verify_duration_seconds{kind=attestation}",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_verify_chain_rejects_other_challenge() {
+        let (attestation, mut challenge, ..) = recorded();
+        challenge[0] ^= 0x01;
+        assert_eq!(attestation.verify_chain(&challenge, RECORDED_AT), Err(AppAttestError::InvalidNonce));
+    }
+
+    #[test]
+    fn test_verify_auth_data_rejects_a_key_other_than_the_key_id() {
+        let (attestation, _, app_id_hash, key_id) = recorded();
+        // The P-256 generator: a valid point, but not the attested key.
+        let other_key = hex_literal("046b17d1f2e12c4247f8bce6e563a440f277037d812deb33a0f4a13945d898c2964fe342e2fe1a7f9b8ee7eb4a7c0f9e162bce33576b315ececbb6406837bf51f5");
+        assert_eq!(
+            attestation.verify_auth_data(&app_id_hash, &key_id, &other_key),
+            Err(AppAttestError::InvalidPublicKey)
+        );
+    }
+
+    #[test]
+    fn test_verify_auth_data_rejects_another_credential_key() {
+        let (mut attestation, challenge, app_id_hash, key_id) = recorded();
+        let public_key = attestation.verify_chain(&challenge, RECORDED_AT).unwrap().public_key;
+        // The COSE key ends the recorded authenticator data with `-2: x, -3: y`; swap in the
+        // P-256 generator, a valid point, but not the certified key.
+        let y = attestation.auth_data.len() - 32;
+        let x = y - 3 - 32;
+        assert_eq!(attestation.auth_data[x..x + 32], public_key[1..33]);
+        assert_eq!(attestation.auth_data[y..], public_key[33..]);
+        let generator = hex_literal("046b17d1f2e12c4247f8bce6e563a440f277037d812deb33a0f4a13945d898c2964fe342e2fe1a7f9b8ee7eb4a7c0f9e162bce33576b315ececbb6406837bf51f5");
+        attestation.auth_data[x..x + 32].copy_from_slice(&generator[1..33]);
+        attestation.auth_data[y..].copy_from_slice(&generator[33..]);
+        assert_eq!(
+            attestation.verify_auth_data(&app_id_hash, &key_id, &public_key),
+            Err(AppAttestError::InvalidPublicKey)
+        );
+    }
+
+    #[test]
+    fn test_leaf_key_hashes_to_the_key_id() {
+        let (attestation, challenge, _, key_id) = recorded();
+        let chain = attestation.verify_chain(&challenge, RECORDED_AT).unwrap();
+        let expected = KeyId::for_public_key(&chain.public_key).unwrap();
+        assert_eq!(KeyId::from_bytes(&key_id), Ok(expected));
+
+        let (_, leaf) = X509Certificate::from_der(&attestation.statement.certificates[0]).unwrap();
+        let common_name = leaf.subject().iter_common_name().next().unwrap().as_str().unwrap();
+        assert_eq!(KeyId::from_hex(common_name), Ok(expected));
+
+        let credential_id = AuthenticatorData::new(attestation.auth_data).unwrap().credential_id.unwrap();
+        assert_eq!(KeyId::from_bytes(&credential_id), Ok(expected));
+    }
+
+    fn hex_literal(hex: &str) -> Vec<u8> {
+        (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap()).collect()
+    }
+
+    /// A root named `name`, and the recorded authenticator data under a one-certificate chain
+    /// it signed, certifying the nonce for `challenge`. Returns the leaf's public key too.
+    fn staging_chain(name: &str, challenge: &[u8]) -> (rcgen::Certificate, Attestation, Vec<u8>) {
+        let (recorded, ..) = recorded();
+        let nonce = Attestation::nonce_hash(&recorded.auth_data, &Sha256::digest(challenge));
+        // SEQUENCE { [1] { OCTET STRING nonce } }, the shape of Apple's extension.
+        let extension = [[0x30, 0x24, 0xa1, 0x22, 0x04, 0x20].as_slice(), &nonce].concat();
+        staging_chain_with_extension(name, extension)
+    }
+
+    /// As [`staging_chain`], with `extension` as the leaf's nonce extension.
+    fn staging_chain_with_extension(name: &str, extension: Vec<u8>) -> (rcgen::Certificate, Attestation, Vec<u8>) {
+        use rcgen::{BasicConstraints, CertificateParams, CustomExtension, DnType, IsCa, KeyPair, PublicKeyData};
+
+        let root_key = KeyPair::generate().unwrap();
+        let mut root = CertificateParams::new(Vec::new()).unwrap();
+        root.distinguished_name.push(DnType::CommonName, name);
+        root.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let root = root.self_signed(&root_key).unwrap();
+
+        let (recorded, ..) = recorded();
+        let leaf_key = KeyPair::generate().unwrap();
+        let mut leaf = CertificateParams::new(Vec::new()).unwrap();
+        leaf.distinguished_name.push(DnType::CommonName, "Staging credential");
+        leaf.custom_extensions.push(CustomExtension::from_oid_content(&[1, 2, 840, 113635, 100, 8, 2], extension));
+        let leaf = leaf.signed_by(&leaf_key, &root, &root_key).unwrap();
+
+        let attestation = Attestation {
+            fmt: FORMAT.into(),
+            statement: Statement { certificates: vec![leaf.der().to_vec()], receipt: Vec::new() },
+            auth_data: recorded.auth_data,
+            validated: Validated::default(),
+        };
+        (root, attestation, leaf_key.public_key_raw().to_vec())
+    }
+
+    /// The recorded attestation with its certificates replaced by `chain(recorded chain)`.
+    fn with_chain(chain: impl FnOnce(&[Vec<u8>]) -> Vec<Vec<u8>>) -> (Attestation, Vec<u8>) {
+        let (mut attestation, challenge, ..) = recorded();
+        attestation.statement.certificates = chain(&attestation.statement.certificates);
+        (attestation, challenge)
+    }
+
+    #[test]
+    fn test_overlong_chains_are_rejected_before_parsing() {
+        // A thousand certificates, none of them parseable: the length alone rejects them.
+        let (attestation, challenge) = with_chain(|_| (0..1000u32).map(|i| i.to_be_bytes().to_vec()).collect());
+        assert_eq!(
+            AttestationVerifier::embedded().verify_chain(&attestation, &challenge, RECORDED_AT),
+            Err(AppAttestError::CertificateChainTooLong { max_length: 3, length: 1000 })
+        );
+
+        // The recorded chain is a leaf and an intermediate.
+        let (attestation, challenge, ..) = recorded();
+        let limited = |max_chain_length| {
+            AttestationVerifier::embedded().with_options(VerifyOptions { max_chain_length, ..VerifyOptions::default() })
+        };
+        assert!(limited(2).verify_chain(&attestation, &challenge, RECORDED_AT).is_ok());
+        assert_eq!(
+            limited(1).verify_chain(&attestation, &challenge, RECORDED_AT),
+            Err(AppAttestError::CertificateChainTooLong { max_length: 1, length: 2 })
+        );
+    }
+
+    #[test]
+    fn test_chains_repeating_a_certificate_are_rejected() {
+        let verifier = AttestationVerifier::embedded();
+        let (intermediate, challenge) = with_chain(|chain| vec![chain[0].clone(), chain[1].clone(), chain[1].clone()]);
+        assert_eq!(
+            verifier.verify_chain(&intermediate, &challenge, RECORDED_AT),
+            Err(AppAttestError::DuplicateCertificate { index: 2 })
+        );
+        let (leaf, challenge) = with_chain(|chain| vec![chain[0].clone(), chain[0].clone(), chain[1].clone()]);
+        assert_eq!(
+            verifier.verify_chain(&leaf, &challenge, RECORDED_AT),
+            Err(AppAttestError::DuplicateCertificate { index: 1 })
+        );
+    }
+
+    #[test]
+    fn test_chains_may_end_at_the_trusted_root() {
+        let verifier = AttestationVerifier::embedded();
+        let (_, apple_root) = EMBEDDED_ROOTS.iter().find(|(name, _)| *name == root::APPLE_ROOT_NAME).unwrap();
+        let (excluded, challenge, ..) = recorded();
+        let expected = verifier.verify_chain(&excluded, &challenge, RECORDED_AT).unwrap();
+
+        let (included, challenge) = with_chain(|chain| [chain.to_vec(), vec![apple_root.to_vec()]].concat());
+        assert_eq!(verifier.verify_chain(&included, &challenge, RECORDED_AT), Ok(expected));
+
+        // Anywhere else the root would be anchored at itself.
+        let (inside, challenge) =
+            with_chain(|chain| vec![chain[0].clone(), apple_root.to_vec(), chain[1].clone(), apple_root.to_vec()]);
+        assert_eq!(
+            verifier.verify_chain(&inside, &challenge, RECORDED_AT),
+            Err(AppAttestError::RootInCertificateChain { index: 1 })
+        );
+        let (alone, challenge) = with_chain(|_| vec![apple_root.to_vec()]);
+        assert_eq!(
+            verifier.verify_chain(&alone, &challenge, RECORDED_AT),
+            Err(AppAttestError::RootInCertificateChain { index: 0 })
+        );
+    }
+
+    #[test]
+    fn test_chains_ending_at_an_untrusted_self_signed_root_are_rejected() {
+        let (_, challenge, ..) = recorded();
+        let (root, mut attestation, _) = staging_chain("Apple App Attestation Root CA", &challenge);
+        attestation.statement.certificates.push(root.der().to_vec());
+        // Same name as Apple's root, and validly self-signed, but not the trusted root.
+        assert_eq!(
+            AttestationVerifier::embedded().verify_chain(&attestation, &challenge, RECORDED_AT),
+            Err(AppAttestError::UntrustedSelfSignedCertificate { index: 1 })
+        );
+        // Trusted, it is dropped from the walk.
+        let staging = AttestationVerifier::with_root_der("staging", root.der().to_vec()).unwrap();
+        assert!(staging.verify_chain(&attestation, &challenge, RECORDED_AT).is_ok());
+    }
+
+    /// A chain of one `tests/fixtures/spki` leaf, and a verifier trusting their root.
+    fn spki_fixture(leaf: &[u8]) -> (AttestationVerifier, Attestation) {
+        let root = include_bytes!("../tests/fixtures/spki/root.der").to_vec();
+        let (recorded, ..) = recorded();
+        let attestation = Attestation {
+            fmt: FORMAT.into(),
+            statement: Statement { certificates: vec![leaf.to_vec()], receipt: Vec::new() },
+            auth_data: recorded.auth_data,
+            validated: Validated::default(),
+        };
+        (AttestationVerifier::with_root_der("test", root).unwrap(), attestation)
+    }
+
+    #[test]
+    fn test_leaves_must_certify_a_p256_key() {
+        let (_, challenge, ..) = recorded();
+        let (verifier, rsa) = spki_fixture(include_bytes!("../tests/fixtures/spki/rsa_leaf.der"));
+        assert_eq!(
+            verifier.verify_chain(&rsa, &challenge, RECORDED_AT),
+            Err(AppAttestError::UnsupportedKeyAlgorithm { oid: "1.2.840.113549.1.1.1".into() })
+        );
+
+        let (verifier, compressed) = spki_fixture(include_bytes!("../tests/fixtures/spki/compressed_leaf.der"));
+        assert_eq!(verifier.verify_chain(&compressed, &challenge, RECORDED_AT), Err(AppAttestError::InvalidPublicKey));
+    }
+
+    #[test]
+    fn test_verifier_applies_its_receipt_policy() {
+        // The recorded receipt was made on 2025-07-04, about 40 hours before RECORDED_AT.
+        let policy = |max_age_days, require_risk_metric| ReceiptPolicy {
+            max_age: std::time::Duration::from_secs(max_age_days * 86_400),
+            require_risk_metric,
+            max_risk_metric: None,
+        };
+        let verify = |policy| {
+            let (attestation, challenge, app_id_hash, key_id) = recorded();
+            let verifier = AttestationVerifier::embedded().with_receipt_policy(policy);
+            verifier.verify_raw(attestation, &challenge, &app_id_hash, &key_id, RECORDED_AT)
+        };
+        assert!(verify(policy(2, false)).is_ok());
+        assert!(matches!(verify(policy(1, false)), Err(AppAttestError::ReceiptRejected { .. })));
+        // An attestation's own receipt has no risk metric.
+        assert_eq!(verify(policy(2, true)), Err(AppAttestError::ReceiptRejected { reason: "no risk metric".into() }));
+    }
+
+    #[test]
+    fn test_verifier_trusts_a_root_loaded_from_file() {
+        let (_, challenge, ..) = recorded();
+        let (root, attestation, leaf_key) = staging_chain("Staging App Attestation Root CA", &challenge);
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("staging.pem"), root.pem()).unwrap();
+        std::fs::write(dir.path().join("staging.der"), root.der()).unwrap();
+
+        for file in ["staging.pem", "staging.der"] {
+            let verifier = AttestationVerifier::with_root_file(dir.path().join(file)).unwrap();
+            assert_eq!(verifier.roots()[0].der, root.der().as_ref());
+            let chain = verifier.verify_chain(&attestation, &challenge, RECORDED_AT).unwrap();
+            assert_eq!(chain.public_key, leaf_key);
+            assert_eq!(chain.root, "staging");
+        }
+
+        // Neither root vouches for the other's chains.
+        assert!(attestation.verify_chain(&challenge, RECORDED_AT).is_err());
+        let staging = AttestationVerifier::with_root_der("staging", root.der().to_vec()).unwrap();
+        let (recorded, ..) = recorded();
+        assert!(staging.verify_chain(&recorded, &challenge, RECORDED_AT).is_err());
+    }
+
+    #[test]
+    fn test_verify_chain_rejects_a_short_nonce_extension() {
+        let (_, challenge, ..) = recorded();
+        // `[1]` holds less than the two bytes of the octet string header, or nothing.
+        for extension in [vec![0x30, 0x03, 0xa1, 0x01, 0x04], vec![0x30, 0x02, 0xa1, 0x00]] {
+            let (root, attestation, _) = staging_chain_with_extension("Staging Root CA", extension);
+            let verifier = AttestationVerifier::with_root_der("staging", root.der().to_vec()).unwrap();
+            assert_eq!(
+                verifier.verify_chain(&attestation, &challenge, RECORDED_AT),
+                Err(AppAttestError::FailedToExtractValueFromASN1Node)
+            );
+        }
+    }
+
+    #[test]
+    fn test_each_chain_reports_the_root_it_anchors_at() {
+        let (_, challenge, ..) = recorded();
+        let (first, first_attestation, _) = staging_chain("Staging Root CA 1", &challenge);
+        // The same name as the first root: only the signature tells them apart.
+        let (second, second_attestation, _) = staging_chain("Staging Root CA 1", &challenge);
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("first.pem"), first.pem()).unwrap();
+        std::fs::write(dir.path().join("second.der"), second.der()).unwrap();
+
+        let roots = root::collect(dir.path())
+            .unwrap()
+            .into_iter()
+            .map(|(name, der)| TrustedRoot { name: name.into(), der: der.into() })
+            .collect();
+        let verifier = AttestationVerifier::with_roots(roots).unwrap();
+        let anchor = |attestation| verifier.verify_chain(attestation, &challenge, RECORDED_AT).unwrap().root;
+        assert_eq!(anchor(&first_attestation), "first");
+        assert_eq!(anchor(&second_attestation), "second");
+
+        // A store without the second root rejects its chain.
+        let only_first = AttestationVerifier::with_root_der("first", first.der().to_vec()).unwrap();
+        assert!(only_first.verify_chain(&second_attestation, &challenge, RECORDED_AT).is_err());
+    }
+
+    #[test]
+    fn test_embedded_apple_root_parses_and_matches_the_pin() {
+        let (_, der) = EMBEDDED_ROOTS.iter().find(|(name, _)| *name == root::APPLE_ROOT_NAME).unwrap();
+        let (rest, cert) = parse_x509_certificate(der).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(cert.subject().to_string(), "CN=Apple App Attestation Root CA, O=Apple Inc., ST=California");
+        assert_eq!(root::check_pin(der, root::APPLE_ROOT_SHA256), Ok(()));
+    }
+
+    #[test]
+    fn test_recorded_chain_anchors_at_apples_root() {
+        let (attestation, challenge, ..) = recorded();
+        let chain = attestation.verify_chain(&challenge, RECORDED_AT).unwrap();
+        assert_eq!(chain.root, root::APPLE_ROOT_NAME);
+        let embedded = AttestationVerifier::embedded();
+        assert!(embedded.roots().iter().any(|trusted| trusted.name == root::APPLE_ROOT_NAME));
+    }
+
+    #[test]
+    fn test_pinned_intermediates_must_sign_the_leaf() {
+        let (attestation, challenge, ..) = recorded();
+        let intermediate = root::spki_sha256(&attestation.statement.certificates[1]).unwrap();
+        let leaf = root::spki_sha256(&attestation.statement.certificates[0]).unwrap();
+        let pinned = |pins: Vec<[u8; 32]>| {
+            let options = VerifyOptions { pinned_intermediate_spki_sha256: Some(pins), ..VerifyOptions::default() };
+            AttestationVerifier::embedded().with_options(options)
+        };
+
+        let expected = attestation.verify_chain(&challenge, RECORDED_AT).unwrap();
+        let verified = pinned(vec![[0; 32], intermediate]).verify_chain(&attestation, &challenge, RECORDED_AT);
+        assert_eq!(verified, Ok(expected));
+
+        let not_pinned = Err(AppAttestError::IntermediateNotPinned { spki_sha256: hex::encode(intermediate) });
+        assert_eq!(pinned(vec![leaf]).verify_chain(&attestation, &challenge, RECORDED_AT), not_pinned);
+        assert_eq!(pinned(vec![]).verify_chain(&attestation, &challenge, RECORDED_AT), not_pinned);
+
+        // A leaf issued by the root directly is pinned at the root.
+        let (staging, staging_attestation, _) = staging_chain("Staging App Attestation Root CA", &challenge);
+        let staging_pin = root::spki_sha256(staging.der()).unwrap();
+        let verifier = |pins| {
+            let options = VerifyOptions { pinned_intermediate_spki_sha256: Some(pins), ..VerifyOptions::default() };
+            AttestationVerifier::with_root_der("staging", staging.der().to_vec()).unwrap().with_options(options)
+        };
+        assert!(verifier(vec![staging_pin]).verify_chain(&staging_attestation, &challenge, RECORDED_AT).is_ok());
+        assert_eq!(
+            verifier(vec![intermediate]).verify_chain(&staging_attestation, &challenge, RECORDED_AT),
+            Err(AppAttestError::IntermediateNotPinned { spki_sha256: hex::encode(staging_pin) })
+        );
+    }
+
+    #[test]
+    fn test_embedded_verifier_matches_verify_raw() {
+        let (attestation, challenge, app_id_hash, key_id) = recorded();
+        let expected = attestation.verify_raw(&challenge, &app_id_hash, &key_id, RECORDED_AT).unwrap();
+        let (attestation, ..) = recorded();
+        let verifier = AttestationVerifier::default();
+        let verified = verifier.verify_raw(attestation, &challenge, &app_id_hash, &key_id, RECORDED_AT);
+        assert_eq!(verified.unwrap(), expected);
+    }
+
+    #[test]
+    fn test_borrowed_attestation_parses_like_the_owned_one() {
+        let cbor = general_purpose::STANDARD.decode(include_str!("../../apps/fixtures/attestation.b64").trim()).unwrap();
+        let (attestation, ..) = recorded();
+        let borrowed = AttestationRef::from_cbor(&cbor).unwrap();
+        assert_eq!(borrowed, attestation.borrowed());
+        assert_eq!(borrowed.to_attestation(), attestation);
+        assert_eq!((borrowed.auth_data(), borrowed.receipt()), (attestation.auth_data(), attestation.receipt()));
+
+        for len in 0..cbor.len() {
+            assert!(AttestationRef::from_cbor(&cbor[..len]).is_err(), "prefix of {} bytes", len);
+        }
+    }
+
+    #[test]
+    fn test_borrowed_attestation_verifies_like_the_owned_one() {
+        let cbor = general_purpose::STANDARD.decode(include_str!("../../apps/fixtures/attestation.b64").trim()).unwrap();
+        let borrowed = AttestationRef::from_cbor(&cbor).unwrap();
+        let (_, challenge, app_id_hash, key_id) = recorded();
+        let other_app_id_hash = Sha256::digest("LMRM26A744.xyz.elus.aegis.other").into();
+        let cases: [(&[u8], &[u8; 32], &[u8], i64); 5] = [
+            (&challenge, &app_id_hash, &key_id, RECORDED_AT),
+            (b"another challenge", &app_id_hash, &key_id, RECORDED_AT),
+            (&challenge, &other_app_id_hash, &key_id, RECORDED_AT),
+            (&challenge, &app_id_hash, &[0; 32], RECORDED_AT),
+            (&challenge, &app_id_hash, &key_id, 0),
+        ];
+        let verifier = AttestationVerifier::embedded();
+        for (challenge, app_id_hash, key_id, time) in cases {
+            let expected = recorded().0.verify_raw(challenge, app_id_hash, key_id, time);
+            assert_eq!(borrowed.verify_raw(challenge, app_id_hash, key_id, time), expected);
+            assert_eq!(verifier.verify_ref(&borrowed, challenge, app_id_hash, key_id, time), expected);
+        }
+
+        let chain = borrowed.verify_chain(&challenge, RECORDED_AT).unwrap();
+        assert_eq!(chain, recorded().0.verify_chain(&challenge, RECORDED_AT).unwrap());
+        assert_eq!(verifier.verify_chain(&recorded().0, &challenge, RECORDED_AT).unwrap(), chain);
+        assert_eq!(
+            borrowed.verify_auth_data(&app_id_hash, &key_id, &chain.public_key),
+            recorded().0.verify_auth_data(&app_id_hash, &key_id, &chain.public_key),
+        );
+    }
+
+    #[test]
+    fn test_verify_debug_traces_a_wrong_challenge() {
+        let (attestation, challenge, app_id_hash, key_id) = recorded();
+        let verifier = AttestationVerifier::embedded();
+        let (result, trace) = verifier.verify_debug(attestation, &challenge, &app_id_hash, &key_id, RECORDED_AT);
+        assert!(result.is_ok());
+        assert_eq!(trace.failed_step, None);
+        let expected_nonce = trace.nonce.clone().unwrap();
+        assert_eq!(trace.certificate_nonce.as_ref(), Some(&expected_nonce));
+        assert_eq!(trace.rp_id_hash, Some(hex::encode(app_id_hash)));
+
+        let (attestation, ..) = recorded();
+        let wrong = b"not the challenge".to_vec();
+        let (result, trace) = verifier.verify_debug(attestation, &wrong, &app_id_hash, &key_id, RECORDED_AT);
+        assert_eq!(result, Err(AppAttestError::InvalidNonce));
+        assert_eq!(trace.failed_step, Some(4));
+        assert_eq!(trace.client_data_hash, Some(hex::encode(Sha256::digest(&wrong))));
+        assert!(trace.nonce.is_some() && trace.nonce != Some(expected_nonce.clone()));
+        assert_eq!(trace.certificate_nonce, Some(expected_nonce));
+        assert_eq!(trace.rp_id_hash, None);
+    }
+
+    #[test]
+    fn test_verify_debug_traces_a_wrong_app_id() {
+        let (attestation, challenge, _, key_id) = recorded();
+        let other_app: [u8; 32] = Sha256::digest("LMRM26A744.xyz.elus.aegis.other").into();
+        let verifier = AttestationVerifier::embedded();
+        let (result, trace) = verifier.verify_debug(attestation, &challenge, &other_app, &key_id, RECORDED_AT);
+        assert_eq!(result, Err(AppAttestError::InvalidAppID));
+        assert_eq!(trace.failed_step, Some(6));
+        assert_eq!(trace.expected_rp_id_hash, Some(hex::encode(other_app)));
+        assert_ne!(trace.rp_id_hash, trace.expected_rp_id_hash);
+        assert_eq!(trace.public_key_hash, trace.key_id);
+        assert_eq!(trace.counter, None);
+
+        let json = serde_json::to_value(&trace).unwrap();
+        assert_eq!(json["failedStep"], 6);
+        assert_eq!(json["rpIdHash"], trace.rp_id_hash.unwrap().as_str());
+    }
+
+    #[test]
+    fn test_verifier_times_its_steps() {
+        use std::time::Duration;
+        use VerificationStep::*;
+        let steps = |timings: &VerificationTimings| timings.steps.iter().map(|timing| timing.step).collect::<Vec<_>>();
+
+        let (attestation, challenge, app_id_hash, key_id) = recorded();
+        let untimed = AttestationVerifier::embedded();
+        let (result, timings) = untimed.verify_timed(attestation, &challenge, &app_id_hash, &key_id, RECORDED_AT);
+        assert_eq!(result.unwrap().timings, None);
+        assert_eq!(timings, None);
+
+        let verifier = AttestationVerifier::embedded().with_timings();
+        let (attestation, ..) = recorded();
+        let verified = verifier.verify_raw(attestation, &challenge, &app_id_hash, &key_id, RECORDED_AT).unwrap();
+        let timings = verified.timings.unwrap();
+        assert_eq!(steps(&timings), [Decode, Chain, Nonce, AuthData]);
+        assert!(timings.get(Chain).unwrap() > Duration::ZERO);
+        assert!(timings.sum() <= timings.total);
+        assert!(timings.total - timings.sum() < Duration::from_millis(5), "{timings:?}");
+
+        let (attestation, ..) = recorded();
+        let borrowed = verifier.verify_ref(&attestation.borrowed(), &challenge, &app_id_hash, &key_id, RECORDED_AT);
+        assert_eq!(steps(&borrowed.unwrap().timings.unwrap()), [Decode, Chain, Nonce, AuthData]);
+
+        // A wrong challenge fails at the nonce, and the authenticator data goes unchecked.
+        let (attestation, ..) = recorded();
+        let (result, timings) = verifier.verify_timed(attestation, b"not the challenge", &app_id_hash, &key_id, RECORDED_AT);
+        assert_eq!(result, Err(AppAttestError::InvalidNonce));
+        assert_eq!(steps(&timings.unwrap()), [Decode, Chain, Nonce]);
+
+        // Expired by then, so it fails in the chain.
+        let (attestation, ..) = recorded();
+        let (result, timings) = verifier.verify_timed(attestation, &challenge, &app_id_hash, &key_id, 2_000_000_000);
+        assert!(matches!(result, Err(AppAttestError::CertificateExpired { .. })));
+        assert_eq!(steps(&timings.unwrap()), [Decode, Chain]);
+
+        let policy = ReceiptPolicy { max_age: Duration::from_secs(2 * 86_400), require_risk_metric: false, max_risk_metric: None };
+        let (attestation, ..) = recorded();
+        let verified = verifier.with_receipt_policy(policy).verify_raw(attestation, &challenge, &app_id_hash, &key_id, RECORDED_AT);
+        assert_eq!(steps(&verified.unwrap().timings.unwrap()), [Decode, Chain, Nonce, AuthData, Receipt]);
+    }
+
+    #[test]
+    fn test_verifier_denies_keys_before_checking_them() {
+        use crate::keypolicy::{InMemoryKeyList, PolicyDecision};
+        use VerificationStep::*;
+        let steps = |timings: Option<VerificationTimings>| timings.unwrap().steps.iter().map(|timing| timing.step).collect::<Vec<_>>();
+        let (attestation, challenge, app_id_hash, key_id) = recorded();
+        let public_key = attestation.borrowed().validate_structure().unwrap().leaf_public_key;
+
+        // Denied by its ID, the chain is not even parsed.
+        let deny_list = Arc::new(InMemoryKeyList::deny());
+        deny_list.insert_key_id(KeyId::from_bytes(&key_id).unwrap());
+        let verifier = AttestationVerifier::embedded().with_timings().with_key_policy(deny_list.clone());
+        let (result, timings) = verifier.verify_timed(attestation, &challenge, &app_id_hash, &key_id, RECORDED_AT);
+        assert_eq!(result, Err(AppAttestError::KeyDenied { reason: "key on the deny list".into() }));
+        assert!(steps(timings).is_empty());
+        let (attestation, ..) = recorded();
+        let borrowed = verifier.verify_ref(&attestation.borrowed(), &challenge, &app_id_hash, &key_id, RECORDED_AT);
+        assert!(matches!(borrowed, Err(AppAttestError::KeyDenied { .. })));
+
+        // A policy that only knows the public key denies it once the chain certified it.
+        struct DenyKey(Vec<u8>);
+        impl KeyPolicy for DenyKey {
+            fn check(&self, _: &KeyId, public_key: Option<&[u8]>) -> PolicyDecision {
+                if public_key == Some(&self.0[..]) {
+                    PolicyDecision::Deny { reason: "fraud".into() }
+                } else {
+                    PolicyDecision::Allow
+                }
+            }
+        }
+        let verifier = AttestationVerifier::embedded().with_timings().with_key_policy(Arc::new(DenyKey(public_key)));
+        let (attestation, ..) = recorded();
+        let (result, timings) = verifier.verify_timed(attestation, &challenge, &app_id_hash, &key_id, RECORDED_AT);
+        assert_eq!(result, Err(AppAttestError::KeyDenied { reason: "fraud".into() }));
+        assert_eq!(steps(timings), [Decode, Chain, Nonce]);
+
+        deny_list.remove_key_id(&KeyId::from_bytes(&key_id).unwrap());
+        let (attestation, ..) = recorded();
+        let verifier = AttestationVerifier::embedded().with_key_policy(deny_list);
+        assert!(verifier.verify_raw(attestation, &challenge, &app_id_hash, &key_id, RECORDED_AT).is_ok());
+    }
+
+    #[test]
+    fn test_verifier_rejects_bad_roots_at_construction() {
+        let dir = tempfile::tempdir().unwrap();
+        let garbage = dir.path().join("garbage.der");
+        std::fs::write(&garbage, b"not a certificate").unwrap();
+        assert!(matches!(AttestationVerifier::with_root_file(&garbage), Err(RootCertError::InvalidCertificate(_))));
+
+        let bad_pem = dir.path().join("bad.pem");
+        std::fs::write(&bad_pem, "-----BEGIN CERTIFICATE-----\n*\n-----END CERTIFICATE-----\n").unwrap();
+        assert!(matches!(AttestationVerifier::with_root_file(&bad_pem), Err(RootCertError::InvalidPem(_))));
+
+        let missing = dir.path().join("missing.pem");
+        assert!(matches!(AttestationVerifier::with_root_file(missing), Err(RootCertError::Io(_))));
+    }
+
+    #[test]
+    fn test_verifier_reads_the_root_named_by_the_environment() {
+        let (staging, ..) = staging_chain("Staging App Attestation Root CA", b"challenge");
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("staging.pem");
+        std::fs::write(&path, staging.pem()).unwrap();
+
+        // The only test that touches the variable, so setting it cannot race another.
+        std::env::set_var(root::ROOT_ENV, &path);
+        let from_env = AttestationVerifier::from_env();
+        std::env::set_var(root::ROOT_ENV, "");
+        let unset = AttestationVerifier::from_env();
+        std::env::remove_var(root::ROOT_ENV);
+
+        assert_eq!(from_env.unwrap().roots()[0].der, staging.der().as_ref());
+        assert_eq!(unset.unwrap(), AttestationVerifier::embedded());
+    }
+
+    #[test]
+    fn verified_results_keep_their_json_shape() {
+        let attestation = VerifiedAttestation {
+            public_key: vec![4; 65],
+            receipt: b"receipt".to_vec(),
+            rp_id_hash: [0x11; 32],
+            environment: Environment::Development,
+            leaf_not_after: Some(1_771_000_000),
+            app_id: Some("TEAMID.com.example.app".into()),
+            timings: None,
+        };
+        crate::json::assert_golden(&attestation, include_str!("../../apps/fixtures/json/verified_attestation.json"));
+
+        let chain = VerifiedChain {
+            public_key: vec![4; 65],
+            leaf_fingerprint: [0x22; 32],
+            auth_data_hash: [0x33; 32],
+            root: root::APPLE_ROOT_NAME.to_string(),
+            leaf_not_after: 1_771_000_000,
+        };
+        crate::json::assert_golden(&chain, include_str!("../../apps/fixtures/json/verified_chain.json"));
+    }
+
+    #[test]
+    fn fixed_size_json_fields_reject_other_lengths() {
+        let golden = include_str!("../../apps/fixtures/json/verified_chain.json");
+        let base64 = |bytes: &[u8]| general_purpose::STANDARD.encode(bytes);
+        let json = golden.replace(&base64(&[0x22; 32]), &base64(&[0x22; 2]));
+        let err = serde_json::from_str::<VerifiedChain>(&json).unwrap_err();
+        assert!(err.to_string().contains("unexpected length 2"), "{err}");
+    }
+}
@@ -0,0 +1,366 @@
+//! An append-only record of verification decisions, for compliance.
+//!
+//! [`crate::attestation::AttestationVerifier`], [`crate::assertion::AssertionVerifier`] and,
+//! with the `store` feature, `service::AppAttestService` hand an [`AuditEvent`] to their
+//! [`AuditSink`] for every decision, once one is set with `with_audit`. [`JsonlAuditSink`]
+//! appends them to a file, one JSON object per line.
+//!
+//! Events never carry the material they were decided on, only its SHA-256: a challenge or a
+//! signature in an audit log is one more copy to protect. The JSON shape is pinned by
+//! `apps/fixtures/json/audit_event.json`, and [`AUDIT_SCHEMA_VERSION`] changes with it.
+
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::AppAttestError;
+use crate::key::KeyId;
+
+/// The `schemaVersion` of the events this build writes.
+pub const AUDIT_SCHEMA_VERSION: u32 = 1;
+
+/// Receives audit events. Implementations should not block for long: verifiers call it
+/// inline, after deciding and before returning.
+pub trait AuditSink: Send + Sync {
+    fn record(&self, event: &AuditEvent);
+}
+
+/// What was decided.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditKind {
+    /// `AttestationVerifier::verify_raw`.
+    Attestation,
+    /// `AttestationVerifier::verify_chain`.
+    AttestationChain,
+    Assertion,
+    /// `AppAttestService::register`, storing a key that passed attestation.
+    Registration,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Decision {
+    Accepted,
+    Rejected,
+}
+
+/// Why something was rejected, as [`AppAttestError::kind`] and [`AppAttestError::code`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditReason {
+    pub kind: String,
+    pub code: u16,
+}
+
+/// The SHA-256 of one input of a decision, e.g. `authData` or `challenge`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputDigest {
+    pub input: String,
+    #[serde(with = "crate::json::bytes")]
+    pub sha256: [u8; 32],
+}
+
+/// One decision. Fields that do not apply, or that the input did not yield, are `null`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEvent {
+    pub schema_version: u32,
+    pub kind: AuditKind,
+    pub decision: Decision,
+    /// `null` when accepted.
+    pub reason: Option<AuditReason>,
+    /// Unix time in milliseconds the decision was made, by this machine's clock.
+    pub decided_at_ms: u64,
+    /// Unix time the certificates were checked at, as the caller passed it; attestations only.
+    pub verification_time: Option<i64>,
+    pub key_id: Option<KeyId>,
+    /// The counter the assertion claims, or the one a service accepted.
+    pub counter: Option<u32>,
+    pub digests: Vec<InputDigest>,
+    /// Version of appattest-rs, whose checks made the decision, as `major.minor.patch`.
+    pub policy_version: String,
+}
+
+impl AuditEvent {
+    /// An accepted event of `kind`, with nothing else known yet.
+    pub fn new(kind: AuditKind) -> Self {
+        let (major, minor, patch) = crate::VERSION;
+        AuditEvent {
+            schema_version: AUDIT_SCHEMA_VERSION,
+            kind,
+            decision: Decision::Accepted,
+            reason: None,
+            decided_at_ms: 0,
+            verification_time: None,
+            key_id: None,
+            counter: None,
+            digests: Vec::new(),
+            policy_version: format!("{major}.{minor}.{patch}"),
+        }
+    }
+
+    pub fn verification_time(mut self, time: i64) -> Self {
+        self.verification_time = Some(time);
+        self
+    }
+
+    pub fn key_id(mut self, key_id: Option<KeyId>) -> Self {
+        self.key_id = key_id;
+        self
+    }
+
+    pub fn counter(mut self, counter: Option<u32>) -> Self {
+        self.counter = counter;
+        self
+    }
+
+    /// Adds the SHA-256 of `bytes` as `input`.
+    pub fn digest(mut self, input: &str, bytes: &[u8]) -> Self {
+        self.digests.push(InputDigest { input: input.to_string(), sha256: Sha256::digest(bytes).into() });
+        self
+    }
+
+    /// Sets the decision from `result`, and the time to now.
+    pub fn decided<T>(mut self, result: &Result<T, AppAttestError>) -> Self {
+        (self.decision, self.reason) = match result {
+            Ok(_) => (Decision::Accepted, None),
+            Err(err) => (Decision::Rejected, Some(AuditReason { kind: err.kind().to_string(), code: err.code() })),
+        };
+        self.decided_at_ms =
+            SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_millis() as u64);
+        self
+    }
+}
+
+/// The sink of a verifier, if it has one. Like [`crate::metrics::Sink`], verifiers compare
+/// equal whatever their sinks.
+#[derive(Clone, Default)]
+pub(crate) struct Audit(pub(crate) Option<Arc<dyn AuditSink>>);
+
+impl fmt::Debug for Audit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(if self.0.is_some() { "AuditSink" } else { "None" })
+    }
+}
+
+impl PartialEq for Audit {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Audit {
+    /// The event `start` builds, or `None` without a sink, so that nothing is hashed then.
+    pub(crate) fn start(&self, start: impl FnOnce() -> AuditEvent) -> Option<AuditEvent> {
+        self.0.as_ref().map(|_| start())
+    }
+
+    /// Records `event` with the decision `result`.
+    pub(crate) fn finish<T>(&self, event: Option<AuditEvent>, result: &Result<T, AppAttestError>) {
+        if let (Some(sink), Some(event)) = (&self.0, event) {
+            sink.record(&event.decided(result));
+        }
+    }
+}
+
+/// Appends events to a file as JSON lines, through a buffer.
+///
+/// Once the file holds `max_bytes` it is flushed, synced and renamed to `<path>.<n>`, `n`
+/// being the lowest number not yet taken, and a new file is started, so rotated files are
+/// never overwritten. [`JsonlAuditSink::flush`] syncs on demand, and dropping the sink
+/// flushes the buffer. A failed write is lost, and reported as a `tracing` event with the
+/// `tracing` feature.
+pub struct JsonlAuditSink {
+    path: PathBuf,
+    max_bytes: u64,
+    file: Mutex<JsonlFile>,
+}
+
+struct JsonlFile {
+    writer: BufWriter<File>,
+    len: u64,
+}
+
+impl JsonlAuditSink {
+    /// Appends to `path`, creating it if needed.
+    pub fn open(path: impl Into<PathBuf>, max_bytes: u64) -> io::Result<Self> {
+        let path = path.into();
+        let file = JsonlFile::open(&path)?;
+        Ok(JsonlAuditSink { path, max_bytes, file: Mutex::new(file) })
+    }
+
+    /// The file being written.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes out the buffer and syncs the file.
+    pub fn flush(&self) -> io::Result<()> {
+        let mut file = self.file.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        file.writer.flush()?;
+        file.writer.get_ref().sync_data()
+    }
+
+    fn append(&self, line: &[u8]) -> io::Result<()> {
+        let mut file = self.file.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        file.writer.write_all(line)?;
+        file.len += line.len() as u64;
+        if file.len >= self.max_bytes {
+            file.writer.flush()?;
+            file.writer.get_ref().sync_all()?;
+            fs::rename(&self.path, self.rotated_path())?;
+            *file = JsonlFile::open(&self.path)?;
+        }
+        Ok(())
+    }
+
+    fn rotated_path(&self) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(".");
+        (1u32..)
+            .map(|n| {
+                let mut numbered = path.clone();
+                numbered.push(n.to_string());
+                PathBuf::from(numbered)
+            })
+            .find(|numbered| !numbered.exists())
+            .unwrap_or_default()
+    }
+}
+
+impl JsonlFile {
+    fn open(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let len = file.metadata()?.len();
+        Ok(JsonlFile { writer: BufWriter::new(file), len })
+    }
+}
+
+impl AuditSink for JsonlAuditSink {
+    fn record(&self, event: &AuditEvent) {
+        let written = serde_json::to_vec(event).map_err(io::Error::from).and_then(|mut line| {
+            line.push(b'\n');
+            self.append(&line)
+        });
+        #[cfg(feature = "tracing")]
+        if let Err(error) = &written {
+            tracing::warn!(path = %self.path.display(), %error, "failed to write an audit event");
+        }
+        #[cfg(not(feature = "tracing"))]
+        let _ = written;
+    }
+}
+
+impl Drop for JsonlAuditSink {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose, Engine};
+    use serde_json::Value;
+
+    use super::*;
+    use crate::attestation::{Attestation, AttestationVerifier};
+    use crate::json::assert_golden;
+
+    const APP_ID: &str = "LMRM26A744.xyz.elus.aegis.app-attester";
+    const CHALLENGE: &str = "ExYiYJ4jA3rGL9oQN8ihDP4seccnxwHSEU28u8aCQaU=";
+    const KEY_ID: &str = "HD8zVqwgesSBwPaYj5icRg1GI/b6SOoa4TqieTEBA0g=";
+    const RECORDED_AT: i64 = 1_751_800_000;
+
+    fn attestation() -> Attestation {
+        Attestation::from_base64(include_str!("../../apps/fixtures/attestation.b64").trim()).unwrap()
+    }
+
+    fn decoded(base64: &str) -> Vec<u8> {
+        general_purpose::STANDARD.decode(base64).unwrap()
+    }
+
+    fn lines(path: &Path) -> Vec<Value> {
+        fs::read_to_string(path).unwrap().lines().map(|line| serde_json::from_str(line).unwrap()).collect()
+    }
+
+    #[test]
+    fn verifier_writes_a_line_per_decision() {
+        let dir = tempfile::tempdir().unwrap();
+        let sink = Arc::new(JsonlAuditSink::open(dir.path().join("audit.jsonl"), u64::MAX).unwrap());
+        let verifier = AttestationVerifier::embedded().with_audit(sink.clone());
+        let challenge = decoded(CHALLENGE);
+        let app_id_hash: [u8; 32] = Sha256::digest(APP_ID).into();
+        let key_id = decoded(KEY_ID);
+
+        assert!(verifier.verify_raw(attestation(), &challenge, &app_id_hash, &key_id, RECORDED_AT).is_ok());
+        let other_challenge = [0; 32];
+        let result = verifier.verify_raw(attestation(), &other_challenge, &app_id_hash, &key_id, RECORDED_AT);
+        assert_eq!(result, Err(AppAttestError::InvalidNonce));
+        let other_app = Sha256::digest("TEAMID.com.example.other").into();
+        let result = verifier.verify_raw(attestation(), &challenge, &other_app, &key_id, RECORDED_AT);
+        assert_eq!(result, Err(AppAttestError::InvalidAppID));
+        sink.flush().unwrap();
+
+        let lines = lines(sink.path());
+        assert_eq!(lines.len(), 3);
+        let summary: Vec<_> = lines.iter().map(|line| (line["decision"].clone(), line["reason"].clone())).collect();
+        assert_eq!(
+            summary,
+            [
+                (Value::from("accepted"), Value::Null),
+                (Value::from("rejected"), serde_json::json!({"kind": "InvalidNonce", "code": 1001})),
+                (Value::from("rejected"), serde_json::json!({"kind": "InvalidAppID", "code": 1008})),
+            ]
+        );
+        for line in &lines {
+            assert_eq!(line["schemaVersion"], AUDIT_SCHEMA_VERSION);
+            assert_eq!(line["kind"], "attestation");
+            assert_eq!(line["keyId"], KEY_ID);
+            assert_eq!(line["verificationTime"], RECORDED_AT);
+            assert!(line["decidedAtMs"].as_u64().unwrap() > 0);
+        }
+        let challenge_digest = general_purpose::STANDARD.encode(Sha256::digest(&challenge));
+        assert_eq!(lines[0]["digests"][1], serde_json::json!({"input": "challenge", "sha256": challenge_digest}));
+
+        // The challenge itself is nowhere in the log.
+        let log = fs::read_to_string(sink.path()).unwrap();
+        assert!(!log.contains(CHALLENGE));
+    }
+
+    #[test]
+    fn rotates_without_overwriting() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let event = AuditEvent::new(AuditKind::Assertion).counter(Some(1)).decided(&Ok::<_, AppAttestError>(()));
+        let line_len = serde_json::to_vec(&event).unwrap().len() as u64 + 1;
+        let sink = JsonlAuditSink::open(&path, 2 * line_len).unwrap();
+
+        for _ in 0..5 {
+            sink.record(&event);
+        }
+        sink.flush().unwrap();
+        assert_eq!(lines(&dir.path().join("audit.jsonl.1")).len(), 2);
+        assert_eq!(lines(&dir.path().join("audit.jsonl.2")).len(), 2);
+        assert_eq!(lines(&path), [serde_json::to_value(&event).unwrap()]);
+    }
+
+    #[test]
+    fn events_keep_their_json_shape() {
+        let event = AuditEvent {
+            decided_at_ms: 1_751_800_000_123,
+            policy_version: "0.1.0".into(),
+            ..AuditEvent::new(AuditKind::Assertion)
+                .key_id(KeyId::from_base64(KEY_ID).ok())
+                .counter(Some(2))
+                .digest("clientData", b"client data")
+                .decided(&Err::<(), _>(AppAttestError::InvalidCounter))
+        };
+        assert_golden(&event, include_str!("../../apps/fixtures/json/audit_event.json"));
+    }
+}
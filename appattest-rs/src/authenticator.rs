@@ -1,16 +1,19 @@
 use byteorder::{BigEndian, ByteOrder};
 use sha2::{Sha256, Digest};
 use std::error::Error;
+use crate::cose::CoseKey;
 use crate::error::AppAttestError;
 
 pub(crate) struct AuthenticatorData {
     pub(crate) bytes: Vec<u8>,
-    pub(crate) rp_id_hash: Vec<u8>,
+    pub(crate) rp_id_hash: [u8; 32],
     #[allow(unused)]
     pub(crate) flags: u8,
     pub(crate) counter: u32,
     pub(crate) aaguid: Option<AAGUID>, 
     pub(crate) credential_id: Option<Vec<u8>>,
+    /// The credential public key after the credential ID, in attestations.
+    pub(crate) credential_public_key: Option<CoseKey>,
 }
 
 impl AuthenticatorData {
@@ -20,12 +23,13 @@ impl AuthenticatorData {
         }
 
         let mut auth_data = AuthenticatorData {
-            bytes: auth_data_byte.clone(),
-            rp_id_hash: auth_data_byte[0..32].to_vec(),
+            rp_id_hash: auth_data_byte[0..32].try_into().unwrap(),
             flags: auth_data_byte[32],
             counter: BigEndian::read_u32(&auth_data_byte[33..37]),
+            bytes: auth_data_byte,
             aaguid: None,
             credential_id: None,
+            credential_public_key: None,
         };
 
         auth_data.populate_optional_data().map_err(|e| AppAttestError::Message(e.to_string()))?;
@@ -38,8 +42,13 @@ impl AuthenticatorData {
         }
 
         let length = BigEndian::read_u16(&self.bytes[53..55]) as usize;
-        let credential_id = self.bytes[55..55 + length].to_vec();
-        let aaguid = AAGUID::new(self.bytes[37..53].to_vec())?;
+        let credential_id = self.bytes.get(55..55 + length).ok_or("credential ID is truncated")?.to_vec();
+        let aaguid = AAGUID::new(&self.bytes[37..53])?;
+        let key = &self.bytes[55 + length..];
+        if !key.is_empty() {
+            // Extensions may follow the key.
+            self.credential_public_key = Some(CoseKey::from_cbor_prefix(key)?.0);
+        }
 
         self.credential_id = Some(credential_id);
         self.aaguid = Some(aaguid);
@@ -72,14 +81,18 @@ impl AuthenticatorData {
     pub(crate) fn verify_app_id(&self, app_id: &str) -> Result<(), AppAttestError> {
         let mut hasher = Sha256::new();
         hasher.update(app_id.as_bytes());
-        if self.rp_id_hash != hasher.finalize().as_slice() {
+        self.verify_app_id_hash(hasher.finalize().as_slice())
+    }
+
+    pub(crate) fn verify_app_id_hash(&self, app_id_hash: &[u8]) -> Result<(), AppAttestError> {
+        if self.rp_id_hash.as_slice() != app_id_hash {
             Err(AppAttestError::InvalidAppID)
         } else {
             Ok(())
         }
     }
 
-    pub(crate) fn verify_key_id(&self, key_id: &Vec<u8>) -> Result<(), AppAttestError> {
+    pub(crate) fn verify_key_id(&self, key_id: &[u8]) -> Result<(), AppAttestError> {
         if let Some(credential_id) = &self.credential_id {
             if credential_id == key_id {
                 return Ok(());
@@ -95,17 +108,17 @@ const APP_ATTEST: &str = "appattest";
 const APP_ATTEST_DEVELOP: &str = "appattestdevelop";
 
 impl AAGUID {
-    fn new(b: Vec<u8>) -> Result<Self, AppAttestError> {
+    fn new(b: &[u8]) -> Result<Self, AppAttestError> {
         let ids: [&str; 2] = [APP_ATTEST, APP_ATTEST_DEVELOP];
         for &id in ids.iter() {
-            if id.as_bytes() == AAGUID::trim_trailing_zeros(b.as_slice()) {
+            if id.as_bytes() == AAGUID::trim_trailing_zeros(b) {
                 return Ok(AAGUID(id.to_string()));
             }
         }
         Err(AppAttestError::InvalidAAGUID)
     }
 
-    fn bytes(&self) -> Vec<u8> {
+    pub(crate) fn bytes(&self) -> Vec<u8> {
         self.0.as_bytes().to_vec()
     }
 
@@ -153,10 +166,23 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_auth_data_new_truncated_credential_id() {
+        let mut bytes = vec![0u8; 55];
+        bytes[37..46].copy_from_slice(APP_ATTEST.as_bytes());
+        BigEndian::write_u16(&mut bytes[53..55], 32);
+        assert!(AuthenticatorData::new(bytes.clone()).is_err());
+
+        bytes.extend([7; 32]);
+        let auth_data = AuthenticatorData::new(bytes).unwrap();
+        assert_eq!(auth_data.credential_id, Some(vec![7; 32]));
+        assert!(auth_data.credential_public_key.is_none());
+    }
+
     #[test]
     fn test_aaguid_new_valid() {
         let appattest_bytes = APP_ATTEST.as_bytes().to_vec();
-        let result = AAGUID::new(appattest_bytes);
+        let result = AAGUID::new(&appattest_bytes);
         assert!(result.is_ok());
         assert_eq!(result.unwrap().0, APP_ATTEST);
     }
@@ -164,7 +190,7 @@ mod tests {
     #[test]
     fn test_aaguid_new_invalid() {
         let invalid_bytes = vec![0u8; 16]; // use dummy bytes that does not match any known AAGUID
-        let result = AAGUID::new(invalid_bytes);
+        let result = AAGUID::new(&invalid_bytes);
         assert!(result.is_err());
     }
 
@@ -173,7 +199,7 @@ mod tests {
         let app_id = "app.apple.connect";
         let mut hasher = Sha256::new();
         hasher.update(app_id.as_bytes());
-        let hash = hasher.finalize().to_vec();
+        let hash = hasher.finalize().into();
 
         let auth_data = AuthenticatorData {
             bytes: vec![],
@@ -182,6 +208,7 @@ mod tests {
             counter: 0,
             aaguid: None,
             credential_id: None,
+            credential_public_key: None,
         };
 
         assert!(auth_data.verify_app_id("app.apple.connect").is_ok());
@@ -193,11 +220,12 @@ mod tests {
         let key_id = vec![1, 2, 3, 4];
         let auth_data = AuthenticatorData {
             bytes: vec![],
-            rp_id_hash: vec![],
+            rp_id_hash: [0; 32],
             flags: 0,
             counter: 0,
             aaguid: None,
             credential_id: Some(key_id.clone()),
+            credential_public_key: None,
         };
 
         assert!(auth_data.verify_key_id(&key_id).is_ok());
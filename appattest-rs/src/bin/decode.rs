@@ -0,0 +1,64 @@
+//! Prints what is inside an App Attest attestation or assertion, from base64 or raw CBOR on
+//! stdin or in a file. Nothing is verified; see `appattest_rs::decode`.
+//!
+//! ```text
+//! decode [--file <path>] [--json]
+//! ```
+//!
+//! Exits with 1 if the input is not an attestation or assertion, and 2 on a usage error.
+
+use std::io::{Read, Write};
+use std::process::ExitCode;
+
+use appattest_rs::decode::decode;
+
+const USAGE: &str = "usage: decode [--file <path>] [--json]
+
+Prints the fields of a base64 or CBOR attestation or assertion, read from <path> or stdin,
+without verifying it.";
+
+fn main() -> ExitCode {
+    let (mut file, mut json) = (None, false);
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--file" => match args.next() {
+                Some(path) => file = Some(path),
+                None => return usage("--file needs a path"),
+            },
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return ExitCode::SUCCESS;
+            }
+            other => return usage(&format!("unexpected argument {other:?}")),
+        }
+    }
+
+    let mut input = Vec::new();
+    let read = match &file {
+        Some(path) => std::fs::read(path).map(|bytes| input = bytes),
+        None => std::io::stdin().read_to_end(&mut input).map(drop),
+    };
+    if let Err(e) = read {
+        eprintln!("error: failed to read {}: {e}", file.as_deref().unwrap_or("stdin"));
+        return ExitCode::FAILURE;
+    }
+
+    let decoded = match decode(&input) {
+        Ok(decoded) => decoded,
+        Err(e) => {
+            eprintln!("error: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let output = if json { serde_json::to_string_pretty(&decoded).map(|json| json + "\n") } else { Ok(decoded.to_string()) };
+    // A closed pipe, e.g. into `head`, is not worth a panic.
+    let _ = std::io::stdout().lock().write_all(output.unwrap_or_default().as_bytes());
+    ExitCode::SUCCESS
+}
+
+fn usage(problem: &str) -> ExitCode {
+    eprintln!("error: {problem}\n\n{USAGE}");
+    ExitCode::from(2)
+}
@@ -0,0 +1,271 @@
+//! A small CBOR reader for the attestation and assertion objects, used in place of
+//! ciborium's generic deserializer when the `minimal-cbor` feature is enabled.
+//!
+//! It handles exactly what App Attest produces: definite-length maps with text keys whose
+//! values are byte strings, text strings, arrays of byte strings and nested maps, and the
+//! integer-keyed COSE key in the authenticator data, see [`crate::cose`]. Values under keys
+//! the caller does not ask for are skipped. Indefinite lengths, reserved
+//! additional info and truncated input are errors, never panics.
+//!
+//! Without the feature, [`from_base64`] feeds ciborium straight from the base64 text, so
+//! the decoded object is never held in full next to the text and the parsed value.
+
+use std::cell::RefCell;
+use std::io;
+
+use base64::engine::general_purpose;
+use base64::read::DecoderReader;
+use base64::DecodeError;
+use serde::de::DeserializeOwned;
+
+use crate::error::AppAttestError;
+use crate::sensitive::base64_problem;
+
+/// Size of ciborium's scratch buffer, the one `ciborium::from_reader` puts on the stack.
+const SCRATCH_LEN: usize = 4096;
+
+thread_local! {
+    /// ciborium's scratch buffer for [`from_base64`], reused by each call on the thread.
+    static SCRATCH: RefCell<Vec<u8>> = RefCell::new(vec![0; SCRATCH_LEN]);
+}
+
+/// Deserializes the CBOR item in the standard base64 `text`, decoding the base64 as ciborium
+/// reads it. Invalid base64 fails as `STANDARD.decode` does, even after the item, and
+/// invalid CBOR with `Message(unparsable)`.
+pub(crate) fn from_base64<T: DeserializeOwned>(text: &str, unparsable: &str) -> Result<T, AppAttestError> {
+    let mut reader = DecoderReader::new(text.as_bytes(), &general_purpose::STANDARD);
+    let parsed = SCRATCH.with(|scratch| match scratch.try_borrow_mut() {
+        Ok(mut scratch) => ciborium::de::from_reader_with_buffer(&mut reader, &mut scratch[..]),
+        Err(_) => ciborium::de::from_reader_with_buffer(&mut reader, &mut [0; SCRATCH_LEN]),
+    });
+    let base64_error = |e: io::Error| {
+        let problem = match e.get_ref().and_then(|inner| inner.downcast_ref::<DecodeError>()) {
+            Some(e) => base64_problem(e),
+            None => e.kind().to_string(),
+        };
+        AppAttestError::Message(format!("Failed to decode Base64: {problem}"))
+    };
+    let value = match parsed {
+        Ok(value) => value,
+        Err(ciborium::de::Error::Io(e)) => return Err(base64_error(e)),
+        Err(_) => return Err(AppAttestError::Message(unparsable.to_string())),
+    };
+    // Whatever follows the item is ignored, as by `from_cbor`, but must still be base64.
+    io::copy(&mut reader, &mut io::sink()).map_err(base64_error)?;
+    Ok(value)
+}
+
+/// Deepest nesting `skip` follows before giving up.
+const MAX_DEPTH: usize = 16;
+
+const UNSIGNED: u8 = 0;
+const NEGATIVE: u8 = 1;
+const BYTES: u8 = 2;
+pub(crate) const TEXT: u8 = 3;
+const ARRAY: u8 = 4;
+const MAP: u8 = 5;
+const TAG: u8 = 6;
+const SIMPLE: u8 = 7;
+
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+fn malformed(what: &str) -> AppAttestError {
+    AppAttestError::Message(format!("malformed CBOR: {}", what))
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Reader { bytes, pos: 0 }
+    }
+
+    /// How many bytes have been read.
+    pub(crate) fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// The major type of the next item, without reading it.
+    pub(crate) fn peek_major(&self) -> Result<u8, AppAttestError> {
+        self.bytes.get(self.pos).map(|initial| initial >> 5).ok_or_else(|| malformed("unexpected end of input"))
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], AppAttestError> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| malformed("unexpected end of input"))?;
+        let out = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(out)
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N], AppAttestError> {
+        let mut out = [0; N];
+        out.copy_from_slice(self.take(N)?);
+        Ok(out)
+    }
+
+    /// Reads an item header and returns its major type and argument.
+    fn header(&mut self) -> Result<(u8, u64), AppAttestError> {
+        let initial = self.take(1)?[0];
+        let major = initial >> 5;
+        let argument = match initial & 0x1f {
+            info @ 0..=23 => info as u64,
+            24 => self.take(1)?[0] as u64,
+            25 => u16::from_be_bytes(self.take_array()?) as u64,
+            26 => u32::from_be_bytes(self.take_array()?) as u64,
+            27 => u64::from_be_bytes(self.take_array()?),
+            31 => return Err(malformed("indefinite lengths are not supported")),
+            _ => return Err(malformed("reserved additional information")),
+        };
+        Ok((major, argument))
+    }
+
+    fn length(&mut self, major: u8, what: &str) -> Result<usize, AppAttestError> {
+        match self.header()? {
+            (found, len) if found == major => usize::try_from(len).map_err(|_| malformed("length overflows")),
+            _ => Err(malformed(&format!("expected {}", what))),
+        }
+    }
+
+    pub(crate) fn map(&mut self) -> Result<usize, AppAttestError> {
+        self.length(MAP, "a map")
+    }
+
+    pub(crate) fn array(&mut self) -> Result<usize, AppAttestError> {
+        self.length(ARRAY, "an array")
+    }
+
+    /// Reads an unsigned or negative integer.
+    pub(crate) fn integer(&mut self) -> Result<i64, AppAttestError> {
+        let overflow = || malformed("integer overflows");
+        match self.header()? {
+            (UNSIGNED, value) => i64::try_from(value).map_err(|_| overflow()),
+            (NEGATIVE, value) => i64::try_from(value).map(|value| -1 - value).map_err(|_| overflow()),
+            _ => Err(malformed("expected an integer")),
+        }
+    }
+
+    pub(crate) fn bytes(&mut self) -> Result<&'a [u8], AppAttestError> {
+        let len = self.length(BYTES, "a byte string")?;
+        self.take(len)
+    }
+
+    pub(crate) fn text(&mut self) -> Result<&'a str, AppAttestError> {
+        let len = self.length(TEXT, "a text string")?;
+        std::str::from_utf8(self.take(len)?).map_err(|_| malformed("text is not UTF-8"))
+    }
+
+    /// Reads an array of byte strings, such as the `x5c` certificate chain, borrowed from the
+    /// input.
+    pub(crate) fn byte_strings(&mut self) -> Result<Vec<&'a [u8]>, AppAttestError> {
+        let len = self.array()?;
+        // Every element takes at least one byte, which bounds the allocation.
+        let mut out = Vec::with_capacity(len.min(self.bytes.len() - self.pos));
+        for _ in 0..len {
+            out.push(self.bytes()?);
+        }
+        Ok(out)
+    }
+
+    /// Skips one item of any type.
+    pub(crate) fn skip(&mut self) -> Result<(), AppAttestError> {
+        self.skip_nested(0)
+    }
+
+    fn skip_nested(&mut self, depth: usize) -> Result<(), AppAttestError> {
+        if depth > MAX_DEPTH {
+            return Err(malformed("nested too deeply"));
+        }
+        let (major, argument) = self.header()?;
+        let argument = usize::try_from(argument).map_err(|_| malformed("length overflows"))?;
+        match major {
+            UNSIGNED | NEGATIVE | SIMPLE => {}
+            BYTES | TEXT => {
+                self.take(argument)?;
+            }
+            ARRAY | MAP => {
+                let items = match major {
+                    MAP => argument.checked_mul(2).ok_or_else(|| malformed("length overflows"))?,
+                    _ => argument,
+                };
+                for _ in 0..items {
+                    self.skip_nested(depth + 1)?;
+                }
+            }
+            TAG => self.skip_nested(depth + 1)?,
+            _ => unreachable!("major type is three bits"),
+        }
+        Ok(())
+    }
+}
+
+/// Stores a map value, rejecting a key that appears twice.
+pub(crate) fn set_once<T>(slot: &mut Option<T>, value: T, key: &str) -> Result<(), AppAttestError> {
+    if slot.replace(value).is_some() {
+        return Err(malformed(&format!("duplicate field `{}`", key)));
+    }
+    Ok(())
+}
+
+/// Takes a map value that must have been present.
+pub(crate) fn required<T>(slot: Option<T>, key: &str) -> Result<T, AppAttestError> {
+    slot.ok_or_else(|| malformed(&format!("missing field `{}`", key)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reads_definite_items() {
+        // {"a": h'0102', "b": [h'03'], "c": "x"}
+        let bytes = [0xa3, 0x61, b'a', 0x42, 1, 2, 0x61, b'b', 0x81, 0x41, 3, 0x61, b'c', 0x61, b'x'];
+        let mut reader = Reader::new(&bytes);
+        assert_eq!(reader.map().unwrap(), 3);
+        assert_eq!(reader.text().unwrap(), "a");
+        assert_eq!(reader.bytes().unwrap(), &[1, 2]);
+        assert_eq!(reader.text().unwrap(), "b");
+        assert_eq!(reader.byte_strings().unwrap(), vec![&[3][..]]);
+        assert_eq!(reader.text().unwrap(), "c");
+        reader.skip().unwrap();
+        assert!(reader.skip().is_err());
+    }
+
+    #[test]
+    fn test_reads_integers() {
+        // 2, -7, -1 with a needlessly long argument, and 2^63, which does not fit.
+        let bytes = [0x02, 0x26, 0x38, 0x00, 0x1b, 0x80, 0, 0, 0, 0, 0, 0, 0];
+        let mut reader = Reader::new(&bytes);
+        assert_eq!(reader.integer().unwrap(), 2);
+        assert_eq!(reader.integer().unwrap(), -7);
+        assert_eq!(reader.position(), 2);
+        assert_eq!(reader.integer().unwrap(), -1);
+        assert!(reader.integer().is_err());
+        assert!(Reader::new(&[0x41, 0]).integer().is_err());
+    }
+
+    #[test]
+    fn test_rejects_malformed_input() {
+        for bytes in [
+            &[0xbf, 0xff][..],                                       // indefinite map
+            &[0x5f, 0x41, 0, 0xff],                                  // indefinite byte string
+            &[0x5b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff], // length past the input
+            &[0x9b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff], // array longer than the input
+            &[0x1c],                                                 // reserved additional info
+            &[0x62, 0xff, 0xff],                                     // text that is not UTF-8
+        ] {
+            let mut reader = Reader::new(bytes);
+            let result = match bytes[0] >> 5 {
+                ARRAY => reader.byte_strings().map(drop),
+                TEXT => reader.text().map(drop),
+                _ => reader.skip(),
+            };
+            assert!(result.is_err(), "{:02x?}", bytes);
+        }
+        assert!(Reader::new(&[0x81; 64]).skip().is_err());
+    }
+}
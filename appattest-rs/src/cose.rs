@@ -0,0 +1,215 @@
+//! COSE keys (RFC 9052, section 7), the form of the credential public key in the attested
+//! credential data of App Attest and WebAuthn authenticator data.
+//!
+//! Only what those flows use is supported: EC2 keys on P-256, optionally marked for ES256.
+
+use std::{error::Error, fmt};
+
+use crate::cbor::{self, Reader};
+use crate::error::AppAttestError;
+use crate::key::PublicKey;
+
+/// `kty` of elliptic curve keys with `x` and `y` coordinates.
+pub const KTY_EC2: i64 = 2;
+/// `crv` of P-256.
+pub const CRV_P256: i64 = 1;
+/// `alg` of ECDSA with SHA-256.
+pub const ALG_ES256: i64 = -7;
+
+const LABEL_KTY: i64 = 1;
+const LABEL_ALG: i64 = 3;
+const LABEL_CRV: i64 = -1;
+const LABEL_X: i64 = -2;
+const LABEL_Y: i64 = -3;
+
+/// Why bytes are not a supported COSE key.
+#[derive(Debug, PartialEq)]
+pub enum CoseError {
+    /// Not a CBOR map of COSE key parameters, or bytes follow it.
+    Malformed(String),
+    /// A required parameter, named by its label, is missing.
+    Missing(&'static str),
+    /// A `kty` other than EC2.
+    UnsupportedKeyType(i64),
+    /// A `crv` other than P-256.
+    UnsupportedCurve(i64),
+    /// An `alg` other than ES256.
+    UnsupportedAlgorithm(i64),
+    /// The coordinates are not a point on P-256.
+    InvalidPoint,
+}
+
+impl fmt::Display for CoseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CoseError::Malformed(e) => write!(f, "malformed COSE key: {e}"),
+            CoseError::Missing(label) => write!(f, "COSE key lacks `{label}`"),
+            CoseError::UnsupportedKeyType(kty) => write!(f, "unsupported COSE key type {kty}"),
+            CoseError::UnsupportedCurve(crv) => write!(f, "unsupported COSE curve {crv}"),
+            CoseError::UnsupportedAlgorithm(alg) => write!(f, "unsupported COSE algorithm {alg}"),
+            CoseError::InvalidPoint => write!(f, "COSE key is not a point on P-256"),
+        }
+    }
+}
+
+impl Error for CoseError {}
+
+impl From<CoseError> for AppAttestError {
+    fn from(error: CoseError) -> Self {
+        AppAttestError::Message(error.to_string())
+    }
+}
+
+impl From<AppAttestError> for CoseError {
+    fn from(error: AppAttestError) -> Self {
+        CoseError::Malformed(error.to_string())
+    }
+}
+
+/// An EC2 P-256 COSE key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CoseKey {
+    alg: Option<i64>,
+    key: PublicKey,
+}
+
+impl CoseKey {
+    /// Parses `bytes`, which must hold exactly one COSE key. Parameters may come in any order;
+    /// ones this crate does not use, such as `kid`, are skipped.
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self, CoseError> {
+        let (key, len) = Self::from_cbor_prefix(bytes)?;
+        if len != bytes.len() {
+            return Err(CoseError::Malformed(format!("{} bytes after the key", bytes.len() - len)));
+        }
+        Ok(key)
+    }
+
+    /// Parses the COSE key at the start of `bytes` and returns it with its length, for
+    /// authenticator data, where extensions may follow it.
+    pub(crate) fn from_cbor_prefix(bytes: &[u8]) -> Result<(Self, usize), CoseError> {
+        let mut reader = Reader::new(bytes);
+        let (mut kty, mut alg, mut crv, mut x, mut y) = (None, None, None, None, None);
+        for _ in 0..reader.map()? {
+            if reader.peek_major()? == cbor::TEXT {
+                reader.text()?;
+                reader.skip()?;
+                continue;
+            }
+            match reader.integer()? {
+                LABEL_KTY => cbor::set_once(&mut kty, reader.integer()?, "kty")?,
+                LABEL_ALG => cbor::set_once(&mut alg, reader.integer()?, "alg")?,
+                LABEL_CRV => cbor::set_once(&mut crv, reader.integer()?, "crv")?,
+                LABEL_X => cbor::set_once(&mut x, reader.bytes()?, "x")?,
+                LABEL_Y => cbor::set_once(&mut y, reader.bytes()?, "y")?,
+                _ => reader.skip()?,
+            }
+        }
+
+        match kty.ok_or(CoseError::Missing("kty"))? {
+            KTY_EC2 => {}
+            kty => return Err(CoseError::UnsupportedKeyType(kty)),
+        }
+        match alg {
+            None | Some(ALG_ES256) => {}
+            Some(alg) => return Err(CoseError::UnsupportedAlgorithm(alg)),
+        }
+        match crv.ok_or(CoseError::Missing("crv"))? {
+            CRV_P256 => {}
+            crv => return Err(CoseError::UnsupportedCurve(crv)),
+        }
+        let coordinate = |value: &[u8]| <&[u8; 32]>::try_from(value).map_err(|_| CoseError::InvalidPoint);
+        let x = coordinate(x.ok_or(CoseError::Missing("x"))?)?;
+        let y = coordinate(y.ok_or(CoseError::Missing("y"))?)?;
+        let key = PublicKey::from_coordinates(x, y).map_err(|_| CoseError::InvalidPoint)?;
+        Ok((CoseKey { alg, key }, reader.position()))
+    }
+
+    /// The `alg` parameter, if the key has one; always [`ALG_ES256`].
+    pub fn alg(&self) -> Option<i64> {
+        self.alg
+    }
+
+    pub fn public_key(&self) -> &PublicKey {
+        &self.key
+    }
+}
+
+impl From<CoseKey> for p256::PublicKey {
+    fn from(key: CoseKey) -> Self {
+        key.key.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The credential public key of `apps/fixtures/attestation.b64`:
+    /// `{1: 2, 3: -7, -1: 1, -2: x, -3: y}`.
+    const FIXTURE: &str = "a501020326200121582085e322caa0f981bc18cd53574f1dc761c139d15030a36b1b249cc158c822c450\
+                           2258206b1574b6b1c5b5bfaf8e2a1dfe3deaa87c8802d863c8aac23aa990560a8a56b4";
+
+    fn hex(hex: &str) -> Vec<u8> {
+        (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap()).collect()
+    }
+
+    /// A map of `entries`, each a label and its value already in CBOR.
+    fn map(entries: &[Vec<u8>]) -> Vec<u8> {
+        [vec![0xa0 | entries.len() as u8], entries.concat()].concat()
+    }
+
+    fn entries() -> [Vec<u8>; 5] {
+        let fixture = hex(FIXTURE);
+        [vec![0x01, 0x02], vec![0x03, 0x26], vec![0x20, 0x01], fixture[7..42].to_vec(), fixture[42..].to_vec()]
+    }
+
+    #[test]
+    fn parses_the_fixture_key() {
+        let key = CoseKey::from_cbor(&hex(FIXTURE)).unwrap();
+        assert_eq!(key.alg(), Some(ALG_ES256));
+        let expected = PublicKey::from_pem(include_str!("../../apps/fixtures/public_key.pem")).unwrap();
+        assert_eq!(key.public_key(), &expected);
+        assert_eq!(&p256::PublicKey::from(key), expected.as_p256());
+    }
+
+    #[test]
+    fn parameters_may_come_in_any_order() {
+        let [kty, alg, crv, x, y] = entries();
+        let reordered = map(&[y.clone(), x.clone(), crv.clone(), alg, kty.clone()]);
+        assert_eq!(CoseKey::from_cbor(&reordered), CoseKey::from_cbor(&hex(FIXTURE)));
+
+        // No `alg`, and a `kid` and a text label, which are skipped.
+        let kid = vec![0x02, 0x41, 0x07];
+        let text = vec![0x61, b'n', 0xf5];
+        let key = CoseKey::from_cbor(&map(&[kid, kty, text, crv, x, y])).unwrap();
+        assert_eq!(key.alg(), None);
+    }
+
+    #[test]
+    fn rejects_unsupported_keys() {
+        let [kty, alg, crv, x, y] = entries();
+        let with = |replaced: usize, entry: Vec<u8>| {
+            let mut entries = entries();
+            entries[replaced] = entry;
+            CoseKey::from_cbor(&map(&entries))
+        };
+        assert_eq!(with(0, vec![0x01, 0x03]), Err(CoseError::UnsupportedKeyType(3)));
+        assert_eq!(with(1, vec![0x03, 0x39, 0x01, 0x00]), Err(CoseError::UnsupportedAlgorithm(-257)));
+        assert_eq!(with(2, vec![0x20, 0x02]), Err(CoseError::UnsupportedCurve(2)));
+        let mut off_curve = y.clone();
+        off_curve[34] ^= 1;
+        assert_eq!(with(4, off_curve), Err(CoseError::InvalidPoint));
+        assert_eq!(
+            with(4, vec![0x22, 0xf5]),
+            Err(CoseError::Malformed("malformed CBOR: expected a byte string".into()))
+        );
+
+        assert_eq!(CoseKey::from_cbor(&map(&[kty.clone(), alg, crv.clone(), x.clone()])), Err(CoseError::Missing("y")));
+        assert!(matches!(
+            CoseKey::from_cbor(&map(&[kty.clone(), kty, crv, x, y])),
+            Err(CoseError::Malformed(e)) if e.contains("duplicate")
+        ));
+        assert!(matches!(CoseKey::from_cbor(&[hex(FIXTURE), vec![0]].concat()), Err(CoseError::Malformed(_))));
+        assert!(matches!(CoseKey::from_cbor(&hex(FIXTURE)[..40]), Err(CoseError::Malformed(_))));
+    }
+}
@@ -0,0 +1,324 @@
+//! Dumps of attestation and assertion objects for people, e.g. support engineers looking at
+//! a blob attached to a ticket; the `decode` binary prints them.
+//!
+//! Nothing here verifies anything: a dump says what an object claims, not whether Apple
+//! made it or whether it is meant for your app. It reads the CBOR generically, so that
+//! objects the verifiers reject, with an unknown AAGUID say, still dump as far as they go.
+
+use std::fmt;
+use std::io::Cursor;
+
+use base64::{engine::general_purpose, Engine};
+use ciborium::Value;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use x509_parser::prelude::parse_x509_certificate;
+
+use crate::attestation::Environment;
+use crate::cose::CoseKey;
+use crate::error::AppAttestError;
+use crate::sensitive::base64_problem;
+
+/// What [`decode`] found. In JSON, tagged with `"type": "attestation"` or `"assertion"`.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Decoded {
+    Attestation(AttestationDump),
+    Assertion(AssertionDump),
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttestationDump {
+    /// The statement format, `apple-appattest` for App Attest.
+    pub fmt: Option<String>,
+    /// The `x5c` chain, leaf first.
+    pub certificates: Vec<CertificateDump>,
+    pub auth_data: AuthDataDump,
+    pub receipt_len: Option<usize>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssertionDump {
+    pub signature_len: Option<usize>,
+    pub auth_data: AuthDataDump,
+}
+
+/// A certificate in an attestation's chain. Everything but the length and fingerprint is
+/// `None` if the DER does not parse, and `error` says why.
+#[derive(Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CertificateDump {
+    pub len: usize,
+    /// Hex SHA-256 of the DER.
+    pub sha256: String,
+    pub subject: Option<String>,
+    pub issuer: Option<String>,
+    /// Hex serial number.
+    pub serial: Option<String>,
+    /// Unix time.
+    pub not_before: Option<i64>,
+    /// Unix time.
+    pub not_after: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Authenticator data, as far as it is long enough; `error` says where it stopped.
+#[derive(Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthDataDump {
+    pub len: usize,
+    /// Hex SHA-256 of the app ID the object claims to be for.
+    pub rp_id_hash: Option<String>,
+    pub flags: Option<u8>,
+    pub counter: Option<u32>,
+    /// The AAGUID as text if it is printable, e.g. `appattestdevelop`, otherwise hex.
+    pub aaguid: Option<String>,
+    /// The environment the AAGUID names, if it names one.
+    pub environment: Option<Environment>,
+    /// Base64 credential ID, which in App Attest is the key ID.
+    pub credential_id: Option<String>,
+    /// Hex uncompressed SEC1 credential public key.
+    pub credential_public_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Decodes the attestation or assertion in `input`, base64 text or raw CBOR, telling which it
+/// is from its keys. Fails only if `input` is not base64 or CBOR of a map with either's keys;
+/// a malformed part of one shows in the dump.
+pub fn decode(input: &[u8]) -> Result<Decoded, AppAttestError> {
+    let text: Option<Vec<u8>> = std::str::from_utf8(input)
+        .ok()
+        .map(|text| text.bytes().filter(|b| !b.is_ascii_whitespace()).collect())
+        .filter(|text: &Vec<u8>| !text.is_empty());
+    let cbor = match text.map(|text| general_purpose::STANDARD.decode(text)) {
+        Some(Ok(cbor)) => cbor,
+        Some(Err(e)) => return Err(AppAttestError::Message(format!("Failed to decode Base64: {}", base64_problem(&e)))),
+        None => input.to_vec(),
+    };
+    let value: Value =
+        ciborium::from_reader(Cursor::new(&cbor)).map_err(|e| AppAttestError::Message(format!("not CBOR: {e}")))?;
+    let Value::Map(entries) = value else {
+        return Err(AppAttestError::Message("not a CBOR map".into()));
+    };
+    let field = |name: &str| entries.iter().find(|(key, _)| key.as_text() == Some(name)).map(|(_, value)| value);
+    let bytes = |value: Option<&Value>| value.and_then(Value::as_bytes).map(Vec::as_slice);
+
+    if field("attStmt").is_some() || field("fmt").is_some() {
+        let statement = field("attStmt").and_then(Value::as_map);
+        let statement_field =
+            |name: &str| statement?.iter().find(|(key, _)| key.as_text() == Some(name)).map(|(_, value)| value);
+        let certificates = statement_field("x5c")
+            .and_then(Value::as_array)
+            .map(|chain| chain.iter().filter_map(Value::as_bytes).map(Vec::as_slice).map(certificate).collect())
+            .unwrap_or_default();
+        return Ok(Decoded::Attestation(AttestationDump {
+            fmt: field("fmt").and_then(Value::as_text).map(str::to_string),
+            certificates,
+            auth_data: auth_data(bytes(field("authData"))),
+            receipt_len: bytes(statement_field("receipt")).map(<[u8]>::len),
+        }));
+    }
+    if field("authenticatorData").is_some() || field("signature").is_some() {
+        return Ok(Decoded::Assertion(AssertionDump {
+            signature_len: bytes(field("signature")).map(<[u8]>::len),
+            auth_data: auth_data(bytes(field("authenticatorData"))),
+        }));
+    }
+    Err(AppAttestError::Message("neither an attestation nor an assertion".into()))
+}
+
+fn certificate(der: &[u8]) -> CertificateDump {
+    let mut dump =
+        CertificateDump { len: der.len(), sha256: hex::encode(Sha256::digest(der)), ..CertificateDump::default() };
+    match parse_x509_certificate(der) {
+        Ok((_, certificate)) => {
+            dump.subject = Some(certificate.subject().to_string());
+            dump.issuer = Some(certificate.issuer().to_string());
+            dump.serial = Some(hex::encode(certificate.raw_serial()));
+            dump.not_before = Some(certificate.validity().not_before.timestamp());
+            dump.not_after = Some(certificate.validity().not_after.timestamp());
+        }
+        Err(e) => dump.error = Some(e.to_string()),
+    }
+    dump
+}
+
+/// The layout is that of WebAuthn: `rpIdHash(32) flags(1) signCount(4)`, then, in attestations,
+/// `aaguid(16) credentialIdLength(2) credentialId credentialPublicKey`.
+fn auth_data(bytes: Option<&[u8]>) -> AuthDataDump {
+    let Some(bytes) = bytes else {
+        return AuthDataDump { error: Some("missing".into()), ..AuthDataDump::default() };
+    };
+    let mut dump = AuthDataDump { len: bytes.len(), ..AuthDataDump::default() };
+    let Some((header, rest)) = bytes.split_first_chunk::<37>() else {
+        dump.error = Some(format!("{} bytes, too short for the RP ID hash, flags and counter", bytes.len()));
+        return dump;
+    };
+    dump.rp_id_hash = Some(hex::encode(&header[..32]));
+    dump.flags = Some(header[32]);
+    dump.counter = Some(u32::from_be_bytes([header[33], header[34], header[35], header[36]]));
+    if rest.is_empty() {
+        return dump;
+    }
+
+    let Some((aaguid, rest)) = rest.split_first_chunk::<16>() else {
+        dump.error = Some("truncated in the AAGUID".into());
+        return dump;
+    };
+    let name = aaguid.iter().rposition(|&b| b != 0).map_or(&aaguid[..0], |last| &aaguid[..=last]);
+    dump.environment = match name {
+        b"appattest" => Some(Environment::Production),
+        b"appattestdevelop" => Some(Environment::Development),
+        _ => None,
+    };
+    dump.aaguid = Some(match std::str::from_utf8(name) {
+        Ok(text) if text.bytes().all(|b| b.is_ascii_graphic()) => text.to_string(),
+        _ => hex::encode(aaguid),
+    });
+
+    let Some((length, rest)) = rest.split_first_chunk::<2>() else {
+        dump.error = Some("truncated in the credential ID length".into());
+        return dump;
+    };
+    let length = u16::from_be_bytes(*length) as usize;
+    let Some((credential_id, key)) = rest.split_at_checked(length) else {
+        dump.error = Some(format!("credential ID of {length} bytes, but only {} follow", rest.len()));
+        return dump;
+    };
+    dump.credential_id = Some(general_purpose::STANDARD.encode(credential_id));
+    // Extensions may follow the key, so a prefix is enough.
+    match CoseKey::from_cbor_prefix(key) {
+        Ok((key, _)) => dump.credential_public_key = Some(hex::encode(key.public_key().to_uncompressed())),
+        Err(e) => dump.error = Some(e.to_string()),
+    }
+    dump
+}
+
+/// `YYYY-MM-DDTHH:MM:SSZ`, for the text dump and certificate validity errors.
+pub(crate) fn rfc3339(time: i64) -> String {
+    let (days, seconds) = (time.div_euclid(86_400), time.rem_euclid(86_400));
+    // Howard Hinnant's `civil_from_days`, the inverse of `receipt::days_from_civil`.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    let (h, m, s) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    format!("{year:04}-{month:02}-{day:02}T{h:02}:{m:02}:{s:02}Z")
+}
+
+fn line(f: &mut fmt::Formatter<'_>, indent: usize, key: &str, value: impl fmt::Display) -> fmt::Result {
+    writeln!(f, "{:indent$}{:<width$} {value}", "", format!("{key}:"), width = 24 - indent)
+}
+
+fn optional<T: fmt::Display>(value: &Option<T>) -> String {
+    value.as_ref().map_or("-".to_string(), T::to_string)
+}
+
+impl fmt::Display for AuthDataDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "authenticator data ({} bytes):", self.len)?;
+        line(f, 2, "rp id hash", optional(&self.rp_id_hash))?;
+        let flags = self.flags.map(|flags| {
+            let set: Vec<_> = [(0x01, "UP"), (0x04, "UV"), (0x40, "AT"), (0x80, "ED")]
+                .into_iter()
+                .filter(|(bit, _)| flags & bit != 0)
+                .map(|(_, name)| name)
+                .collect();
+            format!("{flags:#04x} [{}]", set.join(" "))
+        });
+        line(f, 2, "flags", optional(&flags))?;
+        line(f, 2, "counter", optional(&self.counter))?;
+        let environment = self.environment.map(|environment| match environment {
+            Environment::Production => "production",
+            Environment::Development => "development",
+        });
+        line(f, 2, "aaguid", format!("{} ({})", optional(&self.aaguid), environment.unwrap_or("unknown")))?;
+        line(f, 2, "credential id", optional(&self.credential_id))?;
+        line(f, 2, "credential public key", optional(&self.credential_public_key))?;
+        if let Some(error) = &self.error {
+            line(f, 2, "error", error)?;
+        }
+        Ok(())
+    }
+}
+
+/// The text form the `decode` binary prints.
+impl fmt::Display for Decoded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Decoded::Attestation(dump) => {
+                line(f, 0, "type", "attestation")?;
+                line(f, 0, "fmt", optional(&dump.fmt))?;
+                line(f, 0, "receipt length", optional(&dump.receipt_len))?;
+                writeln!(f, "certificates ({}):", dump.certificates.len())?;
+                for (index, certificate) in dump.certificates.iter().enumerate() {
+                    writeln!(f, "  [{index}] {} bytes", certificate.len)?;
+                    line(f, 4, "sha256", &certificate.sha256)?;
+                    line(f, 4, "subject", optional(&certificate.subject))?;
+                    line(f, 4, "issuer", optional(&certificate.issuer))?;
+                    line(f, 4, "serial", optional(&certificate.serial))?;
+                    line(f, 4, "not before", optional(&certificate.not_before.map(rfc3339)))?;
+                    line(f, 4, "not after", optional(&certificate.not_after.map(rfc3339)))?;
+                    if let Some(error) = &certificate.error {
+                        line(f, 4, "error", error)?;
+                    }
+                }
+                write!(f, "{}", dump.auth_data)
+            }
+            Decoded::Assertion(dump) => {
+                line(f, 0, "type", "assertion")?;
+                line(f, 0, "signature length", optional(&dump.signature_len))?;
+                write!(f, "{}", dump.auth_data)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_times() {
+        assert_eq!(rfc3339(0), "1970-01-01T00:00:00Z");
+        assert_eq!(rfc3339(1_751_657_942), "2025-07-04T19:39:02Z");
+        assert_eq!(rfc3339(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(rfc3339(-1), "1969-12-31T23:59:59Z");
+    }
+
+    #[test]
+    fn reads_auth_data_as_far_as_it_goes() {
+        assert_eq!(auth_data(Some(&[0; 36])).rp_id_hash, None);
+
+        let mut bytes = vec![0xab; 32];
+        bytes.extend([0x41, 0, 0, 0, 7]);
+        let dump = auth_data(Some(&bytes));
+        assert_eq!((dump.flags, dump.counter, dump.aaguid, dump.error), (Some(0x41), Some(7), None, None));
+
+        bytes.extend(b"appattestdevelop");
+        bytes.extend([0, 4, 1, 2]);
+        let dump = auth_data(Some(&bytes));
+        assert_eq!(dump.aaguid.as_deref(), Some("appattestdevelop"));
+        assert_eq!(dump.environment, Some(Environment::Development));
+        assert_eq!(dump.credential_id, None);
+        assert_eq!(dump.error.as_deref(), Some("credential ID of 4 bytes, but only 2 follow"));
+    }
+
+    #[test]
+    fn unknown_aaguids_are_hex() {
+        let mut bytes = vec![0; 37];
+        bytes.extend([0xff; 16]);
+        let dump = auth_data(Some(&bytes));
+        assert_eq!(dump.aaguid, Some("ff".repeat(16)));
+        assert_eq!(dump.environment, None);
+    }
+}
@@ -0,0 +1,128 @@
+//! Telling an attestation object from an assertion object without decoding either, for
+//! endpoints that receive both, e.g. a gateway taking one "proof of possession" field.
+//!
+//! [`classify`] reads the keys of the top-level CBOR map and skips their values. Each shape
+//! is closed: an attestation has exactly `fmt`, `attStmt` and `authData`, an assertion
+//! exactly `signature` and `authenticatorData`. Anything else, including a map with keys of
+//! both shapes, a key twice, bytes after the map or a truncated map, is
+//! [`BlobKind::Unknown`], so crafted input fails closed rather than being guessed at. The
+//! verifier a blob is then given still checks all of it.
+
+use crate::cbor::Reader;
+
+/// What [`classify`] took a blob for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlobKind {
+    /// An attestation object, sent once when the key is registered.
+    Attestation,
+    /// An assertion object, sent with each request after that.
+    Assertion,
+    /// Neither, or not CBOR.
+    Unknown,
+}
+
+const ATTESTATION_KEYS: [&str; 3] = ["attStmt", "authData", "fmt"];
+const ASSERTION_KEYS: [&str; 2] = ["authenticatorData", "signature"];
+
+/// Classifies the CBOR object `bytes` by the keys of its map, see the [module](self) docs.
+pub fn classify(bytes: &[u8]) -> BlobKind {
+    let Some(mut keys) = top_level_keys(bytes) else {
+        return BlobKind::Unknown;
+    };
+    keys.sort_unstable();
+    if keys == ATTESTATION_KEYS {
+        BlobKind::Attestation
+    } else if keys == ASSERTION_KEYS {
+        BlobKind::Assertion
+    } else {
+        BlobKind::Unknown
+    }
+}
+
+/// The text keys of the map `bytes` holds, or `None` unless it is one well-formed map of
+/// at most as many entries as an attestation has, with nothing after it.
+fn top_level_keys(bytes: &[u8]) -> Option<Vec<&str>> {
+    let mut reader = Reader::new(bytes);
+    let len = reader.map().ok()?;
+    if len > ATTESTATION_KEYS.len() {
+        return None;
+    }
+    let mut keys = Vec::with_capacity(len);
+    for _ in 0..len {
+        keys.push(reader.text().ok()?);
+        reader.skip().ok()?;
+    }
+    (reader.position() == bytes.len()).then_some(keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose, Engine};
+
+    use super::*;
+
+    fn decoded(base64: &str) -> Vec<u8> {
+        general_purpose::STANDARD.decode(base64.trim()).unwrap()
+    }
+
+    fn attestation() -> Vec<u8> {
+        decoded(include_str!("../../apps/fixtures/attestation.b64"))
+    }
+
+    fn assertion() -> Vec<u8> {
+        decoded(include_str!("../../apps/fixtures/assertion.b64"))
+    }
+
+    /// A map of `keys`, each with an empty byte string as its value.
+    fn map_of(keys: &[&str]) -> Vec<u8> {
+        let mut out = vec![0xa0 | keys.len() as u8];
+        for key in keys {
+            out.push(0x60 | key.len() as u8);
+            out.extend_from_slice(key.as_bytes());
+            out.push(0x40);
+        }
+        out
+    }
+
+    #[test]
+    fn recorded_objects_are_told_apart() {
+        assert_eq!(classify(&attestation()), BlobKind::Attestation);
+        assert_eq!(classify(&assertion()), BlobKind::Assertion);
+        assert_eq!(classify(&map_of(&["fmt", "attStmt", "authData"])), BlobKind::Attestation);
+        assert_eq!(classify(&map_of(&["signature", "authenticatorData"])), BlobKind::Assertion);
+    }
+
+    #[test]
+    fn truncated_objects_are_unknown() {
+        for object in [attestation(), assertion()] {
+            for len in [0, 1, object.len() / 2, object.len() - 1] {
+                assert_eq!(classify(&object[..len]), BlobKind::Unknown, "{len} bytes");
+            }
+        }
+    }
+
+    #[test]
+    fn objects_with_keys_of_both_shapes_are_unknown() {
+        assert_eq!(classify(&map_of(&["signature", "authenticatorData", "authData"])), BlobKind::Unknown);
+        assert_eq!(classify(&map_of(&["fmt", "attStmt", "authenticatorData"])), BlobKind::Unknown);
+        assert_eq!(classify(&map_of(&["signature", "authData"])), BlobKind::Unknown);
+        // One key of each shape per map, five in all, is more than either has.
+        let both = map_of(&["fmt", "attStmt", "authData", "signature", "authenticatorData"]);
+        assert_eq!(classify(&both), BlobKind::Unknown);
+    }
+
+    #[test]
+    fn other_near_misses_are_unknown() {
+        // A key twice in place of another.
+        assert_eq!(classify(&map_of(&["signature", "signature"])), BlobKind::Unknown);
+        assert_eq!(classify(&map_of(&["fmt", "authData", "authData"])), BlobKind::Unknown);
+        // A key missing, or one neither shape has.
+        assert_eq!(classify(&map_of(&["signature"])), BlobKind::Unknown);
+        assert_eq!(classify(&map_of(&["signature", "authenticatorData", "extra"])), BlobKind::Unknown);
+        // Bytes after the map.
+        assert_eq!(classify(&[assertion(), vec![0]].concat()), BlobKind::Unknown);
+        // Not a map, or a key that is not text.
+        assert_eq!(classify(&[0x80]), BlobKind::Unknown);
+        assert_eq!(classify(&[0xa1, 0x01, 0x40]), BlobKind::Unknown);
+    }
+}
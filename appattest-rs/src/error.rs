@@ -1,6 +1,12 @@
 use std::{error::Error, fmt};
 
-#[derive(Debug, PartialEq)]
+use serde::{Deserialize, Serialize};
+
+/// In JSON, `{"kind": "<variant>"}`, with a `"detail"` string for the variants that carry
+/// one; see [`crate::json`].
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(tag = "kind", content = "detail")]
 pub enum AppAttestError {
     InvalidNonce,
     InvalidAppIDHash,
@@ -14,7 +20,66 @@ pub enum AppAttestError {
     ExpectedASN1Node,
     FailedToExtractValueFromASN1Node,
     ExpectedOctetStringInsideASN1Node,
-    
+    UnknownChallenge,
+    UnknownKeyID,
+    /// The Android key attestation extension is missing or malformed.
+    InvalidKeyDescription,
+    /// The Android key attestation certifies another challenge.
+    InvalidChallenge,
+    /// The Android key lives in a weaker environment than required.
+    InvalidSecurityLevel,
+    /// The Android key was attested for another package.
+    InvalidPackageName,
+    /// The attested Android package is signed by another certificate than expected.
+    InvalidSignatureDigest,
+    /// A Play Integrity token does not decrypt, verify or parse.
+    InvalidIntegrityToken(String),
+    /// Play does not vouch for the app or device as required.
+    InvalidIntegrityVerdict(String),
+    /// The Play Integrity verdict is older than allowed, or from the future.
+    StaleIntegrityVerdict,
+    /// The attestation's receipt fails the configured [`crate::receipt::ReceiptPolicy`];
+    /// `reason` names the field and its value.
+    ReceiptRejected { reason: String },
+    /// The certificate chain holds `length` certificates, more than the configured
+    /// [`crate::attestation::VerifyOptions::max_chain_length`].
+    CertificateChainTooLong { max_length: usize, length: usize },
+    /// The certificate at `index` of the chain repeats an earlier one, the leaf included.
+    DuplicateCertificate { index: usize },
+    /// The certificate at `index` of the chain is a trusted root, which is supplied
+    /// separately and must not be anchored at itself.
+    RootInCertificateChain { index: usize },
+    /// The chain ends at the self-signed certificate at `index`, which is not a trusted root.
+    UntrustedSelfSignedCertificate { index: usize },
+    /// The assertion's counter is `u32::MAX`, the last value: the key can assert no more and
+    /// must be replaced by a newly attested one.
+    CounterExhausted,
+    /// A key is not P-256: `oid` is its `SubjectPublicKeyInfo` algorithm, or the curve of an
+    /// EC key on another curve.
+    UnsupportedKeyAlgorithm { oid: String },
+    /// The certificate that signs the leaf has a `SubjectPublicKeyInfo` whose SHA-256,
+    /// `spki_sha256` in hex, is none of
+    /// [`crate::attestation::VerifyOptions::pinned_intermediate_spki_sha256`].
+    IntermediateNotPinned { spki_sha256: String },
+    /// The input is not recognizably an attestation object or an assertion object, see
+    /// [`crate::detect::classify`].
+    UnrecognizedObject,
+    /// The attestation statement's `fmt` is `fmt` rather than `apple-appattest`.
+    UnsupportedFormat { fmt: String },
+    /// The certificate at `index` of the chain, leaf first, is valid only from `not_before`,
+    /// after `checked_at`. The times are RFC 3339; `subject_cn` is the certificate's
+    /// common name, or its whole subject if it has none.
+    CertificateNotYetValid { index: usize, subject_cn: String, not_before: String, not_after: String, checked_at: String },
+    /// The certificate at `index` of the chain expired at `not_after`, before `checked_at`;
+    /// the fields are those of [`AppAttestError::CertificateNotYetValid`].
+    CertificateExpired { index: usize, subject_cn: String, not_before: String, not_after: String, checked_at: String },
+    /// The verifier's [`crate::keypolicy::KeyPolicy`] denied the key, for `reason`, before
+    /// any signature was checked with it.
+    KeyDenied { reason: String },
+    /// A re-attestation came with neither an assertion by the key it replaces nor an
+    /// administrative override.
+    ContinuityNotProven,
+
     Message(String)
 }
 
@@ -34,8 +99,217 @@ impl fmt::Display for AppAttestError {
             AppAttestError::ExpectedASN1Node => write!(f, "expected ASN1 node"),
             AppAttestError::FailedToExtractValueFromASN1Node => write!(f, "failed to extract value from ASN1 node"),
             AppAttestError::ExpectedOctetStringInsideASN1Node => write!(f, "expected octet string inside ASN1 node"),
+            AppAttestError::UnknownChallenge => write!(f, "unknown or expired challenge"),
+            AppAttestError::UnknownKeyID => write!(f, "unknown key ID"),
+            AppAttestError::InvalidKeyDescription => write!(f, "invalid Android key description"),
+            AppAttestError::InvalidChallenge => write!(f, "invalid attestation challenge"),
+            AppAttestError::InvalidSecurityLevel => write!(f, "insufficient security level"),
+            AppAttestError::InvalidPackageName => write!(f, "invalid package name"),
+            AppAttestError::InvalidSignatureDigest => write!(f, "invalid app signature digest"),
+            AppAttestError::InvalidIntegrityToken(e) => write!(f, "invalid Play Integrity token: {e}"),
+            AppAttestError::InvalidIntegrityVerdict(e) => write!(f, "insufficient Play Integrity verdict: {e}"),
+            AppAttestError::StaleIntegrityVerdict => write!(f, "stale Play Integrity verdict"),
+            AppAttestError::ReceiptRejected { reason } => write!(f, "receipt rejected: {reason}"),
+            AppAttestError::CertificateChainTooLong { max_length, length } => {
+                write!(f, "certificate chain has {length} certificates, at most {max_length} are allowed")
+            }
+            AppAttestError::DuplicateCertificate { index } => write!(f, "certificate {index} repeats an earlier one"),
+            AppAttestError::RootInCertificateChain { index } => write!(f, "certificate {index} is a trusted root"),
+            AppAttestError::UntrustedSelfSignedCertificate { index } => {
+                write!(f, "certificate {index} is self-signed but not a trusted root")
+            }
+            AppAttestError::CounterExhausted => write!(f, "counter exhausted, the key must be attested again"),
+            AppAttestError::UnsupportedKeyAlgorithm { oid } => write!(f, "unsupported key algorithm {oid}, expected P-256"),
+            AppAttestError::IntermediateNotPinned { spki_sha256 } => {
+                write!(f, "the leaf's issuer has SPKI SHA-256 {spki_sha256}, which is not pinned")
+            }
+            AppAttestError::UnrecognizedObject => write!(f, "neither an attestation nor an assertion object"),
+            AppAttestError::UnsupportedFormat { fmt } => write!(f, "unsupported attestation format {fmt:?}"),
+            AppAttestError::CertificateNotYetValid { index, subject_cn, not_before, not_after, checked_at } => write!(
+                f,
+                "certificate {index} ({subject_cn}) is valid from {not_before} to {not_after}, not yet at {checked_at}"
+            ),
+            AppAttestError::CertificateExpired { index, subject_cn, not_before, not_after, checked_at } => write!(
+                f,
+                "certificate {index} ({subject_cn}) is valid from {not_before} to {not_after}, no longer at {checked_at}"
+            ),
+            AppAttestError::KeyDenied { reason } => write!(f, "key denied: {reason}"),
+            AppAttestError::ContinuityNotProven => {
+                write!(f, "re-attestation without an assertion by the replaced key")
+            }
+        }
+    }
+}
+
+impl Error for AppAttestError {}
+
+impl AppAttestError {
+    /// Code for errors that are not an `AppAttestError`, e.g. CBOR or certificate parsing failures
+    /// surfaced through `Box<dyn Error>`.
+    pub const UNCLASSIFIED: u16 = 1000;
+
+    /// Stable numeric code for this error, safe to hand to clients and match on.
+    ///
+    /// Codes are never reused or renumbered; new variants get new codes.
+    pub fn code(&self) -> u16 {
+        match self {
+            AppAttestError::Message(_) => Self::UNCLASSIFIED,
+            AppAttestError::InvalidNonce => 1001,
+            AppAttestError::InvalidAppIDHash => 1002,
+            AppAttestError::InvalidPublicKey => 1003,
+            AppAttestError::InvalidCounter => 1004,
+            AppAttestError::InvalidCredentialID => 1005,
+            AppAttestError::InvalidAAGUID => 1006,
+            AppAttestError::InvalidSignature => 1007,
+            AppAttestError::InvalidAppID => 1008,
+            AppAttestError::InvalidClientData => 1009,
+            AppAttestError::ExpectedASN1Node => 1010,
+            AppAttestError::FailedToExtractValueFromASN1Node => 1011,
+            AppAttestError::ExpectedOctetStringInsideASN1Node => 1012,
+            AppAttestError::UnknownChallenge => 1013,
+            AppAttestError::UnknownKeyID => 1014,
+            AppAttestError::InvalidKeyDescription => 1015,
+            AppAttestError::InvalidChallenge => 1016,
+            AppAttestError::InvalidSecurityLevel => 1017,
+            AppAttestError::InvalidPackageName => 1018,
+            AppAttestError::InvalidSignatureDigest => 1019,
+            AppAttestError::InvalidIntegrityToken(_) => 1020,
+            AppAttestError::InvalidIntegrityVerdict(_) => 1021,
+            AppAttestError::StaleIntegrityVerdict => 1022,
+            AppAttestError::ReceiptRejected { .. } => 1023,
+            AppAttestError::CertificateChainTooLong { .. } => 1024,
+            AppAttestError::DuplicateCertificate { .. } => 1025,
+            AppAttestError::RootInCertificateChain { .. } => 1026,
+            AppAttestError::UntrustedSelfSignedCertificate { .. } => 1027,
+            AppAttestError::CounterExhausted => 1028,
+            AppAttestError::UnsupportedKeyAlgorithm { .. } => 1029,
+            AppAttestError::IntermediateNotPinned { .. } => 1030,
+            AppAttestError::UnrecognizedObject => 1031,
+            AppAttestError::UnsupportedFormat { .. } => 1032,
+            AppAttestError::CertificateNotYetValid { .. } => 1033,
+            AppAttestError::CertificateExpired { .. } => 1034,
+            AppAttestError::KeyDenied { .. } => 1035,
+            AppAttestError::ContinuityNotProven => 1036,
+        }
+    }
+
+    /// The variant name, the `kind` of the JSON shape, as a low-cardinality label for
+    /// metrics and logs.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AppAttestError::InvalidNonce => "InvalidNonce",
+            AppAttestError::InvalidAppIDHash => "InvalidAppIDHash",
+            AppAttestError::InvalidPublicKey => "InvalidPublicKey",
+            AppAttestError::InvalidCounter => "InvalidCounter",
+            AppAttestError::InvalidCredentialID => "InvalidCredentialID",
+            AppAttestError::InvalidAAGUID => "InvalidAAGUID",
+            AppAttestError::InvalidSignature => "InvalidSignature",
+            AppAttestError::InvalidAppID => "InvalidAppID",
+            AppAttestError::InvalidClientData => "InvalidClientData",
+            AppAttestError::ExpectedASN1Node => "ExpectedASN1Node",
+            AppAttestError::FailedToExtractValueFromASN1Node => "FailedToExtractValueFromASN1Node",
+            AppAttestError::ExpectedOctetStringInsideASN1Node => "ExpectedOctetStringInsideASN1Node",
+            AppAttestError::UnknownChallenge => "UnknownChallenge",
+            AppAttestError::UnknownKeyID => "UnknownKeyID",
+            AppAttestError::InvalidKeyDescription => "InvalidKeyDescription",
+            AppAttestError::InvalidChallenge => "InvalidChallenge",
+            AppAttestError::InvalidSecurityLevel => "InvalidSecurityLevel",
+            AppAttestError::InvalidPackageName => "InvalidPackageName",
+            AppAttestError::InvalidSignatureDigest => "InvalidSignatureDigest",
+            AppAttestError::InvalidIntegrityToken(_) => "InvalidIntegrityToken",
+            AppAttestError::InvalidIntegrityVerdict(_) => "InvalidIntegrityVerdict",
+            AppAttestError::StaleIntegrityVerdict => "StaleIntegrityVerdict",
+            AppAttestError::ReceiptRejected { .. } => "ReceiptRejected",
+            AppAttestError::CertificateChainTooLong { .. } => "CertificateChainTooLong",
+            AppAttestError::DuplicateCertificate { .. } => "DuplicateCertificate",
+            AppAttestError::RootInCertificateChain { .. } => "RootInCertificateChain",
+            AppAttestError::UntrustedSelfSignedCertificate { .. } => "UntrustedSelfSignedCertificate",
+            AppAttestError::CounterExhausted => "CounterExhausted",
+            AppAttestError::UnsupportedKeyAlgorithm { .. } => "UnsupportedKeyAlgorithm",
+            AppAttestError::IntermediateNotPinned { .. } => "IntermediateNotPinned",
+            AppAttestError::UnrecognizedObject => "UnrecognizedObject",
+            AppAttestError::UnsupportedFormat { .. } => "UnsupportedFormat",
+            AppAttestError::CertificateNotYetValid { .. } => "CertificateNotYetValid",
+            AppAttestError::CertificateExpired { .. } => "CertificateExpired",
+            AppAttestError::KeyDenied { .. } => "KeyDenied",
+            AppAttestError::ContinuityNotProven => "ContinuityNotProven",
+            AppAttestError::Message(_) => "Message",
         }
     }
+
+    /// Code of an error returned by the `verify` functions, which box their errors.
+    pub fn code_of(err: &(dyn Error + 'static)) -> u16 {
+        err.downcast_ref::<AppAttestError>().map_or(Self::UNCLASSIFIED, AppAttestError::code)
+    }
 }
 
-impl Error for AppAttestError {}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_are_stable() {
+        assert_eq!(AppAttestError::InvalidNonce.code(), 1001);
+        assert_eq!(AppAttestError::InvalidCounter.code(), 1004);
+        assert_eq!(AppAttestError::UnknownKeyID.code(), 1014);
+        assert_eq!(AppAttestError::InvalidSignatureDigest.code(), 1019);
+        assert_eq!(AppAttestError::StaleIntegrityVerdict.code(), 1022);
+        assert_eq!(AppAttestError::ReceiptRejected { reason: "x".into() }.code(), 1023);
+        assert_eq!(AppAttestError::CertificateChainTooLong { max_length: 3, length: 4 }.code(), 1024);
+        assert_eq!(AppAttestError::DuplicateCertificate { index: 1 }.code(), 1025);
+        assert_eq!(AppAttestError::RootInCertificateChain { index: 1 }.code(), 1026);
+        assert_eq!(AppAttestError::UntrustedSelfSignedCertificate { index: 1 }.code(), 1027);
+        assert_eq!(AppAttestError::CounterExhausted.code(), 1028);
+        assert_eq!(AppAttestError::UnsupportedKeyAlgorithm { oid: "1.3.132.0.34".into() }.code(), 1029);
+        assert_eq!(AppAttestError::IntermediateNotPinned { spki_sha256: "00".into() }.code(), 1030);
+        assert_eq!(AppAttestError::UnrecognizedObject.code(), 1031);
+        assert_eq!(AppAttestError::UnsupportedFormat { fmt: "packed".into() }.code(), 1032);
+        let (subject_cn, not_before, not_after, checked_at) = (String::new(), String::new(), String::new(), String::new());
+        let not_yet_valid = AppAttestError::CertificateNotYetValid {
+            index: 0,
+            subject_cn: subject_cn.clone(),
+            not_before: not_before.clone(),
+            not_after: not_after.clone(),
+            checked_at: checked_at.clone(),
+        };
+        assert_eq!(not_yet_valid.code(), 1033);
+        let expired = AppAttestError::CertificateExpired { index: 0, subject_cn, not_before, not_after, checked_at };
+        assert_eq!(expired.code(), 1034);
+        assert_eq!(AppAttestError::KeyDenied { reason: "x".into() }.code(), 1035);
+        assert_eq!(AppAttestError::ContinuityNotProven.code(), 1036);
+        assert_eq!(AppAttestError::Message("x".into()).code(), AppAttestError::UNCLASSIFIED);
+    }
+
+    #[test]
+    fn kinds_match_the_json_tag() {
+        for err in [
+            AppAttestError::InvalidNonce,
+            AppAttestError::InvalidCounter,
+            AppAttestError::ReceiptRejected { reason: "x".into() },
+            AppAttestError::CertificateChainTooLong { max_length: 3, length: 4 },
+            AppAttestError::Message("x".into()),
+        ] {
+            assert_eq!(serde_json::to_value(&err).unwrap()["kind"], err.kind());
+        }
+    }
+
+    #[test]
+    fn boxed_errors_keep_their_code() {
+        let boxed: Box<dyn Error> = Box::new(AppAttestError::InvalidSignature);
+        assert_eq!(AppAttestError::code_of(&*boxed), 1007);
+
+        let other: Box<dyn Error> = "not an AppAttestError".into();
+        assert_eq!(AppAttestError::code_of(&*other), AppAttestError::UNCLASSIFIED);
+    }
+
+    #[test]
+    fn errors_keep_their_json_shape() {
+        let errors = vec![
+            AppAttestError::InvalidNonce,
+            AppAttestError::UnknownKeyID,
+            AppAttestError::Message("Failed to decode Base64".into()),
+            AppAttestError::InvalidIntegrityToken("bad signature".into()),
+        ];
+        crate::json::assert_golden(&errors, include_str!("../../apps/fixtures/json/app_attest_error.json"));
+    }
+}
@@ -0,0 +1,79 @@
+//! The JSON shape of the result and error types, for HTTP APIs and `jsonb` columns.
+//!
+//! Field names are camelCase and fixed; renaming a field is a breaking change. Byte fields
+//! are padded standard base64 strings, through [`bytes`]. The shapes are pinned by the golden
+//! files in `apps/fixtures/json/`, and with the `schemars` feature every type also derives
+//! `JsonSchema`.
+
+/// `#[serde(with = "appattest_rs::json::bytes")]` for `Vec<u8>` and `[u8; N]` fields, as
+/// base64 strings.
+pub mod bytes {
+    use base64::{engine::general_purpose, Engine};
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &impl AsRef<[u8]>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&general_purpose::STANDARD.encode(bytes))
+    }
+
+    /// Fails on invalid base64, and on a length that does not fit `T`.
+    pub fn deserialize<'de, D: Deserializer<'de>, T: TryFrom<Vec<u8>>>(deserializer: D) -> Result<T, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        let bytes = general_purpose::STANDARD.decode(text).map_err(D::Error::custom)?;
+        let len = bytes.len();
+        T::try_from(bytes).map_err(|_| D::Error::custom(format!("unexpected length {len}")))
+    }
+}
+
+/// Asserts `value` serializes to `golden`, a file under `apps/fixtures/json/`, and
+/// deserializes back from it.
+#[cfg(test)]
+pub(crate) fn assert_golden<T>(value: &T, golden: &str)
+where
+    T: serde::Serialize + serde::de::DeserializeOwned + PartialEq + std::fmt::Debug,
+{
+    let expected: serde_json::Value = serde_json::from_str(golden).unwrap();
+    let actual = serde_json::to_value(value).unwrap();
+    assert_eq!(
+        actual,
+        expected,
+        "the JSON shape changed; if that is intended, update the golden file to\n{}",
+        serde_json::to_string_pretty(&actual).unwrap()
+    );
+    assert_eq!(&serde_json::from_str::<T>(golden).unwrap(), value);
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Fields {
+        #[serde(with = "super::bytes")]
+        vec: Vec<u8>,
+        #[serde(with = "super::bytes")]
+        array: [u8; 4],
+    }
+
+    #[test]
+    fn bytes_are_padded_base64() {
+        let fields = Fields { vec: b"hello".to_vec(), array: [0xff; 4] };
+        let json = serde_json::to_string(&fields).unwrap();
+        assert_eq!(json, r#"{"vec":"aGVsbG8=","array":"/////w=="}"#);
+        assert_eq!(serde_json::from_str::<Fields>(&json).unwrap(), fields);
+    }
+
+    #[test]
+    fn rejects_bad_base64_and_wrong_lengths() {
+        for json in [r#"{"vec":"%%","array":"/////w=="}"#, r#"{"vec":"","array":"////"}"#, r#"{"vec":1,"array":""}"#] {
+            assert!(serde_json::from_str::<Fields>(json).is_err(), "{json}");
+        }
+    }
+
+    #[cfg(feature = "schemars")]
+    #[test]
+    fn schemas_describe_bytes_as_strings() {
+        let schema = serde_json::to_value(schemars::schema_for!(crate::attestation::VerifiedChain)).unwrap();
+        assert_eq!(schema["properties"]["leafFingerprint"]["type"], "string");
+        assert_eq!(schema["properties"]["root"]["type"], "string");
+    }
+}
@@ -0,0 +1,487 @@
+//! The attested P-256 key in the encodings other systems want: uncompressed SEC1 as App
+//! Attest certifies it, compressed SEC1, DER `SubjectPublicKeyInfo`, PEM, an EC JWK, and the
+//! raw `x`/`y` coordinates the Noir circuits take.
+//!
+//! Every importer is strict: the input must be exactly the encoding it names, with no
+//! trailing bytes, and the point must be on the curve and not the identity. Failures are
+//! [`AppAttestError::InvalidPublicKey`], but for a `SubjectPublicKeyInfo` of another
+//! algorithm or curve, which is [`AppAttestError::UnsupportedKeyAlgorithm`].
+//!
+//! A key's [`KeyId`] is the SHA-256 of its uncompressed SEC1 form, see
+//! [`key_id_for_public_key`]; the client sends it base64-encoded, and the credential
+//! certificate's common name is its hex.
+
+use std::fmt;
+use std::str::FromStr;
+
+use base64::{engine::general_purpose, Engine};
+use p256::elliptic_curve::sec1::{FromEncodedPoint, ToEncodedPoint};
+use p256::pkcs8::der::Decode;
+use p256::pkcs8::spki::SubjectPublicKeyInfoRef;
+use p256::pkcs8::{DecodePublicKey, EncodePublicKey, LineEnding};
+use p256::EncodedPoint;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Digest, Sha256};
+
+use crate::error::AppAttestError;
+
+/// `id-ecPublicKey`, the `SubjectPublicKeyInfo` algorithm of EC keys.
+pub const EC_PUBLIC_KEY_OID: &str = "1.2.840.10045.2.1";
+/// `prime256v1`, the curve parameter of a P-256 key.
+pub const PRIME256V1_OID: &str = "1.2.840.10045.3.1.7";
+
+/// The encodings of a [`PublicKey`], for choosing one at run time, e.g. from a CLI flag.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PublicKeyEncoding {
+    /// `0x04 || x || y`, 65 bytes.
+    Uncompressed,
+    /// `0x02` or `0x03`, by the parity of `y`, then `x`; 33 bytes.
+    Compressed,
+    /// DER `SubjectPublicKeyInfo` with the `id-ecPublicKey` algorithm and `prime256v1` curve.
+    Spki,
+    /// The `Spki` DER as a `PUBLIC KEY` PEM block.
+    Pem,
+    /// An EC JWK as JSON, see [`Jwk`].
+    Jwk,
+    /// `x || y`, 64 bytes.
+    Raw,
+}
+
+/// A public EC JWK: `{"kty":"EC","crv":"P-256","x":...,"y":...}` with unpadded base64url
+/// coordinates.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Jwk {
+    pub kty: String,
+    pub crv: String,
+    pub x: String,
+    pub y: String,
+    /// The private scalar; only ever read, to reject private keys.
+    #[serde(default, skip_serializing)]
+    #[cfg_attr(feature = "schemars", schemars(skip))]
+    d: Option<String>,
+}
+
+/// A P-256 public key, known to be a valid point.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PublicKey(p256::PublicKey);
+
+impl PublicKey {
+    /// Decodes `bytes` in `encoding`; for [`PublicKeyEncoding::Pem`] and
+    /// [`PublicKeyEncoding::Jwk`], `bytes` is the UTF-8 text.
+    pub fn decode(encoding: PublicKeyEncoding, bytes: &[u8]) -> Result<Self, AppAttestError> {
+        let text = || std::str::from_utf8(bytes).map_err(|_| AppAttestError::InvalidPublicKey);
+        match encoding {
+            PublicKeyEncoding::Uncompressed => Self::from_uncompressed(bytes),
+            PublicKeyEncoding::Compressed => Self::from_compressed(bytes),
+            PublicKeyEncoding::Spki => Self::from_spki_der(bytes),
+            PublicKeyEncoding::Pem => Self::from_pem(text()?),
+            PublicKeyEncoding::Jwk => {
+                let jwk = serde_json::from_str(text()?).map_err(|_| AppAttestError::InvalidPublicKey)?;
+                Self::from_jwk(&jwk)
+            }
+            PublicKeyEncoding::Raw => Self::from_raw(bytes),
+        }
+    }
+
+    /// The key in `encoding`; for [`PublicKeyEncoding::Pem`] and [`PublicKeyEncoding::Jwk`],
+    /// UTF-8 text.
+    pub fn encode(&self, encoding: PublicKeyEncoding) -> Vec<u8> {
+        match encoding {
+            PublicKeyEncoding::Uncompressed => self.to_uncompressed().to_vec(),
+            PublicKeyEncoding::Compressed => self.to_compressed().to_vec(),
+            PublicKeyEncoding::Spki => self.to_spki_der(),
+            PublicKeyEncoding::Pem => self.to_pem().into_bytes(),
+            PublicKeyEncoding::Jwk => serde_json::to_vec(&self.to_jwk()).unwrap(),
+            PublicKeyEncoding::Raw => self.to_raw().to_vec(),
+        }
+    }
+
+    /// From `0x04 || x || y`, the form of [`crate::attestation::VerifiedAttestation::public_key`].
+    pub fn from_uncompressed(bytes: &[u8]) -> Result<Self, AppAttestError> {
+        if bytes.len() != 65 || bytes[0] != 0x04 {
+            return Err(AppAttestError::InvalidPublicKey);
+        }
+        Self::from_sec1(bytes)
+    }
+
+    pub fn from_compressed(bytes: &[u8]) -> Result<Self, AppAttestError> {
+        if bytes.len() != 33 || !matches!(bytes[0], 0x02 | 0x03) {
+            return Err(AppAttestError::InvalidPublicKey);
+        }
+        Self::from_sec1(bytes)
+    }
+
+    /// Also reads the key of a certificate, from its `SubjectPublicKeyInfo`. Fails with
+    /// `UnsupportedKeyAlgorithm` unless the algorithm is [`EC_PUBLIC_KEY_OID`] with the
+    /// [`PRIME256V1_OID`] curve, and with `InvalidPublicKey` unless the key is an
+    /// uncompressed point, like [`PublicKey::from_uncompressed`].
+    pub fn from_spki_der(der: &[u8]) -> Result<Self, AppAttestError> {
+        let spki = SubjectPublicKeyInfoRef::from_der(der).map_err(|_| AppAttestError::InvalidPublicKey)?;
+        let algorithm = spki.algorithm.oid.to_string();
+        if algorithm != EC_PUBLIC_KEY_OID {
+            return Err(AppAttestError::UnsupportedKeyAlgorithm { oid: algorithm });
+        }
+        // An EC key without a named curve is reported by its algorithm.
+        let curve = spki.algorithm.parameters_oid().map_or(algorithm, |curve| curve.to_string());
+        if curve != PRIME256V1_OID {
+            return Err(AppAttestError::UnsupportedKeyAlgorithm { oid: curve });
+        }
+        Self::from_uncompressed(spki.subject_public_key.as_bytes().ok_or(AppAttestError::InvalidPublicKey)?)
+    }
+
+    /// From a `PUBLIC KEY` PEM block, with nothing around it but whitespace.
+    pub fn from_pem(pem: &str) -> Result<Self, AppAttestError> {
+        p256::PublicKey::from_public_key_pem(pem.trim()).map(PublicKey).map_err(|_| AppAttestError::InvalidPublicKey)
+    }
+
+    /// Rejects any `kty` but `EC`, any `crv` but `P-256`, coordinates that are not 32 bytes
+    /// of unpadded base64url, and JWKs that carry the private key.
+    pub fn from_jwk(jwk: &Jwk) -> Result<Self, AppAttestError> {
+        if jwk.kty != "EC" || jwk.crv != "P-256" || jwk.d.is_some() {
+            return Err(AppAttestError::InvalidPublicKey);
+        }
+        let coordinate = |text: &str| -> Result<[u8; 32], AppAttestError> {
+            let bytes = general_purpose::URL_SAFE_NO_PAD.decode(text).map_err(|_| AppAttestError::InvalidPublicKey)?;
+            bytes.try_into().map_err(|_| AppAttestError::InvalidPublicKey)
+        };
+        Self::from_coordinates(&coordinate(&jwk.x)?, &coordinate(&jwk.y)?)
+    }
+
+    /// From `x || y`, big-endian.
+    pub fn from_raw(bytes: &[u8]) -> Result<Self, AppAttestError> {
+        if bytes.len() != 64 {
+            return Err(AppAttestError::InvalidPublicKey);
+        }
+        Self::from_coordinates(bytes[..32].try_into().unwrap(), bytes[32..].try_into().unwrap())
+    }
+
+    pub fn from_coordinates(x: &[u8; 32], y: &[u8; 32]) -> Result<Self, AppAttestError> {
+        let point = EncodedPoint::from_affine_coordinates(x.into(), y.into(), false);
+        Option::from(p256::PublicKey::from_encoded_point(&point)).map(PublicKey).ok_or(AppAttestError::InvalidPublicKey)
+    }
+
+    fn from_sec1(bytes: &[u8]) -> Result<Self, AppAttestError> {
+        p256::PublicKey::from_sec1_bytes(bytes).map(PublicKey).map_err(|_| AppAttestError::InvalidPublicKey)
+    }
+
+    pub fn to_uncompressed(&self) -> [u8; 65] {
+        self.0.to_encoded_point(false).as_bytes().try_into().unwrap()
+    }
+
+    pub fn to_compressed(&self) -> [u8; 33] {
+        self.0.to_encoded_point(true).as_bytes().try_into().unwrap()
+    }
+
+    pub fn to_spki_der(&self) -> Vec<u8> {
+        self.0.to_public_key_der().unwrap().into_vec()
+    }
+
+    /// A `PUBLIC KEY` PEM block with `\n` line endings.
+    pub fn to_pem(&self) -> String {
+        self.0.to_public_key_pem(LineEnding::LF).unwrap()
+    }
+
+    pub fn to_jwk(&self) -> Jwk {
+        let (x, y) = self.coordinates();
+        Jwk {
+            kty: "EC".to_string(),
+            crv: "P-256".to_string(),
+            x: general_purpose::URL_SAFE_NO_PAD.encode(x),
+            y: general_purpose::URL_SAFE_NO_PAD.encode(y),
+            d: None,
+        }
+    }
+
+    pub fn to_raw(&self) -> [u8; 64] {
+        self.to_uncompressed()[1..].try_into().unwrap()
+    }
+
+    /// The big-endian `x` and `y` coordinates.
+    pub fn coordinates(&self) -> ([u8; 32], [u8; 32]) {
+        let uncompressed = self.to_uncompressed();
+        (uncompressed[1..33].try_into().unwrap(), uncompressed[33..].try_into().unwrap())
+    }
+
+    pub fn as_p256(&self) -> &p256::PublicKey {
+        &self.0
+    }
+}
+
+impl From<p256::PublicKey> for PublicKey {
+    fn from(key: p256::PublicKey) -> Self {
+        PublicKey(key)
+    }
+}
+
+impl From<PublicKey> for p256::PublicKey {
+    fn from(key: PublicKey) -> Self {
+        key.0
+    }
+}
+
+impl From<&p256::ecdsa::VerifyingKey> for PublicKey {
+    fn from(key: &p256::ecdsa::VerifyingKey) -> Self {
+        PublicKey(key.into())
+    }
+}
+
+/// The App Attest key ID of `sec1_uncompressed`, its SHA-256. Fails with `InvalidPublicKey`
+/// unless the bytes are a 65-byte uncompressed point on P-256.
+pub fn key_id_for_public_key(sec1_uncompressed: &[u8]) -> Result<[u8; 32], AppAttestError> {
+    PublicKey::from_uncompressed(sec1_uncompressed)?;
+    Ok(Sha256::digest(sec1_uncompressed).into())
+}
+
+/// An App Attest key ID. Displays, parses and serializes as standard base64, the form the
+/// client sends.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KeyId([u8; 32]);
+
+impl KeyId {
+    /// See [`key_id_for_public_key`].
+    pub fn for_public_key(sec1_uncompressed: &[u8]) -> Result<Self, AppAttestError> {
+        key_id_for_public_key(sec1_uncompressed).map(KeyId)
+    }
+
+    /// From the 32 raw bytes, e.g. the credential ID of the authenticator data.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, AppAttestError> {
+        let bytes = bytes.try_into().map_err(|_| invalid_key_id(format!("{} bytes, not 32", bytes.len())))?;
+        Ok(KeyId(bytes))
+    }
+
+    pub fn from_base64(text: &str) -> Result<Self, AppAttestError> {
+        let bytes = general_purpose::STANDARD.decode(text).map_err(|e| invalid_key_id(e.to_string()))?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// From hex in either case, the form of the credential certificate's common name.
+    pub fn from_hex(text: &str) -> Result<Self, AppAttestError> {
+        let bytes = hex::decode(text).map_err(|e| invalid_key_id(e.to_string()))?;
+        Self::from_bytes(&bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Lowercase hex.
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.0)
+    }
+}
+
+fn invalid_key_id(detail: String) -> AppAttestError {
+    AppAttestError::Message(format!("invalid key ID: {detail}"))
+}
+
+impl fmt::Display for KeyId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&general_purpose::STANDARD.encode(self.0))
+    }
+}
+
+impl FromStr for KeyId {
+    type Err = AppAttestError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Self::from_base64(text)
+    }
+}
+
+impl AsRef<[u8]> for KeyId {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Serialize for KeyId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        Self::from_base64(&text).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p256::ecdsa::SigningKey;
+    use proptest::prelude::*;
+
+    use super::*;
+
+    const ENCODINGS: [PublicKeyEncoding; 6] = [
+        PublicKeyEncoding::Uncompressed,
+        PublicKeyEncoding::Compressed,
+        PublicKeyEncoding::Spki,
+        PublicKeyEncoding::Pem,
+        PublicKeyEncoding::Jwk,
+        PublicKeyEncoding::Raw,
+    ];
+
+    fn fixture() -> PublicKey {
+        PublicKey::from_pem(include_str!("../../apps/fixtures/public_key.pem")).unwrap()
+    }
+
+    fn keys() -> impl Strategy<Value = PublicKey> {
+        any::<[u8; 32]>().prop_filter_map("not a scalar", |secret| {
+            SigningKey::from_bytes(&secret.into()).ok().map(|key| PublicKey::from(key.verifying_key()))
+        })
+    }
+
+    /// `key` in `encoding`, but with `y` replaced, so the point is off the curve in every
+    /// encoding that carries `y`.
+    fn off_curve(key: &PublicKey, y: [u8; 32], encoding: PublicKeyEncoding) -> Vec<u8> {
+        let (x, _) = key.coordinates();
+        let raw = [x, y].concat();
+        match encoding {
+            PublicKeyEncoding::Uncompressed => [&[0x04][..], &raw].concat(),
+            PublicKeyEncoding::Spki => {
+                let der = key.to_spki_der();
+                [&der[..der.len() - 32], &y[..]].concat()
+            }
+            PublicKeyEncoding::Pem => {
+                let der = off_curve(key, y, PublicKeyEncoding::Spki);
+                let base64 = general_purpose::STANDARD.encode(der);
+                format!("-----BEGIN PUBLIC KEY-----\n{base64}\n-----END PUBLIC KEY-----\n").into_bytes()
+            }
+            PublicKeyEncoding::Jwk => {
+                let jwk = Jwk { y: general_purpose::URL_SAFE_NO_PAD.encode(y), ..key.to_jwk() };
+                serde_json::to_vec(&jwk).unwrap()
+            }
+            PublicKeyEncoding::Raw => raw,
+            PublicKeyEncoding::Compressed => unreachable!("a compressed point has no y"),
+        }
+    }
+
+    #[test]
+    fn encodes_the_fixture_key() {
+        let key = fixture();
+        assert_eq!(key.to_pem(), include_str!("../../apps/fixtures/public_key.pem"));
+        assert_eq!(key.to_jwk().kty, "EC");
+        assert_eq!(key.to_jwk().crv, "P-256");
+        let jwk: serde_json::Value = serde_json::from_slice(&key.encode(PublicKeyEncoding::Jwk)).unwrap();
+        assert_eq!(jwk.as_object().unwrap().len(), 4, "{jwk}");
+        assert_eq!(key.to_spki_der().len(), 91);
+        assert_eq!(key.to_raw()[..], key.to_uncompressed()[1..]);
+        assert_eq!(key.to_compressed()[1..], key.to_uncompressed()[1..33]);
+    }
+
+    #[test]
+    fn importers_reject_other_encodings() {
+        let key = fixture();
+        for (i, from) in ENCODINGS.iter().enumerate() {
+            for (j, to) in ENCODINGS.iter().enumerate() {
+                if i != j {
+                    let bytes = key.encode(*from);
+                    assert!(PublicKey::decode(*to, &bytes).is_err(), "{from:?} decoded as {to:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn importers_are_strict() {
+        let key = fixture();
+        let uncompressed = key.to_uncompressed();
+        assert!(PublicKey::from_uncompressed(&[&uncompressed[..], &[0]].concat()).is_err());
+        assert!(PublicKey::from_uncompressed(&[&[0x06][..], &uncompressed[1..]].concat()).is_err());
+        assert!(PublicKey::from_uncompressed(&[0]).is_err());
+        assert!(PublicKey::from_compressed(&[&[0x02][..], &[0xff; 32]].concat()).is_err(), "x >= p");
+        assert!(PublicKey::from_raw(&[0; 64]).is_err());
+        assert!(PublicKey::from_spki_der(&[key.to_spki_der(), vec![0]].concat()).is_err());
+        // The same key as a compressed point: SEQUENCE { algorithm, BIT STRING { 02|03 x } }.
+        let der = key.to_spki_der();
+        let compressed = [&[0x30, 0x39][..], &der[2..23], &[0x03, 0x22, 0x00], &key.to_compressed()].concat();
+        assert_eq!(PublicKey::from_spki_der(&compressed), Err(AppAttestError::InvalidPublicKey));
+
+        let mut jwk = key.to_jwk();
+        jwk.crv = "P-384".to_string();
+        assert!(PublicKey::from_jwk(&jwk).is_err());
+        let padded = Jwk { x: format!("{}=", key.to_jwk().x), ..key.to_jwk() };
+        assert!(PublicKey::from_jwk(&padded).is_err());
+        let mut json: serde_json::Value = serde_json::to_value(key.to_jwk()).unwrap();
+        json["d"] = "AAAA".into();
+        assert!(PublicKey::decode(PublicKeyEncoding::Jwk, json.to_string().as_bytes()).is_err());
+    }
+
+    #[test]
+    fn spki_of_other_algorithms_and_curves_is_unsupported() {
+        // SEQUENCE { SEQUENCE { rsaEncryption, NULL }, BIT STRING { SEQUENCE { 197, 3 } } }
+        let rsa = hex::decode("301b300d06092a864886f70d0101010500030a003007020200c5020103").unwrap();
+        assert_eq!(
+            PublicKey::from_spki_der(&rsa),
+            Err(AppAttestError::UnsupportedKeyAlgorithm { oid: "1.2.840.113549.1.1.1".into() })
+        );
+        // The fixture key's SPKI with secp384r1 as the curve.
+        let der = fixture().to_spki_der();
+        let p384 = [&[0x30, 0x56, 0x30, 0x10][..], &der[4..13], &[0x06, 0x05, 0x2b, 0x81, 0x04, 0x00, 0x22], &der[23..]].concat();
+        assert_eq!(
+            PublicKey::from_spki_der(&p384),
+            Err(AppAttestError::UnsupportedKeyAlgorithm { oid: "1.3.132.0.34".into() })
+        );
+    }
+
+    #[test]
+    fn key_ids_take_every_form() {
+        let key = fixture();
+        let key_id = KeyId::for_public_key(&key.to_uncompressed()).unwrap();
+        assert_eq!(key_id.as_bytes(), &key_id_for_public_key(&key.to_uncompressed()).unwrap());
+        assert_eq!(KeyId::from_base64(&key_id.to_string()), Ok(key_id));
+        assert_eq!(key_id.to_string().parse(), Ok(key_id));
+        assert_eq!(KeyId::from_hex(&key_id.to_hex().to_uppercase()), Ok(key_id));
+        assert_eq!(KeyId::from_bytes(key_id.as_ref()), Ok(key_id));
+        assert_eq!(serde_json::to_value(key_id).unwrap(), key_id.to_string());
+        assert_eq!(serde_json::from_value::<KeyId>(key_id.to_string().into()).unwrap(), key_id);
+
+        assert!(KeyId::from_base64("a2V5").is_err());
+        assert!(KeyId::from_hex("zz").is_err());
+        assert!(KeyId::from_bytes(&[0; 33]).is_err());
+        assert!(serde_json::from_value::<KeyId>("a2V5".into()).is_err());
+    }
+
+    #[test]
+    fn key_ids_are_only_for_uncompressed_points() {
+        let key = fixture();
+        assert_eq!(key_id_for_public_key(&key.to_compressed()), Err(AppAttestError::InvalidPublicKey));
+        assert_eq!(key_id_for_public_key(&key.to_raw()), Err(AppAttestError::InvalidPublicKey));
+        let mut off_curve = key.to_uncompressed();
+        off_curve[64] ^= 1;
+        assert_eq!(key_id_for_public_key(&off_curve), Err(AppAttestError::InvalidPublicKey));
+    }
+
+    proptest! {
+        #[test]
+        fn every_encoding_round_trips(key in keys()) {
+            for encoding in ENCODINGS {
+                let encoded = key.encode(encoding);
+                prop_assert_eq!(PublicKey::decode(encoding, &encoded), Ok(key.clone()), "{:?}", encoding);
+            }
+            prop_assert_eq!(PublicKey::from_coordinates(&key.coordinates().0, &key.coordinates().1), Ok(key));
+        }
+
+        #[test]
+        fn off_curve_points_are_rejected(key in keys(), flip in 0usize..256) {
+            let (_, mut y) = key.coordinates();
+            y[flip / 8] ^= 1 << (flip % 8);
+            for encoding in ENCODINGS.into_iter().filter(|encoding| *encoding != PublicKeyEncoding::Compressed) {
+                let decoded = PublicKey::decode(encoding, &off_curve(&key, y, encoding));
+                prop_assert_eq!(decoded, Err(AppAttestError::InvalidPublicKey), "{:?}", encoding);
+            }
+        }
+
+        #[test]
+        fn compressed_points_decode_to_their_own_x_or_not_at_all(x in any::<[u8; 32]>(), odd in any::<bool>()) {
+            let compressed = [&[if odd { 0x03 } else { 0x02 }][..], &x].concat();
+            if let Ok(key) = PublicKey::from_compressed(&compressed) {
+                prop_assert_eq!(&key.to_compressed()[..], &compressed[..]);
+            }
+        }
+    }
+}
@@ -0,0 +1,177 @@
+//! Turning known keys away before any signature work is spent on them.
+//!
+//! [`crate::attestation::AttestationVerifier`] and [`crate::assertion::AssertionVerifier`]
+//! consult a [`KeyPolicy`], once one is set with `with_key_policy`: the attestation verifier
+//! with the key ID as soon as it has it, before the chain, and again with the public key the
+//! chain certifies; the assertion verifier with the key ID and public key before the
+//! signature. A denied key fails with [`AppAttestError::KeyDenied`]. With the `store`
+//! feature, `service::AppAttestService::with_key_policy` sets one on both paths.
+//! [`InMemoryKeyList`] denies, or allows only, the keys in its sets.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+use crate::error::AppAttestError;
+use crate::key::KeyId;
+
+/// What a [`KeyPolicy`] decided about a key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PolicyDecision {
+    Allow,
+    /// The verification fails with [`AppAttestError::KeyDenied`] carrying `reason`.
+    Deny { reason: String },
+}
+
+/// Decides whether a key may be verified at all. Called inline by the verifiers, so it
+/// should answer from memory.
+pub trait KeyPolicy: Send + Sync {
+    /// `public_key` is the uncompressed SEC1 key, `None` when only the ID is known yet.
+    fn check(&self, key_id: &KeyId, public_key: Option<&[u8]>) -> PolicyDecision;
+}
+
+/// Whether an [`InMemoryKeyList`] denies the keys it holds or every other key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ListKind {
+    Deny,
+    Allow,
+}
+
+/// Key IDs and public keys held in memory, as a deny list or an allow list. Entries can be
+/// added and removed while verifiers share it.
+///
+/// A key is listed if its ID or its public key is. A public key is listed with its App
+/// Attest key ID, [`KeyId::for_public_key`], so that it is decided on before the key itself
+/// is known.
+pub struct InMemoryKeyList {
+    kind: ListKind,
+    key_ids: RwLock<HashSet<KeyId>>,
+    public_keys: RwLock<HashSet<Vec<u8>>>,
+}
+
+impl InMemoryKeyList {
+    /// An empty deny list, which allows every key.
+    pub fn deny() -> Self {
+        Self::new(ListKind::Deny)
+    }
+
+    /// An empty allow list, which denies every key.
+    pub fn allow() -> Self {
+        Self::new(ListKind::Allow)
+    }
+
+    fn new(kind: ListKind) -> Self {
+        InMemoryKeyList { kind, key_ids: RwLock::default(), public_keys: RwLock::default() }
+    }
+
+    pub fn kind(&self) -> ListKind {
+        self.kind
+    }
+
+    pub fn insert_key_id(&self, key_id: KeyId) {
+        self.key_ids.write().unwrap().insert(key_id);
+    }
+
+    pub fn remove_key_id(&self, key_id: &KeyId) {
+        self.key_ids.write().unwrap().remove(key_id);
+    }
+
+    /// Adds the uncompressed SEC1 `public_key`, and its key ID if it is a key.
+    pub fn insert_public_key(&self, public_key: &[u8]) {
+        if let Ok(key_id) = KeyId::for_public_key(public_key) {
+            self.insert_key_id(key_id);
+        }
+        self.public_keys.write().unwrap().insert(public_key.to_vec());
+    }
+
+    /// Removes `public_key`, and its key ID.
+    pub fn remove_public_key(&self, public_key: &[u8]) {
+        if let Ok(key_id) = KeyId::for_public_key(public_key) {
+            self.remove_key_id(&key_id);
+        }
+        self.public_keys.write().unwrap().remove(public_key);
+    }
+}
+
+impl KeyPolicy for InMemoryKeyList {
+    fn check(&self, key_id: &KeyId, public_key: Option<&[u8]>) -> PolicyDecision {
+        let listed = self.key_ids.read().unwrap().contains(key_id)
+            || public_key.is_some_and(|public_key| self.public_keys.read().unwrap().contains(public_key));
+        let reason = match (self.kind, listed) {
+            (ListKind::Deny, true) => "key on the deny list",
+            (ListKind::Allow, false) => "key not on the allow list",
+            _ => return PolicyDecision::Allow,
+        };
+        PolicyDecision::Deny { reason: reason.into() }
+    }
+}
+
+/// The key policy of a verifier, if it has one. Like [`crate::metrics::Sink`], verifiers
+/// compare equal whatever their policies.
+#[derive(Clone, Default)]
+pub(crate) struct Policy(pub(crate) Option<Arc<dyn KeyPolicy>>);
+
+impl fmt::Debug for Policy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(if self.0.is_some() { "KeyPolicy" } else { "None" })
+    }
+}
+
+impl PartialEq for Policy {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Policy {
+    /// Fails with `KeyDenied` if the policy denies the key. A `key_id` that is not one, or
+    /// a `public_key` of which none can be made, is left for the verification to reject.
+    pub(crate) fn check(&self, key_id: Result<KeyId, AppAttestError>, public_key: Option<&[u8]>) -> Result<(), AppAttestError> {
+        let (Some(policy), Ok(key_id)) = (&self.0, key_id) else {
+            return Ok(());
+        };
+        match policy.check(&key_id, public_key) {
+            PolicyDecision::Allow => Ok(()),
+            PolicyDecision::Deny { reason } => Err(AppAttestError::KeyDenied { reason }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p256::elliptic_curve::sec1::ToEncodedPoint;
+
+    use super::*;
+
+    #[test]
+    fn lists_decide_on_the_id_or_the_key() {
+        let public_key = p256::SecretKey::from_slice(&[7; 32]).unwrap().public_key();
+        let public_key = public_key.to_encoded_point(false);
+        let public_key = public_key.as_bytes();
+        let key_id = KeyId::for_public_key(public_key).unwrap();
+        let (listed, other) = (KeyId::from_bytes(&[1; 32]).unwrap(), KeyId::from_bytes(&[2; 32]).unwrap());
+        let denied = |reason: &str| PolicyDecision::Deny { reason: reason.into() };
+
+        let deny = InMemoryKeyList::deny();
+        assert_eq!(deny.check(&listed, None), PolicyDecision::Allow);
+        deny.insert_key_id(listed);
+        assert_eq!(deny.check(&listed, None), denied("key on the deny list"));
+        assert_eq!(deny.check(&other, None), PolicyDecision::Allow);
+        deny.remove_key_id(&listed);
+        assert_eq!(deny.check(&listed, None), PolicyDecision::Allow);
+
+        // A public key is denied by its ID before it is known, and under any ID once it is.
+        deny.insert_public_key(public_key);
+        assert_eq!(deny.check(&key_id, None), denied("key on the deny list"));
+        assert_eq!(deny.check(&other, Some(public_key)), denied("key on the deny list"));
+        deny.remove_public_key(public_key);
+        assert_eq!(deny.check(&key_id, Some(public_key)), PolicyDecision::Allow);
+
+        let allow = InMemoryKeyList::allow();
+        assert_eq!(allow.check(&key_id, None), denied("key not on the allow list"));
+        allow.insert_public_key(public_key);
+        assert_eq!(allow.check(&key_id, None), PolicyDecision::Allow);
+        assert_eq!(allow.check(&key_id, Some(public_key)), PolicyDecision::Allow);
+        assert_eq!(allow.check(&other, None), denied("key not on the allow list"));
+    }
+}
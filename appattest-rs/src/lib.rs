@@ -1,6 +1,120 @@
 extern crate core;
 
+/// Records `field = value`s on the current span with the `tracing` feature. Without it the
+/// values are not evaluated.
+macro_rules! record {
+    ($($field:ident = $value:expr),+ $(,)?) => {
+        #[cfg(feature = "tracing")]
+        {
+            let span = tracing::Span::current();
+            $(span.record(stringify!($field), $value);)+
+        }
+    };
+}
+
+#[cfg(feature = "android")]
+pub mod androidattest;
 pub mod assertion;
 pub mod attestation;
+pub mod audit;
 pub mod authenticator;
-pub mod error;
\ No newline at end of file
+mod cbor;
+pub mod cose;
+pub mod decode;
+pub mod detect;
+pub mod error;
+pub mod json;
+pub mod key;
+pub mod keypolicy;
+pub mod metrics;
+pub mod platform;
+#[cfg(feature = "play_integrity")]
+pub mod playintegrity;
+pub mod receipt;
+#[cfg(feature = "receipt-exchange")]
+pub mod receiptexchange;
+pub mod root;
+pub mod sensitive;
+#[cfg(feature = "store")]
+pub mod service;
+#[cfg(feature = "store")]
+pub mod store;
+pub mod timings;
+
+fn _assert_send_sync<T: Send + Sync>() {}
+
+/// The types a server builds once and shares between its workers, which must stay
+/// `Send + Sync`; a field that is not fails the build here rather than in the server. See
+/// "Thread safety" in the README.
+const _: fn() = || {
+    _assert_send_sync::<attestation::AttestationVerifier>();
+    _assert_send_sync::<attestation::TrustedRoot>();
+    _assert_send_sync::<attestation::VerifyOptions>();
+    _assert_send_sync::<assertion::AssertionVerifier>();
+    _assert_send_sync::<receipt::ReceiptPolicy>();
+    _assert_send_sync::<platform::AppAttestKey>();
+    _assert_send_sync::<Box<dyn platform::AttestedKey>>();
+    _assert_send_sync::<metrics::NoopSink>();
+    _assert_send_sync::<audit::JsonlAuditSink>();
+    _assert_send_sync::<keypolicy::InMemoryKeyList>();
+    #[cfg(feature = "prometheus")]
+    _assert_send_sync::<metrics::PrometheusSink>();
+    #[cfg(feature = "android")]
+    _assert_send_sync::<androidattest::AndroidAttestationVerifier>();
+    #[cfg(feature = "play_integrity")]
+    _assert_send_sync::<playintegrity::PlayIntegrityVerifier>();
+    #[cfg(feature = "receipt-exchange")]
+    _assert_send_sync::<receiptexchange::ReceiptExchangeClient>();
+    #[cfg(feature = "store")]
+    {
+        _assert_send_sync::<service::AppAttestService>();
+        _assert_send_sync::<store::InMemoryKeyStore>();
+        _assert_send_sync::<store::InMemoryChallengeStore>();
+        _assert_send_sync::<store::InMemoryReplayCache>();
+    }
+    #[cfg(feature = "sqlite")]
+    _assert_send_sync::<store::sqlite::SqliteKeyStore>();
+    #[cfg(feature = "redis")]
+    {
+        _assert_send_sync::<store::redis::RedisChallengeStore>();
+        _assert_send_sync::<store::redis::RedisReplayCache>();
+    }
+};
+
+/// Version of this crate as `(major, minor, patch)`, for verifiers to tell which
+/// verification code produced a result.
+pub const VERSION: (u8, u8, u8) = (
+    version_part(env!("CARGO_PKG_VERSION_MAJOR")),
+    version_part(env!("CARGO_PKG_VERSION_MINOR")),
+    version_part(env!("CARGO_PKG_VERSION_PATCH")),
+);
+
+const fn version_part(digits: &str) -> u8 {
+    let digits = digits.as_bytes();
+    let mut value = 0u8;
+    let mut i = 0;
+    while i < digits.len() {
+        value = value * 10 + (digits[i] - b'0');
+        i += 1;
+    }
+    value
+}
+
+/// Runs `f` under a `fmt` subscriber at `DEBUG` that also reports span closes, and returns
+/// what it wrote.
+#[cfg(all(test, feature = "tracing"))]
+pub(crate) fn traced(f: impl FnOnce()) -> String {
+    use std::sync::{Arc, Mutex};
+    use tracing_subscriber::fmt::format::FmtSpan;
+
+    let output = Arc::new(Mutex::new(Vec::new()));
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(output.clone())
+        .with_max_level(tracing::Level::DEBUG)
+        .with_span_events(FmtSpan::CLOSE)
+        .without_time()
+        .finish();
+    tracing::subscriber::with_default(subscriber, f);
+    let output = output.lock().unwrap();
+    String::from_utf8(output.clone()).unwrap()
+}
@@ -0,0 +1,229 @@
+//! Counters and histograms of verification outcomes, for any metrics backend.
+//!
+//! [`crate::attestation::AttestationVerifier`] and [`crate::assertion::AssertionVerifier`]
+//! report to a [`MetricsSink`], [`NoopSink`] unless one is set with `with_metrics`. With the
+//! `prometheus` feature, `PrometheusSink` records into a `prometheus::Registry`.
+//!
+//! The `verify` methods of [`crate::attestation::Attestation`] and
+//! [`crate::assertion::Assertion`], which the zkVM guests call, report nothing.
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::error::AppAttestError;
+
+/// Counter of verifications, labelled `kind`, e.g. `attestation` or `assertion`, and
+/// `outcome`, `success` or the [`AppAttestError::kind`] of the failure, e.g. `InvalidNonce`.
+pub const VERIFICATIONS_TOTAL: &str = "appattest_verifications_total";
+/// Histogram of the time verifications took, in seconds, labelled `kind`.
+pub const VERIFY_DURATION_SECONDS: &str = "appattest_verify_duration_seconds";
+
+/// Receives metrics. Names follow Prometheus conventions and labels are `(name, value)`
+/// pairs; every call for a metric has the same label names in the same order.
+pub trait MetricsSink: Send + Sync {
+    fn incr_counter(&self, name: &str, labels: &[(&str, &str)]);
+    fn observe_duration(&self, name: &str, labels: &[(&str, &str)], secs: f64);
+    /// Observes a value other than a duration, such as a size in bytes. Ignored unless
+    /// implemented.
+    fn observe_value(&self, _name: &str, _labels: &[(&str, &str)], _value: f64) {}
+}
+
+/// Discards everything.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoopSink;
+
+impl MetricsSink for NoopSink {
+    fn incr_counter(&self, _name: &str, _labels: &[(&str, &str)]) {}
+    fn observe_duration(&self, _name: &str, _labels: &[(&str, &str)], _secs: f64) {}
+}
+
+/// The sink of a verifier. Verifiers compare equal whatever their sinks, which do not change
+/// what they accept.
+#[derive(Clone)]
+pub(crate) struct Sink(pub(crate) Arc<dyn MetricsSink>);
+
+impl Default for Sink {
+    fn default() -> Self {
+        Sink(Arc::new(NoopSink))
+    }
+}
+
+impl fmt::Debug for Sink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("MetricsSink")
+    }
+}
+
+impl PartialEq for Sink {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Sink {
+    /// Runs `verify`, a verification of `kind`, and reports its outcome and duration.
+    pub(crate) fn verification<T>(
+        &self,
+        kind: &str,
+        verify: impl FnOnce() -> Result<T, AppAttestError>,
+    ) -> Result<T, AppAttestError> {
+        let started = Instant::now();
+        let result = verify();
+        let outcome = match &result {
+            Ok(_) => "success",
+            Err(err) => err.kind(),
+        };
+        self.0.incr_counter(VERIFICATIONS_TOTAL, &[("kind", kind), ("outcome", outcome)]);
+        self.0.observe_duration(VERIFY_DURATION_SECONDS, &[("kind", kind)], started.elapsed().as_secs_f64());
+        result
+    }
+}
+
+/// Records into a `prometheus::Registry`, registering each metric on first use with the
+/// label names of that call. A call whose labels do not match the metric's, or whose name the
+/// registry already holds from elsewhere, is dropped.
+///
+/// Durations go into the default buckets, other values into powers of 4 from 1 KiB to 256 MiB,
+/// which suits receipt sizes.
+#[cfg(feature = "prometheus")]
+pub struct PrometheusSink {
+    registry: prometheus::Registry,
+    counters: std::sync::Mutex<std::collections::HashMap<String, prometheus::IntCounterVec>>,
+    histograms: std::sync::Mutex<std::collections::HashMap<String, prometheus::HistogramVec>>,
+}
+
+#[cfg(feature = "prometheus")]
+impl PrometheusSink {
+    pub fn new(registry: prometheus::Registry) -> Self {
+        PrometheusSink { registry, counters: Default::default(), histograms: Default::default() }
+    }
+
+    /// The registry to gather from.
+    pub fn registry(&self) -> &prometheus::Registry {
+        &self.registry
+    }
+
+    fn histogram(&self, name: &str, labels: &[(&str, &str)], buckets: Vec<f64>) -> Option<prometheus::Histogram> {
+        let mut histograms = self.histograms.lock().unwrap();
+        let histogram = match histograms.get(name) {
+            Some(histogram) => histogram.clone(),
+            None => {
+                let opts = prometheus::HistogramOpts::new(name, name).buckets(buckets);
+                let histogram = prometheus::HistogramVec::new(opts, &label_names(labels)).ok()?;
+                self.registry.register(Box::new(histogram.clone())).ok()?;
+                histograms.entry(name.to_string()).or_insert(histogram).clone()
+            }
+        };
+        histogram.get_metric_with_label_values(&label_values(labels)).ok()
+    }
+}
+
+#[cfg(feature = "prometheus")]
+impl MetricsSink for PrometheusSink {
+    fn incr_counter(&self, name: &str, labels: &[(&str, &str)]) {
+        let mut counters = self.counters.lock().unwrap();
+        let counter = match counters.get(name) {
+            Some(counter) => counter.clone(),
+            None => {
+                let Ok(counter) = prometheus::IntCounterVec::new(prometheus::Opts::new(name, name), &label_names(labels))
+                else {
+                    return;
+                };
+                if self.registry.register(Box::new(counter.clone())).is_err() {
+                    return;
+                }
+                counters.entry(name.to_string()).or_insert(counter).clone()
+            }
+        };
+        if let Ok(counter) = counter.get_metric_with_label_values(&label_values(labels)) {
+            counter.inc();
+        }
+    }
+
+    fn observe_duration(&self, name: &str, labels: &[(&str, &str)], secs: f64) {
+        if let Some(histogram) = self.histogram(name, labels, prometheus::DEFAULT_BUCKETS.to_vec()) {
+            histogram.observe(secs);
+        }
+    }
+
+    fn observe_value(&self, name: &str, labels: &[(&str, &str)], value: f64) {
+        let buckets = prometheus::exponential_buckets(1024.0, 4.0, 10).unwrap();
+        if let Some(histogram) = self.histogram(name, labels, buckets) {
+            histogram.observe(value);
+        }
+    }
+}
+
+#[cfg(feature = "prometheus")]
+fn label_names<'a>(labels: &[(&'a str, &str)]) -> Vec<&'a str> {
+    labels.iter().map(|(name, _)| *name).collect()
+}
+
+#[cfg(feature = "prometheus")]
+fn label_values<'a>(labels: &[(&str, &'a str)]) -> Vec<&'a str> {
+    labels.iter().map(|(_, value)| *value).collect()
+}
+
+/// Keeps every call as `name{label=value,...}`, or `name{...} value` for observations.
+#[cfg(test)]
+#[derive(Default)]
+pub(crate) struct RecordingSink(pub(crate) std::sync::Mutex<Vec<String>>);
+
+#[cfg(test)]
+impl RecordingSink {
+    fn push(&self, name: &str, labels: &[(&str, &str)], value: Option<f64>) {
+        let labels: Vec<String> = labels.iter().map(|(name, value)| format!("{name}={value}")).collect();
+        let mut record = format!("{name}{{{}}}", labels.join(","));
+        if let Some(value) = value {
+            record += &format!(" {value}");
+        }
+        self.0.lock().unwrap().push(record);
+    }
+
+    /// The records so far, without the observed values.
+    pub(crate) fn names(&self) -> Vec<String> {
+        let records = self.0.lock().unwrap();
+        records.iter().map(|record| record.split(' ').next().unwrap().to_string()).collect()
+    }
+}
+
+#[cfg(test)]
+impl MetricsSink for RecordingSink {
+    fn incr_counter(&self, name: &str, labels: &[(&str, &str)]) {
+        self.push(name, labels, None);
+    }
+
+    fn observe_duration(&self, name: &str, labels: &[(&str, &str)], secs: f64) {
+        self.push(name, labels, Some(secs));
+    }
+
+    fn observe_value(&self, name: &str, labels: &[(&str, &str)], value: f64) {
+        self.push(name, labels, Some(value));
+    }
+}
+
+#[cfg(all(test, feature = "prometheus"))]
+mod tests {
+    use prometheus::Encoder;
+
+    use super::*;
+
+    #[test]
+    fn prometheus_sink_registers_on_first_use() {
+        let sink = PrometheusSink::new(prometheus::Registry::new());
+        sink.incr_counter("c_total", &[("kind", "attestation"), ("outcome", "success")]);
+        sink.incr_counter("c_total", &[("kind", "attestation"), ("outcome", "success")]);
+        sink.incr_counter("c_total", &[("kind", "assertion")]);
+        sink.observe_duration("d_seconds", &[("kind", "assertion")], 0.02);
+        sink.observe_value("b_bytes", &[], 5000.0);
+
+        let mut text = Vec::new();
+        prometheus::TextEncoder::new().encode(&sink.registry().gather(), &mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert!(text.contains(r#"c_total{kind="attestation",outcome="success"} 2"#), "{text}");
+        assert!(!text.contains(r#"c_total{kind="assertion"}"#), "{text}");
+        assert!(text.contains(r#"d_seconds_count{kind="assertion"} 1"#), "{text}");
+        assert!(text.contains(r#"b_bytes_bucket{le="16384"} 1"#), "{text}");
+    }
+}
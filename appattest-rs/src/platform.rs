@@ -0,0 +1,197 @@
+//! Attested keys whatever attested them, so that a relying party can store and check Apple
+//! and Android keys side by side.
+//!
+//! [`AttestedKey`] is what the server side needs of a key once its attestation has passed:
+//! its bytes and ID, and a way to check its assertions. [`AppAttestKey`] implements it for
+//! App Attest keys. The platform verifiers, e.g. [`crate::attestation::Attestation::verify_raw`],
+//! are unchanged; this wraps what they return.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::assertion::Assertion;
+use crate::attestation::{Environment, VerifiedAttestation};
+use crate::error::AppAttestError;
+use crate::key::KeyId;
+
+/// The attestation service that vouched for a key, `"apple"` or `"android"` in JSON.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum Platform {
+    /// App Attest. The default, since records from before platforms existed are Apple's.
+    #[default]
+    Apple,
+    /// Android hardware key attestation.
+    Android,
+}
+
+impl Platform {
+    /// The name in JSON and in the stores.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Platform::Apple => "apple",
+            Platform::Android => "android",
+        }
+    }
+
+    /// The platform [`Platform::as_str`] names.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "apple" => Some(Platform::Apple),
+            "android" => Some(Platform::Android),
+            _ => None,
+        }
+    }
+}
+
+/// A key whose attestation has been verified, on any [`Platform`].
+pub trait AttestedKey: Send + Sync {
+    fn platform(&self) -> Platform;
+
+    /// The public key, in the platform's encoding: uncompressed SEC1 for Apple.
+    fn public_key(&self) -> &[u8];
+
+    /// The ID the client refers to the key by.
+    fn key_id(&self) -> KeyId;
+
+    /// The App Attest environment of the key, or `None` on a platform without one.
+    fn environment(&self) -> Option<Environment>;
+
+    /// Unix time the certificate the key was attested with expires, if known.
+    fn leaf_not_after(&self) -> Option<i64> {
+        None
+    }
+
+    /// Verifies `assertion`, as the client sent it, over `client_data` for `app_id`, and
+    /// returns its counter, which must be greater than `previous_counter` unless this is the
+    /// key's first assertion, `None`.
+    fn verify_assertion(
+        &self,
+        assertion: &[u8],
+        client_data: &[u8],
+        app_id: &str,
+        previous_counter: Option<u32>,
+    ) -> Result<u32, AppAttestError>;
+}
+
+/// An App Attest key, e.g. from a [`VerifiedAttestation`] or a stored record.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AppAttestKey {
+    key_id: KeyId,
+    public_key: Vec<u8>,
+    environment: Option<Environment>,
+    leaf_not_after: Option<i64>,
+}
+
+impl AppAttestKey {
+    /// The key with uncompressed SEC1 `public_key`; fails with `InvalidPublicKey` if it is not
+    /// a P-256 point.
+    pub fn new(public_key: Vec<u8>, environment: Option<Environment>) -> Result<Self, AppAttestError> {
+        Ok(AppAttestKey { key_id: KeyId::for_public_key(&public_key)?, public_key, environment, leaf_not_after: None })
+    }
+}
+
+impl TryFrom<&VerifiedAttestation> for AppAttestKey {
+    type Error = AppAttestError;
+
+    fn try_from(verified: &VerifiedAttestation) -> Result<Self, AppAttestError> {
+        let key = Self::new(verified.public_key.clone(), Some(verified.environment))?;
+        Ok(AppAttestKey { leaf_not_after: verified.leaf_not_after, ..key })
+    }
+}
+
+impl AttestedKey for AppAttestKey {
+    fn platform(&self) -> Platform {
+        Platform::Apple
+    }
+
+    fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    fn key_id(&self) -> KeyId {
+        self.key_id
+    }
+
+    fn environment(&self) -> Option<Environment> {
+        self.environment
+    }
+
+    fn leaf_not_after(&self) -> Option<i64> {
+        self.leaf_not_after
+    }
+
+    /// [`Assertion::verify_raw`] of the CBOR `assertion`.
+    fn verify_assertion(
+        &self,
+        assertion: &[u8],
+        client_data: &[u8],
+        app_id: &str,
+        previous_counter: Option<u32>,
+    ) -> Result<u32, AppAttestError> {
+        let assertion = Assertion::from_cbor(assertion)?;
+        let counter = assertion.counter()?;
+        let app_id_hash = Sha256::digest(app_id).into();
+        assertion.verify_raw(client_data, &app_id_hash, &self.public_key, previous_counter)?;
+        Ok(counter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose, Engine};
+
+    use super::*;
+    use crate::attestation::Attestation;
+
+    const APP_ID: &str = "LMRM26A744.xyz.elus.aegis.app-attester";
+
+    fn decoded(base64: &str) -> Vec<u8> {
+        general_purpose::STANDARD.decode(base64.trim()).unwrap()
+    }
+
+    fn recorded_key() -> AppAttestKey {
+        let attestation = Attestation::from_base64(include_str!("../../apps/fixtures/attestation.b64").trim()).unwrap();
+        let verified = attestation
+            .verify_raw(
+                &decoded("ExYiYJ4jA3rGL9oQN8ihDP4seccnxwHSEU28u8aCQaU="),
+                &Sha256::digest(APP_ID).into(),
+                &decoded("HD8zVqwgesSBwPaYj5icRg1GI/b6SOoa4TqieTEBA0g="),
+                1_751_800_000,
+            )
+            .unwrap();
+        AppAttestKey::try_from(&verified).unwrap()
+    }
+
+    #[test]
+    fn app_attest_key_is_the_attested_key() {
+        let key = recorded_key();
+        assert_eq!(key.platform(), Platform::Apple);
+        assert_eq!(key.key_id(), KeyId::from_base64("HD8zVqwgesSBwPaYj5icRg1GI/b6SOoa4TqieTEBA0g=").unwrap());
+        assert_eq!(key.environment(), Some(Environment::Development));
+        // 2026-01-22T05:05:02Z, from the leaf certificate.
+        assert_eq!(key.leaf_not_after(), Some(1_769_058_302));
+        assert_eq!(AppAttestKey::new(key.public_key.clone(), None).unwrap().leaf_not_after(), None);
+        assert_eq!(AppAttestKey::new(vec![4; 65], None), Err(AppAttestError::InvalidPublicKey));
+    }
+
+    #[test]
+    fn app_attest_key_verifies_assertions() {
+        let key = recorded_key();
+        let assertion = decoded(include_str!("../../apps/fixtures/assertion.b64"));
+        let client_data = decoded(include_str!("../../apps/fixtures/client_data.b64"));
+        assert_eq!(key.verify_assertion(&assertion, &client_data, APP_ID, None), Ok(1));
+        assert_eq!(key.verify_assertion(&assertion, &client_data, APP_ID, Some(1)), Err(AppAttestError::InvalidCounter));
+        assert_eq!(key.verify_assertion(&assertion, b"x = 16", APP_ID, Some(0)), Err(AppAttestError::InvalidSignature));
+    }
+
+    #[test]
+    fn platforms_round_trip_through_their_names() {
+        for platform in [Platform::Apple, Platform::Android] {
+            assert_eq!(Platform::from_name(platform.as_str()), Some(platform));
+            assert_eq!(serde_json::to_value(platform).unwrap(), platform.as_str());
+        }
+        assert_eq!(Platform::from_name("Apple"), None);
+    }
+}
@@ -0,0 +1,461 @@
+//! Play Integrity API tokens: the verdict Google Play signs and encrypts for an app, which
+//! the app hands to its server. A token is a compact JWE (`A256KW` / `A256GCM`) around a
+//! compact JWS (`ES256`) whose payload is the verdict JSON.
+//!
+//! Verification is local: the caller supplies the decryption and verification keys from
+//! the Play Console and the current time, and nothing here touches the network.
+
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use aes_kw::KekAes256;
+use base64::{engine::general_purpose, Engine};
+use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+use p256::pkcs8::DecodePublicKey;
+use serde::Deserialize;
+
+use crate::error::AppAttestError;
+
+/// The verdict a token carries, as Google Play writes it. Numbers are JSON strings.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrityPayload {
+    pub request_details: RequestDetails,
+    pub app_integrity: AppIntegrity,
+    pub device_integrity: DeviceIntegrity,
+    #[serde(default)]
+    pub account_details: Option<AccountDetails>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestDetails {
+    pub request_package_name: String,
+    /// The nonce the app passed to the classic API.
+    #[serde(default)]
+    pub nonce: Option<String>,
+    /// The request hash the app passed to the standard API.
+    #[serde(default)]
+    pub request_hash: Option<String>,
+    pub timestamp_millis: String,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AppIntegrity {
+    pub app_recognition_verdict: String,
+    /// Absent unless the verdict is `PLAY_RECOGNIZED` or `UNRECOGNIZED_VERSION`.
+    #[serde(default)]
+    pub package_name: Option<String>,
+    /// Unpadded base64url SHA-256 of each of the app's signing certificates.
+    #[serde(default)]
+    pub certificate_sha256_digest: Vec<String>,
+    #[serde(default)]
+    pub version_code: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceIntegrity {
+    #[serde(default)]
+    pub device_recognition_verdict: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountDetails {
+    pub app_licensing_verdict: String,
+}
+
+/// How much of Play's device integrity a device has to meet. Ordered: each level implies
+/// the ones before it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DeviceRecognition {
+    /// `MEETS_BASIC_INTEGRITY`: possibly rooted or unlocked, but not an emulator.
+    Basic,
+    /// `MEETS_DEVICE_INTEGRITY`: a genuine, Play-certified device.
+    Device,
+    /// `MEETS_STRONG_INTEGRITY`: with hardware-backed proof of boot integrity.
+    Strong,
+}
+
+impl DeviceRecognition {
+    fn from_label(label: &str) -> Option<Self> {
+        match label {
+            "MEETS_BASIC_INTEGRITY" => Some(DeviceRecognition::Basic),
+            "MEETS_DEVICE_INTEGRITY" => Some(DeviceRecognition::Device),
+            "MEETS_STRONG_INTEGRITY" => Some(DeviceRecognition::Strong),
+            _ => None,
+        }
+    }
+}
+
+/// What a token has to say for [`PlayIntegrityVerifier::verify`] to accept it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExpectedIntegrity {
+    pub package_name: String,
+    /// The nonce the server issued, exactly as the app passed it to Play.
+    pub nonce: String,
+    /// SHA-256 of each certificate the app may be signed with. Every digest the token
+    /// states has to be one of them.
+    pub certificate_sha256_digests: Vec<[u8; 32]>,
+    pub min_device_recognition: DeviceRecognition,
+    /// How old, in seconds, the verdict may be.
+    pub max_age_secs: i64,
+}
+
+/// What a successful [`PlayIntegrityVerifier::verify`] establishes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IntegrityVerdict {
+    pub package_name: String,
+    pub version_code: Option<u64>,
+    pub certificate_sha256_digests: Vec<[u8; 32]>,
+    /// The strongest device integrity level the device meets, at least the required one.
+    pub device_recognition: DeviceRecognition,
+    /// When Play issued the verdict, in Unix milliseconds.
+    pub timestamp_millis: i64,
+    /// `LICENSED`, `UNLICENSED` or `UNEVALUATED`, if Play reported it.
+    pub app_licensing: Option<String>,
+}
+
+/// Decrypts and verifies Play Integrity tokens with an app's response keys.
+#[derive(Clone)]
+pub struct PlayIntegrityVerifier {
+    decryption_key: [u8; 32],
+    verification_key: VerifyingKey,
+}
+
+impl std::fmt::Debug for PlayIntegrityVerifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Not the decryption key, which is secret.
+        f.debug_struct("PlayIntegrityVerifier").field("verification_key", &self.verification_key).finish()
+    }
+}
+
+impl PlayIntegrityVerifier {
+    /// The Play Console's response keys, base64-decoded: the 32-byte AES decryption key and
+    /// the DER `SubjectPublicKeyInfo` of the P-256 verification key.
+    pub fn new(decryption_key: &[u8], verification_key: &[u8]) -> Result<Self, AppAttestError> {
+        let decryption_key = decryption_key
+            .try_into()
+            .map_err(|_| AppAttestError::Message("Play Integrity decryption key is not 32 bytes".into()))?;
+        let verification_key = VerifyingKey::from_public_key_der(verification_key)
+            .map_err(|e| AppAttestError::Message(format!("invalid Play Integrity verification key: {e}")))?;
+        Ok(PlayIntegrityVerifier { decryption_key, verification_key })
+    }
+
+    /// Decrypts `token` and checks its signature, without judging the verdict.
+    pub fn decode(&self, token: &str) -> Result<IntegrityPayload, AppAttestError> {
+        let jws = self.decrypt(token.trim())?;
+        let jws = std::str::from_utf8(&jws).map_err(|_| invalid("the decrypted token is not text"))?;
+        let payload = self.verify_signature(jws)?;
+        serde_json::from_slice(&payload).map_err(|e| invalid(&format!("unexpected verdict JSON: {e}")))
+    }
+
+    /// Decodes `token` and checks it against `expected` at `now`, Unix seconds: the package,
+    /// nonce, signing certificates, that Play recognizes the app, the device's integrity
+    /// level and the verdict's age.
+    pub fn verify(
+        &self,
+        token: &str,
+        expected: &ExpectedIntegrity,
+        now: i64,
+    ) -> Result<IntegrityVerdict, AppAttestError> {
+        let payload = self.decode(token)?;
+        let request = &payload.request_details;
+        let app = &payload.app_integrity;
+
+        if request.request_package_name != expected.package_name {
+            return Err(AppAttestError::InvalidPackageName);
+        }
+        if request.nonce.as_deref() != Some(expected.nonce.as_str()) {
+            return Err(AppAttestError::InvalidNonce);
+        }
+        let timestamp_millis: i64 =
+            request.timestamp_millis.parse().map_err(|_| invalid("timestampMillis is not a number"))?;
+        let age_millis = now.saturating_mul(1000).saturating_sub(timestamp_millis);
+        if !(0..=expected.max_age_secs.saturating_mul(1000)).contains(&age_millis) {
+            return Err(AppAttestError::StaleIntegrityVerdict);
+        }
+
+        if app.app_recognition_verdict != "PLAY_RECOGNIZED" {
+            return Err(AppAttestError::InvalidIntegrityVerdict(format!("app is {}", app.app_recognition_verdict)));
+        }
+        if app.package_name.as_deref() != Some(expected.package_name.as_str()) {
+            return Err(AppAttestError::InvalidPackageName);
+        }
+        let certificate_sha256_digests = app
+            .certificate_sha256_digest
+            .iter()
+            .map(|digest| {
+                let digest = general_purpose::URL_SAFE_NO_PAD.decode(digest.trim_end_matches('=')).ok()?;
+                <[u8; 32]>::try_from(digest).ok().filter(|d| expected.certificate_sha256_digests.contains(d))
+            })
+            .collect::<Option<Vec<_>>>()
+            .filter(|digests| !digests.is_empty())
+            .ok_or(AppAttestError::InvalidSignatureDigest)?;
+
+        let verdicts = &payload.device_integrity.device_recognition_verdict;
+        let device_recognition = verdicts
+            .iter()
+            .filter_map(|label| DeviceRecognition::from_label(label))
+            .max()
+            .filter(|level| *level >= expected.min_device_recognition)
+            .ok_or_else(|| AppAttestError::InvalidIntegrityVerdict(format!("device meets {verdicts:?}")))?;
+
+        let version_code = app.version_code.as_deref().map(str::parse).transpose();
+        let version_code = version_code.map_err(|_| invalid("versionCode is not a number"))?;
+        Ok(IntegrityVerdict {
+            package_name: expected.package_name.clone(),
+            version_code,
+            certificate_sha256_digests,
+            device_recognition,
+            timestamp_millis,
+            app_licensing: payload.account_details.map(|details| details.app_licensing_verdict),
+        })
+    }
+
+    /// The plaintext of the compact JWE `token`: unwraps the content key with the
+    /// decryption key, then opens the AES-GCM ciphertext.
+    fn decrypt(&self, token: &str) -> Result<Vec<u8>, AppAttestError> {
+        let [header, encrypted_key, iv, ciphertext, tag] = parts(token, "JWE")?;
+        check_header(header, "alg", "A256KW")?;
+        check_header(header, "enc", "A256GCM")?;
+
+        let encrypted_key = decode_part(encrypted_key)?;
+        let mut key = [0u8; 32];
+        KekAes256::new(&self.decryption_key.into())
+            .unwrap(&encrypted_key, &mut key)
+            .map_err(|_| invalid("the content key does not unwrap with the decryption key"))?;
+
+        let iv = decode_part(iv)?;
+        if iv.len() != 12 {
+            return Err(invalid("the IV is not 12 bytes"));
+        }
+        let sealed = [decode_part(ciphertext)?, decode_part(tag)?].concat();
+        Aes256Gcm::new(&key.into())
+            // The protected header, as it appears in the token, is the additional data.
+            .decrypt(Nonce::from_slice(&iv), Payload { msg: &sealed, aad: header.as_bytes() })
+            .map_err(|_| invalid("the token does not decrypt"))
+    }
+
+    /// The payload of the compact JWS `jws`, once its ES256 signature checks out.
+    fn verify_signature(&self, jws: &str) -> Result<Vec<u8>, AppAttestError> {
+        let [header, payload, signature] = parts(jws, "JWS")?;
+        check_header(header, "alg", "ES256")?;
+        // Raw `r || s`, not DER.
+        let signature =
+            Signature::from_slice(&decode_part(signature)?).map_err(|_| AppAttestError::InvalidSignature)?;
+        let signed = &jws[..header.len() + 1 + payload.len()];
+        self.verification_key.verify(signed.as_bytes(), &signature).map_err(|_| AppAttestError::InvalidSignature)?;
+        decode_part(payload)
+    }
+}
+
+fn invalid(reason: &str) -> AppAttestError {
+    AppAttestError::InvalidIntegrityToken(reason.to_string())
+}
+
+/// The `N` dot-separated parts of a compact JWE or JWS.
+fn parts<const N: usize>(compact: &str, kind: &str) -> Result<[&str; N], AppAttestError> {
+    let parts: Vec<&str> = compact.split('.').collect();
+    parts.try_into().map_err(|_| invalid(&format!("a compact {kind} has {N} parts")))
+}
+
+fn decode_part(part: &str) -> Result<Vec<u8>, AppAttestError> {
+    general_purpose::URL_SAFE_NO_PAD.decode(part).map_err(|_| invalid("a part is not unpadded base64url"))
+}
+
+/// Checks that the protected `header` sets `name` to `value`.
+fn check_header(header: &str, name: &str, value: &str) -> Result<(), AppAttestError> {
+    let json: serde_json::Value =
+        serde_json::from_slice(&decode_part(header)?).map_err(|_| invalid("the header is not JSON"))?;
+    match json.get(name).and_then(|v| v.as_str()) {
+        Some(found) if found == value => Ok(()),
+        found => Err(invalid(&format!("header {name} is {found:?}, expected {value}"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use aes_gcm::AeadInPlace;
+    use p256::ecdsa::{signature::Signer, SigningKey};
+    use sha2::{Digest, Sha256};
+
+    use super::*;
+
+    const DECRYPTION_KEY: [u8; 32] = [0x42; 32];
+    const PACKAGE: &str = "xyz.elus.aegis.attester";
+    const NONCE: &str = "R2Rra24fVm5xa2Mg";
+    /// 2025-10-14T00:00:00Z.
+    const NOW: i64 = 1_760_400_000;
+
+    fn signing_key() -> SigningKey {
+        SigningKey::from_bytes(&[0x07; 32].into()).unwrap()
+    }
+
+    /// The DER `SubjectPublicKeyInfo` the Play Console hands out for `key`.
+    fn spki(key: &SigningKey) -> Vec<u8> {
+        let prefix = [
+            0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08, 0x2a, 0x86, 0x48,
+            0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00,
+        ];
+        [prefix.as_slice(), key.verifying_key().to_encoded_point(false).as_bytes()].concat()
+    }
+
+    fn certificate_digest() -> [u8; 32] {
+        Sha256::digest(b"release signing certificate").into()
+    }
+
+    fn verifier() -> PlayIntegrityVerifier {
+        PlayIntegrityVerifier::new(&DECRYPTION_KEY, &spki(&signing_key())).unwrap()
+    }
+
+    fn expected() -> ExpectedIntegrity {
+        ExpectedIntegrity {
+            package_name: PACKAGE.into(),
+            nonce: NONCE.into(),
+            certificate_sha256_digests: vec![certificate_digest()],
+            min_device_recognition: DeviceRecognition::Device,
+            max_age_secs: 300,
+        }
+    }
+
+    fn b64(bytes: &[u8]) -> String {
+        general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+    }
+
+    /// A verdict issued at `NOW - 10` that passes [`expected`].
+    fn payload() -> serde_json::Value {
+        serde_json::json!({
+            "requestDetails": {
+                "requestPackageName": PACKAGE,
+                "nonce": NONCE,
+                "timestampMillis": ((NOW - 10) * 1000).to_string(),
+            },
+            "appIntegrity": {
+                "appRecognitionVerdict": "PLAY_RECOGNIZED",
+                "packageName": PACKAGE,
+                "certificateSha256Digest": [b64(&certificate_digest())],
+                "versionCode": "42",
+            },
+            "deviceIntegrity": {
+                "deviceRecognitionVerdict": ["MEETS_BASIC_INTEGRITY", "MEETS_DEVICE_INTEGRITY"],
+            },
+            "accountDetails": { "appLicensingVerdict": "LICENSED" },
+        })
+    }
+
+    /// Signs `payload` with `signing_key` and encrypts it under `decryption_key`, as Play does.
+    fn token_with(payload: &serde_json::Value, signing_key: &SigningKey, decryption_key: [u8; 32]) -> String {
+        let header = b64(br#"{"alg":"ES256"}"#);
+        let signed = format!("{header}.{}", b64(payload.to_string().as_bytes()));
+        let signature: Signature = signing_key.sign(signed.as_bytes());
+        let jws = format!("{signed}.{}", b64(&signature.to_bytes()));
+
+        let content_key = [0x0c; 32];
+        let mut encrypted_key = [0u8; 40];
+        KekAes256::new(&decryption_key.into()).wrap(&content_key, &mut encrypted_key).unwrap();
+        let iv = [0x1d; 12];
+        let header = b64(br#"{"alg":"A256KW","enc":"A256GCM"}"#);
+        let mut ciphertext = jws.into_bytes();
+        let tag = Aes256Gcm::new(&content_key.into())
+            .encrypt_in_place_detached(Nonce::from_slice(&iv), header.as_bytes(), &mut ciphertext)
+            .unwrap();
+        [header, b64(&encrypted_key), b64(&iv), b64(&ciphertext), b64(&tag)].join(".")
+    }
+
+    fn token(payload: &serde_json::Value) -> String {
+        token_with(payload, &signing_key(), DECRYPTION_KEY)
+    }
+
+    #[test]
+    fn verifies_a_token_and_reports_its_verdict() {
+        let verdict = verifier().verify(&token(&payload()), &expected(), NOW).unwrap();
+        assert_eq!(
+            verdict,
+            IntegrityVerdict {
+                package_name: PACKAGE.into(),
+                version_code: Some(42),
+                certificate_sha256_digests: vec![certificate_digest()],
+                device_recognition: DeviceRecognition::Device,
+                timestamp_millis: (NOW - 10) * 1000,
+                app_licensing: Some("LICENSED".into()),
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_tokens_from_other_keys_or_tampered() {
+        let other_signer = SigningKey::from_bytes(&[0x08; 32].into()).unwrap();
+        let forged = token_with(&payload(), &other_signer, DECRYPTION_KEY);
+        assert_eq!(verifier().decode(&forged), Err(AppAttestError::InvalidSignature));
+
+        let other_app = token_with(&payload(), &signing_key(), [0x43; 32]);
+        assert!(matches!(verifier().decode(&other_app), Err(AppAttestError::InvalidIntegrityToken(_))));
+
+        // A flipped ciphertext byte fails the GCM tag.
+        let token = token(&payload());
+        let mut parts: Vec<String> = token.split('.').map(str::to_string).collect();
+        let mut ciphertext = general_purpose::URL_SAFE_NO_PAD.decode(&parts[3]).unwrap();
+        ciphertext[0] ^= 0x01;
+        parts[3] = b64(&ciphertext);
+        assert!(matches!(verifier().decode(&parts.join(".")), Err(AppAttestError::InvalidIntegrityToken(_))));
+
+        for garbage in ["", "a.b.c", "not a token"] {
+            assert!(matches!(verifier().decode(garbage), Err(AppAttestError::InvalidIntegrityToken(_))), "{garbage:?}");
+        }
+    }
+
+    #[test]
+    fn rejects_verdicts_for_another_request() {
+        /// Why the verdict of [`payload`] with `edit` made to it is rejected.
+        fn rejected(edit: impl Fn(&mut serde_json::Value)) -> AppAttestError {
+            let mut payload = payload();
+            edit(&mut payload);
+            verifier().verify(&token(&payload), &expected(), NOW).unwrap_err()
+        }
+
+        assert_eq!(rejected(|p| p["requestDetails"]["nonce"] = "other".into()), AppAttestError::InvalidNonce);
+        let other = "com.example.other";
+        assert_eq!(
+            rejected(|p| p["requestDetails"]["requestPackageName"] = other.into()),
+            AppAttestError::InvalidPackageName
+        );
+        assert_eq!(rejected(|p| p["appIntegrity"]["packageName"] = other.into()), AppAttestError::InvalidPackageName);
+        let digests = |value: serde_json::Value| {
+            move |p: &mut serde_json::Value| p["appIntegrity"]["certificateSha256Digest"] = value.clone()
+        };
+        assert_eq!(rejected(digests(serde_json::json!([b64(&[0x01; 32])]))), AppAttestError::InvalidSignatureDigest);
+        assert_eq!(rejected(digests(serde_json::json!([]))), AppAttestError::InvalidSignatureDigest);
+    }
+
+    #[test]
+    fn rejects_weak_or_stale_verdicts() {
+        let mut unevaluated = payload();
+        unevaluated["appIntegrity"] = serde_json::json!({ "appRecognitionVerdict": "UNEVALUATED" });
+        let error = verifier().verify(&token(&unevaluated), &expected(), NOW).unwrap_err();
+        assert!(
+            matches!(error, AppAttestError::InvalidIntegrityVerdict(ref m) if m.contains("UNEVALUATED")),
+            "{error}"
+        );
+
+        let mut basic_only = payload();
+        basic_only["deviceIntegrity"]["deviceRecognitionVerdict"] = serde_json::json!(["MEETS_BASIC_INTEGRITY"]);
+        let basic = token(&basic_only);
+        assert!(matches!(verifier().verify(&basic, &expected(), NOW), Err(AppAttestError::InvalidIntegrityVerdict(_))));
+        let lenient = ExpectedIntegrity { min_device_recognition: DeviceRecognition::Basic, ..expected() };
+        assert_eq!(verifier().verify(&basic, &lenient, NOW).unwrap().device_recognition, DeviceRecognition::Basic);
+
+        let token = token(&payload());
+        assert_eq!(verifier().verify(&token, &expected(), NOW + 291), Err(AppAttestError::StaleIntegrityVerdict));
+        assert_eq!(verifier().verify(&token, &expected(), NOW - 11), Err(AppAttestError::StaleIntegrityVerdict));
+        assert!(verifier().verify(&token, &expected(), NOW + 290).is_ok());
+    }
+
+    #[test]
+    fn rejects_bad_keys_up_front() {
+        assert!(PlayIntegrityVerifier::new(&[0; 16], &spki(&signing_key())).is_err());
+        assert!(PlayIntegrityVerifier::new(&DECRYPTION_KEY, b"not a key").is_err());
+        assert!(!format!("{:?}", verifier()).contains("66, 66"), "the decryption key stays out of Debug");
+    }
+}
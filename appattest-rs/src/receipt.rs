@@ -0,0 +1,544 @@
+//! App Attest receipts: the PKCS #7 structure Apple hands out with an attestation, and again
+//! on every exchange, whose fields carry the app's fraud metric.
+//!
+//! Only the payload is parsed. Apple's signature over it is not checked, so a receipt's
+//! fields are only as trustworthy as the channel it came in on: an attestation that
+//! verified, or a TLS connection to Apple.
+
+use std::time::Duration;
+
+use crate::error::AppAttestError;
+
+/// The fields of a receipt, by Apple's numbering.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Receipt {
+    /// Team and bundle ID, e.g. `ABCD123456.com.example.app` (field 2).
+    pub app_id: String,
+    /// DER of the attested key's certificate (field 3).
+    pub attested_certificate: Vec<u8>,
+    /// Hash of the client data the receipt was issued for (field 4).
+    pub client_hash: Vec<u8>,
+    /// Opaque token Apple uses to track the key (field 5).
+    pub token: String,
+    /// `ATTEST` for the receipt in an attestation, `RECEIPT` for one from an exchange (field 6).
+    pub receipt_type: String,
+    /// `sandbox` or `production`, or absent in production receipts (field 7).
+    pub environment: Option<String>,
+    /// RFC 3339 time the receipt was made (field 12).
+    pub creation_time: String,
+    /// Roughly how many attested keys the device has made for the app in the last 30 days,
+    /// only in `RECEIPT` receipts (field 17).
+    pub risk_metric: Option<u32>,
+    /// RFC 3339 time from which the receipt may be exchanged (field 19).
+    pub not_before: Option<String>,
+    /// RFC 3339 time after which Apple no longer exchanges the receipt (field 21).
+    pub expiration_time: String,
+    /// The receipt as received, to send back in the next exchange.
+    pub raw: Vec<u8>,
+}
+
+const OID_SIGNED_DATA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x02];
+
+impl Receipt {
+    /// Parses a receipt from its BER, as it appears in an attestation's statement or in the
+    /// body of an exchange response.
+    pub fn from_ber(ber: &[u8]) -> Result<Self, AppAttestError> {
+        let mut input = Ber(ber);
+        let mut content_info = input.expect(SEQUENCE)?;
+        if content_info.expect(OID)?.0 != OID_SIGNED_DATA {
+            return Err(malformed("not a PKCS #7 signed data"));
+        }
+        let mut signed_data = content_info.expect(EXPLICIT_0)?.expect(SEQUENCE)?;
+        signed_data.expect(INTEGER)?; // version
+        signed_data.expect(SET)?; // digest algorithms
+        let mut encap_content_info = signed_data.expect(SEQUENCE)?;
+        encap_content_info.expect(OID)?;
+        let payload = encap_content_info.expect(EXPLICIT_0)?.octets()?;
+
+        let mut fields = Ber(&payload).expect(SET)?;
+        let mut receipt = Fields::default();
+        while !fields.is_empty() {
+            let mut field = fields.expect(SEQUENCE)?;
+            let kind = field.expect(INTEGER)?.unsigned()?;
+            field.expect(INTEGER)?; // version
+            let value = field.expect(OCTET_STRING)?.0.to_vec();
+            receipt.set(kind, value)?;
+        }
+        receipt.finish(ber.to_vec())
+    }
+
+    /// Whether to exchange this receipt with Apple at `now`, in seconds since the Unix epoch:
+    /// once its `not_before` has passed, or straight away if it has none, as in an
+    /// attestation's receipt, and until it expires. A time that does not parse counts as
+    /// passed.
+    pub fn needs_refresh(&self, now: i64) -> bool {
+        let passed = |time: &str| unix_time(time).map_or(true, |time| time <= now);
+        self.not_before.as_deref().map_or(true, passed) && !passed(&self.expiration_time)
+    }
+}
+
+/// What an [`AttestationVerifier`](crate::attestation::AttestationVerifier) requires of the
+/// receipt in each attestation it accepts, see
+/// [`with_receipt_policy`](crate::attestation::AttestationVerifier::with_receipt_policy).
+///
+/// An attestation's own receipt never has a risk metric, so `require_risk_metric` only
+/// passes receipts from an exchange. `not_before` is not checked: it says when a receipt
+/// may be exchanged, see [`Receipt::needs_refresh`], not when it becomes valid.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReceiptPolicy {
+    /// How long before the verification time the receipt may have been made.
+    pub max_age: Duration,
+    /// Whether a receipt without a risk metric is rejected.
+    pub require_risk_metric: bool,
+    /// Highest risk metric accepted. A receipt without one passes, unless one is required.
+    pub max_risk_metric: Option<u32>,
+}
+
+impl ReceiptPolicy {
+    /// Checks `receipt` at `now`, in seconds since the Unix epoch, failing with
+    /// [`AppAttestError::ReceiptRejected`] for a receipt made in the future, one older than
+    /// `max_age`, one that has expired, or one whose risk metric is missing or too high.
+    pub fn check(&self, receipt: &Receipt, now: i64) -> Result<(), AppAttestError> {
+        let created = field_time(&receipt.creation_time, "creation time")?;
+        if created > now {
+            return Err(rejected(format!("creation time {} is in the future", receipt.creation_time)));
+        }
+        let max_age = i64::try_from(self.max_age.as_secs()).unwrap_or(i64::MAX);
+        if now - created > max_age {
+            return Err(rejected(format!(
+                "creation time {} is more than {}s before the verification time",
+                receipt.creation_time, max_age
+            )));
+        }
+        if field_time(&receipt.expiration_time, "expiration time")? <= now {
+            return Err(rejected(format!("expired at {}", receipt.expiration_time)));
+        }
+        match (receipt.risk_metric, self.max_risk_metric) {
+            (None, _) if self.require_risk_metric => Err(rejected("no risk metric".into())),
+            (Some(metric), Some(max)) if metric > max => Err(rejected(format!("risk metric {metric} is above {max}"))),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn rejected(reason: String) -> AppAttestError {
+    AppAttestError::ReceiptRejected { reason }
+}
+
+fn field_time(value: &str, field: &str) -> Result<i64, AppAttestError> {
+    unix_time(value).ok_or_else(|| rejected(format!("{field} {value:?} is not an RFC 3339 UTC time")))
+}
+
+/// Seconds since the Unix epoch of an RFC 3339 time in UTC, e.g. `2025-07-04T19:39:02.554Z`,
+/// the form Apple writes; fractions of a second are dropped.
+fn unix_time(value: &str) -> Option<i64> {
+    let (date, time) = value.strip_suffix(['Z', 'z'])?.split_once(['T', 't'])?;
+    let [year, month, day] = date.split('-').collect::<Vec<_>>()[..] else {
+        return None;
+    };
+    let time = time.split_once('.').map_or(time, |(whole, _)| whole);
+    let [h, m, s] = time.split(':').collect::<Vec<_>>()[..] else {
+        return None;
+    };
+    let number = |digits: &str, len: usize| {
+        if digits.len() == len && digits.bytes().all(|b| b.is_ascii_digit()) {
+            digits.parse::<i64>().ok()
+        } else {
+            None
+        }
+    };
+    let (year, month, day) = (number(year, 4)?, number(month, 2)?, number(day, 2)?);
+    let (h, m, s) = (number(h, 2)?, number(m, 2)?, number(s, 2)?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || h > 23 || m > 59 || s > 60 {
+        return None;
+    }
+    Some(days_from_civil(year, month, day) * 86_400 + h * 3600 + m * 60 + s)
+}
+
+/// Days from 1970-01-01 to the civil date, from Howard Hinnant's `days_from_civil`.
+pub(crate) fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+#[derive(Default)]
+struct Fields {
+    app_id: Option<String>,
+    attested_certificate: Option<Vec<u8>>,
+    client_hash: Option<Vec<u8>>,
+    token: Option<String>,
+    receipt_type: Option<String>,
+    environment: Option<String>,
+    creation_time: Option<String>,
+    risk_metric: Option<u32>,
+    not_before: Option<String>,
+    expiration_time: Option<String>,
+}
+
+impl Fields {
+    fn set(&mut self, kind: u64, value: Vec<u8>) -> Result<(), AppAttestError> {
+        match kind {
+            2 => self.app_id = Some(text(value)?),
+            3 => self.attested_certificate = Some(value),
+            4 => self.client_hash = Some(value),
+            5 => self.token = Some(text(value)?),
+            6 => self.receipt_type = Some(text(value)?),
+            7 => self.environment = Some(text(value)?),
+            12 => self.creation_time = Some(text(value)?),
+            17 => {
+                let metric = text(value)?;
+                self.risk_metric = Some(metric.parse().map_err(|_| malformed("risk metric is not a number"))?);
+            }
+            19 => self.not_before = Some(text(value)?),
+            21 => self.expiration_time = Some(text(value)?),
+            // Apple may add fields; the documented ones are all that is read.
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn finish(self, raw: Vec<u8>) -> Result<Receipt, AppAttestError> {
+        fn required<T>(value: Option<T>, field: &str) -> Result<T, AppAttestError> {
+            value.ok_or_else(|| malformed(&format!("missing {field}")))
+        }
+        Ok(Receipt {
+            app_id: required(self.app_id, "app ID")?,
+            attested_certificate: required(self.attested_certificate, "attested certificate")?,
+            client_hash: required(self.client_hash, "client hash")?,
+            token: required(self.token, "token")?,
+            receipt_type: required(self.receipt_type, "receipt type")?,
+            environment: self.environment,
+            creation_time: required(self.creation_time, "creation time")?,
+            risk_metric: self.risk_metric,
+            not_before: self.not_before,
+            expiration_time: required(self.expiration_time, "expiration time")?,
+            raw,
+        })
+    }
+}
+
+fn malformed(what: &str) -> AppAttestError {
+    AppAttestError::Message(format!("malformed receipt: {what}"))
+}
+
+fn text(value: Vec<u8>) -> Result<String, AppAttestError> {
+    String::from_utf8(value).map_err(|_| malformed("field is not UTF-8"))
+}
+
+const INTEGER: u8 = 0x02;
+const OCTET_STRING: u8 = 0x04;
+const OID: u8 = 0x06;
+const SEQUENCE: u8 = 0x30;
+const SET: u8 = 0x31;
+const EXPLICIT_0: u8 = 0xa0;
+const CONSTRUCTED: u8 = 0x20;
+
+/// Deepest nesting of indefinite lengths or octet string pieces followed before giving up.
+const MAX_DEPTH: usize = 32;
+
+/// Reads BER, which receipts use instead of DER: Apple writes them with indefinite lengths
+/// and splits the payload into a constructed octet string. Only low tag numbers are read,
+/// which is all a receipt has.
+struct Ber<'a>(&'a [u8]);
+
+impl<'a> Ber<'a> {
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The next element, as its tag and contents.
+    fn next(&mut self) -> Result<(u8, Ber<'a>), AppAttestError> {
+        self.next_nested(0)
+    }
+
+    fn next_nested(&mut self, depth: usize) -> Result<(u8, Ber<'a>), AppAttestError> {
+        if depth > MAX_DEPTH {
+            return Err(malformed("nested too deeply"));
+        }
+        let [tag, first, rest @ ..] = self.0 else {
+            return Err(malformed("truncated"));
+        };
+        if tag & 0x1f == 0x1f {
+            return Err(malformed("high tag numbers are not supported"));
+        }
+        let (contents, rest) = match *first {
+            0x80 => {
+                if tag & CONSTRUCTED == 0 {
+                    return Err(malformed("indefinite length on a primitive element"));
+                }
+                // Skip the children to find the end-of-contents octets that close this one.
+                let mut children = Ber(rest);
+                while !children.0.starts_with(&[0, 0]) {
+                    children.next_nested(depth + 1)?;
+                }
+                let len = rest.len() - children.0.len();
+                (&rest[..len], &rest[len + 2..])
+            }
+            len if len < 0x80 => split(rest, len as usize)?,
+            len => {
+                let octets = (len & 0x7f) as usize;
+                if octets > 4 || rest.len() < octets {
+                    return Err(malformed("length out of range"));
+                }
+                let len = rest[..octets].iter().fold(0usize, |len, &byte| (len << 8) | byte as usize);
+                split(&rest[octets..], len)?
+            }
+        };
+        self.0 = rest;
+        Ok((*tag, Ber(contents)))
+    }
+
+    fn expect(&mut self, tag: u8) -> Result<Ber<'a>, AppAttestError> {
+        match self.next()? {
+            (found, contents) if found == tag => Ok(contents),
+            (found, _) => Err(malformed(&format!("expected tag {tag:#04x}, found {found:#04x}"))),
+        }
+    }
+
+    /// The contents of an octet string, joining the pieces of a constructed one.
+    fn octets(&mut self) -> Result<Vec<u8>, AppAttestError> {
+        self.octets_nested(0)
+    }
+
+    fn octets_nested(&mut self, depth: usize) -> Result<Vec<u8>, AppAttestError> {
+        if depth > MAX_DEPTH {
+            return Err(malformed("nested too deeply"));
+        }
+        let (tag, mut contents) = self.next()?;
+        match tag {
+            OCTET_STRING => Ok(contents.0.to_vec()),
+            _ if tag == OCTET_STRING | CONSTRUCTED => {
+                let mut octets = Vec::new();
+                while !contents.is_empty() {
+                    octets.extend(contents.octets_nested(depth + 1)?);
+                }
+                Ok(octets)
+            }
+            _ => Err(malformed(&format!("expected an octet string, found tag {tag:#04x}"))),
+        }
+    }
+
+    /// These contents as an unsigned integer of at most 8 bytes.
+    fn unsigned(&self) -> Result<u64, AppAttestError> {
+        match self.0 {
+            [] => Err(malformed("empty integer")),
+            [first, ..] if first & 0x80 != 0 => Err(malformed("negative integer")),
+            bytes if bytes.len() > 9 || (bytes.len() == 9 && bytes[0] != 0) => Err(malformed("integer too large")),
+            bytes => Ok(bytes.iter().fold(0, |value, &byte| (value << 8) | byte as u64)),
+        }
+    }
+}
+
+fn split(bytes: &[u8], len: usize) -> Result<(&[u8], &[u8]), AppAttestError> {
+    if bytes.len() < len {
+        return Err(malformed("truncated"));
+    }
+    Ok(bytes.split_at(len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attestation::Attestation;
+
+    fn fixture() -> Receipt {
+        let attestation = Attestation::from_base64(include_str!("../../apps/fixtures/attestation.b64").trim()).unwrap();
+        Receipt::from_ber(attestation.receipt()).unwrap()
+    }
+
+    #[test]
+    fn parses_the_receipt_of_an_attestation() {
+        let receipt = fixture();
+        assert_eq!(receipt.app_id, "LMRM26A744.xyz.elus.aegis.app-attester");
+        assert_eq!(receipt.receipt_type, "ATTEST");
+        assert_eq!(receipt.environment.as_deref(), Some("sandbox"));
+        assert_eq!(receipt.creation_time, "2025-07-04T19:39:02.554Z");
+        assert_eq!(receipt.expiration_time, "2025-10-02T19:39:02.554Z");
+        assert_eq!(receipt.client_hash.len(), 32);
+        assert!(receipt.token.starts_with("xhCL65hZsFj5x6du"));
+        // Attestation receipts have no metric yet; only exchanged ones do.
+        assert_eq!(receipt.risk_metric, None);
+        assert_eq!(receipt.not_before, None);
+    }
+
+    #[test]
+    fn attested_certificate_is_the_leaf_of_the_attestation() {
+        let receipt = fixture();
+        let (_, leaf) = x509_parser::parse_x509_certificate(&receipt.attested_certificate).unwrap();
+        assert!(leaf.subject().to_string().contains("AAA Certification"));
+    }
+
+    /// 2025-07-06T00:00:00Z.
+    const NOW: i64 = 1_751_760_000;
+
+    fn policy() -> ReceiptPolicy {
+        ReceiptPolicy { max_age: Duration::from_secs(7 * 86_400), require_risk_metric: false, max_risk_metric: None }
+    }
+
+    /// The recorded receipt as an exchange would return it, with `risk_metric`.
+    fn exchanged(risk_metric: u32) -> Receipt {
+        Receipt {
+            receipt_type: "RECEIPT".into(),
+            creation_time: "2025-07-05T00:00:00Z".into(),
+            risk_metric: Some(risk_metric),
+            not_before: Some("2025-07-06T00:00:00Z".into()),
+            expiration_time: "2025-10-03T00:00:00Z".into(),
+            ..fixture()
+        }
+    }
+
+    fn reason(result: Result<(), AppAttestError>) -> String {
+        match result {
+            Err(AppAttestError::ReceiptRejected { reason }) => reason,
+            other => panic!("expected a rejection, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn reads_rfc_3339_utc_times() {
+        assert_eq!(unix_time("2025-07-04T19:39:02.554Z"), Some(1_751_657_942));
+        assert_eq!(unix_time("2025-07-06T00:00:00Z"), Some(NOW));
+        assert_eq!(unix_time("1970-01-01t00:00:00z"), Some(0));
+        for bad in [
+            "",
+            "2025-07-06T00:00:00",
+            "2025-07-06T00:00:00+02:00",
+            "2025-07-06 00:00:00Z",
+            "2025-13-06T00:00:00Z",
+            "2025-07-06T24:00:00Z",
+            "+2025-07-06T00:00:00Z",
+            "99999999999999-07-06T00:00:00Z",
+        ] {
+            assert_eq!(unix_time(bad), None, "{bad}");
+        }
+    }
+
+    #[test]
+    fn policy_accepts_a_recent_receipt() {
+        assert_eq!(policy().check(&fixture(), NOW), Ok(()));
+        assert_eq!(policy().check(&exchanged(40), NOW), Ok(()));
+    }
+
+    #[test]
+    fn policy_rejects_a_receipt_by_its_times() {
+        let old = Receipt { creation_time: "2025-06-28T23:59:59Z".into(), ..exchanged(1) };
+        assert_eq!(
+            reason(policy().check(&old, NOW)),
+            "creation time 2025-06-28T23:59:59Z is more than 604800s before the verification time"
+        );
+        let just_in_time = Receipt { creation_time: "2025-06-29T00:00:00Z".into(), ..exchanged(1) };
+        assert_eq!(policy().check(&just_in_time, NOW), Ok(()));
+
+        let future = Receipt { creation_time: "2025-07-06T00:00:01Z".into(), ..exchanged(1) };
+        assert_eq!(reason(policy().check(&future, NOW)), "creation time 2025-07-06T00:00:01Z is in the future");
+
+        let expired = Receipt { expiration_time: "2025-07-06T00:00:00Z".into(), ..exchanged(1) };
+        assert_eq!(reason(policy().check(&expired, NOW)), "expired at 2025-07-06T00:00:00Z");
+
+        let garbled = Receipt { expiration_time: "next week".into(), ..exchanged(1) };
+        assert_eq!(
+            reason(policy().check(&garbled, NOW)),
+            "expiration time \"next week\" is not an RFC 3339 UTC time"
+        );
+
+        // Not before only says when the receipt may be exchanged.
+        let not_yet_exchangeable = Receipt { not_before: Some("2025-07-07T00:00:00Z".into()), ..exchanged(1) };
+        assert_eq!(policy().check(&not_yet_exchangeable, NOW), Ok(()));
+    }
+
+    #[test]
+    fn policy_rejects_a_receipt_by_its_risk_metric() {
+        let required = ReceiptPolicy { require_risk_metric: true, ..policy() };
+        assert_eq!(reason(required.check(&fixture(), NOW)), "no risk metric");
+        assert_eq!(required.check(&exchanged(0), NOW), Ok(()));
+
+        let capped = ReceiptPolicy { max_risk_metric: Some(5), ..policy() };
+        assert_eq!(capped.check(&exchanged(5), NOW), Ok(()));
+        assert_eq!(reason(capped.check(&exchanged(6), NOW)), "risk metric 6 is above 5");
+        // Without a metric there is nothing to cap, unless one is also required.
+        assert_eq!(capped.check(&fixture(), NOW), Ok(()));
+        let both = ReceiptPolicy { require_risk_metric: true, ..capped };
+        assert_eq!(reason(both.check(&fixture(), NOW)), "no risk metric");
+    }
+
+    #[test]
+    fn needs_refresh_between_not_before_and_expiration() {
+        // An attestation's receipt can be exchanged until it expires on 2025-10-02.
+        let attested = fixture();
+        assert!(attested.needs_refresh(NOW));
+        assert!(!attested.needs_refresh(unix_time("2025-10-02T19:39:02Z").unwrap()));
+
+        let exchanged = exchanged(1);
+        assert!(!exchanged.needs_refresh(NOW - 1));
+        assert!(exchanged.needs_refresh(NOW));
+        assert!(!exchanged.needs_refresh(unix_time("2025-10-03T00:00:00Z").unwrap()));
+
+        let garbled = Receipt { not_before: Some("soon".into()), ..exchanged.clone() };
+        assert!(garbled.needs_refresh(NOW - 1));
+        let garbled = Receipt { expiration_time: "never".into(), ..exchanged };
+        assert!(!garbled.needs_refresh(NOW));
+    }
+
+    /// `tag` and `contents` with a definite length.
+    fn tlv(tag: u8, contents: &[u8]) -> Vec<u8> {
+        let mut der = vec![tag];
+        match contents.len() {
+            len @ 0..=0x7f => der.push(len as u8),
+            len @ 0x80..=0xff => der.extend([0x81, len as u8]),
+            len => der.extend([0x82, (len >> 8) as u8, len as u8]),
+        }
+        der.extend(contents);
+        der
+    }
+
+    #[test]
+    fn reads_definite_lengths_and_split_octet_strings() {
+        // The shape Apple sends, but definite-length, with the payload in two pieces.
+        let field = |kind: u8, value: &[u8]| {
+            tlv(SEQUENCE, &[tlv(INTEGER, &[kind]), tlv(INTEGER, &[1]), tlv(OCTET_STRING, value)].concat())
+        };
+        let fields = [
+            field(2, b"TEAM.app"),
+            field(3, b"cert"),
+            field(4, &[7; 32]),
+            field(5, b"token"),
+            field(6, b"RECEIPT"),
+            field(12, b"2025-07-05T00:00:00Z"),
+            field(17, b"12"),
+            field(19, b"2025-07-06T00:00:00Z"),
+            field(21, b"2025-10-03T00:00:00Z"),
+            field(99, b"from the future"),
+        ];
+        let payload = tlv(SET, &fields.concat());
+        let (first, second) = payload.split_at(10);
+        let pieces = tlv(OCTET_STRING | CONSTRUCTED, &[tlv(OCTET_STRING, first), tlv(OCTET_STRING, second)].concat());
+        let encap = tlv(SEQUENCE, &[tlv(OID, &[0]), tlv(EXPLICIT_0, &pieces)].concat());
+        let signed = tlv(SEQUENCE, &[tlv(INTEGER, &[1]), tlv(SET, &[]), encap].concat());
+        let ber = tlv(SEQUENCE, &[tlv(OID, OID_SIGNED_DATA), tlv(EXPLICIT_0, &signed)].concat());
+
+        let receipt = Receipt::from_ber(&ber).unwrap();
+        assert_eq!(receipt.receipt_type, "RECEIPT");
+        assert_eq!(receipt.client_hash, [7; 32]);
+        assert_eq!(receipt.risk_metric, Some(12));
+        assert_eq!(receipt.not_before.as_deref(), Some("2025-07-06T00:00:00Z"));
+        assert_eq!(receipt.environment, None);
+    }
+
+    #[test]
+    fn rejects_what_is_not_a_receipt() {
+        let raw = fixture().raw;
+        for bad in [&[][..], &[SEQUENCE, 0x80, OID], &raw[..raw.len() / 2], &[SEQUENCE, 3, INTEGER, 1, 0]] {
+            assert!(Receipt::from_ber(bad).is_err(), "{bad:02x?}");
+        }
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error_not_a_stack_overflow() {
+        let nested = [SEQUENCE, 0x80].repeat(100_000);
+        assert_eq!(Receipt::from_ber(&nested), Err(malformed("nested too deeply")));
+    }
+}
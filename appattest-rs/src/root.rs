@@ -0,0 +1,446 @@
+//! Trusted root certificates: PEM decoding, the fingerprint Apple's root is pinned to, and
+//! loading roots from disk.
+//!
+//! `build.rs` includes this file with `#[path]` to check `certificates/` before embedding
+//! it, so it uses nothing but `std`, `base64`, `sha2` and `x509-parser`, which are build
+//! dependencies too.
+
+use std::{fmt, fs, path::Path};
+
+use base64::{engine::general_purpose, Engine};
+use sha2::{Digest, Sha256};
+use x509_parser::parse_x509_certificate;
+
+/// SHA-256 of the DER Apple App Attestation Root CA, as printed by
+/// `openssl x509 -in certificates/Apple_App_Attestation_Root_CA.pem -outform der | shasum -a 256`.
+///
+/// The pin is over the certificate rather than the PEM file, so line endings and trailing
+/// whitespace in the checkout do not matter.
+pub const APPLE_ROOT_SHA256: &str = "1cb9823ba28ba6ad2d33a006941de2ae4f513ef1d4e831b9f7e0fa7b6242c932";
+
+/// File stem of Apple's root in `certificates/`, the one store entry held to
+/// [`APPLE_ROOT_SHA256`].
+pub const APPLE_ROOT_NAME: &str = "Apple_App_Attestation_Root_CA";
+
+/// Directory under `certificates/` holding Google's hardware attestation roots, which
+/// `androidattest` trusts instead of the Apple store.
+pub const ANDROID_ROOT_DIR: &str = "android";
+
+/// Where the `fetch-root` feature downloads Apple's PEM from.
+pub const APPLE_ROOT_URL: &str = "https://www.apple.com/certificateauthority/Apple_App_Attestation_Root_CA.pem";
+
+/// Set to `1` to build with an Apple root that fails the pin, see [`check_apple_pin`].
+pub const ALLOW_STALE_ENV: &str = "APPATTEST_ALLOW_STALE_ROOT";
+
+/// Names a PEM or DER file of the root [`crate::attestation::AttestationVerifier::from_env`]
+/// trusts instead of the embedded one.
+pub const ROOT_ENV: &str = "APPATTEST_ROOT_CA";
+
+/// Why a root certificate could not be loaded.
+#[derive(Debug, PartialEq)]
+pub enum RootCertError {
+    /// The file could not be read.
+    Io(String),
+    /// The PEM is not UTF-8 or its body is not base64.
+    InvalidPem(String),
+    /// The bytes are not an X.509 certificate.
+    InvalidCertificate(String),
+    /// The certificate is not the pinned one.
+    PinMismatch { expected: String, actual: String },
+    /// Two files in a store hold the same certificate or have the same name.
+    Duplicate { file: String, first: String },
+    /// Loading `file` failed.
+    File { file: String, error: Box<RootCertError> },
+    /// Apple's root is not in the store, and there was no way to download it.
+    Missing,
+    /// Downloading Apple's root failed before any response, e.g. on an air-gapped machine.
+    Offline(String),
+    /// The download answered with this HTTP status.
+    Http(u16),
+}
+
+impl fmt::Display for RootCertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RootCertError::Io(e) => write!(f, "failed to read root certificate: {e}"),
+            RootCertError::InvalidPem(e) => write!(f, "invalid root certificate PEM: {e}"),
+            RootCertError::InvalidCertificate(e) => write!(f, "invalid root certificate: {e}"),
+            RootCertError::PinMismatch { expected, actual } => {
+                write!(f, "root certificate SHA-256 is {actual}, expected the pinned {expected}")
+            }
+            RootCertError::Duplicate { file, first } => write!(f, "{file} duplicates {first}"),
+            RootCertError::File { file, error } => write!(f, "{file}: {error}"),
+            RootCertError::Missing => write!(f, "{APPLE_ROOT_NAME}.pem is missing"),
+            RootCertError::Offline(e) => write!(f, "could not reach {APPLE_ROOT_URL}: {e}"),
+            RootCertError::Http(status) => write!(f, "{APPLE_ROOT_URL} answered HTTP {status}"),
+        }
+    }
+}
+
+impl RootCertError {
+    /// What to do about the error when it fails the build.
+    pub fn remediation(&self) -> String {
+        let place = format!(
+            "Place Apple's App Attestation root at certificates/{APPLE_ROOT_NAME}.pem; it is checked into git, so \
+             `git checkout -- certificates/` restores it. Its DER must have SHA-256 {APPLE_ROOT_SHA256}."
+        );
+        match self {
+            RootCertError::File { error, .. } => error.remediation(),
+            RootCertError::Missing => {
+                format!("{place} Alternatively, build with `--features fetch-root` to download {APPLE_ROOT_URL}.")
+            }
+            RootCertError::Offline(_) => format!("This machine looks offline. {place}"),
+            RootCertError::Http(_) => format!("Apple's server refused the download; retry later. {place}"),
+            RootCertError::PinMismatch { .. } => format!(
+                "If Apple has issued a new root, update APPLE_ROOT_SHA256 in src/root.rs after checking the \
+                 certificate out of band. To build with this file anyway, set {ALLOW_STALE_ENV}=1. {place}"
+            ),
+            RootCertError::Duplicate { .. } => "Remove one of the two files from certificates/.".into(),
+            RootCertError::Io(_) | RootCertError::InvalidPem(_) | RootCertError::InvalidCertificate(_) => {
+                "Replace or remove the file in certificates/; every *.pem and *.der there must be one certificate."
+                    .into()
+            }
+        }
+    }
+}
+
+/// Why a [`Fetch`] failed.
+#[derive(Debug, PartialEq)]
+pub enum FetchError {
+    /// No response: DNS, connection or timeout failures.
+    Offline(String),
+    /// A response with a non-success status.
+    Status(u16),
+}
+
+/// Downloads a URL. build.rs implements it over HTTP with the `fetch-root` feature; tests
+/// use canned responses.
+pub trait Fetch {
+    fn fetch(&self, url: &str) -> Result<Vec<u8>, FetchError>;
+}
+
+/// Makes sure `dir` holds Apple's root, as PEM or DER. If neither is there, downloads the
+/// PEM with `fetcher` and writes it only once it matches [`APPLE_ROOT_SHA256`].
+pub fn ensure_apple_root(dir: &Path, fetcher: Option<&dyn Fetch>) -> Result<(), RootCertError> {
+    if ["pem", "der"].iter().any(|ext| dir.join(format!("{APPLE_ROOT_NAME}.{ext}")).exists()) {
+        return Ok(());
+    }
+    let pem = fetcher.ok_or(RootCertError::Missing)?.fetch(APPLE_ROOT_URL).map_err(|e| match e {
+        FetchError::Offline(e) => RootCertError::Offline(e),
+        FetchError::Status(status) => RootCertError::Http(status),
+    })?;
+    decode_pinned(&pem)?;
+    let path = dir.join(format!("{APPLE_ROOT_NAME}.pem"));
+    fs::write(&path, pem).map_err(|e| RootCertError::Io(format!("{}: {e}", path.display())))
+}
+
+/// Checks that the store's [`APPLE_ROOT_NAME`] entry is the pinned root.
+///
+/// With `allow_stale`, a mismatch is returned as a warning for the build to print instead
+/// of an error: for an operator who must build before the pin catches up with a new root.
+pub fn check_apple_pin(roots: &[(String, Vec<u8>)], allow_stale: bool) -> Result<Option<String>, RootCertError> {
+    let (_, der) = roots.iter().find(|(name, _)| name == APPLE_ROOT_NAME).ok_or(RootCertError::Missing)?;
+    match check_pin(der, APPLE_ROOT_SHA256) {
+        Ok(()) => Ok(None),
+        Err(RootCertError::PinMismatch { expected, actual }) if allow_stale => Ok(Some(format!(
+            "USING AN UNPINNED APPLE ROOT: {APPLE_ROOT_NAME} has SHA-256 {actual}, not the pinned {expected}. \
+             Attestations will be checked against whatever that file holds. Unset {ALLOW_STALE_ENV} once the pin \
+             is updated."
+        ))),
+        Err(error) => Err(RootCertError::File { file: APPLE_ROOT_NAME.into(), error: Box::new(error) }),
+    }
+}
+
+impl std::error::Error for RootCertError {}
+
+/// Strips the PEM header and footer and base64-decodes the body.
+pub fn pem_to_der(pem: &[u8]) -> Result<Vec<u8>, RootCertError> {
+    let pem = std::str::from_utf8(pem).map_err(|e| RootCertError::InvalidPem(e.to_string()))?;
+    let b64: String = pem
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with("-----"))
+        .collect();
+    if b64.is_empty() {
+        return Err(RootCertError::InvalidPem("no certificate body".into()));
+    }
+    general_purpose::STANDARD
+        .decode(b64)
+        .map_err(|e| RootCertError::InvalidPem(e.to_string()))
+}
+
+/// Checks that `SHA256(der)` is `pin`, given as lowercase hex.
+pub fn check_pin(der: &[u8], pin: &str) -> Result<(), RootCertError> {
+    let actual: String = Sha256::digest(der).iter().map(|b| format!("{b:02x}")).collect();
+    if actual != pin {
+        return Err(RootCertError::PinMismatch {
+            expected: pin.to_string(),
+            actual,
+        });
+    }
+    Ok(())
+}
+
+/// Decodes `pem` and checks it is the certificate pinned by [`APPLE_ROOT_SHA256`].
+pub fn decode_pinned(pem: &[u8]) -> Result<Vec<u8>, RootCertError> {
+    let der = pem_to_der(pem)?;
+    check_pin(&der, APPLE_ROOT_SHA256)?;
+    Ok(der)
+}
+
+/// Reads the PEM at `path` and checks it like [`decode_pinned`].
+pub fn load_pinned(path: &Path) -> Result<Vec<u8>, RootCertError> {
+    decode_pinned(&read(path)?)
+}
+
+/// Reads the certificate at `path`, PEM or DER, without checking the pin: for roots the
+/// operator chose, such as a staging CA.
+pub fn load(path: &Path) -> Result<Vec<u8>, RootCertError> {
+    let bytes = read(path)?;
+    let der = if bytes.trim_ascii_start().starts_with(b"-----BEGIN") { pem_to_der(&bytes)? } else { bytes };
+    check_certificate(&der)?;
+    Ok(der)
+}
+
+/// Checks that `der` is exactly one X.509 certificate.
+pub fn check_certificate(der: &[u8]) -> Result<(), RootCertError> {
+    match parse_x509_certificate(der) {
+        Ok((rest, _)) if rest.is_empty() => Ok(()),
+        Ok(_) => Err(RootCertError::InvalidCertificate("trailing bytes after the certificate".into())),
+        Err(e) => Err(RootCertError::InvalidCertificate(e.to_string())),
+    }
+}
+
+/// The SHA-256 of the `SubjectPublicKeyInfo` of the certificate `der`, the form of
+/// [`crate::attestation::VerifyOptions::pinned_intermediate_spki_sha256`]. Unlike a
+/// certificate pin it survives the issuer being reissued with the same key.
+pub fn spki_sha256(der: &[u8]) -> Result<[u8; 32], RootCertError> {
+    let (_, cert) = parse_x509_certificate(der).map_err(|e| RootCertError::InvalidCertificate(e.to_string()))?;
+    Ok(Sha256::digest(cert.public_key().raw).into())
+}
+
+/// Loads every `*.pem` and `*.der` file in `dir` as a root store of `(file stem, DER)`,
+/// sorted by file name. Roots are trusted as checked in; [`check_apple_pin`] holds Apple's
+/// to the pin.
+///
+/// Fails on the first file that does not parse, and on two files with the same stem or
+/// certificate, naming the file.
+pub fn collect(dir: &Path) -> Result<Vec<(String, Vec<u8>)>, RootCertError> {
+    let entries = fs::read_dir(dir).map_err(|e| RootCertError::Io(format!("{}: {e}", dir.display())))?;
+    let mut paths = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(|e| RootCertError::Io(format!("{}: {e}", dir.display())))?.path();
+        if matches!(path.extension().and_then(|e| e.to_str()), Some("pem" | "der")) {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    let mut roots: Vec<(String, Vec<u8>, String)> = Vec::with_capacity(paths.len());
+    for path in paths {
+        let file = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        let name = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+        let in_file = |error| RootCertError::File { file: file.clone(), error: Box::new(error) };
+
+        let der = load(&path).map_err(in_file)?;
+        if let Some((_, _, first)) = roots.iter().find(|(n, d, _)| *n == name || *d == der) {
+            return Err(RootCertError::Duplicate { file, first: first.clone() });
+        }
+        roots.push((name, der, file));
+    }
+    Ok(roots.into_iter().map(|(name, der, _)| (name, der)).collect())
+}
+
+fn read(path: &Path) -> Result<Vec<u8>, RootCertError> {
+    fs::read(path).map_err(|e| RootCertError::Io(format!("{}: {e}", path.display())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VENDORED: &[u8] = include_bytes!("../certificates/Apple_App_Attestation_Root_CA.pem");
+
+    #[test]
+    fn vendored_root_matches_the_pin() {
+        let der = decode_pinned(VENDORED).unwrap();
+        // A DER SEQUENCE with a two-byte length.
+        assert_eq!(der[..2], [0x30, 0x82]);
+        assert_eq!(der.len(), 4 + u16::from_be_bytes([der[2], der[3]]) as usize);
+    }
+
+    #[test]
+    fn line_endings_do_not_change_the_certificate() {
+        let crlf = String::from_utf8(VENDORED.to_vec()).unwrap().replace('\n', "\r\n");
+        assert_eq!(decode_pinned(crlf.as_bytes()), decode_pinned(VENDORED));
+    }
+
+    #[test]
+    fn other_certificates_fail_the_pin() {
+        let mut der = pem_to_der(VENDORED).unwrap();
+        der[100] ^= 0x01;
+        assert!(matches!(
+            check_pin(&der, APPLE_ROOT_SHA256),
+            Err(RootCertError::PinMismatch { expected, .. }) if expected == APPLE_ROOT_SHA256
+        ));
+    }
+
+    #[test]
+    fn spki_digests_cover_only_the_key() {
+        let mut der = pem_to_der(VENDORED).unwrap();
+        let pin = spki_sha256(&der).unwrap();
+        assert_ne!(pin, <[u8; 32]>::from(Sha256::digest(&der)));
+        // The last byte is the signature's, outside the key.
+        *der.last_mut().unwrap() ^= 0x01;
+        assert_eq!(spki_sha256(&der), Ok(pin));
+        assert!(matches!(spki_sha256(b"not a certificate"), Err(RootCertError::InvalidCertificate(_))));
+    }
+
+    #[test]
+    fn malformed_pem_is_rejected() {
+        let empty = b"-----BEGIN CERTIFICATE-----\n-----END CERTIFICATE-----\n";
+        assert!(matches!(pem_to_der(empty), Err(RootCertError::InvalidPem(_))));
+        assert!(matches!(pem_to_der(b"not * base64"), Err(RootCertError::InvalidPem(_))));
+        let missing = load_pinned(Path::new("/nonexistent/root.pem"));
+        assert!(matches!(missing, Err(RootCertError::Io(_))));
+    }
+
+    #[test]
+    fn collect_reads_every_root_in_the_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("Apple_App_Attestation_Root_CA.pem"), VENDORED).unwrap();
+        fs::write(dir.path().join("README.md"), "not a certificate").unwrap();
+        let roots = collect(dir.path()).unwrap();
+        assert_eq!(roots, [(APPLE_ROOT_NAME.to_string(), pem_to_der(VENDORED).unwrap())]);
+    }
+
+    #[test]
+    fn collect_names_the_offending_file() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.pem"), VENDORED).unwrap();
+        fs::write(dir.path().join("b.der"), pem_to_der(VENDORED).unwrap()).unwrap();
+        let error = collect(dir.path()).unwrap_err();
+        assert_eq!(error, RootCertError::Duplicate { file: "b.der".into(), first: "a.pem".into() });
+
+        fs::remove_file(dir.path().join("b.der")).unwrap();
+        fs::write(dir.path().join("a.der"), b"not a certificate").unwrap();
+        let error = collect(dir.path()).unwrap_err();
+        assert!(matches!(&error, RootCertError::File { file, .. } if file == "a.der"), "{error}");
+        assert!(error.to_string().starts_with("a.der: invalid root certificate"), "{error}");
+    }
+
+    #[test]
+    fn an_updated_pem_replaces_its_store_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("staging.pem");
+        fs::write(&path, VENDORED).unwrap();
+        let before = collect(dir.path()).unwrap();
+
+        // A new root under the same file name, as when a CA is reissued.
+        let mut der = pem_to_der(VENDORED).unwrap();
+        der[100] ^= 0x01;
+        let body = general_purpose::STANDARD.encode(&der);
+        fs::write(&path, format!("-----BEGIN CERTIFICATE-----\n{body}\n-----END CERTIFICATE-----\n")).unwrap();
+        assert_eq!(collect(dir.path()).unwrap(), [("staging".to_string(), der)]);
+        assert_ne!(collect(dir.path()).unwrap(), before);
+
+        // A stale DER left beside it is refused rather than embedded.
+        fs::write(dir.path().join("staging.der"), &before[0].1).unwrap();
+        assert!(matches!(collect(dir.path()), Err(RootCertError::Duplicate { .. })));
+    }
+
+    #[test]
+    fn apples_root_is_held_to_the_pin_unless_stale_roots_are_allowed() {
+        let pinned = vec![(APPLE_ROOT_NAME.to_string(), pem_to_der(VENDORED).unwrap())];
+        assert_eq!(check_apple_pin(&pinned, false), Ok(None));
+
+        let mut stale = pinned.clone();
+        stale[0].1[100] ^= 0x01;
+        match check_apple_pin(&stale, false).unwrap_err() {
+            RootCertError::File { error, .. } => assert!(matches!(*error, RootCertError::PinMismatch { .. })),
+            other => panic!("expected a pin mismatch, got {other}"),
+        }
+        let warning = check_apple_pin(&stale, true).unwrap().unwrap();
+        assert!(warning.contains(APPLE_ROOT_SHA256) && warning.contains(ALLOW_STALE_ENV), "{warning}");
+
+        let other = vec![("staging".to_string(), pinned[0].1.clone())];
+        assert_eq!(check_apple_pin(&other, true), Err(RootCertError::Missing));
+    }
+
+    /// Answers every fetch with `response`, counting the calls.
+    struct Canned {
+        response: Result<Vec<u8>, FetchError>,
+        calls: std::cell::Cell<usize>,
+    }
+
+    impl Canned {
+        fn new(response: Result<Vec<u8>, FetchError>) -> Self {
+            Canned { response, calls: std::cell::Cell::new(0) }
+        }
+    }
+
+    impl Fetch for Canned {
+        fn fetch(&self, url: &str) -> Result<Vec<u8>, FetchError> {
+            assert_eq!(url, APPLE_ROOT_URL);
+            self.calls.set(self.calls.get() + 1);
+            match &self.response {
+                Ok(body) => Ok(body.clone()),
+                Err(FetchError::Offline(e)) => Err(FetchError::Offline(e.clone())),
+                Err(FetchError::Status(status)) => Err(FetchError::Status(*status)),
+            }
+        }
+    }
+
+    #[test]
+    fn ensure_apple_root_downloads_only_when_missing() {
+        let dir = tempfile::tempdir().unwrap();
+        let pem = dir.path().join("Apple_App_Attestation_Root_CA.pem");
+        let fetcher = Canned::new(Ok(VENDORED.to_vec()));
+
+        ensure_apple_root(dir.path(), Some(&fetcher)).unwrap();
+        assert_eq!(fs::read(&pem).unwrap(), VENDORED);
+        ensure_apple_root(dir.path(), Some(&fetcher)).unwrap();
+        assert_eq!(fetcher.calls.get(), 1);
+    }
+
+    #[test]
+    fn ensure_apple_root_tells_failures_apart() {
+        let dir = tempfile::tempdir().unwrap();
+        let ensure = |response| ensure_apple_root(dir.path(), Some(&Canned::new(response))).unwrap_err();
+
+        assert_eq!(ensure_apple_root(dir.path(), None), Err(RootCertError::Missing));
+        assert_eq!(ensure(Err(FetchError::Offline("dns error".into()))), RootCertError::Offline("dns error".into()));
+        assert_eq!(ensure(Err(FetchError::Status(503))), RootCertError::Http(503));
+
+        let mut tampered = pem_to_der(VENDORED).unwrap();
+        tampered[100] ^= 0x01;
+        let body = general_purpose::STANDARD.encode(tampered);
+        let tampered = format!("-----BEGIN CERTIFICATE-----\n{body}\n-----END CERTIFICATE-----\n");
+        assert!(matches!(ensure(Ok(tampered.into_bytes())), RootCertError::PinMismatch { .. }));
+        // Nothing that failed the pin was kept.
+        assert!(!dir.path().join("Apple_App_Attestation_Root_CA.pem").exists());
+    }
+
+    #[test]
+    fn remediation_says_where_the_root_goes_and_what_it_hashes_to() {
+        for error in [RootCertError::Missing, RootCertError::Offline("timed out".into()), RootCertError::Http(404)] {
+            let advice = error.remediation();
+            assert!(advice.contains("certificates/Apple_App_Attestation_Root_CA.pem"), "{advice}");
+            assert!(advice.contains(APPLE_ROOT_SHA256), "{advice}");
+        }
+        assert!(RootCertError::Missing.remediation().contains("fetch-root"));
+        let mismatch = RootCertError::PinMismatch { expected: APPLE_ROOT_SHA256.into(), actual: "00".into() };
+        let in_file = RootCertError::File { file: APPLE_ROOT_NAME.into(), error: Box::new(mismatch) };
+        assert!(in_file.remediation().contains(ALLOW_STALE_ENV));
+    }
+
+    #[test]
+    fn load_accepts_pem_and_der() {
+        let dir = tempfile::tempdir().unwrap();
+        let der = pem_to_der(VENDORED).unwrap();
+        fs::write(dir.path().join("root.pem"), VENDORED).unwrap();
+        fs::write(dir.path().join("root.der"), &der).unwrap();
+        assert_eq!(load(&dir.path().join("root.pem")).unwrap(), der);
+        assert_eq!(load(&dir.path().join("root.der")).unwrap(), der);
+    }
+}
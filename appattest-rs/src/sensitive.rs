@@ -0,0 +1,104 @@
+//! Keeping replayable input out of logs and error messages.
+//!
+//! Whoever reads a challenge, client data, signature or a whole attestation or assertion
+//! object can replay it, so none of them may end up in a log. [`Sensitive`] wraps such a
+//! value and formats as its length and the first bytes of its SHA-256: enough to match a
+//! log line with a request, not to rebuild the request. The `Debug` of each type that holds
+//! one goes through it, and [`base64_problem`] describes bad base64 without quoting it.
+
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+use base64::DecodeError;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Bytes that are formatted as `<len bytes, sha256 1a2b3c4d…>` rather than shown. It
+/// serializes, compares and derefs as the value it wraps.
+#[derive(Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Sensitive<T>(pub T);
+
+/// Bytes of the SHA-256 a [`Sensitive`] shows.
+const SHOWN_DIGEST_LEN: usize = 4;
+
+impl<T> Sensitive<T> {
+    /// Returns the wrapped value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: AsRef<[u8]>> fmt::Display for Sensitive<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bytes = self.0.as_ref();
+        let digest = Sha256::digest(bytes);
+        write!(f, "<{} bytes, sha256 {}…>", bytes.len(), hex::encode(&digest[..SHOWN_DIGEST_LEN]))
+    }
+}
+
+impl<T: AsRef<[u8]>> fmt::Debug for Sensitive<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl<T> Deref for Sensitive<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Sensitive<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: AsRef<[u8]>> AsRef<[u8]> for Sensitive<T> {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_ref()
+    }
+}
+
+impl<T> From<T> for Sensitive<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+/// Describes what is wrong with base64 input by position only. `DecodeError`'s own
+/// `Display` quotes the offending byte, a byte of what may be a secret.
+pub fn base64_problem(error: &DecodeError) -> String {
+    match error {
+        DecodeError::InvalidByte(offset, _) => format!("invalid symbol at offset {offset}"),
+        DecodeError::InvalidLength(len) => format!("invalid length {len}"),
+        DecodeError::InvalidLastSymbol(offset, _) => format!("invalid last symbol at offset {offset}"),
+        DecodeError::InvalidPadding => "invalid padding".into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose, Engine};
+
+    use super::*;
+
+    #[test]
+    fn sensitive_bytes_show_their_length_and_digest_prefix() {
+        let secret = Sensitive(b"challenge".to_vec());
+        assert_eq!(format!("{secret:?}"), "<9 bytes, sha256 2dd00bd7…>");
+        assert_eq!(secret.to_string(), format!("{secret:?}"));
+        assert_eq!(format!("{:?}", Sensitive(&b""[..])), "<0 bytes, sha256 e3b0c442…>");
+        assert_eq!(serde_json::to_string(&secret).unwrap(), serde_json::to_string(&*secret).unwrap());
+    }
+
+    #[test]
+    fn base64_problems_do_not_quote_the_input() {
+        let error = general_purpose::STANDARD.decode("AAA!").unwrap_err();
+        assert_eq!(base64_problem(&error), "invalid symbol at offset 3");
+        assert!(error.to_string().contains("33"));
+    }
+}
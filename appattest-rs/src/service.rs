@@ -0,0 +1,719 @@
+//! The key half of a relying party's flow over a [`KeyStore`]: storing keys that passed
+//! attestation, and checking their assertions against the stored counter, for keys of
+//! every [`Platform`] it knows how to load.
+//!
+//! Attestation itself stays with the platform's verifier, whose result gives the
+//! [`AttestedKey`] to register, e.g. an [`AppAttestKey`] from a
+//! [`crate::attestation::VerifiedAttestation`]. The exception is
+//! [`AppAttestService::verify_request`], for endpoints that take App Attest attestations
+//! and assertions alike, which verifies attestations with its own [`AttestationVerifier`],
+//! and [`AppAttestService::reattest`], which replaces a device's key with a newly attested
+//! one.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use sha2::{Digest, Sha256};
+
+use crate::attestation::{AttestationRef, AttestationVerifier};
+use crate::audit::{Audit, AuditEvent, AuditKind, AuditSink};
+use crate::detect::{classify, BlobKind};
+use crate::error::AppAttestError;
+use crate::key::KeyId;
+use crate::keypolicy::{KeyPolicy, Policy};
+use crate::platform::{AppAttestKey, AttestedKey, Platform};
+use crate::sensitive::Sensitive;
+use crate::store::{AcceptedAssertion, KeyRecord, KeyStore, DEFAULT_COUNTER_WARNING_THRESHOLD};
+
+/// Turns a stored record back into the key that checks its assertions.
+pub type KeyLoader = Box<dyn Fn(&KeyRecord) -> Result<Box<dyn AttestedKey>, AppAttestError> + Send + Sync>;
+
+/// What [`AppAttestService::verify_request`] checks a blob against besides the stored keys.
+#[derive(Clone, Copy)]
+pub struct RequestContext<'a> {
+    /// The challenge the server issued, which an attestation must be over. Consume it from
+    /// the [`crate::store::ChallengeStore`] before verifying.
+    pub challenge: &'a [u8],
+    /// The client data an assertion signs.
+    pub client_data: &'a [u8],
+    /// Unix time an attestation's certificates are checked at, and the key's expiry is
+    /// compared with.
+    pub time: i64,
+}
+
+impl fmt::Debug for RequestContext<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequestContext")
+            .field("challenge", &Sensitive(self.challenge))
+            .field("client_data", &Sensitive(self.client_data))
+            .field("time", &self.time)
+            .finish()
+    }
+}
+
+/// A new key's attestation, for [`AppAttestService::reattest`].
+#[derive(Clone, Copy)]
+pub struct AttestationRequest<'a> {
+    /// The ID of the new key, as sent by the client.
+    pub key_id: KeyId,
+    /// The App Attest attestation object.
+    pub attestation: &'a [u8],
+    /// The challenge the server issued, which the attestation must be over. Consume it from
+    /// the [`crate::store::ChallengeStore`] before verifying.
+    pub challenge: &'a [u8],
+    /// Unix time the attestation's certificates are checked at.
+    pub time: i64,
+}
+
+impl fmt::Debug for AttestationRequest<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AttestationRequest")
+            .field("key_id", &self.key_id)
+            .field("attestation", &Sensitive(self.attestation))
+            .field("challenge", &Sensitive(self.challenge))
+            .field("time", &self.time)
+            .finish()
+    }
+}
+
+/// An assertion by a stored key over `client_data`, the proof of continuity
+/// [`AppAttestService::reattest`] takes from the key being replaced.
+#[derive(Clone, Copy)]
+pub struct AssertionRequest<'a> {
+    pub assertion: &'a [u8],
+    /// The client data the assertion signs. For a re-attestation, the new attestation's
+    /// challenge, so that the proof cannot be reused for another.
+    pub client_data: &'a [u8],
+}
+
+impl fmt::Debug for AssertionRequest<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AssertionRequest")
+            .field("assertion", &Sensitive(self.assertion))
+            .field("client_data", &Sensitive(self.client_data))
+            .finish()
+    }
+}
+
+/// What [`AppAttestService::verify_request`] took a blob for, and its result.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RequestOutcome {
+    /// An attestation, whose key is now stored.
+    Registered(KeyRecord),
+    /// An assertion by a stored key, whose counter moved to the assertion's.
+    Asserted(AcceptedAssertion),
+}
+
+/// A request [`AppAttestService::verify_request`] accepted.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VerifiedRequest {
+    /// What the blob was taken for, and its result.
+    pub outcome: RequestOutcome,
+    /// Whether the key's attestation certificate expires within the service's
+    /// [reattestation window](AppAttestService::with_reattestation_window). Advisory: the
+    /// request was accepted, but the client should attest a new key soon.
+    pub reattestation_recommended: bool,
+}
+
+/// Registers keys and checks their assertions. One service is shared between a server's
+/// workers: it takes no lock of its own, and two assertions racing for a key are decided by
+/// the store's [`KeyStore::update_counter`], after both were verified.
+pub struct AppAttestService {
+    keys: Arc<dyn KeyStore>,
+    app_id: String,
+    loaders: HashMap<Platform, KeyLoader>,
+    audit: Audit,
+    counter_warning_threshold: u32,
+    attestations: AttestationVerifier,
+    reattestation_window: Option<Duration>,
+    key_policy: Policy,
+}
+
+impl AppAttestService {
+    /// Keys in `keys`, whose assertions must be for `app_id`. Apple keys are loaded as
+    /// [`AppAttestKey`]s; other platforms need [`AppAttestService::with_platform`].
+    pub fn new(keys: Arc<dyn KeyStore>, app_id: impl Into<String>) -> Self {
+        let apple: KeyLoader = Box::new(|record| {
+            Ok(Box::new(AppAttestKey::new(record.public_key.clone(), record.environment)?) as Box<dyn AttestedKey>)
+        });
+        AppAttestService {
+            keys,
+            app_id: app_id.into(),
+            loaders: HashMap::from([(Platform::Apple, apple)]),
+            audit: Audit::default(),
+            counter_warning_threshold: DEFAULT_COUNTER_WARNING_THRESHOLD,
+            attestations: AttestationVerifier::embedded(),
+            reattestation_window: None,
+            key_policy: Policy::default(),
+        }
+    }
+
+    /// Loads the stored keys of `platform` with `load`, replacing any loader it had.
+    pub fn with_platform(
+        mut self,
+        platform: Platform,
+        load: impl Fn(&KeyRecord) -> Result<Box<dyn AttestedKey>, AppAttestError> + Send + Sync + 'static,
+    ) -> Self {
+        self.loaders.insert(platform, Box::new(load));
+        self
+    }
+
+    /// Records each registration and assertion to `sink`, see [`crate::audit`]: assertions
+    /// with the digests of the assertion and the client data, and the counter accepted.
+    pub fn with_audit(mut self, sink: Arc<dyn AuditSink>) -> Self {
+        self.audit = Audit(Some(sink));
+        self
+    }
+
+    /// Warns of keys whose counter passed `threshold`, see [`KeyRecord::verify_assertion`],
+    /// rather than [`DEFAULT_COUNTER_WARNING_THRESHOLD`].
+    pub fn with_counter_warning_threshold(mut self, threshold: u32) -> Self {
+        self.counter_warning_threshold = threshold;
+        self
+    }
+
+    /// Verifies the attestations given to [`AppAttestService::verify_request`] with
+    /// `verifier` rather than one trusting the embedded roots, under the service's key
+    /// policy if it has one.
+    pub fn with_attestation_verifier(mut self, verifier: AttestationVerifier) -> Self {
+        self.attestations = match &self.key_policy.0 {
+            Some(policy) => verifier.with_key_policy(policy.clone()),
+            None => verifier,
+        };
+        self
+    }
+
+    /// Consults `policy`, see [`crate::keypolicy`], on the key of each attestation
+    /// [`AppAttestService::verify_request`] verifies, as its attestation verifier does, and
+    /// of each assertion, with the stored public key, before its signature is checked.
+    pub fn with_key_policy(mut self, policy: Arc<dyn KeyPolicy>) -> Self {
+        self.attestations = self.attestations.with_key_policy(policy.clone());
+        self.key_policy = Policy(Some(policy));
+        self
+    }
+
+    /// Recommends re-attestation in [`AppAttestService::verify_request`]'s results for keys
+    /// whose attestation certificate expires within `window` of the request, see
+    /// [`KeyRecord::expires_within`]. Without a window it is never recommended.
+    pub fn with_reattestation_window(mut self, window: Duration) -> Self {
+        self.reattestation_window = Some(window);
+        self
+    }
+
+    /// The store the keys are in.
+    pub fn keys(&self) -> &Arc<dyn KeyStore> {
+        &self.keys
+    }
+
+    /// Stores `key`, which has just passed attestation with `receipt`, replacing any record
+    /// under its ID.
+    pub fn register(&self, key: &dyn AttestedKey, receipt: Vec<u8>) -> Result<KeyRecord, AppAttestError> {
+        let event = self.audit.start(|| {
+            AuditEvent::new(AuditKind::Registration).key_id(Some(key.key_id())).digest("receipt", &receipt)
+        });
+        let record = KeyRecord::new(key, receipt);
+        let result = self.keys.put(record.clone());
+        self.audit.finish(event, &result);
+        result?;
+        Ok(record)
+    }
+
+    /// Verifies `assertion` by the key stored under `key_id` over `client_data`, advances
+    /// the stored counter to the assertion's and returns it, with a warning if the key is
+    /// running out of counter values.
+    ///
+    /// Fails with `UnknownKeyID` if no key is stored under `key_id`, and with a `Message`
+    /// if its platform has no loader.
+    pub fn assert(&self, key_id: &KeyId, assertion: &[u8], client_data: &[u8]) -> Result<AcceptedAssertion, AppAttestError> {
+        self.audited_assertion(key_id, assertion, client_data).map(|(_, accepted)| accepted)
+    }
+
+    /// [`AppAttestService::assert`], also returning the key's record as it was before the
+    /// assertion.
+    fn audited_assertion(
+        &self,
+        key_id: &KeyId,
+        assertion: &[u8],
+        client_data: &[u8],
+    ) -> Result<(KeyRecord, AcceptedAssertion), AppAttestError> {
+        let event = self.audit.start(|| {
+            AuditEvent::new(AuditKind::Assertion)
+                .key_id(Some(*key_id))
+                .digest("assertion", assertion)
+                .digest("clientData", client_data)
+        });
+        let result = self.checked_assertion(key_id, assertion, client_data);
+        let counter = result.as_ref().ok().map(|(_, accepted)| accepted.counter);
+        self.audit.finish(event.map(|event| event.counter(counter)), &result);
+        result
+    }
+
+    /// Verifies `blob`, an App Attest attestation or assertion object by the key `key_id`,
+    /// as whichever [`classify`] takes it for: an attestation is verified for this service's
+    /// app and registered, an assertion is checked as by [`AppAttestService::assert`].
+    ///
+    /// Either way, the result recommends re-attestation if the key's certificate expires
+    /// within the [reattestation window](AppAttestService::with_reattestation_window) of
+    /// `context.time`.
+    ///
+    /// Fails with `UnrecognizedObject` if the blob is neither, without verifying anything.
+    /// Other platforms' assertions, which are not CBOR, go through `assert`.
+    pub fn verify_request(
+        &self,
+        key_id: &KeyId,
+        blob: &[u8],
+        context: &RequestContext<'_>,
+    ) -> Result<VerifiedRequest, AppAttestError> {
+        let (record, outcome) = match classify(blob) {
+            BlobKind::Attestation => {
+                let app_id_hash: [u8; 32] = Sha256::digest(&self.app_id).into();
+                let verified = self.attestations.verify_ref(
+                    &AttestationRef::from_cbor(blob)?,
+                    context.challenge,
+                    &app_id_hash,
+                    key_id.as_bytes(),
+                    context.time,
+                )?;
+                let key = AppAttestKey::try_from(&verified)?;
+                let record = self.register(&key, verified.receipt)?;
+                (record.clone(), RequestOutcome::Registered(record))
+            }
+            BlobKind::Assertion => {
+                let (record, accepted) = self.audited_assertion(key_id, blob, context.client_data)?;
+                (record, RequestOutcome::Asserted(accepted))
+            }
+            BlobKind::Unknown => return Err(AppAttestError::UnrecognizedObject),
+        };
+        let reattestation_recommended =
+            self.reattestation_window.is_some_and(|window| record.expires_within(window, context.time));
+        Ok(VerifiedRequest { outcome, reattestation_recommended })
+    }
+
+    /// Replaces the key of a device, stored under `device_id`, with the new key of
+    /// `request`, e.g. once [`VerifiedRequest::reattestation_recommended`] is set, and
+    /// returns the new record. The old record is archived, see [`KeyStore::archive`], so its
+    /// assertions fail with `UnknownKeyID` from then on.
+    ///
+    /// The request must prove it comes from the device: `proof_of_continuity` is an
+    /// assertion by the old key over the new attestation's challenge, checked as by
+    /// [`AppAttestService::assert`], so it advances the old key's counter. Without one,
+    /// only `admin_override`, for a device whose old key is lost, lets it through; otherwise
+    /// it fails with `ContinuityNotProven`. A proof over other client data fails with
+    /// `InvalidClientData`.
+    ///
+    /// Fails with `UnknownKeyID` if no key is stored under `device_id`, before anything is
+    /// verified, and leaves the old record live if the new attestation fails.
+    pub fn reattest(
+        &self,
+        device_id: &KeyId,
+        request: &AttestationRequest<'_>,
+        proof_of_continuity: Option<&AssertionRequest<'_>>,
+        admin_override: bool,
+    ) -> Result<KeyRecord, AppAttestError> {
+        if self.keys.get(device_id)?.is_none() {
+            return Err(AppAttestError::UnknownKeyID);
+        }
+        match proof_of_continuity {
+            Some(proof) if proof.client_data != request.challenge => return Err(AppAttestError::InvalidClientData),
+            Some(proof) => {
+                self.audited_assertion(device_id, proof.assertion, proof.client_data)?;
+            }
+            None if admin_override => {}
+            None => return Err(AppAttestError::ContinuityNotProven),
+        }
+
+        let app_id_hash: [u8; 32] = Sha256::digest(&self.app_id).into();
+        let verified = self.attestations.verify_ref(
+            &AttestationRef::from_cbor(request.attestation)?,
+            request.challenge,
+            &app_id_hash,
+            request.key_id.as_bytes(),
+            request.time,
+        )?;
+        let key = AppAttestKey::try_from(&verified)?;
+        // Archived first, so that a device attesting under its old key ID keeps the new record.
+        self.keys.archive(device_id)?;
+        self.register(&key, verified.receipt)
+    }
+
+    fn checked_assertion(
+        &self,
+        key_id: &KeyId,
+        assertion: &[u8],
+        client_data: &[u8],
+    ) -> Result<(KeyRecord, AcceptedAssertion), AppAttestError> {
+        let record = self.keys.get(key_id)?.ok_or(AppAttestError::UnknownKeyID)?;
+        self.key_policy.check(Ok(*key_id), Some(&record.public_key))?;
+        let load = self
+            .loaders
+            .get(&record.platform)
+            .ok_or_else(|| AppAttestError::Message(format!("no verifier for {} keys", record.platform.as_str())))?;
+        let key = load(&record)?;
+        let accepted =
+            record.verify_assertion(&*key, assertion, client_data, &self.app_id, self.counter_warning_threshold)?;
+        // Re-checked under the store's lock: a concurrent assertion may have advanced the counter.
+        self.keys.update_counter(key_id, accepted.counter)?;
+        Ok((record, accepted))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose, Engine};
+
+    use super::*;
+    use crate::attestation::{Attestation, Environment};
+    use crate::store::{InMemoryKeyStore, KeyWarning};
+
+    const APP_ID: &str = "LMRM26A744.xyz.elus.aegis.app-attester";
+
+    fn decoded(base64: &str) -> Vec<u8> {
+        general_purpose::STANDARD.decode(base64.trim()).unwrap()
+    }
+
+    fn apple_key() -> AppAttestKey {
+        let attestation = Attestation::from_base64(include_str!("../../apps/fixtures/attestation.b64").trim()).unwrap();
+        let verified = attestation
+            .verify_raw(
+                &decoded("ExYiYJ4jA3rGL9oQN8ihDP4seccnxwHSEU28u8aCQaU="),
+                &Sha256::digest(APP_ID).into(),
+                &decoded("HD8zVqwgesSBwPaYj5icRg1GI/b6SOoa4TqieTEBA0g="),
+                1_751_800_000,
+            )
+            .unwrap();
+        AppAttestKey::try_from(&verified).unwrap()
+    }
+
+    /// A second platform whose assertions are `counter || client_data` in the clear.
+    struct StubKey {
+        key_id: KeyId,
+        public_key: Vec<u8>,
+    }
+
+    impl AttestedKey for StubKey {
+        fn platform(&self) -> Platform {
+            Platform::Android
+        }
+
+        fn public_key(&self) -> &[u8] {
+            &self.public_key
+        }
+
+        fn key_id(&self) -> KeyId {
+            self.key_id
+        }
+
+        fn environment(&self) -> Option<Environment> {
+            None
+        }
+
+        fn verify_assertion(
+            &self,
+            assertion: &[u8],
+            client_data: &[u8],
+            app_id: &str,
+            previous_counter: Option<u32>,
+        ) -> Result<u32, AppAttestError> {
+            assert_eq!(app_id, APP_ID);
+            let (counter, signed) = assertion.split_first_chunk().ok_or(AppAttestError::InvalidSignature)?;
+            if signed != client_data {
+                return Err(AppAttestError::InvalidSignature);
+            }
+            let counter = u32::from_be_bytes(*counter);
+            if previous_counter.is_some_and(|previous| counter <= previous) {
+                return Err(AppAttestError::InvalidCounter);
+            }
+            Ok(counter)
+        }
+    }
+
+    fn stub_key() -> StubKey {
+        StubKey { key_id: KeyId::from_bytes(&[7; 32]).unwrap(), public_key: b"stub key".to_vec() }
+    }
+
+    fn stub_assertion(counter: u32, client_data: &[u8]) -> Vec<u8> {
+        [&counter.to_be_bytes()[..], client_data].concat()
+    }
+
+    fn accepted(counter: u32) -> AcceptedAssertion {
+        AcceptedAssertion { counter, warning: None }
+    }
+
+    fn service() -> AppAttestService {
+        AppAttestService::new(Arc::new(InMemoryKeyStore::new()), APP_ID).with_platform(Platform::Android, |record| {
+            Ok(Box::new(StubKey { key_id: record.key_id, public_key: record.public_key.clone() }))
+        })
+    }
+
+    #[test]
+    fn apple_and_stub_keys_side_by_side() {
+        let service = service();
+        let apple = service.register(&apple_key(), b"receipt".to_vec()).unwrap();
+        assert_eq!(
+            (apple.platform, apple.environment, apple.counter),
+            (Platform::Apple, Some(Environment::Development), None)
+        );
+        let stub = service.register(&stub_key(), Vec::new()).unwrap();
+        assert_eq!((stub.platform, stub.environment), (Platform::Android, None));
+
+        let assertion = decoded(include_str!("../../apps/fixtures/assertion.b64"));
+        let client_data = decoded(include_str!("../../apps/fixtures/client_data.b64"));
+        assert_eq!(service.assert(&apple.key_id, &assertion, &client_data), Ok(accepted(1)));
+        assert_eq!(service.assert(&stub.key_id, &stub_assertion(5, b"hello"), b"hello"), Ok(accepted(5)));
+
+        // Each key's counter moved on its own, and replays of either fail.
+        assert_eq!(service.keys().get(&apple.key_id).unwrap().unwrap().counter, Some(1));
+        assert_eq!(service.keys().get(&stub.key_id).unwrap().unwrap().counter, Some(5));
+        assert_eq!(service.assert(&apple.key_id, &assertion, &client_data), Err(AppAttestError::InvalidCounter));
+        assert_eq!(
+            service.assert(&stub.key_id, &stub_assertion(5, b"hello"), b"hello"),
+            Err(AppAttestError::InvalidCounter)
+        );
+
+        // A key is only checked by its own platform's verifier.
+        assert_eq!(service.assert(&stub.key_id, &assertion, &client_data), Err(AppAttestError::InvalidSignature));
+        assert!(service.assert(&apple.key_id, &stub_assertion(6, b"hello"), b"hello").is_err());
+    }
+
+    #[test]
+    fn unknown_keys_and_platforms_are_errors() {
+        let service = AppAttestService::new(Arc::new(InMemoryKeyStore::new()), APP_ID);
+        let stub = stub_key();
+        assert_eq!(service.assert(&stub.key_id, b"", b""), Err(AppAttestError::UnknownKeyID));
+
+        service.register(&stub, Vec::new()).unwrap();
+        assert_eq!(
+            service.assert(&stub.key_id, &stub_assertion(1, b""), b""),
+            Err(AppAttestError::Message("no verifier for android keys".into()))
+        );
+    }
+
+    #[test]
+    fn counters_warn_near_and_fail_at_exhaustion() {
+        let service = service().with_counter_warning_threshold(u32::MAX - 10);
+        let stub = service.register(&stub_key(), Vec::new()).unwrap();
+        let assert_counter = |counter| service.assert(&stub.key_id, &stub_assertion(counter, b""), b"");
+
+        assert_eq!(assert_counter(u32::MAX - 10), Ok(accepted(u32::MAX - 10)));
+        let warning = KeyWarning::CounterNearExhaustion { counter: u32::MAX - 9, threshold: u32::MAX - 10 };
+        assert_eq!(assert_counter(u32::MAX - 9), Ok(AcceptedAssertion { counter: u32::MAX - 9, warning: Some(warning) }));
+        assert_eq!(
+            serde_json::to_value(warning).unwrap(),
+            serde_json::json!({"kind": "CounterNearExhaustion", "counter": u32::MAX - 9, "threshold": u32::MAX - 10})
+        );
+        assert_eq!(assert_counter(u32::MAX - 1).unwrap().counter, u32::MAX - 1);
+
+        // The last value is refused and not stored, so the key stays one short of it.
+        assert_eq!(assert_counter(u32::MAX), Err(AppAttestError::CounterExhausted));
+        assert_eq!(service.keys().get(&stub.key_id).unwrap().unwrap().counter, Some(u32::MAX - 1));
+    }
+
+    #[test]
+    fn first_assertions_may_start_at_zero_or_one() {
+        let service = service();
+        let stub = service.register(&stub_key(), Vec::new()).unwrap();
+        let assert_counter = |counter| service.assert(&stub.key_id, &stub_assertion(counter, b""), b"");
+        assert_eq!(assert_counter(0), Ok(accepted(0)));
+        assert_eq!(assert_counter(0), Err(AppAttestError::InvalidCounter));
+        assert_eq!(assert_counter(1), Ok(accepted(1)));
+        assert_eq!(assert_counter(1), Err(AppAttestError::InvalidCounter));
+
+        let other = StubKey { key_id: KeyId::from_bytes(&[8; 32]).unwrap(), ..stub_key() };
+        let other = service.register(&other, Vec::new()).unwrap();
+        assert_eq!(service.assert(&other.key_id, &stub_assertion(1, b""), b""), Ok(accepted(1)));
+        assert_eq!(service.assert(&other.key_id, &stub_assertion(0, b""), b""), Err(AppAttestError::InvalidCounter));
+    }
+
+    #[test]
+    fn requests_are_dispatched_by_their_shape() {
+        let service = service();
+        let key_id = KeyId::from_base64("HD8zVqwgesSBwPaYj5icRg1GI/b6SOoa4TqieTEBA0g=").unwrap();
+        let attestation = decoded(include_str!("../../apps/fixtures/attestation.b64"));
+        let assertion = decoded(include_str!("../../apps/fixtures/assertion.b64"));
+        let context = RequestContext {
+            challenge: &decoded("ExYiYJ4jA3rGL9oQN8ihDP4seccnxwHSEU28u8aCQaU="),
+            client_data: &decoded(include_str!("../../apps/fixtures/client_data.b64")),
+            time: 1_751_800_000,
+        };
+
+        // Before registration the assertion's key is unknown.
+        assert_eq!(service.verify_request(&key_id, &assertion, &context), Err(AppAttestError::UnknownKeyID));
+
+        let Ok(VerifiedRequest { outcome: RequestOutcome::Registered(record), reattestation_recommended: false }) =
+            service.verify_request(&key_id, &attestation, &context)
+        else {
+            panic!("the attestation did not register");
+        };
+        assert_eq!((record.key_id, record.platform, record.counter), (key_id, Platform::Apple, None));
+        assert_eq!(service.keys().get(&key_id).unwrap(), Some(record));
+
+        let asserted = VerifiedRequest { outcome: RequestOutcome::Asserted(accepted(1)), reattestation_recommended: false };
+        assert_eq!(service.verify_request(&key_id, &assertion, &context), Ok(asserted));
+        assert_eq!(service.verify_request(&key_id, &assertion, &context), Err(AppAttestError::InvalidCounter));
+    }
+
+    #[test]
+    fn requests_are_verified_as_what_they_look_like() {
+        let service = service();
+        let key_id = KeyId::from_base64("HD8zVqwgesSBwPaYj5icRg1GI/b6SOoa4TqieTEBA0g=").unwrap();
+        let attestation = decoded(include_str!("../../apps/fixtures/attestation.b64"));
+        let context = RequestContext { challenge: b"another challenge", client_data: b"", time: 1_751_800_000 };
+
+        // An attestation must still pass: over another challenge it registers nothing.
+        assert_eq!(service.verify_request(&key_id, &attestation, &context), Err(AppAttestError::InvalidNonce));
+        assert_eq!(service.keys().get(&key_id).unwrap(), None);
+
+        // Neither shape, truncated, or both at once: nothing is verified.
+        for blob in [&b""[..], &attestation[..attestation.len() - 1], &b"\xa3\x69signature\x40\x68authData\x40\x63fmt\x40"[..]] {
+            assert_eq!(service.verify_request(&key_id, blob, &context), Err(AppAttestError::UnrecognizedObject));
+        }
+    }
+
+    #[test]
+    fn reattestation_is_recommended_from_the_start_of_the_window() {
+        const LEAF_NOT_AFTER: i64 = 1_769_058_302;
+        const WINDOW: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+        let service = service().with_reattestation_window(WINDOW);
+        let key_id = KeyId::from_base64("HD8zVqwgesSBwPaYj5icRg1GI/b6SOoa4TqieTEBA0g=").unwrap();
+        let assertion = decoded(include_str!("../../apps/fixtures/assertion.b64"));
+        let client_data = decoded(include_str!("../../apps/fixtures/client_data.b64"));
+        let window_start = LEAF_NOT_AFTER - WINDOW.as_secs() as i64;
+
+        let attestation = decoded(include_str!("../../apps/fixtures/attestation.b64"));
+        let context = RequestContext {
+            challenge: &decoded("ExYiYJ4jA3rGL9oQN8ihDP4seccnxwHSEU28u8aCQaU="),
+            client_data: &client_data,
+            time: 1_751_800_000,
+        };
+        let registered = service.verify_request(&key_id, &attestation, &context).unwrap();
+        assert!(!registered.reattestation_recommended);
+        let RequestOutcome::Registered(record) = registered.outcome else { panic!("the attestation did not register") };
+        assert_eq!(record.leaf_not_after, Some(LEAF_NOT_AFTER));
+
+        // The recorded assertion is accepted once per registration; re-registering the key
+        // starts its counter over.
+        for (time, recommended) in
+            [(window_start - 1, false), (window_start, true), (LEAF_NOT_AFTER, true), (LEAF_NOT_AFTER + 1, true)]
+        {
+            service.register(&apple_key(), Vec::new()).unwrap();
+            let context = RequestContext { challenge: b"", client_data: &client_data, time };
+            let verified = service.verify_request(&key_id, &assertion, &context).unwrap();
+            assert_eq!(verified.outcome, RequestOutcome::Asserted(accepted(1)), "at {time}");
+            assert_eq!(verified.reattestation_recommended, recommended, "at {time}");
+        }
+
+        // Without a window it is never recommended, even past the expiry.
+        let service = self::service();
+        service.register(&apple_key(), Vec::new()).unwrap();
+        let context = RequestContext { challenge: b"", client_data: &client_data, time: LEAF_NOT_AFTER + 1 };
+        assert!(!service.verify_request(&key_id, &assertion, &context).unwrap().reattestation_recommended);
+    }
+
+    #[test]
+    fn denied_keys_are_turned_away_before_verification() {
+        let deny_list = Arc::new(crate::keypolicy::InMemoryKeyList::deny());
+        let loads = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counted = loads.clone();
+        let service = AppAttestService::new(Arc::new(InMemoryKeyStore::new()), APP_ID)
+            .with_platform(Platform::Android, move |record| {
+                counted.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                Ok(Box::new(StubKey { key_id: record.key_id, public_key: record.public_key.clone() }))
+            })
+            .with_key_policy(deny_list.clone());
+        let denied = || AppAttestError::KeyDenied { reason: "key on the deny list".into() };
+
+        // An assertion by a denied public key: the key is not even loaded, and its counter stays.
+        let stub = service.register(&stub_key(), Vec::new()).unwrap();
+        deny_list.insert_public_key(&stub.public_key);
+        assert_eq!(service.assert(&stub.key_id, &stub_assertion(1, b""), b""), Err(denied()));
+        assert_eq!(loads.load(std::sync::atomic::Ordering::SeqCst), 0);
+        assert_eq!(service.keys().get(&stub.key_id).unwrap().unwrap().counter, None);
+
+        // An attestation of a denied key ID registers nothing.
+        let key_id = KeyId::from_base64("HD8zVqwgesSBwPaYj5icRg1GI/b6SOoa4TqieTEBA0g=").unwrap();
+        deny_list.insert_key_id(key_id);
+        let attestation = decoded(include_str!("../../apps/fixtures/attestation.b64"));
+        let context = RequestContext {
+            challenge: &decoded("ExYiYJ4jA3rGL9oQN8ihDP4seccnxwHSEU28u8aCQaU="),
+            client_data: b"",
+            time: 1_751_800_000,
+        };
+        assert_eq!(service.verify_request(&key_id, &attestation, &context), Err(denied()));
+        assert_eq!(service.keys().get(&key_id).unwrap(), None);
+
+        deny_list.remove_public_key(&stub.public_key);
+        assert_eq!(service.assert(&stub.key_id, &stub_assertion(1, b""), b""), Ok(accepted(1)));
+    }
+
+    #[test]
+    fn reattestation_needs_the_old_key_or_an_override() {
+        let stub = stub_key();
+        let challenge = decoded("ExYiYJ4jA3rGL9oQN8ihDP4seccnxwHSEU28u8aCQaU=");
+        let attestation = decoded(include_str!("../../apps/fixtures/attestation.b64"));
+        let request = AttestationRequest {
+            key_id: KeyId::from_base64("HD8zVqwgesSBwPaYj5icRg1GI/b6SOoa4TqieTEBA0g=").unwrap(),
+            attestation: &attestation,
+            challenge: &challenge,
+            time: 1_751_800_000,
+        };
+        let old_assertion = stub_assertion(1, &challenge);
+        let proof = AssertionRequest { assertion: &old_assertion, client_data: &challenge };
+
+        // Without a proof, or with one over other client data or by another key, nothing changes.
+        let service = service();
+        service.register(&stub, Vec::new()).unwrap();
+        assert_eq!(service.reattest(&stub.key_id, &request, None, false), Err(AppAttestError::ContinuityNotProven));
+        let elsewhere = stub_assertion(1, b"elsewhere");
+        let misdirected = AssertionRequest { assertion: &elsewhere, client_data: b"elsewhere" };
+        assert_eq!(
+            service.reattest(&stub.key_id, &request, Some(&misdirected), false),
+            Err(AppAttestError::InvalidClientData)
+        );
+        let forged = AssertionRequest { assertion: &stub_assertion(1, b"forged"), client_data: &challenge };
+        assert_eq!(service.reattest(&stub.key_id, &request, Some(&forged), false), Err(AppAttestError::InvalidSignature));
+        assert_eq!(service.reattest(&request.key_id, &request, Some(&proof), false), Err(AppAttestError::UnknownKeyID));
+        assert_eq!(service.keys().get(&stub.key_id).unwrap().unwrap().counter, None);
+        assert_eq!(service.keys().get(&request.key_id).unwrap(), None);
+
+        // With the old key's assertion the new key takes over, and the old one is archived.
+        let keys = Arc::new(InMemoryKeyStore::new());
+        let service = AppAttestService::new(keys.clone(), APP_ID).with_platform(Platform::Android, |record| {
+            Ok(Box::new(StubKey { key_id: record.key_id, public_key: record.public_key.clone() }))
+        });
+        let old = service.register(&stub, Vec::new()).unwrap();
+        let new = service.reattest(&stub.key_id, &request, Some(&proof), false).unwrap();
+        assert_eq!((new.key_id, new.platform, new.counter), (request.key_id, Platform::Apple, None));
+        assert_eq!(service.keys().get(&request.key_id).unwrap(), Some(new));
+        assert_eq!(service.keys().get(&stub.key_id).unwrap(), None);
+        assert_eq!(keys.archived(&stub.key_id), [KeyRecord { counter: Some(1), ..old }]);
+
+        // The archived key verifies no more assertions, nor another re-attestation.
+        assert_eq!(
+            service.assert(&stub.key_id, &stub_assertion(2, b"hello"), b"hello"),
+            Err(AppAttestError::UnknownKeyID)
+        );
+        let again = stub_assertion(2, &challenge);
+        let again = AssertionRequest { assertion: &again, client_data: &challenge };
+        assert_eq!(service.reattest(&stub.key_id, &request, Some(&again), false), Err(AppAttestError::UnknownKeyID));
+
+        // An administrator may replace a key that is lost, but the attestation must still pass.
+        let service = self::service();
+        service.register(&stub, Vec::new()).unwrap();
+        let stale = AttestationRequest { challenge: b"another challenge", ..request };
+        assert_eq!(service.reattest(&stub.key_id, &stale, None, true), Err(AppAttestError::InvalidNonce));
+        assert!(service.keys().get(&stub.key_id).unwrap().is_some());
+        assert_eq!(service.reattest(&stub.key_id, &request, None, true).unwrap().key_id, request.key_id);
+        assert_eq!(service.keys().get(&stub.key_id).unwrap(), None);
+    }
+
+    #[test]
+    fn records_at_the_last_counter_value_are_exhausted() {
+        let record = KeyRecord { counter: Some(u32::MAX), ..KeyRecord::new(&stub_key(), Vec::new()) };
+        assert_eq!(
+            record.verify_assertion(&stub_key(), &stub_assertion(1, b""), b"", APP_ID, u32::MAX),
+            Err(AppAttestError::CounterExhausted)
+        );
+    }
+}
@@ -0,0 +1,431 @@
+//! Server-side state for the attest/assert flow: issued challenges, attested keys and the
+//! assertions already seen.
+//!
+//! The traits are what a relying party implements over its own storage; the in-memory
+//! implementations are enough for tests and single-process deployments. With the `sqlite`
+//! feature, [`sqlite::SqliteKeyStore`] keeps keys in a database file; with the `redis`
+//! feature, the stores in [`redis`] share challenges and seen assertions between instances.
+//!
+//! The traits take `&self` and are `Send + Sync`, so a server shares one store between its
+//! workers behind an `Arc` and each implementation does its own locking. The ones here hold
+//! a single `Mutex` for the length of one call and call nothing else while holding it, so no
+//! two locks are ever taken together and a call cannot deadlock. A [`KeyStore`] decides a
+//! race between assertions in [`KeyStore::update_counter`], under its lock or in one
+//! statement, rather than by the caller holding a lock across the verification.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use base64::{engine::general_purpose, Engine};
+use serde::{Deserialize, Serialize};
+
+use crate::attestation::Environment;
+use crate::error::AppAttestError;
+use crate::key::KeyId;
+use crate::platform::{AttestedKey, Platform};
+
+#[cfg(feature = "redis")]
+pub mod redis;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+/// An attested key, as stored after a successful attestation.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct KeyRecord {
+    /// Key identifier, base64 in JSON as sent by the client.
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+    pub key_id: KeyId,
+    /// Uncompressed SEC1 public key from the credential certificate.
+    #[serde(with = "crate::json::bytes")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+    pub public_key: Vec<u8>,
+    /// Counter of the last accepted assertion; `None` right after attestation, so the first
+    /// assertion may have any counter, `0` included. Records stored before this was optional
+    /// hold `0` instead, and their first assertion must exceed it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub counter: Option<u32>,
+    /// App Attest receipt returned with the attestation, kept for fraud-metric refreshes.
+    #[serde(with = "crate::json::bytes")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+    pub receipt: Vec<u8>,
+    /// The App Attest environment of the key, when the attestation was verified in a way
+    /// that reports it, e.g. [`crate::attestation::Attestation::verify_raw`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<Environment>,
+    /// The platform that attested the key; `apple` for records from before there was a choice.
+    #[serde(default)]
+    pub platform: Platform,
+    /// Unix time the key's attestation certificate expires, see
+    /// [`KeyRecord::expires_within`]; `None` if the platform has none or the record predates
+    /// it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub leaf_not_after: Option<i64>,
+}
+
+impl KeyRecord {
+    /// The record of a key that has just passed attestation: `key`'s ID, public key,
+    /// environment, platform and certificate expiry, the `receipt` that came with it, and no
+    /// counter yet.
+    pub fn new(key: &dyn AttestedKey, receipt: Vec<u8>) -> Self {
+        KeyRecord {
+            key_id: key.key_id(),
+            public_key: key.public_key().to_vec(),
+            counter: None,
+            receipt,
+            environment: key.environment(),
+            platform: key.platform(),
+            leaf_not_after: key.leaf_not_after(),
+        }
+    }
+
+    /// Whether the attestation certificate expires within `window` of `now`, Unix seconds,
+    /// or has already: time to have the client attest a new key, though the assertions of
+    /// this one keep verifying. `false` when the expiry is unknown.
+    pub fn expires_within(&self, window: Duration, now: i64) -> bool {
+        let window = i64::try_from(window.as_secs()).unwrap_or(i64::MAX);
+        self.leaf_not_after.is_some_and(|not_after| not_after <= now.saturating_add(window))
+    }
+
+    /// Verifies `assertion` over `client_data` for `app_id` with `key`, this record's key as
+    /// loaded, against the stored counter. Does not store the new counter.
+    ///
+    /// Fails with `CounterExhausted` once the counter reaches `u32::MAX`, whichever platform
+    /// the key is from, and warns with [`KeyWarning::CounterNearExhaustion`] once it passes
+    /// `warning_threshold`, e.g. [`DEFAULT_COUNTER_WARNING_THRESHOLD`].
+    pub fn verify_assertion(
+        &self,
+        key: &dyn AttestedKey,
+        assertion: &[u8],
+        client_data: &[u8],
+        app_id: &str,
+        warning_threshold: u32,
+    ) -> Result<AcceptedAssertion, AppAttestError> {
+        if self.counter == Some(u32::MAX) {
+            return Err(AppAttestError::CounterExhausted);
+        }
+        let counter = key.verify_assertion(assertion, client_data, app_id, self.counter)?;
+        if counter == u32::MAX {
+            return Err(AppAttestError::CounterExhausted);
+        }
+        let warning = (counter > warning_threshold)
+            .then_some(KeyWarning::CounterNearExhaustion { counter, threshold: warning_threshold });
+        Ok(AcceptedAssertion { counter, warning })
+    }
+}
+
+/// The counter above which [`KeyRecord::verify_assertion`] warns by default: `i32::MAX`,
+/// past which a store keeping counters as signed 32-bit integers would overflow.
+pub const DEFAULT_COUNTER_WARNING_THRESHOLD: u32 = i32::MAX as u32;
+
+/// Something about an accepted assertion's key that the server should act on before it
+/// becomes an error. In JSON, `{"kind": "<variant>", ...}`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(tag = "kind")]
+pub enum KeyWarning {
+    /// The key's counter passed `threshold`. Have the client attest a new key: at
+    /// `u32::MAX` its assertions fail with `CounterExhausted`.
+    CounterNearExhaustion { counter: u32, threshold: u32 },
+}
+
+/// An assertion [`KeyRecord::verify_assertion`] accepted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct AcceptedAssertion {
+    /// The assertion's counter, to store as the record's.
+    pub counter: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warning: Option<KeyWarning>,
+}
+
+/// Storage for attested keys.
+///
+/// Failures of the storage itself are `AppAttestError::Message`s.
+pub trait KeyStore: Send + Sync {
+    fn get(&self, key_id: &KeyId) -> Result<Option<KeyRecord>, AppAttestError>;
+
+    /// Inserts `record`, replacing any existing record for the same key ID.
+    fn put(&self, record: KeyRecord) -> Result<(), AppAttestError>;
+
+    /// Advances the stored counter to `counter`.
+    ///
+    /// Fails with `UnknownKeyID` if there is no record, and with `InvalidCounter` unless
+    /// `counter` is greater than the stored value or there is none yet, so two racing
+    /// assertions cannot both win.
+    fn update_counter(&self, key_id: &KeyId, counter: u32) -> Result<(), AppAttestError>;
+
+    /// Moves the record out of the live keys, for a key a re-attestation replaced: `get`
+    /// returns `None` for it from then on, but the record is kept for audits rather than
+    /// deleted. Fails with `UnknownKeyID` if there is no record.
+    fn archive(&self, key_id: &KeyId) -> Result<(), AppAttestError>;
+}
+
+/// Issues single-use challenges and checks them when they come back.
+pub trait ChallengeStore: Send + Sync {
+    /// Returns a fresh base64 challenge.
+    fn issue(&self) -> Result<String, AppAttestError>;
+
+    /// Returns `true` if `challenge` was issued and has not expired, and forgets it either way.
+    fn consume(&self, challenge: &str) -> bool;
+}
+
+/// Remembers the assertions a server has accepted, for a time to live, so that one captured
+/// in transit cannot be submitted again, e.g. to another instance before the counter update
+/// reaches it.
+pub trait ReplayCache: Send + Sync {
+    /// Records `digest`, e.g. the SHA-256 of the assertion as received, and returns `true`
+    /// unless it was already recorded and has not expired.
+    fn insert(&self, digest: &[u8; 32]) -> bool;
+}
+
+#[derive(Default)]
+pub struct InMemoryKeyStore {
+    records: Mutex<Records>,
+}
+
+/// Live and archived records, behind one lock so that archiving takes only one.
+#[derive(Default)]
+struct Records {
+    live: HashMap<KeyId, KeyRecord>,
+    archived: HashMap<KeyId, Vec<KeyRecord>>,
+}
+
+impl InMemoryKeyStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The records archived under `key_id`, oldest first.
+    pub fn archived(&self, key_id: &KeyId) -> Vec<KeyRecord> {
+        self.records.lock().unwrap().archived.get(key_id).cloned().unwrap_or_default()
+    }
+}
+
+impl KeyStore for InMemoryKeyStore {
+    fn get(&self, key_id: &KeyId) -> Result<Option<KeyRecord>, AppAttestError> {
+        Ok(self.records.lock().unwrap().live.get(key_id).cloned())
+    }
+
+    fn put(&self, record: KeyRecord) -> Result<(), AppAttestError> {
+        self.records.lock().unwrap().live.insert(record.key_id, record);
+        Ok(())
+    }
+
+    fn update_counter(&self, key_id: &KeyId, counter: u32) -> Result<(), AppAttestError> {
+        let mut records = self.records.lock().unwrap();
+        let record = records.live.get_mut(key_id).ok_or(AppAttestError::UnknownKeyID)?;
+        if record.counter.is_some_and(|stored| counter <= stored) {
+            return Err(AppAttestError::InvalidCounter);
+        }
+        record.counter = Some(counter);
+        Ok(())
+    }
+
+    fn archive(&self, key_id: &KeyId) -> Result<(), AppAttestError> {
+        let mut records = self.records.lock().unwrap();
+        let record = records.live.remove(key_id).ok_or(AppAttestError::UnknownKeyID)?;
+        records.archived.entry(*key_id).or_default().push(record);
+        Ok(())
+    }
+}
+
+/// Challenges held in memory for a fixed time to live.
+pub struct InMemoryChallengeStore {
+    ttl: Duration,
+    issued: Mutex<HashMap<String, Instant>>,
+}
+
+impl InMemoryChallengeStore {
+    /// Length of the random challenges, before base64 encoding.
+    pub const CHALLENGE_LEN: usize = 32;
+
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            issued: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl ChallengeStore for InMemoryChallengeStore {
+    fn issue(&self) -> Result<String, AppAttestError> {
+        let mut bytes = [0u8; Self::CHALLENGE_LEN];
+        getrandom::getrandom(&mut bytes)
+            .map_err(|e| AppAttestError::Message(format!("failed to generate challenge: {}", e)))?;
+        let challenge = general_purpose::STANDARD.encode(bytes);
+
+        let mut issued = self.issued.lock().unwrap();
+        let now = Instant::now();
+        issued.retain(|_, at| now.duration_since(*at) < self.ttl);
+        issued.insert(challenge.clone(), now);
+        Ok(challenge)
+    }
+
+    fn consume(&self, challenge: &str) -> bool {
+        match self.issued.lock().unwrap().remove(challenge) {
+            Some(at) => at.elapsed() < self.ttl,
+            None => false,
+        }
+    }
+}
+
+/// Assertion digests held in memory for a fixed time to live.
+pub struct InMemoryReplayCache {
+    ttl: Duration,
+    seen: Mutex<HashMap<[u8; 32], Instant>>,
+}
+
+impl InMemoryReplayCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl ReplayCache for InMemoryReplayCache {
+    fn insert(&self, digest: &[u8; 32]) -> bool {
+        let mut seen = self.seen.lock().unwrap();
+        let now = Instant::now();
+        seen.retain(|_, at| now.duration_since(*at) < self.ttl);
+        seen.insert(*digest, now).is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The key ID of the recorded attestation in `apps/fixtures`.
+    pub(super) fn key_id() -> KeyId {
+        KeyId::from_base64("HD8zVqwgesSBwPaYj5icRg1GI/b6SOoa4TqieTEBA0g=").unwrap()
+    }
+
+    /// A key ID nothing is stored under.
+    pub(super) fn other_key_id() -> KeyId {
+        KeyId::from_bytes(&[2; 32]).unwrap()
+    }
+
+    pub(super) fn record() -> KeyRecord {
+        KeyRecord {
+            key_id: key_id(),
+            public_key: vec![4; 65],
+            counter: None,
+            receipt: Vec::new(),
+            environment: None,
+            platform: Platform::Apple,
+            leaf_not_after: None,
+        }
+    }
+
+    #[test]
+    fn counter_only_moves_forward() {
+        let store = InMemoryKeyStore::new();
+        store.put(record()).unwrap();
+
+        // The first counter may be 0, but only once.
+        store.update_counter(&key_id(), 0).unwrap();
+        assert_eq!(store.update_counter(&key_id(), 0), Err(AppAttestError::InvalidCounter));
+        store.update_counter(&key_id(), 2).unwrap();
+        assert_eq!(store.update_counter(&key_id(), 2), Err(AppAttestError::InvalidCounter));
+        assert_eq!(store.update_counter(&key_id(), 1), Err(AppAttestError::InvalidCounter));
+        assert_eq!(store.get(&key_id()).unwrap().unwrap().counter, Some(2));
+        assert_eq!(store.update_counter(&other_key_id(), 1), Err(AppAttestError::UnknownKeyID));
+    }
+
+    #[test]
+    fn archived_keys_are_kept_but_not_live() {
+        let store = InMemoryKeyStore::new();
+        store.put(record()).unwrap();
+        store.update_counter(&key_id(), 3).unwrap();
+        store.archive(&key_id()).unwrap();
+
+        assert_eq!(store.get(&key_id()).unwrap(), None);
+        assert_eq!(store.update_counter(&key_id(), 4), Err(AppAttestError::UnknownKeyID));
+        assert_eq!(store.archive(&key_id()), Err(AppAttestError::UnknownKeyID));
+        assert_eq!(store.archived(&key_id()), [KeyRecord { counter: Some(3), ..record() }]);
+
+        // Attested again and replaced again, the key has two archived records.
+        store.put(record()).unwrap();
+        store.archive(&key_id()).unwrap();
+        assert_eq!(store.archived(&key_id()).len(), 2);
+        assert!(store.archived(&other_key_id()).is_empty());
+    }
+
+    #[test]
+    fn expiry_is_due_from_the_start_of_the_window() {
+        let record = KeyRecord { leaf_not_after: Some(1_000), ..record() };
+        let day = Duration::from_secs(86_400);
+        assert!(!record.expires_within(day, 1_000 - 86_401));
+        assert!(record.expires_within(day, 1_000 - 86_400));
+        assert!(record.expires_within(Duration::ZERO, 1_000));
+        assert!(!record.expires_within(Duration::ZERO, 999));
+        // Already expired, or a window too long for an `i64`.
+        assert!(record.expires_within(day, 2_000));
+        assert!(record.expires_within(Duration::MAX, 0));
+
+        // Without a known expiry nothing is due.
+        assert!(!record().expires_within(Duration::MAX, i64::MAX));
+    }
+
+    #[test]
+    fn challenges_are_single_use() {
+        let store = InMemoryChallengeStore::new(Duration::from_secs(60));
+        let challenge = store.issue().unwrap();
+        assert_ne!(challenge, store.issue().unwrap());
+
+        assert!(store.consume(&challenge));
+        assert!(!store.consume(&challenge));
+        assert!(!store.consume("bmV2ZXIgaXNzdWVk"));
+    }
+
+    #[test]
+    fn expired_challenges_are_rejected() {
+        let store = InMemoryChallengeStore::new(Duration::ZERO);
+        let challenge = store.issue().unwrap();
+        assert!(!store.consume(&challenge));
+    }
+
+    #[test]
+    fn replays_are_caught_until_they_expire() {
+        let cache = InMemoryReplayCache::new(Duration::from_secs(60));
+        assert!(cache.insert(&[1; 32]));
+        assert!(!cache.insert(&[1; 32]));
+        assert!(cache.insert(&[2; 32]));
+
+        let cache = InMemoryReplayCache::new(Duration::ZERO);
+        assert!(cache.insert(&[1; 32]));
+        assert!(cache.insert(&[1; 32]));
+    }
+
+    #[test]
+    fn record_keeps_its_json_shape() {
+        let record =
+            KeyRecord { counter: Some(7), receipt: b"receipt".to_vec(), leaf_not_after: Some(1_771_000_000), ..record() };
+        crate::json::assert_golden(&record, include_str!("../../apps/fixtures/json/key_record.json"));
+
+        let record = KeyRecord { environment: Some(Environment::Production), ..record };
+        let json = serde_json::to_value(&record).unwrap();
+        assert_eq!(json["environment"], "production");
+        assert_eq!(serde_json::from_value::<KeyRecord>(json).unwrap(), record);
+
+        // Records stored before the platform field are Apple's.
+        let mut json = serde_json::to_value(KeyRecord { platform: Platform::Android, ..record }).unwrap();
+        assert_eq!(json["platform"], "android");
+        json.as_object_mut().unwrap().remove("platform");
+        assert_eq!(serde_json::from_value::<KeyRecord>(json).unwrap().platform, Platform::Apple);
+
+        // A key without assertions has no counter, which is not a counter of 0.
+        let fresh = serde_json::to_value(record()).unwrap();
+        assert!(fresh.get("counter").is_none());
+        assert_eq!(serde_json::from_value::<KeyRecord>(fresh).unwrap(), record());
+        let mut json = serde_json::to_value(record()).unwrap();
+        json["counter"] = 0.into();
+        assert_eq!(serde_json::from_value::<KeyRecord>(json).unwrap().counter, Some(0));
+    }
+}
@@ -0,0 +1,301 @@
+//! A [`ChallengeStore`] and a [`ReplayCache`] in Redis, for deployments with several
+//! instances behind a load balancer: a challenge issued by one instance can be consumed by
+//! any other, exactly once.
+//!
+//! Each challenge or digest is a key under a prefix, set with `SET NX PX` so that it expires
+//! with the time to live; consuming a challenge is a `GETDEL`, which needs Redis 6.2. The
+//! stores use the blocking client and reconnect on the next call after an error.
+//!
+//! What a store does while Redis is unreachable is its [`FailureMode`]. Issuing a challenge
+//! fails either way, since no other instance could check it.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use base64::{engine::general_purpose, Engine};
+
+use super::{ChallengeStore, InMemoryChallengeStore, ReplayCache};
+use crate::error::AppAttestError;
+
+/// The default prefix of challenge keys; keep it apart from the application's own keys.
+pub const CHALLENGE_PREFIX: &str = "appattest:challenge:";
+/// The default prefix of assertion digest keys.
+pub const REPLAY_PREFIX: &str = "appattest:replay:";
+
+/// How long connecting, and then each command, may take before Redis counts as unreachable.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// What [`ChallengeStore::consume`] and [`ReplayCache::insert`] answer when Redis cannot be
+/// reached.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FailureMode {
+    /// Reject: no challenge is valid and every assertion is a replay. The default.
+    #[default]
+    Closed,
+    /// Accept, keeping the service up at the cost of the single-use guarantees until Redis
+    /// is back.
+    Open,
+}
+
+impl FailureMode {
+    fn answer(self) -> bool {
+        self == FailureMode::Open
+    }
+}
+
+/// A client with one lazily opened connection, locked for one command at a time.
+struct Redis {
+    client: ::redis::Client,
+    conn: Mutex<Option<::redis::Connection>>,
+    prefix: String,
+    timeout: Duration,
+    failure_mode: FailureMode,
+}
+
+impl Redis {
+    fn new(client: ::redis::Client, prefix: &str) -> Self {
+        Redis {
+            client,
+            conn: Mutex::new(None),
+            prefix: prefix.to_string(),
+            timeout: DEFAULT_TIMEOUT,
+            failure_mode: FailureMode::default(),
+        }
+    }
+
+    fn key(&self, name: &str) -> String {
+        format!("{}{name}", self.prefix)
+    }
+
+    /// Runs `cmd`, connecting first if needed. After an error the connection is dropped, so
+    /// the next call starts with a fresh one.
+    fn query<T: ::redis::FromRedisValue>(&self, cmd: &::redis::Cmd) -> Result<T, ::redis::RedisError> {
+        let mut conn = self.conn.lock().unwrap();
+        if conn.is_none() {
+            *conn = Some(self.connect()?);
+        }
+        let result = cmd.query(conn.as_mut().unwrap());
+        if result.is_err() {
+            *conn = None;
+        }
+        result
+    }
+
+    fn connect(&self) -> Result<::redis::Connection, ::redis::RedisError> {
+        let conn = self.client.get_connection_with_timeout(self.timeout)?;
+        conn.set_read_timeout(Some(self.timeout))?;
+        conn.set_write_timeout(Some(self.timeout))?;
+        Ok(conn)
+    }
+
+    /// The failure mode's answer, for a command that failed with `error`.
+    fn unavailable(&self, error: ::redis::RedisError) -> bool {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(%error, failure_mode = ?self.failure_mode, "redis unavailable");
+        #[cfg(not(feature = "tracing"))]
+        let _ = error;
+        self.failure_mode.answer()
+    }
+}
+
+/// `SET key 1 NX PX ttl`: `true` if the key was not there.
+fn set_nx(key: &str, ttl: Duration) -> ::redis::Cmd {
+    let mut cmd = ::redis::cmd("SET");
+    cmd.arg(key).arg(1).arg("NX").arg("PX").arg(u64::try_from(ttl.as_millis()).unwrap_or(u64::MAX).max(1));
+    cmd
+}
+
+fn open(url: &str) -> Result<::redis::Client, AppAttestError> {
+    ::redis::Client::open(url).map_err(|e| AppAttestError::Message(format!("redis: {e}")))
+}
+
+/// Challenges in Redis for a fixed time to live.
+pub struct RedisChallengeStore {
+    redis: Redis,
+    ttl: Duration,
+}
+
+impl RedisChallengeStore {
+    /// A store on the server at `url`, e.g. `redis://127.0.0.1/`. Nothing is connected until
+    /// the first call.
+    pub fn open(url: &str, ttl: Duration) -> Result<Self, AppAttestError> {
+        Ok(Self::with_client(open(url)?, ttl))
+    }
+
+    pub fn with_client(client: ::redis::Client, ttl: Duration) -> Self {
+        RedisChallengeStore { redis: Redis::new(client, CHALLENGE_PREFIX), ttl }
+    }
+
+    pub fn with_failure_mode(mut self, failure_mode: FailureMode) -> Self {
+        self.redis.failure_mode = failure_mode;
+        self
+    }
+
+    /// Replaces [`CHALLENGE_PREFIX`].
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.redis.prefix = prefix.to_string();
+        self
+    }
+
+    /// How long connecting and each command may take, one second unless set.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.redis.timeout = timeout;
+        self
+    }
+}
+
+impl ChallengeStore for RedisChallengeStore {
+    fn issue(&self) -> Result<String, AppAttestError> {
+        let mut bytes = [0u8; InMemoryChallengeStore::CHALLENGE_LEN];
+        getrandom::getrandom(&mut bytes)
+            .map_err(|e| AppAttestError::Message(format!("failed to generate challenge: {}", e)))?;
+        let challenge = general_purpose::STANDARD.encode(bytes);
+
+        let set: Option<String> = self
+            .redis
+            .query(&set_nx(&self.redis.key(&challenge), self.ttl))
+            .map_err(|e| AppAttestError::Message(format!("redis: {e}")))?;
+        match set {
+            Some(_) => Ok(challenge),
+            None => Err(AppAttestError::Message("redis: generated a challenge that is already issued".to_string())),
+        }
+    }
+
+    fn consume(&self, challenge: &str) -> bool {
+        let mut getdel = ::redis::cmd("GETDEL");
+        getdel.arg(self.redis.key(challenge));
+        match self.redis.query::<Option<String>>(&getdel) {
+            Ok(value) => value.is_some(),
+            Err(error) => self.redis.unavailable(error),
+        }
+    }
+}
+
+/// Assertion digests in Redis for a fixed time to live.
+pub struct RedisReplayCache {
+    redis: Redis,
+    ttl: Duration,
+}
+
+impl RedisReplayCache {
+    /// A cache on the server at `url`, e.g. `redis://127.0.0.1/`. Nothing is connected until
+    /// the first call.
+    pub fn open(url: &str, ttl: Duration) -> Result<Self, AppAttestError> {
+        Ok(Self::with_client(open(url)?, ttl))
+    }
+
+    pub fn with_client(client: ::redis::Client, ttl: Duration) -> Self {
+        RedisReplayCache { redis: Redis::new(client, REPLAY_PREFIX), ttl }
+    }
+
+    pub fn with_failure_mode(mut self, failure_mode: FailureMode) -> Self {
+        self.redis.failure_mode = failure_mode;
+        self
+    }
+
+    /// Replaces [`REPLAY_PREFIX`].
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.redis.prefix = prefix.to_string();
+        self
+    }
+
+    /// How long connecting and each command may take, one second unless set.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.redis.timeout = timeout;
+        self
+    }
+}
+
+impl ReplayCache for RedisReplayCache {
+    fn insert(&self, digest: &[u8; 32]) -> bool {
+        let key = self.redis.key(&general_purpose::URL_SAFE_NO_PAD.encode(digest));
+        match self.redis.query::<Option<String>>(&set_nx(&key, self.ttl)) {
+            Ok(set) => set.is_some(),
+            Err(error) => self.redis.unavailable(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    /// Nothing listens on port 1.
+    const UNREACHABLE: &str = "redis://127.0.0.1:1/";
+
+    fn url() -> String {
+        std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1/".to_string())
+    }
+
+    /// A prefix of its own for each test, so that tests sharing a server do not see each
+    /// other's keys.
+    fn prefix() -> String {
+        let mut bytes = [0u8; 8];
+        getrandom::getrandom(&mut bytes).unwrap();
+        format!("appattest-test:{}:", general_purpose::URL_SAFE_NO_PAD.encode(bytes))
+    }
+
+    #[test]
+    fn unreachable_redis_follows_the_failure_mode() {
+        let ttl = Duration::from_secs(60);
+        let closed = RedisChallengeStore::open(UNREACHABLE, ttl).unwrap();
+        assert!(matches!(closed.issue(), Err(AppAttestError::Message(_))));
+        assert!(!closed.consume("Y2hhbGxlbmdl"));
+        let open = closed.with_failure_mode(FailureMode::Open);
+        assert!(matches!(open.issue(), Err(AppAttestError::Message(_))));
+        assert!(open.consume("Y2hhbGxlbmdl"));
+
+        let closed = RedisReplayCache::open(UNREACHABLE, ttl).unwrap();
+        assert!(!closed.insert(&[1; 32]));
+        assert!(closed.with_failure_mode(FailureMode::Open).insert(&[1; 32]));
+    }
+
+    #[test]
+    #[ignore = "needs a Redis server at REDIS_URL, e.g. docker run -p 6379:6379 redis"]
+    fn challenges_are_single_use_across_instances() {
+        let prefix = prefix();
+        let ttl = Duration::from_secs(60);
+        let issuer = RedisChallengeStore::open(&url(), ttl).unwrap().with_prefix(&prefix);
+        let others: Vec<_> =
+            (0..4).map(|_| RedisChallengeStore::open(&url(), ttl).unwrap().with_prefix(&prefix)).collect();
+
+        let challenge = issuer.issue().unwrap();
+        assert_ne!(challenge, issuer.issue().unwrap());
+        let accepted = thread::scope(|scope| {
+            let racers: Vec<_> = others.iter().map(|other| scope.spawn(|| other.consume(&challenge))).collect();
+            racers.into_iter().filter(|racer| racer.join().unwrap()).count()
+        });
+        assert_eq!(accepted, 1);
+        assert!(!issuer.consume(&challenge));
+        assert!(!issuer.consume("bmV2ZXIgaXNzdWVk"));
+    }
+
+    #[test]
+    #[ignore = "needs a Redis server at REDIS_URL, e.g. docker run -p 6379:6379 redis"]
+    fn expired_challenges_are_rejected() {
+        let store = RedisChallengeStore::open(&url(), Duration::from_millis(1)).unwrap().with_prefix(&prefix());
+        let challenge = store.issue().unwrap();
+        thread::sleep(Duration::from_millis(50));
+        assert!(!store.consume(&challenge));
+    }
+
+    #[test]
+    #[ignore = "needs a Redis server at REDIS_URL, e.g. docker run -p 6379:6379 redis"]
+    fn replays_are_caught_across_instances() {
+        let prefix = prefix();
+        let ttl = Duration::from_secs(60);
+        let first = RedisReplayCache::open(&url(), ttl).unwrap().with_prefix(&prefix);
+        let second = RedisReplayCache::open(&url(), ttl).unwrap().with_prefix(&prefix);
+
+        assert!(first.insert(&[1; 32]));
+        assert!(!second.insert(&[1; 32]));
+        assert!(second.insert(&[2; 32]));
+
+        let expiring = RedisReplayCache::open(&url(), Duration::from_millis(1)).unwrap().with_prefix(&prefix);
+        assert!(expiring.insert(&[3; 32]));
+        thread::sleep(Duration::from_millis(50));
+        assert!(expiring.insert(&[3; 32]));
+    }
+}
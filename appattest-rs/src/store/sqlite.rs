@@ -0,0 +1,369 @@
+//! A [`KeyStore`] in a SQLite database, for deployments whose keys must survive restarts.
+//!
+//! The schema is created and upgraded by [`migrate`] from the SQL files in `migrations/`,
+//! which are compiled into the crate; the version a database is at is its
+//! `PRAGMA user_version`. Besides the [`KeyRecord`] fields, each row keeps when the key was
+//! stored, `created_at`, and when its counter last moved, `last_used_at`, both Unix seconds.
+//! [`KeyStore::archive`] moves a row to `archived_keys`, stamped with `archived_at`.
+
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+
+use super::{KeyRecord, KeyStore};
+use crate::key::KeyId;
+use crate::attestation::Environment;
+use crate::error::AppAttestError;
+use crate::platform::Platform;
+
+/// The migrations, in order; a database at version `n` has had the first `n` applied.
+pub const MIGRATIONS: &[&str] = &[
+    include_str!("../../migrations/0001_keys.sql"),
+    include_str!("../../migrations/0002_platform.sql"),
+    include_str!("../../migrations/0003_optional_counter.sql"),
+    include_str!("../../migrations/0004_leaf_not_after.sql"),
+    include_str!("../../migrations/0005_archived_keys.sql"),
+];
+
+/// How long a statement waits for another connection's write lock before failing.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Keys in a SQLite database. Several processes may open the same file: counter updates are
+/// a single compare-and-swap `UPDATE`, so of two assertions racing with the same counter,
+/// only one is accepted.
+///
+/// The store has one connection, behind a `Mutex`, so the threads of one process take turns;
+/// other processes wait up to five seconds for SQLite's write lock.
+pub struct SqliteKeyStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteKeyStore {
+    /// Opens the database at `path`, creating it if needed, and migrates it.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, AppAttestError> {
+        let conn = Connection::open(path).map_err(db)?;
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0)).map_err(db)?;
+        Self::with_connection(conn)
+    }
+
+    /// A database that lives as long as the store, for tests.
+    pub fn open_in_memory() -> Result<Self, AppAttestError> {
+        Self::with_connection(Connection::open_in_memory().map_err(db)?)
+    }
+
+    /// Migrates `conn` and stores keys in it.
+    pub fn with_connection(mut conn: Connection) -> Result<Self, AppAttestError> {
+        conn.busy_timeout(BUSY_TIMEOUT).map_err(db)?;
+        migrate(&mut conn)?;
+        Ok(SqliteKeyStore { conn: Mutex::new(conn) })
+    }
+}
+
+/// Applies the [`MIGRATIONS`] `conn` lacks, each in its own transaction. Fails on a database
+/// migrated by a newer version of the crate.
+pub fn migrate(conn: &mut Connection) -> Result<(), AppAttestError> {
+    loop {
+        // Immediate, so that of two processes migrating at once, the second waits and then
+        // sees the first one's version.
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(db)?;
+        let version: usize = tx.pragma_query_value(None, "user_version", |row| row.get(0)).map_err(db)?;
+        let Some(migration) = MIGRATIONS.get(version) else {
+            if version > MIGRATIONS.len() {
+                return Err(AppAttestError::Message(format!(
+                    "key store: the database is at version {version}, newer than the {} this crate knows",
+                    MIGRATIONS.len()
+                )));
+            }
+            return Ok(());
+        };
+        tx.execute_batch(migration).map_err(db)?;
+        tx.pragma_update(None, "user_version", version + 1).map_err(db)?;
+        tx.commit().map_err(db)?;
+    }
+}
+
+impl KeyStore for SqliteKeyStore {
+    fn get(&self, key_id: &KeyId) -> Result<Option<KeyRecord>, AppAttestError> {
+        let conn = self.conn.lock().unwrap();
+        let row = conn
+            .query_row(
+                "SELECT public_key, counter, environment, receipt, platform, leaf_not_after FROM keys WHERE key_id = ?1",
+                [key_id.to_string()],
+                |row| {
+                    Ok((
+                        row.get::<_, Vec<u8>>(0)?,
+                        row.get(1)?,
+                        row.get::<_, Option<String>>(2)?,
+                        row.get(3)?,
+                        row.get::<_, String>(4)?,
+                        row.get(5)?,
+                    ))
+                },
+            )
+            .optional()
+            .map_err(db)?;
+        let Some((public_key, counter, environment, receipt, platform, leaf_not_after)) = row else {
+            return Ok(None);
+        };
+        let environment = match environment.as_deref() {
+            None => None,
+            Some("production") => Some(Environment::Production),
+            Some("development") => Some(Environment::Development),
+            Some(other) => return Err(AppAttestError::Message(format!("key store: unknown environment {other:?}"))),
+        };
+        let platform = Platform::from_name(&platform)
+            .ok_or_else(|| AppAttestError::Message(format!("key store: unknown platform {platform:?}")))?;
+        Ok(Some(KeyRecord { key_id: *key_id, public_key, counter, receipt, environment, platform, leaf_not_after }))
+    }
+
+    fn put(&self, record: KeyRecord) -> Result<(), AppAttestError> {
+        let environment = record.environment.map(|environment| match environment {
+            Environment::Production => "production",
+            Environment::Development => "development",
+        });
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO keys (key_id, public_key, counter, environment, receipt, platform, leaf_not_after, created_at,
+                 last_used_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, NULL)
+             ON CONFLICT (key_id) DO UPDATE SET public_key = excluded.public_key, counter = excluded.counter,
+                 environment = excluded.environment, receipt = excluded.receipt, platform = excluded.platform,
+                 leaf_not_after = excluded.leaf_not_after, created_at = excluded.created_at, last_used_at = NULL",
+            params![
+                record.key_id.to_string(),
+                record.public_key,
+                record.counter,
+                environment,
+                record.receipt,
+                record.platform.as_str(),
+                record.leaf_not_after,
+                now()
+            ],
+        )
+        .map_err(db)?;
+        Ok(())
+    }
+
+    fn update_counter(&self, key_id: &KeyId, counter: u32) -> Result<(), AppAttestError> {
+        let conn = self.conn.lock().unwrap();
+        let key_id = key_id.to_string();
+        let updated = conn
+            .execute(
+                "UPDATE keys SET counter = ?2, last_used_at = ?3 WHERE key_id = ?1 AND (counter IS NULL OR counter < ?2)",
+                params![key_id, counter, now()],
+            )
+            .map_err(db)?;
+        if updated == 1 {
+            return Ok(());
+        }
+        let exists = conn.query_row("SELECT 1 FROM keys WHERE key_id = ?1", [key_id], |_| Ok(())).optional();
+        match exists.map_err(db)? {
+            Some(()) => Err(AppAttestError::InvalidCounter),
+            None => Err(AppAttestError::UnknownKeyID),
+        }
+    }
+
+    fn archive(&self, key_id: &KeyId) -> Result<(), AppAttestError> {
+        let mut conn = self.conn.lock().unwrap();
+        let key_id = key_id.to_string();
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(db)?;
+        let archived = tx
+            .execute(
+                "INSERT INTO archived_keys (key_id, public_key, counter, environment, receipt, created_at, last_used_at,
+                     platform, leaf_not_after, archived_at)
+                 SELECT key_id, public_key, counter, environment, receipt, created_at, last_used_at, platform,
+                     leaf_not_after, ?2
+                 FROM keys WHERE key_id = ?1",
+                params![key_id, now()],
+            )
+            .map_err(db)?;
+        if archived == 0 {
+            return Err(AppAttestError::UnknownKeyID);
+        }
+        tx.execute("DELETE FROM keys WHERE key_id = ?1", [key_id]).map_err(db)?;
+        tx.commit().map_err(db)
+    }
+}
+
+fn db(error: rusqlite::Error) -> AppAttestError {
+    AppAttestError::Message(format!("key store: {error}"))
+}
+
+fn now() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+
+    use super::*;
+    use crate::store::tests::{key_id, other_key_id, record};
+
+    #[test]
+    fn keys_survive_a_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keys.db");
+        let record =
+            KeyRecord { receipt: b"receipt".to_vec(), environment: Some(Environment::Development), ..record() };
+
+        let store = SqliteKeyStore::open(&path).unwrap();
+        store.put(record.clone()).unwrap();
+        store.update_counter(&record.key_id, 3).unwrap();
+        drop(store);
+
+        let store = SqliteKeyStore::open(&path).unwrap();
+        assert_eq!(store.get(&record.key_id).unwrap(), Some(KeyRecord { counter: Some(3), ..record }));
+        assert_eq!(store.get(&other_key_id()).unwrap(), None);
+
+        let conn = Connection::open(&path).unwrap();
+        let (created_at, last_used_at): (i64, Option<i64>) = conn
+            .query_row("SELECT created_at, last_used_at FROM keys", [], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap();
+        assert!(created_at > 0);
+        assert!(last_used_at >= Some(created_at));
+    }
+
+    #[test]
+    fn counter_only_moves_forward() {
+        let store = SqliteKeyStore::open_in_memory().unwrap();
+        store.put(record()).unwrap();
+
+        store.update_counter(&key_id(), 0).unwrap();
+        assert_eq!(store.update_counter(&key_id(), 0), Err(AppAttestError::InvalidCounter));
+        store.update_counter(&key_id(), 2).unwrap();
+        assert_eq!(store.update_counter(&key_id(), 2), Err(AppAttestError::InvalidCounter));
+        assert_eq!(store.update_counter(&key_id(), 1), Err(AppAttestError::InvalidCounter));
+        assert_eq!(store.update_counter(&other_key_id(), 1), Err(AppAttestError::UnknownKeyID));
+
+        // Attesting the key again starts it over.
+        store.put(record()).unwrap();
+        assert_eq!(store.get(&key_id()).unwrap().unwrap().counter, None);
+    }
+
+    #[test]
+    fn counters_above_i32_max_round_trip() {
+        // SQLite integers are 64-bit, so the whole `u32` range is stored as is.
+        let store = SqliteKeyStore::open_in_memory().unwrap();
+        store.put(record()).unwrap();
+        store.update_counter(&key_id(), i32::MAX as u32 + 1).unwrap();
+        store.update_counter(&key_id(), u32::MAX - 1).unwrap();
+        assert_eq!(store.get(&key_id()).unwrap().unwrap().counter, Some(u32::MAX - 1));
+        assert_eq!(store.update_counter(&key_id(), i32::MAX as u32), Err(AppAttestError::InvalidCounter));
+    }
+
+    #[test]
+    fn racing_connections_accept_each_counter_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keys.db");
+        let stores = [SqliteKeyStore::open(&path).unwrap(), SqliteKeyStore::open(&path).unwrap()];
+        stores[0].put(record()).unwrap();
+
+        const ROUNDS: u32 = 50;
+        let barrier = Arc::new(Barrier::new(stores.len()));
+        let accepted: u32 = thread::scope(|scope| {
+            let racers: Vec<_> = stores
+                .iter()
+                .map(|store| {
+                    let barrier = barrier.clone();
+                    scope.spawn(move || {
+                        (1..=ROUNDS)
+                            .filter(|&counter| {
+                                barrier.wait();
+                                match store.update_counter(&key_id(), counter) {
+                                    Ok(()) => true,
+                                    Err(AppAttestError::InvalidCounter) => false,
+                                    Err(other) => panic!("{other}"),
+                                }
+                            })
+                            .count() as u32
+                    })
+                })
+                .collect();
+            racers.into_iter().map(|racer| racer.join().unwrap()).sum()
+        });
+
+        assert_eq!(accepted, ROUNDS);
+        assert_eq!(stores[1].get(&key_id()).unwrap().unwrap().counter, Some(ROUNDS));
+    }
+
+    #[test]
+    fn keys_keep_their_platform() {
+        let store = SqliteKeyStore::open_in_memory().unwrap();
+        let android = KeyRecord { platform: Platform::Android, ..record() };
+        store.put(android.clone()).unwrap();
+        assert_eq!(store.get(&key_id()).unwrap(), Some(android));
+    }
+
+    #[test]
+    fn keys_keep_their_leaf_expiry() {
+        let store = SqliteKeyStore::open_in_memory().unwrap();
+        let expiring = KeyRecord { leaf_not_after: Some(1_771_000_000), ..record() };
+        store.put(expiring.clone()).unwrap();
+        assert_eq!(store.get(&key_id()).unwrap(), Some(expiring));
+
+        // Attesting the key again without a chain forgets the old expiry.
+        store.put(record()).unwrap();
+        assert_eq!(store.get(&key_id()).unwrap().unwrap().leaf_not_after, None);
+    }
+
+    #[test]
+    fn archived_keys_move_to_their_own_table() {
+        let store = SqliteKeyStore::open_in_memory().unwrap();
+        store.put(record()).unwrap();
+        store.update_counter(&key_id(), 3).unwrap();
+        store.archive(&key_id()).unwrap();
+
+        assert_eq!(store.get(&key_id()).unwrap(), None);
+        assert_eq!(store.update_counter(&key_id(), 4), Err(AppAttestError::UnknownKeyID));
+        assert_eq!(store.archive(&other_key_id()), Err(AppAttestError::UnknownKeyID));
+
+        // Attested again, the key is live once more and can be archived a second time.
+        store.put(record()).unwrap();
+        assert_eq!(store.get(&key_id()).unwrap(), Some(record()));
+        store.archive(&key_id()).unwrap();
+        let conn = store.conn.lock().unwrap();
+        let counters: Vec<Option<u32>> = conn
+            .prepare("SELECT counter FROM archived_keys WHERE key_id = ?1 ORDER BY rowid")
+            .unwrap()
+            .query_map([key_id().to_string()], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(counters, [Some(3), None]);
+    }
+
+    #[test]
+    fn keys_from_before_platforms_are_apple_keys() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(MIGRATIONS[0]).unwrap();
+        conn.pragma_update(None, "user_version", 1).unwrap();
+        conn.execute(
+            "INSERT INTO keys (key_id, public_key, counter, receipt, created_at) VALUES (?1, ?2, 0, x'', 0)",
+            params![key_id().to_string(), record().public_key],
+        )
+        .unwrap();
+
+        let store = SqliteKeyStore::with_connection(conn).unwrap();
+        assert_eq!(store.get(&key_id()).unwrap(), Some(KeyRecord { counter: Some(0), ..record() }));
+    }
+
+    #[test]
+    fn migrations_run_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keys.db");
+        SqliteKeyStore::open(&path).unwrap().put(record()).unwrap();
+        let store = SqliteKeyStore::open(&path).unwrap();
+        assert!(store.get(&key_id()).unwrap().is_some());
+
+        let mut conn = Connection::open(&path).unwrap();
+        let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0)).unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+
+        conn.pragma_update(None, "user_version", MIGRATIONS.len() + 1).unwrap();
+        assert!(matches!(migrate(&mut conn), Err(AppAttestError::Message(msg)) if msg.contains("newer")));
+    }
+}
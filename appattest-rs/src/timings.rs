@@ -0,0 +1,111 @@
+//! How long each step of a verification took, for tracking latency objectives without a
+//! profiler.
+//!
+//! [`crate::attestation::AttestationVerifier`] and [`crate::assertion::AssertionVerifier`]
+//! time their steps once `with_timings` is set; each returns them from `verify_timed`, on
+//! failure too, and the attestation verifier also in
+//! [`crate::attestation::VerifiedAttestation::timings`]. Without it the verifiers read no
+//! clock, and neither do the `verify` methods the zkVM guests call.
+
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// A timed step. Attestations go through `decode`, `chain`, `nonce`, `authData` and, with a
+/// receipt policy, `receipt`; assertions through `decode`, `nonce`, `signature` and
+/// `authData`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum VerificationStep {
+    /// Parsing the certificates and the trusted roots, or the assertion's key and signature
+    /// and its authenticator data.
+    Decode,
+    /// Walking the chain to a trusted root: signatures, validity and pins (Apple's step 1).
+    Chain,
+    /// Computing the nonce, and for attestations comparing the certified one (steps 2 to 4).
+    Nonce,
+    /// Checking the assertion's signature over the nonce.
+    Signature,
+    /// Checking the authenticator data against the key, app ID, counter and AAGUID (steps 5
+    /// to 9).
+    AuthData,
+    /// Checking the receipt against the verifier's [`crate::receipt::ReceiptPolicy`].
+    Receipt,
+}
+
+/// The duration of one step.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct StepTiming {
+    pub step: VerificationStep,
+    pub duration: Duration,
+}
+
+/// The steps a verification ran, in order. A verification that failed ran up to the step
+/// that failed, which is the last.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationTimings {
+    pub steps: Vec<StepTiming>,
+    /// The whole verification. The steps follow each other without gaps, so this exceeds
+    /// their sum only by the time before the first.
+    pub total: Duration,
+}
+
+impl VerificationTimings {
+    /// How long `step` took, or `None` if the verification did not get to it.
+    pub fn get(&self, step: VerificationStep) -> Option<Duration> {
+        self.steps.iter().find(|timing| timing.step == step).map(|timing| timing.duration)
+    }
+
+    /// The sum of the steps' durations.
+    pub fn sum(&self) -> Duration {
+        self.steps.iter().map(|timing| timing.duration).sum()
+    }
+}
+
+/// Times the steps of one verification, or does nothing if it was made [`Timer::new`]
+/// with `enabled` false: each step is then a check of a `None`.
+#[derive(Debug)]
+pub(crate) struct Timer(Option<Running>);
+
+#[derive(Debug)]
+struct Running {
+    started: Instant,
+    current: Option<(VerificationStep, Instant)>,
+    steps: Vec<StepTiming>,
+}
+
+impl Running {
+    fn end_step(&mut self, now: Instant) {
+        if let Some((step, started)) = self.current.take() {
+            self.steps.push(StepTiming { step, duration: now - started });
+        }
+    }
+}
+
+impl Timer {
+    pub(crate) fn new(enabled: bool) -> Self {
+        Timer(enabled.then(|| Running { started: Instant::now(), current: None, steps: Vec::new() }))
+    }
+
+    /// Step `step` starts, and the one before ends.
+    pub(crate) fn step(&mut self, step: VerificationStep) {
+        if let Some(running) = &mut self.0 {
+            let now = Instant::now();
+            running.end_step(now);
+            running.current = Some((step, now));
+        }
+    }
+
+    /// Ends the current step, and the timings, if enabled.
+    pub(crate) fn finish(self) -> Option<VerificationTimings> {
+        let mut running = self.0?;
+        let now = Instant::now();
+        running.end_step(now);
+        Some(VerificationTimings { steps: running.steps, total: now - running.started })
+    }
+}
@@ -0,0 +1,80 @@
+//! Peak heap use of decoding the recorded objects from base64, measured by a counting global
+//! allocator. Streaming the base64 into the CBOR parser must never hold the decoded bytes in
+//! full next to the parsed object, as decoding to a buffer first does, and parsing an
+//! `AttestationRef` must copy none of the byte strings out of the CBOR.
+//!
+//! The allocator is shared by every test in this binary, so this file holds a single test.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use appattest_rs::assertion::Assertion;
+use appattest_rs::attestation::{Attestation, AttestationRef};
+use base64::{engine::general_purpose, Engine};
+
+#[path = "../benches/bench_utils/mod.rs"]
+mod bench_utils;
+
+use bench_utils::{decoded, fixture, manifest};
+
+struct Counting;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let current = CURRENT.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+        PEAK.fetch_max(current, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        CURRENT.fetch_sub(layout.size(), Ordering::SeqCst);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+/// The most bytes `f` had allocated at once, beyond what was allocated before it, and its
+/// result, which is dropped after the measurement.
+fn peak<T>(f: impl FnOnce() -> T) -> (usize, T) {
+    let start = CURRENT.load(Ordering::SeqCst);
+    PEAK.store(start, Ordering::SeqCst);
+    let result = f();
+    (PEAK.load(Ordering::SeqCst) - start, result)
+}
+
+#[test]
+#[cfg_attr(feature = "minimal-cbor", ignore = "the minimal reader parses from a decoded buffer")]
+fn streaming_base64_allocates_less_than_decoding_first() {
+    let manifest = manifest();
+    let text = fixture(&manifest.attestation.file);
+    // Warm up the thread-local scratch buffer, which outlives each call.
+    Attestation::from_base64(&text).unwrap();
+
+    let (streamed, attestation) = peak(|| Attestation::from_base64(&text).unwrap());
+    let (buffered, expected) =
+        peak(|| Attestation::from_cbor(&general_purpose::STANDARD.decode(&text).unwrap()).unwrap());
+    assert_eq!(attestation, expected);
+    assert!(streamed < buffered, "streamed {streamed} bytes, buffered {buffered}");
+
+    for recorded in &manifest.assertions {
+        let text = fixture(&recorded.file);
+        let (streamed, assertion) = peak(|| Assertion::from_base64(&text).unwrap());
+        let (buffered, expected) =
+            peak(|| Assertion::from_cbor(&general_purpose::STANDARD.decode(&text).unwrap()).unwrap());
+        assert_eq!(assertion, expected);
+        assert!(streamed < buffered, "{}: streamed {streamed} bytes, buffered {buffered}", recorded.file);
+    }
+
+    // Only the list of certificates is allocated, a few pointers per certificate.
+    let cbor = decoded(&manifest.attestation.file);
+    let (borrowed, attestation) = peak(|| AttestationRef::from_cbor(&cbor).unwrap());
+    let (owned, expected) = peak(|| Attestation::from_cbor(&cbor).unwrap());
+    assert_eq!(attestation.to_attestation(), expected);
+    assert!(borrowed < cbor.len() / 16, "borrowed {borrowed} bytes of {}", cbor.len());
+    assert!(borrowed < owned, "borrowed {borrowed} bytes, owned {owned}");
+}
@@ -0,0 +1,212 @@
+//! One verifier, and one service, shared by 32 threads verifying a mix of valid and invalid
+//! inputs from the device recording: each thread must get the result it would get alone,
+//! and the run must finish rather than deadlock.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Barrier};
+use std::thread;
+use std::time::Duration;
+
+use appattest_rs::assertion::AssertionVerifier;
+use appattest_rs::attestation::AttestationVerifier;
+use appattest_rs::audit::{AuditEvent, AuditSink};
+use appattest_rs::error::AppAttestError;
+use sha2::{Digest, Sha256};
+
+#[path = "../benches/bench_utils/mod.rs"]
+mod bench_utils;
+
+use bench_utils::{decoded, manifest, Manifest};
+
+const THREADS: usize = 32;
+const ROUNDS: usize = 8;
+/// Far longer than the run takes; reaching it means the threads are stuck.
+const DEADLINE: Duration = Duration::from_secs(120);
+
+/// Counts the events it is given.
+#[derive(Default)]
+struct CountingSink(AtomicUsize);
+
+impl AuditSink for CountingSink {
+    fn record(&self, _event: &AuditEvent) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// What a case verifies to: the attested public key, the assertion's signature and key, or
+/// the error.
+type Outcome = Result<Vec<u8>, AppAttestError>;
+
+/// An input for the shared verifiers, valid or not.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Case {
+    Attestation,
+    AttestationWrongChallenge,
+    AttestationWrongAppId,
+    Assertion,
+    AssertionReplayed,
+}
+
+const CASES: [Case; 5] = [
+    Case::Attestation,
+    Case::AttestationWrongChallenge,
+    Case::AttestationWrongAppId,
+    Case::Assertion,
+    Case::AssertionReplayed,
+];
+
+struct Verifiers {
+    attestation: AttestationVerifier,
+    assertion: AssertionVerifier,
+}
+
+impl Case {
+    /// Runs the case; assertions are checked against `public_key`, the attested key.
+    fn run(self, manifest: &Manifest, public_key: &[u8], verifiers: &Verifiers) -> Outcome {
+        let mut challenge = decoded(&manifest.attestation.challenge);
+        let mut app_id_hash = manifest.app_id_hash();
+        let recorded = &manifest.assertions[0];
+        match self {
+            Case::AttestationWrongChallenge => challenge[0] ^= 1,
+            Case::AttestationWrongAppId => app_id_hash = Sha256::digest("LMRM26A744.xyz.elus.aegis.other").into(),
+            _ => {}
+        }
+        match self {
+            Case::Attestation | Case::AttestationWrongChallenge | Case::AttestationWrongAppId => verifiers
+                .attestation
+                .verify_raw(
+                    manifest.attestation(),
+                    &challenge,
+                    &app_id_hash,
+                    &decoded(&manifest.attestation.key_id),
+                    manifest.recorded_at,
+                )
+                .map(|verified| verified.public_key),
+            Case::Assertion | Case::AssertionReplayed => verifiers
+                .assertion
+                .verify_raw(
+                    recorded.assertion(),
+                    &decoded(&recorded.client_data),
+                    &app_id_hash,
+                    public_key,
+                    (self == Case::AssertionReplayed).then_some(recorded.counter),
+                )
+                .map(|parts| parts.concat()),
+        }
+    }
+}
+
+fn attested_public_key(manifest: &Manifest) -> Vec<u8> {
+    manifest
+        .attestation()
+        .verify_raw(
+            &decoded(&manifest.attestation.challenge),
+            &manifest.app_id_hash(),
+            &decoded(&manifest.attestation.key_id),
+            manifest.recorded_at,
+        )
+        .unwrap()
+        .public_key
+}
+
+/// Runs `f` on its own thread and returns its result, failing if it takes past
+/// [`DEADLINE`].
+fn before_deadline<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> T {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || sender.send(f()).unwrap());
+    receiver.recv_timeout(DEADLINE).expect("deadlocked: the threads did not finish")
+}
+
+#[test]
+fn one_verifier_serves_many_threads() {
+    let manifest = manifest();
+    let sink = Arc::new(CountingSink::default());
+    let verifiers = Verifiers {
+        attestation: AttestationVerifier::embedded().with_audit(sink.clone()),
+        assertion: AssertionVerifier::default().with_audit(sink.clone()),
+    };
+    let public_key = attested_public_key(&manifest);
+    let expected: Vec<Outcome> = CASES.iter().map(|case| case.run(&manifest, &public_key, &verifiers)).collect();
+    assert!(expected[0].is_ok() && expected[3].is_ok());
+    assert_eq!(expected[1], Err(AppAttestError::InvalidNonce));
+    assert_eq!(expected[2], Err(AppAttestError::InvalidAppID));
+    assert_eq!(expected[4], Err(AppAttestError::InvalidCounter));
+    sink.0.store(0, Ordering::Relaxed);
+
+    let shared = Arc::new((manifest, public_key, verifiers));
+    let mismatches = before_deadline(move || {
+        let barrier = Barrier::new(THREADS);
+        thread::scope(|scope| {
+            let workers: Vec<_> = (0..THREADS)
+                .map(|index| {
+                    let (barrier, shared, expected) = (&barrier, &shared, &expected);
+                    scope.spawn(move || {
+                        barrier.wait();
+                        (0..ROUNDS)
+                            .map(|round| (index + round) % CASES.len())
+                            .filter(|&i| CASES[i].run(&shared.0, &shared.1, &shared.2) != expected[i])
+                            .map(|i| CASES[i])
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            workers.into_iter().flat_map(|worker| worker.join().unwrap()).collect::<Vec<_>>()
+        })
+    });
+
+    assert_eq!(mismatches, []);
+    // One audit event per verification by a shared verifier, none lost to a race.
+    assert_eq!(sink.0.load(Ordering::Relaxed), THREADS * ROUNDS);
+}
+
+#[cfg(feature = "store")]
+#[test]
+fn one_service_accepts_a_counter_once() {
+    use appattest_rs::platform::{AppAttestKey, AttestedKey};
+    use appattest_rs::service::AppAttestService;
+    use appattest_rs::store::InMemoryKeyStore;
+
+    let manifest = manifest();
+    let verified = manifest
+        .attestation()
+        .verify_raw(
+            &decoded(&manifest.attestation.challenge),
+            &manifest.app_id_hash(),
+            &decoded(&manifest.attestation.key_id),
+            manifest.recorded_at,
+        )
+        .unwrap();
+    let service = AppAttestService::new(Arc::new(InMemoryKeyStore::new()), manifest.app_id.clone());
+    let key = AppAttestKey::try_from(&verified).unwrap();
+    service.register(&key, verified.receipt).unwrap();
+
+    let recorded = &manifest.assertions[0];
+    let (assertion, client_data) = (decoded(&recorded.file), decoded(&recorded.client_data));
+    let shared = Arc::new((service, key));
+    let results = before_deadline({
+        let shared = shared.clone();
+        move || {
+            let barrier = Barrier::new(THREADS);
+            thread::scope(|scope| {
+                let workers: Vec<_> = (0..THREADS)
+                    .map(|_| {
+                        let (barrier, shared, assertion, client_data) = (&barrier, &shared, &assertion, &client_data);
+                        scope.spawn(move || {
+                            barrier.wait();
+                            let (service, key) = &**shared;
+                            service.assert(&key.key_id(), assertion, client_data).map(|accepted| accepted.counter)
+                        })
+                    })
+                    .collect();
+                workers.into_iter().map(|worker| worker.join().unwrap()).collect::<Vec<_>>()
+            })
+        }
+    });
+
+    // Every thread verified the same assertion; the store let exactly one of them advance
+    // the counter, and the rest lost the race.
+    assert_eq!(results.iter().filter(|result| **result == Ok(recorded.counter)).count(), 1);
+    assert!(results.iter().all(|result| *result == Ok(recorded.counter) || *result == Err(AppAttestError::InvalidCounter)));
+    let (service, key) = &*shared;
+    assert_eq!(service.keys().get(&key.key_id()).unwrap().unwrap().counter, Some(recorded.counter));
+}
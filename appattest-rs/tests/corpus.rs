@@ -0,0 +1,21 @@
+//! Runs every input under `tests/corpus/` through its fuzz target's harness: the crashes the
+//! targets in `fuzz/` found, minimized, and the recorded objects they start from.
+
+use std::path::PathBuf;
+
+#[path = "../../fuzz/src/appattest.rs"]
+mod harness;
+
+#[test]
+fn corpus_inputs_do_not_panic() {
+    let corpus = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+    for (target, run) in harness::TARGETS {
+        let mut inputs: Vec<_> = std::fs::read_dir(corpus.join(target)).unwrap().map(|e| e.unwrap().path()).collect();
+        inputs.sort();
+        assert!(!inputs.is_empty(), "no corpus for {target}");
+        for input in inputs {
+            println!("{target}: {}", input.display());
+            run(&std::fs::read(&input).unwrap());
+        }
+    }
+}
@@ -0,0 +1,94 @@
+//! The `decode` binary over the device recording described by `apps/fixtures/manifest.json`,
+//! and over input that is neither an attestation nor an assertion.
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+use appattest_rs::decode::{decode, Decoded};
+
+#[path = "../benches/bench_utils/mod.rs"]
+mod bench_utils;
+
+use bench_utils::{decoded, fixture, fixtures, manifest};
+
+fn run(args: &[&str], stdin: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_decode"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn dumps_the_recorded_attestation() {
+    let manifest = manifest();
+    let Decoded::Attestation(dump) = decode(fixture(&manifest.attestation.file).as_bytes()).unwrap() else {
+        panic!("not an attestation");
+    };
+    assert_eq!(dump.fmt.as_deref(), Some("apple-appattest"));
+    assert_eq!(dump.certificates.len(), 2);
+    assert!(dump.certificates.iter().all(|certificate| certificate.error.is_none()));
+    assert!(dump.receipt_len.is_some_and(|len| len > 0));
+    assert_eq!(dump.auth_data.counter, Some(0));
+    assert_eq!(dump.auth_data.environment, Some(manifest.environment));
+    assert_eq!(dump.auth_data.credential_id, Some(fixture(&manifest.attestation.key_id)));
+    assert_eq!(dump.auth_data.error, None);
+}
+
+#[test]
+fn dumps_the_recorded_assertions() {
+    let manifest = manifest();
+    for recorded in &manifest.assertions {
+        // Raw CBOR decodes as well as base64.
+        let Decoded::Assertion(dump) = decode(&decoded(&recorded.file)).unwrap() else {
+            panic!("{} is not an assertion", recorded.file);
+        };
+        assert_eq!(dump.auth_data.counter, Some(recorded.counter), "{}", recorded.file);
+        assert!(dump.signature_len.is_some_and(|len| len > 0));
+        assert_eq!((dump.auth_data.aaguid, dump.auth_data.error), (None, None));
+    }
+}
+
+#[test]
+fn prints_text_and_json() {
+    let manifest = manifest();
+    let path = fixtures().join(&manifest.attestation.file);
+    let output = run(&["--file", path.to_str().unwrap()], b"");
+    assert!(output.status.success());
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert!(text.starts_with("type:"));
+    assert!(text.contains("appattestdevelop (development)"));
+
+    let output = run(&["--json"], fixture(&manifest.assertions[0].file).as_bytes());
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["type"], "assertion");
+    assert_eq!(json["authData"]["counter"], manifest.assertions[0].counter);
+}
+
+#[test]
+fn rejects_garbage_without_panicking() {
+    for garbage in [&b""[..], b"not base64!", b"AAAA", b"\xff\xfe\x00garbage", b"oWFhAQ=="] {
+        assert!(decode(garbage).is_err(), "{garbage:?}");
+        let output = run(&[], garbage);
+        assert_eq!(output.status.code(), Some(1), "{garbage:?}");
+        assert!(String::from_utf8_lossy(&output.stderr).starts_with("error: "));
+    }
+
+    // Attestation keys with nonsense under them still dump, as far as they go.
+    let Decoded::Attestation(dump) = decode(b"omNmbXQBZ2F0dFN0bXSgaGF1dGhEYXRhQwECAw==").unwrap() else {
+        panic!("not an attestation");
+    };
+    assert_eq!((dump.fmt, dump.certificates.len()), (None, 0));
+    assert!(dump.auth_data.error.is_some());
+}
+
+#[test]
+fn usage_errors() {
+    assert_eq!(run(&["--file"], b"").status.code(), Some(2));
+    assert_eq!(run(&["--bogus"], b"").status.code(), Some(2));
+}
@@ -0,0 +1,231 @@
+//! The registration and assertion flow of a relying party, through the public API, over the
+//! device recording described by `apps/fixtures/manifest.json`: the attestation, then each
+//! assertion in order, each against the counter of the one before.
+//!
+//! Everything is checked at the recording's time, so the suite runs offline and does not
+//! expire with the leaf certificate.
+
+use appattest_rs::assertion::Assertion;
+use appattest_rs::attestation::{Attestation, Environment};
+use appattest_rs::error::AppAttestError;
+use appattest_rs::key::{KeyId, PublicKey};
+use sha2::{Digest, Sha256};
+
+#[path = "../benches/bench_utils/mod.rs"]
+mod bench_utils;
+
+use bench_utils::{decoded, fixture, manifest, Manifest, RecordedAssertion};
+
+impl Manifest {
+    /// Verifies the attestation and returns the attested key.
+    fn register(&self) -> Vec<u8> {
+        let verified = self
+            .attestation()
+            .verify_raw(
+                &decoded(&self.attestation.challenge),
+                &self.app_id_hash(),
+                &decoded(&self.attestation.key_id),
+                self.recorded_at,
+            )
+            .unwrap();
+        assert_eq!(verified.environment, self.environment);
+        verified.public_key
+    }
+}
+
+impl RecordedAssertion {
+    fn verify(&self, manifest: &Manifest, public_key: &[u8], previous_counter: Option<u32>) -> Result<u32, AppAttestError> {
+        let assertion = self.assertion();
+        let counter = assertion.counter()?;
+        assertion.verify_raw(&decoded(&self.client_data), &manifest.app_id_hash(), public_key, previous_counter)?;
+        Ok(counter)
+    }
+}
+
+#[test]
+fn registration_then_assertions() {
+    let manifest = manifest();
+    assert!(!manifest.assertions.is_empty());
+
+    let public_key = manifest.register();
+    let expected = PublicKey::from_pem(&fixture(&manifest.attestation.public_key)).unwrap();
+    assert_eq!(PublicKey::from_uncompressed(&public_key), Ok(expected));
+    assert_eq!(KeyId::for_public_key(&public_key), KeyId::from_base64(&fixture(&manifest.attestation.key_id)));
+
+    let mut counter = None;
+    for recorded in &manifest.assertions {
+        counter = Some(recorded.verify(&manifest, &public_key, counter).unwrap());
+        assert_eq!(counter, Some(recorded.counter), "{}", recorded.file);
+    }
+}
+
+#[test]
+fn the_text_api_agrees() {
+    let manifest = manifest();
+    let (public_key, _) = manifest
+        .attestation()
+        .verify(
+            &fixture(&manifest.attestation.challenge),
+            &manifest.app_id,
+            &fixture(&manifest.attestation.key_id),
+            manifest.recorded_at,
+            Some(manifest.environment == Environment::Development),
+        )
+        .unwrap();
+    assert_eq!(public_key, manifest.register());
+
+    let pem = fixture(&manifest.attestation.public_key);
+    let mut counter = None;
+    for recorded in &manifest.assertions {
+        let client_data = fixture(&recorded.client_data);
+        recorded.assertion().verify(&client_data, &manifest.app_id, &pem, counter, None).unwrap();
+        counter = Some(recorded.counter);
+    }
+}
+
+#[test]
+fn a_development_key_needs_development_allowed() {
+    let manifest = manifest();
+    assert_eq!(manifest.environment, Environment::Development);
+    let result = manifest.attestation().verify(
+        &fixture(&manifest.attestation.challenge),
+        &manifest.app_id,
+        &fixture(&manifest.attestation.key_id),
+        manifest.recorded_at,
+        Some(false),
+    );
+    let err = result.unwrap_err().downcast::<AppAttestError>().unwrap();
+    assert_eq!(*err, AppAttestError::InvalidAAGUID);
+}
+
+#[test]
+fn wrong_challenge() {
+    let manifest = manifest();
+    let mut challenge = decoded(&manifest.attestation.challenge);
+    challenge[0] ^= 1;
+    let result = manifest.attestation().verify_raw(
+        &challenge,
+        &manifest.app_id_hash(),
+        &decoded(&manifest.attestation.key_id),
+        manifest.recorded_at,
+    );
+    assert_eq!(result, Err(AppAttestError::InvalidNonce));
+}
+
+#[test]
+fn wrong_app_id() {
+    let manifest = manifest();
+    let other: [u8; 32] = Sha256::digest("LMRM26A744.xyz.elus.aegis.other").into();
+    let result = manifest.attestation().verify_raw(
+        &decoded(&manifest.attestation.challenge),
+        &other,
+        &decoded(&manifest.attestation.key_id),
+        manifest.recorded_at,
+    );
+    assert_eq!(result, Err(AppAttestError::InvalidAppID));
+
+    let public_key = manifest.register();
+    let recorded = &manifest.assertions[0];
+    let result = recorded.assertion().verify_raw(&decoded(&recorded.client_data), &other, &public_key, None);
+    assert_eq!(result, Err(AppAttestError::InvalidAppID));
+}
+
+#[test]
+fn wrong_key_id() {
+    let manifest = manifest();
+    let mut key_id = decoded(&manifest.attestation.key_id);
+    key_id[0] ^= 1;
+    let result = manifest.attestation().verify_raw(
+        &decoded(&manifest.attestation.challenge),
+        &manifest.app_id_hash(),
+        &key_id,
+        manifest.recorded_at,
+    );
+    assert_eq!(result, Err(AppAttestError::InvalidPublicKey));
+}
+
+#[test]
+fn replayed_counter() {
+    let manifest = manifest();
+    let public_key = manifest.register();
+    let mut counter = None;
+    for recorded in &manifest.assertions {
+        let accepted = recorded.verify(&manifest, &public_key, counter).unwrap();
+        counter = Some(accepted);
+        // Submitting the same assertion again, or any older one, must fail.
+        for seen in manifest.assertions.iter().take_while(|seen| seen.counter <= accepted) {
+            assert_eq!(seen.verify(&manifest, &public_key, counter), Err(AppAttestError::InvalidCounter));
+        }
+    }
+}
+
+#[test]
+fn other_client_data() {
+    let manifest = manifest();
+    let public_key = manifest.register();
+    let recorded = &manifest.assertions[0];
+    let result = recorded.assertion().verify_raw(b"x = 16", &manifest.app_id_hash(), &public_key, None);
+    assert_eq!(result, Err(AppAttestError::InvalidSignature));
+}
+
+#[test]
+fn truncated_inputs() {
+    let manifest = manifest();
+    let attestation = decoded(&manifest.attestation.file);
+    for len in 0..attestation.len() {
+        assert!(Attestation::from_cbor(&attestation[..len]).is_err(), "attestation cut at {len}");
+    }
+    for recorded in &manifest.assertions {
+        let assertion = decoded(&recorded.file);
+        for len in 0..assertion.len() {
+            assert!(Assertion::from_cbor(&assertion[..len]).is_err(), "{} cut at {len}", recorded.file);
+        }
+    }
+
+    // Truncated base64 fails to decode rather than producing a shorter object.
+    let text = fixture(&manifest.attestation.file);
+    assert!(Attestation::from_base64(&text[..text.len() - 1]).is_err());
+    assert!(Attestation::from_base64("").is_err());
+}
+
+/// The recorded attestation with its authenticator data cut to `len` bytes, still valid CBOR.
+fn with_auth_data_cut(manifest: &Manifest, len: usize) -> Attestation {
+    let cbor = decoded(&manifest.attestation.file);
+    let auth_data = manifest.attestation().auth_data().to_vec();
+    // `authData` is the last entry, a byte string with a one-byte length.
+    let header = cbor.len() - auth_data.len() - 2;
+    assert_eq!(cbor[header..header + 2], [0x58, auth_data.len() as u8]);
+    let cut = [&cbor[..header], &[0x58, len as u8], &auth_data[..len]].concat();
+    Attestation::from_cbor(&cut).unwrap()
+}
+
+#[test]
+fn truncated_auth_data() {
+    let manifest = manifest();
+    let public_key = manifest.register();
+    let key_id = decoded(&manifest.attestation.key_id);
+    let full = manifest.attestation().auth_data().len();
+    for len in 0..full {
+        let attestation = with_auth_data_cut(&manifest, len);
+        let result = attestation.verify_auth_data(&manifest.app_id_hash(), &key_id, &public_key);
+        assert!(result.is_err(), "auth data cut at {len}");
+    }
+}
+
+#[test]
+fn example_fixtures_are_the_recording() {
+    // `examples/fixtures` backs the examples and doc tests; it must stay a copy of the
+    // recording this suite checks.
+    let examples = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/fixtures");
+    let mut copied = 0;
+    for entry in std::fs::read_dir(&examples).unwrap() {
+        let name = entry.unwrap().file_name().into_string().unwrap();
+        if name == "README.md" {
+            continue;
+        }
+        let copy = std::fs::read_to_string(examples.join(&name)).unwrap();
+        assert_eq!(copy.trim(), fixture(&name), "examples/fixtures/{name}");
+        copied += 1;
+    }
+    assert_eq!(copied, 8);
+}
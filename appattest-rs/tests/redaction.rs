@@ -0,0 +1,151 @@
+//! The `Debug` of every public type that holds replayable input, over the device recording
+//! described by `apps/fixtures/manifest.json`: none may show a challenge, client data,
+//! signature or object, whole or cut short, in base64, hex or as `Vec<u8>`'s `Debug` lists
+//! bytes.
+
+use appattest_rs::assertion::Assertion;
+use appattest_rs::attestation::{Attestation, AttestationRef};
+use appattest_rs::sensitive::Sensitive;
+use base64::{engine::general_purpose, Engine};
+use ciborium::Value;
+
+#[path = "../benches/bench_utils/mod.rs"]
+mod bench_utils;
+
+use bench_utils::{decoded, fixture, manifest};
+
+/// Bytes of a secret whose encodings are looked for, as a log line might cut it.
+const PREFIX_LEN: usize = 15;
+
+fn assert_redacted(debug: &str, secret: &[u8]) {
+    for secret in [secret, &secret[..secret.len().min(PREFIX_LEN)]] {
+        let listed = format!("{secret:?}");
+        let encodings = [
+            general_purpose::STANDARD.encode(secret),
+            general_purpose::STANDARD_NO_PAD.encode(secret),
+            general_purpose::URL_SAFE_NO_PAD.encode(secret),
+            hex::encode(secret),
+            listed.trim_end_matches(']').to_string(),
+        ];
+        for encoding in encodings {
+            assert!(!debug.contains(&encoding), "{debug} shows {encoding}");
+        }
+    }
+}
+
+/// The object itself and every byte string in it, at any depth.
+fn secrets_of(cbor: &[u8]) -> Vec<Vec<u8>> {
+    fn walk(value: &Value, out: &mut Vec<Vec<u8>>) {
+        match value {
+            Value::Bytes(bytes) => out.push(bytes.clone()),
+            Value::Array(items) => items.iter().for_each(|item| walk(item, out)),
+            Value::Map(entries) => entries.iter().for_each(|(_, value)| walk(value, out)),
+            _ => {}
+        }
+    }
+    let mut out = vec![cbor.to_vec()];
+    walk(&ciborium::from_reader::<Value, _>(cbor).unwrap(), &mut out);
+    out
+}
+
+#[test]
+fn attestations_show_no_byte_strings() {
+    let manifest = manifest();
+    let cbor = decoded(&manifest.attestation.file);
+    let secrets = secrets_of(&cbor);
+    assert!(secrets.len() > 3, "the object, its certificates, receipt and authenticator data");
+
+    let attestation = manifest.attestation();
+    let borrowed = AttestationRef::from_cbor(&cbor).unwrap();
+    for debug in [format!("{attestation:?}"), format!("{borrowed:?}"), format!("{attestation:#?}")] {
+        assert!(debug.contains("apple-appattest"), "{debug}");
+        secrets.iter().for_each(|secret| assert_redacted(&debug, secret));
+    }
+}
+
+/// The byte string under `key` in the CBOR map `cbor`.
+fn field(cbor: &[u8], key: &str) -> Vec<u8> {
+    let value: Value = ciborium::from_reader(cbor).unwrap();
+    let entries = value.into_map().unwrap();
+    let (_, value) = entries.into_iter().find(|(k, _)| k.as_text() == Some(key)).unwrap();
+    value.into_bytes().unwrap()
+}
+
+#[test]
+fn assertions_show_no_signature() {
+    for recorded in manifest().assertions {
+        let cbor = decoded(&recorded.file);
+        let debug = format!("{:?}", recorded.assertion());
+        assert_redacted(&debug, &cbor);
+        assert_redacted(&debug, &field(&cbor, "signature"));
+    }
+}
+
+#[test]
+fn sensitive_values_show_a_digest_prefix() {
+    let manifest = manifest();
+    let challenge = decoded(&manifest.attestation.challenge);
+    let debug = format!("{:?}", Sensitive(&challenge));
+    assert!(debug.starts_with(&format!("<{} bytes, sha256 ", challenge.len())), "{debug}");
+    assert_redacted(&debug, &challenge);
+    assert_redacted(&Sensitive(&challenge).to_string(), &challenge);
+}
+
+#[test]
+fn base64_errors_do_not_quote_the_input() {
+    let manifest = manifest();
+    let corrupted = |file: &str| {
+        let mut text = fixture(file);
+        text.insert(40, '!');
+        text
+    };
+    let errors = [
+        Attestation::from_base64(&corrupted(&manifest.attestation.file)).unwrap_err(),
+        Assertion::from_base64(&corrupted(&manifest.assertions[0].file)).unwrap_err(),
+    ];
+    for error in errors {
+        // `DecodeError`'s own message names the byte, `!` being 33.
+        let debug = format!("{error:?} {error}");
+        assert!(debug.contains("invalid symbol at offset"), "{debug}");
+        assert!(!debug.contains("33"), "{debug}");
+    }
+}
+
+#[cfg(feature = "store")]
+#[test]
+fn request_contexts_show_no_challenge_or_client_data() {
+    use appattest_rs::service::RequestContext;
+
+    let manifest = manifest();
+    let challenge = decoded(&manifest.attestation.challenge);
+    let client_data = decoded(&manifest.assertions[0].client_data);
+    let context = RequestContext { challenge: &challenge, client_data: &client_data, time: manifest.recorded_at };
+    let debug = format!("{context:?}");
+    assert!(debug.contains(&manifest.recorded_at.to_string()), "{debug}");
+    assert_redacted(&debug, &challenge);
+    assert_redacted(&debug, &client_data);
+}
+
+#[cfg(feature = "store")]
+#[test]
+fn reattestation_requests_show_no_blobs() {
+    use appattest_rs::key::KeyId;
+    use appattest_rs::service::{AssertionRequest, AttestationRequest};
+
+    let manifest = manifest();
+    let attestation = decoded(&manifest.attestation.file);
+    let challenge = decoded(&manifest.attestation.challenge);
+    let assertion = decoded(&manifest.assertions[0].file);
+    let request = AttestationRequest {
+        key_id: KeyId::from_bytes(&[1; 32]).unwrap(),
+        attestation: &attestation,
+        challenge: &challenge,
+        time: manifest.recorded_at,
+    };
+    let proof = AssertionRequest { assertion: &assertion, client_data: &challenge };
+    let debug = format!("{request:?} {proof:?}");
+    assert!(debug.contains(&manifest.recorded_at.to_string()), "{debug}");
+    for blob in [&attestation, &challenge, &assertion] {
+        assert_redacted(&debug, blob);
+    }
+}
@@ -1,39 +1,249 @@
+use std::env;
+use std::fs;
 use std::process::Command;
 use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[path = "build/bb.rs"]
+mod bb;
+#[path = "build/freshness.rs"]
+mod freshness;
+#[path = "build/nargo.rs"]
+mod nargo;
+#[path = "build/project.rs"]
+mod project;
 
 fn main() {
-    compile_noir();
+    emit_build_info();
+    noir();
+    proto();
+    capi();
+}
+
+/// Writes `include/mobiscale.h` for the `capi` feature from `src/capi.rs`, and tells the
+/// C test which target to compile for.
+#[cfg(feature = "capi")]
+fn capi() {
+    println!("cargo:rerun-if-changed=src/capi.rs");
+    for var in ["TARGET", "HOST"] {
+        println!("cargo:rustc-env=CAPI_{var}={}", env::var(var).unwrap());
+    }
+    cbindgen::Builder::new()
+        .with_src("src/capi.rs")
+        .with_language(cbindgen::Language::C)
+        .with_include_guard("MOBISCALE_H")
+        .with_cpp_compat(true)
+        .generate()
+        .unwrap_or_else(|e| panic!("failed to generate the C header: {e}"))
+        .write_to_file("include/mobiscale.h");
+}
+
+#[cfg(not(feature = "capi"))]
+fn capi() {}
+
+/// Compiles `proto/mobiscale.proto` into OUT_DIR for the `proto` feature, with the protoc
+/// from `protoc-bin-vendored` so nothing has to be installed.
+#[cfg(feature = "proto")]
+fn proto() {
+    println!("cargo:rerun-if-changed=proto/mobiscale.proto");
+    let protoc = protoc_bin_vendored::protoc_bin_path().expect("no vendored protoc for this host");
+    prost_build::Config::new()
+        .protoc_executable(protoc)
+        .compile_protos(&["proto/mobiscale.proto"], &["proto"])
+        .unwrap_or_else(|e| panic!("failed to compile proto/mobiscale.proto: {e}"));
+}
+
+#[cfg(not(feature = "proto"))]
+fn proto() {}
+
+/// Exposes the git commit, build time and resolved risc0 version to the crate as env vars.
+fn emit_build_info() {
+    let git_commit = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .and_then(|o| String::from_utf8(o.stdout).ok())
+        .map(|s| s.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    let built_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    let risc0_version = std::fs::read_to_string("../Cargo.lock")
+        .ok()
+        .and_then(|lock| locked_version(&lock, "risc0-zkvm"))
+        .unwrap_or_else(|| "unknown".to_string());
+
+    println!("cargo:rustc-env=MOBISCALE_GIT_COMMIT={git_commit}");
+    println!("cargo:rustc-env=MOBISCALE_BUILT_AT={built_at}");
+    println!("cargo:rustc-env=MOBISCALE_RISC0_VERSION={risc0_version}");
+
+    println!("cargo:rerun-if-changed=../Cargo.lock");
+    println!("cargo:rerun-if-changed=../.git/HEAD");
+}
+
+/// Finds the version of `package` in the contents of a `Cargo.lock` file.
+fn locked_version(lock: &str, package: &str) -> Option<String> {
+    let name_line = format!("name = \"{package}\"");
+    let mut lines = lock.lines();
+    lines.find(|l| l.trim() == name_line)?;
+    lines
+        .next()?
+        .trim()
+        .strip_prefix("version = \"")?
+        .strip_suffix('"')
+        .map(str::to_string)
+}
+
+/// Compiles the Noir circuit and stages what `noir` embeds of it in `OUT_DIR/noir`: its
+/// sources, and with the `noir` feature its verification key.
+///
+/// The project is `NOIR_PROJECT_DIR`, or ../ecdsa by default. Without the `noir` feature a
+/// missing project just leaves nothing to compile; with it the build fails, unless
+/// `SKIP_NOIR=1`, which turns that into a warning.
+fn noir() {
+    println!("cargo:rerun-if-env-changed={}", project::PROJECT_DIR_ENV);
+    println!("cargo:rerun-if-env-changed=SKIP_NOIR");
+    let required = cfg!(feature = "noir");
+    let skip = env::var("SKIP_NOIR").as_deref() == Ok("1");
+
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR is set by cargo");
+    let project = match project::resolve(env::var(project::PROJECT_DIR_ENV).ok().as_deref(), Path::new(&manifest_dir)) {
+        Ok(dir) => Some(dir),
+        Err(dir) => {
+            // Without the feature, a missing project only means there is no circuit to build.
+            let message =
+                format!("no Noir project at {}; set {} to its directory", dir.display(), project::PROJECT_DIR_ENV);
+            if required && !skip {
+                panic!("{message}");
+            }
+            if required {
+                println!("cargo:warning={message}. Building without the circuit or its verification key.");
+            }
+            None
+        }
+    };
+
+    let out_dir = Path::new(&env::var("OUT_DIR").expect("OUT_DIR is set by cargo")).join("noir");
+    fs::create_dir_all(&out_dir).expect("unable to create noir/ in OUT_DIR");
+    stage_circuit(project.as_deref(), &out_dir);
+    if let Some(dir) = &project {
+        compile_noir(dir, required, skip);
+    }
+    if required {
+        embed_noir_vk(project.as_deref(), skip, &out_dir);
+    }
+}
+
+/// Copies the circuit's `Nargo.toml` and `src/main.nr` into `out_dir` for
+/// `prove_assertion_noir`; both are left empty without a project.
+fn stage_circuit(project: Option<&Path>, out_dir: &Path) {
+    for (source, staged) in [("Nargo.toml", "Nargo.toml"), ("src/main.nr", "main.nr")] {
+        let contents = match project {
+            Some(dir) => fs::read(dir.join(source)).unwrap_or_else(|e| panic!("unable to read {source}: {e}")),
+            None => Vec::new(),
+        };
+        fs::write(out_dir.join(staged), contents).expect("unable to stage the circuit");
+    }
 }
 
-fn compile_noir() {
-    // 1. Ensure `nargo` is available in the user's PATH.
-    let nargo_available = Command::new("nargo")
+/// Compiles the Noir circuit in `project` with `nargo`, unless it was last compiled from
+/// sources with the same content hash or `FORCE_NOIR_REBUILD=1`.
+///
+/// Only the `noir` feature makes this a requirement. Without it, a missing `nargo` or a
+/// failed compile is a warning, so the risc0 path builds on machines without Noir.
+/// `SKIP_NOIR=1` skips the step either way.
+fn compile_noir(project: &Path, required: bool, skip: bool) {
+    println!("cargo:rerun-if-env-changed=FORCE_NOIR_REBUILD");
+    let files = freshness::source_files(project)
+        .unwrap_or_else(|e| panic!("unable to list the sources of {}: {e}", project.display()));
+    // Per file: a directory does not reliably rerun us on every platform when a file in it
+    // changes. A new file only matters once an existing one declares it as a module.
+    for file in &files {
+        println!("cargo:rerun-if-changed={}", file.display());
+    }
+
+    if skip {
+        return;
+    }
+    let hash = freshness::source_hash(project, &files)
+        .unwrap_or_else(|e| panic!("unable to hash the sources of {}: {e}", project.display()));
+    let artifact = project.join("target/ecdsa.json");
+    let force = env::var("FORCE_NOIR_REBUILD").as_deref() == Ok("1");
+    if !freshness::needs_compile(&artifact, &hash, force) {
+        return;
+    }
+
+    // 1. Ensure `nargo` is available in the user's PATH, and is a release the circuit compiles with.
+    let version_output = Command::new("nargo")
         .arg("--version")
         .output()
-        .map(|o| o.status.success())
-        .unwrap_or(false);
+        .ok()
+        .filter(|o| o.status.success());
 
-    if !nargo_available {
-        panic!(
-            "`nargo` was not found in your PATH.\n\
+    let Some(version_output) = version_output else {
+        let message = "`nargo` was not found in your PATH, so the Noir circuit was not compiled. \
              Install Noir by following the quick‑start guide: \
-             https://noir-lang.org/docs/getting_started/quick_start"
-        );
+             https://noir-lang.org/docs/getting_started/quick_start";
+        return give_up(required, message);
+    };
+    if let Err(message) = nargo::check(&String::from_utf8_lossy(&version_output.stdout)) {
+        return give_up(required, &message);
     }
 
-    // 2. Run `nargo compile` in the ../ecdsa folder to build the Noir proof.
-    let ecdsa_dir = Path::new("../ecdsa");
-    let status = Command::new("nargo")
+    // 2. Run `nargo compile` in the project to build the circuit.
+    let output = Command::new("nargo")
         .arg("compile")
-        .current_dir(&ecdsa_dir)
-        .status()
+        .current_dir(project)
+        .output()
         .expect("Failed to spawn `nargo compile`");
 
-    if !status.success() {
-        panic!("`nargo compile` failed for project at {:?}", ecdsa_dir);
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = format!(
+            "`nargo compile` failed for project at {:?} ({}):\n{}",
+            project,
+            output.status,
+            stderr.trim_end()
+        );
+        return give_up(required, &message);
+    }
+    if let Err(e) = freshness::record(&artifact, &hash) {
+        println!("cargo:warning=unable to record the Noir sources' hash ({e}); the next build compiles again");
+    }
+}
+
+/// Writes the circuit's verification key to `out_dir/vk`, which `noir::VERIFICATION_KEY`
+/// embeds. Runs on every build script run, so the key always belongs to the compiled circuit.
+/// With `SKIP_NOIR=1` and no compiled circuit, the key is left empty.
+fn embed_noir_vk(project: Option<&Path>, skip: bool, out_dir: &Path) {
+    let artifact = project.map(|dir| dir.join("target/ecdsa.json")).filter(|artifact| artifact.is_file());
+    let Some(artifact) = artifact else {
+        if !skip {
+            panic!("the `noir` feature embeds the verification key of the compiled circuit, but there is none");
+        }
+        if project.is_some() {
+            println!("cargo:warning=SKIP_NOIR=1 and the circuit was never compiled; embedding no verification key");
+        }
+        fs::write(out_dir.join("vk"), []).expect("unable to write the verification key");
+        return;
+    };
+    if let Err(message) = bb::write_vk(&artifact, out_dir) {
+        panic!("{message}");
     }
+}
 
-    // 3. Invalidate the Cargo build when anything in the ../ecdsa directory changes.
-    println!("cargo:rerun-if-changed=../ecdsa");
-    println!("cargo:rerun-if-changed=../ecdsa/src");
-}
\ No newline at end of file
+/// Fails the build over the Noir step if the `noir` feature requires it, and otherwise
+/// warns, one `cargo:warning` per line, and carries on without the circuit.
+fn give_up(required: bool, message: &str) {
+    if required {
+        panic!("{message}");
+    }
+    for line in message.lines() {
+        println!("cargo:warning={line}");
+    }
+    println!("cargo:warning=(set SKIP_NOIR=1 to skip this step)");
+}
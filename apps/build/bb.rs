@@ -0,0 +1,76 @@
+//! Writing the `ecdsa` circuit's verification key with Barretenberg's `bb`, so build.rs can
+//! embed it. Compiled into the crate's tests through `#[path]`, like `freshness.rs`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Runs `bb write_vk` over the compiled circuit `bytecode`, which writes `out_dir/vk`, and
+/// returns that path.
+pub fn write_vk(bytecode: &Path, out_dir: &Path) -> Result<PathBuf, String> {
+    fs::create_dir_all(out_dir).map_err(|e| format!("failed to create {}: {e}", out_dir.display()))?;
+    let output = Command::new("bb")
+        .arg("write_vk")
+        .arg("-b")
+        .arg(bytecode)
+        .arg("-o")
+        .arg(out_dir)
+        .output()
+        .map_err(|e| format!("failed to run `bb write_vk` ({e}); install Barretenberg's bb with `bbup`"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "`bb write_vk` failed for {} ({}):\n{}",
+            bytecode.display(),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        ));
+    }
+    let vk = out_dir.join("vk");
+    if !vk.is_file() {
+        return Err(format!("`bb write_vk` did not write {}", vk.display()));
+    }
+    Ok(vk)
+}
+
+#[cfg(test)]
+mod tests {
+    use sha3::{Digest, Keccak256};
+
+    use super::*;
+
+    const ECDSA_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../ecdsa");
+
+    /// Compiles the circuit in `dir` and returns the keccak digest of its verification key.
+    fn vk_digest(dir: &Path) -> [u8; 32] {
+        let status = Command::new("nargo").arg("compile").current_dir(dir).status().unwrap();
+        assert!(status.success(), "nargo compile failed in {}", dir.display());
+        let vk = write_vk(&dir.join("target/ecdsa.json"), &dir.join("target")).unwrap();
+        Keccak256::digest(fs::read(vk).unwrap()).into()
+    }
+
+    #[test]
+    #[ignore = "compiles the ecdsa circuit twice; needs nargo and bb"]
+    fn changing_the_circuit_changes_the_vk_digest() {
+        let available = ["nargo", "bb"]
+            .iter()
+            .all(|tool| Command::new(tool).arg("--version").output().is_ok_and(|o| o.status.success()));
+        if !available {
+            eprintln!("skipping: nargo or bb is not on PATH");
+            return;
+        }
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::copy(Path::new(ECDSA_DIR).join("Nargo.toml"), dir.path().join("Nargo.toml")).unwrap();
+        let main_nr = fs::read_to_string(Path::new(ECDSA_DIR).join("src/main.nr")).unwrap();
+        fs::write(dir.path().join("src/main.nr"), &main_nr).unwrap();
+        let before = vk_digest(dir.path());
+        assert_eq!(vk_digest(dir.path()), before, "the same circuit gives the same key");
+
+        // One more constraint on the private inputs.
+        let last = "    assert(verify_secp256r1_ecdsa(";
+        let tweaked = main_nr.replacen(last, &format!("    assert(r != s);\n{last}"), 1);
+        assert_ne!(tweaked, main_nr);
+        fs::write(dir.path().join("src/main.nr"), tweaked).unwrap();
+        assert_ne!(vk_digest(dir.path()), before);
+    }
+}
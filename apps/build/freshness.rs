@@ -0,0 +1,145 @@
+//! Whether the compiled circuit still belongs to its sources, so build.rs can skip
+//! `nargo compile`. The sources' content hash is stored next to the artifact; modification
+//! times are not used, since a checkout or a `touch` changes them without changing anything.
+//! Compiled into the crate's tests through `#[path]`, since build scripts have none.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+/// The files `nargo compile` reads in `project`: its `Nargo.toml` and everything under
+/// `src/`, sorted so the hash does not depend on directory order.
+pub fn source_files(project: &Path) -> io::Result<Vec<PathBuf>> {
+    fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                walk(&path, files)?;
+            } else {
+                files.push(path);
+            }
+        }
+        Ok(())
+    }
+
+    let mut files = vec![project.join("Nargo.toml")];
+    walk(&project.join("src"), &mut files)?;
+    files.sort();
+    Ok(files)
+}
+
+/// Hex SHA-256 over `files`, each as its path relative to `project` and its contents, so
+/// renaming a file changes the hash as well as editing one.
+pub fn source_hash(project: &Path, files: &[PathBuf]) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    for file in files {
+        let name = file.strip_prefix(project).unwrap_or(file).to_string_lossy();
+        let contents = fs::read(file)?;
+        // Length-prefixed, so no two file lists hash alike by shifting bytes between entries.
+        for part in [name.as_bytes(), &contents] {
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part);
+        }
+    }
+    Ok(hasher.finalize().iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    }))
+}
+
+/// Where the hash of the sources `artifact` was compiled from is kept: next to it, with
+/// `.sha256` appended.
+pub fn hash_file(artifact: &Path) -> PathBuf {
+    let mut name = artifact.as_os_str().to_owned();
+    name.push(".sha256");
+    PathBuf::from(name)
+}
+
+/// Whether `artifact` has to be compiled: if `force`, if it does not exist, or if it was
+/// compiled from sources with another hash than `hash`.
+pub fn needs_compile(artifact: &Path, hash: &str, force: bool) -> bool {
+    force || !artifact.is_file() || !fs::read_to_string(hash_file(artifact)).is_ok_and(|stored| stored.trim() == hash)
+}
+
+/// Records that `artifact` was compiled from sources hashing to `hash`.
+pub fn record(artifact: &Path, hash: &str) -> io::Result<()> {
+    fs::write(hash_file(artifact), hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A Noir project with a nested module and a compiled artifact, and its artifact path.
+    fn project() -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src/lib")).unwrap();
+        fs::create_dir_all(dir.path().join("target")).unwrap();
+        fs::write(dir.path().join("Nargo.toml"), "[package]\nname = \"ecdsa\"\n").unwrap();
+        fs::write(dir.path().join("src/main.nr"), "mod lib;\nfn main() {}\n").unwrap();
+        fs::write(dir.path().join("src/lib/util.nr"), "fn util() {}\n").unwrap();
+        let artifact = dir.path().join("target/ecdsa.json");
+        fs::write(&artifact, "{}").unwrap();
+        (dir, artifact)
+    }
+
+    fn hash(project: &Path) -> String {
+        source_hash(project, &source_files(project).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn lists_the_manifest_and_every_source_sorted() {
+        let (dir, _) = project();
+        let files = source_files(dir.path()).unwrap();
+        let names: Vec<_> = files.iter().map(|f| f.strip_prefix(dir.path()).unwrap().to_str().unwrap()).collect();
+        assert_eq!(names, ["Nargo.toml", "src/lib/util.nr", "src/main.nr"]);
+        // Not build output, which would change the hash on every compile.
+        assert!(!files.iter().any(|f| f.starts_with(dir.path().join("target"))));
+    }
+
+    #[test]
+    fn hash_follows_contents_and_names_only() {
+        let (dir, _) = project();
+        let before = hash(dir.path());
+        assert_eq!(before.len(), 64);
+        assert_eq!(hash(dir.path()), before);
+
+        // Rewriting a file as it was moves its mtime but not the hash.
+        fs::write(dir.path().join("src/main.nr"), "mod lib;\nfn main() {}\n").unwrap();
+        assert_eq!(hash(dir.path()), before);
+
+        fs::write(dir.path().join("src/lib/util.nr"), "fn util() { assert(true); }\n").unwrap();
+        let edited = hash(dir.path());
+        assert_ne!(edited, before);
+
+        fs::rename(dir.path().join("src/lib/util.nr"), dir.path().join("src/lib/helpers.nr")).unwrap();
+        assert_ne!(hash(dir.path()), edited);
+    }
+
+    #[test]
+    fn recorded_hash_skips_the_compile_until_the_sources_change() {
+        let (dir, artifact) = project();
+        let current = hash(dir.path());
+        assert!(needs_compile(&artifact, &current, false), "nothing recorded yet");
+
+        record(&artifact, &current).unwrap();
+        assert_eq!(hash_file(&artifact), dir.path().join("target/ecdsa.json.sha256"));
+        assert!(!needs_compile(&artifact, &current, false));
+        assert!(needs_compile(&artifact, &current, true), "FORCE_NOIR_REBUILD");
+
+        fs::write(dir.path().join("Nargo.toml"), "[package]\nname = \"ecdsa\"\ntype = \"bin\"\n").unwrap();
+        assert!(needs_compile(&artifact, &hash(dir.path()), false));
+    }
+
+    #[test]
+    fn missing_artifact_needs_a_compile_whatever_was_recorded() {
+        let (dir, artifact) = project();
+        let current = hash(dir.path());
+        record(&artifact, &current).unwrap();
+        fs::remove_file(&artifact).unwrap();
+        assert!(needs_compile(&artifact, &current, false));
+    }
+}
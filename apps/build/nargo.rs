@@ -0,0 +1,203 @@
+//! Which `nargo` the circuit in ../ecdsa compiles with, and whether the installed one is it.
+//! Noir breaks compatibility often enough that a mismatch is worth catching before
+//! `nargo compile` fails somewhere inside a dependency. Compiled into the crate's tests
+//! through `#[path]`, like `freshness.rs`.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::Range;
+
+/// The nargo releases the circuit and its pinned dependencies are known to compile with.
+/// The end is exclusive; bump both bounds together with the dependencies in Nargo.toml.
+pub const SUPPORTED: Range<&str> = "1.0.0-beta.3".."1.1.0";
+
+/// A semver version; build metadata is dropped since it does not affect ordering.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    pub pre: Vec<Identifier>,
+}
+
+/// One dot-separated part of a pre-release, e.g. `beta` or `3` in `1.0.0-beta.3`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Identifier {
+    // Declared first: semver orders numeric identifiers below alphanumeric ones.
+    Numeric(u64),
+    Alpha(String),
+}
+
+impl Version {
+    /// Parses `1.0.0`, `1.0.0-beta.3` or `1.0.0-beta.3+ceaa198`, with an optional leading `v`.
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.strip_prefix('v').unwrap_or(s);
+        let s = s.split_once('+').map_or(s, |(version, _build)| version);
+        let (core, pre) = match s.split_once('-') {
+            Some((core, pre)) => (core, Some(pre)),
+            None => (s, None),
+        };
+
+        let mut numbers = core.split('.').map(|n| n.parse::<u64>().ok());
+        let (Some(Some(major)), Some(Some(minor)), Some(Some(patch)), None) =
+            (numbers.next(), numbers.next(), numbers.next(), numbers.next())
+        else {
+            return None;
+        };
+
+        let pre = match pre {
+            None => Vec::new(),
+            Some(pre) => pre
+                .split('.')
+                .map(|id| match id.parse() {
+                    Ok(n) => Some(Identifier::Numeric(n)),
+                    Err(_) if !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') => {
+                        Some(Identifier::Alpha(id.to_string()))
+                    }
+                    Err(_) => None,
+                })
+                .collect::<Option<_>>()?,
+        };
+        Some(Version { major, minor, patch, pre })
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (self.pre.is_empty(), other.pre.is_empty()) {
+                // A pre-release comes before its release.
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => self.pre.cmp(&other.pre),
+            })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        for (i, id) in self.pre.iter().enumerate() {
+            f.write_str(if i == 0 { "-" } else { "." })?;
+            match id {
+                Identifier::Numeric(n) => write!(f, "{n}")?,
+                Identifier::Alpha(s) => f.write_str(s)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The nargo version in the output of `nargo --version`.
+///
+/// Current releases print `nargo version = 1.0.0-beta.3` followed by a `noirc version` line;
+/// releases before 0.19 printed `nargo 0.10.5 (git version hash: …)`.
+pub fn parse_version_output(output: &str) -> Option<Version> {
+    output.lines().find_map(|line| {
+        let rest = line.trim().strip_prefix("nargo")?;
+        let rest = rest.trim_start().strip_prefix("version").unwrap_or(rest);
+        let rest = rest.trim_start().strip_prefix('=').unwrap_or(rest);
+        Version::parse(rest.split_whitespace().next()?)
+    })
+}
+
+/// Checks the output of `nargo --version` against [`SUPPORTED`], returning the installed
+/// version or a message naming it, the supported range and how to install a supported one.
+pub fn check(output: &str) -> Result<Version, String> {
+    let min = Version::parse(SUPPORTED.start).expect("SUPPORTED.start is a version");
+    let max = Version::parse(SUPPORTED.end).expect("SUPPORTED.end is a version");
+    let install = format!("install a supported one with `noirup --version {min}`");
+
+    let Some(installed) = parse_version_output(output) else {
+        return Err(format!(
+            "could not read the nargo version from `nargo --version` (printed {:?}); \
+             the circuit in ../ecdsa needs nargo >= {min}, < {max}; {install}",
+            output.trim()
+        ));
+    };
+    if installed < min || installed >= max {
+        return Err(format!(
+            "nargo {installed} is installed, but the circuit in ../ecdsa needs nargo >= {min}, < {max}; {install}"
+        ));
+    }
+    Ok(installed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(s: &str) -> Version {
+        Version::parse(s).unwrap()
+    }
+
+    #[test]
+    fn parses_current_version_output() {
+        let output = "nargo version = 1.0.0-beta.3\n\
+                      noirc version = 1.0.0-beta.3+ceaa1986628197bd1170147f6a07f0f98d21030a\n\
+                      (git version hash: ceaa1986628197bd1170147f6a07f0f98d21030a, is dirty: false)\n";
+        assert_eq!(parse_version_output(output), Some(v("1.0.0-beta.3")));
+        assert_eq!(parse_version_output("nargo version = 0.36.0\nnoirc version = 0.36.0+801c71"), Some(v("0.36.0")));
+    }
+
+    #[test]
+    fn parses_old_version_output() {
+        let output = "nargo 0.10.5 (git version hash: 1b3be6e2a4b8f2f7d0ee1ff9c1c8e6a8a0b14e31, is dirty: false)";
+        assert_eq!(parse_version_output(output), Some(v("0.10.5")));
+    }
+
+    #[test]
+    fn rejects_output_without_a_version() {
+        assert_eq!(parse_version_output(""), None);
+        assert_eq!(parse_version_output("error: unrecognized subcommand '--version'"), None);
+        assert_eq!(parse_version_output("nargo version = unknown"), None);
+        assert_eq!(parse_version_output("noirc version = 1.0.0-beta.3"), None);
+    }
+
+    #[test]
+    fn parse_handles_prefixes_build_metadata_and_bad_input() {
+        assert_eq!(v("v1.2.3"), v("1.2.3"));
+        assert_eq!(v("1.0.0-beta.3+ceaa198").pre, v("1.0.0-beta.3").pre);
+        assert_eq!(v("1.0.0-beta.3").to_string(), "1.0.0-beta.3");
+        for bad in ["1.0", "1.0.0.0", "1.x.0", "1.0.0-", "1.0.0-beta..3", ""] {
+            assert_eq!(Version::parse(bad), None, "{bad:?}");
+        }
+    }
+
+    #[test]
+    fn orders_pre_releases_like_semver() {
+        let ordered = ["0.36.0", "1.0.0-alpha", "1.0.0-beta.2", "1.0.0-beta.3", "1.0.0-beta.10", "1.0.0", "1.0.1"];
+        for pair in ordered.windows(2) {
+            assert!(v(pair[0]) < v(pair[1]), "{} < {}", pair[0], pair[1]);
+        }
+        assert!(v("1.0.0-1") < v("1.0.0-alpha"));
+    }
+
+    #[test]
+    fn check_accepts_the_supported_range_only() {
+        assert_eq!(check("nargo version = 1.0.0-beta.3\n"), Ok(v("1.0.0-beta.3")));
+        assert!(check("nargo version = 1.0.0\n").is_ok());
+        assert!(check("nargo version = 1.0.0-beta.2\n").is_err());
+        assert!(check("nargo version = 1.1.0\n").is_err());
+    }
+
+    #[test]
+    fn check_names_both_versions_and_the_install_command() {
+        let message = check("nargo version = 0.36.0\nnoirc version = 0.36.0+801c71").unwrap_err();
+        assert!(message.contains("nargo 0.36.0 is installed"), "{message}");
+        assert!(message.contains(">= 1.0.0-beta.3, < 1.1.0"), "{message}");
+        assert!(message.contains("noirup --version 1.0.0-beta.3"), "{message}");
+
+        let message = check("garbage").unwrap_err();
+        assert!(message.contains("\"garbage\""), "{message}");
+        assert!(message.contains("noirup"), "{message}");
+    }
+}
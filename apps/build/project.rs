@@ -0,0 +1,76 @@
+//! Where the `ecdsa` Noir project is, so build.rs also works with the apps crate vendored
+//! into another workspace. Compiled into the crate's tests through `#[path]`, like
+//! `freshness.rs`.
+
+use std::path::{Path, PathBuf};
+
+/// Overrides the project's location; a relative path is taken from the apps crate.
+pub const PROJECT_DIR_ENV: &str = "NOIR_PROJECT_DIR";
+/// Where the project sits in this repository, relative to the apps crate.
+pub const DEFAULT_PROJECT_DIR: &str = "../ecdsa";
+
+/// The Noir project to build: `env_value`, the value of [`PROJECT_DIR_ENV`], if it is set and
+/// not blank, and [`DEFAULT_PROJECT_DIR`] otherwise, resolved against `manifest_dir` unless
+/// absolute. `Err` carries the resolved path when no `Nargo.toml` is there.
+pub fn resolve(env_value: Option<&str>, manifest_dir: &Path) -> Result<PathBuf, PathBuf> {
+    let dir = match env_value.map(str::trim) {
+        Some(value) if !value.is_empty() => value,
+        _ => DEFAULT_PROJECT_DIR,
+    };
+    // `join` keeps an absolute `dir` as it is.
+    let dir = manifest_dir.join(dir);
+    if dir.join("Nargo.toml").is_file() {
+        Ok(dir)
+    } else {
+        Err(dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    /// A workspace with the apps crate at `apps/` and Noir projects at `ecdsa/` and `vendor/circuit/`.
+    fn workspace() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for project in ["ecdsa", "vendor/circuit"] {
+            fs::create_dir_all(dir.path().join(project)).unwrap();
+            fs::write(dir.path().join(project).join("Nargo.toml"), "[package]\n").unwrap();
+        }
+        fs::create_dir_all(dir.path().join("apps")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn defaults_to_the_sibling_project() {
+        let workspace = workspace();
+        let apps = workspace.path().join("apps");
+        assert_eq!(resolve(None, &apps), Ok(apps.join("../ecdsa")));
+        assert_eq!(resolve(Some("  "), &apps), Ok(apps.join("../ecdsa")));
+    }
+
+    #[test]
+    fn env_override_is_relative_to_the_crate_unless_absolute() {
+        let workspace = workspace();
+        let apps = workspace.path().join("apps");
+        assert_eq!(resolve(Some("../vendor/circuit"), &apps), Ok(apps.join("../vendor/circuit")));
+
+        let absolute = workspace.path().join("vendor/circuit");
+        assert!(absolute.is_absolute());
+        assert_eq!(resolve(Some(absolute.to_str().unwrap()), Path::new("/elsewhere")), Ok(absolute));
+    }
+
+    #[test]
+    fn missing_project_is_an_error_naming_the_path() {
+        let workspace = workspace();
+        let apps = workspace.path().join("apps");
+        assert_eq!(resolve(Some("../nowhere"), &apps), Err(apps.join("../nowhere")));
+        // A directory without a Nargo.toml is not a project either.
+        assert_eq!(resolve(Some("."), &apps), Err(apps.join(".")));
+
+        fs::remove_dir_all(workspace.path().join("ecdsa")).unwrap();
+        assert_eq!(resolve(None, &apps), Err(apps.join("../ecdsa")));
+    }
+}
@@ -0,0 +1,17 @@
+// Recomputes the hashes and signature in bundle.json with ethers.js v6:
+//
+//     npm install ethers@6 && node sign.mjs
+//
+// and prints bundle.json with them filled in.
+import { readFileSync } from "node:fs";
+import { TypedDataEncoder, Wallet } from "ethers";
+
+const fixture = JSON.parse(readFileSync(new URL("./bundle.json", import.meta.url), "utf8"));
+const { domain, types, message } = fixture;
+const wallet = new Wallet(fixture.privateKey);
+
+fixture.structHash = TypedDataEncoder.hashStruct("RelayBundle", types, message);
+fixture.digest = TypedDataEncoder.hash(domain, types, message);
+fixture.signature = await wallet.signTypedData(domain, types, message);
+fixture.signer = wallet.address;
+console.log(JSON.stringify(fixture, null, 2));
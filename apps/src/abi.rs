@@ -0,0 +1,460 @@
+//! The assertion and attestation journals in Solidity's ABI encoding, so that a contract can
+//! read one with `abi.decode` instead of slicing the packed layout the guests commit.
+//!
+//! Every field is static, so an encoding is one 32-byte word per field and a contract
+//! decodes it with `abi.decode(journal, (AssertionJournal))` given
+//!
+//! ```solidity
+//! struct AssertionJournal {
+//!     uint8 version;
+//!     bytes6 policy;
+//!     bytes32 rpIdHash;
+//!     bytes32 publicKeyX;
+//!     bytes32 publicKeyY;
+//!     bytes32 signatureR;
+//!     bytes32 signatureS;
+//!     uint32 counter;
+//!     bytes32 clientDataHash;
+//!     bytes32 attestationImageId;
+//!     bytes32 attestationClaimDigest;
+//!     bytes32 challengeHash;
+//!     uint32 allowlistEntry;
+//!     bytes32 sessionNonce;
+//!     bytes32 inputDigest;
+//!     bytes32 policyHash;
+//!     bytes32 certChainImageId;
+//! }
+//!
+//! struct AttestationJournal {
+//!     uint8 version;
+//!     bytes6 policy;
+//!     bytes32 publicKeyX;
+//!     bytes32 publicKeyY;
+//!     bytes32 keyId;
+//!     bytes32 rpIdHash;
+//!     uint8 environment;
+//!     int64 timestamp;
+//!     bytes32 timeSigner;
+//!     uint32 allowlistEntry;
+//!     bytes32 certChainImageId;
+//!     bytes32 certChainClaimDigest;
+//!     bytes32 inputDigest;
+//!     bytes32 policyHash;
+//! }
+//! ```
+//!
+//! The fields are those of [`AssertionJournal`] and [`AttestationJournal`], in the same
+//! order, and `version` is the packed layout's version byte. The attestation's public key is
+//! split into its coordinates, dropping the `0x04` of uncompressed SEC1. A journal of a
+//! previous layout, as `mobiscale_verifier::codec` reads it, keeps its version and has
+//! zeros for the fields it lacks: `policyHash`, and an assertion's `certChainImageId`.
+//!
+//! The guests still commit the packed layout, which is what their receipts prove: a host
+//! re-encodes a verified journal with [`encode_assertion_journal`] before submitting it,
+//! and the contract checks the receipt against the packed bytes, or recomputes them.
+
+use guest_core::{
+    AssertionJournal, AttestationJournal, Policy, ASSERTION_JOURNAL_V7, ASSERTION_JOURNAL_V9, ATTESTATION_JOURNAL_V6,
+    ATTESTATION_JOURNAL_V7,
+};
+use mobiscale_verifier::{JournalCodec, ASSERTION_JOURNALS, ATTESTATION_JOURNALS};
+
+use crate::Risc0Error;
+
+/// Words in an ABI-encoded assertion journal.
+const ASSERTION_WORDS: usize = 17;
+
+/// Words in an ABI-encoded attestation journal.
+const ATTESTATION_WORDS: usize = 14;
+
+/// `abi.encode` of `journal` as the Solidity `AssertionJournal`.
+pub fn encode_assertion_journal(journal: &AssertionJournal) -> Vec<u8> {
+    let mut words = Words::with_capacity(ASSERTION_WORDS);
+    words.uint(journal.version().into());
+    words.fixed_bytes(&journal.policy.encode());
+    words.fixed_bytes(&journal.rp_id_hash);
+    words.fixed_bytes(&journal.public_key_x);
+    words.fixed_bytes(&journal.public_key_y);
+    words.fixed_bytes(&journal.signature_r);
+    words.fixed_bytes(&journal.signature_s);
+    words.uint(journal.counter.into());
+    words.fixed_bytes(&journal.client_data_hash);
+    words.fixed_bytes(&journal.attestation_image_id);
+    words.fixed_bytes(&journal.attestation_claim_digest);
+    words.fixed_bytes(&journal.challenge_hash);
+    words.uint(journal.allowlist_entry.into());
+    words.fixed_bytes(&journal.session_nonce);
+    words.fixed_bytes(&journal.input_digest);
+    words.fixed_bytes(&journal.policy_hash.unwrap_or_default());
+    words.fixed_bytes(&journal.cert_chain_image_id.unwrap_or_default());
+    words.0
+}
+
+/// Reads [`encode_assertion_journal`], rejecting versions [`ASSERTION_JOURNALS`] does not
+/// read, any length but the exact one and values out of their Solidity type's range, as
+/// `abi.decode` would.
+pub fn decode_assertion_journal(bytes: &[u8]) -> Result<AssertionJournal, Risc0Error> {
+    let mut words = Reader::new(bytes, ASSERTION_WORDS)?;
+    let version = words.version(&ASSERTION_JOURNALS)?;
+    Ok(AssertionJournal {
+        policy: Policy::decode(&words.fixed_bytes("policy")?),
+        rp_id_hash: words.fixed_bytes("rpIdHash")?,
+        public_key_x: words.fixed_bytes("publicKeyX")?,
+        public_key_y: words.fixed_bytes("publicKeyY")?,
+        signature_r: words.fixed_bytes("signatureR")?,
+        signature_s: words.fixed_bytes("signatureS")?,
+        counter: words.uint("counter")?,
+        client_data_hash: words.fixed_bytes("clientDataHash")?,
+        attestation_image_id: words.fixed_bytes("attestationImageId")?,
+        attestation_claim_digest: words.fixed_bytes("attestationClaimDigest")?,
+        challenge_hash: words.fixed_bytes("challengeHash")?,
+        allowlist_entry: words.uint("allowlistEntry")?,
+        session_nonce: words.fixed_bytes("sessionNonce")?,
+        input_digest: words.fixed_bytes("inputDigest")?,
+        policy_hash: words.committed_fixed_bytes("policyHash", version != ASSERTION_JOURNAL_V7)?,
+        cert_chain_image_id: words.committed_fixed_bytes("certChainImageId", version == ASSERTION_JOURNAL_V9)?,
+    })
+}
+
+/// `abi.encode` of `journal` as the Solidity `AttestationJournal`.
+pub fn encode_attestation_journal(journal: &AttestationJournal) -> Vec<u8> {
+    let mut words = Words::with_capacity(ATTESTATION_WORDS);
+    let version = if journal.policy_hash.is_some() { ATTESTATION_JOURNAL_V7 } else { ATTESTATION_JOURNAL_V6 };
+    words.uint(version.into());
+    words.fixed_bytes(&journal.policy.encode());
+    words.fixed_bytes(&journal.public_key[1..33]);
+    words.fixed_bytes(&journal.public_key[33..]);
+    words.fixed_bytes(&journal.key_id);
+    words.fixed_bytes(&journal.rp_id_hash);
+    words.uint(journal.environment.into());
+    words.int(journal.timestamp);
+    words.fixed_bytes(&journal.time_signer);
+    words.uint(journal.allowlist_entry.into());
+    words.fixed_bytes(&journal.cert_chain_image_id);
+    words.fixed_bytes(&journal.cert_chain_claim_digest);
+    words.fixed_bytes(&journal.input_digest);
+    words.fixed_bytes(&journal.policy_hash.unwrap_or_default());
+    words.0
+}
+
+/// Reads [`encode_attestation_journal`], like [`decode_assertion_journal`].
+pub fn decode_attestation_journal(bytes: &[u8]) -> Result<AttestationJournal, Risc0Error> {
+    let mut words = Reader::new(bytes, ATTESTATION_WORDS)?;
+    let version = words.version(&ATTESTATION_JOURNALS)?;
+    let policy = Policy::decode(&words.fixed_bytes("policy")?);
+    let mut public_key = [0x04; 65];
+    public_key[1..33].copy_from_slice(&words.fixed_bytes::<32>("publicKeyX")?);
+    public_key[33..].copy_from_slice(&words.fixed_bytes::<32>("publicKeyY")?);
+    Ok(AttestationJournal {
+        policy,
+        public_key,
+        key_id: words.fixed_bytes("keyId")?,
+        rp_id_hash: words.fixed_bytes("rpIdHash")?,
+        environment: words.uint("environment")?,
+        timestamp: words.int("timestamp")?,
+        time_signer: words.fixed_bytes("timeSigner")?,
+        allowlist_entry: words.uint("allowlistEntry")?,
+        cert_chain_image_id: words.fixed_bytes("certChainImageId")?,
+        cert_chain_claim_digest: words.fixed_bytes("certChainClaimDigest")?,
+        input_digest: words.fixed_bytes("inputDigest")?,
+        policy_hash: words.committed_fixed_bytes("policyHash", version != ATTESTATION_JOURNAL_V6)?,
+    })
+}
+
+/// An encoding under construction, one word per field.
+struct Words(Vec<u8>);
+
+impl Words {
+    fn with_capacity(words: usize) -> Self {
+        Words(Vec::with_capacity(words * 32))
+    }
+
+    /// `uintN`, big-endian and left-padded.
+    fn uint(&mut self, value: u64) {
+        self.0.extend_from_slice(&[0; 24]);
+        self.0.extend_from_slice(&value.to_be_bytes());
+    }
+
+    /// `intN`, big-endian and sign-extended.
+    fn int(&mut self, value: i64) {
+        self.0.extend_from_slice(&[if value < 0 { 0xff } else { 0 }; 24]);
+        self.0.extend_from_slice(&value.to_be_bytes());
+    }
+
+    /// `bytesN`, right-padded.
+    fn fixed_bytes(&mut self, value: &[u8]) {
+        self.0.extend_from_slice(value);
+        self.0.extend_from_slice(&[0; 32][value.len()..]);
+    }
+}
+
+/// The words of an encoding whose length [`Reader::new`] checked, in field order.
+struct Reader<'a> {
+    words: std::slice::ChunksExact<'a, u8>,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8], words: usize) -> Result<Self, Risc0Error> {
+        if bytes.len() != words * 32 {
+            return Err(Risc0Error::JournalError(format!(
+                "ABI journal is {} bytes, expected {}",
+                bytes.len(),
+                words * 32
+            )));
+        }
+        Ok(Reader { words: bytes.chunks_exact(32) })
+    }
+
+    fn next(&mut self) -> &'a [u8] {
+        // `new` checked the length, and each decoder reads exactly its word count.
+        self.words.next().unwrap_or(&[0; 32])
+    }
+
+    /// A version `codec` reads.
+    fn version<J>(&mut self, codec: &JournalCodec<J>) -> Result<u8, Risc0Error> {
+        let found: u8 = self.uint("version")?;
+        if !codec.supports(found) {
+            return Err(Risc0Error::UnsupportedJournalVersion { found, supported: codec.supported() });
+        }
+        Ok(found)
+    }
+
+    /// A `bytes32` the journal's version appended, or `None` if it had not `committed` the
+    /// field, and then only if it is zero.
+    fn committed_fixed_bytes(&mut self, field: &str, committed: bool) -> Result<Option<[u8; 32]>, Risc0Error> {
+        let value = self.fixed_bytes(field)?;
+        match (committed, value == [0; 32]) {
+            (true, _) => Ok(Some(value)),
+            (false, true) => Ok(None),
+            (false, false) => Err(out_of_range(field)),
+        }
+    }
+
+    /// A `uintN` that fits `T`.
+    fn uint<T: TryFrom<u64>>(&mut self, field: &str) -> Result<T, Risc0Error> {
+        let word = self.next();
+        let (high, low) = word.split_at(24);
+        let value = u64::from_be_bytes(low.try_into().unwrap_or_default());
+        if high.iter().any(|&b| b != 0) {
+            return Err(out_of_range(field));
+        }
+        T::try_from(value).map_err(|_| out_of_range(field))
+    }
+
+    /// An `int64`, whose upper bytes must be its sign extension.
+    fn int(&mut self, field: &str) -> Result<i64, Risc0Error> {
+        let word = self.next();
+        let (high, low) = word.split_at(24);
+        let value = i64::from_be_bytes(low.try_into().unwrap_or_default());
+        let sign = if value < 0 { 0xff } else { 0 };
+        if high.iter().any(|&b| b != sign) {
+            return Err(out_of_range(field));
+        }
+        Ok(value)
+    }
+
+    /// A `bytesN`, whose padding must be zeros.
+    fn fixed_bytes<const N: usize>(&mut self, field: &str) -> Result<[u8; N], Risc0Error> {
+        let (value, padding) = self.next().split_at(N);
+        if padding.iter().any(|&b| b != 0) {
+            return Err(out_of_range(field));
+        }
+        value.try_into().map_err(|_| out_of_range(field))
+    }
+}
+
+fn out_of_range(field: &str) -> Risc0Error {
+    Risc0Error::JournalError(format!("ABI journal field {field} is out of range"))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{FixedBytes, B256};
+    use alloy::sol;
+    use alloy::sol_types::SolValue;
+    use guest_core::{ASSERTION_JOURNAL_V8, POLICY};
+
+    use super::*;
+
+    sol! {
+        struct SolAssertionJournal {
+            uint8 version;
+            bytes6 policy;
+            bytes32 rpIdHash;
+            bytes32 publicKeyX;
+            bytes32 publicKeyY;
+            bytes32 signatureR;
+            bytes32 signatureS;
+            uint32 counter;
+            bytes32 clientDataHash;
+            bytes32 attestationImageId;
+            bytes32 attestationClaimDigest;
+            bytes32 challengeHash;
+            uint32 allowlistEntry;
+            bytes32 sessionNonce;
+            bytes32 inputDigest;
+            bytes32 policyHash;
+            bytes32 certChainImageId;
+        }
+
+        struct SolAttestationJournal {
+            uint8 version;
+            bytes6 policy;
+            bytes32 publicKeyX;
+            bytes32 publicKeyY;
+            bytes32 keyId;
+            bytes32 rpIdHash;
+            uint8 environment;
+            int64 timestamp;
+            bytes32 timeSigner;
+            uint32 allowlistEntry;
+            bytes32 certChainImageId;
+            bytes32 certChainClaimDigest;
+            bytes32 inputDigest;
+            bytes32 policyHash;
+        }
+    }
+
+    fn assertion() -> AssertionJournal {
+        AssertionJournal {
+            policy: POLICY,
+            rp_id_hash: [1; 32],
+            public_key_x: [2; 32],
+            public_key_y: [3; 32],
+            signature_r: [4; 32],
+            signature_s: [5; 32],
+            counter: u32::MAX,
+            client_data_hash: [6; 32],
+            attestation_image_id: [7; 32],
+            attestation_claim_digest: [8; 32],
+            challenge_hash: [9; 32],
+            allowlist_entry: 2,
+            session_nonce: [10; 32],
+            input_digest: [11; 32],
+            policy_hash: Some([12; 32]),
+            cert_chain_image_id: Some([13; 32]),
+        }
+    }
+
+    fn attestation() -> AttestationJournal {
+        let mut public_key = [0x04; 65];
+        public_key[1..33].fill(1);
+        public_key[33..].fill(2);
+        AttestationJournal {
+            policy: POLICY,
+            public_key,
+            key_id: [3; 32],
+            rp_id_hash: [4; 32],
+            environment: AttestationJournal::DEVELOPMENT,
+            timestamp: -1_751_800_000,
+            time_signer: [5; 32],
+            allowlist_entry: guest_core::NO_ALLOWLIST,
+            cert_chain_image_id: [6; 32],
+            cert_chain_claim_digest: [7; 32],
+            input_digest: [8; 32],
+            policy_hash: Some([9; 32]),
+        }
+    }
+
+    #[test]
+    fn alloy_decodes_the_assertion_journal() {
+        let journal = assertion();
+        let bytes = encode_assertion_journal(&journal);
+        let decoded = SolAssertionJournal::abi_decode(&bytes).unwrap();
+        assert_eq!(decoded.version, ASSERTION_JOURNAL_V9);
+        assert_eq!(decoded.policy, FixedBytes::from(POLICY.encode()));
+        assert_eq!(decoded.rpIdHash, B256::from(journal.rp_id_hash));
+        assert_eq!(decoded.signatureS, B256::from(journal.signature_s));
+        assert_eq!(decoded.counter, u32::MAX);
+        assert_eq!(decoded.allowlistEntry, 2);
+        assert_eq!(decoded.sessionNonce, B256::from(journal.session_nonce));
+        assert_eq!(decoded.inputDigest, B256::from(journal.input_digest));
+        assert_eq!(decoded.policyHash, B256::from(journal.policy_hash.unwrap()));
+        assert_eq!(decoded.certChainImageId, B256::from(journal.cert_chain_image_id.unwrap()));
+        assert_eq!(decoded.abi_encode(), bytes);
+        assert_eq!(decode_assertion_journal(&bytes), Ok(journal));
+    }
+
+    #[test]
+    fn alloy_decodes_the_attestation_journal() {
+        let journal = attestation();
+        let bytes = encode_attestation_journal(&journal);
+        let decoded = SolAttestationJournal::abi_decode(&bytes).unwrap();
+        assert_eq!(decoded.version, ATTESTATION_JOURNAL_V7);
+        assert_eq!(decoded.publicKeyX, B256::repeat_byte(1));
+        assert_eq!(decoded.publicKeyY, B256::repeat_byte(2));
+        assert_eq!(decoded.environment, AttestationJournal::DEVELOPMENT);
+        assert_eq!(decoded.timestamp, -1_751_800_000);
+        assert_eq!(decoded.allowlistEntry, guest_core::NO_ALLOWLIST);
+        assert_eq!(decoded.certChainClaimDigest, B256::from(journal.cert_chain_claim_digest));
+        assert_eq!(decoded.inputDigest, B256::from(journal.input_digest));
+        assert_eq!(decoded.policyHash, B256::from(journal.policy_hash.unwrap()));
+        assert_eq!(decoded.abi_encode(), bytes);
+        assert_eq!(decode_attestation_journal(&bytes), Ok(journal));
+    }
+
+    #[test]
+    fn previous_versions_keep_their_version_and_zeros_for_the_fields_they_lack() {
+        let journal = AssertionJournal { cert_chain_image_id: None, ..assertion() };
+        let mut bytes = encode_assertion_journal(&journal);
+        let decoded = SolAssertionJournal::abi_decode(&bytes).unwrap();
+        assert_eq!(decoded.version, ASSERTION_JOURNAL_V8);
+        assert_eq!(decoded.policyHash, B256::from(journal.policy_hash.unwrap()));
+        assert_eq!(decoded.certChainImageId, B256::ZERO);
+        assert_eq!(decode_assertion_journal(&bytes), Ok(journal));
+        // A version 8 journal commits no certificate chain image, so cannot carry one.
+        bytes[17 * 32 - 1] = 1;
+        assert_eq!(
+            decode_assertion_journal(&bytes),
+            Err(Risc0Error::JournalError("ABI journal field certChainImageId is out of range".into()))
+        );
+
+        let journal = AssertionJournal { policy_hash: None, cert_chain_image_id: None, ..assertion() };
+        let bytes = encode_assertion_journal(&journal);
+        let decoded = SolAssertionJournal::abi_decode(&bytes).unwrap();
+        assert_eq!(decoded.version, ASSERTION_JOURNAL_V7);
+        assert_eq!(decoded.policyHash, B256::ZERO);
+        assert_eq!(decode_assertion_journal(&bytes), Ok(journal));
+
+        let journal = AttestationJournal { policy_hash: None, ..attestation() };
+        let mut bytes = encode_attestation_journal(&journal);
+        assert_eq!(decode_attestation_journal(&bytes), Ok(journal));
+        // A version 6 journal commits no policy hash, so cannot carry one.
+        bytes[14 * 32 - 1] = 1;
+        assert_eq!(
+            decode_attestation_journal(&bytes),
+            Err(Risc0Error::JournalError("ABI journal field policyHash is out of range".into()))
+        );
+    }
+
+    #[test]
+    fn rejects_what_abi_decode_rejects() {
+        let bytes = encode_assertion_journal(&assertion());
+        assert!(matches!(decode_assertion_journal(&bytes[..bytes.len() - 1]), Err(Risc0Error::JournalError(_))));
+        assert!(decode_attestation_journal(&bytes).is_err());
+
+        // A counter above `uint32`.
+        let mut dirty = bytes.clone();
+        dirty[7 * 32 + 27] = 1;
+        assert_eq!(
+            decode_assertion_journal(&dirty),
+            Err(Risc0Error::JournalError("ABI journal field counter is out of range".into()))
+        );
+        // A non-zero byte after the six of `policy`.
+        let mut dirty = bytes.clone();
+        dirty[32 + 6] = 1;
+        assert!(decode_assertion_journal(&dirty).is_err());
+        // A version no codec reads.
+        let mut dirty = bytes;
+        dirty[31] = 10;
+        assert_eq!(
+            decode_assertion_journal(&dirty),
+            Err(Risc0Error::UnsupportedJournalVersion { found: 10, supported: vec![7, 8, 9] })
+        );
+
+        // A positive timestamp with the sign extension of a negative one.
+        let mut dirty = encode_attestation_journal(&AttestationJournal { timestamp: 1, ..attestation() });
+        dirty[7 * 32] = 0xff;
+        assert!(decode_attestation_journal(&dirty).is_err());
+    }
+}
@@ -0,0 +1,90 @@
+use risc0_zkvm::{default_prover, ExecutorEnv, ProverOpts, Receipt, VerifierContext};
+
+use crate::Risc0Error;
+
+/// Something that can turn an executor environment and a guest ELF into a receipt.
+///
+/// The real implementation delegates to `default_prover()`; tests swap in a backend that
+/// fabricates receipts so the surrounding logic can be exercised without running the zkVM.
+pub trait ProofBackend: Send + Sync {
+    fn prove(&self, env: ExecutorEnv<'_>, elf: &[u8], opts: &ProverOpts) -> Result<Receipt, Risc0Error>;
+}
+
+/// Backend that runs the prover selected by the risc0 environment (local, Bonsai or dev mode).
+#[derive(Default)]
+pub struct DefaultBackend;
+
+impl ProofBackend for DefaultBackend {
+    fn prove(&self, env: ExecutorEnv<'_>, elf: &[u8], opts: &ProverOpts) -> Result<Receipt, Risc0Error> {
+        // `default_prover()` hands out an `Rc`, so it is created per call rather than stored.
+        let info = default_prover()
+            .prove_with_ctx(env, &VerifierContext::default(), elf, opts)
+            .map_err(|e| Risc0Error::from_prover(&e))?;
+        record!(cycles = info.stats.total_cycles, segments = info.stats.segments);
+        Ok(info.receipt)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod mock {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    use risc0_zkvm::{compute_image_id, FakeReceipt, InnerReceipt, ReceiptClaim};
+
+    use super::*;
+
+    /// Backend returning fake (dev-mode) receipts over a scripted journal.
+    ///
+    /// Each call pops the next scripted result; once the script is exhausted the last entry
+    /// is repeated. The number of calls is recorded so tests can assert on it.
+    pub(crate) struct MockBackend {
+        script: Mutex<Vec<Result<Vec<u8>, Risc0Error>>>,
+        calls: AtomicUsize,
+    }
+
+    impl MockBackend {
+        /// Always succeeds, committing `journal`.
+        pub(crate) fn with_journal(journal: Vec<u8>) -> Self {
+            Self::scripted(vec![Ok(journal)])
+        }
+
+        /// Always fails with `error`.
+        pub(crate) fn failing(error: Risc0Error) -> Self {
+            Self::scripted(vec![Err(error)])
+        }
+
+        pub(crate) fn scripted(mut script: Vec<Result<Vec<u8>, Risc0Error>>) -> Self {
+            assert!(!script.is_empty(), "mock backend needs at least one scripted result");
+            script.reverse();
+            Self {
+                script: Mutex::new(script),
+                calls: AtomicUsize::new(0),
+            }
+        }
+
+        pub(crate) fn calls(&self) -> usize {
+            self.calls.load(Ordering::SeqCst)
+        }
+    }
+
+    impl ProofBackend for MockBackend {
+        fn prove(&self, _env: ExecutorEnv<'_>, elf: &[u8], _opts: &ProverOpts) -> Result<Receipt, Risc0Error> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+
+            let next = {
+                let mut script = self.script.lock().unwrap();
+                if script.len() > 1 {
+                    script.pop().unwrap()
+                } else {
+                    script[0].clone()
+                }
+            };
+            let journal = next?;
+
+            let image_id = compute_image_id(elf).map_err(|e| Risc0Error::ProveError(e.to_string()))?;
+            let claim = ReceiptClaim::ok(image_id, journal.clone());
+            Ok(Receipt::new(InnerReceipt::Fake(FakeReceipt::new(claim)), journal))
+        }
+    }
+}
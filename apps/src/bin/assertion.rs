@@ -1,27 +1,140 @@
-use mopro_bindings::AssertionProofOutput;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Instant;
 
-fn main() {
-    println!("Generating the execution proof for the application...");
+use appattest_rs::assertion::Assertion;
+use base64::{engine::general_purpose, Engine};
+use clap::Parser;
+use mopro_bindings::cli::{
+    assertion_request, attested_public_key, ensure_dir, file_or_inline, public_key_pem, session_nonce, write_file,
+    write_output, CliResult,
+};
+use mopro_bindings::{handle_version_full_flag, Prover};
+use risc0_zkvm::Receipt;
+use sha2::{Digest, Sha256};
+
+/// Verifies an App Attest assertion and proves the assertion guest.
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    /// Base64 CBOR assertion.
+    #[arg(long, required_unless_present = "assertion_file", conflicts_with = "assertion_file")]
+    assertion: Option<String>,
+    /// File containing the base64 CBOR assertion.
+    #[arg(long)]
+    assertion_file: Option<PathBuf>,
+    /// Base64 client data the assertion signs, or a file containing it.
+    #[arg(long)]
+    client_data: String,
+    /// `<team id>.<bundle id>` of the asserting app.
+    #[arg(long)]
+    app_id: String,
+    /// Receipt of the attestation guest for the asserting key, as written by `attestation`.
+    #[arg(long)]
+    attestation_receipt: PathBuf,
+    /// Counter value of the last accepted assertion for this key; omit it for the key's first
+    /// assertion.
+    #[arg(long)]
+    previous_counter: Option<u32>,
+    /// Hex 32-byte nonce the relying party issued for this session; all zeros by default.
+    #[arg(long, value_parser = session_nonce)]
+    session_nonce: Option<[u8; 32]>,
+    /// Directory the receipt is written to.
+    #[arg(long, default_value = "output")]
+    out_dir: PathBuf,
+    /// Also write a JSON manifest describing the inputs and the proof.
+    #[arg(long)]
+    manifest: Option<PathBuf>,
+    /// Overwrite existing output files.
+    #[arg(long)]
+    force: bool,
+}
+
+fn main() -> ExitCode {
+    handle_version_full_flag();
+
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> CliResult<()> {
+    let assertion_b64 = match (&cli.assertion, &cli.assertion_file) {
+        (Some(inline), _) => inline.trim().to_string(),
+        (None, Some(path)) => file_or_inline(path.to_str().ok_or("assertion file path is not UTF-8")?)?,
+        (None, None) => unreachable!("clap requires one of --assertion/--assertion-file"),
+    };
+    let client_data_b64 = file_or_inline(&cli.client_data)?;
+    let attestation_receipt = std::fs::read(&cli.attestation_receipt)
+        .map_err(|e| format!("failed to read {}: {e}", cli.attestation_receipt.display()))?;
+    let public_key_pem = public_key_pem(&hex::encode(attested_public_key(&attestation_receipt)?))?;
+    let request = assertion_request(
+        &assertion_b64,
+        &client_data_b64,
+        &cli.app_id,
+        attestation_receipt.clone(),
+        cli.previous_counter,
+        cli.session_nonce.unwrap_or([0; 32]),
+    )?;
 
-    let AssertionProofOutput { proof, .. } = mopro_bindings::prove_assertion()
-        .expect("Failed to run the application and generate the execution proof");
+    // Refuse early, before spending time on a proof that could not be saved.
+    let receipt_path = cli.out_dir.join("receipt.bin");
+    for path in std::iter::once(&receipt_path).chain(cli.manifest.as_ref()) {
+        if path.exists() && !cli.force {
+            return Err(format!("{} already exists (pass --force to overwrite)", path.display()));
+        }
+    }
 
+    println!("Verifying the assertion...");
+    let assertion = Assertion::from_base64(&assertion_b64).map_err(|e| format!("invalid assertion: {e}"))?;
+    assertion
+        .verify(&client_data_b64, &cli.app_id, &public_key_pem, cli.previous_counter, None)
+        .map_err(|e| format!("assertion verification failed: {e}"))?;
+
+    println!("Generating the execution proof for the application...");
+    let started = Instant::now();
+    let output = Prover::new()
+        .prove_assertion(request)
+        .map_err(|e| format!("failed to prove assertion: {e}"))?;
+    let elapsed = started.elapsed();
+    let proof = output.proof;
     println!("Execution proof generated successfully!");
-    // The size of the receipt.
     println!("Receipt size: {} bytes", proof.receipt.len());
 
-    // Save the receipt to files in the output directory.
-
-    println!("Saving journal and receipt to files...");
-    // Check if the output directory exists, create it if not.
-    let output_dir = "output";
-    std::fs::create_dir_all(&output_dir).expect("Failed to create output directory");
-    // Save the journal to a file.
-    let receipt_path = std::path::Path::new(&output_dir).join("receipt.bin");
-    std::fs::write(&receipt_path, proof.receipt)
-        .expect("Failed to write receipt to file");
-
-    println!("Journal and receipt saved to: {:?}", output_dir);
-    // Print the paths to the console.
-    println!("Receipt path: {:?}", receipt_path);
-}
\ No newline at end of file
+    ensure_dir(&cli.out_dir)?;
+    let receipt_path = write_output(&cli.out_dir, "receipt.bin", &proof.receipt, cli.force)?;
+    println!("Receipt path: {}", receipt_path.display());
+
+    if let Some(manifest_path) = &cli.manifest {
+        let receipt: Receipt = bincode::deserialize(&proof.receipt).map_err(|e| format!("invalid receipt: {e}"))?;
+        let manifest = serde_json::json!({
+            "inputs": {
+                "assertion_sha256": sha256_hex_of_b64(&assertion_b64)?,
+                "client_data_sha256": sha256_hex_of_b64(&client_data_b64)?,
+                "public_key_sha256": hex::encode(Sha256::digest(public_key_pem.as_bytes())),
+                "attestation_receipt_sha256": hex::encode(Sha256::digest(&attestation_receipt)),
+                "app_id": cli.app_id,
+                "previous_counter": cli.previous_counter,
+                "session_nonce": hex::encode(cli.session_nonce.unwrap_or([0; 32])),
+            },
+            "journal_hex": hex::encode(&receipt.journal.bytes),
+            "receipt_path": receipt_path,
+            "receipt_size": proof.receipt.len(),
+            "image_id": proof.metadata.image_id_hex,
+            "proving_time_ms": elapsed.as_millis() as u64,
+        });
+        let json = serde_json::to_vec_pretty(&manifest).map_err(|e| e.to_string())?;
+        write_file(manifest_path, &json, cli.force)?;
+        println!("Manifest path: {}", manifest_path.display());
+    }
+    Ok(())
+}
+
+fn sha256_hex_of_b64(value: &str) -> CliResult<String> {
+    let bytes = general_purpose::STANDARD.decode(value).map_err(|e| format!("invalid base64 input: {e}"))?;
+    Ok(hex::encode(Sha256::digest(bytes)))
+}
@@ -1,27 +1,194 @@
-use mopro_bindings::Risc0ProofOutput;
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
 
-fn main() {
-    println!("Generating the execution proof for the application...");
+use appattest_rs::attestation::Attestation;
+use clap::{Parser, Subcommand, ValueEnum};
+use mopro_bindings::cli::{attestation_request, ensure_dir, file_or_inline, now_unix, write_output, CliResult};
+use mopro_bindings::{
+    claim_digest_hex, decode_attestation_journal, handle_version_full_flag, AttestationJournal, ProofKind, Prover,
+    ProverConfig, TimeTokenRequest,
+};
+use risc0_zkvm::Receipt;
 
-    let Risc0ProofOutput { receipt } = mopro_bindings::prove_attestation()
-        .expect("Failed to run the application and generate the execution proof");
+/// Verifies an App Attest attestation object and proves the attestation guest.
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
 
-    println!("Execution proof generated successfully!");
-    // The size of the receipt.
-    println!("Receipt size: {} bytes", receipt.len());
+#[derive(Subcommand)]
+enum Command {
+    /// Check the attestation and write receipts for the certificate chain and attestation guests.
+    Prove(ProveArgs),
+}
 
-    // Save the receipt to files in the output directory.
+#[derive(clap::Args)]
+struct ProveArgs {
+    /// Base64 CBOR attestation object, or a file containing it.
+    #[arg(long)]
+    attestation: String,
+    /// Base64 challenge the attestation was generated for.
+    #[arg(long)]
+    challenge: String,
+    /// `<team id>.<bundle id>` of the attesting app.
+    #[arg(long)]
+    app_id: String,
+    /// Base64 key identifier returned by `generateKey`.
+    #[arg(long)]
+    key_id: String,
+    /// Directory the receipts are written to.
+    #[arg(long, default_value = "output")]
+    out_dir: PathBuf,
+    /// Certificate chain receipt an earlier run wrote for the same attestation object, to
+    /// reuse instead of proving the chain again.
+    #[arg(long)]
+    cert_chain_receipt: Option<PathBuf>,
+    #[arg(long, value_enum, default_value_t = KindArg::Composite)]
+    proof_kind: KindArg,
+    /// Unix time used for certificate validity checks and committed to the journal; defaults to now.
+    #[arg(long)]
+    timestamp: Option<i64>,
+    /// Hex SEC1 public key of a time source that signed `--timestamp`.
+    #[arg(long, requires_all = ["timestamp", "time_signature"])]
+    time_signer: Option<String>,
+    /// Hex DER signature by `--time-signer` over the little-endian timestamp bytes.
+    #[arg(long, requires = "time_signer")]
+    time_signature: Option<String>,
+    /// Accept attestations from the App Attest development environment.
+    #[arg(long)]
+    development: bool,
+    /// Print a machine-readable summary instead of progress messages.
+    #[arg(long)]
+    json: bool,
+}
 
-    println!("Saving journal and receipt to files...");
-    // Check if the output directory exists, create it if not.
-    let output_dir = "output";
-    std::fs::create_dir_all(&output_dir).expect("Failed to create output directory");
-    // Save the journal to a file.
-    let receipt_path = std::path::Path::new(&output_dir).join("receipt.bin");
-    std::fs::write(&receipt_path, receipt)
-        .expect("Failed to write receipt to file");
+#[derive(Clone, Copy, ValueEnum)]
+enum KindArg {
+    Composite,
+    Succinct,
+}
 
-    println!("Journal and receipt saved to: {:?}", output_dir);
-    // Print the paths to the console.
-    println!("Receipt path: {:?}", receipt_path);
-}
\ No newline at end of file
+impl From<KindArg> for ProofKind {
+    fn from(kind: KindArg) -> Self {
+        match kind {
+            KindArg::Composite => ProofKind::Composite,
+            KindArg::Succinct => ProofKind::Succinct,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    handle_version_full_flag();
+
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Command::Prove(args) => prove(args),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn prove(args: ProveArgs) -> CliResult<()> {
+    let log = |msg: &str| {
+        if !args.json {
+            println!("{msg}");
+        }
+    };
+
+    let attestation_b64 = file_or_inline(&args.attestation)?;
+    let timestamp = args.timestamp.unwrap_or_else(now_unix);
+
+    log("Verifying the attestation object...");
+    let attestation = Attestation::from_base64(&attestation_b64).map_err(|e| format!("invalid attestation: {e}"))?;
+    attestation
+        .verify(&args.challenge, &args.app_id, &args.key_id, timestamp, Some(args.development))
+        .map_err(|e| format!("attestation verification failed: {e}"))?;
+
+    let mut request = attestation_request(&attestation_b64, &args.challenge, &args.app_id, &args.key_id, timestamp)?;
+    if let (Some(signer), Some(signature)) = (&args.time_signer, &args.time_signature) {
+        request.time_token = Some(TimeTokenRequest {
+            signer: hex::decode(signer).map_err(|e| format!("invalid --time-signer: {e}"))?,
+            signature: hex::decode(signature).map_err(|e| format!("invalid --time-signature: {e}"))?,
+        });
+    }
+
+    let prover = Prover::with_config(ProverConfig {
+        proof_kind: args.proof_kind.into(),
+        ..Default::default()
+    });
+    let cert_chain_receipt = match &args.cert_chain_receipt {
+        Some(path) => {
+            log("Reusing the certificate chain receipt...");
+            fs::read(path).map_err(|e| format!("failed to read {}: {e}", path.display()))?
+        }
+        None => {
+            log("Proving the certificate chain...");
+            prover
+                .prove_cert_chain(request.clone())
+                .map_err(|e| format!("failed to prove the certificate chain: {e}"))?
+                .receipt
+        }
+    };
+
+    log("Generating the execution proof for the application...");
+    let output = prover
+        .prove_attestation_with_cert_chain(request, cert_chain_receipt.clone())
+        .map_err(|e| format!("failed to prove attestation: {e}"))?;
+
+    let receipt: Receipt = bincode::deserialize(&output.receipt).map_err(|e| format!("invalid receipt: {e}"))?;
+    let claim_digest = claim_digest_hex(&receipt).map_err(|e| e.to_string())?;
+    let journal = decode_attestation_journal(&receipt.journal.bytes).map_err(|e| e.to_string())?;
+
+    ensure_dir(&args.out_dir)?;
+    let receipt_path = write_output(&args.out_dir, "receipt.bin", &output.receipt, true)?;
+    let cert_chain_path = write_output(&args.out_dir, "cert_chain_receipt.bin", &cert_chain_receipt, true)?;
+    let environment = match journal.environment {
+        AttestationJournal::PRODUCTION => "production",
+        AttestationJournal::DEVELOPMENT => "development",
+        _ => "unknown",
+    };
+
+    if args.json {
+        let summary = serde_json::json!({
+            "receipt_path": receipt_path,
+            "receipt_size": output.receipt.len(),
+            "cert_chain_receipt_path": cert_chain_path,
+            "journal_size": receipt.journal.bytes.len(),
+            "public_key": hex::encode(journal.public_key),
+            "journal": {
+                "key_id": hex::encode(journal.key_id),
+                "rp_id_hash": hex::encode(journal.rp_id_hash),
+                "environment": environment,
+                "timestamp": journal.timestamp,
+                "time_signer": hex::encode(journal.time_signer),
+                "allowlist_entry": journal.allowlist_entry,
+                "cert_chain_image_id": hex::encode(journal.cert_chain_image_id),
+                "cert_chain_claim_digest": hex::encode(journal.cert_chain_claim_digest),
+            },
+            "image_id": output.metadata.image_id_hex,
+            "claim_digest": claim_digest,
+            "proving_time_ms": output.metadata.proving_time_ms,
+        });
+        println!("{summary}");
+    } else {
+        println!("Receipt size: {} bytes", output.receipt.len());
+        println!("Image ID:     {}", output.metadata.image_id_hex);
+        println!("Claim digest: {claim_digest}");
+        println!("Public key:   {}", hex::encode(journal.public_key));
+        println!("Key ID:       {}", hex::encode(journal.key_id));
+        println!("RP ID hash:   {}", hex::encode(journal.rp_id_hash));
+        println!("Environment:  {environment}");
+        println!("Checked at:   {}", journal.timestamp);
+        println!("Receipt path: {}", receipt_path.display());
+        println!("Chain receipt: {}", cert_chain_path.display());
+    }
+    Ok(())
+}
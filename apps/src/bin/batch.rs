@@ -0,0 +1,250 @@
+use std::fmt::Write as _;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use appattest_rs::assertion::Assertion;
+use clap::{Parser, ValueEnum};
+use mopro_bindings::cli::{attested_public_key, public_key_pem, write_file, AssertionFile, CliResult};
+use mopro_bindings::{handle_version_full_flag, GuestInput, Prover};
+
+/// Proves a directory of archived assertions, writing `<name>.receipt.bin` next to each
+/// `<name>.json` input.
+///
+/// Inputs are JSON objects with base64 `assertion` and `client_data`, the `app_id`, the
+/// path of the key's `attestation_receipt` relative to the input and an optional
+/// `previous_counter`. Inputs that already have a receipt are skipped, so an interrupted run
+/// can simply be started again. A failing input is reported and the batch carries on; the
+/// exit code is non-zero if any input failed.
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    /// Directory of `*.json` assertion inputs.
+    dir: PathBuf,
+    /// Number of inputs proved in parallel.
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    jobs: u32,
+    /// Prove inputs again even if they already have a receipt.
+    #[arg(long)]
+    force: bool,
+    #[arg(long, value_enum, default_value_t = Format::Json)]
+    format: Format,
+    /// Write the report to this file instead of stdout.
+    #[arg(long)]
+    report: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Csv,
+    Json,
+}
+
+enum Outcome {
+    Proved { cycles: u64, proving_time_ms: u64 },
+    Skipped,
+    Failed(String),
+}
+
+struct Entry {
+    input: PathBuf,
+    receipt: PathBuf,
+    outcome: Outcome,
+}
+
+fn main() -> ExitCode {
+    handle_version_full_flag();
+
+    match run(Cli::parse()) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Returns whether every input was proved or skipped.
+fn run(cli: Cli) -> CliResult<bool> {
+    let mut inputs: Vec<PathBuf> = std::fs::read_dir(&cli.dir)
+        .map_err(|e| format!("failed to read {}: {e}", cli.dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    inputs.sort();
+
+    let started = Instant::now();
+    let prover = Prover::new();
+    let next = AtomicUsize::new(0);
+    let entries = Mutex::new(Vec::with_capacity(inputs.len()));
+    std::thread::scope(|scope| {
+        for _ in 0..cli.jobs.min(inputs.len().max(1) as u32) {
+            scope.spawn(|| {
+                while let Some(input) = inputs.get(next.fetch_add(1, Ordering::SeqCst)) {
+                    let receipt = receipt_path(input);
+                    let outcome = if receipt.exists() && !cli.force {
+                        Outcome::Skipped
+                    } else {
+                        catch_unwind(AssertUnwindSafe(|| process(&prover, input, &receipt)))
+                            .unwrap_or_else(|_| Err("panicked while proving".to_string()))
+                            .unwrap_or_else(Outcome::Failed)
+                    };
+                    log(input, &outcome);
+                    entries.lock().unwrap().push(Entry {
+                        input: input.clone(),
+                        receipt,
+                        outcome,
+                    });
+                }
+            });
+        }
+    });
+    let mut entries = entries.into_inner().unwrap();
+    entries.sort_by(|a, b| a.input.cmp(&b.input));
+
+    let total_time_ms = started.elapsed().as_millis() as u64;
+    let report = match cli.format {
+        Format::Csv => csv(&entries),
+        Format::Json => json(&entries, total_time_ms)?,
+    };
+    match &cli.report {
+        Some(path) => {
+            write_file(path, report.as_bytes(), true)?;
+        }
+        None => print!("{report}"),
+    }
+    Ok(!entries.iter().any(|e| matches!(e.outcome, Outcome::Failed(_))))
+}
+
+fn receipt_path(input: &Path) -> PathBuf {
+    input.with_extension("receipt.bin")
+}
+
+/// Verifies one input natively, counts its cycles and proves it.
+fn process(prover: &Prover, input: &Path, receipt_path: &Path) -> CliResult<Outcome> {
+    let input = AssertionFile::read(input)?;
+    let request = input.request()?;
+
+    let public_key_pem = public_key_pem(&hex::encode(attested_public_key(&request.attestation_receipt)?))?;
+    Assertion::from_base64(&input.assertion)
+        .map_err(|e| format!("invalid assertion: {e}"))?
+        .verify(
+            &input.client_data,
+            &input.app_id,
+            &public_key_pem,
+            input.previous_counter,
+            None,
+        )
+        .map_err(|e| format!("assertion verification failed: {e}"))?;
+
+    let guest_input = GuestInput::try_from(&request).map_err(|e| e.to_string())?;
+    let session = guest_input
+        .execute()
+        .map_err(|e| format!("failed to execute guest: {e}"))?;
+    let cycles = session.segments.iter().map(|s| 1u64 << s.po2).sum();
+
+    let output = prover
+        .prove_assertion(request)
+        .map_err(|e| format!("failed to prove: {e}"))?;
+    write_file(receipt_path, &output.proof.receipt, true)?;
+    Ok(Outcome::Proved {
+        cycles,
+        proving_time_ms: output.proof.metadata.proving_time_ms,
+    })
+}
+
+fn log(input: &Path, outcome: &Outcome) {
+    let name = input.file_name().unwrap_or_default().to_string_lossy();
+    match outcome {
+        Outcome::Proved { proving_time_ms, .. } => eprintln!("{name}: proved in {proving_time_ms} ms"),
+        Outcome::Skipped => eprintln!("{name}: skipped, receipt exists"),
+        Outcome::Failed(reason) => eprintln!("{name}: failed: {reason}"),
+    }
+}
+
+impl Outcome {
+    fn status(&self) -> &'static str {
+        match self {
+            Outcome::Proved { .. } => "proved",
+            Outcome::Skipped => "skipped",
+            Outcome::Failed(_) => "failed",
+        }
+    }
+}
+
+fn csv(entries: &[Entry]) -> String {
+    let mut out = "input,status,cycles,proving_time_ms,receipt,reason\n".to_string();
+    for entry in entries {
+        let (cycles, time, reason) = match &entry.outcome {
+            Outcome::Proved {
+                cycles,
+                proving_time_ms,
+            } => (cycles.to_string(), proving_time_ms.to_string(), String::new()),
+            Outcome::Skipped => (String::new(), String::new(), String::new()),
+            Outcome::Failed(reason) => (
+                String::new(),
+                String::new(),
+                format!("\"{}\"", reason.replace('"', "\"\"")),
+            ),
+        };
+        let receipt = match entry.outcome {
+            Outcome::Failed(_) => String::new(),
+            _ => entry.receipt.display().to_string(),
+        };
+        let _ = writeln!(
+            out,
+            "{},{},{cycles},{time},{receipt},{reason}",
+            entry.input.display(),
+            entry.outcome.status()
+        );
+    }
+    out
+}
+
+fn json(entries: &[Entry], total_time_ms: u64) -> CliResult<String> {
+    let count = |status: &str| entries.iter().filter(|e| e.outcome.status() == status).count();
+    let total_cycles: u64 = entries
+        .iter()
+        .map(|e| match e.outcome {
+            Outcome::Proved { cycles, .. } => cycles,
+            _ => 0,
+        })
+        .sum();
+    let items: Vec<serde_json::Value> = entries
+        .iter()
+        .map(|entry| {
+            let mut item = serde_json::json!({
+                "input": entry.input,
+                "status": entry.outcome.status(),
+            });
+            match &entry.outcome {
+                Outcome::Proved {
+                    cycles,
+                    proving_time_ms,
+                } => {
+                    item["receipt"] = serde_json::json!(entry.receipt);
+                    item["cycles"] = serde_json::json!(cycles);
+                    item["proving_time_ms"] = serde_json::json!(proving_time_ms);
+                }
+                Outcome::Skipped => item["receipt"] = serde_json::json!(entry.receipt),
+                Outcome::Failed(reason) => item["reason"] = serde_json::json!(reason),
+            }
+            item
+        })
+        .collect();
+    let report = serde_json::json!({
+        "proved": count("proved"),
+        "skipped": count("skipped"),
+        "failed": count("failed"),
+        "total_cycles": total_cycles,
+        "total_time_ms": total_time_ms,
+        "inputs": items,
+    });
+    serde_json::to_string_pretty(&report)
+        .map(|s| s + "\n")
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,376 @@
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+use clap::{Parser, ValueEnum};
+use mopro_bindings::cli::{recorded_attestation, write_file, AssertionFile, CliResult};
+use mopro_bindings::{execute_guest, handle_version_full_flag, DefaultBackend, GuestInput, ProofBackend};
+use risc0_zkvm::{ExecutorEnv, ProverOpts, ReceiptKind};
+
+const FORMAT_HELP: &str = "\
+OUTPUT FORMAT
+  Every row is one (guest, po2, hashfn, kind) configuration. Columns, in this order:
+
+    guest          cert-chain | attestation | assertion
+    po2            segment size limit (log2 cycles)
+    hashfn         sha-256 | poseidon2
+    kind           composite | succinct
+    segments       number of segments the executor produced
+    user_cycles    cycles spent running guest code
+    total_cycles   cycles after padding every segment to its power of two
+    iterations     measured proving runs (warmup runs excluded)
+    median_ms      median proving wall time
+    p95_ms         95th percentile proving wall time (nearest rank)
+    receipt_bytes  size of the bincode-serialized receipt
+
+  CSV has a header row with these names; JSON is an array of objects with the same keys.
+  Columns are only ever appended, so scripts comparing runs keep working.";
+
+/// Measures executor cycles and proving time for the guests across prover configurations.
+#[derive(Parser)]
+#[command(version, after_long_help = FORMAT_HELP)]
+struct Cli {
+    /// Guests to benchmark.
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "assertion")]
+    guest: Vec<GuestArg>,
+    /// Assertion the assertion guest verifies, in the `batch` input format.
+    #[arg(long, default_value = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/batch/first.json"))]
+    assertion_input: PathBuf,
+    /// Fixtures directory holding the attestation the certificate chain and attestation guests
+    /// verify. Benchmarking the attestation guest proves its certificate chain once first.
+    #[arg(long, default_value = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures"))]
+    fixtures: PathBuf,
+    /// Segment size limits to try.
+    #[arg(long, value_delimiter = ',', default_values_t = [16, 18, 20])]
+    po2: Vec<u32>,
+    /// Hash functions to try.
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "sha-256,poseidon2")]
+    hashfn: Vec<HashArg>,
+    /// Receipt kinds to try.
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "composite,succinct")]
+    kind: Vec<KindArg>,
+    /// Measured proving runs per configuration.
+    #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u32).range(1..))]
+    iterations: u32,
+    /// Unmeasured proving runs before the measured ones.
+    #[arg(long, default_value_t = 1)]
+    warmup: u32,
+    #[arg(long, value_enum, default_value_t = Format::Table)]
+    format: Format,
+    /// Write the table to this file instead of stdout; the summary still goes to stderr.
+    #[arg(long)]
+    out: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum GuestArg {
+    CertChain,
+    Attestation,
+    Assertion,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum HashArg {
+    #[value(name = "sha-256")]
+    Sha256,
+    Poseidon2,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum KindArg {
+    Composite,
+    Succinct,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Table,
+    Csv,
+    Json,
+}
+
+impl GuestArg {
+    fn input(self, cli: &Cli) -> CliResult<GuestInput> {
+        Ok(match self {
+            GuestArg::CertChain => GuestInput::CertChain((&recorded_attestation(&cli.fixtures)?).into()),
+            GuestArg::Attestation => {
+                let request = recorded_attestation(&cli.fixtures)?;
+                let chain = GuestInput::CertChain((&request).into());
+                eprintln!("proving the certificate chain the attestation guest relies on...");
+                let receipt = DefaultBackend
+                    .prove(chain.env().map_err(|e| e.to_string())?, chain.kind().elf(), &ProverOpts::fast())
+                    .map_err(|e| format!("failed to prove the certificate chain: {e}"))?;
+                GuestInput::attestation(&request, receipt)
+            }
+            GuestArg::Assertion => {
+                let request = AssertionFile::read(&cli.assertion_input)?.request()?;
+                GuestInput::try_from(&request).map_err(|e| e.to_string())?
+            }
+        })
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            GuestArg::CertChain => "cert-chain",
+            GuestArg::Attestation => "attestation",
+            GuestArg::Assertion => "assertion",
+        }
+    }
+}
+
+impl HashArg {
+    fn name(self) -> &'static str {
+        match self {
+            HashArg::Sha256 => "sha-256",
+            HashArg::Poseidon2 => "poseidon2",
+        }
+    }
+}
+
+impl KindArg {
+    fn name(self) -> &'static str {
+        match self {
+            KindArg::Composite => "composite",
+            KindArg::Succinct => "succinct",
+        }
+    }
+
+    fn receipt_kind(self) -> ReceiptKind {
+        match self {
+            KindArg::Composite => ReceiptKind::Composite,
+            KindArg::Succinct => ReceiptKind::Succinct,
+        }
+    }
+}
+
+/// Cycle counts reported by the executor; independent of hash function and receipt kind.
+#[derive(Clone, Copy)]
+struct Cycles {
+    segments: usize,
+    user: u64,
+    total: u64,
+}
+
+struct Row {
+    guest: &'static str,
+    po2: u32,
+    hashfn: &'static str,
+    kind: &'static str,
+    cycles: Cycles,
+    iterations: u32,
+    median_ms: u64,
+    p95_ms: u64,
+    receipt_bytes: usize,
+}
+
+fn main() -> ExitCode {
+    handle_version_full_flag();
+
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> CliResult<()> {
+    let mut rows = Vec::new();
+    for &guest in &cli.guest {
+        let input = guest.input(&cli)?;
+        for &po2 in &cli.po2 {
+            let cycles = execute(&input, po2)?;
+            for &hashfn in &cli.hashfn {
+                for &kind in &cli.kind {
+                    eprintln!("{} po2={po2} {} {}...", guest.name(), hashfn.name(), kind.name());
+                    let opts = ProverOpts::from_max_po2(po2 as usize)
+                        .with_hashfn(hashfn.name().to_string())
+                        .with_receipt_kind(kind.receipt_kind());
+                    let (times, receipt_bytes) = prove(&input, po2, &opts, cli.warmup, cli.iterations)?;
+                    rows.push(Row {
+                        guest: guest.name(),
+                        po2,
+                        hashfn: hashfn.name(),
+                        kind: kind.name(),
+                        cycles,
+                        iterations: cli.iterations,
+                        median_ms: percentile(&times, 50),
+                        p95_ms: percentile(&times, 95),
+                        receipt_bytes,
+                    });
+                }
+            }
+        }
+    }
+
+    let output = match cli.format {
+        Format::Table => table(&rows),
+        Format::Csv => csv(&rows),
+        Format::Json => json(&rows)?,
+    };
+    match &cli.out {
+        Some(path) => {
+            write_file(path, output.as_bytes(), true)?;
+        }
+        None => print!("{output}"),
+    }
+    eprint!("{}", summary(&rows));
+    Ok(())
+}
+
+fn env(input: &GuestInput, po2: u32) -> CliResult<ExecutorEnv<'static>> {
+    input
+        .env_builder()
+        .map_err(|e| e.to_string())?
+        .segment_limit_po2(po2)
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+fn execute(input: &GuestInput, po2: u32) -> CliResult<Cycles> {
+    let session = execute_guest(env(input, po2)?, input.kind()).map_err(|e| format!("failed to execute guest: {e}"))?;
+    Ok(Cycles {
+        segments: session.segments.len(),
+        user: session.segments.iter().map(|s| s.cycles as u64).sum(),
+        total: session.segments.iter().map(|s| 1u64 << s.po2).sum(),
+    })
+}
+
+/// Proves `warmup + iterations` times and returns the measured durations and receipt size.
+fn prove(
+    input: &GuestInput,
+    po2: u32,
+    opts: &ProverOpts,
+    warmup: u32,
+    iterations: u32,
+) -> CliResult<(Vec<Duration>, usize)> {
+    let mut times = Vec::with_capacity(iterations as usize);
+    let mut receipt_bytes = 0;
+    for run in 0..warmup + iterations {
+        let started = Instant::now();
+        let receipt = DefaultBackend
+            .prove(env(input, po2)?, input.kind().elf(), opts)
+            .map_err(|e| format!("failed to prove: {e}"))?;
+        if run >= warmup {
+            times.push(started.elapsed());
+        }
+        receipt_bytes = bincode::serialized_size(&receipt).map_err(|e| e.to_string())? as usize;
+    }
+    Ok((times, receipt_bytes))
+}
+
+/// Nearest-rank percentile of `times`, in milliseconds.
+fn percentile(times: &[Duration], pct: usize) -> u64 {
+    let mut sorted = times.to_vec();
+    sorted.sort();
+    let rank = (pct * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1].as_millis() as u64
+}
+
+const COLUMNS: [&str; 11] = [
+    "guest",
+    "po2",
+    "hashfn",
+    "kind",
+    "segments",
+    "user_cycles",
+    "total_cycles",
+    "iterations",
+    "median_ms",
+    "p95_ms",
+    "receipt_bytes",
+];
+
+impl Row {
+    fn fields(&self) -> [String; 11] {
+        [
+            self.guest.to_string(),
+            self.po2.to_string(),
+            self.hashfn.to_string(),
+            self.kind.to_string(),
+            self.cycles.segments.to_string(),
+            self.cycles.user.to_string(),
+            self.cycles.total.to_string(),
+            self.iterations.to_string(),
+            self.median_ms.to_string(),
+            self.p95_ms.to_string(),
+            self.receipt_bytes.to_string(),
+        ]
+    }
+}
+
+fn csv(rows: &[Row]) -> String {
+    let mut out = COLUMNS.join(",") + "\n";
+    for row in rows {
+        out += &(row.fields().join(",") + "\n");
+    }
+    out
+}
+
+fn table(rows: &[Row]) -> String {
+    let cells: Vec<[String; 11]> = rows.iter().map(Row::fields).collect();
+    let widths: Vec<usize> = (0..COLUMNS.len())
+        .map(|i| {
+            cells
+                .iter()
+                .map(|r| r[i].len())
+                .chain([COLUMNS[i].len()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let mut out = String::new();
+    let line = |out: &mut String, values: &[&str]| {
+        let padded: Vec<String> = values.iter().zip(&widths).map(|(v, w)| format!("{v:>w$}")).collect();
+        out.push_str(padded.join("  ").trim_end());
+        out.push('\n');
+    };
+    line(&mut out, &COLUMNS);
+    for row in &cells {
+        line(&mut out, &row.iter().map(String::as_str).collect::<Vec<_>>());
+    }
+    out
+}
+
+fn json(rows: &[Row]) -> CliResult<String> {
+    let values: Vec<serde_json::Value> = rows
+        .iter()
+        .map(|row| {
+            serde_json::json!({
+                "guest": row.guest,
+                "po2": row.po2,
+                "hashfn": row.hashfn,
+                "kind": row.kind,
+                "segments": row.cycles.segments,
+                "user_cycles": row.cycles.user,
+                "total_cycles": row.cycles.total,
+                "iterations": row.iterations,
+                "median_ms": row.median_ms,
+                "p95_ms": row.p95_ms,
+                "receipt_bytes": row.receipt_bytes,
+            })
+        })
+        .collect();
+    serde_json::to_string_pretty(&values)
+        .map(|s| s + "\n")
+        .map_err(|e| e.to_string())
+}
+
+/// Fastest configuration per guest, for a quick read of the results.
+fn summary(rows: &[Row]) -> String {
+    let mut out = String::new();
+    for guest in ["attestation", "assertion"] {
+        let Some(best) = rows.iter().filter(|r| r.guest == guest).min_by_key(|r| r.median_ms) else {
+            continue;
+        };
+        let _ = writeln!(
+            out,
+            "{guest}: {} user cycles; fastest is po2={} {} {} at {} ms median ({} bytes)",
+            best.cycles.user, best.po2, best.hashfn, best.kind, best.median_ms, best.receipt_bytes
+        );
+    }
+    out
+}
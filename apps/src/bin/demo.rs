@@ -0,0 +1,121 @@
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use appattest_rs::assertion::Assertion;
+use appattest_rs::attestation::Attestation;
+use clap::Parser;
+use mopro_bindings::cli::{assertion_request, file_or_inline, recorded_attestation, CliResult};
+use mopro_bindings::{handle_version_full_flag, verify_assertion_proof, Prover};
+use p256::pkcs8::{EncodePublicKey, LineEnding};
+
+/// Runs the whole pipeline (attest, prove, assert, prove, verify) over recorded fixtures.
+///
+/// Proving runs in RISC Zero dev mode unless `RISC0_DEV_MODE` is already set, so this needs
+/// neither a device nor a GPU.
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    /// Directory holding the recorded fixtures.
+    #[arg(long, default_value = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures"))]
+    fixtures: PathBuf,
+}
+
+fn main() -> ExitCode {
+    handle_version_full_flag();
+
+    if std::env::var_os("RISC0_DEV_MODE").is_none() {
+        std::env::set_var("RISC0_DEV_MODE", "1");
+    }
+    match run(Cli::parse()) {
+        Ok(()) => {
+            println!("Demo completed successfully.");
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> CliResult<()> {
+    let fixture = |name: &str| {
+        let path = cli.fixtures.join(name);
+        if !path.is_file() {
+            return Err(format!("missing fixture {}", path.display()));
+        }
+        file_or_inline(path.to_str().ok_or("fixture path is not UTF-8")?)
+    };
+    let app_id = fixture("app_id.txt")?;
+    let recorded_at: i64 = fixture("recorded_at.txt")?
+        .parse()
+        .map_err(|e| format!("invalid recorded_at.txt: {e}"))?;
+
+    let public_key = step(1, "verify attestation", || {
+        let attestation = Attestation::from_base64(&fixture("attestation.b64")?).map_err(|e| e.to_string())?;
+        let (public_key, _receipt) = attestation
+            .verify(
+                &fixture("challenge.b64")?,
+                &app_id,
+                &fixture("key_id.b64")?,
+                recorded_at,
+                Some(true),
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(public_key)
+    })?;
+    println!("      attested key: {}", hex::encode(&public_key));
+
+    let attestation = step(2, "prove attestation", || {
+        Prover::new()
+            .prove_attestation(recorded_attestation(&cli.fixtures)?)
+            .map_err(|e| e.to_string())
+    })?;
+    println!("      receipt: {} bytes", attestation.receipt.len());
+
+    let assertion_b64 = fixture("assertion.b64")?;
+    let client_data_b64 = fixture("client_data.b64")?;
+    step(3, "verify assertion", || {
+        let key = p256::PublicKey::from_sec1_bytes(&public_key).map_err(|e| e.to_string())?;
+        let pem = key.to_public_key_pem(LineEnding::LF).map_err(|e| e.to_string())?;
+        let assertion = Assertion::from_base64(&assertion_b64).map_err(|e| e.to_string())?;
+        assertion
+            .verify(&client_data_b64, &app_id, &pem, 0, None)
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    })?;
+
+    let output = step(4, "prove assertion", || {
+        let receipt = attestation.receipt.clone();
+        let request = assertion_request(&assertion_b64, &client_data_b64, &app_id, receipt, 0, [0; 32])?;
+        Prover::new().prove_assertion(request).map_err(|e| e.to_string())
+    })?;
+    println!(
+        "      receipt: {} bytes, image {}, {} ms",
+        output.proof.receipt.len(),
+        output.proof.metadata.image_id_hex,
+        output.proof.metadata.proving_time_ms
+    );
+
+    step(5, "verify receipt", || {
+        let journal = verify_assertion_proof(output.proof.receipt.clone(), None).map_err(|e| e.to_string())?;
+        if journal != output.signature_data {
+            return Err("verified journal differs from the prover's output".to_string());
+        }
+        Ok(())
+    })?;
+    Ok(())
+}
+
+/// Runs one numbered step, reporting which step failed before propagating the error.
+fn step<T, E: Display>(n: u32, name: &str, f: impl FnOnce() -> Result<T, E>) -> CliResult<T> {
+    println!("[{n}/5] {name}...");
+    match f() {
+        Ok(value) => {
+            println!("      ok");
+            Ok(value)
+        }
+        Err(e) => Err(format!("step {n} ({name}) failed: {e}")),
+    }
+}
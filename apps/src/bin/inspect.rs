@@ -0,0 +1,196 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::Parser;
+use mopro_bindings::cli::{policy_json, CliResult};
+use mopro_bindings::{
+    claim_digest_hex, decode_assertion_journal, decode_attestation_journal, decode_cert_chain_journal, deserialize_receipt,
+    handle_version_full_flag, AssertionJournal, AttestationJournal, GuestKind,
+};
+use risc0_zkvm::sha::{Digest, Digestible};
+use risc0_zkvm::{InnerReceipt, Receipt};
+use serde_json::{json, Value};
+
+/// Prints what is inside a serialized receipt. Nothing is verified; use `verify` for that.
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    /// Bincode-serialized receipt.
+    receipt: PathBuf,
+    /// Print the report as JSON.
+    #[arg(long)]
+    json: bool,
+}
+
+fn main() -> ExitCode {
+    handle_version_full_flag();
+
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> CliResult<()> {
+    let bytes = std::fs::read(&cli.receipt).map_err(|e| format!("failed to read {}: {e}", cli.receipt.display()))?;
+    let report = inspect(&deserialize_receipt(&bytes).map_err(|e| e.to_string())?)?;
+
+    if cli.json {
+        println!("{report}");
+        return Ok(());
+    }
+    for key in [
+        "kind",
+        "image_id",
+        "guest",
+        "exit_code",
+        "claim_digest",
+        "journal_len",
+        "layout",
+    ] {
+        println!("{:<13} {}", format!("{key}:"), plain(&report[key]));
+    }
+    match report["journal"].as_object() {
+        Some(fields) => {
+            for (name, value) in fields {
+                println!("  {:<13} {}", format!("{name}:"), plain(value));
+            }
+        }
+        None if !report["journal_hex"].as_str().unwrap_or_default().is_empty() => {
+            print!(
+                "{}",
+                hex_dump(&hex::decode(report["journal_hex"].as_str().unwrap()).unwrap())
+            );
+        }
+        None => {}
+    }
+    Ok(())
+}
+
+fn inspect(receipt: &Receipt) -> CliResult<Value> {
+    let claim = receipt.claim().map_err(|e| format!("receipt has no claim: {e}"))?;
+    let claim = claim.as_value().map_err(|_| "receipt claim is pruned".to_string())?;
+    let image_id = claim.pre.digest();
+    let guest = [GuestKind::Assertion, GuestKind::Attestation, GuestKind::CertChain]
+        .into_iter()
+        .find(|kind| Digest::from(kind.image_id()) == image_id);
+    let journal = &receipt.journal.bytes;
+
+    let (layout, decoded) = match guest {
+        _ if journal.is_empty() => ("empty", Value::Null),
+        Some(GuestKind::Assertion) => match decode_assertion_journal(journal) {
+            Ok(decoded) => ("assertion", assertion_json(journal[0], &decoded)),
+            Err(_) => ("unknown", Value::Null),
+        },
+        Some(GuestKind::Attestation) => match decode_attestation_journal(journal) {
+            Ok(decoded) => (
+                "attestation",
+                json!({
+                    "version": journal[0],
+                    "policy": policy_json(&decoded.policy),
+                    "public_key": hex::encode(decoded.public_key),
+                    "key_id": hex::encode(decoded.key_id),
+                    "rp_id_hash": hex::encode(decoded.rp_id_hash),
+                    "environment": match decoded.environment {
+                        AttestationJournal::PRODUCTION => "production",
+                        AttestationJournal::DEVELOPMENT => "development",
+                        _ => "unknown",
+                    },
+                    "timestamp": decoded.timestamp,
+                    "time_signer": hex::encode(decoded.time_signer),
+                    "allowlist_entry": decoded.allowlist_entry,
+                    "cert_chain_image_id": hex::encode(decoded.cert_chain_image_id),
+                    "cert_chain_claim_digest": hex::encode(decoded.cert_chain_claim_digest),
+                }),
+            ),
+            Err(_) => ("unknown", Value::Null),
+        },
+        Some(GuestKind::CertChain) => match decode_cert_chain_journal(journal) {
+            Ok(decoded) => (
+                "cert_chain",
+                json!({
+                    "version": journal[0],
+                    "policy": policy_json(&decoded.policy),
+                    "public_key": hex::encode(decoded.public_key),
+                    "leaf_fingerprint": hex::encode(decoded.leaf_fingerprint),
+                    "auth_data_hash": hex::encode(decoded.auth_data_hash),
+                    "timestamp": decoded.timestamp,
+                    "time_signer": hex::encode(decoded.time_signer),
+                }),
+            ),
+            Err(_) => ("unknown", Value::Null),
+        },
+        None => ("unknown", Value::Null),
+    };
+
+    Ok(json!({
+        "kind": receipt_kind(receipt),
+        "image_id": hex::encode(image_id),
+        "guest": match guest {
+            Some(GuestKind::Assertion) => "assertion",
+            Some(GuestKind::Attestation) => "attestation",
+            Some(GuestKind::CertChain) => "cert_chain",
+            None => "unknown",
+        },
+        "exit_code": format!("{:?}", claim.exit_code),
+        "claim_digest": claim_digest_hex(receipt).map_err(|e| e.to_string())?,
+        "journal_len": journal.len(),
+        "layout": layout,
+        "journal": decoded,
+        "journal_hex": hex::encode(journal),
+    }))
+}
+
+fn assertion_json(version: u8, journal: &AssertionJournal) -> Value {
+    json!({
+        "version": version,
+        "policy": policy_json(&journal.policy),
+        "rp_id_hash": hex::encode(journal.rp_id_hash),
+        "public_key_x": hex::encode(journal.public_key_x),
+        "public_key_y": hex::encode(journal.public_key_y),
+        "signature_r": hex::encode(journal.signature_r),
+        "signature_s": hex::encode(journal.signature_s),
+        "counter": journal.counter,
+        "client_data_hash": hex::encode(journal.client_data_hash),
+        "attestation_image_id": hex::encode(journal.attestation_image_id),
+        "attestation_claim_digest": hex::encode(journal.attestation_claim_digest),
+        "challenge_hash": hex::encode(journal.challenge_hash),
+        "allowlist_entry": journal.allowlist_entry,
+        "session_nonce": hex::encode(journal.session_nonce),
+    })
+}
+
+fn receipt_kind(receipt: &Receipt) -> &'static str {
+    match &receipt.inner {
+        InnerReceipt::Composite(_) => "composite",
+        InnerReceipt::Succinct(_) => "succinct",
+        InnerReceipt::Groth16(_) => "groth16",
+        InnerReceipt::Fake(_) => "fake",
+        _ => "unknown",
+    }
+}
+
+fn plain(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => "-".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Offset, hex and ASCII columns, 16 bytes per line.
+fn hex_dump(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for (i, chunk) in bytes.chunks(16).enumerate() {
+        let hex: Vec<String> = chunk.iter().map(|b| format!("{b:02x}")).collect();
+        let ascii: String = chunk
+            .iter()
+            .map(|&b| if b.is_ascii_graphic() { b as char } else { '.' })
+            .collect();
+        out += &format!("  {:08x}  {:<47}  {ascii}\n", i * 16, hex.join(" "));
+    }
+    out
+}
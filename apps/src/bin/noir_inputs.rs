@@ -0,0 +1,112 @@
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use appattest_rs::assertion::Assertion;
+use base64::{engine::general_purpose, Engine};
+use clap::Parser;
+use mopro_bindings::cli::{file_or_inline, write_file, CliResult};
+use mopro_bindings::noir::NoirCircuitInputs;
+use mopro_bindings::{handle_version_full_flag, verify_assertion_proof, SignatureData};
+use p256::ecdsa::signature::hazmat::PrehashVerifier;
+use p256::ecdsa::{Signature, VerifyingKey};
+use p256::EncodedPoint;
+use sha2::{Digest, Sha256};
+
+/// Writes the `ecdsa` Noir project's `Prover.toml` from an assertion proof.
+///
+/// Every input is a `[u8; 32]` array, written as one quoted hex byte per element:
+/// `sha256_hash` is the digest the assertion signature covers, `SHA256(nonce)`, and
+/// `r`, `s`, `public_key_x`, `public_key_y` come straight from the journal.
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    /// Assertion receipt; it is verified before its journal is used.
+    #[arg(long, required_unless_present = "signature_json", conflicts_with = "signature_json")]
+    receipt: Option<PathBuf>,
+    /// JSON object with hex `signature_r`, `signature_s`, `public_key_x` and `public_key_y`,
+    /// e.g. the `journal` printed by `verify --json`.
+    #[arg(long)]
+    signature_json: Option<PathBuf>,
+    /// Base64 CBOR assertion the proof was made for, or a file containing it.
+    #[arg(long)]
+    assertion: String,
+    /// Base64 client data the assertion signs, or a file containing it.
+    #[arg(long)]
+    client_data: String,
+    /// Noir project the `Prover.toml` is written into.
+    #[arg(long, default_value = concat!(env!("CARGO_MANIFEST_DIR"), "/../ecdsa"))]
+    ecdsa_dir: PathBuf,
+    /// Overwrite an existing `Prover.toml`.
+    #[arg(long)]
+    force: bool,
+}
+
+fn main() -> ExitCode {
+    handle_version_full_flag();
+
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> CliResult<()> {
+    let signature = match (&cli.receipt, &cli.signature_json) {
+        (Some(path), _) => {
+            let bytes = std::fs::read(path).map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+            verify_assertion_proof(bytes, None).map_err(|e| e.to_string())?
+        }
+        (None, Some(path)) => signature_from_json(path)?,
+        (None, None) => unreachable!("clap requires one of --receipt/--signature-json"),
+    };
+    let [r, s, x, y] = [
+        ("signature_r", &signature.signature_r),
+        ("signature_s", &signature.signature_s),
+        ("public_key_x", &signature.public_key_x),
+        ("public_key_y", &signature.public_key_y),
+    ]
+    .map(|(name, value)| <[u8; 32]>::try_from(value.as_slice()).map_err(|_| format!("{name} is not 32 bytes")));
+    let (r, s, x, y) = (r?, s?, x?, y?);
+
+    let assertion = Assertion::from_base64(&file_or_inline(&cli.assertion)?).map_err(|e| e.to_string())?;
+    let client_data = general_purpose::STANDARD
+        .decode(file_or_inline(&cli.client_data)?)
+        .map_err(|e| format!("invalid client data: {e}"))?;
+    let message_hash: [u8; 32] = Sha256::digest(assertion.nonce(&client_data)).into();
+
+    // Catch mismatched inputs here rather than as an opaque constraint failure in nargo.
+    let key = VerifyingKey::from_encoded_point(&EncodedPoint::from_affine_coordinates(&x.into(), &y.into(), false))
+        .map_err(|_| "public key is not a valid P-256 point".to_string())?;
+    let sig = Signature::from_scalars(r, s).map_err(|e| format!("invalid signature scalars: {e}"))?;
+    key.verify_prehash(&message_hash, &sig)
+        .map_err(|_| "signature does not verify over this assertion and client data".to_string())?;
+
+    let inputs = NoirCircuitInputs { sha256_hash: message_hash, r, s, public_key_x: x, public_key_y: y };
+    let path = write_file(&cli.ecdsa_dir.join("Prover.toml"), inputs.to_prover_toml().as_bytes(), cli.force)?;
+    println!("Wrote {}", path.display());
+    Ok(())
+}
+
+fn signature_from_json(path: &Path) -> CliResult<SignatureData> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+    let json: serde_json::Value = serde_json::from_str(&text).map_err(|e| format!("invalid signature JSON: {e}"))?;
+    let field = |name: &str| {
+        let value = json[name].as_str().ok_or(format!("signature JSON is missing {name}"))?;
+        hex::decode(value.trim_start_matches("0x")).map_err(|e| format!("{name} is not hex: {e}"))
+    };
+    let optional = |name: &str| if json[name].is_null() { Ok(Vec::new()) } else { field(name) };
+    Ok(SignatureData {
+        signature_r: field("signature_r")?,
+        signature_s: field("signature_s")?,
+        public_key_x: field("public_key_x")?,
+        public_key_y: field("public_key_y")?,
+        // Not needed for the Noir inputs, so older JSON without them still works.
+        client_data_hash: optional("client_data_hash")?,
+        challenge_hash: optional("challenge_hash")?,
+        session_nonce: optional("session_nonce")?,
+        policy: None,
+    })
+}
@@ -0,0 +1,192 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use alloy::network::{EthereumWallet, TransactionBuilder};
+use alloy::primitives::{Address, Bytes};
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy::rpc::types::TransactionRequest;
+use alloy::signers::local::PrivateKeySigner;
+use alloy::sol;
+use alloy::sol_types::SolCall;
+use clap::Parser;
+use mopro_bindings::cli::{file_or_inline, CliResult};
+use mopro_bindings::{deserialize_receipt, handle_version_full_flag, HybridProofOutput};
+use risc0_zkvm::sha::Digestible;
+use risc0_zkvm::{default_prover, InnerReceipt, ProverOpts, Receipt};
+
+sol! {
+    /// Entry point of the app contract that consumes assertion proofs.
+    interface IAppContract {
+        function submit(bytes calldata seal, bytes calldata journal) external;
+    }
+}
+
+/// Selector the RISC Zero verifier router uses for fake (dev-mode) seals.
+const FAKE_SELECTOR: [u8; 4] = [0xff; 4];
+
+/// Sends a receipt to the app contract as `submit(seal, journal)`.
+///
+/// Composite and succinct receipts are compressed to Groth16 first, which needs a prover able
+/// to run the Groth16 step (x86 with Docker, or Bonsai). Dev-mode receipts are sent with a fake
+/// seal, which only a verifier deployed in dev mode accepts.
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    /// Bincode-serialized receipt.
+    #[arg(long, required_unless_present = "bundle", conflicts_with = "bundle")]
+    receipt: Option<PathBuf>,
+    /// Receipt and Noir proof written by `HybridProofOutput::to_bytes`. The bundle must pass
+    /// its consistency check; its receipt is the one sent.
+    #[arg(long)]
+    bundle: Option<PathBuf>,
+    /// Address of the app contract.
+    #[arg(long)]
+    contract: Address,
+    /// JSON-RPC endpoint of the chain.
+    #[arg(long, required_unless_present = "dry_run")]
+    rpc_url: Option<String>,
+    /// Hex private key of the sender, or a file containing it.
+    #[arg(long, required_unless_present_any = ["keystore", "dry_run"], conflicts_with = "keystore")]
+    private_key: Option<String>,
+    /// Encrypted JSON keystore of the sender; the password is read from `KEYSTORE_PASSWORD`.
+    #[arg(long)]
+    keystore: Option<PathBuf>,
+    /// Print the calldata instead of sending a transaction.
+    #[arg(long)]
+    dry_run: bool,
+    /// Print a machine-readable summary.
+    #[arg(long)]
+    json: bool,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    handle_version_full_flag();
+
+    match run(Cli::parse()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> CliResult<()> {
+    let receipt = groth16_or_fake(deserialize_receipt(&receipt_bytes(&cli)?).map_err(|e| e.to_string())?)?;
+    let calldata = Bytes::from(
+        IAppContract::submitCall {
+            seal: encode_seal(&receipt)?.into(),
+            journal: receipt.journal.bytes.clone().into(),
+        }
+        .abi_encode(),
+    );
+
+    if cli.dry_run {
+        if cli.json {
+            println!("{}", serde_json::json!({ "to": cli.contract, "calldata": calldata }));
+        } else {
+            println!("to:       {}", cli.contract);
+            println!("calldata: {calldata}");
+        }
+        return Ok(());
+    }
+
+    let signer = signer(&cli)?;
+    let rpc_url = cli
+        .rpc_url
+        .as_deref()
+        .expect("clap requires --rpc-url without --dry-run");
+    let provider = ProviderBuilder::new()
+        .wallet(EthereumWallet::from(signer))
+        .connect_http(rpc_url.parse().map_err(|e| format!("invalid --rpc-url: {e}"))?);
+
+    let tx = TransactionRequest::default().with_to(cli.contract).with_input(calldata);
+    let gas = provider
+        .estimate_gas(tx.clone())
+        .await
+        .map_err(|e| format!("gas estimation failed: {e}"))?;
+    let pending = provider
+        .send_transaction(tx.with_gas_limit(gas))
+        .await
+        .map_err(|e| format!("failed to send transaction: {e}"))?;
+    let tx_hash = *pending.tx_hash();
+    if !cli.json {
+        println!("Sent {tx_hash}, waiting for the receipt...");
+    }
+    let tx_receipt = pending
+        .get_receipt()
+        .await
+        .map_err(|e| format!("failed to get the receipt of {tx_hash}: {e}"))?;
+
+    let status = if tx_receipt.status() { "success" } else { "reverted" };
+    if cli.json {
+        let summary = serde_json::json!({
+            "tx_hash": tx_hash,
+            "status": status,
+            "block_number": tx_receipt.block_number,
+            "gas_used": tx_receipt.gas_used,
+        });
+        println!("{summary}");
+    } else {
+        println!("Transaction {tx_hash}: {status} (gas used {})", tx_receipt.gas_used);
+    }
+    if !tx_receipt.status() {
+        return Err(format!("transaction {tx_hash} reverted"));
+    }
+    Ok(())
+}
+
+/// The receipt named by `--receipt`, or the one in the `--bundle` after checking it matches
+/// the bundle's Noir values.
+fn receipt_bytes(cli: &Cli) -> CliResult<Vec<u8>> {
+    let path = cli.receipt.as_ref().or(cli.bundle.as_ref()).expect("clap requires --receipt or --bundle");
+    let bytes = std::fs::read(path).map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+    if cli.receipt.is_some() {
+        return Ok(bytes);
+    }
+    let bundle = HybridProofOutput::from_bytes(&bytes).map_err(|e| e.to_string())?;
+    bundle.consistency_check().map_err(|e| e.to_string())?;
+    Ok(bundle.receipt)
+}
+
+/// Compresses `receipt` to Groth16 unless it already is one, or is a dev-mode receipt.
+fn groth16_or_fake(receipt: Receipt) -> CliResult<Receipt> {
+    match receipt.inner {
+        InnerReceipt::Groth16(_) | InnerReceipt::Fake(_) => Ok(receipt),
+        _ => default_prover()
+            .compress(&ProverOpts::groth16(), &receipt)
+            .map_err(|e| format!("failed to compress the receipt to Groth16: {e}")),
+    }
+}
+
+/// Seal in the layout the RISC Zero verifier router expects: a 4-byte selector naming the
+/// verifier, followed by the proof.
+fn encode_seal(receipt: &Receipt) -> CliResult<Vec<u8>> {
+    let (selector, proof) = match &receipt.inner {
+        InnerReceipt::Groth16(groth16) => {
+            let selector: [u8; 4] = groth16.verifier_parameters.as_bytes()[..4].try_into().unwrap();
+            (selector, groth16.seal.clone())
+        }
+        InnerReceipt::Fake(fake) => {
+            let claim = fake.claim.digest();
+            (FAKE_SELECTOR, claim.as_bytes().to_vec())
+        }
+        _ => return Err("only Groth16 and dev-mode receipts can be sent on-chain".to_string()),
+    };
+    Ok([selector.as_slice(), &proof].concat())
+}
+
+fn signer(cli: &Cli) -> CliResult<PrivateKeySigner> {
+    match (&cli.private_key, &cli.keystore) {
+        (Some(key), _) => file_or_inline(key)?
+            .parse()
+            .map_err(|e| format!("invalid private key: {e}")),
+        (None, Some(path)) => {
+            let password =
+                std::env::var("KEYSTORE_PASSWORD").map_err(|_| "KEYSTORE_PASSWORD is not set".to_string())?;
+            PrivateKeySigner::decrypt_keystore(path, password).map_err(|e| format!("failed to decrypt keystore: {e}"))
+        }
+        (None, None) => Err("--private-key or --keystore is required to send".to_string()),
+    }
+}
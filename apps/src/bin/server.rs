@@ -0,0 +1,67 @@
+use std::net::SocketAddr;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
+
+use appattest_rs::store::{InMemoryChallengeStore, InMemoryKeyStore};
+use clap::Parser;
+use mopro_bindings::handle_version_full_flag;
+use mopro_bindings::server::{router, upload_router, ServerConfig, ServerState, Uploads};
+
+/// Serves challenge issuing, attestation, assertion and receipt verification over HTTP.
+///
+/// Keys and challenges are held in memory and lost on restart, as are proof bundles
+/// uploaded with `--upload-token`.
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    /// Address to listen on.
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    listen: SocketAddr,
+    /// `<team id>.<bundle id>` of the app whose keys are accepted.
+    #[arg(long)]
+    app_id: String,
+    /// Accept keys from the App Attest development environment.
+    #[arg(long)]
+    development: bool,
+    /// Seconds an issued challenge stays valid.
+    #[arg(long, default_value_t = 300)]
+    challenge_ttl: u64,
+    /// Accept proof bundle uploads under `/uploads` from clients sending this bearer token.
+    #[arg(long)]
+    upload_token: Option<String>,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    handle_version_full_flag();
+
+    let cli = Cli::parse();
+    let state = ServerState {
+        keys: Arc::new(InMemoryKeyStore::new()),
+        challenges: Arc::new(InMemoryChallengeStore::new(Duration::from_secs(cli.challenge_ttl))),
+        config: Arc::new(ServerConfig {
+            app_id: cli.app_id,
+            development: cli.development,
+            verification_time: None,
+        }),
+    };
+
+    let listener = match tokio::net::TcpListener::bind(cli.listen).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("error: failed to listen on {}: {e}", cli.listen);
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut app = router(state);
+    if let Some(token) = &cli.upload_token {
+        app = app.merge(upload_router(Arc::new(Uploads::default()), token));
+    }
+    println!("Listening on {}", cli.listen);
+    if let Err(e) = axum::serve(listener, app).await {
+        eprintln!("error: {e}");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,182 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Parser, ValueEnum};
+use mopro_bindings::cli::{appattest_version, now_unix, policy_json, session_nonce, CliResult};
+use mopro_bindings::{
+    check_attestation_freshness, check_attestation_guest, check_cert_chain_guest, check_minimum_policy,
+    check_session_nonce, claim_digest_hex, decode_assertion_journal, decode_attestation_journal,
+    decode_cert_chain_journal, handle_version_full_flag, verify_receipt, AttestationJournal, GuestKind,
+};
+
+/// Checks a receipt written by the `attestation` or `assertion` binaries without proving anything.
+///
+/// An attestation receipt is only accepted if it relies on this build's certificate chain guest,
+/// and an assertion receipt if it relies on this build's attestation guest.
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    /// Bincode-serialized receipt.
+    #[arg(long)]
+    receipt: PathBuf,
+    /// Guest the receipt was produced by.
+    #[arg(long, value_enum)]
+    kind: KindArg,
+    /// Fail unless the journal equals these hex bytes.
+    #[arg(long)]
+    expect_journal: Option<String>,
+    /// Fail if an attestation journal's timestamp is more than this many seconds old.
+    #[arg(long)]
+    max_age: Option<i64>,
+    /// Unix time `--max-age` is measured from; defaults to now.
+    #[arg(long, requires = "max_age")]
+    now: Option<i64>,
+    /// Fail unless an assertion journal commits this hex session nonce.
+    #[arg(long, value_parser = session_nonce)]
+    session_nonce: Option<[u8; 32]>,
+    /// Fail if the guest verified with an appattest-rs older than this `major.minor.patch`.
+    #[arg(long, value_parser = appattest_version)]
+    min_appattest_version: Option<(u8, u8, u8)>,
+    /// Print a machine-readable summary instead of text.
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum KindArg {
+    CertChain,
+    Attestation,
+    Assertion,
+}
+
+impl From<KindArg> for GuestKind {
+    fn from(kind: KindArg) -> Self {
+        match kind {
+            KindArg::CertChain => GuestKind::CertChain,
+            KindArg::Attestation => GuestKind::Attestation,
+            KindArg::Assertion => GuestKind::Assertion,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    handle_version_full_flag();
+
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> CliResult<()> {
+    let bytes = std::fs::read(&cli.receipt).map_err(|e| format!("failed to read {}: {e}", cli.receipt.display()))?;
+    let kind = GuestKind::from(cli.kind);
+    let receipt = verify_receipt(&bytes, kind).map_err(|e| e.to_string())?;
+    let journal = &receipt.journal.bytes;
+
+    if let Some(expected) = &cli.expect_journal {
+        let expected =
+            hex::decode(expected.trim_start_matches("0x")).map_err(|e| format!("invalid --expect-journal: {e}"))?;
+        if &expected != journal {
+            return Err(format!("journal mismatch: receipt commits {}", hex::encode(journal)));
+        }
+    }
+
+    let decoded = match kind {
+        GuestKind::Assertion => {
+            let data = decode_assertion_journal(journal).map_err(|e| e.to_string())?;
+            check_attestation_guest(&data).map_err(|e| e.to_string())?;
+            if let Some(expected) = &cli.session_nonce {
+                check_session_nonce(&data, expected).map_err(|e| e.to_string())?;
+            }
+            if let Some(minimum) = cli.min_appattest_version {
+                check_minimum_policy(&data.policy, minimum).map_err(|e| e.to_string())?;
+            }
+            serde_json::json!({
+                "version": journal[0],
+                "policy": policy_json(&data.policy),
+                "rp_id_hash": hex::encode(data.rp_id_hash),
+                "public_key_x": hex::encode(data.public_key_x),
+                "public_key_y": hex::encode(data.public_key_y),
+                "signature_r": hex::encode(data.signature_r),
+                "signature_s": hex::encode(data.signature_s),
+                "counter": data.counter,
+                "client_data_hash": hex::encode(data.client_data_hash),
+                "attestation_image_id": hex::encode(data.attestation_image_id),
+                "attestation_claim_digest": hex::encode(data.attestation_claim_digest),
+                "challenge_hash": hex::encode(data.challenge_hash),
+                "allowlist_entry": data.allowlist_entry,
+                "session_nonce": hex::encode(data.session_nonce),
+            })
+        }
+        GuestKind::Attestation => {
+            let data = decode_attestation_journal(journal).map_err(|e| e.to_string())?;
+            check_cert_chain_guest(&data).map_err(|e| e.to_string())?;
+            if let Some(max_age) = cli.max_age {
+                let now = cli.now.unwrap_or_else(now_unix);
+                check_attestation_freshness(&data, now, max_age).map_err(|e| e.to_string())?;
+            }
+            if let Some(minimum) = cli.min_appattest_version {
+                check_minimum_policy(&data.policy, minimum).map_err(|e| e.to_string())?;
+            }
+            serde_json::json!({
+                "version": journal[0],
+                "policy": policy_json(&data.policy),
+                "public_key": hex::encode(data.public_key),
+                "key_id": hex::encode(data.key_id),
+                "rp_id_hash": hex::encode(data.rp_id_hash),
+                "environment": match data.environment {
+                    AttestationJournal::PRODUCTION => "production",
+                    AttestationJournal::DEVELOPMENT => "development",
+                    _ => "unknown",
+                },
+                "timestamp": data.timestamp,
+                "time_signer": hex::encode(data.time_signer),
+                "allowlist_entry": data.allowlist_entry,
+                "cert_chain_image_id": hex::encode(data.cert_chain_image_id),
+                "cert_chain_claim_digest": hex::encode(data.cert_chain_claim_digest),
+            })
+        }
+        GuestKind::CertChain => {
+            let data = decode_cert_chain_journal(journal).map_err(|e| e.to_string())?;
+            if let Some(minimum) = cli.min_appattest_version {
+                check_minimum_policy(&data.policy, minimum).map_err(|e| e.to_string())?;
+            }
+            serde_json::json!({
+                "version": journal[0],
+                "policy": policy_json(&data.policy),
+                "public_key": hex::encode(data.public_key),
+                "leaf_fingerprint": hex::encode(data.leaf_fingerprint),
+                "auth_data_hash": hex::encode(data.auth_data_hash),
+                "timestamp": data.timestamp,
+                "time_signer": hex::encode(data.time_signer),
+            })
+        }
+    };
+    let summary = serde_json::json!({
+        "verified": true,
+        "image_id": hex::encode(risc0_zkvm::sha::Digest::from(kind.image_id())),
+        "claim_digest": claim_digest_hex(&receipt).map_err(|e| e.to_string())?,
+        "journal_hex": hex::encode(journal),
+        "journal": decoded,
+    });
+
+    if cli.json {
+        println!("{summary}");
+    } else {
+        println!("Receipt verified.");
+        println!("Image ID:     {}", summary["image_id"].as_str().unwrap_or_default());
+        println!("Claim digest: {}", summary["claim_digest"].as_str().unwrap_or_default());
+        println!(
+            "Journal:      {}",
+            serde_json::to_string_pretty(&summary["journal"]).map_err(|e| e.to_string())?
+        );
+        if decoded.is_null() {
+            println!("Journal hex:  {}", hex::encode(journal));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,88 @@
+use methods::{ASSERTION_ID, ATTESTATION_REST_ID, CERT_CHAIN_ID};
+use risc0_zkvm::sha::Digest;
+use serde::{Deserialize, Serialize};
+
+/// Identifies the exact build that produced a proof.
+#[derive(uniffi::Record, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct BuildInfo {
+    pub crate_version: String,
+    pub risc0_version: String,
+    pub assertion_image_id_hex: String,
+    pub attestation_image_id_hex: String,
+    /// Image ID of the certificate chain guest the attestation guest relies on.
+    pub cert_chain_image_id_hex: String,
+    pub git_commit: String,
+    /// Unix timestamp (seconds) of the build.
+    pub built_at: u64,
+}
+
+impl std::fmt::Display for BuildInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "mopro_bindings {}", self.crate_version)?;
+        writeln!(f, "risc0-zkvm      {}", self.risc0_version)?;
+        writeln!(f, "assertion id    {}", self.assertion_image_id_hex)?;
+        writeln!(f, "attestation id  {}", self.attestation_image_id_hex)?;
+        writeln!(f, "cert chain id   {}", self.cert_chain_image_id_hex)?;
+        writeln!(f, "git commit      {}", self.git_commit)?;
+        write!(f, "built at        {}", self.built_at)
+    }
+}
+
+#[uniffi::export]
+pub fn get_build_info() -> BuildInfo {
+    BuildInfo {
+        crate_version: env!("CARGO_PKG_VERSION").to_string(),
+        risc0_version: env!("MOBISCALE_RISC0_VERSION").to_string(),
+        assertion_image_id_hex: image_id_hex(ASSERTION_ID),
+        attestation_image_id_hex: image_id_hex(ATTESTATION_REST_ID),
+        cert_chain_image_id_hex: image_id_hex(CERT_CHAIN_ID),
+        git_commit: env!("MOBISCALE_GIT_COMMIT").to_string(),
+        built_at: env!("MOBISCALE_BUILT_AT").parse().unwrap_or(0),
+    }
+}
+
+pub(crate) fn image_id_hex(image_id: [u32; 8]) -> String {
+    hex::encode(Digest::from(image_id).as_bytes())
+}
+
+/// Prints the full build info and exits if `--version-full` was passed on the command line.
+pub fn handle_version_full_flag() {
+    if std::env::args().any(|arg| arg == "--version-full") {
+        println!("{}", get_build_info());
+        std::process::exit(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_ids_match_methods_constants() {
+        let info = get_build_info();
+        assert_eq!(info.assertion_image_id_hex.len(), 64);
+        assert_eq!(info.attestation_image_id_hex.len(), 64);
+        assert_eq!(
+            hex::decode(&info.assertion_image_id_hex).unwrap(),
+            Digest::from(ASSERTION_ID).as_bytes()
+        );
+        assert_eq!(
+            hex::decode(&info.attestation_image_id_hex).unwrap(),
+            Digest::from(ATTESTATION_REST_ID).as_bytes()
+        );
+        assert_eq!(
+            hex::decode(&info.cert_chain_image_id_hex).unwrap(),
+            Digest::from(CERT_CHAIN_ID).as_bytes()
+        );
+    }
+
+    #[test]
+    fn versions_are_populated() {
+        let info = get_build_info();
+        assert_eq!(info.crate_version, env!("CARGO_PKG_VERSION"));
+        assert!(!info.risc0_version.is_empty());
+        assert!(!info.git_commit.is_empty());
+    }
+}
@@ -0,0 +1,183 @@
+//! Receipts kept on disk by input, so that a retried flow does not prove the same input twice.
+//!
+//! A receipt is stored as `<dir>/<digest>.receipt`, `digest` being [`GuestInput::digest`] in
+//! hex, and is only returned if it still verifies against the guest's image ID. The digest
+//! covers the image ID, so a new guest build misses every old entry, which then ages out.
+//! Files are written to a temporary name and renamed into place, so a reader never sees half
+//! a receipt, even with several provers sharing the directory.
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use risc0_zkvm::Receipt;
+
+use crate::{verify_receipt, GuestInput, Risc0Error};
+
+const EXTENSION: &str = "receipt";
+
+/// A directory of receipts holding at most `max_bytes`; the least recently used go first.
+#[derive(Clone, Debug)]
+pub struct ReceiptCache {
+    dir: PathBuf,
+    max_bytes: u64,
+}
+
+impl ReceiptCache {
+    /// Creates `dir` if needed.
+    pub fn new(dir: impl Into<PathBuf>, max_bytes: u64) -> Result<Self, Risc0Error> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir).map_err(|e| {
+            Risc0Error::ProveError(format!("failed to create receipt cache {}: {e}", dir.display()))
+        })?;
+        Ok(Self { dir, max_bytes })
+    }
+
+    /// The receipt stored for `input`, if there is one and it verifies. A hit counts as a
+    /// use for eviction.
+    pub fn get(&self, input: &GuestInput) -> Option<Receipt> {
+        let path = self.path(input)?;
+        let bytes = fs::read(&path).ok()?;
+        let receipt = verify_receipt(&bytes, input.kind()).ok()?;
+        // The modification time is the last use; failing to update it only ages the entry.
+        let _ = File::options().append(true).open(&path).and_then(|file| file.set_modified(SystemTime::now()));
+        Some(receipt)
+    }
+
+    /// Stores `receipt` for `input`, then evicts down to the size bound. A receipt that
+    /// cannot be stored is only not cached, so failures are not reported.
+    pub fn put(&self, input: &GuestInput, receipt: &Receipt) {
+        let Some(path) = self.path(input) else { return };
+        let Ok(bytes) = bincode::serialize(receipt) else { return };
+        if self.write(&path, &bytes).is_ok() {
+            self.evict();
+        }
+    }
+
+    fn path(&self, input: &GuestInput) -> Option<PathBuf> {
+        let digest = input.digest().ok()?;
+        Some(self.dir.join(format!("{}.{EXTENSION}", hex::encode(digest))))
+    }
+
+    fn write(&self, path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+        let mut file = tempfile::NamedTempFile::new_in(&self.dir)?;
+        file.write_all(bytes)?;
+        file.as_file().sync_all()?;
+        file.persist(path).map_err(|e| e.error)?;
+        Ok(())
+    }
+
+    /// Removes the least recently used receipts until the rest fit in `max_bytes`.
+    fn evict(&self) {
+        let Ok(entries) = fs::read_dir(&self.dir) else { return };
+        let mut receipts: Vec<(SystemTime, u64, PathBuf)> = entries
+            .filter_map(Result::ok)
+            .filter(|entry| entry.path().extension().is_some_and(|extension| extension == EXTENSION))
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+                Some((metadata.modified().ok()?, metadata.len(), entry.path()))
+            })
+            .collect();
+        let mut total: u64 = receipts.iter().map(|(_, len, _)| len).sum();
+        receipts.sort();
+        for (_, len, path) in receipts {
+            if total <= self.max_bytes {
+                break;
+            }
+            if fs::remove_file(&path).is_ok() {
+                total -= len;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use methods::CERT_CHAIN_ID;
+    use risc0_zkvm::{FakeReceipt, InnerReceipt, ReceiptClaim};
+
+    use super::*;
+    use crate::backend::mock::MockBackend;
+    use crate::tests::{sample_attestation_request, sample_journal};
+    use crate::{AttestationRequest, CertChainInput, Prover, ProverConfig};
+
+    fn input(challenge: u8) -> GuestInput {
+        let request = AttestationRequest { challenge: vec![challenge; 32], ..sample_attestation_request() };
+        GuestInput::CertChain(CertChainInput::from(&request))
+    }
+
+    fn receipt(image_id: [u32; 8]) -> Receipt {
+        let claim = ReceiptClaim::ok(image_id, vec![1, 2, 3]);
+        Receipt::new(InnerReceipt::Fake(FakeReceipt::new(claim)), vec![1, 2, 3])
+    }
+
+    fn files(dir: &Path) -> usize {
+        fs::read_dir(dir).unwrap().count()
+    }
+
+    fn age(cache: &ReceiptCache, input: &GuestInput, secs: u64) {
+        let file = File::options().append(true).open(cache.path(input).unwrap()).unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(secs)).unwrap();
+    }
+
+    #[test]
+    fn second_proof_of_an_input_comes_from_the_cache() {
+        std::env::set_var("RISC0_DEV_MODE", "1");
+        let dir = tempfile::tempdir().unwrap();
+        let backend = Arc::new(MockBackend::with_journal(sample_journal()));
+        let prover = Prover::with_backend(backend.clone(), ProverConfig::default());
+        prover.set_receipt_cache(dir.path().to_str().unwrap().into(), u64::MAX).unwrap();
+
+        let first = prover.prove_cert_chain(sample_attestation_request()).unwrap();
+        let second = prover.prove_cert_chain(sample_attestation_request()).unwrap();
+        assert_eq!(backend.calls(), 1);
+        assert_eq!(second.receipt, first.receipt);
+        assert_eq!(files(dir.path()), 1);
+
+        // Another input is proven.
+        let request = AttestationRequest { challenge: vec![1; 32], ..sample_attestation_request() };
+        prover.prove_cert_chain(request).unwrap();
+        assert_eq!(backend.calls(), 2);
+    }
+
+    #[test]
+    fn receipts_of_another_image_are_misses() {
+        std::env::set_var("RISC0_DEV_MODE", "1");
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ReceiptCache::new(dir.path(), u64::MAX).unwrap();
+
+        cache.put(&input(0), &receipt([7; 8]));
+        assert!(cache.get(&input(0)).is_none());
+        cache.put(&input(0), &receipt(CERT_CHAIN_ID));
+        assert_eq!(cache.get(&input(0)).unwrap().journal.bytes, vec![1, 2, 3]);
+        assert!(cache.get(&input(1)).is_none());
+
+        fs::write(cache.path(&input(1)).unwrap(), b"truncated").unwrap();
+        assert!(cache.get(&input(1)).is_none());
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used() {
+        std::env::set_var("RISC0_DEV_MODE", "1");
+        let dir = tempfile::tempdir().unwrap();
+        let len = bincode::serialize(&receipt(CERT_CHAIN_ID)).unwrap().len() as u64;
+        let cache = ReceiptCache::new(dir.path(), 2 * len).unwrap();
+
+        cache.put(&input(0), &receipt(CERT_CHAIN_ID));
+        age(&cache, &input(0), 20);
+        cache.put(&input(1), &receipt(CERT_CHAIN_ID));
+        age(&cache, &input(1), 10);
+        // Using the older entry makes the other one the least recently used.
+        assert!(cache.get(&input(0)).is_some());
+
+        cache.put(&input(2), &receipt(CERT_CHAIN_ID));
+        assert_eq!(files(dir.path()), 2);
+        assert!(cache.get(&input(0)).is_some());
+        assert!(cache.get(&input(1)).is_none());
+        assert!(cache.get(&input(2)).is_some());
+    }
+}
@@ -0,0 +1,382 @@
+//! Plain C interface, for hosts that cannot use the uniffi bindings. With the `capi`
+//! feature, build.rs writes its header to `include/mobiscale.h`.
+//!
+//! Every function returns [`MOBISCALE_OK`] or an error code: [`Risc0Error::code`] for
+//! failures of the operation itself, or one of the `MOBISCALE_ERR_*` codes below.
+//! [`mobiscale_last_error`] gives the message of the last failure on the calling thread.
+//!
+//! Memory: input pointers are only borrowed for the call. Results are written to
+//! [`MobiscaleBuffer`] out-parameters, which the library allocates; they belong to the
+//! caller, who releases each with [`mobiscale_free_buffer`] exactly once. Out-parameters
+//! are emptied on entry, so after a failure they are empty, which is safe to free. No
+//! panic crosses the boundary; one is reported as [`MOBISCALE_ERR_PANIC`].
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+use crate::{
+    get_build_info, prove_assertion, prove_attestation, verify_assertion_proof, verify_attestation_proof,
+    AssertionRequest, AttestationRequest, Risc0Error,
+};
+
+/// The call succeeded.
+pub const MOBISCALE_OK: i32 = 0;
+/// A pointer was null where it may not be, a string was not UTF-8 or a number was out of range.
+pub const MOBISCALE_ERR_INVALID_ARGUMENT: i32 = 3001;
+/// The library panicked; the message says where.
+pub const MOBISCALE_ERR_PANIC: i32 = 3002;
+
+/// `guest` of [`mobiscale_verify_proof`] for an attestation receipt.
+pub const MOBISCALE_GUEST_ATTESTATION: u32 = 1;
+/// `guest` of [`mobiscale_verify_proof`] for an assertion receipt.
+pub const MOBISCALE_GUEST_ASSERTION: u32 = 2;
+
+/// Bytes the library allocated and the caller owns. Empty buffers have a null `data`.
+#[repr(C)]
+pub struct MobiscaleBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+impl MobiscaleBuffer {
+    const EMPTY: Self = Self { data: ptr::null_mut(), len: 0 };
+
+    fn new(bytes: Vec<u8>) -> Self {
+        if bytes.is_empty() {
+            return Self::EMPTY;
+        }
+        let bytes = bytes.into_boxed_slice();
+        let len = bytes.len();
+        Self { data: Box::into_raw(bytes).cast(), len }
+    }
+}
+
+struct Failure {
+    code: i32,
+    message: String,
+}
+
+impl From<Risc0Error> for Failure {
+    fn from(error: Risc0Error) -> Self {
+        Self { code: error.code().into(), message: error.to_string() }
+    }
+}
+
+fn invalid(message: impl Into<String>) -> Failure {
+    Failure { code: MOBISCALE_ERR_INVALID_ARGUMENT, message: message.into() }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+/// Runs `body`, turning its failure or panic into a code and the thread's last error.
+fn call(body: impl FnOnce() -> Result<(), Failure>) -> i32 {
+    let failure = match catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(())) => return MOBISCALE_OK,
+        Ok(Err(failure)) => failure,
+        Err(panic) => {
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".into());
+            Failure { code: MOBISCALE_ERR_PANIC, message: format!("panic: {message}") }
+        }
+    };
+    LAST_ERROR.with(|last| *last.borrow_mut() = failure.message);
+    failure.code
+}
+
+/// # Safety
+/// `data` is null with `len` 0, or points to `len` readable bytes that outlive `'a`.
+unsafe fn bytes<'a>(name: &str, data: *const u8, len: usize) -> Result<&'a [u8], Failure> {
+    if !data.is_null() {
+        Ok(std::slice::from_raw_parts(data, len))
+    } else if len == 0 {
+        Ok(&[])
+    } else {
+        Err(invalid(format!("{name} is null")))
+    }
+}
+
+/// # Safety
+/// `string` is null or a NUL-terminated string.
+unsafe fn string(name: &str, string: *const c_char) -> Result<String, Failure> {
+    if string.is_null() {
+        return Err(invalid(format!("{name} is null")));
+    }
+    let string = CStr::from_ptr(string).to_str().map_err(|_| invalid(format!("{name} is not UTF-8")))?;
+    Ok(string.to_owned())
+}
+
+/// Empties `out`, so it is safe to free whatever happens next.
+///
+/// # Safety
+/// `out` is null or points to a writable [`MobiscaleBuffer`].
+unsafe fn out<'a>(name: &str, out: *mut MobiscaleBuffer) -> Result<&'a mut MobiscaleBuffer, Failure> {
+    let out = out.as_mut().ok_or_else(|| invalid(format!("{name} is null")))?;
+    *out = MobiscaleBuffer::EMPTY;
+    Ok(out)
+}
+
+/// # Safety
+/// As [`out`], except a null `out` is allowed and means the result was not asked for.
+unsafe fn optional_out<'a>(out: *mut MobiscaleBuffer) -> Option<&'a mut MobiscaleBuffer> {
+    let out = out.as_mut()?;
+    *out = MobiscaleBuffer::EMPTY;
+    Some(out)
+}
+
+fn json(value: &impl serde::Serialize) -> Result<Vec<u8>, Failure> {
+    serde_json::to_vec(value).map_err(|e| Risc0Error::SerializeError(e.to_string()).into())
+}
+
+/// Proves an attestation, like `prove_attestation`, and writes the bincode receipt to
+/// `out_receipt` and, unless `out_json` is null, the whole `Risc0ProofOutput` as JSON to
+/// `out_json`.
+///
+/// # Safety
+/// Each `(pointer, len)` pair is null with `len` 0 or points to `len` readable bytes,
+/// `app_id` is a NUL-terminated string, and the out-parameters point to writable
+/// buffers; `out_json` may be null.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn mobiscale_prove_attestation(
+    attestation: *const u8,
+    attestation_len: usize,
+    challenge: *const u8,
+    challenge_len: usize,
+    app_id: *const c_char,
+    key_id: *const u8,
+    key_id_len: usize,
+    timestamp: i64,
+    out_receipt: *mut MobiscaleBuffer,
+    out_json: *mut MobiscaleBuffer,
+) -> i32 {
+    call(|| {
+        let out_receipt = out("out_receipt", out_receipt)?;
+        let out_json = optional_out(out_json);
+        let request = AttestationRequest {
+            attestation: bytes("attestation", attestation, attestation_len)?.to_vec(),
+            challenge: bytes("challenge", challenge, challenge_len)?.to_vec(),
+            app_id: string("app_id", app_id)?,
+            key_id: bytes("key_id", key_id, key_id_len)?.to_vec(),
+            timestamp,
+            time_token: None,
+        };
+        let output = prove_attestation(request)?;
+        if let Some(out_json) = out_json {
+            *out_json = MobiscaleBuffer::new(json(&output)?);
+        }
+        *out_receipt = MobiscaleBuffer::new(output.receipt);
+        Ok(())
+    })
+}
+
+/// Proves an assertion, like `prove_assertion`, and writes the bincode receipt to
+/// `out_receipt` and, unless `out_json` is null, the whole `AssertionProofOutput` as JSON
+/// to `out_json`. `previous_counter` is null before the key's first assertion, and
+/// `session_nonce` is 32 bytes, or null for none.
+///
+/// # Safety
+/// As [`mobiscale_prove_attestation`]; `previous_counter` is null or points to a readable
+/// `u32`, and `session_nonce` is null or points to 32 readable bytes.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn mobiscale_prove_assertion(
+    assertion: *const u8,
+    assertion_len: usize,
+    client_data: *const u8,
+    client_data_len: usize,
+    app_id: *const c_char,
+    attestation_receipt: *const u8,
+    attestation_receipt_len: usize,
+    previous_counter: *const u32,
+    session_nonce: *const u8,
+    out_receipt: *mut MobiscaleBuffer,
+    out_json: *mut MobiscaleBuffer,
+) -> i32 {
+    call(|| {
+        let out_receipt = out("out_receipt", out_receipt)?;
+        let out_json = optional_out(out_json);
+        let session_nonce =
+            if session_nonce.is_null() { vec![0; 32] } else { bytes("session_nonce", session_nonce, 32)?.to_vec() };
+        let request = AssertionRequest {
+            assertion: bytes("assertion", assertion, assertion_len)?.to_vec(),
+            client_data: bytes("client_data", client_data, client_data_len)?.to_vec(),
+            app_id: string("app_id", app_id)?,
+            attestation_receipt: bytes("attestation_receipt", attestation_receipt, attestation_receipt_len)?.to_vec(),
+            previous_counter: previous_counter.as_ref().copied(),
+            session_nonce,
+        };
+        let output = prove_assertion(request)?;
+        if let Some(out_json) = out_json {
+            *out_json = MobiscaleBuffer::new(json(&output)?);
+        }
+        *out_receipt = MobiscaleBuffer::new(output.proof.receipt);
+        Ok(())
+    })
+}
+
+/// Verifies a receipt of `guest`, [`MOBISCALE_GUEST_ATTESTATION`] or
+/// [`MOBISCALE_GUEST_ASSERTION`], and writes what it proves as JSON to `out_json`: a
+/// `VerifiedAttestation` or a `SignatureData`.
+///
+/// # Safety
+/// `receipt` is null with `receipt_len` 0 or points to `receipt_len` readable bytes, and
+/// `out_json` points to a writable buffer.
+#[no_mangle]
+pub unsafe extern "C" fn mobiscale_verify_proof(
+    guest: u32,
+    receipt: *const u8,
+    receipt_len: usize,
+    out_json: *mut MobiscaleBuffer,
+) -> i32 {
+    call(|| {
+        let out_json = out("out_json", out_json)?;
+        let receipt = bytes("receipt", receipt, receipt_len)?.to_vec();
+        let verified = match guest {
+            MOBISCALE_GUEST_ATTESTATION => json(&verify_attestation_proof(receipt, None)?)?,
+            MOBISCALE_GUEST_ASSERTION => json(&verify_assertion_proof(receipt, None)?)?,
+            other => return Err(invalid(format!("unknown guest {other}"))),
+        };
+        *out_json = MobiscaleBuffer::new(verified);
+        Ok(())
+    })
+}
+
+/// Writes the `BuildInfo` of the library as JSON to `out_json`.
+///
+/// # Safety
+/// `out_json` points to a writable buffer.
+#[no_mangle]
+pub unsafe extern "C" fn mobiscale_build_info(out_json: *mut MobiscaleBuffer) -> i32 {
+    call(|| {
+        let out_json = out("out_json", out_json)?;
+        *out_json = MobiscaleBuffer::new(json(&get_build_info())?);
+        Ok(())
+    })
+}
+
+/// Writes the UTF-8 message of the last failed call on this thread to `out_message`,
+/// without a terminating NUL; empty if none failed yet.
+///
+/// # Safety
+/// `out_message` points to a writable buffer.
+#[no_mangle]
+pub unsafe extern "C" fn mobiscale_last_error(out_message: *mut MobiscaleBuffer) -> i32 {
+    // Not through `call`, which would overwrite the message on failure.
+    let Some(out_message) = optional_out(out_message) else {
+        return MOBISCALE_ERR_INVALID_ARGUMENT;
+    };
+    *out_message = MobiscaleBuffer::new(LAST_ERROR.with(|last| last.borrow().clone().into_bytes()));
+    MOBISCALE_OK
+}
+
+/// Releases a buffer the library wrote. Freeing an empty buffer does nothing.
+///
+/// # Safety
+/// `buffer` was written by this library and not freed before.
+#[no_mangle]
+pub unsafe extern "C" fn mobiscale_free_buffer(buffer: MobiscaleBuffer) {
+    if !buffer.data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(buffer.data, buffer.len)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Takes the contents of `buffer` and frees it.
+    fn take(buffer: MobiscaleBuffer) -> Vec<u8> {
+        let bytes = unsafe { bytes("buffer", buffer.data, buffer.len) }.ok().unwrap().to_vec();
+        unsafe { mobiscale_free_buffer(buffer) };
+        bytes
+    }
+
+    fn last_error() -> String {
+        let mut message = MobiscaleBuffer::EMPTY;
+        assert_eq!(unsafe { mobiscale_last_error(&mut message) }, MOBISCALE_OK);
+        String::from_utf8(take(message)).unwrap()
+    }
+
+    #[test]
+    fn panics_become_an_error_code() {
+        assert_eq!(call(|| panic!("boom")), MOBISCALE_ERR_PANIC);
+        assert_eq!(last_error(), "panic: boom");
+
+        let code = call(|| {
+            let index = std::hint::black_box(3);
+            let _ = [0u8; 2][index];
+            Ok(())
+        });
+        assert_eq!(code, MOBISCALE_ERR_PANIC);
+        assert!(last_error().contains("index out of bounds"));
+    }
+
+    #[test]
+    fn failures_keep_the_stable_codes_and_their_message() {
+        let code = call(|| Err(Risc0Error::InvalidReceipt("truncated".into()).into()));
+        assert_eq!(code, 2004);
+        assert_eq!(last_error(), "Invalid receipt: truncated");
+
+        // A later success leaves the message of the last failure.
+        assert_eq!(call(|| Ok(())), MOBISCALE_OK);
+        assert_eq!(last_error(), "Invalid receipt: truncated");
+    }
+
+    #[test]
+    fn outputs_are_emptied_first_and_checked_for_null() {
+        let mut json = MobiscaleBuffer { data: ptr::NonNull::dangling().as_ptr(), len: 7 };
+        let receipt = [0xde, 0xad];
+        let code = unsafe { mobiscale_verify_proof(MOBISCALE_GUEST_ASSERTION, receipt.as_ptr(), 2, &mut json) };
+        assert_eq!(code, 2004);
+        assert!(json.data.is_null() && json.len == 0);
+
+        let code = unsafe { mobiscale_verify_proof(MOBISCALE_GUEST_ASSERTION, receipt.as_ptr(), 2, ptr::null_mut()) };
+        assert_eq!(code, MOBISCALE_ERR_INVALID_ARGUMENT);
+        assert_eq!(last_error(), "out_json is null");
+    }
+
+    #[test]
+    fn inputs_are_checked_before_use() {
+        let mut json = MobiscaleBuffer::EMPTY;
+        assert_eq!(unsafe { mobiscale_verify_proof(7, ptr::null(), 0, &mut json) }, MOBISCALE_ERR_INVALID_ARGUMENT);
+        assert_eq!(last_error(), "unknown guest 7");
+        let code = unsafe { mobiscale_verify_proof(MOBISCALE_GUEST_ASSERTION, ptr::null(), 4, &mut json) };
+        assert_eq!(code, MOBISCALE_ERR_INVALID_ARGUMENT);
+        assert_eq!(last_error(), "receipt is null");
+
+        let mut receipt = MobiscaleBuffer::EMPTY;
+        let invalid_utf8 = c"\xff";
+        let code = unsafe {
+            mobiscale_prove_assertion(
+                ptr::null(),
+                0,
+                ptr::null(),
+                0,
+                invalid_utf8.as_ptr(),
+                ptr::null(),
+                0,
+                0,
+                ptr::null(),
+                &mut receipt,
+                ptr::null_mut(),
+            )
+        };
+        assert_eq!(code, MOBISCALE_ERR_INVALID_ARGUMENT);
+        assert_eq!(last_error(), "app_id is not UTF-8");
+    }
+
+    #[test]
+    fn build_info_is_the_json_shape() {
+        let mut json = MobiscaleBuffer::EMPTY;
+        assert_eq!(unsafe { mobiscale_build_info(&mut json) }, MOBISCALE_OK);
+        let info: crate::BuildInfo = serde_json::from_slice(&take(json)).unwrap();
+        assert_eq!(info, get_build_info());
+    }
+}
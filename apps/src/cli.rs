@@ -0,0 +1,317 @@
+//! Small helpers shared by the command-line binaries in `src/bin`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use appattest_rs::sensitive::base64_problem;
+use base64::{engine::general_purpose, Engine};
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use p256::pkcs8::{DecodePublicKey, EncodePublicKey, LineEnding};
+use serde::Deserialize;
+
+use crate::{decode_attestation_journal, deserialize_receipt, AssertionRequest, AttestationRequest, Policy};
+
+/// Error type for the binaries: a message that is printed before exiting non-zero.
+pub type CliResult<T> = Result<T, String>;
+
+/// Returns the contents of `value` if it names an existing file, or `value` itself otherwise.
+///
+/// Surrounding whitespace is trimmed so files written with a trailing newline work as-is.
+pub fn file_or_inline(value: &str) -> CliResult<String> {
+    let path = Path::new(value);
+    if path.is_file() {
+        let contents = fs::read_to_string(path).map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+        Ok(contents.trim().to_string())
+    } else {
+        Ok(value.trim().to_string())
+    }
+}
+
+/// Creates `dir` (and parents) if needed.
+pub fn ensure_dir(dir: &Path) -> CliResult<()> {
+    fs::create_dir_all(dir).map_err(|e| format!("failed to create {}: {e}", dir.display()))
+}
+
+/// Writes `bytes` to `dir/name`, refusing to replace an existing file unless `force` is set.
+pub fn write_output(dir: &Path, name: &str, bytes: &[u8], force: bool) -> CliResult<PathBuf> {
+    write_file(&dir.join(name), bytes, force)
+}
+
+/// Writes `bytes` to `path`, refusing to replace an existing file unless `force` is set.
+pub fn write_file(path: &Path, bytes: &[u8], force: bool) -> CliResult<PathBuf> {
+    if path.exists() && !force {
+        return Err(format!("{} already exists (pass --force to overwrite)", path.display()));
+    }
+    fs::write(path, bytes).map_err(|e| format!("failed to write {}: {e}", path.display()))?;
+    Ok(path.to_path_buf())
+}
+
+/// Accepts a PEM public key as-is, or converts a hex uncompressed SEC1 P-256 point into PEM.
+pub fn public_key_pem(value: &str) -> CliResult<String> {
+    if value.starts_with("-----BEGIN") {
+        return Ok(value.to_string());
+    }
+    let sec1 = hex::decode(value.trim_start_matches("0x")).map_err(|e| format!("public key is neither PEM nor hex: {e}"))?;
+    let key = p256::PublicKey::from_sec1_bytes(&sec1).map_err(|_| "public key is not a valid P-256 point".to_string())?;
+    key.to_public_key_pem(LineEnding::LF).map_err(|e| e.to_string())
+}
+
+/// Accepts a hex uncompressed SEC1 P-256 point as-is, or converts a PEM public key into one.
+pub fn public_key_sec1(value: &str) -> CliResult<Vec<u8>> {
+    let key = if value.starts_with("-----BEGIN") {
+        p256::PublicKey::from_public_key_pem(value).map_err(|e| format!("invalid PEM public key: {e}"))?
+    } else {
+        let sec1 = hex::decode(value.trim_start_matches("0x"))
+            .map_err(|e| format!("public key is neither PEM nor hex: {e}"))?;
+        p256::PublicKey::from_sec1_bytes(&sec1).map_err(|_| "public key is not a valid P-256 point".to_string())?
+    };
+    Ok(key.to_encoded_point(false).as_bytes().to_vec())
+}
+
+/// Builds the prover's request from the textual forms the binaries take: base64 assertion
+/// and client data, and the serialized receipt of the key's attestation.
+pub fn assertion_request(
+    assertion_b64: &str,
+    client_data_b64: &str,
+    app_id: &str,
+    attestation_receipt: Vec<u8>,
+    previous_counter: Option<u32>,
+    session_nonce: [u8; 32],
+) -> CliResult<AssertionRequest> {
+    Ok(AssertionRequest {
+        assertion: decode_base64("assertion", assertion_b64)?,
+        client_data: decode_base64("client data", client_data_b64)?,
+        app_id: app_id.to_string(),
+        attestation_receipt,
+        previous_counter,
+        session_nonce: session_nonce.to_vec(),
+    })
+}
+
+/// Parses a hex session nonce, which must be exactly 32 bytes.
+pub fn session_nonce(value: &str) -> CliResult<[u8; 32]> {
+    let bytes = hex::decode(value.trim().trim_start_matches("0x"))
+        .map_err(|e| format!("session nonce is not hex: {e}"))?;
+    bytes
+        .try_into()
+        .map_err(|bytes: Vec<u8>| format!("session nonce is {} bytes, expected 32", bytes.len()))
+}
+
+/// Returns the SEC1 public key an attestation receipt's journal commits to.
+pub fn attested_public_key(attestation_receipt: &[u8]) -> CliResult<Vec<u8>> {
+    let receipt = deserialize_receipt(attestation_receipt).map_err(|e| e.to_string())?;
+    let journal = decode_attestation_journal(&receipt.journal.bytes).map_err(|e| e.to_string())?;
+    Ok(journal.public_key.to_vec())
+}
+
+/// Builds the prover's request from base64 attestation, challenge and key ID.
+pub fn attestation_request(
+    attestation_b64: &str,
+    challenge_b64: &str,
+    app_id: &str,
+    key_id_b64: &str,
+    timestamp: i64,
+) -> CliResult<AttestationRequest> {
+    Ok(AttestationRequest {
+        attestation: decode_base64("attestation", attestation_b64)?,
+        challenge: decode_base64("challenge", challenge_b64)?,
+        app_id: app_id.to_string(),
+        key_id: decode_base64("key ID", key_id_b64)?,
+        timestamp,
+        time_token: None,
+    })
+}
+
+/// Reads the recorded attestation from a fixtures directory laid out like `apps/fixtures`,
+/// checked at the time it was recorded.
+pub fn recorded_attestation(dir: &Path) -> CliResult<AttestationRequest> {
+    let read = |name: &str| {
+        let path = dir.join(name);
+        fs::read_to_string(&path)
+            .map(|s| s.trim().to_string())
+            .map_err(|e| format!("failed to read {}: {e}", path.display()))
+    };
+    let recorded_at = read("recorded_at.txt")?
+        .parse()
+        .map_err(|e| format!("invalid recorded_at.txt: {e}"))?;
+    attestation_request(
+        &read("attestation.b64")?,
+        &read("challenge.b64")?,
+        &read("app_id.txt")?,
+        &read("key_id.b64")?,
+        recorded_at,
+    )
+}
+
+fn decode_base64(what: &str, value: &str) -> CliResult<Vec<u8>> {
+    general_purpose::STANDARD
+        .decode(value.trim())
+        .map_err(|e| format!("{what} is not base64: {}", base64_problem(&e)))
+}
+
+/// An assertion stored as JSON, the input format of `batch` and `bench`.
+#[derive(Deserialize)]
+pub struct AssertionFile {
+    /// Base64 CBOR assertion.
+    pub assertion: String,
+    /// Base64 client data.
+    pub client_data: String,
+    pub app_id: String,
+    /// Receipt of the key's attestation, relative to the JSON file.
+    pub attestation_receipt: PathBuf,
+    /// Absent before the key's first assertion.
+    #[serde(default)]
+    pub previous_counter: Option<u32>,
+    /// Hex session nonce; all zeros when absent.
+    #[serde(default)]
+    pub session_nonce: Option<String>,
+    #[serde(skip)]
+    dir: PathBuf,
+}
+
+impl AssertionFile {
+    pub fn read(path: &Path) -> CliResult<Self> {
+        let text = fs::read_to_string(path).map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+        let mut file: Self =
+            serde_json::from_str(&text).map_err(|e| format!("malformed input {}: {e}", path.display()))?;
+        file.dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        Ok(file)
+    }
+
+    /// Reads the attestation receipt the file points at.
+    pub fn attestation_receipt(&self) -> CliResult<Vec<u8>> {
+        let path = self.dir.join(&self.attestation_receipt);
+        fs::read(&path).map_err(|e| format!("failed to read {}: {e}", path.display()))
+    }
+
+    pub fn request(&self) -> CliResult<AssertionRequest> {
+        assertion_request(
+            &self.assertion,
+            &self.client_data,
+            &self.app_id,
+            self.attestation_receipt()?,
+            self.previous_counter,
+            self.session_nonce.as_deref().map_or(Ok([0; 32]), session_nonce)?,
+        )
+    }
+}
+
+/// Parses a `major.minor.patch` appattest-rs version.
+pub fn appattest_version(value: &str) -> CliResult<(u8, u8, u8)> {
+    let parts: Vec<u8> = value
+        .split('.')
+        .map(|part| part.parse().map_err(|_| format!("{value:?} is not a major.minor.patch version")))
+        .collect::<CliResult<_>>()?;
+    match parts[..] {
+        [major, minor, patch] => Ok((major, minor, patch)),
+        _ => Err(format!("{value:?} is not a major.minor.patch version")),
+    }
+}
+
+/// The `policy` object of the journals the binaries print.
+pub fn policy_json(policy: &Policy) -> serde_json::Value {
+    let (major, minor, patch) = policy.appattest_version;
+    serde_json::json!({
+        "appattest_version": format!("{major}.{minor}.{patch}"),
+        "counter_policy": policy.counter_policy,
+        "low_s": policy.low_s,
+        "production": policy.environments & Policy::PRODUCTION != 0,
+        "development": policy.environments & Policy::DEVELOPMENT != 0,
+    })
+}
+
+/// Current Unix time in seconds.
+pub fn now_unix() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn appattest_versions_parse() {
+        assert_eq!(appattest_version("0.12.3"), Ok((0, 12, 3)));
+        assert!(appattest_version("0.12").is_err());
+        assert!(appattest_version("0.x.1").is_err());
+    }
+
+    #[test]
+    fn inline_values_are_returned_trimmed() {
+        assert_eq!(file_or_inline(" b2E= \n").unwrap(), "b2E=");
+    }
+
+    #[test]
+    fn existing_files_are_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("input.b64");
+        fs::write(&path, "b2E=\n").unwrap();
+        assert_eq!(file_or_inline(path.to_str().unwrap()).unwrap(), "b2E=");
+    }
+
+    #[test]
+    fn hex_public_keys_are_converted_to_pem() {
+        let hex = "0485e322caa0f981bc18cd53574f1dc761c139d15030a36b1b249cc158c822c4506b1574b6b1c5b5bfaf8e2a1dfe3deaa87c8802d863c8aac23aa990560a8a56b4";
+        let pem = public_key_pem(hex).unwrap();
+        assert!(pem.starts_with("-----BEGIN PUBLIC KEY-----"));
+        assert_eq!(public_key_pem(&pem).unwrap(), pem);
+        assert!(public_key_pem("04abcd").is_err());
+    }
+
+    #[test]
+    fn pem_public_keys_are_converted_to_sec1() {
+        let hex = "0485e322caa0f981bc18cd53574f1dc761c139d15030a36b1b249cc158c822c4506b1574b6b1c5b5bfaf8e2a1dfe3deaa87c8802d863c8aac23aa990560a8a56b4";
+        let pem = public_key_pem(hex).unwrap();
+        assert_eq!(hex::encode(public_key_sec1(&pem).unwrap()), hex);
+        assert_eq!(hex::encode(public_key_sec1(hex).unwrap()), hex);
+    }
+
+    #[test]
+    fn assertion_requests_decode_base64_inputs() {
+        let request = assertion_request("omE=", "eCA9IDE1", "app", vec![1, 2], Some(3), [4; 32]).unwrap();
+        assert_eq!(request.assertion, vec![0xa2, 0x61]);
+        assert_eq!(request.client_data, b"x = 15");
+        assert_eq!(request.attestation_receipt, vec![1, 2]);
+        assert_eq!(request.previous_counter, Some(3));
+        assert_eq!(request.session_nonce, vec![4; 32]);
+        assert!(assertion_request("not base64!", "eCA9IDE1", "app", vec![], Some(3), [0; 32]).is_err());
+    }
+
+    #[test]
+    fn session_nonces_are_32_hex_bytes() {
+        assert_eq!(session_nonce(&"ab".repeat(32)).unwrap(), [0xab; 32]);
+        assert!(session_nonce(&"ab".repeat(16)).unwrap_err().contains("16 bytes"));
+        assert!(session_nonce("zz").is_err());
+    }
+
+    #[test]
+    fn assertion_files_resolve_the_receipt_next_to_them() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("attestation")).unwrap();
+        fs::write(dir.path().join("attestation/receipt.bin"), b"receipt").unwrap();
+        let path = dir.path().join("input.json");
+        fs::write(
+            &path,
+            r#"{"assertion": "omE=", "client_data": "eCA9IDE1", "app_id": "app", "attestation_receipt": "attestation/receipt.bin"}"#,
+        )
+        .unwrap();
+
+        let request = AssertionFile::read(&path).unwrap().request().unwrap();
+        assert_eq!(request.attestation_receipt, b"receipt");
+        assert_eq!(request.previous_counter, None);
+        assert_eq!(request.session_nonce, vec![0; 32]);
+        assert!(attested_public_key(&request.attestation_receipt).is_err());
+    }
+
+    #[test]
+    fn outputs_are_not_overwritten_without_force() {
+        let dir = tempfile::tempdir().unwrap();
+        write_output(dir.path(), "receipt.bin", b"one", false).unwrap();
+        assert!(write_output(dir.path(), "receipt.bin", b"two", false).is_err());
+        write_output(dir.path(), "receipt.bin", b"two", true).unwrap();
+        assert_eq!(fs::read(dir.path().join("receipt.bin")).unwrap(), b"two");
+    }
+}
@@ -0,0 +1,169 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use risc0_zkvm::ReceiptKind;
+
+/// Coarse classification of prover failures, used to decide whether a retry can help.
+#[derive(uniffi::Enum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// The prover could not allocate memory; usually clears once the app is under less pressure.
+    ResourceExhausted,
+    /// Anything else that went wrong while proving.
+    ProverFailure,
+    /// Input, serialization or journal problems that will fail the same way every time.
+    Permanent,
+}
+
+/// How often, and after which errors, a failed proof is attempted again.
+#[derive(uniffi::Record, Clone, Debug, PartialEq)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one. `0` and `1` both mean "no retries".
+    pub max_attempts: u32,
+    /// Pause before the first retry; doubled for every further retry and jittered by up to 25%.
+    pub backoff_ms: u64,
+    /// Error classes that are worth retrying. `Permanent` errors are never retried.
+    pub retry_on: Vec<ErrorClass>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            backoff_ms: 0,
+            retry_on: Vec::new(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub(crate) fn should_retry(&self, attempt: u32, class: ErrorClass) -> bool {
+        class != ErrorClass::Permanent && attempt < self.max_attempts && self.retry_on.contains(&class)
+    }
+
+    /// Delay before retry number `retry` (starting at 1).
+    pub(crate) fn backoff(&self, retry: u32) -> Duration {
+        backoff(self.backoff_ms, retry)
+    }
+}
+
+/// `backoff_ms`, doubled for every retry after the first and jittered by up to 25%.
+pub(crate) fn backoff(backoff_ms: u64, retry: u32) -> Duration {
+    let base = backoff_ms.saturating_mul(1u64 << retry.saturating_sub(1).min(16));
+    Duration::from_millis(base.saturating_add(jitter(base / 4)))
+}
+
+/// Which kind of receipt the prover produces.
+#[derive(uniffi::Enum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ProofKind {
+    /// One receipt per segment: quickest to produce, largest to ship.
+    #[default]
+    Composite,
+    /// All segments recursively compressed into a single constant-size receipt.
+    Succinct,
+}
+
+impl ProofKind {
+    pub(crate) fn receipt_kind(self) -> ReceiptKind {
+        match self {
+            ProofKind::Composite => ReceiptKind::Composite,
+            ProofKind::Succinct => ReceiptKind::Succinct,
+        }
+    }
+}
+
+/// How long a prover waits while its [`crate::HostConditions`] ask it to pause.
+#[derive(uniffi::Record, Clone, Debug, PartialEq)]
+pub struct PausePolicy {
+    /// Longest pause before proving fails with [`crate::Risc0Error::PausedTooLong`].
+    pub max_pause_ms: u64,
+    /// Pause between checks of whether the host still wants the prover paused.
+    pub poll_ms: u64,
+}
+
+impl Default for PausePolicy {
+    fn default() -> Self {
+        Self {
+            max_pause_ms: 120_000,
+            poll_ms: 500,
+        }
+    }
+}
+
+/// Configuration shared by every proof produced through a [`crate::Prover`].
+#[derive(uniffi::Record, Clone, Debug, PartialEq)]
+pub struct ProverConfig {
+    pub retry: RetryPolicy,
+    pub proof_kind: ProofKind,
+    pub pause: PausePolicy,
+    /// Cycles a guest run may take before the executor stops it with
+    /// [`crate::Risc0Error::CycleLimitExceeded`]; [`crate::DEFAULT_MAX_CYCLES`] by default.
+    pub max_cycles: u64,
+}
+
+impl Default for ProverConfig {
+    fn default() -> Self {
+        Self {
+            retry: RetryPolicy::default(),
+            proof_kind: ProofKind::default(),
+            pause: PausePolicy::default(),
+            max_cycles: crate::DEFAULT_MAX_CYCLES,
+        }
+    }
+}
+
+/// Pseudo-random value in `0..=max`, good enough to spread out retries of concurrent provers.
+fn jitter(max: u64) -> u64 {
+    if max == 0 {
+        return 0;
+    }
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos() as u64)
+        .unwrap_or(0);
+    nanos % (max + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            backoff_ms: 100,
+            retry_on: vec![ErrorClass::ResourceExhausted],
+        }
+    }
+
+    #[test]
+    fn default_policy_never_retries() {
+        let policy = RetryPolicy::default();
+        assert!(!policy.should_retry(1, ErrorClass::ResourceExhausted));
+        assert!(!policy.should_retry(1, ErrorClass::ProverFailure));
+    }
+
+    #[test]
+    fn retries_only_listed_classes_up_to_the_limit() {
+        let policy = policy();
+        assert!(policy.should_retry(1, ErrorClass::ResourceExhausted));
+        assert!(policy.should_retry(2, ErrorClass::ResourceExhausted));
+        assert!(!policy.should_retry(3, ErrorClass::ResourceExhausted));
+        assert!(!policy.should_retry(1, ErrorClass::ProverFailure));
+    }
+
+    #[test]
+    fn permanent_errors_are_never_retried() {
+        let mut policy = policy();
+        policy.retry_on.push(ErrorClass::Permanent);
+        assert!(!policy.should_retry(1, ErrorClass::Permanent));
+    }
+
+    #[test]
+    fn backoff_doubles_with_bounded_jitter() {
+        let policy = policy();
+        for retry in 1..=3u32 {
+            let base = 100 * 2u64.pow(retry - 1);
+            let delay = policy.backoff(retry).as_millis() as u64;
+            assert!(delay >= base && delay <= base + base / 4, "retry {retry}: {delay}ms");
+        }
+    }
+}
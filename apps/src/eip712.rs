@@ -0,0 +1,280 @@
+//! EIP-712 signatures over proof bundles, so that a relayer can submit a proof on-chain and
+//! pay the gas while the contract still learns which owner sent it.
+//!
+//! The owner signs a [`RelayBundle`] in the domain `{name: "MobiScale", version: "1",
+//! chainId, verifyingContract}`, where `verifyingContract` is the app contract that consumes
+//! the proof. The contract must hash the same way:
+//!
+//! ```text
+//! RelayBundle(bytes32 imageId,bytes32 claimDigest,bytes32 journalDigest,uint256 nonce,uint256 deadline)
+//! ```
+//!
+//! Neither the nonce nor the deadline is checked here; they are for the contract, which
+//! should accept each nonce of a signer once and nothing after the deadline.
+
+use alloy::primitives::{keccak256, Address, Signature, B256, U256};
+use alloy::signers::local::PrivateKeySigner;
+use alloy::signers::SignerSync;
+use alloy::sol;
+use alloy::sol_types::{eip712_domain, SolStruct};
+use risc0_zkvm::sha::Digestible;
+use risc0_zkvm::Receipt;
+
+use crate::Risc0Error;
+
+sol! {
+    /// A proof as the owner authorizes its relayed submission.
+    #[derive(Debug, PartialEq, Eq)]
+    struct RelayBundle {
+        bytes32 imageId;
+        bytes32 claimDigest;
+        bytes32 journalDigest;
+        uint256 nonce;
+        uint256 deadline;
+    }
+}
+
+impl RelayBundle {
+    /// The bundle of `receipt`, to be submitted as `nonce` by the Unix time `deadline`.
+    pub fn for_receipt(receipt: &Receipt, nonce: U256, deadline: U256) -> Result<Self, Risc0Error> {
+        let claim = receipt.claim().map_err(|e| Risc0Error::InvalidReceipt(format!("Receipt has no claim: {e}")))?;
+        let image_id =
+            claim.as_value().map_err(|_| Risc0Error::InvalidReceipt("Receipt claim is pruned".into()))?.pre.digest();
+        Ok(RelayBundle {
+            imageId: B256::from_slice(image_id.as_bytes()),
+            claimDigest: B256::from_slice(claim.digest().as_bytes()),
+            journalDigest: B256::from_slice(receipt.journal.digest().as_bytes()),
+            nonce,
+            deadline,
+        })
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum Eip712Error {
+    #[error("invalid private key: {0}")]
+    InvalidPrivateKey(String),
+    #[error("invalid signature: {0}")]
+    InvalidSignature(String),
+}
+
+/// A secp256k1 signature with its recovery ID, as Ethereum tooling exchanges them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Eip712Signature {
+    pub r: [u8; 32],
+    pub s: [u8; 32],
+    /// 27 or 28.
+    pub v: u8,
+}
+
+impl Eip712Signature {
+    /// `r || s || v`, the 65 bytes that ethers.js' `signTypedData` returns and Solidity's
+    /// `ECDSA.recover` takes.
+    pub fn to_bytes(&self) -> [u8; 65] {
+        let mut bytes = [0; 65];
+        bytes[..32].copy_from_slice(&self.r);
+        bytes[32..64].copy_from_slice(&self.s);
+        bytes[64] = self.v;
+        bytes
+    }
+
+    /// Reads [`Eip712Signature::to_bytes`]; `v` may also be given as 0 or 1.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Eip712Error> {
+        if bytes.len() != 65 {
+            return Err(Eip712Error::InvalidSignature(format!("expected 65 bytes, got {}", bytes.len())));
+        }
+        let v = match bytes[64] {
+            v @ (0 | 1) => v + 27,
+            v @ (27 | 28) => v,
+            v => return Err(Eip712Error::InvalidSignature(format!("v is {v}"))),
+        };
+        Ok(Eip712Signature { r: bytes[..32].try_into().unwrap(), s: bytes[32..64].try_into().unwrap(), v })
+    }
+
+    fn to_alloy(self) -> Signature {
+        Signature::new(U256::from_be_bytes(self.r), U256::from_be_bytes(self.s), self.v == 28)
+    }
+}
+
+impl From<Signature> for Eip712Signature {
+    fn from(signature: Signature) -> Self {
+        Eip712Signature { r: signature.r().to_be_bytes(), s: signature.s().to_be_bytes(), v: 27 + signature.v() as u8 }
+    }
+}
+
+/// The EIP-712 struct hash of `bundle`, which [`sign_bundle_eip712`] signs.
+pub fn bundle_typed_data_digest(bundle: &RelayBundle) -> [u8; 32] {
+    bundle.eip712_hash_struct().0
+}
+
+/// `keccak256(0x1901 || domainSeparator || structHash)`, the hash that is actually signed.
+fn signing_hash(bundle_digest: [u8; 32], chain_id: u64, verifying_contract: [u8; 20]) -> B256 {
+    let domain = eip712_domain! {
+        name: "MobiScale",
+        version: "1",
+        chain_id: chain_id,
+        verifying_contract: Address::from(verifying_contract),
+    };
+    keccak256([&[0x19, 0x01], domain.hash_struct().as_slice(), &bundle_digest].concat())
+}
+
+/// Signs the bundle with struct hash `bundle_digest`, see [`bundle_typed_data_digest`], for
+/// the contract `verifying_contract` on `chain_id`, with the 32-byte secp256k1 `private_key`.
+///
+/// Signatures are deterministic (RFC 6979) and have a low `s`, as ethers.js produces them.
+pub fn sign_bundle_eip712(
+    bundle_digest: [u8; 32],
+    chain_id: u64,
+    verifying_contract: [u8; 20],
+    private_key: Vec<u8>,
+) -> Result<Eip712Signature, Eip712Error> {
+    // Checked here since shorter keys would be zero-padded.
+    if private_key.len() != 32 {
+        return Err(Eip712Error::InvalidPrivateKey(format!("expected 32 bytes, got {}", private_key.len())));
+    }
+    let signer =
+        PrivateKeySigner::from_slice(&private_key).map_err(|e| Eip712Error::InvalidPrivateKey(e.to_string()))?;
+    let signature = signer
+        .sign_hash_sync(&signing_hash(bundle_digest, chain_id, verifying_contract))
+        .map_err(|e| Eip712Error::InvalidPrivateKey(e.to_string()))?;
+    Ok(signature.into())
+}
+
+/// The address that made `signature` over the bundle with struct hash `bundle_digest` for
+/// `verifying_contract` on `chain_id`.
+///
+/// A signature over anything else recovers to some other address rather than failing, so
+/// compare the result with the expected owner. Signatures with a high `s` are rejected, as
+/// OpenZeppelin's `ECDSA.recover` rejects them.
+pub fn recover_bundle_signer(
+    bundle_digest: [u8; 32],
+    chain_id: u64,
+    verifying_contract: [u8; 20],
+    signature: &Eip712Signature,
+) -> Result<[u8; 20], Eip712Error> {
+    let signature = signature.to_alloy();
+    if signature.normalize_s().is_some() {
+        return Err(Eip712Error::InvalidSignature("s is in the upper half of the curve order".into()));
+    }
+    let signer = signature
+        .recover_address_from_prehash(&signing_hash(bundle_digest, chain_id, verifying_contract))
+        .map_err(|e| Eip712Error::InvalidSignature(e.to_string()))?;
+    Ok(signer.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+
+    /// Typed data with its hashes and signature, see `fixtures/README.md`.
+    fn fixture() -> Value {
+        serde_json::from_str(include_str!("../fixtures/eip712/bundle.json")).unwrap()
+    }
+
+    fn bytes(value: &Value) -> Vec<u8> {
+        hex::decode(value.as_str().unwrap().trim_start_matches("0x")).unwrap()
+    }
+
+    fn bundle(fixture: &Value) -> RelayBundle {
+        let message = &fixture["message"];
+        let word = |name: &str| B256::from_slice(&bytes(&message[name]));
+        let number = |name: &str| message[name].as_str().unwrap().parse::<U256>().unwrap();
+        RelayBundle {
+            imageId: word("imageId"),
+            claimDigest: word("claimDigest"),
+            journalDigest: word("journalDigest"),
+            nonce: number("nonce"),
+            deadline: number("deadline"),
+        }
+    }
+
+    fn domain(fixture: &Value) -> (u64, [u8; 20]) {
+        let domain = &fixture["domain"];
+        (domain["chainId"].as_u64().unwrap(), bytes(&domain["verifyingContract"]).try_into().unwrap())
+    }
+
+    #[test]
+    fn matches_ethers() {
+        let fixture = fixture();
+        let (chain_id, contract) = domain(&fixture);
+        let digest = bundle_typed_data_digest(&bundle(&fixture));
+        assert_eq!(digest.to_vec(), bytes(&fixture["structHash"]));
+        assert_eq!(signing_hash(digest, chain_id, contract).to_vec(), bytes(&fixture["digest"]));
+
+        let signature = sign_bundle_eip712(digest, chain_id, contract, bytes(&fixture["privateKey"])).unwrap();
+        assert_eq!(signature.to_bytes().to_vec(), bytes(&fixture["signature"]));
+        let signer = recover_bundle_signer(digest, chain_id, contract, &signature).unwrap();
+        assert_eq!(signer.to_vec(), bytes(&fixture["signer"]));
+    }
+
+    #[test]
+    fn signatures_are_bound_to_the_bundle_and_domain() {
+        let fixture = fixture();
+        let (chain_id, contract) = domain(&fixture);
+        let signer = bytes(&fixture["signer"]);
+        let signature = Eip712Signature::from_bytes(&bytes(&fixture["signature"])).unwrap();
+        let mut later = bundle(&fixture);
+        later.deadline += U256::from(1);
+
+        let recovered = [
+            recover_bundle_signer(bundle_typed_data_digest(&later), chain_id, contract, &signature),
+            recover_bundle_signer(bundle_typed_data_digest(&bundle(&fixture)), 1, contract, &signature),
+            recover_bundle_signer(bundle_typed_data_digest(&bundle(&fixture)), chain_id, [0; 20], &signature),
+        ];
+        for recovered in recovered {
+            // Either the signature does not recover at all, or to someone else.
+            assert_ne!(recovered.map(|address| address.to_vec()), Ok(signer.clone()));
+        }
+    }
+
+    #[test]
+    fn high_s_and_malformed_signatures_are_rejected() {
+        let fixture = fixture();
+        let (chain_id, contract) = domain(&fixture);
+        let digest = bundle_typed_data_digest(&bundle(&fixture));
+        let signature = Eip712Signature::from_bytes(&bytes(&fixture["signature"])).unwrap();
+
+        // The same signature with `s` mirrored to `n - s` and the parity flipped.
+        let n = U256::from_str_radix("fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141", 16).unwrap();
+        let high = Eip712Signature {
+            s: (n - U256::from_be_bytes(signature.s)).to_be_bytes(),
+            v: if signature.v == 27 { 28 } else { 27 },
+            ..signature
+        };
+        assert!(matches!(
+            recover_bundle_signer(digest, chain_id, contract, &high),
+            Err(Eip712Error::InvalidSignature(_))
+        ));
+
+        assert!(Eip712Signature::from_bytes(&signature.to_bytes()[..64]).is_err());
+        let mut bad_v = signature.to_bytes();
+        bad_v[64] = 29;
+        assert!(Eip712Signature::from_bytes(&bad_v).is_err());
+        bad_v[64] = 1;
+        assert_eq!(Eip712Signature::from_bytes(&bad_v).unwrap().v, 28);
+    }
+
+    #[test]
+    fn private_keys_must_be_scalars() {
+        for key in [vec![], vec![1; 31], vec![0; 32], vec![0xff; 32]] {
+            let result = sign_bundle_eip712([0; 32], 1, [0; 20], key);
+            assert!(matches!(result, Err(Eip712Error::InvalidPrivateKey(_))));
+        }
+    }
+
+    #[test]
+    fn bundles_commit_to_the_receipt() {
+        use risc0_zkvm::{FakeReceipt, InnerReceipt, ReceiptClaim};
+        use sha2::{Digest, Sha256};
+
+        let journal = vec![1, 2, 3, 4];
+        let claim = ReceiptClaim::ok(methods::ASSERTION_ID, journal.clone());
+        let receipt = Receipt::new(InnerReceipt::Fake(FakeReceipt::new(claim.clone())), journal.clone());
+        let bundle = RelayBundle::for_receipt(&receipt, U256::from(1), U256::from(2)).unwrap();
+        assert_eq!(bundle.imageId.0, *risc0_zkvm::sha::Digest::from(methods::ASSERTION_ID).as_bytes());
+        assert_eq!(bundle.claimDigest.0, *claim.digest().as_bytes());
+        assert_eq!(bundle.journalDigest.0, <[u8; 32]>::from(Sha256::digest(&journal)));
+    }
+}
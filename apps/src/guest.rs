@@ -0,0 +1,172 @@
+use guest_core::{AssertionInput, AttestationInput, CertChainInput};
+use mobiscale_verifier::ProvenInput;
+use methods::{
+    ASSERTION_ELF, ASSERTION_ID, ATTESTATION_REST_ELF, ATTESTATION_REST_ID, CERT_CHAIN_ELF, CERT_CHAIN_ID,
+};
+use risc0_zkvm::{default_executor, ExecutorEnv, ExecutorEnvBuilder, Receipt, SessionInfo};
+use sha2::{Digest, Sha256};
+
+use crate::Risc0Error;
+
+/// Largest segment the assertion guest is split into and proven with, as log2 of its cycles.
+pub const ASSERTION_MAX_PO2: u32 = 17;
+
+/// Cycles a guest run may take unless configured otherwise, see
+/// [`ProverConfig::max_cycles`](crate::ProverConfig::max_cycles). Far more than `bench`
+/// counts for any guest on the recorded inputs, but finite, so that an input sending a
+/// guest into a runaway execution fails instead of running the device out of memory.
+pub const DEFAULT_MAX_CYCLES: u64 = 1 << 30;
+
+/// The guest programs shipped with the app.
+#[derive(uniffi::Enum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GuestKind {
+    /// Checks an attestation object's certificate chain and nonce.
+    CertChain,
+    /// Checks the rest of the attestation object against a certificate chain receipt.
+    Attestation,
+    Assertion,
+}
+
+impl GuestKind {
+    pub fn image_id(self) -> [u32; 8] {
+        match self {
+            GuestKind::CertChain => CERT_CHAIN_ID,
+            GuestKind::Attestation => ATTESTATION_REST_ID,
+            GuestKind::Assertion => ASSERTION_ID,
+        }
+    }
+
+    /// `cert_chain`, `attestation` or `assertion`, as in the server's API and metrics.
+    pub fn name(self) -> &'static str {
+        match self {
+            GuestKind::CertChain => "cert_chain",
+            GuestKind::Attestation => "attestation",
+            GuestKind::Assertion => "assertion",
+        }
+    }
+
+    pub fn elf(self) -> &'static [u8] {
+        match self {
+            GuestKind::CertChain => CERT_CHAIN_ELF,
+            GuestKind::Attestation => ATTESTATION_REST_ELF,
+            GuestKind::Assertion => ASSERTION_ELF,
+        }
+    }
+}
+
+/// The input of one guest run, as the prove functions write it to the executor.
+#[derive(Clone, Debug)]
+pub enum GuestInput {
+    CertChain(CertChainInput),
+    /// The attestation guest's input and the certificate chain receipt it verifies by composition.
+    Attestation { input: AttestationInput, cert_chain: Receipt },
+    /// The assertion guest's input and the attestation receipt it verifies by composition.
+    Assertion { input: AssertionInput, attestation: Receipt },
+}
+
+impl GuestInput {
+    pub fn kind(&self) -> GuestKind {
+        match self {
+            GuestInput::CertChain(_) => GuestKind::CertChain,
+            GuestInput::Attestation { .. } => GuestKind::Attestation,
+            GuestInput::Assertion { .. } => GuestKind::Assertion,
+        }
+    }
+
+    /// Starts an environment holding the input. Callers may adjust limits before building.
+    ///
+    /// The run is limited to [`DEFAULT_MAX_CYCLES`], and the assertion guest is split into
+    /// segments of at most [`ASSERTION_MAX_PO2`].
+    pub fn env_builder(&self) -> Result<ExecutorEnvBuilder<'static>, Risc0Error> {
+        let mut builder = ExecutorEnv::builder();
+        builder.session_limit(Some(DEFAULT_MAX_CYCLES));
+        let written = match self {
+            GuestInput::CertChain(input) => builder.write(input),
+            GuestInput::Attestation { input, cert_chain } => {
+                builder.add_assumption(cert_chain.clone());
+                builder.write(input)
+            }
+            GuestInput::Assertion { input, attestation } => {
+                builder.add_assumption(attestation.clone()).segment_limit_po2(ASSERTION_MAX_PO2);
+                builder.write(input)
+            }
+        };
+        written.map_err(env_error)?;
+        Ok(builder)
+    }
+
+    /// `SHA256` of the guest's image ID followed by the input as `env::read` reads it. Equal
+    /// digests mean equal receipts, up to the proof itself: a receipt a guest composes is
+    /// named by the image ID and journal in its input.
+    pub fn digest(&self) -> Result<[u8; 32], Risc0Error> {
+        let words = match self {
+            GuestInput::CertChain(input) => risc0_zkvm::serde::to_vec(input),
+            GuestInput::Attestation { input, .. } => risc0_zkvm::serde::to_vec(input),
+            GuestInput::Assertion { input, .. } => risc0_zkvm::serde::to_vec(input),
+        }
+        .map_err(env_error)?;
+        let mut hasher = Sha256::new();
+        for word in self.kind().image_id().into_iter().chain(words) {
+            hasher.update(word.to_le_bytes());
+        }
+        Ok(hasher.finalize().into())
+    }
+
+    /// The `input_digest` the guest commits for this input, see [`guest_core::digest`].
+    /// Unlike [`GuestInput::digest`] it names the input alone, not the guest build, and is
+    /// what the server and the guests agree on. Fails on a certificate chain journal that
+    /// does not decode.
+    pub fn canonical_digest(&self) -> Result<[u8; 32], Risc0Error> {
+        match self {
+            GuestInput::CertChain(input) => Ok(input.canonical_digest()),
+            GuestInput::Attestation { input, .. } => {
+                input.canonical_digest().map_err(|e| Risc0Error::JournalError(e.to_string()))
+            }
+            GuestInput::Assertion { input, .. } => Ok(input.canonical_digest()),
+        }
+    }
+
+    pub fn env(&self) -> Result<ExecutorEnv<'static>, Risc0Error> {
+        self.env_within(DEFAULT_MAX_CYCLES)
+    }
+
+    /// The environment, with the run limited to `max_cycles`.
+    pub fn env_within(&self, max_cycles: u64) -> Result<ExecutorEnv<'static>, Risc0Error> {
+        self.env_builder()?.session_limit(Some(max_cycles)).build().map_err(env_error)
+    }
+
+    /// Runs the guest without proving. A rejected input fails as it would when proving, with
+    /// [`Risc0Error::GuestRejected`].
+    pub fn execute(&self) -> Result<SessionInfo, Risc0Error> {
+        self.execute_within(DEFAULT_MAX_CYCLES)
+    }
+
+    /// [`GuestInput::execute`], stopping the run with [`Risc0Error::CycleLimitExceeded`]
+    /// after `max_cycles`, as a prover configured with them would.
+    pub fn execute_within(&self, max_cycles: u64) -> Result<SessionInfo, Risc0Error> {
+        execute_guest(self.env_within(max_cycles)?, self.kind()).map_err(|e| e.within_cycle_limit(max_cycles))
+    }
+}
+
+/// The guest's input without the receipt it composes, for
+/// [`mobiscale_verifier::verify_receipt_matches_inputs`].
+impl From<&GuestInput> for ProvenInput {
+    fn from(input: &GuestInput) -> Self {
+        match input {
+            GuestInput::CertChain(input) => ProvenInput::CertChain(input.clone()),
+            GuestInput::Attestation { input, .. } => ProvenInput::Attestation(input.clone()),
+            GuestInput::Assertion { input, .. } => ProvenInput::Assertion(input.clone()),
+        }
+    }
+}
+
+/// Runs `kind` in `env` without proving, mapping its errors like the prover's.
+pub fn execute_guest(env: ExecutorEnv<'_>, kind: GuestKind) -> Result<SessionInfo, Risc0Error> {
+    default_executor()
+        .execute(env, kind.elf())
+        .map_err(|e| Risc0Error::from_prover(&e))
+}
+
+pub(crate) fn env_error(e: impl std::fmt::Display) -> Risc0Error {
+    Risc0Error::ProveError(format!("Failed to create ExecutorEnv: {}", e))
+}
@@ -0,0 +1,74 @@
+//! Hooks through which the app on the device can hold the prover back, e.g. while the phone
+//! is hot, before iOS throttles or kills it.
+//!
+//! The zkVM proves the segments of one guest run without yielding, so a [`crate::Prover`]
+//! consults its [`HostConditions`] before each guest run and each retry: between the two
+//! steps of an attestation, say, but not within one step.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+
+/// Device conditions the prover waits out, implemented by the app.
+#[uniffi::export(with_foreign)]
+pub trait HostConditions: Send + Sync {
+    /// Whether the prover should pause before its next guest run. While it returns true the
+    /// prover checks again every [`PausePolicy::poll_ms`](crate::PausePolicy::poll_ms).
+    fn should_pause(&self) -> bool;
+
+    /// The device's thermal state changed to `level`, `ProcessInfo.ThermalState`'s raw
+    /// value on iOS: 0 nominal, 1 fair, 2 serious, 3 critical. The prover never calls it;
+    /// it is how the app feeds a [`ThermalThrottle`].
+    fn on_thermal_state(&self, level: u8);
+}
+
+/// [`HostConditions`] that pause the prover while the last thermal state the app reported
+/// is at least `pause_at`.
+#[derive(Debug)]
+pub struct ThermalThrottle {
+    pause_at: u8,
+    level: AtomicU8,
+}
+
+impl ThermalThrottle {
+    /// Starts at the nominal state, 0.
+    pub fn new(pause_at: u8) -> Self {
+        Self { pause_at, level: AtomicU8::new(0) }
+    }
+}
+
+/// A [`ThermalThrottle`] for the bindings: the app passes it to
+/// [`Prover::set_host_conditions`](crate::Prover::set_host_conditions) and forwards each
+/// thermal state notification to its `on_thermal_state`.
+#[uniffi::export]
+pub fn thermal_throttle(pause_at: u8) -> Arc<dyn HostConditions> {
+    Arc::new(ThermalThrottle::new(pause_at))
+}
+
+impl HostConditions for ThermalThrottle {
+    fn should_pause(&self) -> bool {
+        self.level.load(Ordering::SeqCst) >= self.pause_at
+    }
+
+    fn on_thermal_state(&self, level: u8) {
+        self.level.store(level, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn throttle_pauses_from_its_level_on() {
+        let throttle = ThermalThrottle::new(2);
+        assert!(!throttle.should_pause());
+        throttle.on_thermal_state(1);
+        assert!(!throttle.should_pause());
+        throttle.on_thermal_state(3);
+        assert!(throttle.should_pause());
+        throttle.on_thermal_state(2);
+        assert!(throttle.should_pause());
+        throttle.on_thermal_state(0);
+        assert!(!throttle.should_pause());
+    }
+}
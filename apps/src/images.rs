@@ -0,0 +1,463 @@
+//! Image IDs a verifier accepts, so that proofs from the previous guest build keep verifying
+//! while the apps in the field upgrade.
+//!
+//! [`crate::verify_assertion_proof`] and [`crate::verify_attestation_proof`] accept this
+//! build's guests only. A [`TrustedImages`] registry accepts any image registered for the
+//! receipt's kind, reports which one verified, and stops accepting an image once the time
+//! the caller passes is past its `deprecated_after`.
+
+use std::sync::{Arc, RwLock};
+
+use guest_core::{ASSERTION_JOURNAL_V9, ATTESTATION_JOURNAL_V7, CERT_CHAIN_JOURNAL_V2};
+use mobiscale_verifier::{ASSERTION_JOURNALS, ATTESTATION_JOURNALS, CERT_CHAIN_JOURNALS};
+use risc0_zkvm::sha::Digest;
+use risc0_zkvm::Receipt;
+
+use crate::{
+    check_expected_policy, decode_assertion_journal, decode_attestation_journal, deserialize_receipt, GuestKind,
+    Risc0Error, SignatureData, VerificationPolicyData, VerifiedAttestation,
+};
+
+/// One guest build a [`TrustedImages`] registry accepts.
+#[derive(uniffi::Record, Clone, Debug, PartialEq, Eq)]
+pub struct TrustedImage {
+    pub kind: GuestKind,
+    pub image_id_hex: String,
+    /// Version byte of the journals the build commits.
+    pub journal_version: u8,
+    /// Unix timestamp (seconds) after which the build's receipts are rejected. `None` for a
+    /// build that is not deprecated.
+    pub deprecated_after: Option<i64>,
+}
+
+impl TrustedImage {
+    fn expired(&self, now: i64) -> bool {
+        self.deprecated_after.is_some_and(|deprecated_after| now > deprecated_after)
+    }
+}
+
+/// A verified assertion receipt's signature data, the image it verified against, the
+/// attestation image its key comes from and the certificate chain image behind that.
+#[derive(uniffi::Record, Clone, Debug, PartialEq)]
+pub struct TrustedAssertion {
+    pub signature: SignatureData,
+    pub image: TrustedImage,
+    pub attestation_image: TrustedImage,
+    pub cert_chain_image: TrustedImage,
+}
+
+/// A verified attestation receipt, the image it verified against and the certificate chain
+/// image its journal relies on.
+#[derive(uniffi::Record, Clone, Debug, PartialEq)]
+pub struct TrustedAttestation {
+    pub attestation: VerifiedAttestation,
+    pub image: TrustedImage,
+    pub cert_chain_image: TrustedImage,
+}
+
+/// The journal version this build decodes for `kind`.
+fn current_journal_version(kind: GuestKind) -> u8 {
+    match kind {
+        GuestKind::CertChain => CERT_CHAIN_JOURNAL_V2,
+        GuestKind::Attestation => ATTESTATION_JOURNAL_V7,
+        GuestKind::Assertion => ASSERTION_JOURNAL_V9,
+    }
+}
+
+/// The journal versions this build decodes for `kind`, oldest first.
+fn supported_journal_versions(kind: GuestKind) -> Vec<u8> {
+    match kind {
+        GuestKind::CertChain => CERT_CHAIN_JOURNALS.supported(),
+        GuestKind::Attestation => ATTESTATION_JOURNALS.supported(),
+        GuestKind::Assertion => ASSERTION_JOURNALS.supported(),
+    }
+}
+
+/// The error for an assertion journal of `version`, which names no certificate chain image.
+fn predates_cert_chain_image_ids(version: u8) -> Risc0Error {
+    Risc0Error::VerificationFailed(format!("assertion journal version {version} predates certificate chain image IDs"))
+}
+
+/// The guest builds whose receipts a verifier accepts, by kind.
+#[derive(uniffi::Object, Debug, Default)]
+pub struct TrustedImages {
+    images: RwLock<Vec<(Digest, TrustedImage)>>,
+}
+
+#[uniffi::export]
+impl TrustedImages {
+    /// Trusts this build's three guests, none of them deprecated.
+    #[uniffi::constructor]
+    pub fn new() -> Arc<Self> {
+        let images = Self::default();
+        for kind in [GuestKind::CertChain, GuestKind::Attestation, GuestKind::Assertion] {
+            images.insert(Digest::from(kind.image_id()), kind, current_journal_version(kind), None);
+        }
+        Arc::new(images)
+    }
+
+    /// Trusts nothing until images are added.
+    #[uniffi::constructor]
+    pub fn empty() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Trusts the `kind` guest with image ID `image_id_hex`, replacing any earlier entry for
+    /// it, e.g. to set its `deprecated_after`.
+    ///
+    /// Fails with [`Risc0Error::UnsupportedJournalVersion`] for a `journal_version` this
+    /// build does not decode for `kind`. The previous build's version is decoded, with the
+    /// fields it did not commit `None`, so that its image can stay trusted while it is phased
+    /// out. An assertion image older than version 9 is refused as
+    /// [`TrustedImages::verify_assertion_proof`] would refuse its receipts.
+    pub fn add_trusted_image(
+        &self,
+        kind: GuestKind,
+        image_id_hex: String,
+        journal_version: u8,
+        deprecated_after: Option<i64>,
+    ) -> Result<(), Risc0Error> {
+        let id = mobiscale_verifier::parse_image_id(&image_id_hex)?;
+        let supported = supported_journal_versions(kind);
+        if !supported.contains(&journal_version) {
+            return Err(Risc0Error::UnsupportedJournalVersion { found: journal_version, supported });
+        }
+        if kind == GuestKind::Assertion && journal_version < ASSERTION_JOURNAL_V9 {
+            return Err(predates_cert_chain_image_ids(journal_version));
+        }
+        self.insert(id, kind, journal_version, deprecated_after);
+        Ok(())
+    }
+
+    /// The trusted images, in the order they were added.
+    pub fn trusted_images(&self) -> Vec<TrustedImage> {
+        self.images.read().unwrap().iter().map(|(_, image)| image.clone()).collect()
+    }
+
+    /// Verifies an assertion receipt against the trusted assertion images, and checks that
+    /// its journal relies on a trusted attestation image and certificate chain image, and
+    /// returns the signature data from its journal. `now` is the Unix time deprecations are
+    /// checked at, and `expected_policy` is checked as by [`crate::verify_assertion_proof`].
+    ///
+    /// A journal older than version 9 names no certificate chain image and is rejected.
+    pub fn verify_assertion_proof(
+        &self,
+        receipt: Vec<u8>,
+        now: i64,
+        expected_policy: Option<VerificationPolicyData>,
+    ) -> Result<TrustedAssertion, Risc0Error> {
+        let (receipt, image) = self.verify(&receipt, GuestKind::Assertion, now)?;
+        let journal = decode_assertion_journal(&receipt.journal.bytes)?;
+        check_expected_policy(journal.policy_hash.as_ref(), expected_policy)?;
+        let attestation_id = Digest::from(journal.attestation_image_id);
+        let attestation_image = self.find(GuestKind::Attestation, |id, _| *id == attestation_id).ok_or_else(|| {
+            Risc0Error::VerificationFailed(format!(
+                "assertion relies on attestation guest {attestation_id}, which is not trusted"
+            ))
+        })?;
+        check_not_expired(&attestation_image, now)?;
+        let cert_chain_id = journal
+            .cert_chain_image_id
+            .map(Digest::from)
+            .ok_or_else(|| predates_cert_chain_image_ids(image.journal_version))?;
+        let cert_chain_image = self.find(GuestKind::CertChain, |id, _| *id == cert_chain_id).ok_or_else(|| {
+            Risc0Error::VerificationFailed(format!(
+                "assertion relies on certificate chain guest {cert_chain_id}, which is not trusted"
+            ))
+        })?;
+        check_not_expired(&cert_chain_image, now)?;
+        Ok(TrustedAssertion { signature: SignatureData::from(&journal), image, attestation_image, cert_chain_image })
+    }
+
+    /// Verifies an attestation receipt against the trusted attestation images, and checks
+    /// that its journal relies on a trusted certificate chain image, deprecations being
+    /// checked at the Unix time `now`, and that it commits `expected_policy` if given.
+    pub fn verify_attestation_proof(
+        &self,
+        receipt: Vec<u8>,
+        now: i64,
+        expected_policy: Option<VerificationPolicyData>,
+    ) -> Result<TrustedAttestation, Risc0Error> {
+        let (receipt, image) = self.verify(&receipt, GuestKind::Attestation, now)?;
+        let journal = decode_attestation_journal(&receipt.journal.bytes)?;
+        check_expected_policy(journal.policy_hash.as_ref(), expected_policy)?;
+        let cert_chain_id = Digest::from(journal.cert_chain_image_id);
+        let cert_chain_image = self.find(GuestKind::CertChain, |id, _| *id == cert_chain_id).ok_or_else(|| {
+            Risc0Error::VerificationFailed(format!(
+                "attestation relies on certificate chain guest {cert_chain_id}, which is not trusted"
+            ))
+        })?;
+        check_not_expired(&cert_chain_image, now)?;
+        Ok(TrustedAttestation {
+            attestation: VerifiedAttestation { journal: receipt.journal.bytes, policy: journal.policy.into() },
+            image,
+            cert_chain_image,
+        })
+    }
+}
+
+impl TrustedImages {
+    fn insert(&self, id: Digest, kind: GuestKind, journal_version: u8, deprecated_after: Option<i64>) {
+        let image = TrustedImage { kind, image_id_hex: hex::encode(id.as_bytes()), journal_version, deprecated_after };
+        let mut images = self.images.write().unwrap();
+        match images.iter_mut().find(|(existing, image)| *existing == id && image.kind == kind) {
+            Some(entry) => entry.1 = image,
+            None => images.push((id, image)),
+        }
+    }
+
+    fn find(&self, kind: GuestKind, matches: impl Fn(&Digest, &TrustedImage) -> bool) -> Option<TrustedImage> {
+        let images = self.images.read().unwrap();
+        images.iter().find(|(id, image)| image.kind == kind && matches(id, image)).map(|(_, image)| image.clone())
+    }
+
+    /// Decodes `bytes` and verifies the receipt against each trusted image of `kind` whose
+    /// journal version it carries, failing if none verifies or the one that does is expired.
+    fn verify(&self, bytes: &[u8], kind: GuestKind, now: i64) -> Result<(Receipt, TrustedImage), Risc0Error> {
+        let receipt = deserialize_receipt(bytes)?;
+        let version = receipt.journal.bytes.first().copied();
+        let image = self
+            .find(kind, |id, image| Some(image.journal_version) == version && receipt.verify(*id).is_ok())
+            .ok_or_else(|| {
+                Risc0Error::VerificationFailed(format!("receipt verifies against no trusted {} image", kind.name()))
+            })?;
+        check_not_expired(&image, now)?;
+        Ok((receipt, image))
+    }
+}
+
+fn check_not_expired(image: &TrustedImage, now: i64) -> Result<(), Risc0Error> {
+    if image.expired(now) {
+        return Err(Risc0Error::VerificationFailed(format!(
+            "{} image {} was deprecated after {} (now {now})",
+            image.kind.name(),
+            image.image_id_hex,
+            image.deprecated_after.unwrap_or_default()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use guest_core::{ASSERTION_JOURNAL_V7, ASSERTION_JOURNAL_V8, ATTESTATION_JOURNAL_V6};
+    use methods::{ASSERTION_ID, ATTESTATION_REST_ID, CERT_CHAIN_ID};
+    use risc0_zkvm::{FakeReceipt, InnerReceipt, ReceiptClaim};
+
+    use super::*;
+    use crate::build_info::image_id_hex;
+    use crate::tests::sample_journal;
+    use crate::{AssertionJournal, AttestationJournal, VerificationPolicy, NO_ALLOWLIST, POLICY};
+
+    const NOW: i64 = 1_751_800_000;
+    const OLD_ID: [u32; 8] = [7; 8];
+
+    fn receipt(image_id: [u32; 8], journal: Vec<u8>) -> Vec<u8> {
+        let claim = ReceiptClaim::ok(image_id, journal.clone());
+        bincode::serialize(&Receipt::new(InnerReceipt::Fake(FakeReceipt::new(claim)), journal)).unwrap()
+    }
+
+    fn attestation_journal(cert_chain_image_id: [u32; 8]) -> Vec<u8> {
+        AttestationJournal {
+            policy: POLICY,
+            public_key: [4; 65],
+            key_id: [7; 32],
+            rp_id_hash: [8; 32],
+            environment: AttestationJournal::PRODUCTION,
+            timestamp: NOW,
+            time_signer: [0; 32],
+            allowlist_entry: NO_ALLOWLIST,
+            cert_chain_image_id: Digest::from(cert_chain_image_id).into(),
+            cert_chain_claim_digest: [0; 32],
+            input_digest: [0; 32],
+            policy_hash: Some([0; 32]),
+        }
+        .encode()
+    }
+
+    fn with_old(kind: GuestKind, deprecated_after: Option<i64>) -> Arc<TrustedImages> {
+        let images = TrustedImages::new();
+        images.add_trusted_image(kind, image_id_hex(OLD_ID), current_journal_version(kind), deprecated_after).unwrap();
+        images
+    }
+
+    #[test]
+    fn receipts_of_either_assertion_image_verify() {
+        std::env::set_var("RISC0_DEV_MODE", "1");
+        let images = with_old(GuestKind::Assertion, Some(NOW));
+
+        let current = images.verify_assertion_proof(receipt(ASSERTION_ID, sample_journal()), NOW, None).unwrap();
+        assert_eq!(current.image.image_id_hex, image_id_hex(ASSERTION_ID));
+        assert_eq!(current.image.deprecated_after, None);
+        assert_eq!(current.signature.policy, Some(POLICY.into()));
+
+        let old = images.verify_assertion_proof(receipt(OLD_ID, sample_journal()), NOW, None).unwrap();
+        assert_eq!(old.image.image_id_hex, image_id_hex(OLD_ID));
+        assert_eq!(old.image.deprecated_after, Some(NOW));
+        assert_eq!(old.signature, current.signature);
+
+        assert!(matches!(
+            images.verify_assertion_proof(receipt([8; 8], sample_journal()), NOW, None),
+            Err(Risc0Error::VerificationFailed(msg)) if msg.contains("no trusted assertion image")
+        ));
+    }
+
+    #[test]
+    fn deprecated_images_expire() {
+        std::env::set_var("RISC0_DEV_MODE", "1");
+        let images = with_old(GuestKind::Assertion, Some(NOW));
+        let old = receipt(OLD_ID, sample_journal());
+
+        assert!(images.verify_assertion_proof(old.clone(), NOW, None).is_ok());
+        assert!(matches!(
+            images.verify_assertion_proof(old.clone(), NOW + 1, None),
+            Err(Risc0Error::VerificationFailed(msg)) if msg.contains("deprecated after 1751800000")
+        ));
+        assert!(images.verify_assertion_proof(receipt(ASSERTION_ID, sample_journal()), NOW + 1, None).is_ok());
+
+        // Re-adding the image replaces its deprecation.
+        images.add_trusted_image(GuestKind::Assertion, image_id_hex(OLD_ID), ASSERTION_JOURNAL_V9, None).unwrap();
+        assert_eq!(images.trusted_images().len(), 4);
+        assert!(images.verify_assertion_proof(old, NOW + 1, None).is_ok());
+    }
+
+    #[test]
+    fn attestations_may_rely_on_any_trusted_cert_chain_image() {
+        std::env::set_var("RISC0_DEV_MODE", "1");
+        let images = with_old(GuestKind::Attestation, None);
+        images.add_trusted_image(GuestKind::CertChain, image_id_hex(OLD_ID), CERT_CHAIN_JOURNAL_V2, Some(NOW)).unwrap();
+
+        for attestation_id in [ATTESTATION_REST_ID, OLD_ID] {
+            let on_current_chain = receipt(attestation_id, attestation_journal(CERT_CHAIN_ID));
+            let verified = images.verify_attestation_proof(on_current_chain, NOW, None).unwrap();
+            assert_eq!(verified.image.image_id_hex, image_id_hex(attestation_id));
+            assert_eq!(verified.cert_chain_image.image_id_hex, image_id_hex(CERT_CHAIN_ID));
+        }
+
+        let on_old_chain = receipt(ATTESTATION_REST_ID, attestation_journal(OLD_ID));
+        let verified = images.verify_attestation_proof(on_old_chain.clone(), NOW, None).unwrap();
+        assert_eq!(verified.cert_chain_image.deprecated_after, Some(NOW));
+        assert!(matches!(
+            images.verify_attestation_proof(on_old_chain, NOW + 1, None),
+            Err(Risc0Error::VerificationFailed(msg)) if msg.contains("cert_chain image")
+        ));
+        assert!(matches!(
+            images.verify_attestation_proof(receipt(ATTESTATION_REST_ID, attestation_journal([9; 8])), NOW, None),
+            Err(Risc0Error::VerificationFailed(msg)) if msg.contains("not trusted")
+        ));
+    }
+
+    #[test]
+    fn assertions_must_rely_on_a_trusted_attestation_image() {
+        std::env::set_var("RISC0_DEV_MODE", "1");
+        let images = with_old(GuestKind::Attestation, Some(NOW));
+        let relying_on = |attestation_id: [u32; 8]| {
+            let journal = AssertionJournal {
+                attestation_image_id: Digest::from(attestation_id).into(),
+                ..AssertionJournal::decode(&sample_journal()).unwrap()
+            };
+            receipt(ASSERTION_ID, journal.encode())
+        };
+
+        for attestation_id in [ATTESTATION_REST_ID, OLD_ID] {
+            let verified = images.verify_assertion_proof(relying_on(attestation_id), NOW, None).unwrap();
+            assert_eq!(verified.attestation_image.image_id_hex, image_id_hex(attestation_id));
+        }
+        assert!(matches!(
+            images.verify_assertion_proof(relying_on(OLD_ID), NOW + 1, None),
+            Err(Risc0Error::VerificationFailed(msg)) if msg.contains("attestation image")
+        ));
+        // A forged attestation guest could commit any key for the assertion to verify with.
+        assert!(matches!(
+            images.verify_assertion_proof(relying_on([9; 8]), NOW, None),
+            Err(Risc0Error::VerificationFailed(msg)) if msg.contains("not trusted")
+        ));
+    }
+
+    #[test]
+    fn assertions_must_rely_on_a_trusted_cert_chain_image() {
+        std::env::set_var("RISC0_DEV_MODE", "1");
+        let images = with_old(GuestKind::CertChain, Some(NOW));
+        let relying_on = |cert_chain_id: Option<[u32; 8]>| {
+            let journal = AssertionJournal {
+                cert_chain_image_id: cert_chain_id.map(|id| Digest::from(id).into()),
+                ..AssertionJournal::decode(&sample_journal()).unwrap()
+            };
+            receipt(ASSERTION_ID, journal.encode())
+        };
+
+        for cert_chain_id in [CERT_CHAIN_ID, OLD_ID] {
+            let verified = images.verify_assertion_proof(relying_on(Some(cert_chain_id)), NOW, None).unwrap();
+            assert_eq!(verified.cert_chain_image.image_id_hex, image_id_hex(cert_chain_id));
+        }
+        assert!(matches!(
+            images.verify_assertion_proof(relying_on(Some(OLD_ID)), NOW + 1, None),
+            Err(Risc0Error::VerificationFailed(msg)) if msg.contains("cert_chain image")
+        ));
+        // An attestation guest of a trusted image still verifies whichever chain its host names.
+        assert!(matches!(
+            images.verify_assertion_proof(relying_on(Some([9; 8])), NOW, None),
+            Err(Risc0Error::VerificationFailed(msg)) if msg.contains("certificate chain guest")
+        ));
+    }
+
+    #[test]
+    fn images_are_trusted_for_their_kind_only() {
+        std::env::set_var("RISC0_DEV_MODE", "1");
+        let images = TrustedImages::empty();
+        assert!(images.verify_assertion_proof(receipt(ASSERTION_ID, sample_journal()), NOW, None).is_err());
+
+        images.add_trusted_image(GuestKind::Attestation, image_id_hex(ASSERTION_ID), ATTESTATION_JOURNAL_V7, None).unwrap();
+        assert!(images.verify_assertion_proof(receipt(ASSERTION_ID, sample_journal()), NOW, None).is_err());
+    }
+
+    #[test]
+    fn images_of_the_previous_journal_version_verify_without_a_policy_hash() {
+        std::env::set_var("RISC0_DEV_MODE", "1");
+        let images = TrustedImages::new();
+        images.add_trusted_image(GuestKind::Attestation, image_id_hex(OLD_ID), ATTESTATION_JOURNAL_V6, None).unwrap();
+        let v6 = AttestationJournal {
+            policy_hash: None,
+            ..AttestationJournal::decode(&attestation_journal(CERT_CHAIN_ID)).unwrap()
+        };
+
+        let old = images.verify_attestation_proof(receipt(OLD_ID, v6.encode()), NOW, None).unwrap();
+        assert_eq!(old.image.journal_version, ATTESTATION_JOURNAL_V6);
+        assert_eq!(old.attestation.policy, POLICY.into());
+        // A version 6 journal commits no policy hash to compare an expected policy with.
+        let expected = VerificationPolicyData::from(&VerificationPolicy::for_app_ids::<&str>(&[]));
+        assert!(matches!(
+            images.verify_attestation_proof(receipt(OLD_ID, v6.encode()), NOW, Some(expected)),
+            Err(Risc0Error::VerificationFailed(msg)) if msg.contains("predates policy hashes")
+        ));
+        // The image verifies journals of its own version only.
+        let current = attestation_journal(CERT_CHAIN_ID);
+        assert!(images.verify_attestation_proof(receipt(OLD_ID, current), NOW, None).is_err());
+    }
+
+    #[test]
+    fn assertion_images_of_previous_journal_versions_are_refused() {
+        // Their journals do not say which certificate chain guest certified the key, so
+        // none of their receipts could verify.
+        let images = TrustedImages::empty();
+        for version in [ASSERTION_JOURNAL_V7, ASSERTION_JOURNAL_V8] {
+            assert_eq!(
+                images.add_trusted_image(GuestKind::Assertion, image_id_hex(OLD_ID), version, None),
+                Err(predates_cert_chain_image_ids(version))
+            );
+        }
+        assert!(images.trusted_images().is_empty());
+        images.add_trusted_image(GuestKind::Assertion, image_id_hex(OLD_ID), ASSERTION_JOURNAL_V9, None).unwrap();
+    }
+
+    #[test]
+    fn undecodable_journal_versions_and_ids_are_refused() {
+        let images = TrustedImages::empty();
+        assert_eq!(
+            images.add_trusted_image(GuestKind::Assertion, image_id_hex(OLD_ID), 6, None),
+            Err(Risc0Error::UnsupportedJournalVersion { found: 6, supported: vec![7, 8, 9] })
+        );
+        assert!(images.add_trusted_image(GuestKind::Assertion, "abcd".into(), 8, None).is_err());
+        assert!(images.trusted_images().is_empty());
+    }
+}
@@ -12,112 +12,1464 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-// This application demonstrates how to send an off-chain proof request
-// to the Bonsai proving service and publish the received proofs directly
-// to your deployed app contract.
+// This library proves the App Attest guests on device. The `publish` binary
+// (built with the `publish` feature) sends the resulting proofs to your
+// deployed app contract.
 
 // Allow unexpected cfg for the full file
 #![allow(unexpected_cfgs)]
 
-use std::time;
-use methods::{ASSERTION_ELF, ATTESTATION_ELF};
-use risc0_zkvm::{default_prover, ExecutorEnv, ProverOpts, VerifierContext};
+use std::sync::{Arc, RwLock};
+use std::{fmt, thread, time};
+use appattest_rs::sensitive::Sensitive;
+use methods::{
+    ASSERTION_ID, ATTESTATION_REST_ID, CERT_CHAIN_ID, WARM_UP_ELF,
+};
+use risc0_zkvm::sha::Digestible;
+use risc0_zkvm::{ExecutorEnv, ProverOpts, Receipt};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Records `field = value`s on the current span with the `tracing` feature. Without it the
+/// values are not evaluated.
+macro_rules! record {
+    ($($field:ident = $value:expr),+ $(,)?) => {
+        #[cfg(feature = "tracing")]
+        {
+            let span = tracing::Span::current();
+            $(span.record(stringify!($field), $value);)+
+        }
+    };
+}
+
+pub mod abi;
+mod backend;
+mod build_info;
+mod cache;
+#[cfg(feature = "capi")]
+pub mod capi;
+pub mod cli;
+mod config;
+#[cfg(feature = "eip712")]
+pub mod eip712;
+mod guest;
+mod host;
+mod images;
+pub mod metrics;
+pub mod noir;
+#[cfg(feature = "proto")]
+pub mod proto;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "uploader")]
+pub mod uploader;
+mod verify;
+mod warm_up;
+#[cfg(test)]
+#[path = "../build/bb.rs"]
+mod bb;
+#[cfg(test)]
+#[path = "../build/freshness.rs"]
+mod freshness;
+#[cfg(test)]
+#[path = "../build/nargo.rs"]
+mod nargo;
+#[cfg(test)]
+#[path = "../build/project.rs"]
+mod project;
+
+pub use backend::{DefaultBackend, ProofBackend};
+pub use build_info::{get_build_info, handle_version_full_flag, BuildInfo};
+pub use cache::ReceiptCache;
+pub use config::{ErrorClass, PausePolicy, ProofKind, ProverConfig, RetryPolicy};
+pub use metrics::{MetricsSink, NoopSink};
+pub use mobiscale_verifier::{
+    verify_receipt_matches_inputs, PolicyData, ProvenInput, SignatureData, VerificationPolicyData, VerifiedAttestation,
+    VerifierError,
+};
+pub use guest::{execute_guest, GuestInput, GuestKind, ASSERTION_MAX_PO2, DEFAULT_MAX_CYCLES};
+pub use host::{thermal_throttle, HostConditions, ThermalThrottle};
+pub use guest_core::{
+    abort, AssertionInput, AssertionJournal, AttestationInput, AttestationJournal, CertChainInput, CertChainJournal,
+    Policy, TimeToken, VerificationPolicy, NO_ALLOWLIST, POLICY,
+};
+pub use images::{TrustedAssertion, TrustedAttestation, TrustedImage, TrustedImages};
+pub use noir::{prove_assertion_noir, HybridProofOutput, NoirProofOutput};
+pub use verify::{
+    check_attestation_freshness, check_attestation_guest, check_cert_chain_guest, check_expected_policy,
+    check_minimum_policy, check_session_nonce, deserialize_receipt, verify_assertion_proof, verify_attestation_proof,
+    verify_receipt,
+};
+pub use warm_up::{CancelToken, WarmUpReport};
 
 mopro_ffi::app!();
 
-#[derive(uniffi::Error, thiserror::Error, Debug)]
+/// In JSON, `{"kind": "<variant>", "detail": ...}`, like `AppAttestError`.
+#[derive(uniffi::Error, thiserror::Error, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(tag = "kind", content = "detail")]
 pub enum Risc0Error {
     #[error("Failed to prove: {0}")]
     ProveError(String),
     #[error("Failed to serialize receipt: {0}")]
     SerializeError(String),
+    #[error("Failed to decode journal: {0}")]
+    JournalError(String),
+    #[error("Invalid receipt: {0}")]
+    InvalidReceipt(String),
+    #[error("Receipt verification failed: {0}")]
+    VerificationFailed(String),
+    /// The guest refused its input; `code` is one of the `guest_core::abort` codes.
+    #[error("Guest rejected the input (abort {code}): {reason}")]
+    GuestRejected { code: u8, reason: String },
+    /// `nargo` or `bb`, which [`noir::prove_assertion_noir`] shells out to, is not installed.
+    #[error("Noir toolchain missing: {0}")]
+    ToolchainMissing(String),
+    /// The [`HostConditions`] kept the prover paused for longer than
+    /// [`PausePolicy::max_pause_ms`].
+    #[error("Paused by the host for more than {max_pause_ms} ms")]
+    PausedTooLong { max_pause_ms: u64 },
+    /// The [`CancelToken`] of a [`Prover::warm_up`] was cancelled before it started proving.
+    #[error("Cancelled")]
+    Cancelled,
+    /// A guest run took more than `limit` cycles, the configured
+    /// [`ProverConfig::max_cycles`], and was stopped.
+    #[error("Guest exceeded the limit of {limit} cycles")]
+    CycleLimitExceeded { limit: u64 },
+    /// A journal's version byte is none of the `supported` ones this build reads, see
+    /// `mobiscale_verifier::codec`.
+    #[error("Unsupported journal version {found}, this build reads {supported:?}")]
+    UnsupportedJournalVersion { found: u8, supported: Vec<u8> },
+    /// The prover failed to allocate memory, as the source of its error reports. Worth
+    /// retrying once the app is under less pressure.
+    #[error("Out of memory: {0}")]
+    OutOfMemory(String),
+}
+
+impl Risc0Error {
+    /// Classifies the error for the retry policy.
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            Risc0Error::OutOfMemory(_) => ErrorClass::ResourceExhausted,
+            Risc0Error::ProveError(_) | Risc0Error::PausedTooLong { .. } => ErrorClass::ProverFailure,
+            Risc0Error::SerializeError(_)
+            | Risc0Error::JournalError(_)
+            | Risc0Error::InvalidReceipt(_)
+            | Risc0Error::VerificationFailed(_)
+            | Risc0Error::GuestRejected { .. }
+            | Risc0Error::ToolchainMissing(_)
+            | Risc0Error::Cancelled
+            | Risc0Error::CycleLimitExceeded { .. }
+            | Risc0Error::UnsupportedJournalVersion { .. } => ErrorClass::Permanent,
+        }
+    }
+
+    /// Stable numeric code, in the 2000 range next to the `AppAttestError` codes.
+    pub fn code(&self) -> u16 {
+        match self {
+            Risc0Error::ProveError(_) => 2001,
+            Risc0Error::SerializeError(_) => 2002,
+            Risc0Error::JournalError(_) => 2003,
+            Risc0Error::InvalidReceipt(_) => 2004,
+            Risc0Error::VerificationFailed(_) => 2005,
+            Risc0Error::GuestRejected { .. } => 2006,
+            Risc0Error::ToolchainMissing(_) => 2007,
+            Risc0Error::PausedTooLong { .. } => 2008,
+            Risc0Error::Cancelled => 2009,
+            Risc0Error::CycleLimitExceeded { .. } => 2010,
+            Risc0Error::UnsupportedJournalVersion { .. } => 2011,
+            Risc0Error::OutOfMemory(_) => 2012,
+        }
+    }
+
+    /// Maps an error of the executor or prover, reporting a guest abort as [`Risc0Error::GuestRejected`].
+    pub fn from_execution(error: &(impl std::fmt::Display + ?Sized)) -> Self {
+        // The alternate form includes the whole anyhow chain, where the guest's panic message sits.
+        match guest_core::parse_abort(&format!("{error:#}")) {
+            Some((code, reason)) => Risc0Error::GuestRejected { code, reason },
+            None => Risc0Error::ProveError(error.to_string()),
+        }
+    }
+
+    /// [`Risc0Error::from_execution`] for an error of the prover or executor itself, reporting
+    /// an allocation failure anywhere in its source chain as [`Risc0Error::OutOfMemory`].
+    pub(crate) fn from_prover<E>(error: &E) -> Self
+    where
+        E: fmt::Display + AsRef<dyn std::error::Error + Send + Sync + 'static>,
+    {
+        let mut source: Option<&(dyn std::error::Error + 'static)> = Some(error.as_ref());
+        while let Some(cause) = source {
+            let out_of_memory = cause.is::<std::collections::TryReserveError>()
+                || cause.downcast_ref::<std::io::Error>().is_some_and(|e| e.kind() == std::io::ErrorKind::OutOfMemory);
+            if out_of_memory {
+                return Risc0Error::OutOfMemory(error.to_string());
+            }
+            source = cause.source();
+        }
+        Risc0Error::from_execution(error)
+    }
+
+    /// Reports the executor stopping a run limited to `limit` cycles as
+    /// [`Risc0Error::CycleLimitExceeded`]; other errors are returned unchanged.
+    pub(crate) fn within_cycle_limit(self, limit: u64) -> Self {
+        match self {
+            Risc0Error::ProveError(msg) if msg.to_lowercase().contains("session limit exceeded") => {
+                Risc0Error::CycleLimitExceeded { limit }
+            }
+            other => other,
+        }
+    }
+}
+
+/// Keeps the variant, and with it the code, of the light verifier's errors.
+impl From<VerifierError> for Risc0Error {
+    fn from(error: VerifierError) -> Self {
+        match error {
+            VerifierError::JournalError(msg) => Risc0Error::JournalError(msg),
+            VerifierError::InvalidReceipt(msg) => Risc0Error::InvalidReceipt(msg),
+            VerifierError::VerificationFailed(msg) => Risc0Error::VerificationFailed(msg),
+            VerifierError::UnsupportedJournalVersion { found, supported } => {
+                Risc0Error::UnsupportedJournalVersion { found, supported }
+            }
+        }
+    }
+}
+
+/// Receives progress notifications from a [`Prover`].
+#[uniffi::export(with_foreign)]
+pub trait ProgressListener: Send + Sync {
+    /// Called before retry number `attempt` (the first retry is attempt 2).
+    fn on_retry(&self, attempt: u32);
+
+    /// Called when the [`HostConditions`] pause the prover.
+    fn on_paused(&self) {}
+
+    /// Called when the prover goes on after a pause.
+    fn on_resumed(&self) {}
+}
+
+/// An assertion to prove, together with the attestation receipt for the key that signed it.
+#[derive(uniffi::Record, Clone, PartialEq)]
+pub struct AssertionRequest {
+    /// CBOR assertion object returned by `generateAssertion`.
+    pub assertion: Vec<u8>,
+    /// Client data the assertion signs.
+    pub client_data: Vec<u8>,
+    /// `<team id>.<bundle id>` of the asserting app.
+    pub app_id: String,
+    /// Receipt returned by `prove_attestation` for the signing key. The assertion guest
+    /// verifies it by composition and takes the public key from its journal.
+    pub attestation_receipt: Vec<u8>,
+    /// Counter value of the last accepted assertion for this key, `None` before the first.
+    pub previous_counter: Option<u32>,
+    /// 32-byte nonce the relying party issued for this session, committed to the journal so
+    /// it can reject proofs made for another session. All zeros if it issued none.
+    pub session_nonce: Vec<u8>,
+}
+
+/// Shows the assertion, client data and receipt only as [`Sensitive`].
+impl fmt::Debug for AssertionRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AssertionRequest")
+            .field("assertion", &Sensitive(&self.assertion))
+            .field("client_data", &Sensitive(&self.client_data))
+            .field("app_id", &self.app_id)
+            .field("attestation_receipt", &Sensitive(&self.attestation_receipt))
+            .field("previous_counter", &self.previous_counter)
+            .field("session_nonce", &self.session_nonce)
+            .finish()
+    }
+}
+
+impl TryFrom<&AssertionRequest> for GuestInput {
+    type Error = Risc0Error;
+
+    fn try_from(request: &AssertionRequest) -> Result<Self, Risc0Error> {
+        let attestation = deserialize_receipt(&request.attestation_receipt)?;
+        let session_nonce = request.session_nonce.as_slice().try_into().map_err(|_| {
+            Risc0Error::ProveError(format!("session nonce is {} bytes, expected 32", request.session_nonce.len()))
+        })?;
+        let input = AssertionInput {
+            assertion: request.assertion.clone(),
+            client_data: request.client_data.clone(),
+            app_id_hash: Sha256::digest(request.app_id.as_bytes()).into(),
+            attestation_journal: attestation.journal.bytes.clone(),
+            attestation_image_id: ATTESTATION_REST_ID,
+            previous_counter: request.previous_counter,
+            session_nonce,
+        };
+        Ok(GuestInput::Assertion { input, attestation })
+    }
 }
 
-#[derive(uniffi::Record)]
+/// An attestation object to prove, with the values the relying party expects it to carry.
+#[derive(uniffi::Record, Clone, PartialEq)]
+pub struct AttestationRequest {
+    /// CBOR attestation object returned by `attestKey`.
+    pub attestation: Vec<u8>,
+    /// Challenge the attestation was generated for.
+    pub challenge: Vec<u8>,
+    /// `<team id>.<bundle id>` of the attesting app.
+    pub app_id: String,
+    /// Key identifier returned by `generateKey`.
+    pub key_id: Vec<u8>,
+    /// Unix time the certificate chain is checked at; it is committed to the journal.
+    pub timestamp: i64,
+    /// Signature over `timestamp` from a time source the verifier trusts, if the host has one.
+    pub time_token: Option<TimeTokenRequest>,
+}
+
+/// Shows the attestation object and challenge only as [`Sensitive`].
+impl fmt::Debug for AttestationRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AttestationRequest")
+            .field("attestation", &Sensitive(&self.attestation))
+            .field("challenge", &Sensitive(&self.challenge))
+            .field("app_id", &self.app_id)
+            .field("key_id", &self.key_id)
+            .field("timestamp", &self.timestamp)
+            .field("time_token", &self.time_token)
+            .finish()
+    }
+}
+
+/// A time source's signature over a timestamp; see [`TimeToken`].
+#[derive(uniffi::Record, Clone, PartialEq)]
+pub struct TimeTokenRequest {
+    /// Uncompressed SEC1 public key of the time source.
+    pub signer: Vec<u8>,
+    /// DER-encoded ECDSA P-256 signature over the little-endian timestamp bytes.
+    pub signature: Vec<u8>,
+}
+
+impl fmt::Debug for TimeTokenRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TimeTokenRequest")
+            .field("signer", &self.signer)
+            .field("signature", &Sensitive(&self.signature))
+            .finish()
+    }
+}
+
+impl From<&AttestationRequest> for CertChainInput {
+    fn from(request: &AttestationRequest) -> Self {
+        Self {
+            attestation: request.attestation.clone(),
+            challenge: request.challenge.clone(),
+            timestamp: request.timestamp,
+            time_token: request.time_token.as_ref().map(|token| TimeToken {
+                signer: token.signer.clone(),
+                signature: token.signature.clone(),
+            }),
+        }
+    }
+}
+
+impl GuestInput {
+    /// The attestation guest's input for `request`, relying on `cert_chain`, a certificate
+    /// chain receipt for the same request.
+    pub fn attestation(request: &AttestationRequest, cert_chain: Receipt) -> Self {
+        let input = request.attestation_input(cert_chain.journal.bytes.clone());
+        GuestInput::Attestation { input, cert_chain }
+    }
+}
+
+impl AttestationRequest {
+    /// The `input_digest` the attestation guest commits for this request, see
+    /// [`guest_core::digest`]. It covers the certificate chain guest's input, so it is known
+    /// before either guest runs.
+    pub fn canonical_digest(&self) -> [u8; 32] {
+        let cert_chain = CertChainInput::from(self).canonical_digest();
+        self.attestation_input(Vec::new()).canonical_digest_over(&cert_chain)
+    }
+
+    fn attestation_input(&self, cert_chain_journal: Vec<u8>) -> AttestationInput {
+        AttestationInput {
+            attestation: self.attestation.clone(),
+            app_id_hash: Sha256::digest(self.app_id.as_bytes()).into(),
+            key_id: self.key_id.clone(),
+            cert_chain_journal,
+            cert_chain_image_id: CERT_CHAIN_ID,
+        }
+    }
+}
+
+impl AssertionRequest {
+    /// The `input_digest` the assertion guest commits for this request, see
+    /// [`guest_core::digest`]. Fails as proving would on an attestation receipt that does
+    /// not decode or a session nonce that is not 32 bytes.
+    pub fn canonical_digest(&self) -> Result<[u8; 32], Risc0Error> {
+        GuestInput::try_from(self)?.canonical_digest()
+    }
+}
+
+#[derive(uniffi::Record, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
 pub struct AssertionProofOutput {
     pub signature_data: SignatureData,
     pub proof: Risc0ProofOutput
 }
-#[derive(uniffi::Record, Clone)]
+#[derive(uniffi::Record, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
 pub struct Risc0ProofOutput {
+    #[serde(with = "appattest_rs::json::bytes")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
     pub receipt: Vec<u8>,
+    pub metadata: ProofMetadata,
+}
+
+/// Describes how and by which build a proof was produced.
+#[derive(uniffi::Record, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ProofMetadata {
+    pub build_info: BuildInfo,
+    /// Image ID of the guest that was proven, as hex.
+    pub image_id_hex: String,
+    /// Wall time spent proving, including retries.
+    pub proving_time_ms: u64,
+}
+
+impl ProofMetadata {
+    fn new(image_id: [u32; 8], started: time::Instant) -> Self {
+        Self {
+            build_info: get_build_info(),
+            image_id_hex: build_info::image_id_hex(image_id),
+            proving_time_ms: started.elapsed().as_millis() as u64,
+        }
+    }
+}
+
+/// Proves the attestation and assertion guests through a pluggable [`ProofBackend`].
+///
+/// The free functions below use a `Prover` over the [`DefaultBackend`]; construct one with
+/// [`Prover::with_backend`] to inject a different backend.
+#[derive(uniffi::Object)]
+pub struct Prover {
+    backend: Arc<dyn ProofBackend>,
+    config: ProverConfig,
+    listener: RwLock<Option<Arc<dyn ProgressListener>>>,
+    host: RwLock<Option<Arc<dyn HostConditions>>>,
+    metrics: RwLock<Arc<dyn MetricsSink>>,
+    cache: RwLock<Option<ReceiptCache>>,
+    warm: warm_up::WarmState,
 }
 
-#[derive(uniffi::Record, Clone)]
-pub struct SignatureData {
-    pub signature_r: Vec<u8>,
-    pub signature_s: Vec<u8>,
-    pub public_key_x: Vec<u8>,
-    pub public_key_y: Vec<u8>,
+impl Prover {
+    pub fn with_backend(backend: Arc<dyn ProofBackend>, config: ProverConfig) -> Self {
+        Self {
+            backend,
+            config,
+            listener: RwLock::new(None),
+            host: RwLock::new(None),
+            metrics: RwLock::new(Arc::new(NoopSink)),
+            cache: RwLock::new(None),
+            warm: Default::default(),
+        }
+    }
+
+    /// Reports proving durations, outcomes, retries and receipt sizes to `sink`, see
+    /// [`metrics`].
+    pub fn set_metrics_sink(&self, sink: Arc<dyn MetricsSink>) {
+        *self.metrics.write().unwrap() = sink;
+    }
+
+    fn metrics(&self) -> Arc<dyn MetricsSink> {
+        self.metrics.read().unwrap().clone()
+    }
+
+    /// Applies the configured receipt kind to the guest's base options.
+    fn opts(&self, base: ProverOpts) -> ProverOpts {
+        base.with_receipt_kind(self.config.proof_kind.receipt_kind())
+    }
+
+    /// The receipt for `input` from the receipt cache, or else proven and then cached.
+    fn prove_guest(&self, input: &GuestInput, opts: &ProverOpts) -> Result<Receipt, Risc0Error> {
+        let cache = self.cache.read().unwrap().clone();
+        if let Some(receipt) = cache.as_ref().and_then(|cache| cache.get(input)) {
+            return Ok(receipt);
+        }
+        let receipt = self.prove_with_retry(input.kind(), || input.env_within(self.config.max_cycles), opts)?;
+        if let Some(cache) = &cache {
+            cache.put(input, &receipt);
+        }
+        Ok(receipt)
+    }
+
+    /// Runs the backend on `guest`, rebuilding the environment and retrying according to the
+    /// retry policy.
+    fn prove_with_retry<'a, F>(&self, guest: GuestKind, build_env: F, opts: &ProverOpts) -> Result<Receipt, Risc0Error>
+    where
+        F: Fn() -> Result<ExecutorEnv<'a>, Risc0Error>,
+    {
+        let policy = &self.config.retry;
+        let sink = self.metrics();
+        let labels = [("guest", guest.name())];
+        let started = time::Instant::now();
+        let finish = |result: Result<Receipt, Risc0Error>, attempts: u32| {
+            record!(attempts = attempts);
+            let outcome = if result.is_ok() { "success" } else { "failure" };
+            sink.incr_counter(metrics::PROOFS_TOTAL, &[labels[0], ("outcome", outcome)]);
+            sink.observe_duration(metrics::PROVE_DURATION_SECONDS, &labels, started.elapsed().as_secs_f64());
+            result
+        };
+        let mut attempt = 1;
+        loop {
+            if let Err(err) = self.wait_for_host() {
+                return finish(Err(err), attempt);
+            }
+            let err = match self.backend.prove(build_env()?, guest.elf(), opts) {
+                Ok(receipt) => return finish(Ok(receipt), attempt),
+                Err(err) => err.within_cycle_limit(self.config.max_cycles),
+            };
+            if !policy.should_retry(attempt, err.class()) {
+                return finish(Err(err), attempt);
+            }
+            #[cfg(feature = "tracing")]
+            tracing::warn!(attempt, error = %err, "retrying proof");
+            sink.incr_counter(metrics::PROVE_RETRIES_TOTAL, &labels);
+
+            thread::sleep(policy.backoff(attempt));
+            attempt += 1;
+            if let Some(listener) = self.listener.read().unwrap().as_ref() {
+                listener.on_retry(attempt);
+            }
+        }
+    }
+
+    /// Sleeps while the [`HostConditions`] ask for a pause, for at most the configured
+    /// [`PausePolicy::max_pause_ms`].
+    fn wait_for_host(&self) -> Result<(), Risc0Error> {
+        let Some(host) = self.host.read().unwrap().clone() else {
+            return Ok(());
+        };
+        if !host.should_pause() {
+            return Ok(());
+        }
+        let listener = self.listener.read().unwrap().clone();
+        if let Some(listener) = &listener {
+            listener.on_paused();
+        }
+        #[cfg(feature = "tracing")]
+        tracing::info!("paused by the host");
+
+        let policy = &self.config.pause;
+        let max_pause = time::Duration::from_millis(policy.max_pause_ms);
+        let started = time::Instant::now();
+        loop {
+            let remaining = max_pause.saturating_sub(started.elapsed());
+            if remaining.is_zero() {
+                return Err(Risc0Error::PausedTooLong { max_pause_ms: policy.max_pause_ms });
+            }
+            thread::sleep(remaining.min(time::Duration::from_millis(policy.poll_ms)));
+            if !host.should_pause() {
+                break;
+            }
+        }
+        if let Some(listener) = &listener {
+            listener.on_resumed();
+        }
+        Ok(())
+    }
+
+    /// [`serialize_receipt`], reporting the size of the receipt of `guest`.
+    fn serialize(&self, guest: GuestKind, receipt: &Receipt) -> Result<Vec<u8>, Risc0Error> {
+        let bytes = serialize_receipt(receipt)?;
+        self.metrics().observe_value(metrics::RECEIPT_SIZE_BYTES, &[("guest", guest.name())], bytes.len() as f64);
+        Ok(bytes)
+    }
 }
 
 #[uniffi::export]
-pub fn prove_attestation() -> Result<Risc0ProofOutput, Risc0Error> {
-    // Parse CLI Arguments: The application starts by parsing command-line arguments provided by the user.
-
-    // // Create an alloy provider for that private key and URL.
-    let timestamp: i64 = time::SystemTime::now()
-        .duration_since(time::UNIX_EPOCH)
-        .expect("Time went backwards")
-        .as_secs() as i64;
-    let bytes: [u8; 8] = timestamp.to_le_bytes(); // or to_be_bytes()
-    let input: &[u8] = &bytes;
-
-    let env = ExecutorEnv::builder().write_slice(&input).build().map_err(|e| {
-        Risc0Error::ProveError(format!("Failed to create ExecutorEnv: {}", e))
-    })?;
-
-    let receipt = default_prover()
-        .prove_with_ctx(
-            env,
-            &VerifierContext::default(),
-            ATTESTATION_ELF,
-            &ProverOpts::fast()
-        ).map_err(|e| Risc0Error::ProveError(e.to_string()))?
-        .receipt;
-
-    let receipt_bytes = bincode::serialize(&receipt)
-        .map_err(|e| Risc0Error::SerializeError(format!("Failed to serialize receipt: {}", e)))?;
+impl Prover {
+    #[uniffi::constructor]
+    pub fn new() -> Arc<Self> {
+        Self::with_config(ProverConfig::default())
+    }
+
+    #[uniffi::constructor]
+    pub fn with_config(config: ProverConfig) -> Arc<Self> {
+        Arc::new(Self {
+            warm: warm_up::PROCESS.clone(),
+            ..Self::with_backend(Arc::new(DefaultBackend), config)
+        })
+    }
+
+    pub fn set_progress_listener(&self, listener: Option<Arc<dyn ProgressListener>>) {
+        *self.listener.write().unwrap() = listener;
+    }
+
+    /// Pauses before each guest run and retry while `conditions` ask for it; see
+    /// [`HostConditions`].
+    pub fn set_host_conditions(&self, conditions: Option<Arc<dyn HostConditions>>) {
+        *self.host.write().unwrap() = conditions;
+    }
+
+    /// Keeps receipts in `dir`, at most `max_bytes` of them, and returns a kept receipt
+    /// instead of proving an input again; see [`ReceiptCache`].
+    pub fn set_receipt_cache(&self, dir: String, max_bytes: u64) -> Result<(), Risc0Error> {
+        *self.cache.write().unwrap() = Some(ReceiptCache::new(dir, max_bytes)?);
+        Ok(())
+    }
+
+    /// Proves the tiny `warm_up` guest, with the configured receipt kind, so that the
+    /// prover's lazy initialization (hashing tables, Metal pipelines) is done before the
+    /// first real proof, e.g. while the user is still on an earlier screen.
+    ///
+    /// Once a warm-up succeeds, later calls return at once with `already_warm` set: for
+    /// any prover over the [`DefaultBackend`] in the process, otherwise for this prover. A
+    /// call made while another is running waits for it. It fails with
+    /// [`Risc0Error::Cancelled`] if `cancel` is cancelled before the proof starts, and a
+    /// failed or cancelled warm-up leaves the prover cold for the next call to retry.
+    pub fn warm_up(&self, cancel: Option<Arc<CancelToken>>) -> Result<WarmUpReport, Risc0Error> {
+        let started = time::Instant::now();
+        let cancelled = || cancel.as_ref().is_some_and(|cancel| cancel.is_cancelled());
+        let report = |already_warm| WarmUpReport { duration_ms: started.elapsed().as_millis() as u64, already_warm };
+
+        let mut warm = self.warm.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if *warm {
+            return Ok(report(true));
+        }
+        if cancelled() {
+            return Err(Risc0Error::Cancelled);
+        }
+        self.wait_for_host()?;
+        if cancelled() {
+            return Err(Risc0Error::Cancelled);
+        }
+        let env = ExecutorEnv::builder().build().map_err(guest::env_error)?;
+        self.backend.prove(env, WARM_UP_ELF, &self.opts(ProverOpts::fast()))?;
+        *warm = true;
+        Ok(report(false))
+    }
+
+    /// Proves the attestation in `request`: [`Prover::prove_cert_chain`] followed by
+    /// [`Prover::prove_attestation_with_cert_chain`].
+    pub fn prove_attestation(&self, request: AttestationRequest) -> Result<Risc0ProofOutput, Risc0Error> {
+        let started = time::Instant::now();
+        let cert_chain = self.prove_cert_chain(request.clone())?;
+        let mut output = self.prove_attestation_with_cert_chain(request, cert_chain.receipt)?;
+        output.metadata.proving_time_ms = started.elapsed().as_millis() as u64;
+        Ok(output)
+    }
+
+    /// Proves the certificate chain and nonce of the attestation in `request`, the expensive
+    /// part of an attestation. The receipt does not depend on the app ID or key ID, so a
+    /// caller can keep it per key and pass it to [`Prover::prove_attestation_with_cert_chain`].
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        name = "prover.prove", skip_all,
+        fields(guest = ?GuestKind::CertChain, attempts, cycles, segments, receipt_len), err(level = "info"),
+    ))]
+    pub fn prove_cert_chain(&self, request: AttestationRequest) -> Result<Risc0ProofOutput, Risc0Error> {
+        let input = GuestInput::CertChain(CertChainInput::from(&request));
+
+        let started = time::Instant::now();
+        let receipt = self.prove_guest(&input, &self.opts(ProverOpts::fast()))?;
+
+        Ok(Risc0ProofOutput {
+            receipt: self.serialize(GuestKind::CertChain, &receipt)?,
+            metadata: ProofMetadata::new(CERT_CHAIN_ID, started),
+        })
+    }
+
+    /// Proves the rest of the attestation in `request` given `cert_chain_receipt`, returned
+    /// by [`Prover::prove_cert_chain`] for the same request. The attestation guest verifies
+    /// that receipt by composition, so the receipt returned here carries both claims.
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        name = "prover.prove", skip_all,
+        fields(guest = ?GuestKind::Attestation, attempts, cycles, segments, receipt_len), err(level = "info"),
+    ))]
+    pub fn prove_attestation_with_cert_chain(
+        &self,
+        request: AttestationRequest,
+        cert_chain_receipt: Vec<u8>,
+    ) -> Result<Risc0ProofOutput, Risc0Error> {
+        let input = GuestInput::attestation(&request, deserialize_receipt(&cert_chain_receipt)?);
+
+        let started = time::Instant::now();
+        let receipt = self.prove_guest(&input, &self.opts(ProverOpts::fast()))?;
+
+        // Return the receipt as output.
+        Ok(Risc0ProofOutput {
+            receipt: self.serialize(GuestKind::Attestation, &receipt)?,
+            metadata: ProofMetadata::new(ATTESTATION_REST_ID, started),
+        })
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        name = "prover.prove", skip_all,
+        fields(guest = ?GuestKind::Assertion, attempts, cycles, segments, receipt_len), err(level = "info"),
+    ))]
+    pub fn prove_assertion(&self, request: AssertionRequest) -> Result<AssertionProofOutput, Risc0Error> {
+        let input = GuestInput::try_from(&request)?;
 
-    // Return the journal and receipt as output.
-    Ok(Risc0ProofOutput {
-        receipt: receipt_bytes,
-    })
+        let started = time::Instant::now();
+        let opts = self.opts(ProverOpts::from_max_po2(ASSERTION_MAX_PO2 as usize));
+        let receipt = self.prove_guest(&input, &opts)?;
+
+        // Extract the journal from the receipt.
+        let signature_data = extract_signature_data(&receipt)?;
+
+        // Return the signature data and receipt as output.
+        Ok(AssertionProofOutput {
+            signature_data,
+            proof: Risc0ProofOutput {
+                receipt: self.serialize(GuestKind::Assertion, &receipt)?,
+                metadata: ProofMetadata::new(ASSERTION_ID, started),
+            },
+        })
+    }
 }
 
 #[uniffi::export]
-pub fn prove_assertion() -> Result<AssertionProofOutput, Risc0Error> {
-
-    let env = ExecutorEnv::builder().build().map_err(|e| {
-        Risc0Error::ProveError(format!("Failed to create ExecutorEnv: {}", e))
-    })?;
-
-    let receipt = default_prover()
-        .prove_with_ctx(
-            env,
-            &VerifierContext::default(),
-            ASSERTION_ELF,
-            &ProverOpts::from_max_po2(18),
-        ).map_err(|e| Risc0Error::ProveError(e.to_string()))?
-        .receipt;
-
-    // Extract the journal from the receipt.
-    let journal_parts: [Vec<u8>; 4] = receipt.journal.decode().unwrap();
-    let signature_data = SignatureData {
-        signature_r: journal_parts[0].as_slice().try_into().unwrap(),
-        signature_s: journal_parts[1].as_slice().try_into().unwrap(),
-        public_key_x: journal_parts[2].as_slice().try_into().unwrap(),
-        public_key_y: journal_parts[3].as_slice().try_into().unwrap(),
-    };
+pub fn prove_attestation(request: AttestationRequest) -> Result<Risc0ProofOutput, Risc0Error> {
+    Prover::new().prove_attestation(request)
+}
+
+#[uniffi::export]
+pub fn prove_assertion(request: AssertionRequest) -> Result<AssertionProofOutput, Risc0Error> {
+    Prover::new().prove_assertion(request)
+}
+
+/// [`Prover::warm_up`] for the provers of the free functions and of [`Prover::with_config`],
+/// which share their warm-up.
+#[uniffi::export]
+pub fn warm_up_prover(config: ProverConfig, cancel: Option<Arc<CancelToken>>) -> Result<WarmUpReport, Risc0Error> {
+    Prover::with_config(config).warm_up(cancel)
+}
+
+/// Hex digest of the receipt's claim, the value a verifier contract ultimately checks.
+pub fn claim_digest_hex(receipt: &Receipt) -> Result<String, Risc0Error> {
+    let claim = receipt
+        .claim()
+        .map_err(|e| Risc0Error::SerializeError(format!("Receipt has no claim: {}", e)))?;
+    Ok(hex::encode(claim.digest().as_bytes()))
+}
 
-    let receipt_bytes = bincode::serialize(&receipt)
+fn serialize_receipt(receipt: &Receipt) -> Result<Vec<u8>, Risc0Error> {
+    let bytes = bincode::serialize(receipt)
         .map_err(|e| Risc0Error::SerializeError(format!("Failed to serialize receipt: {}", e)))?;
+    record!(receipt_len = bytes.len());
+    Ok(bytes)
+}
+
+/// Decodes the journal committed by the assertion guest, dispatching on its version byte.
+pub fn decode_assertion_journal(journal: &[u8]) -> Result<AssertionJournal, Risc0Error> {
+    Ok(mobiscale_verifier::decode_assertion_journal(journal)?)
+}
+
+/// Decodes the journal committed by the attestation guest, dispatching on its version byte.
+pub fn decode_attestation_journal(journal: &[u8]) -> Result<AttestationJournal, Risc0Error> {
+    Ok(mobiscale_verifier::decode_attestation_journal(journal)?)
+}
+
+/// Decodes the journal committed by the certificate chain guest, dispatching on its version
+/// byte.
+pub fn decode_cert_chain_journal(journal: &[u8]) -> Result<CertChainJournal, Risc0Error> {
+    Ok(mobiscale_verifier::decode_cert_chain_journal(journal)?)
+}
+
+/// Decodes the signature and public key from the assertion guest's journal.
+pub(crate) fn extract_signature_data(receipt: &Receipt) -> Result<SignatureData, Risc0Error> {
+    Ok(SignatureData::from(&decode_assertion_journal(&receipt.journal.bytes)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use risc0_zkvm::{FakeReceipt, InnerReceipt, ReceiptClaim};
+
+    use super::*;
+    use backend::mock::MockBackend;
+
+    fn prover(backend: MockBackend) -> (Prover, Arc<MockBackend>) {
+        prover_with_config(backend, ProverConfig::default())
+    }
+
+    fn prover_with_config(backend: MockBackend, config: ProverConfig) -> (Prover, Arc<MockBackend>) {
+        let backend = Arc::new(backend);
+        (Prover::with_backend(backend.clone(), config), backend)
+    }
+
+    fn retrying(max_attempts: u32, backoff_ms: u64) -> ProverConfig {
+        ProverConfig {
+            retry: RetryPolicy {
+                max_attempts,
+                backoff_ms,
+                retry_on: vec![ErrorClass::ResourceExhausted],
+            },
+            ..Default::default()
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingListener(std::sync::Mutex<Vec<u32>>);
+
+    impl ProgressListener for RecordingListener {
+        fn on_retry(&self, attempt: u32) {
+            self.0.lock().unwrap().push(attempt);
+        }
+    }
+
+    /// Records pauses and resumptions as `paused` and `resumed`, and retries as `retry`.
+    #[derive(Default)]
+    struct EventListener(std::sync::Mutex<Vec<&'static str>>);
+
+    impl ProgressListener for EventListener {
+        fn on_retry(&self, _attempt: u32) {
+            self.0.lock().unwrap().push("retry");
+        }
+
+        fn on_paused(&self) {
+            self.0.lock().unwrap().push("paused");
+        }
+
+        fn on_resumed(&self) {
+            self.0.lock().unwrap().push("resumed");
+        }
+    }
+
+    /// Answers `should_pause` from a script, repeating its last answer once exhausted, and
+    /// counts the checks.
+    struct ScriptedHost {
+        script: std::sync::Mutex<Vec<bool>>,
+        checks: std::sync::atomic::AtomicUsize,
+    }
+
+    impl ScriptedHost {
+        fn new(mut script: Vec<bool>) -> Arc<Self> {
+            script.reverse();
+            Arc::new(Self { script: std::sync::Mutex::new(script), checks: Default::default() })
+        }
+
+        fn checks(&self) -> usize {
+            self.checks.load(std::sync::atomic::Ordering::SeqCst)
+        }
+    }
+
+    impl HostConditions for ScriptedHost {
+        fn should_pause(&self) -> bool {
+            self.checks.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let mut script = self.script.lock().unwrap();
+            if script.len() > 1 {
+                script.pop().unwrap()
+            } else {
+                script[0]
+            }
+        }
+
+        fn on_thermal_state(&self, _level: u8) {}
+    }
+
+    fn pausing(max_pause_ms: u64) -> ProverConfig {
+        ProverConfig { pause: PausePolicy { max_pause_ms, poll_ms: 10 }, ..Default::default() }
+    }
+
+    /// Keeps the name and labels of every metric reported, as `name{label=value,...}`.
+    #[derive(Default)]
+    struct RecordingSink(std::sync::Mutex<Vec<String>>);
+
+    impl RecordingSink {
+        fn push(&self, name: &str, labels: &[(&str, &str)]) {
+            let labels: Vec<String> = labels.iter().map(|(name, value)| format!("{name}={value}")).collect();
+            self.0.lock().unwrap().push(format!("{name}{{{}}}", labels.join(",")));
+        }
+    }
+
+    impl MetricsSink for RecordingSink {
+        fn incr_counter(&self, name: &str, labels: &[(&str, &str)]) {
+            self.push(name, labels);
+        }
+
+        fn observe_duration(&self, name: &str, labels: &[(&str, &str)], _secs: f64) {
+            self.push(name, labels);
+        }
+
+        fn observe_value(&self, name: &str, labels: &[(&str, &str)], _value: f64) {
+            self.push(name, labels);
+        }
+    }
+
+    pub(crate) fn sample_request() -> AssertionRequest {
+        let journal = AttestationJournal {
+            policy: POLICY,
+            public_key: [4; 65],
+            key_id: [7; 32],
+            rp_id_hash: Sha256::digest(b"TEAMID.com.example.app").into(),
+            environment: AttestationJournal::DEVELOPMENT,
+            timestamp: 1_751_800_000,
+            time_signer: [0; 32],
+            allowlist_entry: NO_ALLOWLIST,
+            cert_chain_image_id: risc0_zkvm::sha::Digest::from(CERT_CHAIN_ID).into(),
+            cert_chain_claim_digest: [0; 32],
+            input_digest: [0; 32],
+            policy_hash: Some([0; 32]),
+        }
+        .encode();
+        let claim = ReceiptClaim::ok(ATTESTATION_REST_ID, journal.clone());
+        let attestation = Receipt::new(InnerReceipt::Fake(FakeReceipt::new(claim)), journal);
+        AssertionRequest {
+            assertion: vec![0xa2],
+            client_data: b"x = 15".to_vec(),
+            app_id: "TEAMID.com.example.app".into(),
+            attestation_receipt: serialize_receipt(&attestation).unwrap(),
+            previous_counter: None,
+            session_nonce: vec![9; 32],
+        }
+    }
+
+    pub(crate) fn sample_attestation_request() -> AttestationRequest {
+        AttestationRequest {
+            attestation: vec![0xa3],
+            challenge: vec![9; 32],
+            app_id: "TEAMID.com.example.app".into(),
+            key_id: vec![7; 32],
+            timestamp: 1_751_800_000,
+            time_token: None,
+        }
+    }
+
+    pub(crate) fn sample_journal() -> Vec<u8> {
+        AssertionJournal {
+            policy: POLICY,
+            rp_id_hash: [0; 32],
+            public_key_x: [3; 32],
+            public_key_y: [4; 32],
+            signature_r: [1; 32],
+            signature_s: [2; 32],
+            counter: 1,
+            client_data_hash: [5; 32],
+            attestation_image_id: risc0_zkvm::sha::Digest::from(ATTESTATION_REST_ID).into(),
+            attestation_claim_digest: [7; 32],
+            challenge_hash: [8; 32],
+            allowlist_entry: NO_ALLOWLIST,
+            session_nonce: [9; 32],
+            input_digest: [0; 32],
+            policy_hash: Some([0; 32]),
+            cert_chain_image_id: Some(risc0_zkvm::sha::Digest::from(CERT_CHAIN_ID).into()),
+        }
+        .encode()
+    }
+
+    #[test]
+    fn prove_assertion_decodes_signature_data() {
+        let (prover, backend) = prover(MockBackend::with_journal(sample_journal()));
+
+        let output = prover.prove_assertion(sample_request()).unwrap();
+        assert_eq!(backend.calls(), 1);
+        assert_eq!(
+            output.signature_data,
+            SignatureData {
+                signature_r: vec![1; 32],
+                signature_s: vec![2; 32],
+                public_key_x: vec![3; 32],
+                public_key_y: vec![4; 32],
+                client_data_hash: vec![5; 32],
+                challenge_hash: vec![8; 32],
+                session_nonce: vec![9; 32],
+                policy: Some(POLICY.into()),
+            }
+        );
+    }
+
+    #[test]
+    fn receipt_bytes_round_trip() {
+        let journal = sample_journal();
+        let (prover, _) = prover(MockBackend::with_journal(journal.clone()));
+
+        let output = prover.prove_assertion(sample_request()).unwrap();
+        let receipt: Receipt = bincode::deserialize(&output.proof.receipt).unwrap();
+        assert_eq!(receipt.journal.bytes, journal);
+    }
+
+    #[test]
+    fn receipts_match_the_request_they_answer() {
+        let request = sample_request();
+        let mut journal = AssertionJournal::decode(&sample_journal()).unwrap();
+        journal.input_digest = request.canonical_digest().unwrap();
+        let (prover, _) = prover(MockBackend::with_journal(journal.encode()));
+
+        let output = prover.prove_assertion(request.clone()).unwrap();
+        let receipt = deserialize_receipt(&output.proof.receipt).unwrap();
+        let input = GuestInput::try_from(&request).unwrap();
+        assert!(verify_receipt_matches_inputs(&receipt, &ProvenInput::from(&input)));
+
+        let other = AssertionRequest { client_data: b"x = 16".to_vec(), ..request };
+        let other = GuestInput::try_from(&other).unwrap();
+        assert!(!verify_receipt_matches_inputs(&receipt, &ProvenInput::from(&other)));
+    }
+
+    #[test]
+    fn metadata_identifies_the_proven_guest() {
+        let (prover, _) = prover(MockBackend::with_journal(sample_journal()));
+
+        let output = prover.prove_assertion(sample_request()).unwrap();
+        let metadata = output.proof.metadata;
+        assert_eq!(metadata.build_info, get_build_info());
+        assert_eq!(metadata.image_id_hex, metadata.build_info.assertion_image_id_hex);
+
+        let attestation = prover.prove_attestation(sample_attestation_request()).unwrap();
+        assert_eq!(
+            attestation.metadata.image_id_hex,
+            attestation.metadata.build_info.attestation_image_id_hex
+        );
+    }
+
+    #[test]
+    fn attestations_are_proven_in_two_steps() {
+        let (prover, backend) = prover(MockBackend::with_journal(sample_journal()));
+
+        let cert_chain = prover.prove_cert_chain(sample_attestation_request()).unwrap();
+        assert_eq!(cert_chain.metadata.image_id_hex, build_info::image_id_hex(CERT_CHAIN_ID));
+        assert_eq!(backend.calls(), 1);
+
+        // A kept certificate chain receipt is reused without proving the chain again.
+        for _ in 0..2 {
+            prover
+                .prove_attestation_with_cert_chain(sample_attestation_request(), cert_chain.receipt.clone())
+                .unwrap();
+        }
+        assert_eq!(backend.calls(), 3);
+
+        prover.prove_attestation(sample_attestation_request()).unwrap();
+        assert_eq!(backend.calls(), 5);
+    }
+
+    #[test]
+    fn attestation_input_names_the_cert_chain_receipt() {
+        let journal = vec![1, 2, 3];
+        let claim = ReceiptClaim::ok(CERT_CHAIN_ID, journal.clone());
+        let cert_chain = Receipt::new(InnerReceipt::Fake(FakeReceipt::new(claim)), journal.clone());
+
+        let GuestInput::Attestation { input, .. } = GuestInput::attestation(&sample_attestation_request(), cert_chain)
+        else {
+            panic!("expected an attestation input");
+        };
+        assert_eq!(input.cert_chain_journal, journal);
+        assert_eq!(input.cert_chain_image_id, CERT_CHAIN_ID);
+        assert_eq!(input.app_id_hash, <[u8; 32]>::from(Sha256::digest(b"TEAMID.com.example.app")));
+    }
+
+    #[test]
+    fn prove_error_is_passed_through() {
+        let error = Risc0Error::ProveError("out of memory".into());
+        let (prover, _) = prover(MockBackend::failing(error.clone()));
+
+        assert_eq!(prover.prove_attestation(sample_attestation_request()).err(), Some(error.clone()));
+        assert_eq!(prover.prove_assertion(sample_request()).err(), Some(error));
+    }
+
+    #[test]
+    fn no_retries_by_default() {
+        let (prover, backend) = prover(MockBackend::failing(Risc0Error::OutOfMemory("out of memory".into())));
+
+        assert!(prover.prove_assertion(sample_request()).is_err());
+        assert_eq!(backend.calls(), 1);
+    }
+
+    #[test]
+    fn transient_failures_are_retried_until_success() {
+        let oom = || Err(Risc0Error::OutOfMemory("out of memory".into()));
+        let backend = MockBackend::scripted(vec![oom(), oom(), Ok(sample_journal())]);
+        let (prover, backend) = prover_with_config(backend, retrying(3, 10));
+        let listener = Arc::new(RecordingListener::default());
+        prover.set_progress_listener(Some(listener.clone()));
+
+        let started = time::Instant::now();
+        assert!(prover.prove_assertion(sample_request()).is_ok());
+        let elapsed = started.elapsed();
+
+        assert_eq!(backend.calls(), 3);
+        assert_eq!(*listener.0.lock().unwrap(), vec![2, 3]);
+        // 10ms + 20ms of backoff, each with at most 25% jitter.
+        assert!(elapsed >= time::Duration::from_millis(30), "{elapsed:?}");
+        assert!(elapsed < time::Duration::from_secs(2), "{elapsed:?}");
+    }
+
+    #[test]
+    fn proving_waits_while_the_host_asks_for_a_pause() {
+        let (prover, backend) = prover_with_config(MockBackend::with_journal(sample_journal()), pausing(10_000));
+        let listener = Arc::new(EventListener::default());
+        prover.set_progress_listener(Some(listener.clone()));
+        // Paused at the first check and the one after, cleared at the third.
+        let host = ScriptedHost::new(vec![true, true, false]);
+        prover.set_host_conditions(Some(host.clone()));
+
+        let started = time::Instant::now();
+        assert!(prover.prove_assertion(sample_request()).is_ok());
+        assert!(started.elapsed() >= time::Duration::from_millis(20), "{:?}", started.elapsed());
+        assert_eq!(host.checks(), 3);
+        assert_eq!(backend.calls(), 1);
+        assert_eq!(*listener.0.lock().unwrap(), ["paused", "resumed"]);
+
+        // Each guest run is a chance to pause; the host no longer asks for one.
+        prover.prove_attestation(sample_attestation_request()).unwrap();
+        assert_eq!(host.checks(), 5);
+        assert_eq!(*listener.0.lock().unwrap(), ["paused", "resumed"]);
+    }
+
+    #[test]
+    fn pausing_too_long_fails_without_proving() {
+        let (prover, backend) = prover_with_config(MockBackend::with_journal(sample_journal()), pausing(30));
+        let listener = Arc::new(EventListener::default());
+        prover.set_progress_listener(Some(listener.clone()));
+        prover.set_host_conditions(Some(ScriptedHost::new(vec![true])));
+
+        let error = prover.prove_cert_chain(sample_attestation_request()).unwrap_err();
+        assert_eq!(error, Risc0Error::PausedTooLong { max_pause_ms: 30 });
+        assert_eq!(error.code(), 2008);
+        assert_eq!(backend.calls(), 0);
+        assert_eq!(*listener.0.lock().unwrap(), ["paused"]);
+
+        // A thermal throttle below its level does not pause.
+        let throttle = thermal_throttle(2);
+        throttle.on_thermal_state(1);
+        prover.set_host_conditions(Some(throttle));
+        assert!(prover.prove_cert_chain(sample_attestation_request()).is_ok());
+    }
+
+    #[test]
+    fn warming_up_proves_once_and_proving_goes_on() {
+        let (prover, backend) = prover(MockBackend::scripted(vec![Ok(Vec::new()), Ok(sample_journal())]));
+
+        assert!(!prover.warm_up(None).unwrap().already_warm);
+        assert_eq!(backend.calls(), 1);
+        assert!(prover.warm_up(None).unwrap().already_warm);
+        assert_eq!(backend.calls(), 1);
+
+        prover.prove_assertion(sample_request()).unwrap();
+        assert_eq!(backend.calls(), 2);
+    }
+
+    #[test]
+    fn cancelled_or_failed_warm_ups_leave_the_prover_cold() {
+        let error = Risc0Error::ProveError("out of memory".into());
+        let (prover, backend) = prover(MockBackend::scripted(vec![Err(error.clone()), Ok(Vec::new())]));
+
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        let cancelled = prover.warm_up(Some(cancel)).unwrap_err();
+        assert_eq!(cancelled, Risc0Error::Cancelled);
+        assert_eq!(cancelled.code(), 2009);
+        assert_eq!(backend.calls(), 0);
+
+        assert_eq!(prover.warm_up(Some(CancelToken::new())).err(), Some(error));
+        assert!(!prover.warm_up(None).unwrap().already_warm);
+        assert_eq!(backend.calls(), 2);
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn proving_reports_attempts_and_receipt_size() {
+        use tracing_subscriber::fmt::format::FmtSpan;
+
+        let oom = || Err(Risc0Error::OutOfMemory("out of memory".into()));
+        let (prover, _) = prover_with_config(MockBackend::scripted(vec![oom(), Ok(sample_journal())]), retrying(2, 1));
+        let output = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(output.clone())
+            .with_span_events(FmtSpan::CLOSE)
+            .without_time()
+            .finish();
+        tracing::subscriber::with_default(subscriber, || prover.prove_assertion(sample_request()).unwrap());
+
+        let output = String::from_utf8(output.lock().unwrap().clone()).unwrap();
+        assert!(output.contains("retrying proof attempt=1"), "{output}");
+        assert!(output.contains("prover.prove{guest=Assertion attempts=2 receipt_len="), "{output}");
+    }
+
+    #[test]
+    fn proving_reports_metrics() {
+        let oom = || Err(Risc0Error::OutOfMemory("out of memory".into()));
+        let (prover, _) = prover_with_config(MockBackend::scripted(vec![oom(), Ok(sample_journal())]), retrying(2, 1));
+        let sink = Arc::new(RecordingSink::default());
+        prover.set_metrics_sink(sink.clone());
+
+        prover.prove_assertion(sample_request()).unwrap();
+        assert_eq!(
+            *sink.0.lock().unwrap(),
+            [
+                "mobiscale_prove_retries_total{guest=assertion}",
+                "mobiscale_proofs_total{guest=assertion,outcome=success}",
+                "mobiscale_prove_duration_seconds{guest=assertion}",
+                "mobiscale_receipt_size_bytes{guest=assertion}",
+            ]
+        );
+
+        sink.0.lock().unwrap().clear();
+        let failing = MockBackend::failing(Risc0Error::ProveError("bad input".into()));
+        let (failing, _) = prover_with_config(failing, ProverConfig::default());
+        failing.set_metrics_sink(sink.clone());
+        assert!(failing.prove_cert_chain(sample_attestation_request()).is_err());
+        assert_eq!(
+            *sink.0.lock().unwrap(),
+            [
+                "mobiscale_proofs_total{guest=cert_chain,outcome=failure}",
+                "mobiscale_prove_duration_seconds{guest=cert_chain}",
+            ]
+        );
+    }
+
+    #[test]
+    fn gives_up_after_max_attempts() {
+        let error = Risc0Error::OutOfMemory("out of memory".into());
+        let (prover, backend) = prover_with_config(MockBackend::failing(error.clone()), retrying(2, 1));
+
+        assert_eq!(prover.prove_attestation(sample_attestation_request()).err(), Some(error));
+        assert_eq!(backend.calls(), 2);
+    }
+
+    #[test]
+    fn runaway_guests_fail_with_the_configured_cycle_limit() {
+        let error = Risc0Error::ProveError("Session limit exceeded".into());
+        let config = ProverConfig { max_cycles: 1 << 16, ..retrying(3, 1) };
+        let (prover, backend) = prover_with_config(MockBackend::failing(error), config);
+
+        let error = prover.prove_assertion(sample_request()).unwrap_err();
+        assert_eq!(error, Risc0Error::CycleLimitExceeded { limit: 1 << 16 });
+        assert_eq!(error.code(), 2010);
+        assert_eq!(backend.calls(), 1);
+        assert_eq!(ProverConfig::default().max_cycles, DEFAULT_MAX_CYCLES);
+    }
+
+    #[test]
+    fn journals_of_unknown_versions_name_the_supported_ones() {
+        let mut journal = sample_journal();
+        journal[0] = 10;
+        let error = decode_assertion_journal(&journal).unwrap_err();
+        assert_eq!(error, Risc0Error::UnsupportedJournalVersion { found: 10, supported: vec![7, 8, 9] });
+        assert_eq!(error.code(), 2011);
+        assert_eq!(error.class(), ErrorClass::Permanent);
+    }
+
+    #[test]
+    fn unlisted_failures_are_not_retried() {
+        let error = Risc0Error::ProveError("guest panicked: invalid signature".into());
+        let (prover, backend) = prover_with_config(MockBackend::failing(error.clone()), retrying(5, 1));
+
+        assert_eq!(prover.prove_attestation(sample_attestation_request()).err(), Some(error));
+        assert_eq!(backend.calls(), 1);
+    }
+
+    #[test]
+    fn errors_are_classified() {
+        let class = |e: Risc0Error| e.class();
+        assert_eq!(class(Risc0Error::OutOfMemory("x".into())), ErrorClass::ResourceExhausted);
+        // By variant, not by message.
+        assert_eq!(class(Risc0Error::ProveError("failed to allocate buffer".into())), ErrorClass::ProverFailure);
+        assert_eq!(class(Risc0Error::ProveError("guest panicked".into())), ErrorClass::ProverFailure);
+        assert_eq!(class(Risc0Error::SerializeError("x".into())), ErrorClass::Permanent);
+        assert_eq!(class(Risc0Error::JournalError("x".into())), ErrorClass::Permanent);
+    }
+
+    #[test]
+    fn allocation_failures_are_found_in_the_error_source() {
+        #[derive(Debug, thiserror::Error)]
+        #[error("proving failed")]
+        struct Wrapped(#[source] std::io::Error);
+
+        let error: Box<dyn std::error::Error + Send + Sync> =
+            Box::new(Wrapped(std::io::Error::from(std::io::ErrorKind::OutOfMemory)));
+        let error = Risc0Error::from_prover(&error);
+        assert_eq!(error, Risc0Error::OutOfMemory("proving failed".into()));
+        assert_eq!((error.class(), error.code()), (ErrorClass::ResourceExhausted, 2012));
+
+        let error: Box<dyn std::error::Error + Send + Sync> = "failed to allocate buffer".into();
+        assert_eq!(Risc0Error::from_prover(&error), Risc0Error::ProveError("failed to allocate buffer".into()));
+    }
+
+    #[test]
+    fn guest_aborts_are_reported_with_their_code() {
+        let error = "execution failed: Guest panicked: guest abort 13: Assertion verification failed: InvalidCounter";
+        assert_eq!(
+            Risc0Error::from_execution(error),
+            Risc0Error::GuestRejected {
+                code: abort::COUNTER_REPLAY,
+                reason: "Assertion verification failed: InvalidCounter".into(),
+            }
+        );
+        assert_eq!(Risc0Error::from_execution("segment limit"), Risc0Error::ProveError("segment limit".into()));
+        let rejected = Risc0Error::GuestRejected { code: 11, reason: "x".into() };
+        assert_eq!((rejected.class(), rejected.code()), (ErrorClass::Permanent, 2006));
+    }
+
+    #[test]
+    fn requests_hash_the_app_id_for_the_guest() {
+        let Ok(GuestInput::Assertion { input, attestation }) = GuestInput::try_from(&sample_request()) else {
+            panic!("expected an assertion input");
+        };
+        assert_eq!(input.app_id_hash, <[u8; 32]>::from(Sha256::digest(b"TEAMID.com.example.app")));
+        assert_eq!(input.client_data, b"x = 15");
+        assert_eq!(input.attestation_journal, attestation.journal.bytes);
+        assert_eq!(input.attestation_image_id, ATTESTATION_REST_ID);
+        assert_eq!(input.session_nonce, [9; 32]);
+    }
+
+    #[test]
+    fn session_nonces_must_be_32_bytes() {
+        let request = AssertionRequest {
+            session_nonce: vec![9; 16],
+            ..sample_request()
+        };
+        let (prover, backend) = prover(MockBackend::with_journal(sample_journal()));
+
+        assert!(matches!(
+            prover.prove_assertion(request),
+            Err(Risc0Error::ProveError(msg)) if msg.contains("16 bytes")
+        ));
+        assert_eq!(backend.calls(), 0);
+    }
+
+    #[test]
+    fn requests_show_no_replayable_input() {
+        use base64::{engine::general_purpose, Engine};
+
+        let fixtures = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures");
+        let mut attestation = cli::recorded_attestation(&fixtures).unwrap();
+        attestation.time_token = Some(TimeTokenRequest { signer: vec![4; 65], signature: b"time signature".to_vec() });
+        let assertion = cli::assertion_request(
+            include_str!("../fixtures/assertion.b64"),
+            include_str!("../fixtures/client_data.b64"),
+            &attestation.app_id,
+            b"attestation receipt".to_vec(),
+            Some(1),
+            [9; 32],
+        )
+        .unwrap();
+        let debug = format!("{attestation:?} {assertion:?}");
+        assert!(debug.contains(&attestation.app_id), "{debug}");
+
+        let time_token = attestation.time_token.as_ref().unwrap();
+        let secrets = [
+            &attestation.attestation,
+            &attestation.challenge,
+            &time_token.signature,
+            &assertion.assertion,
+            &assertion.client_data,
+            &assertion.attestation_receipt,
+        ];
+        for secret in secrets {
+            let listed = format!("{secret:?}");
+            for shown in [general_purpose::STANDARD.encode(secret), listed.trim_end_matches(']').to_string()] {
+                assert!(!debug.contains(&shown), "{debug} shows {shown}");
+            }
+        }
+    }
+
+    #[test]
+    fn undecodable_attestation_receipts_are_rejected() {
+        let request = AssertionRequest {
+            attestation_receipt: vec![0xff; 8],
+            ..sample_request()
+        };
+        let (prover, backend) = prover(MockBackend::with_journal(sample_journal()));
+
+        assert!(matches!(prover.prove_assertion(request), Err(Risc0Error::InvalidReceipt(_))));
+        assert_eq!(backend.calls(), 0);
+    }
+
+    #[test]
+    fn unknown_journal_versions_are_rejected() {
+        let mut journal = sample_journal();
+        journal[0] = 0xff;
+        let (prover, _) = prover(MockBackend::with_journal(journal));
+
+        assert_eq!(
+            prover.prove_assertion(sample_request()).err(),
+            Some(Risc0Error::UnsupportedJournalVersion { found: 255, supported: vec![7, 8, 9] })
+        );
+    }
+
+    #[test]
+    fn malformed_journal_is_a_journal_error() {
+        let (prover, _) = prover(MockBackend::with_journal(vec![0xde, 0xad]));
+
+        assert!(matches!(
+            prover.prove_assertion(sample_request()),
+            Err(Risc0Error::JournalError(_))
+        ));
+    }
+
+    /// Asserts `value` serializes to the golden file `golden` and deserializes back from it.
+    fn assert_golden<T>(value: &T, golden: &str)
+    where
+        T: Serialize + serde::de::DeserializeOwned + PartialEq + std::fmt::Debug,
+    {
+        let expected: serde_json::Value = serde_json::from_str(golden).unwrap();
+        let actual = serde_json::to_value(value).unwrap();
+        assert_eq!(
+            actual,
+            expected,
+            "the JSON shape changed; if that is intended, update the golden file to\n{}",
+            serde_json::to_string_pretty(&actual).unwrap()
+        );
+        assert_eq!(&serde_json::from_str::<T>(golden).unwrap(), value);
+    }
+
+    fn sample_policy() -> PolicyData {
+        PolicyData {
+            appattest_major: 0,
+            appattest_minor: 1,
+            appattest_patch: 0,
+            counter_policy: 1,
+            low_s: true,
+            environments: 3,
+        }
+    }
+
+    #[test]
+    fn results_keep_their_json_shape() {
+        let metadata = ProofMetadata {
+            build_info: BuildInfo {
+                crate_version: "0.1.0".into(),
+                risc0_version: "2.0.2".into(),
+                assertion_image_id_hex: "a1b2".into(),
+                attestation_image_id_hex: "c3d4".into(),
+                cert_chain_image_id_hex: "e5f6".into(),
+                git_commit: "0123abc".into(),
+                built_at: 1751700000,
+            },
+            image_id_hex: "a1b2".into(),
+            proving_time_ms: 4200,
+        };
+        let output = AssertionProofOutput {
+            signature_data: SignatureData {
+                signature_r: vec![1; 32],
+                signature_s: vec![2; 32],
+                public_key_x: vec![3; 32],
+                public_key_y: vec![4; 32],
+                client_data_hash: vec![5; 32],
+                challenge_hash: vec![0; 32],
+                session_nonce: vec![6; 32],
+                policy: Some(sample_policy()),
+            },
+            proof: Risc0ProofOutput { receipt: b"receipt".to_vec(), metadata },
+        };
+        assert_golden(&output, include_str!("../fixtures/json/assertion_proof_output.json"));
+
+        let attestation = VerifiedAttestation { journal: b"journal".to_vec(), policy: sample_policy() };
+        assert_golden(&attestation, include_str!("../fixtures/json/verified_attestation_proof.json"));
+    }
+
+    #[test]
+    fn errors_keep_their_json_shape() {
+        let errors = vec![
+            Risc0Error::ProveError("out of memory".into()),
+            Risc0Error::GuestRejected { code: 3, reason: "bad nonce".into() },
+            Risc0Error::ToolchainMissing("nargo".into()),
+        ];
+        assert_golden(&errors, include_str!("../fixtures/json/risc0_error.json"));
+    }
 
-    // Return the signature data and receipt as output.
-    Ok(AssertionProofOutput {
-        signature_data,
-        proof: Risc0ProofOutput {
-            receipt: receipt_bytes,
-        },
-    })
+    #[test]
+    fn signature_data_without_a_policy_is_null() {
+        let golden: serde_json::Value =
+            serde_json::from_str(include_str!("../fixtures/json/assertion_proof_output.json")).unwrap();
+        let mut json = golden["signatureData"].clone();
+        json["policy"] = serde_json::Value::Null;
+        let signature: SignatureData = serde_json::from_value(json).unwrap();
+        assert_eq!(signature.policy, None);
+        assert_eq!(signature.session_nonce, [6; 32]);
+    }
 }
@@ -0,0 +1,17 @@
+//! Names of the metrics a [`crate::Prover`] reports to the sink set with
+//! [`crate::Prover::set_metrics_sink`]. Every metric is labelled `guest`, see
+//! [`crate::GuestKind::name`].
+//!
+//! The sink is appattest-rs's [`MetricsSink`], so one sink, e.g. its `PrometheusSink`, can
+//! collect both proving and verification.
+
+pub use appattest_rs::metrics::{MetricsSink, NoopSink};
+
+/// Counter of proofs, also labelled `outcome`, `success` or `failure`.
+pub const PROOFS_TOTAL: &str = "mobiscale_proofs_total";
+/// Histogram of the time proofs took in seconds, retries and backoff included.
+pub const PROVE_DURATION_SECONDS: &str = "mobiscale_prove_duration_seconds";
+/// Counter of retried attempts.
+pub const PROVE_RETRIES_TOTAL: &str = "mobiscale_prove_retries_total";
+/// Histogram of the size of serialized receipts in bytes.
+pub const RECEIPT_SIZE_BYTES: &str = "mobiscale_receipt_size_bytes";
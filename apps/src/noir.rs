@@ -0,0 +1,609 @@
+//! Proving an assertion's P-256 signature with the `ecdsa` Noir circuit, as a SNARK next to
+//! the risc0 receipt.
+//!
+//! Proving shells out to `nargo`, which solves the witness, and to Barretenberg's `bb` CLI,
+//! which proves; both have to be on `PATH`. The circuit's source is compiled into the
+//! library, so neither the repository nor a compiled artifact is needed at run time.
+//! Verifying needs only `bb`.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+use p256::ecdsa::signature::hazmat::PrehashVerifier;
+use p256::ecdsa::{Signature, VerifyingKey};
+use p256::EncodedPoint;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    check_attestation_guest, decode_assertion_journal, deserialize_receipt, verify_receipt, AssertionJournal, GuestKind,
+    Risc0Error, SignatureData,
+};
+
+// Staged by build.rs from the project `NOIR_PROJECT_DIR` names, ../ecdsa by default; both are
+// empty if it was built without one.
+const NARGO_TOML: &str = include_str!(concat!(env!("OUT_DIR"), "/noir/Nargo.toml"));
+const MAIN_NR: &str = include_str!(concat!(env!("OUT_DIR"), "/noir/main.nr"));
+/// Package name in `NARGO_TOML`, which nargo names the compiled circuit and witness after.
+const PACKAGE: &str = "ecdsa";
+
+/// The circuit's public inputs, in the order [`verify_noir_proof`] takes them. Each is a
+/// `[u8; 32]`.
+pub const PUBLIC_INPUTS: [&str; 3] = ["sha256_hash", "public_key_x", "public_key_y"];
+
+/// Verification key of the circuit this library was built with, written by `bb write_vk`
+/// in build.rs. A verifier contract deployed for it carries the same
+/// [`get_noir_verification_key_digest`]. Empty if it was built with `SKIP_NOIR=1` before the
+/// circuit was ever compiled.
+#[cfg(feature = "noir")]
+pub const VERIFICATION_KEY: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/noir/vk"));
+
+/// Flags byte of the authenticator data App Attest assertions carry, which the assertion
+/// journal does not commit.
+const ASSERTION_FLAGS: u8 = 0x40;
+
+/// The `ecdsa` circuit's inputs, named as in its `main`. Each is a big-endian `[u8; 32]`,
+/// which is how the circuit takes them, not a field element.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NoirCircuitInputs {
+    /// `SHA256(nonce)`, the digest the assertion signature covers.
+    pub sha256_hash: [u8; 32],
+    pub r: [u8; 32],
+    pub s: [u8; 32],
+    pub public_key_x: [u8; 32],
+    pub public_key_y: [u8; 32],
+}
+
+impl NoirCircuitInputs {
+    /// The input names, in the order of the circuit's `main`.
+    pub const NAMES: [&'static str; 5] = ["sha256_hash", "r", "s", "public_key_x", "public_key_y"];
+
+    /// The inputs proving that `signature` signs `message_hash`.
+    pub fn new(signature: &SignatureData, message_hash: &[u8]) -> Result<Self, Risc0Error> {
+        let array = |name: &str, value: &[u8]| {
+            <[u8; 32]>::try_from(value)
+                .map_err(|_| Risc0Error::ProveError(format!("{name} is {} bytes, expected 32", value.len())))
+        };
+        Ok(NoirCircuitInputs {
+            sha256_hash: array("sha256_hash", message_hash)?,
+            r: array("r", &signature.signature_r)?,
+            s: array("s", &signature.signature_s)?,
+            public_key_x: array("public_key_x", &signature.public_key_x)?,
+            public_key_y: array("public_key_y", &signature.public_key_y)?,
+        })
+    }
+
+    /// Every input with its name, in [`Self::NAMES`] order.
+    pub fn fields(&self) -> [(&'static str, &[u8; 32]); 5] {
+        let values = [&self.sha256_hash, &self.r, &self.s, &self.public_key_x, &self.public_key_y];
+        let mut names = Self::NAMES.into_iter();
+        values.map(|value| (names.next().expect("one name per input"), value))
+    }
+
+    /// The public inputs, the way [`verify_noir_proof`] takes them.
+    pub fn public_inputs(&self) -> Vec<Vec<u8>> {
+        vec![self.sha256_hash.to_vec(), self.public_key_x.to_vec(), self.public_key_y.to_vec()]
+    }
+
+    /// The circuit's `Prover.toml`, every input written as quoted hex bytes.
+    pub fn to_prover_toml(&self) -> String {
+        let mut toml = String::new();
+        for (name, value) in self.fields() {
+            let bytes: Vec<String> = value.iter().map(|b| format!("\"0x{b:02x}\"")).collect();
+            let _ = writeln!(toml, "{name} = [{}]", bytes.join(", "));
+        }
+        toml
+    }
+}
+
+/// Verifies an assertion receipt and returns the circuit inputs for the signature it proves.
+///
+/// `client_data` is what the assertion signs; it has to hash to the journal's
+/// `client_data_hash`. The journal does not carry the authenticator data the signature
+/// covers, so it is rebuilt from `rp_id_hash`, the App Attest flags and `counter`, and the
+/// signature is checked over the result before it is handed to the circuit.
+pub fn circuit_inputs_from_receipt(receipt: &[u8], client_data: &[u8]) -> Result<NoirCircuitInputs, Risc0Error> {
+    let receipt = verify_receipt(receipt, GuestKind::Assertion)?;
+    let journal = decode_assertion_journal(&receipt.journal.bytes)?;
+    check_attestation_guest(&journal)?;
+
+    if Sha256::digest(client_data).as_slice() != journal.client_data_hash {
+        return Err(Risc0Error::VerificationFailed("client data does not match the journal's client_data_hash".into()));
+    }
+    inputs_from_journal(&journal)
+}
+
+/// The circuit inputs for the signature in `journal`, rebuilding the message it signs.
+fn inputs_from_journal(journal: &AssertionJournal) -> Result<NoirCircuitInputs, Risc0Error> {
+    let mut auth_data = journal.rp_id_hash.to_vec();
+    auth_data.push(ASSERTION_FLAGS);
+    auth_data.extend_from_slice(&journal.counter.to_be_bytes());
+    let nonce = Sha256::new().chain_update(&auth_data).chain_update(journal.client_data_hash).finalize();
+    let sha256_hash: [u8; 32] = Sha256::digest(nonce).into();
+
+    let point =
+        EncodedPoint::from_affine_coordinates(&journal.public_key_x.into(), &journal.public_key_y.into(), false);
+    let signed = VerifyingKey::from_encoded_point(&point)
+        .ok()
+        .zip(Signature::from_scalars(journal.signature_r, journal.signature_s).ok())
+        .is_some_and(|(key, signature)| key.verify_prehash(&sha256_hash, &signature).is_ok());
+    if !signed {
+        return Err(Risc0Error::VerificationFailed(
+            "the journal's signature does not verify over the rebuilt authenticator data".into(),
+        ));
+    }
+
+    Ok(NoirCircuitInputs {
+        sha256_hash,
+        r: journal.signature_r,
+        s: journal.signature_s,
+        public_key_x: journal.public_key_x,
+        public_key_y: journal.public_key_y,
+    })
+}
+
+/// A Noir proof of an assertion signature, as written by `bb`.
+#[derive(uniffi::Record, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NoirProofOutput {
+    /// UltraHonk proof, without its public inputs: the message hash and the public key,
+    /// which the verifier supplies.
+    pub proof: Vec<u8>,
+    /// Verification key of the `ecdsa` circuit.
+    pub verification_key: Vec<u8>,
+}
+
+/// [`VERIFICATION_KEY`], for the bindings.
+#[cfg(feature = "noir")]
+#[uniffi::export]
+pub fn get_noir_verification_key() -> Vec<u8> {
+    VERIFICATION_KEY.to_vec()
+}
+
+/// Keccak-256 of [`VERIFICATION_KEY`], for checking a client, a server and a verifier
+/// contract agree on the circuit without passing the key around.
+#[cfg(feature = "noir")]
+#[uniffi::export]
+pub fn get_noir_verification_key_digest() -> Vec<u8> {
+    use sha3::Keccak256;
+    Keccak256::digest(VERIFICATION_KEY).to_vec()
+}
+
+/// An assertion's risc0 receipt and Noir proof in one artifact, with the values both of
+/// them prove stated once, so the two cannot be paired up wrongly on the way to their
+/// verifiers. Written to disk with [`HybridProofOutput::to_bytes`].
+#[derive(uniffi::Record, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HybridProofOutput {
+    /// Bincode-serialized assertion receipt.
+    pub receipt: Vec<u8>,
+    pub noir: NoirProofOutput,
+    /// `SHA256(nonce)`, the digest the signature covers.
+    pub message_hash: Vec<u8>,
+    pub public_key_x: Vec<u8>,
+    pub public_key_y: Vec<u8>,
+    pub signature_r: Vec<u8>,
+    pub signature_s: Vec<u8>,
+}
+
+impl HybridProofOutput {
+    /// Bundles `receipt` with `noir`, a proof over the signature in its journal.
+    pub fn new(receipt: Vec<u8>, noir: NoirProofOutput) -> Result<Self, Risc0Error> {
+        let inputs = inputs_from_journal(&journal_of(&receipt)?)?;
+        Ok(HybridProofOutput {
+            receipt,
+            noir,
+            message_hash: inputs.sha256_hash.to_vec(),
+            public_key_x: inputs.public_key_x.to_vec(),
+            public_key_y: inputs.public_key_y.to_vec(),
+            signature_r: inputs.r.to_vec(),
+            signature_s: inputs.s.to_vec(),
+        })
+    }
+
+    /// The Noir circuit inputs the bundle states.
+    pub fn circuit_inputs(&self) -> Result<NoirCircuitInputs, Risc0Error> {
+        let signature = SignatureData {
+            signature_r: self.signature_r.clone(),
+            signature_s: self.signature_s.clone(),
+            public_key_x: self.public_key_x.clone(),
+            public_key_y: self.public_key_y.clone(),
+            client_data_hash: Vec::new(),
+            challenge_hash: Vec::new(),
+            session_nonce: Vec::new(),
+            policy: None,
+        };
+        NoirCircuitInputs::new(&signature, &self.message_hash).map_err(|e| match e {
+            Risc0Error::ProveError(msg) => Risc0Error::VerificationFailed(format!("proof bundle: {msg}")),
+            e => e,
+        })
+    }
+
+    /// Checks that the receipt's journal commits the values the bundle states, which are
+    /// the Noir proof's public inputs. Neither proof is verified: that is
+    /// [`verify_receipt`]'s and [`verify_noir_proof`]'s job, with these same values.
+    pub fn consistency_check(&self) -> Result<(), Risc0Error> {
+        let stated = self.circuit_inputs()?;
+        let committed = inputs_from_journal(&journal_of(&self.receipt)?)?;
+        for ((name, stated), (_, committed)) in stated.fields().into_iter().zip(committed.fields()) {
+            if stated != committed {
+                return Err(Risc0Error::VerificationFailed(format!(
+                    "proof bundle states another {name} than its receipt's journal"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Serializes the bundle with bincode, like a receipt.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Risc0Error> {
+        bincode::serialize(self).map_err(|e| Risc0Error::SerializeError(e.to_string()))
+    }
+
+    /// Decodes a bundle written by [`HybridProofOutput::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Risc0Error> {
+        bincode::deserialize(bytes).map_err(|e| Risc0Error::InvalidReceipt(format!("invalid proof bundle: {e}")))
+    }
+}
+
+/// The assertion journal of a serialized receipt, which is not verified.
+fn journal_of(receipt: &[u8]) -> Result<AssertionJournal, Risc0Error> {
+    decode_assertion_journal(&deserialize_receipt(receipt)?.journal.bytes)
+}
+
+/// Proves in the `ecdsa` circuit that `signature` signs `message_hash`, the `SHA256(nonce)`
+/// of the assertion it came from.
+///
+/// Fails with [`Risc0Error::ToolchainMissing`] if `nargo` or `bb` is not on `PATH`, or if the
+/// library was built without the circuit.
+#[uniffi::export]
+pub fn prove_assertion_noir(signature: SignatureData, message_hash: Vec<u8>) -> Result<NoirProofOutput, Risc0Error> {
+    if MAIN_NR.is_empty() {
+        return Err(Risc0Error::ToolchainMissing(
+            "this library was built without the ecdsa circuit; set NOIR_PROJECT_DIR to build it in".into(),
+        ));
+    }
+    let inputs = NoirCircuitInputs::new(&signature, &message_hash)?;
+    let public_inputs = encode_public_inputs(&inputs.public_inputs())?;
+
+    let dir = tempfile::tempdir().map_err(|e| Risc0Error::ProveError(format!("failed to create a work dir: {e}")))?;
+    let write = |path: &str, contents: &str| {
+        let path = dir.path().join(path);
+        fs::create_dir_all(path.parent().expect("path is inside the work dir"))
+            .and_then(|()| fs::write(&path, contents))
+            .map_err(|e| Risc0Error::ProveError(format!("failed to write {}: {e}", path.display())))
+    };
+    write("Nargo.toml", NARGO_TOML)?;
+    write("src/main.nr", MAIN_NR)?;
+    write("Prover.toml", &inputs.to_prover_toml())?;
+
+    // Writes target/<PACKAGE>.json and the witness, target/<PACKAGE>.gz.
+    run("nargo", &["execute"], dir.path())?;
+    let bytecode = format!("target/{PACKAGE}.json");
+    let witness = format!("target/{PACKAGE}.gz");
+    run("bb", &["prove", "-b", &bytecode, "-w", &witness, "-o", "target"], dir.path())?;
+    run("bb", &["write_vk", "-b", &bytecode, "-o", "target"], dir.path())?;
+
+    let read = |name: &str| {
+        let path = dir.path().join("target").join(name);
+        fs::read(&path).map_err(|e| Risc0Error::ProveError(format!("bb did not write {}: {e}", path.display())))
+    };
+    // Newer bb writes the public inputs next to the proof; they have to be what
+    // `verify_noir_proof` will supply, or no proof made here would verify.
+    if let Ok(written) = fs::read(dir.path().join("target/public_inputs")) {
+        if written != public_inputs {
+            return Err(Risc0Error::ProveError(
+                "bb wrote the public inputs in another layout than verify_noir_proof supplies".into(),
+            ));
+        }
+    }
+    let verification_key = read("vk")?;
+    #[cfg(feature = "noir")]
+    if !VERIFICATION_KEY.is_empty() && verification_key != VERIFICATION_KEY {
+        return Err(Risc0Error::ProveError(
+            "bb wrote another verification key than the one embedded at build time; is it the same bb release?".into(),
+        ));
+    }
+    Ok(NoirProofOutput { proof: read("proof")?, verification_key })
+}
+
+/// Verifies a proof made by [`prove_assertion_noir`] with `bb verify`.
+///
+/// `public_inputs` are the message hash and the public key, as listed in [`PUBLIC_INPUTS`].
+/// Any other shape is an error, reported before `bb` runs; a proof `bb` rejects, whether
+/// tampered with or made for other inputs, is `Ok(false)`. Servers call this from Rust, so
+/// unlike proving it is not exported to the bindings.
+pub fn verify_noir_proof(
+    proof: Vec<u8>,
+    verification_key: Vec<u8>,
+    public_inputs: Vec<Vec<u8>>,
+) -> Result<bool, Risc0Error> {
+    let public_inputs = encode_public_inputs(&public_inputs)?;
+    if proof.is_empty() || verification_key.is_empty() {
+        return Err(Risc0Error::VerificationFailed("empty Noir proof or verification key".into()));
+    }
+
+    let dir =
+        tempfile::tempdir().map_err(|e| Risc0Error::VerificationFailed(format!("failed to create a work dir: {e}")))?;
+    for (name, bytes) in [("proof", &proof), ("vk", &verification_key), ("public_inputs", &public_inputs)] {
+        fs::write(dir.path().join(name), bytes)
+            .map_err(|e| Risc0Error::VerificationFailed(format!("failed to write {name}: {e}")))?;
+    }
+    let args = ["verify", "-k", "vk", "-p", "proof", "-i", "public_inputs"];
+    let output = spawn("bb", &args, dir.path(), Risc0Error::VerificationFailed)?;
+    Ok(output.status.success())
+}
+
+/// `public_inputs` as `bb` reads them: one 32-byte big-endian field element per byte of
+/// each public array, in declaration order.
+fn encode_public_inputs(public_inputs: &[Vec<u8>]) -> Result<Vec<u8>, Risc0Error> {
+    if public_inputs.len() != PUBLIC_INPUTS.len() {
+        return Err(Risc0Error::VerificationFailed(format!(
+            "expected {} Noir public inputs ({}), got {}",
+            PUBLIC_INPUTS.len(),
+            PUBLIC_INPUTS.join(", "),
+            public_inputs.len()
+        )));
+    }
+    let mut fields = Vec::with_capacity(PUBLIC_INPUTS.len() * 32 * 32);
+    for (name, value) in PUBLIC_INPUTS.iter().zip(public_inputs) {
+        if value.len() != 32 {
+            return Err(Risc0Error::VerificationFailed(format!("{name} is {} bytes, expected 32", value.len())));
+        }
+        for byte in value {
+            fields.extend_from_slice(&[0; 31]);
+            fields.push(*byte);
+        }
+    }
+    Ok(fields)
+}
+
+/// Runs `tool` in `dir`, reporting a tool that is not installed as
+/// [`Risc0Error::ToolchainMissing`] and any other failure to start it as `error`.
+fn spawn(tool: &str, args: &[&str], dir: &Path, error: fn(String) -> Risc0Error) -> Result<Output, Risc0Error> {
+    Command::new(tool).args(args).current_dir(dir).output().map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => Risc0Error::ToolchainMissing(format!("`{tool}` is not on PATH")),
+        _ => error(format!("failed to run `{tool}`: {e}")),
+    })
+}
+
+/// [`spawn`]s a proving step, reporting a failed run with its stderr.
+fn run(tool: &str, args: &[&str], dir: &Path) -> Result<Output, Risc0Error> {
+    let output = spawn(tool, args, dir, Risc0Error::ProveError)?;
+    if !output.status.success() {
+        return Err(Risc0Error::ProveError(format!(
+            "`{tool} {}` failed ({}): {}",
+            args.join(" "),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        )));
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::backend::mock::MockBackend;
+    use crate::tests::sample_request;
+    use crate::{AssertionJournal, Prover, ProverConfig, NO_ALLOWLIST, POLICY};
+
+    const APP_ID: &str = "LMRM26A744.xyz.elus.aegis.app-attester";
+    const CLIENT_DATA: &[u8] = b"x = 15";
+    /// `SHA256(nonce)` of the fixture assertion, and its signature and key.
+    const MESSAGE_HASH: &str = "cf844b753a9264b4980e7cbb75b98d8d2ffb11531f2c8ebd522359fb7109c76d";
+    const SIGNATURE_R: &str = "38fb757e98a68de08beb869019ba0f671b91204cb7fdbab318a1921d0ddb77e8";
+    const SIGNATURE_S: &str = "c98ff7da70a0e691a98095e10dca9b8bd0a9e6f10b37e175bda8c34dfc00c5b2";
+    const PUBLIC_KEY_X: &str = "85e322caa0f981bc18cd53574f1dc761c139d15030a36b1b249cc158c822c450";
+    const PUBLIC_KEY_Y: &str = "6b1574b6b1c5b5bfaf8e2a1dfe3deaa87c8802d863c8aac23aa990560a8a56b4";
+
+    fn array(hex: &str) -> [u8; 32] {
+        hex::decode(hex).unwrap().try_into().unwrap()
+    }
+
+    /// What the assertion guest commits for the fixture assertion.
+    fn fixture_journal() -> AssertionJournal {
+        AssertionJournal {
+            policy: POLICY,
+            rp_id_hash: Sha256::digest(APP_ID).into(),
+            public_key_x: array(PUBLIC_KEY_X),
+            public_key_y: array(PUBLIC_KEY_Y),
+            signature_r: array(SIGNATURE_R),
+            signature_s: array(SIGNATURE_S),
+            counter: 1,
+            client_data_hash: Sha256::digest(CLIENT_DATA).into(),
+            attestation_image_id: risc0_zkvm::sha::Digest::from(GuestKind::Attestation.image_id()).into(),
+            attestation_claim_digest: [7; 32],
+            challenge_hash: [0; 32],
+            allowlist_entry: NO_ALLOWLIST,
+            session_nonce: [0; 32],
+            input_digest: [0; 32],
+            policy_hash: Some([0; 32]),
+            cert_chain_image_id: Some(risc0_zkvm::sha::Digest::from(GuestKind::CertChain.image_id()).into()),
+        }
+    }
+
+    fn receipt(journal: AssertionJournal) -> Vec<u8> {
+        let backend = Arc::new(MockBackend::with_journal(journal.encode()));
+        let prover = Prover::with_backend(backend, ProverConfig::default());
+        prover.prove_assertion(sample_request()).unwrap().proof.receipt
+    }
+
+    fn fixture_inputs() -> NoirCircuitInputs {
+        NoirCircuitInputs {
+            sha256_hash: array(MESSAGE_HASH),
+            r: array(SIGNATURE_R),
+            s: array(SIGNATURE_S),
+            public_key_x: array(PUBLIC_KEY_X),
+            public_key_y: array(PUBLIC_KEY_Y),
+        }
+    }
+
+    /// `(name, type)` of every parameter of the circuit's `main`, `pub` included in the type.
+    fn main_parameters() -> Vec<(&'static str, &'static str)> {
+        let start = MAIN_NR.find("fn main(").expect("the circuit has a main") + "fn main(".len();
+        let end = start + MAIN_NR[start..].find(')').unwrap();
+        MAIN_NR[start..end]
+            .split(',')
+            .map(str::trim)
+            .filter(|parameter| !parameter.is_empty())
+            .map(|parameter| {
+                let (name, ty) = parameter.split_once(':').unwrap();
+                (name.trim(), ty.trim())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn inputs_come_from_the_receipt_journal() {
+        std::env::set_var("RISC0_DEV_MODE", "1");
+        let inputs = circuit_inputs_from_receipt(&receipt(fixture_journal()), CLIENT_DATA).unwrap();
+        assert_eq!(inputs, fixture_inputs());
+    }
+
+    #[test]
+    fn inputs_need_the_client_data_the_journal_commits() {
+        std::env::set_var("RISC0_DEV_MODE", "1");
+        let error = circuit_inputs_from_receipt(&receipt(fixture_journal()), b"x = 16").unwrap_err();
+        assert!(
+            matches!(error, Risc0Error::VerificationFailed(ref msg) if msg.contains("client_data_hash")),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn inputs_need_a_signature_over_the_rebuilt_authenticator_data() {
+        std::env::set_var("RISC0_DEV_MODE", "1");
+        let journal = AssertionJournal { counter: 2, ..fixture_journal() };
+        let error = circuit_inputs_from_receipt(&receipt(journal), CLIENT_DATA).unwrap_err();
+        assert!(matches!(error, Risc0Error::VerificationFailed(ref msg) if msg.contains("does not verify")), "{error}");
+    }
+
+    fn bundle() -> HybridProofOutput {
+        let noir = NoirProofOutput { proof: vec![1; 64], verification_key: vec![2; 64] };
+        HybridProofOutput::new(receipt(fixture_journal()), noir).unwrap()
+    }
+
+    #[test]
+    fn bundle_states_the_journal_values_once() {
+        let bundle = bundle();
+        assert_eq!(bundle.circuit_inputs().unwrap(), fixture_inputs());
+        assert_eq!(bundle.consistency_check(), Ok(()));
+        assert_eq!(HybridProofOutput::from_bytes(&bundle.to_bytes().unwrap()).unwrap(), bundle);
+    }
+
+    #[test]
+    fn mismatched_bundle_fails_the_consistency_check() {
+        let mut other_key = bundle();
+        other_key.public_key_x[0] ^= 0x01;
+        assert_eq!(
+            other_key.consistency_check(),
+            Err(Risc0Error::VerificationFailed(
+                "proof bundle states another public_key_x than its receipt's journal".into()
+            ))
+        );
+
+        // The Noir values of one assertion with the receipt of another.
+        let other = AssertionJournal { counter: 2, session_nonce: [9; 32], ..fixture_journal() };
+        let other_receipt = HybridProofOutput { receipt: receipt(other), ..bundle() };
+        assert!(matches!(other_receipt.consistency_check(), Err(Risc0Error::VerificationFailed(_))));
+
+        let mut truncated = bundle();
+        truncated.signature_s.pop();
+        assert_eq!(
+            truncated.consistency_check(),
+            Err(Risc0Error::VerificationFailed("proof bundle: s is 31 bytes, expected 32".into()))
+        );
+    }
+
+    #[test]
+    fn prover_toml_writes_each_input_as_hex_bytes() {
+        let toml = fixture_inputs().to_prover_toml();
+        let lines: Vec<&str> = toml.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert!(lines[0].starts_with(r#"sha256_hash = ["0xcf", "0x84", "0x4b","#), "{}", lines[0]);
+        assert!(lines[4].starts_with("public_key_y = [") && lines[4].ends_with(r#""0x56", "0xb4"]"#), "{}", lines[4]);
+
+        let parsed: toml::Table = toml.parse().unwrap();
+        assert_eq!(parsed["r"].as_array().unwrap().len(), 32);
+    }
+
+    #[test]
+    fn inputs_of_the_wrong_length_are_rejected() {
+        let signature = SignatureData {
+            signature_r: vec![0; 32],
+            signature_s: vec![0; 32],
+            public_key_x: vec![0; 31],
+            public_key_y: vec![0; 32],
+            client_data_hash: Vec::new(),
+            challenge_hash: Vec::new(),
+            session_nonce: Vec::new(),
+            policy: None,
+        };
+        let error = NoirCircuitInputs::new(&signature, &[0; 32]).unwrap_err();
+        assert_eq!(error, Risc0Error::ProveError("public_key_x is 31 bytes, expected 32".into()));
+    }
+
+    #[test]
+    fn a_missing_tool_is_a_typed_error() {
+        let error = run("mopro-no-such-noir-tool", &["--version"], Path::new(".")).unwrap_err();
+        assert_eq!(error, Risc0Error::ToolchainMissing("`mopro-no-such-noir-tool` is not on PATH".into()));
+        assert_eq!(error.code(), 2007);
+    }
+
+    #[test]
+    fn public_inputs_are_one_field_per_byte() {
+        let fields = encode_public_inputs(&[vec![1; 32], vec![2; 32], vec![3; 32]]).unwrap();
+        assert_eq!(fields.len(), 3 * 32 * 32);
+        let first = &fields[..32];
+        assert_eq!((first[..31].iter().all(|b| *b == 0), first[31]), (true, 1));
+        assert_eq!(fields[32 * 32 + 31], 2);
+        assert_eq!(fields[fields.len() - 1], 3);
+    }
+
+    #[test]
+    fn public_inputs_of_another_layout_are_rejected_before_bb_runs() {
+        // Errors the same with or without bb installed, which a ToolchainMissing would not.
+        let verify = |inputs: Vec<Vec<u8>>| verify_noir_proof(vec![1; 64], vec![1; 64], inputs).unwrap_err();
+        assert_eq!(
+            verify(vec![vec![0; 32]; 2]),
+            Risc0Error::VerificationFailed(
+                "expected 3 Noir public inputs (sha256_hash, public_key_x, public_key_y), got 2".into()
+            )
+        );
+        assert_eq!(
+            verify(vec![vec![0; 32], vec![0; 33], vec![0; 32]]),
+            Risc0Error::VerificationFailed("public_key_x is 33 bytes, expected 32".into())
+        );
+        assert!(matches!(
+            verify_noir_proof(Vec::new(), vec![1; 64], vec![vec![0; 32]; 3]),
+            Err(Risc0Error::VerificationFailed(_))
+        ));
+    }
+
+    #[cfg(feature = "noir")]
+    #[test]
+    fn embedded_verification_key_is_what_the_bindings_get() {
+        assert!(!VERIFICATION_KEY.is_empty());
+        assert_eq!(get_noir_verification_key(), VERIFICATION_KEY);
+        let digest = get_noir_verification_key_digest();
+        assert_eq!(digest.len(), 32);
+        assert_eq!(digest, sha3::Keccak256::digest(VERIFICATION_KEY).to_vec());
+    }
+
+    #[test]
+    fn circuit_main_takes_the_inputs_written_here() {
+        let parameters = main_parameters();
+        let names: Vec<&str> = parameters.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, NoirCircuitInputs::NAMES);
+        for (name, ty) in &parameters {
+            assert_eq!(ty.trim_start_matches("pub "), "[u8; 32]", "{name}");
+        }
+        let public: Vec<&str> =
+            parameters.iter().filter(|(_, ty)| ty.starts_with("pub ")).map(|(name, _)| *name).collect();
+        assert_eq!(public, PUBLIC_INPUTS);
+        assert!(NARGO_TOML.contains(&format!("name = \"{PACKAGE}\"")));
+    }
+}
@@ -0,0 +1,520 @@
+//! Protobuf messages for moving proofs between services, from `proto/mobiscale.proto`,
+//! and conversions from and to the native types.
+//!
+//! Converting to protobuf cannot fail. Converting back checks what protobuf cannot
+//! express: fixed-size byte fields have their length, single-byte numbers fit, message
+//! fields the native type requires are present and enums hold a known guest.
+
+use crate::{
+    AssertionProofOutput, AssertionRequest, AttestationRequest, BuildInfo, GuestKind, PolicyData, ProofMetadata,
+    Risc0ProofOutput, SignatureData, TimeTokenRequest,
+};
+
+/// The generated messages.
+pub mod pb {
+    include!(concat!(env!("OUT_DIR"), "/mobiscale.v1.rs"));
+}
+
+/// Why a message does not convert to its native type.
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum ProtoError {
+    #[error("missing {0}")]
+    MissingField(&'static str),
+    #[error("{field} has unknown value {value}")]
+    UnknownEnumValue { field: &'static str, value: i32 },
+    #[error("{field} is {actual} bytes, expected {expected}")]
+    InvalidLength { field: &'static str, expected: usize, actual: usize },
+    #[error("{field} is {value}, which does not fit in a byte")]
+    OutOfRange { field: &'static str, value: u32 },
+    #[error("expected a proof of the {expected:?} guest, got {actual:?}")]
+    WrongGuest { expected: GuestKind, actual: GuestKind },
+}
+
+fn fixed(field: &'static str, bytes: &[u8], expected: usize) -> Result<(), ProtoError> {
+    if bytes.len() == expected {
+        Ok(())
+    } else {
+        Err(ProtoError::InvalidLength { field, expected, actual: bytes.len() })
+    }
+}
+
+fn byte(field: &'static str, value: u32) -> Result<u8, ProtoError> {
+    u8::try_from(value).map_err(|_| ProtoError::OutOfRange { field, value })
+}
+
+fn required<T>(field: &'static str, value: Option<T>) -> Result<T, ProtoError> {
+    value.ok_or(ProtoError::MissingField(field))
+}
+
+impl From<GuestKind> for pb::GuestKind {
+    fn from(kind: GuestKind) -> Self {
+        match kind {
+            GuestKind::CertChain => pb::GuestKind::CertChain,
+            GuestKind::Attestation => pb::GuestKind::Attestation,
+            GuestKind::Assertion => pb::GuestKind::Assertion,
+        }
+    }
+}
+
+/// The guest in a message's `guest` field; unspecified counts as missing.
+fn guest(value: i32) -> Result<GuestKind, ProtoError> {
+    match pb::GuestKind::try_from(value) {
+        Ok(pb::GuestKind::CertChain) => Ok(GuestKind::CertChain),
+        Ok(pb::GuestKind::Attestation) => Ok(GuestKind::Attestation),
+        Ok(pb::GuestKind::Assertion) => Ok(GuestKind::Assertion),
+        Ok(pb::GuestKind::Unspecified) => Err(ProtoError::MissingField("guest")),
+        Err(_) => Err(ProtoError::UnknownEnumValue { field: "guest", value }),
+    }
+}
+
+impl From<TimeTokenRequest> for pb::TimeToken {
+    fn from(token: TimeTokenRequest) -> Self {
+        Self { signer: token.signer, signature: token.signature }
+    }
+}
+
+impl TryFrom<pb::TimeToken> for TimeTokenRequest {
+    type Error = ProtoError;
+
+    fn try_from(token: pb::TimeToken) -> Result<Self, ProtoError> {
+        fixed("time_token.signer", &token.signer, 65)?;
+        Ok(Self { signer: token.signer, signature: token.signature })
+    }
+}
+
+impl From<AttestationRequest> for pb::AttestationRequest {
+    fn from(request: AttestationRequest) -> Self {
+        Self {
+            attestation: request.attestation,
+            challenge: request.challenge,
+            app_id: request.app_id,
+            key_id: request.key_id,
+            timestamp: request.timestamp,
+            time_token: request.time_token.map(Into::into),
+        }
+    }
+}
+
+impl TryFrom<pb::AttestationRequest> for AttestationRequest {
+    type Error = ProtoError;
+
+    fn try_from(request: pb::AttestationRequest) -> Result<Self, ProtoError> {
+        Ok(Self {
+            attestation: request.attestation,
+            challenge: request.challenge,
+            app_id: request.app_id,
+            key_id: request.key_id,
+            timestamp: request.timestamp,
+            time_token: request.time_token.map(TryInto::try_into).transpose()?,
+        })
+    }
+}
+
+impl From<AssertionRequest> for pb::AssertionRequest {
+    fn from(request: AssertionRequest) -> Self {
+        Self {
+            assertion: request.assertion,
+            client_data: request.client_data,
+            app_id: request.app_id,
+            attestation_receipt: request.attestation_receipt,
+            previous_counter: request.previous_counter,
+            session_nonce: request.session_nonce,
+        }
+    }
+}
+
+impl TryFrom<pb::AssertionRequest> for AssertionRequest {
+    type Error = ProtoError;
+
+    fn try_from(request: pb::AssertionRequest) -> Result<Self, ProtoError> {
+        fixed("session_nonce", &request.session_nonce, 32)?;
+        Ok(Self {
+            assertion: request.assertion,
+            client_data: request.client_data,
+            app_id: request.app_id,
+            attestation_receipt: request.attestation_receipt,
+            previous_counter: request.previous_counter,
+            session_nonce: request.session_nonce,
+        })
+    }
+}
+
+impl From<PolicyData> for pb::Policy {
+    fn from(policy: PolicyData) -> Self {
+        Self {
+            appattest_major: policy.appattest_major.into(),
+            appattest_minor: policy.appattest_minor.into(),
+            appattest_patch: policy.appattest_patch.into(),
+            counter_policy: policy.counter_policy.into(),
+            low_s: policy.low_s,
+            environments: policy.environments.into(),
+        }
+    }
+}
+
+impl TryFrom<pb::Policy> for PolicyData {
+    type Error = ProtoError;
+
+    fn try_from(policy: pb::Policy) -> Result<Self, ProtoError> {
+        Ok(Self {
+            appattest_major: byte("policy.appattest_major", policy.appattest_major)?,
+            appattest_minor: byte("policy.appattest_minor", policy.appattest_minor)?,
+            appattest_patch: byte("policy.appattest_patch", policy.appattest_patch)?,
+            counter_policy: byte("policy.counter_policy", policy.counter_policy)?,
+            low_s: policy.low_s,
+            environments: byte("policy.environments", policy.environments)?,
+        })
+    }
+}
+
+impl From<SignatureData> for pb::SignatureData {
+    fn from(data: SignatureData) -> Self {
+        Self {
+            signature_r: data.signature_r,
+            signature_s: data.signature_s,
+            public_key_x: data.public_key_x,
+            public_key_y: data.public_key_y,
+            client_data_hash: data.client_data_hash,
+            challenge_hash: data.challenge_hash,
+            session_nonce: data.session_nonce,
+            policy: data.policy.map(Into::into),
+        }
+    }
+}
+
+impl TryFrom<pb::SignatureData> for SignatureData {
+    type Error = ProtoError;
+
+    fn try_from(data: pb::SignatureData) -> Result<Self, ProtoError> {
+        for (field, bytes) in [
+            ("signature_r", &data.signature_r),
+            ("signature_s", &data.signature_s),
+            ("public_key_x", &data.public_key_x),
+            ("public_key_y", &data.public_key_y),
+            ("client_data_hash", &data.client_data_hash),
+            ("challenge_hash", &data.challenge_hash),
+            ("session_nonce", &data.session_nonce),
+        ] {
+            fixed(field, bytes, 32)?;
+        }
+        Ok(Self {
+            signature_r: data.signature_r,
+            signature_s: data.signature_s,
+            public_key_x: data.public_key_x,
+            public_key_y: data.public_key_y,
+            client_data_hash: data.client_data_hash,
+            challenge_hash: data.challenge_hash,
+            session_nonce: data.session_nonce,
+            policy: data.policy.map(TryInto::try_into).transpose()?,
+        })
+    }
+}
+
+impl From<BuildInfo> for pb::BuildInfo {
+    fn from(info: BuildInfo) -> Self {
+        Self {
+            crate_version: info.crate_version,
+            risc0_version: info.risc0_version,
+            assertion_image_id_hex: info.assertion_image_id_hex,
+            attestation_image_id_hex: info.attestation_image_id_hex,
+            cert_chain_image_id_hex: info.cert_chain_image_id_hex,
+            git_commit: info.git_commit,
+            built_at: info.built_at,
+        }
+    }
+}
+
+impl From<pb::BuildInfo> for BuildInfo {
+    fn from(info: pb::BuildInfo) -> Self {
+        Self {
+            crate_version: info.crate_version,
+            risc0_version: info.risc0_version,
+            assertion_image_id_hex: info.assertion_image_id_hex,
+            attestation_image_id_hex: info.attestation_image_id_hex,
+            cert_chain_image_id_hex: info.cert_chain_image_id_hex,
+            git_commit: info.git_commit,
+            built_at: info.built_at,
+        }
+    }
+}
+
+impl From<ProofMetadata> for pb::ProofMetadata {
+    fn from(metadata: ProofMetadata) -> Self {
+        Self {
+            build_info: Some(metadata.build_info.into()),
+            image_id_hex: metadata.image_id_hex,
+            proving_time_ms: metadata.proving_time_ms,
+        }
+    }
+}
+
+impl TryFrom<pb::ProofMetadata> for ProofMetadata {
+    type Error = ProtoError;
+
+    fn try_from(metadata: pb::ProofMetadata) -> Result<Self, ProtoError> {
+        Ok(Self {
+            build_info: required("metadata.build_info", metadata.build_info)?.into(),
+            image_id_hex: metadata.image_id_hex,
+            proving_time_ms: metadata.proving_time_ms,
+        })
+    }
+}
+
+impl From<(GuestKind, Risc0ProofOutput)> for pb::ProofEnvelope {
+    fn from((guest, proof): (GuestKind, Risc0ProofOutput)) -> Self {
+        Self { guest: pb::GuestKind::from(guest).into(), receipt: proof.receipt, metadata: Some(proof.metadata.into()) }
+    }
+}
+
+impl TryFrom<pb::ProofEnvelope> for (GuestKind, Risc0ProofOutput) {
+    type Error = ProtoError;
+
+    fn try_from(envelope: pb::ProofEnvelope) -> Result<Self, ProtoError> {
+        let proof = Risc0ProofOutput {
+            receipt: envelope.receipt,
+            metadata: required("metadata", envelope.metadata)?.try_into()?,
+        };
+        Ok((guest(envelope.guest)?, proof))
+    }
+}
+
+/// The proof in `envelope`, which has to be of the `expected` guest.
+fn proof_of(expected: GuestKind, envelope: Option<pb::ProofEnvelope>) -> Result<Risc0ProofOutput, ProtoError> {
+    let (actual, proof) = required("proof", envelope)?.try_into()?;
+    if actual != expected {
+        return Err(ProtoError::WrongGuest { expected, actual });
+    }
+    Ok(proof)
+}
+
+/// An attestation guest proof, as `prove_attestation` returns it.
+impl From<Risc0ProofOutput> for pb::AttestationResult {
+    fn from(proof: Risc0ProofOutput) -> Self {
+        Self { proof: Some((GuestKind::Attestation, proof).into()) }
+    }
+}
+
+impl TryFrom<pb::AttestationResult> for Risc0ProofOutput {
+    type Error = ProtoError;
+
+    fn try_from(result: pb::AttestationResult) -> Result<Self, ProtoError> {
+        proof_of(GuestKind::Attestation, result.proof)
+    }
+}
+
+impl From<AssertionProofOutput> for pb::AssertionResult {
+    fn from(output: AssertionProofOutput) -> Self {
+        Self {
+            signature_data: Some(output.signature_data.into()),
+            proof: Some((GuestKind::Assertion, output.proof).into()),
+        }
+    }
+}
+
+impl TryFrom<pb::AssertionResult> for AssertionProofOutput {
+    type Error = ProtoError;
+
+    fn try_from(result: pb::AssertionResult) -> Result<Self, ProtoError> {
+        Ok(Self {
+            signature_data: required("signature_data", result.signature_data)?.try_into()?,
+            proof: proof_of(GuestKind::Assertion, result.proof)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+
+    use super::*;
+
+    /// Converts `value` to `P`, through the wire format and back.
+    fn round_trip<N, P>(value: N) -> N
+    where
+        P: Message + Default + From<N>,
+        N: TryFrom<P, Error = ProtoError>,
+    {
+        let bytes = P::from(value).encode_to_vec();
+        N::try_from(P::decode(bytes.as_slice()).unwrap()).unwrap()
+    }
+
+    fn policy() -> PolicyData {
+        PolicyData {
+            appattest_major: 0,
+            appattest_minor: 1,
+            appattest_patch: 0,
+            counter_policy: 1,
+            low_s: true,
+            environments: 3,
+        }
+    }
+
+    fn signature_data() -> SignatureData {
+        SignatureData {
+            signature_r: vec![1; 32],
+            signature_s: vec![2; 32],
+            public_key_x: vec![3; 32],
+            public_key_y: vec![4; 32],
+            client_data_hash: vec![5; 32],
+            challenge_hash: vec![0; 32],
+            session_nonce: vec![6; 32],
+            policy: Some(policy()),
+        }
+    }
+
+    fn proof() -> Risc0ProofOutput {
+        Risc0ProofOutput {
+            receipt: b"receipt".to_vec(),
+            metadata: ProofMetadata {
+                build_info: BuildInfo {
+                    crate_version: "0.1.0".into(),
+                    risc0_version: "2.0.2".into(),
+                    assertion_image_id_hex: "a1b2".into(),
+                    attestation_image_id_hex: "c3d4".into(),
+                    cert_chain_image_id_hex: "e5f6".into(),
+                    git_commit: "0123abc".into(),
+                    built_at: 1751700000,
+                },
+                image_id_hex: "c3d4".into(),
+                proving_time_ms: 4200,
+            },
+        }
+    }
+
+    #[test]
+    fn attestation_requests_round_trip() {
+        let request = AttestationRequest {
+            attestation: b"attestation".to_vec(),
+            challenge: b"challenge".to_vec(),
+            app_id: "TEAM.app".into(),
+            key_id: vec![9; 32],
+            timestamp: 1751800000,
+            time_token: Some(TimeTokenRequest { signer: vec![4; 65], signature: vec![0x30, 0x44] }),
+        };
+        assert_eq!(round_trip::<_, pb::AttestationRequest>(request.clone()), request);
+        let untimed = AttestationRequest { time_token: None, ..request };
+        assert_eq!(round_trip::<_, pb::AttestationRequest>(untimed.clone()), untimed);
+    }
+
+    #[test]
+    fn time_tokens_round_trip_and_check_the_signer_length() {
+        let token = TimeTokenRequest { signer: vec![4; 65], signature: vec![0x30, 0x44] };
+        assert_eq!(round_trip::<_, pb::TimeToken>(token.clone()), token);
+
+        let short = pb::TimeToken { signer: vec![4; 33], signature: Vec::new() };
+        assert_eq!(
+            TimeTokenRequest::try_from(short),
+            Err(ProtoError::InvalidLength { field: "time_token.signer", expected: 65, actual: 33 })
+        );
+    }
+
+    #[test]
+    fn assertion_requests_round_trip_and_check_the_nonce_length() {
+        let request = AssertionRequest {
+            assertion: b"assertion".to_vec(),
+            client_data: b"{\"x\":15}".to_vec(),
+            app_id: "TEAM.app".into(),
+            attestation_receipt: b"receipt".to_vec(),
+            previous_counter: Some(3),
+            session_nonce: vec![7; 32],
+        };
+        assert_eq!(round_trip::<_, pb::AssertionRequest>(request.clone()), request);
+
+        let message = pb::AssertionRequest { session_nonce: Vec::new(), ..request.into() };
+        assert_eq!(
+            AssertionRequest::try_from(message),
+            Err(ProtoError::InvalidLength { field: "session_nonce", expected: 32, actual: 0 })
+        );
+    }
+
+    #[test]
+    fn policies_round_trip_and_reject_numbers_over_a_byte() {
+        assert_eq!(round_trip::<_, pb::Policy>(policy()), policy());
+
+        let message = pb::Policy { environments: 256, ..policy().into() };
+        assert_eq!(
+            PolicyData::try_from(message),
+            Err(ProtoError::OutOfRange { field: "policy.environments", value: 256 })
+        );
+    }
+
+    #[test]
+    fn signature_data_round_trips_and_checks_every_length() {
+        assert_eq!(round_trip::<_, pb::SignatureData>(signature_data()), signature_data());
+        let unpolicied = SignatureData { policy: None, ..signature_data() };
+        assert_eq!(round_trip::<_, pb::SignatureData>(unpolicied.clone()), unpolicied);
+
+        let message = pb::SignatureData { public_key_y: vec![4; 33], ..signature_data().into() };
+        assert_eq!(
+            SignatureData::try_from(message),
+            Err(ProtoError::InvalidLength { field: "public_key_y", expected: 32, actual: 33 })
+        );
+    }
+
+    #[test]
+    fn build_info_and_metadata_round_trip() {
+        let info = proof().metadata.build_info;
+        let bytes = pb::BuildInfo::from(info.clone()).encode_to_vec();
+        assert_eq!(BuildInfo::from(pb::BuildInfo::decode(bytes.as_slice()).unwrap()), info);
+
+        assert_eq!(round_trip::<_, pb::ProofMetadata>(proof().metadata), proof().metadata);
+        let message = pb::ProofMetadata { build_info: None, ..proof().metadata.into() };
+        assert_eq!(ProofMetadata::try_from(message), Err(ProtoError::MissingField("metadata.build_info")));
+    }
+
+    #[test]
+    fn envelopes_round_trip_for_every_guest() {
+        for kind in [GuestKind::CertChain, GuestKind::Attestation, GuestKind::Assertion] {
+            assert_eq!(round_trip::<_, pb::ProofEnvelope>((kind, proof())), (kind, proof()));
+        }
+    }
+
+    #[test]
+    fn envelopes_reject_unknown_and_unspecified_guests_and_missing_metadata() {
+        let envelope = pb::ProofEnvelope::from((GuestKind::Assertion, proof()));
+        let convert = <(GuestKind, Risc0ProofOutput)>::try_from;
+
+        assert_eq!(
+            convert(pb::ProofEnvelope { guest: 42, ..envelope.clone() }),
+            Err(ProtoError::UnknownEnumValue { field: "guest", value: 42 })
+        );
+        assert_eq!(
+            convert(pb::ProofEnvelope { guest: pb::GuestKind::Unspecified.into(), ..envelope.clone() }),
+            Err(ProtoError::MissingField("guest"))
+        );
+        assert_eq!(
+            convert(pb::ProofEnvelope { metadata: None, ..envelope }),
+            Err(ProtoError::MissingField("metadata"))
+        );
+    }
+
+    #[test]
+    fn attestation_results_round_trip_and_hold_attestation_proofs_only() {
+        assert_eq!(round_trip::<_, pb::AttestationResult>(proof()), proof());
+
+        let wrong = pb::AttestationResult { proof: Some((GuestKind::CertChain, proof()).into()) };
+        assert_eq!(
+            Risc0ProofOutput::try_from(wrong),
+            Err(ProtoError::WrongGuest { expected: GuestKind::Attestation, actual: GuestKind::CertChain })
+        );
+        assert_eq!(
+            Risc0ProofOutput::try_from(pb::AttestationResult { proof: None }),
+            Err(ProtoError::MissingField("proof"))
+        );
+    }
+
+    #[test]
+    fn assertion_results_round_trip_and_require_signature_data() {
+        let output = AssertionProofOutput { signature_data: signature_data(), proof: proof() };
+        assert_eq!(round_trip::<_, pb::AssertionResult>(output.clone()), output);
+
+        let message = pb::AssertionResult { signature_data: None, ..output.clone().into() };
+        assert_eq!(AssertionProofOutput::try_from(message), Err(ProtoError::MissingField("signature_data")));
+
+        let wrong = pb::AssertionResult { proof: Some((GuestKind::Attestation, proof()).into()), ..output.into() };
+        assert_eq!(
+            AssertionProofOutput::try_from(wrong),
+            Err(ProtoError::WrongGuest { expected: GuestKind::Assertion, actual: GuestKind::Attestation })
+        );
+    }
+}
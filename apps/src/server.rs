@@ -0,0 +1,373 @@
+//! HTTP verification service over the appattest types, served by the `server` binary.
+//!
+//! Every route takes and returns JSON. Failures are reported as
+//! `{"error": {"code": <u16>, "message": "..."}}`, where `code` is the stable
+//! [`AppAttestError::code`] or [`Risc0Error::code`] of the underlying error.
+//!
+//! The exception is [`upload_router`]'s `PUT /uploads/{id}`, which takes proof bundles in
+//! raw chunks and answers with status codes, as the `uploader` module describes.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use appattest_rs::attestation::{AttestationRef, Environment};
+use appattest_rs::error::AppAttestError;
+use appattest_rs::key::KeyId;
+use appattest_rs::platform::AppAttestKey;
+use appattest_rs::sensitive::base64_problem;
+use appattest_rs::service::AppAttestService;
+use appattest_rs::store::{ChallengeStore, KeyRecord, KeyStore, KeyWarning};
+use axum::body::Bytes;
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{post, put};
+use axum::{Json, Router};
+use base64::{engine::general_purpose, Engine};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    check_attestation_guest, check_cert_chain_guest, check_session_nonce, cli::now_unix, decode_assertion_journal,
+    decode_attestation_journal, verify_receipt, GuestKind, Risc0Error,
+};
+
+pub struct ServerConfig {
+    /// `<team id>.<bundle id>` that attestations and assertions must be bound to.
+    pub app_id: String,
+    /// Accept keys from the App Attest development environment.
+    pub development: bool,
+    /// Fixed Unix time for certificate validity checks; the clock is used when `None`.
+    pub verification_time: Option<i64>,
+}
+
+#[derive(Clone)]
+pub struct ServerState {
+    pub keys: Arc<dyn KeyStore>,
+    pub challenges: Arc<dyn ChallengeStore>,
+    pub config: Arc<ServerConfig>,
+}
+
+pub fn router(state: ServerState) -> Router {
+    Router::new()
+        .route("/challenge", post(challenge))
+        .route("/attest", post(attest))
+        .route("/assert", post(assert))
+        .route("/verify-proof", post(verify_proof))
+        .with_state(state)
+}
+
+#[derive(Serialize)]
+pub struct ChallengeResponse {
+    pub challenge: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttestRequest {
+    pub key_id: String,
+    pub challenge: String,
+    pub attestation: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttestResponse {
+    pub key_id: String,
+    /// Uncompressed SEC1 public key, hex.
+    pub public_key: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssertRequest {
+    pub key_id: String,
+    pub assertion: String,
+    pub client_data: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssertResponse {
+    pub key_id: String,
+    pub counter: u32,
+    /// Set once the key's counter nears `u32::MAX`; the client should attest a new key.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<KeyWarning>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyProofRequest {
+    /// Base64 bincode-serialized receipt.
+    pub receipt: String,
+    /// `"attestation"` or `"assertion"`; both image IDs are tried when absent.
+    pub guest: Option<String>,
+    /// A challenge from `/challenge` the proof was requested with as its session nonce.
+    /// When present, only an assertion receipt committing it is accepted, and only once.
+    pub session_nonce: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyProofResponse {
+    pub guest: &'static str,
+    pub journal: String,
+}
+
+/// Error response carrying a stable code.
+pub struct ApiError {
+    status: StatusCode,
+    code: u16,
+    message: String,
+}
+
+impl From<AppAttestError> for ApiError {
+    fn from(err: AppAttestError) -> Self {
+        let status = match err {
+            AppAttestError::UnknownKeyID => StatusCode::NOT_FOUND,
+            _ => StatusCode::BAD_REQUEST,
+        };
+        Self {
+            status,
+            code: err.code(),
+            message: err.to_string(),
+        }
+    }
+}
+
+impl From<Risc0Error> for ApiError {
+    fn from(err: Risc0Error) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            code: err.code(),
+            message: err.to_string(),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = serde_json::json!({ "error": { "code": self.code, "message": self.message } });
+        (self.status, Json(body)).into_response()
+    }
+}
+
+type ApiResult<T> = Result<Json<T>, ApiError>;
+
+async fn challenge(State(state): State<ServerState>) -> ApiResult<ChallengeResponse> {
+    Ok(Json(ChallengeResponse {
+        challenge: state.challenges.issue()?,
+    }))
+}
+
+async fn attest(State(state): State<ServerState>, Json(req): Json<AttestRequest>) -> ApiResult<AttestResponse> {
+    if !state.challenges.consume(&req.challenge) {
+        return Err(AppAttestError::UnknownChallenge.into());
+    }
+
+    let key_id = KeyId::from_base64(&req.key_id)?;
+    let config = &state.config;
+    let time = config.verification_time.unwrap_or_else(now_unix);
+    // Parsed in place: the certificates and receipt are not copied out of the decoded body.
+    let attestation = general_purpose::STANDARD
+        .decode(&req.attestation)
+        .map_err(|e| AppAttestError::Message(format!("Failed to decode Base64: {}", base64_problem(&e))))?;
+    let challenge = general_purpose::STANDARD
+        .decode(&req.challenge)
+        .map_err(|e| AppAttestError::Message(format!("Failed to decode Base64 challenge: {}", base64_problem(&e))))?;
+    let app_id_hash: [u8; 32] = Sha256::digest(config.app_id.as_bytes()).into();
+    let verified = AttestationRef::from_cbor(&attestation)?.verify_raw(&challenge, &app_id_hash, key_id.as_bytes(), time)?;
+    if verified.environment == Environment::Development && !config.development {
+        return Err(AppAttestError::InvalidAAGUID.into());
+    }
+    let public_key = verified.public_key;
+
+    let record = KeyRecord::new(&AppAttestKey::new(public_key.clone(), None)?, verified.receipt);
+    state.keys.put(KeyRecord { leaf_not_after: verified.leaf_not_after, ..record })?;
+    Ok(Json(AttestResponse {
+        key_id: key_id.to_string(),
+        public_key: hex::encode(public_key),
+    }))
+}
+
+async fn assert(State(state): State<ServerState>, Json(req): Json<AssertRequest>) -> ApiResult<AssertResponse> {
+    let key_id = KeyId::from_base64(&req.key_id)?;
+    let assertion = general_purpose::STANDARD
+        .decode(&req.assertion)
+        .map_err(|e| AppAttestError::Message(format!("Failed to decode Base64: {}", base64_problem(&e))))?;
+    let client_data = general_purpose::STANDARD
+        .decode(&req.client_data)
+        .map_err(|_| AppAttestError::Message("failed to decode client data".to_string()))?;
+
+    // Only Apple keys are attested here so far; the service looks up each key's platform.
+    let service = AppAttestService::new(state.keys.clone(), state.config.app_id.as_str());
+    let accepted = service.assert(&key_id, &assertion, &client_data)?;
+    Ok(Json(AssertResponse {
+        key_id: key_id.to_string(),
+        counter: accepted.counter,
+        warning: accepted.warning,
+    }))
+}
+
+async fn verify_proof(
+    State(state): State<ServerState>,
+    Json(req): Json<VerifyProofRequest>,
+) -> ApiResult<VerifyProofResponse> {
+    let receipt = general_purpose::STANDARD
+        .decode(&req.receipt)
+        .map_err(|e| Risc0Error::InvalidReceipt(format!("receipt is not base64: {}", base64_problem(&e))))?;
+    let candidates = match req.guest.as_deref() {
+        None => vec![GuestKind::Assertion, GuestKind::Attestation],
+        Some("assertion") => vec![GuestKind::Assertion],
+        Some("attestation") => vec![GuestKind::Attestation],
+        Some(other) => return Err(Risc0Error::InvalidReceipt(format!("unknown guest {other:?}")).into()),
+    };
+    // Consumed up front, like an attestation challenge: a nonce backs one attempt at most.
+    let session_nonce = match &req.session_nonce {
+        Some(nonce) if !state.challenges.consume(nonce) => return Err(AppAttestError::UnknownChallenge.into()),
+        Some(nonce) => Some(
+            general_purpose::STANDARD
+                .decode(nonce)
+                .map_err(|_| AppAttestError::UnknownChallenge)?,
+        ),
+        None => None,
+    };
+
+    // Verifying a receipt is CPU-bound; keep it off the async workers.
+    let (guest, journal) = tokio::task::spawn_blocking(move || {
+        let mut last_err = None;
+        for guest in candidates {
+            match verify_receipt(&receipt, guest) {
+                Ok(verified) => return Ok((guest, verified.journal.bytes)),
+                Err(e) => last_err = Some(e),
+            }
+        }
+        Err(last_err.expect("at least one candidate guest"))
+    })
+    .await
+    .map_err(|e| Risc0Error::VerificationFailed(e.to_string()))??;
+
+    if guest == GuestKind::Attestation {
+        check_cert_chain_guest(&decode_attestation_journal(&journal)?)?;
+    }
+    if guest == GuestKind::Assertion {
+        check_attestation_guest(&decode_assertion_journal(&journal)?)?;
+    }
+    if let Some(expected) = session_nonce {
+        if guest != GuestKind::Assertion {
+            return Err(Risc0Error::VerificationFailed("only assertion receipts commit a session nonce".into()).into());
+        }
+        check_session_nonce(&decode_assertion_journal(&journal)?, &expected)?;
+    }
+
+    Ok(Json(VerifyProofResponse {
+        guest: guest.name(),
+        journal: hex::encode(journal),
+    }))
+}
+
+/// Largest bundle `PUT /uploads/{id}` takes. Chunks are further limited to axum's default
+/// body limit of 2 MiB.
+pub const MAX_UPLOAD_SIZE: u64 = 16 << 20;
+
+/// Bundles arriving through [`upload_router`], held in memory.
+#[derive(Default)]
+pub struct Uploads(Mutex<HashMap<String, Upload>>);
+
+enum Upload {
+    Partial { total: u64, bytes: Vec<u8> },
+    Complete(Vec<u8>),
+}
+
+impl Uploads {
+    /// The bundle uploaded as `id`, once all of it has arrived.
+    pub fn get(&self, id: &str) -> Option<Vec<u8>> {
+        match self.0.lock().unwrap().get(id) {
+            Some(Upload::Complete(bytes)) => Some(bytes.clone()),
+            _ => None,
+        }
+    }
+
+    /// Adds `chunk`, the bytes from `first` on of an upload of `total` bytes, or with
+    /// `None` only reports on the upload, and says how to answer.
+    fn put(&self, id: &str, total: u64, chunk: Option<(u64, &[u8])>) -> Response {
+        let mut uploads = self.0.lock().unwrap();
+        let upload = uploads.entry(id.to_string()).or_insert_with(|| Upload::Partial { total, bytes: Vec::new() });
+        let bytes = match upload {
+            Upload::Complete(bytes) if bytes.len() as u64 == total => return StatusCode::CREATED.into_response(),
+            Upload::Partial { total: started, bytes } if *started == total => bytes,
+            _ => return StatusCode::CONFLICT.into_response(),
+        };
+        if let Some((first, chunk)) = chunk {
+            let held = bytes.len() as u64;
+            // A chunk past a gap is dropped; the client learns where to resume from the answer.
+            if first <= held {
+                let new = (held - first) as usize;
+                bytes.extend(chunk.get(new..).unwrap_or_default());
+            }
+        }
+        if bytes.len() as u64 == total {
+            if hex::encode(Sha256::digest(&bytes[..])) != id {
+                uploads.remove(id);
+                return StatusCode::UNPROCESSABLE_ENTITY.into_response();
+            }
+            *upload = Upload::Complete(std::mem::take(bytes));
+            return StatusCode::CREATED.into_response();
+        }
+        match bytes.len() {
+            0 => StatusCode::PERMANENT_REDIRECT.into_response(),
+            held => {
+                let range = [(header::RANGE, format!("bytes=0-{}", held - 1))];
+                (StatusCode::PERMANENT_REDIRECT, range).into_response()
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+struct UploadState {
+    uploads: Arc<Uploads>,
+    authorization: Arc<str>,
+}
+
+/// `PUT /uploads/{id}`, the server end of the `uploader` wire format, for clients that send
+/// `auth_token`. Finished bundles are in `uploads`.
+pub fn upload_router(uploads: Arc<Uploads>, auth_token: &str) -> Router {
+    let authorization = format!("Bearer {auth_token}").into();
+    Router::new().route("/uploads/{id}", put(upload)).with_state(UploadState { uploads, authorization })
+}
+
+async fn upload(State(state): State<UploadState>, Path(id): Path<String>, headers: HeaderMap, body: Bytes) -> Response {
+    let authorized = headers.get(header::AUTHORIZATION).and_then(|value| value.to_str().ok());
+    if authorized != Some(&*state.authorization) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    let is_digest = id.len() == 64 && id.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'));
+    let range = headers.get(header::CONTENT_RANGE).and_then(|value| value.to_str().ok()).and_then(content_range);
+    let (Some((range, total)), true) = (range, is_digest) else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+    if total > MAX_UPLOAD_SIZE {
+        return StatusCode::PAYLOAD_TOO_LARGE.into_response();
+    }
+    let chunk = match range {
+        None if body.is_empty() => None,
+        Some((first, last)) if last < total && last - first + 1 == body.len() as u64 => Some((first, &body[..])),
+        _ => return StatusCode::BAD_REQUEST.into_response(),
+    };
+    state.uploads.put(&id, total, chunk)
+}
+
+/// `bytes {first}-{last}/{total}` as `(Some((first, last)), total)`, or `bytes */{total}`
+/// as `(None, total)`.
+fn content_range(value: &str) -> Option<(Option<(u64, u64)>, u64)> {
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let total = total.parse().ok()?;
+    if range == "*" {
+        return Some((None, total));
+    }
+    let (first, last) = range.split_once('-')?;
+    let (first, last): (u64, u64) = (first.parse().ok()?, last.parse().ok()?);
+    (first <= last).then_some((Some((first, last)), total))
+}
@@ -0,0 +1,307 @@
+//! Uploading a proof from the device in chunks, so that a dropped connection on a mobile
+//! network costs one chunk rather than the whole receipt.
+//!
+//! # Wire format
+//!
+//! Modelled on resumable uploads to cloud object stores. A bundle is uploaded to
+//! `{endpoint}/{id}`, where `id` is the lowercase hex SHA-256 of its bytes, so retrying, or
+//! uploading again after the app restarts, continues the same upload. Every request is a
+//! `PUT` with `Authorization: Bearer <auth token>` and a `Content-Range`:
+//!
+//! * `bytes {first}-{last}/{total}` sends the bytes `first..=last` of `total` as the body.
+//! * `bytes */{total}`, with an empty body, asks how much has arrived.
+//!
+//! The server answers:
+//!
+//! * `308` while the upload is incomplete, with `Range: bytes=0-{last}` for the bytes it
+//!   holds, or no `Range` if it holds none. A chunk that starts past them is not stored; one
+//!   that overlaps them only adds its new bytes.
+//! * `201` once all `total` bytes have arrived and hash to `id`, and to any request for the
+//!   upload after that.
+//! * `401` for a wrong token, `400` for a malformed `id` or `Content-Range`, `409` for a
+//!   `total` other than the one the upload started with, `413` for a bundle over the
+//!   server's limit, and `422` if the bytes do not hash to `id`, which starts it over.
+//!
+//! [`ProofUploader`] asks first, then sends chunks from the last acknowledged byte on. A
+//! request that gets no answer, or a `408`, `429` or `5xx`, is retried after a backoff,
+//! asking again where to resume; any other status ends the upload. The server side is
+//! `server::upload_router`, with the `server` feature.
+
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use sha2::{Digest, Sha256};
+
+use crate::config::backoff;
+use crate::Risc0ProofOutput;
+
+/// Longest a single request may take, within the overall timeout.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A proof to upload: a receipt serialized with bincode, as in [`Risc0ProofOutput::receipt`].
+#[derive(uniffi::Record, Clone, Debug, PartialEq)]
+pub struct ProofBundle {
+    pub receipt: Vec<u8>,
+}
+
+impl ProofBundle {
+    /// The ID the bundle is uploaded under: the lowercase hex SHA-256 of its bytes.
+    pub fn upload_id(&self) -> String {
+        hex::encode(Sha256::digest(&self.receipt))
+    }
+}
+
+impl From<&Risc0ProofOutput> for ProofBundle {
+    fn from(output: &Risc0ProofOutput) -> Self {
+        ProofBundle { receipt: output.receipt.clone() }
+    }
+}
+
+/// How a [`ProofUploader`] splits and retries an upload.
+#[derive(uniffi::Record, Clone, Debug, PartialEq)]
+pub struct UploadConfig {
+    /// Bytes sent per request.
+    pub chunk_size: u64,
+    /// Failed requests in a row before giving up; any progress resets the count.
+    pub max_attempts: u32,
+    /// Pause after the first failure; doubled after every further one and jittered by up to 25%.
+    pub backoff_ms: u64,
+    /// Longest the whole upload may take, pauses included.
+    pub timeout_ms: u64,
+}
+
+impl Default for UploadConfig {
+    fn default() -> Self {
+        Self { chunk_size: 256 * 1024, max_attempts: 8, backoff_ms: 500, timeout_ms: 120_000 }
+    }
+}
+
+/// What a finished upload did.
+#[derive(uniffi::Record, Clone, Debug, PartialEq)]
+pub struct UploadResult {
+    /// The ID the server holds the bundle under, see [`ProofBundle::upload_id`].
+    pub upload_id: String,
+    pub size: u64,
+    /// Requests that failed and were retried.
+    pub retries: u32,
+}
+
+#[derive(uniffi::Error, thiserror::Error, Debug, Clone, PartialEq)]
+pub enum UploadError {
+    #[error("invalid upload endpoint: {0}")]
+    InvalidEndpoint(String),
+    /// The server did not accept the auth token.
+    #[error("upload unauthorized")]
+    Unauthorized,
+    /// The server refused the upload in a way retrying does not change, see the wire format.
+    #[error("upload rejected with HTTP {status}")]
+    Rejected { status: u16 },
+    /// The server answered in a way the wire format does not allow.
+    #[error("upload protocol error: {0}")]
+    Protocol(String),
+    /// `max_attempts` requests in a row failed.
+    #[error("upload failed after {attempts} attempts: {last_error}")]
+    Failed { attempts: u32, last_error: String },
+    /// The upload took longer than `timeout_ms`; `sent` bytes had been acknowledged.
+    #[error("upload timed out after {sent} of {total} bytes")]
+    TimedOut { sent: u64, total: u64 },
+}
+
+/// Receives progress notifications from a [`ProofUploader`].
+#[uniffi::export(with_foreign)]
+pub trait UploadProgressListener: Send + Sync {
+    /// Called whenever the server acknowledges more bytes, and once more when all `total`
+    /// have arrived. `sent` never decreases within an upload.
+    fn on_progress(&self, sent: u64, total: u64);
+}
+
+#[derive(uniffi::Object)]
+pub struct ProofUploader {
+    endpoint: String,
+    auth_token: String,
+    config: UploadConfig,
+    client: reqwest::blocking::Client,
+    listener: RwLock<Option<Arc<dyn UploadProgressListener>>>,
+}
+
+/// The server's answer to one request.
+enum Acknowledged {
+    /// The bytes before this offset have arrived.
+    Upto(u64),
+    Complete,
+}
+
+/// Why a request did not get an [`Acknowledged`].
+enum Failure {
+    Retry(String),
+    Fatal(UploadError),
+}
+
+impl ProofUploader {
+    fn build(endpoint: &str, auth_token: &str, config: UploadConfig) -> Result<Self, UploadError> {
+        let url = reqwest::Url::parse(endpoint).map_err(|e| UploadError::InvalidEndpoint(e.to_string()))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(UploadError::InvalidEndpoint(format!("{endpoint} is not an HTTP URL")));
+        }
+        // A 308 here means "resume", not a redirect.
+        let client = reqwest::blocking::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .map_err(|e| UploadError::InvalidEndpoint(e.to_string()))?;
+        Ok(ProofUploader {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            auth_token: auth_token.to_string(),
+            config,
+            client,
+            listener: RwLock::new(None),
+        })
+    }
+
+    fn progress(&self, sent: u64, total: u64) {
+        if let Some(listener) = self.listener.read().unwrap().as_ref() {
+            listener.on_progress(sent, total);
+        }
+    }
+
+    /// Sends `range`, with `body` if it is a chunk, and reads the answer.
+    fn put(&self, url: &str, range: &str, body: &[u8], timeout: Duration) -> Result<Acknowledged, Failure> {
+        let response = self
+            .client
+            .put(url)
+            .bearer_auth(&self.auth_token)
+            .header(reqwest::header::CONTENT_RANGE, range)
+            .body(body.to_vec())
+            .timeout(timeout.min(REQUEST_TIMEOUT))
+            .send()
+            .map_err(|e| Failure::Retry(e.to_string()))?;
+        match response.status().as_u16() {
+            200 | 201 => Ok(Acknowledged::Complete),
+            308 => {
+                let range = response.headers().get(reqwest::header::RANGE).map(|value| value.to_str());
+                match range {
+                    None => Ok(Acknowledged::Upto(0)),
+                    Some(Ok(range)) => acknowledged(range)
+                        .map(Acknowledged::Upto)
+                        .ok_or_else(|| Failure::Fatal(UploadError::Protocol(format!("malformed Range {range:?}")))),
+                    Some(Err(_)) => Err(Failure::Fatal(UploadError::Protocol("Range is not text".into()))),
+                }
+            }
+            401 => Err(Failure::Fatal(UploadError::Unauthorized)),
+            status @ (408 | 429 | 500..=599) => Err(Failure::Retry(format!("HTTP {status}"))),
+            status => Err(Failure::Fatal(UploadError::Rejected { status })),
+        }
+    }
+}
+
+#[uniffi::export]
+impl ProofUploader {
+    /// Uploads to `endpoint`, authorized by `auth_token`, with the default [`UploadConfig`].
+    #[uniffi::constructor]
+    pub fn new(endpoint: String, auth_token: String) -> Result<Arc<Self>, UploadError> {
+        Self::with_config(endpoint, auth_token, UploadConfig::default())
+    }
+
+    /// Uploads to `endpoint`, authorized by `auth_token`, split and retried as `config` says.
+    #[uniffi::constructor]
+    pub fn with_config(endpoint: String, auth_token: String, config: UploadConfig) -> Result<Arc<Self>, UploadError> {
+        Self::build(&endpoint, &auth_token, config).map(Arc::new)
+    }
+
+    pub fn set_progress_listener(&self, listener: Option<Arc<dyn UploadProgressListener>>) {
+        *self.listener.write().unwrap() = listener;
+    }
+
+    /// Uploads `bundle`, resuming whatever part of it the server already holds, and blocks
+    /// until all of it has arrived or the upload fails.
+    pub fn upload(&self, bundle: &ProofBundle) -> Result<UploadResult, UploadError> {
+        let deadline = Instant::now() + Duration::from_millis(self.config.timeout_ms);
+        let data = &bundle.receipt;
+        let total = data.len() as u64;
+        let upload_id = bundle.upload_id();
+        let url = format!("{}/{upload_id}", self.endpoint);
+        let chunk_size = self.config.chunk_size.max(1);
+
+        let mut sent = 0;
+        let mut failures = 0;
+        let mut retries = 0;
+        // Ask before sending anything: an earlier attempt may have left part of the bundle.
+        let mut ask = true;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(UploadError::TimedOut { sent, total });
+            }
+            let result = if ask {
+                self.put(&url, &format!("bytes */{total}"), &[], remaining)
+            } else {
+                let end = total.min(sent + chunk_size);
+                let chunk = &data[sent as usize..end as usize];
+                self.put(&url, &format!("bytes {sent}-{}/{total}", end - 1), chunk, remaining)
+            };
+            match result {
+                Ok(Acknowledged::Complete) => {
+                    self.progress(total, total);
+                    return Ok(UploadResult { upload_id, size: total, retries });
+                }
+                Ok(Acknowledged::Upto(upto)) if upto > total => {
+                    return Err(UploadError::Protocol(format!("{upto} bytes acknowledged of {total}")));
+                }
+                Ok(Acknowledged::Upto(upto)) => {
+                    if upto > sent {
+                        failures = 0;
+                        self.progress(upto, total);
+                    }
+                    // Normally `upto >= sent`; if the server lost bytes, send them again.
+                    sent = upto;
+                    ask = false;
+                }
+                Err(Failure::Fatal(error)) => return Err(error),
+                Err(Failure::Retry(error)) => {
+                    failures += 1;
+                    retries += 1;
+                    if failures >= self.config.max_attempts {
+                        return Err(UploadError::Failed { attempts: failures, last_error: error });
+                    }
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    thread::sleep(backoff(self.config.backoff_ms, failures).min(remaining));
+                    ask = true;
+                }
+            }
+        }
+    }
+}
+
+/// The bytes a `Range: bytes=0-{last}` acknowledges, `last + 1`.
+fn acknowledged(range: &str) -> Option<u64> {
+    let last: u64 = range.trim().strip_prefix("bytes=0-")?.parse().ok()?;
+    last.checked_add(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_acknowledged_range() {
+        assert_eq!(acknowledged("bytes=0-0"), Some(1));
+        assert_eq!(acknowledged("bytes=0-262143"), Some(262_144));
+        for bad in ["", "bytes=0-", "bytes=1-5", "bytes=0--1", "items=0-5", "bytes=0-18446744073709551615"] {
+            assert_eq!(acknowledged(bad), None, "{bad}");
+        }
+    }
+
+    #[test]
+    fn only_http_endpoints() {
+        assert!(ProofUploader::new("https://example.com/uploads/".into(), "token".into()).is_ok());
+        for bad in ["example.com/uploads", "ftp://example.com/uploads"] {
+            assert!(matches!(ProofUploader::new(bad.into(), "token".into()), Err(UploadError::InvalidEndpoint(_))));
+        }
+    }
+
+    #[test]
+    fn upload_id_is_the_digest_of_the_receipt() {
+        let bundle = ProofBundle { receipt: b"abc".to_vec() };
+        assert_eq!(bundle.upload_id(), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    }
+}
@@ -0,0 +1,312 @@
+use risc0_zkvm::Receipt;
+
+use crate::{
+    decode_assertion_journal, decode_attestation_journal, AssertionJournal, AttestationJournal, GuestKind, Policy,
+    Risc0Error, SignatureData, VerificationPolicy, VerificationPolicyData, VerifiedAttestation,
+};
+
+/// Decodes a bincode-serialized receipt as written by the prove functions.
+pub fn deserialize_receipt(bytes: &[u8]) -> Result<Receipt, Risc0Error> {
+    Ok(mobiscale_verifier::deserialize_receipt(bytes)?)
+}
+
+/// Decodes `bytes` and verifies the receipt against the image ID of `kind`.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    name = "receipt.verify", skip_all, fields(guest = ?kind, receipt_len = bytes.len()), err(level = "info"),
+))]
+pub fn verify_receipt(bytes: &[u8], kind: GuestKind) -> Result<Receipt, Risc0Error> {
+    Ok(mobiscale_verifier::verify_receipt(bytes, kind.image_id())?)
+}
+
+/// Verifies an assertion receipt, checks that its key comes from this build's attestation
+/// guest and returns the signature data from its journal. With an `expected_policy`, also
+/// rejects a journal committing another one, see [`check_expected_policy`].
+#[uniffi::export]
+pub fn verify_assertion_proof(
+    receipt: Vec<u8>,
+    expected_policy: Option<VerificationPolicyData>,
+) -> Result<SignatureData, Risc0Error> {
+    let receipt = verify_receipt(&receipt, GuestKind::Assertion)?;
+    let journal = decode_assertion_journal(&receipt.journal.bytes)?;
+    check_attestation_guest(&journal)?;
+    check_expected_policy(journal.policy_hash.as_ref(), expected_policy)?;
+    Ok(SignatureData::from(&journal))
+}
+
+/// Verifies an attestation receipt and returns its raw journal and committed policy,
+/// checking `expected_policy` like [`verify_assertion_proof`].
+#[uniffi::export]
+pub fn verify_attestation_proof(
+    receipt: Vec<u8>,
+    expected_policy: Option<VerificationPolicyData>,
+) -> Result<VerifiedAttestation, Risc0Error> {
+    let receipt = verify_receipt(&receipt, GuestKind::Attestation)?;
+    let journal = decode_attestation_journal(&receipt.journal.bytes)?;
+    check_cert_chain_guest(&journal)?;
+    check_expected_policy(journal.policy_hash.as_ref(), expected_policy)?;
+    Ok(VerifiedAttestation {
+        journal: receipt.journal.bytes,
+        policy: journal.policy.into(),
+    })
+}
+
+/// [`mobiscale_verifier::check_cert_chain_image`] against this build's certificate chain guest.
+pub fn check_cert_chain_guest(journal: &AttestationJournal) -> Result<(), Risc0Error> {
+    Ok(mobiscale_verifier::check_cert_chain_image(journal, GuestKind::CertChain.image_id())?)
+}
+
+/// [`mobiscale_verifier::check_attestation_image`] and
+/// [`mobiscale_verifier::check_assertion_cert_chain_image`] against this build's guests.
+pub fn check_attestation_guest(journal: &AssertionJournal) -> Result<(), Risc0Error> {
+    mobiscale_verifier::check_attestation_image(journal, GuestKind::Attestation.image_id())?;
+    Ok(mobiscale_verifier::check_assertion_cert_chain_image(journal, GuestKind::CertChain.image_id())?)
+}
+
+/// Rejects a journal whose committed `policy_hash` is not that of `expected`, or that
+/// predates `policy_hash`, and an `expected` with an app ID hash that is not 32 bytes.
+/// `None` accepts any.
+///
+/// The guests commit the hash of the [`VerificationPolicy`] they were built with, so this is
+/// how a server configured with its own allow-list and [`Policy`] rejects receipts of a
+/// guest that checks something else.
+pub fn check_expected_policy(
+    policy_hash: Option<&[u8; 32]>,
+    expected: Option<VerificationPolicyData>,
+) -> Result<(), Risc0Error> {
+    let Some(expected) = expected else {
+        return Ok(());
+    };
+    let expected = VerificationPolicy::try_from(expected)?;
+    Ok(mobiscale_verifier::check_policy_hash(policy_hash, &expected)?)
+}
+
+/// Rejects an attestation journal whose committed timestamp is more than `max_age_secs`
+/// before `now`, or later than `now`.
+///
+/// The guest checks the certificate chain at whatever time the host supplies, so a
+/// verifier must bound that time itself; on chain, `now` is the block timestamp.
+pub fn check_attestation_freshness(journal: &AttestationJournal, now: i64, max_age_secs: i64) -> Result<(), Risc0Error> {
+    if journal.timestamp > now {
+        return Err(Risc0Error::VerificationFailed(format!(
+            "attestation timestamp {} is in the future (now {now})",
+            journal.timestamp
+        )));
+    }
+    if now.saturating_sub(journal.timestamp) > max_age_secs {
+        return Err(Risc0Error::VerificationFailed(format!(
+            "attestation timestamp {} is older than {max_age_secs} s (now {now})",
+            journal.timestamp
+        )));
+    }
+    Ok(())
+}
+
+/// Rejects an assertion journal committing a session nonce other than the one the verifier
+/// issued for this session.
+///
+/// The guest commits whatever nonce the host passed in, so this check is what makes a proof
+/// generated for an earlier session unusable in this one.
+pub fn check_session_nonce(journal: &AssertionJournal, expected: &[u8]) -> Result<(), Risc0Error> {
+    if journal.session_nonce.as_slice() != expected {
+        return Err(Risc0Error::VerificationFailed(format!(
+            "session nonce {} does not match the issued {}",
+            hex::encode(journal.session_nonce),
+            hex::encode(expected)
+        )));
+    }
+    Ok(())
+}
+
+/// Rejects a journal committed under an appattest-rs older than `minimum`, given as
+/// `(major, minor, patch)`.
+///
+/// Image IDs change with every guest build; this lets a verifier accept any build that
+/// verifies with a recent enough appattest-rs instead of pinning each image ID.
+pub fn check_minimum_policy(policy: &Policy, minimum: (u8, u8, u8)) -> Result<(), Risc0Error> {
+    if policy.appattest_version < minimum {
+        let (major, minor, patch) = policy.appattest_version;
+        let (min_major, min_minor, min_patch) = minimum;
+        return Err(Risc0Error::VerificationFailed(format!(
+            "proof was produced with appattest-rs {major}.{minor}.{patch}, \
+             {min_major}.{min_minor}.{min_patch} or later is required"
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use risc0_zkvm::sha::Digest;
+
+    use super::*;
+    use crate::backend::mock::MockBackend;
+    use crate::tests::{sample_attestation_request, sample_journal, sample_request};
+    use crate::{decode_assertion_journal, Prover, ProverConfig, NO_ALLOWLIST, POLICY};
+
+    fn assertion_receipt() -> Vec<u8> {
+        let prover = Prover::with_backend(
+            Arc::new(MockBackend::with_journal(sample_journal())),
+            ProverConfig::default(),
+        );
+        prover.prove_assertion(sample_request()).unwrap().proof.receipt
+    }
+
+    fn attestation_journal(timestamp: i64) -> AttestationJournal {
+        AttestationJournal {
+            policy: POLICY,
+            public_key: [4; 65],
+            key_id: [7; 32],
+            rp_id_hash: [8; 32],
+            environment: AttestationJournal::PRODUCTION,
+            timestamp,
+            time_signer: [0; 32],
+            allowlist_entry: NO_ALLOWLIST,
+            cert_chain_image_id: Digest::from(GuestKind::CertChain.image_id()).into(),
+            cert_chain_claim_digest: [0; 32],
+            input_digest: [0; 32],
+            policy_hash: Some([0; 32]),
+        }
+    }
+
+    fn attestation_receipt(journal: &AttestationJournal) -> Vec<u8> {
+        let prover = Prover::with_backend(
+            Arc::new(MockBackend::with_journal(journal.encode())),
+            ProverConfig::default(),
+        );
+        prover.prove_attestation(sample_attestation_request()).unwrap().receipt
+    }
+
+    #[test]
+    fn stale_attestation_timestamp_is_rejected() {
+        let now = 1_751_800_000;
+        assert!(check_attestation_freshness(&attestation_journal(now - 300), now, 300).is_ok());
+        assert!(matches!(
+            check_attestation_freshness(&attestation_journal(now - 301), now, 300),
+            Err(Risc0Error::VerificationFailed(msg)) if msg.contains("older than 300 s")
+        ));
+        assert!(check_attestation_freshness(&attestation_journal(now + 1), now, 300).is_err());
+        assert!(check_attestation_freshness(&attestation_journal(i64::MIN), now, 300).is_err());
+    }
+
+    #[test]
+    fn mismatched_session_nonce_is_rejected() {
+        let journal = decode_assertion_journal(&sample_journal()).unwrap();
+        assert!(check_session_nonce(&journal, &[9; 32]).is_ok());
+        assert!(matches!(
+            check_session_nonce(&journal, &[8; 32]),
+            Err(Risc0Error::VerificationFailed(msg)) if msg.contains("does not match")
+        ));
+    }
+
+    #[test]
+    fn old_appattest_versions_are_rejected() {
+        let policy = Policy {
+            appattest_version: (0, 2, 1),
+            ..POLICY
+        };
+        assert!(check_minimum_policy(&policy, (0, 2, 1)).is_ok());
+        assert!(check_minimum_policy(&policy, (0, 1, 9)).is_ok());
+        assert!(matches!(
+            check_minimum_policy(&policy, (0, 3, 0)),
+            Err(Risc0Error::VerificationFailed(msg)) if msg.contains("0.3.0 or later")
+        ));
+    }
+
+    #[test]
+    fn verified_proofs_carry_the_build_policy() {
+        std::env::set_var("RISC0_DEV_MODE", "1");
+        let signature = verify_assertion_proof(assertion_receipt(), None).unwrap();
+        assert_eq!(signature.policy, Some(POLICY.into()));
+    }
+
+    #[test]
+    fn attestations_must_rely_on_this_builds_cert_chain_guest() {
+        std::env::set_var("RISC0_DEV_MODE", "1");
+        let journal = attestation_journal(1_751_800_000);
+        assert_eq!(verify_attestation_proof(attestation_receipt(&journal), None).unwrap().policy, POLICY.into());
+
+        let journal = AttestationJournal {
+            cert_chain_image_id: [0xee; 32],
+            ..journal
+        };
+        assert!(matches!(
+            verify_attestation_proof(attestation_receipt(&journal), None),
+            Err(Risc0Error::VerificationFailed(msg)) if msg.contains("certificate chain guest eeee")
+        ));
+    }
+
+    #[test]
+    fn assertions_must_rely_on_this_builds_attestation_guest() {
+        std::env::set_var("RISC0_DEV_MODE", "1");
+        assert!(verify_assertion_proof(assertion_receipt(), None).is_ok());
+
+        let receipt = |journal: AssertionJournal| {
+            let prover = Prover::with_backend(
+                Arc::new(MockBackend::with_journal(journal.encode())),
+                ProverConfig::default(),
+            );
+            prover.prove_assertion(sample_request()).unwrap().proof.receipt
+        };
+        // A forged attestation guest could commit any key for the assertion to verify with.
+        let journal = AssertionJournal {
+            attestation_image_id: [0xee; 32],
+            ..decode_assertion_journal(&sample_journal()).unwrap()
+        };
+        assert!(matches!(
+            verify_assertion_proof(receipt(journal), None),
+            Err(Risc0Error::VerificationFailed(msg)) if msg.contains("attestation guest eeee")
+        ));
+        // And this build's attestation guest attests any key given a forged chain receipt.
+        let journal = AssertionJournal {
+            cert_chain_image_id: Some([0xee; 32]),
+            ..decode_assertion_journal(&sample_journal()).unwrap()
+        };
+        assert!(matches!(
+            verify_assertion_proof(receipt(journal), None),
+            Err(Risc0Error::VerificationFailed(msg)) if msg.contains("certificate chain guest eeee")
+        ));
+    }
+
+    #[test]
+    fn proofs_must_commit_the_expected_policy() {
+        std::env::set_var("RISC0_DEV_MODE", "1");
+        let build = VerificationPolicy::for_app_ids::<&str>(&[]);
+        let journal = AttestationJournal {
+            policy_hash: Some(build.hash()),
+            ..attestation_journal(1_751_800_000)
+        };
+        let expected = Some(VerificationPolicyData::from(&build));
+        assert!(verify_attestation_proof(attestation_receipt(&journal), expected).is_ok());
+
+        let allowlisted = VerificationPolicy::for_app_ids(&["TEAMID.com.example.app"]);
+        assert!(matches!(
+            verify_attestation_proof(attestation_receipt(&journal), Some((&allowlisted).into())),
+            Err(Risc0Error::VerificationFailed(msg)) if msg.contains("verification policy")
+        ));
+        // `sample_journal` commits no policy hash at all.
+        assert!(verify_assertion_proof(assertion_receipt(), Some((&build).into())).is_err());
+        assert!(check_expected_policy(None, None).is_ok());
+    }
+
+    #[test]
+    fn garbage_is_an_invalid_receipt() {
+        assert!(matches!(
+            verify_assertion_proof(vec![0xff; 16], None),
+            Err(Risc0Error::InvalidReceipt(_))
+        ));
+    }
+
+    #[test]
+    fn receipt_for_other_guest_is_rejected() {
+        std::env::set_var("RISC0_DEV_MODE", "1");
+        let receipt = assertion_receipt();
+
+        assert!(verify_assertion_proof(receipt.clone(), None).is_ok());
+        assert!(matches!(
+            verify_attestation_proof(receipt, None),
+            Err(Risc0Error::VerificationFailed(_))
+        ));
+    }
+}
@@ -0,0 +1,51 @@
+//! Paying the prover's cold start before the first real proof.
+//!
+//! The first proof in a process is markedly slower than the next: the prover allocates its
+//! hashing tables and, on Metal, compiles its pipelines on first use. [`crate::Prover::warm_up`]
+//! proves the tiny `warm_up` guest to get that done, e.g. while the user is still on an
+//! earlier screen, and [`CancelToken`] stops it if the user never gets to proving.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+/// Whether a prover has been warmed up. Provers over the [`crate::DefaultBackend`] share
+/// [`PROCESS`], since what the warm-up initializes lives as long as the process.
+pub(crate) type WarmState = Arc<Mutex<bool>>;
+
+pub(crate) static PROCESS: LazyLock<WarmState> = LazyLock::new(WarmState::default);
+
+/// What a [`crate::Prover::warm_up`] did.
+#[derive(uniffi::Record, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct WarmUpReport {
+    /// Wall time of the call, including any wait for the host or for a concurrent warm-up.
+    pub duration_ms: u64,
+    /// The prover was warm already, so nothing was proven.
+    pub already_warm: bool,
+}
+
+/// Cancels a warm-up from another thread. The zkVM proves without yielding, so a warm-up
+/// checks the token before it starts proving; cancelling after that lets the proof finish.
+#[derive(uniffi::Object, Debug, Default)]
+pub struct CancelToken {
+    cancelled: AtomicBool,
+}
+
+#[uniffi::export]
+impl CancelToken {
+    #[uniffi::constructor]
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
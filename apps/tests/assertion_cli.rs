@@ -0,0 +1,97 @@
+use std::path::{Path, PathBuf};
+
+use assert_cmd::Command;
+use sha2::{Digest, Sha256};
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures");
+const APP_ID: &str = "LMRM26A744.xyz.elus.aegis.app-attester";
+
+/// Proves the fixture attestation in dev mode and returns the receipt path.
+fn attestation_receipt(dir: &Path) -> PathBuf {
+    Command::cargo_bin("attestation")
+        .unwrap()
+        .env("RISC0_DEV_MODE", "1")
+        .args(["prove", "--attestation"])
+        .arg(Path::new(FIXTURES).join("attestation.b64"))
+        .args(["--challenge", "ExYiYJ4jA3rGL9oQN8ihDP4seccnxwHSEU28u8aCQaU="])
+        .args(["--app-id", APP_ID, "--key-id", "HD8zVqwgesSBwPaYj5icRg1GI/b6SOoa4TqieTEBA0g="])
+        .args(["--timestamp", "1751800000", "--development", "--out-dir"])
+        .arg(dir)
+        .assert()
+        .success();
+    dir.join("receipt.bin")
+}
+
+fn assertion_cmd(attestation: &Path, out_dir: &Path, manifest: &Path) -> Command {
+    let mut cmd = Command::cargo_bin("assertion").unwrap();
+    cmd.env("RISC0_DEV_MODE", "1")
+        .arg("--assertion-file")
+        .arg(Path::new(FIXTURES).join("assertion.b64"))
+        .arg("--client-data")
+        .arg(Path::new(FIXTURES).join("client_data.b64"))
+        .args(["--app-id", APP_ID, "--attestation-receipt"])
+        .arg(attestation)
+        .arg("--out-dir")
+        .arg(out_dir)
+        .arg("--manifest")
+        .arg(manifest);
+    cmd
+}
+
+#[test]
+fn prove_writes_manifest() {
+    let dir = tempfile::tempdir().unwrap();
+    let manifest_path = dir.path().join("manifest.json");
+    let attestation = attestation_receipt(&dir.path().join("attestation"));
+
+    assertion_cmd(&attestation, &dir.path().join("out"), &manifest_path).assert().success();
+
+    let manifest: serde_json::Value = serde_json::from_slice(&std::fs::read(&manifest_path).unwrap()).unwrap();
+    let receipt = std::fs::read(dir.path().join("out/receipt.bin")).unwrap();
+    assert_eq!(manifest["receipt_size"], receipt.len());
+    assert_eq!(manifest["image_id"].as_str().unwrap().len(), 64);
+    assert!(!manifest["journal_hex"].as_str().unwrap().is_empty());
+    assert_eq!(manifest["inputs"]["app_id"], APP_ID);
+    assert!(manifest["inputs"]["previous_counter"].is_null());
+    // sha256("x = 15")
+    assert_eq!(
+        manifest["inputs"]["client_data_sha256"],
+        "a3c1ab09ba1fa877e6bdf7a389c27352d071154956f0707e898a31185792be79"
+    );
+    assert_eq!(
+        manifest["inputs"]["attestation_receipt_sha256"],
+        hex::encode(Sha256::digest(std::fs::read(&attestation).unwrap()))
+    );
+    assert!(manifest["proving_time_ms"].is_u64());
+}
+
+#[test]
+fn refuses_to_overwrite_without_force() {
+    let dir = tempfile::tempdir().unwrap();
+    let out_dir = dir.path().join("out");
+    std::fs::create_dir_all(&out_dir).unwrap();
+    std::fs::write(out_dir.join("receipt.bin"), b"existing").unwrap();
+    let manifest_path = dir.path().join("manifest.json");
+    let attestation = attestation_receipt(&dir.path().join("attestation"));
+
+    assertion_cmd(&attestation, &out_dir, &manifest_path)
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("--force"));
+    assert_eq!(std::fs::read(out_dir.join("receipt.bin")).unwrap(), b"existing");
+
+    assertion_cmd(&attestation, &out_dir, &manifest_path).arg("--force").assert().success();
+}
+
+#[test]
+fn rejects_an_attestation_receipt_that_does_not_decode() {
+    let dir = tempfile::tempdir().unwrap();
+    let attestation = dir.path().join("attestation.bin");
+    std::fs::write(&attestation, b"garbage").unwrap();
+
+    assertion_cmd(&attestation, &dir.path().join("out"), &dir.path().join("manifest.json"))
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("Invalid receipt"));
+    assert!(!dir.path().join("out/receipt.bin").exists());
+}
@@ -0,0 +1,178 @@
+//! Runs the assertion guest on tampered inputs: the execution itself must fail, so no
+//! receipt can exist for an assertion that does not verify, and the host must see the
+//! guest's abort code.
+
+use std::time::{Duration, Instant};
+
+use base64::{engine::general_purpose, Engine};
+use mopro_bindings::{
+    abort, warm_up_prover, AssertionJournal, AssertionRequest, AttestationJournal, GuestInput, Prover, ProverConfig,
+    Risc0Error, ASSERTION_MAX_PO2, NO_ALLOWLIST, POLICY,
+};
+use risc0_zkvm::sha::Digestible;
+use risc0_zkvm::{Digest, FakeReceipt, InnerReceipt, Receipt, ReceiptClaim};
+use sha2::{Digest as _, Sha256};
+
+const ASSERTION: &str = include_str!("../fixtures/assertion.b64");
+const APP_ID: &str = "LMRM26A744.xyz.elus.aegis.app-attester";
+const PUBLIC_KEY_HEX: &str = "0485e322caa0f981bc18cd53574f1dc761c139d15030a36b1b249cc158c822c4506b1574b6b1c5b5bfaf8e2a1dfe3deaa87c8802d863c8aac23aa990560a8a56b4";
+/// The P-256 generator: a valid point, but not the key that signed the fixture.
+const OTHER_KEY_HEX: &str = "046b17d1f2e12c4247f8bce6e563a440f277037d812deb33a0f4a13945d898c2964fe342e2fe1a7f9b8ee7eb4a7c0f9e162bce33576b315ececbb6406837bf51f5";
+
+/// User cycles the recorded assertion may take. A loose ceiling meant to catch regressions
+/// such as a dependency pulling in a heavier decoder; `bench` reports the actual count.
+const USER_CYCLE_BUDGET: u64 = 8 << 20;
+
+// The CBOR map starts with `"signature": bytes(71)`; the DER signature follows this header.
+const SIGNATURE_OFFSET: usize = 13;
+
+/// A fake receipt of `image_id` committing an attestation of `public_key_hex` for the fixture app.
+fn attestation_receipt(image_id: [u32; 8], public_key_hex: &str) -> Receipt {
+    let journal = AttestationJournal {
+        policy: POLICY,
+        public_key: hex::decode(public_key_hex).unwrap().try_into().unwrap(),
+        key_id: [0; 32],
+        rp_id_hash: Sha256::digest(APP_ID).into(),
+        environment: AttestationJournal::DEVELOPMENT,
+        timestamp: 1_751_800_000,
+        time_signer: [0; 32],
+        allowlist_entry: NO_ALLOWLIST,
+        cert_chain_image_id: [0; 32],
+        cert_chain_claim_digest: [0; 32],
+        input_digest: [0; 32],
+        policy_hash: Some([0; 32]),
+    }
+    .encode();
+    let claim = ReceiptClaim::ok(image_id, journal.clone());
+    Receipt::new(InnerReceipt::Fake(FakeReceipt::new(claim)), journal)
+}
+
+fn request_attested_by(attestation: &Receipt) -> AssertionRequest {
+    AssertionRequest {
+        assertion: general_purpose::STANDARD.decode(ASSERTION.trim()).unwrap(),
+        client_data: b"x = 15".to_vec(),
+        app_id: APP_ID.to_string(),
+        attestation_receipt: bincode::serialize(attestation).unwrap(),
+        previous_counter: None,
+        session_nonce: vec![0; 32],
+    }
+}
+
+fn recorded_request() -> AssertionRequest {
+    request_attested_by(&attestation_receipt(methods::ATTESTATION_REST_ID, PUBLIC_KEY_HEX))
+}
+
+fn tampered_request() -> AssertionRequest {
+    let mut request = recorded_request();
+    // A byte inside `r`, past the DER sequence and integer headers.
+    request.assertion[SIGNATURE_OFFSET + 10] ^= 0x01;
+    request
+}
+
+fn execute(request: &AssertionRequest) -> Result<Vec<u8>, Risc0Error> {
+    Ok(GuestInput::try_from(request)?.execute()?.journal.bytes)
+}
+
+/// The abort code and reason `request` is rejected with.
+fn rejection(request: &AssertionRequest) -> (u8, String) {
+    match execute(request) {
+        Err(Risc0Error::GuestRejected { code, reason }) => (code, reason),
+        other => panic!("expected a guest abort, got {other:?}"),
+    }
+}
+
+#[test]
+fn recorded_assertion_executes() {
+    let attestation = attestation_receipt(methods::ATTESTATION_REST_ID, PUBLIC_KEY_HEX);
+    let request = request_attested_by(&attestation);
+    let journal = AssertionJournal::decode(&execute(&request).unwrap()).unwrap();
+    assert_eq!(journal.counter, 1);
+    // The host names the input exactly as the guest does.
+    assert_eq!(journal.input_digest, request.canonical_digest().unwrap());
+    assert_eq!(journal.policy, POLICY);
+    assert_eq!(Digest::from(journal.attestation_image_id), Digest::from(methods::ATTESTATION_REST_ID));
+    assert_eq!(Digest::from(journal.attestation_claim_digest), attestation.claim().unwrap().digest());
+}
+
+#[test]
+fn recorded_assertion_fits_the_segment_limit() {
+    let session = GuestInput::try_from(&recorded_request()).unwrap().execute().unwrap();
+    assert!(session.segments.iter().all(|s| s.po2 <= ASSERTION_MAX_PO2));
+    let user_cycles: u64 = session.segments.iter().map(|s| s.cycles as u64).sum();
+    assert!(user_cycles <= USER_CYCLE_BUDGET, "{user_cycles} user cycles");
+}
+
+#[test]
+fn tampered_signature_fails_execution() {
+    let (code, reason) = rejection(&tampered_request());
+    assert_eq!(code, abort::SIGNATURE_INVALID);
+    assert!(reason.contains("InvalidSignature"), "{reason}");
+}
+
+#[test]
+fn assertion_by_another_attested_key_fails_execution() {
+    let request = request_attested_by(&attestation_receipt(methods::ATTESTATION_REST_ID, OTHER_KEY_HEX));
+    assert_eq!(rejection(&request).0, abort::SIGNATURE_INVALID);
+}
+
+#[test]
+fn replayed_counter_fails_execution() {
+    let request = AssertionRequest {
+        previous_counter: Some(1),
+        ..recorded_request()
+    };
+    assert_eq!(rejection(&request).0, abort::COUNTER_REPLAY);
+}
+
+#[test]
+fn assertion_for_another_app_fails_execution() {
+    let request = AssertionRequest {
+        app_id: "TEAMID.com.example.other".to_string(),
+        ..recorded_request()
+    };
+    assert_eq!(rejection(&request).0, abort::APP_ID_MISMATCH);
+}
+
+#[test]
+fn malformed_assertion_fails_execution() {
+    let mut request = recorded_request();
+    request.assertion.truncate(20);
+    assert_eq!(rejection(&request).0, abort::MALFORMED_INPUT);
+}
+
+#[test]
+fn receipt_of_another_guest_fails_execution() {
+    // Same journal, but claimed by the assertion guest: the attestation claim stays unresolved.
+    let request = request_attested_by(&attestation_receipt(methods::ASSERTION_ID, PUBLIC_KEY_HEX));
+    assert!(execute(&request).is_err());
+}
+
+#[test]
+fn tampered_signature_is_not_proven() {
+    std::env::set_var("RISC0_DEV_MODE", "1");
+    match Prover::new().prove_assertion(tampered_request()) {
+        Err(Risc0Error::GuestRejected { code, .. }) => assert_eq!(code, abort::SIGNATURE_INVALID),
+        other => panic!("expected a guest abort, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn recorded_assertion_proves_after_a_warm_up() {
+    std::env::set_var("RISC0_DEV_MODE", "1");
+    assert!(!warm_up_prover(ProverConfig::default(), None).unwrap().already_warm);
+    assert!(warm_up_prover(ProverConfig::default(), None).unwrap().already_warm);
+    Prover::new().prove_assertion(recorded_request()).unwrap();
+}
+
+#[test]
+fn runs_past_the_cycle_limit_stop_early() {
+    let limit = 1 << 16;
+    let started = Instant::now();
+    let input = GuestInput::try_from(&recorded_request()).unwrap();
+    assert_eq!(input.execute_within(limit).err(), Some(Risc0Error::CycleLimitExceeded { limit }));
+    assert!(started.elapsed() < Duration::from_secs(30), "{:?}", started.elapsed());
+
+    std::env::set_var("RISC0_DEV_MODE", "1");
+    let prover = Prover::with_config(ProverConfig { max_cycles: limit, ..Default::default() });
+    assert_eq!(prover.prove_assertion(recorded_request()).err(), Some(Risc0Error::CycleLimitExceeded { limit }));
+}
@@ -0,0 +1,89 @@
+use assert_cmd::Command;
+use base64::{engine::general_purpose, Engine};
+use sha2::{Digest, Sha256};
+
+const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/attestation.b64");
+const CHALLENGE: &str = "ExYiYJ4jA3rGL9oQN8ihDP4seccnxwHSEU28u8aCQaU=";
+const APP_ID: &str = "LMRM26A744.xyz.elus.aegis.app-attester";
+const KEY_ID: &str = "HD8zVqwgesSBwPaYj5icRg1GI/b6SOoa4TqieTEBA0g=";
+/// Inside the validity window of the fixture's leaf certificate.
+const TIMESTAMP: &str = "1751800000";
+
+fn attestation_cmd(out_dir: &std::path::Path, challenge: &str) -> Command {
+    let mut cmd = Command::cargo_bin("attestation").unwrap();
+    cmd.env("RISC0_DEV_MODE", "1").args([
+        "prove",
+        "--attestation",
+        FIXTURE,
+        "--challenge",
+        challenge,
+        "--app-id",
+        APP_ID,
+        "--key-id",
+        KEY_ID,
+        "--timestamp",
+        TIMESTAMP,
+        "--development",
+        "--json",
+        "--out-dir",
+        out_dir.to_str().unwrap(),
+    ]);
+    cmd
+}
+
+#[test]
+fn prove_writes_receipt_and_json_summary() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let output = attestation_cmd(dir.path(), CHALLENGE).assert().success().get_output().stdout.clone();
+    let summary: serde_json::Value = serde_json::from_slice(&output).unwrap();
+
+    let receipt = std::fs::read(dir.path().join("receipt.bin")).unwrap();
+    assert_eq!(summary["receipt_size"], receipt.len());
+    assert_eq!(summary["image_id"].as_str().unwrap().len(), 64);
+    assert_eq!(summary["claim_digest"].as_str().unwrap().len(), 64);
+
+    // What the journal commits, recomputed from the inputs.
+    let journal = &summary["journal"];
+    assert_eq!(journal["key_id"], hex::encode(general_purpose::STANDARD.decode(KEY_ID).unwrap()));
+    assert_eq!(journal["rp_id_hash"], hex::encode(Sha256::digest(APP_ID)));
+    assert_eq!(journal["environment"], "development");
+    assert_eq!(journal["timestamp"], TIMESTAMP.parse::<i64>().unwrap());
+    assert!(dir.path().join("cert_chain_receipt.bin").is_file());
+}
+
+#[test]
+fn a_kept_cert_chain_receipt_is_reused() {
+    let first = tempfile::tempdir().unwrap();
+    let output = attestation_cmd(first.path(), CHALLENGE).assert().success().get_output().stdout.clone();
+    let first_summary: serde_json::Value = serde_json::from_slice(&output).unwrap();
+
+    let second = tempfile::tempdir().unwrap();
+    let output = attestation_cmd(second.path(), CHALLENGE)
+        .arg("--cert-chain-receipt")
+        .arg(first.path().join("cert_chain_receipt.bin"))
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let summary: serde_json::Value = serde_json::from_slice(&output).unwrap();
+
+    // The same chain claim, so the same attestation journal.
+    assert_eq!(summary["journal"], first_summary["journal"]);
+    assert_eq!(
+        std::fs::read(second.path().join("cert_chain_receipt.bin")).unwrap(),
+        std::fs::read(first.path().join("cert_chain_receipt.bin")).unwrap()
+    );
+}
+
+#[test]
+fn wrong_challenge_exits_non_zero() {
+    let dir = tempfile::tempdir().unwrap();
+
+    attestation_cmd(dir.path(), "d3JvbmcgY2hhbGxlbmdl")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("attestation verification failed"));
+    assert!(!dir.path().join("receipt.bin").exists());
+}
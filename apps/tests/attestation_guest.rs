@@ -0,0 +1,184 @@
+//! Runs the certificate chain and attestation guests on the recorded attestation and on
+//! inputs they must reject, and proves the two composed in dev mode.
+
+use mopro_bindings::cli::recorded_attestation;
+use mopro_bindings::{
+    abort, claim_digest_hex, deserialize_receipt, verify_attestation_proof, AttestationJournal, AttestationRequest,
+    CertChainJournal, GuestInput, Prover, Risc0Error, TimeTokenRequest, POLICY,
+};
+use p256::ecdsa::{signature::Signer, Signature, SigningKey};
+use risc0_zkvm::sha::Digestible;
+use risc0_zkvm::{FakeReceipt, InnerReceipt, Receipt, ReceiptClaim};
+use sha2::{Digest, Sha256};
+
+const PUBLIC_KEY_HEX: &str = "0485e322caa0f981bc18cd53574f1dc761c139d15030a36b1b249cc158c822c4506b1574b6b1c5b5bfaf8e2a1dfe3deaa87c8802d863c8aac23aa990560a8a56b4";
+
+fn recorded_request() -> AttestationRequest {
+    recorded_attestation(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures").as_ref()).unwrap()
+}
+
+/// A receipt the executor accepts as the assumption for `journal`; proving would need a real one.
+fn cert_chain_receipt(journal: Vec<u8>) -> Receipt {
+    let claim = ReceiptClaim::ok(methods::CERT_CHAIN_ID, journal.clone());
+    Receipt::new(InnerReceipt::Fake(FakeReceipt::new(claim)), journal)
+}
+
+fn execute_cert_chain(request: &AttestationRequest) -> Result<Vec<u8>, Risc0Error> {
+    Ok(GuestInput::CertChain(request.into()).execute()?.journal.bytes)
+}
+
+/// Runs the certificate chain guest, then the attestation guest on its journal.
+fn execute(request: &AttestationRequest) -> Result<Vec<u8>, Risc0Error> {
+    let chain = cert_chain_receipt(execute_cert_chain(request)?);
+    Ok(GuestInput::attestation(request, chain).execute()?.journal.bytes)
+}
+
+/// The abort code and reason `result` failed with.
+fn rejection(result: Result<Vec<u8>, Risc0Error>) -> (u8, String) {
+    match result {
+        Err(Risc0Error::GuestRejected { code, reason }) => (code, reason),
+        other => panic!("expected a guest abort, got {other:?}"),
+    }
+}
+
+#[test]
+fn recorded_attestation_commits_its_key() {
+    let request = recorded_request();
+    let journal = AttestationJournal::decode(&execute(&request).unwrap()).unwrap();
+    assert_eq!(hex::encode(journal.public_key), PUBLIC_KEY_HEX);
+    // The key ID is the key's hash, and the app ID hash the one the host computed.
+    assert_eq!(journal.key_id, <[u8; 32]>::from(Sha256::digest(journal.public_key)));
+    assert_eq!(journal.rp_id_hash, <[u8; 32]>::from(Sha256::digest(&request.app_id)));
+    assert_eq!(journal.environment, AttestationJournal::DEVELOPMENT);
+    assert_eq!(journal.timestamp, request.timestamp);
+    assert_eq!(journal.time_signer, [0; 32]);
+    // The built guest commits the policy constant the host links against.
+    assert_eq!(journal.policy, POLICY);
+}
+
+#[test]
+fn attestation_journal_names_the_cert_chain_claim() {
+    let request = recorded_request();
+    let chain = execute_cert_chain(&request).unwrap();
+    let journal = AttestationJournal::decode(&execute(&request).unwrap()).unwrap();
+
+    let claim = ReceiptClaim::ok(methods::CERT_CHAIN_ID, chain.clone());
+    assert_eq!(journal.cert_chain_claim_digest, <[u8; 32]>::from(claim.digest()));
+    assert_eq!(
+        journal.cert_chain_image_id,
+        <[u8; 32]>::from(risc0_zkvm::sha::Digest::from(methods::CERT_CHAIN_ID))
+    );
+    let chain = CertChainJournal::decode(&chain).unwrap();
+    assert_eq!(chain.public_key, journal.public_key);
+    assert_eq!(chain.timestamp, journal.timestamp);
+}
+
+fn time_token(timestamp: i64) -> TimeTokenRequest {
+    let key = SigningKey::from_slice(&[7; 32]).unwrap();
+    let signature: Signature = key.sign(&timestamp.to_le_bytes());
+    TimeTokenRequest {
+        signer: key.verifying_key().to_encoded_point(false).as_bytes().to_vec(),
+        signature: signature.to_der().as_bytes().to_vec(),
+    }
+}
+
+#[test]
+fn guests_commit_the_digests_the_host_computes() {
+    let mut request = recorded_request();
+    request.time_token = Some(time_token(request.timestamp));
+    let chain = CertChainJournal::decode(&execute_cert_chain(&request).unwrap()).unwrap();
+    assert_eq!(chain.input_digest, GuestInput::CertChain((&request).into()).canonical_digest().unwrap());
+
+    let journal = AttestationJournal::decode(&execute(&request).unwrap()).unwrap();
+    assert_eq!(journal.input_digest, request.canonical_digest());
+    let other = AttestationRequest { challenge: vec![0; 32], ..request };
+    assert_ne!(other.canonical_digest(), journal.input_digest);
+}
+
+#[test]
+fn time_token_signer_is_committed() {
+    let mut request = recorded_request();
+    let token = time_token(request.timestamp);
+    request.time_token = Some(token.clone());
+    let journal = AttestationJournal::decode(&execute(&request).unwrap()).unwrap();
+    assert_eq!(journal.time_signer, <[u8; 32]>::from(Sha256::digest(&token.signer)));
+}
+
+#[test]
+fn time_token_for_another_timestamp_fails_execution() {
+    let mut request = recorded_request();
+    request.time_token = Some(time_token(request.timestamp - 1));
+    let (code, reason) = rejection(execute_cert_chain(&request));
+    assert_eq!(code, abort::TIME_TOKEN_INVALID);
+    assert!(reason.contains("Time token verification failed"), "{reason}");
+}
+
+#[test]
+fn wrong_challenge_fails_execution() {
+    let mut request = recorded_request();
+    request.challenge[0] ^= 0x01;
+    assert_eq!(rejection(execute_cert_chain(&request)).0, abort::ATTESTATION_INVALID);
+}
+
+#[test]
+fn expired_certificates_fail_execution() {
+    let mut request = recorded_request();
+    request.timestamp = 1_900_000_000;
+    let (code, reason) = rejection(execute_cert_chain(&request));
+    assert_eq!(code, abort::ATTESTATION_INVALID);
+    // The leaf expired first, and the reason says so and when.
+    assert!(reason.contains("CertificateExpired { index: 0"), "{reason}");
+    assert!(reason.contains("checked_at: \"2030-03-17T17:46:40Z\""), "{reason}");
+}
+
+#[test]
+fn malformed_attestation_fails_execution() {
+    let mut request = recorded_request();
+    request.attestation.truncate(64);
+    assert_eq!(rejection(execute_cert_chain(&request)).0, abort::MALFORMED_INPUT);
+}
+
+#[test]
+fn other_app_id_fails_the_attestation_guest() {
+    let request = AttestationRequest {
+        app_id: "TEAMID.com.example.other".into(),
+        ..recorded_request()
+    };
+    // The chain does not depend on the app ID; the rest of the object does.
+    assert!(execute_cert_chain(&request).is_ok());
+    assert_eq!(rejection(execute(&request)).0, abort::APP_ID_MISMATCH);
+}
+
+#[test]
+fn chain_of_another_object_fails_the_attestation_guest() {
+    let request = recorded_request();
+    let mut chain = CertChainJournal::decode(&execute_cert_chain(&request).unwrap()).unwrap();
+    chain.auth_data_hash[0] ^= 0x01;
+
+    let input = GuestInput::attestation(&request, cert_chain_receipt(chain.encode()));
+    let (code, reason) = rejection(input.execute().map(|session| session.journal.bytes));
+    assert_eq!(code, abort::ATTESTATION_INVALID);
+    assert!(reason.contains("Authenticator data"), "{reason}");
+}
+
+#[test]
+fn composed_proof_verifies_in_dev_mode() {
+    std::env::set_var("RISC0_DEV_MODE", "1");
+    let prover = Prover::new();
+    let request = recorded_request();
+
+    let chain = prover.prove_cert_chain(request.clone()).unwrap();
+    let chain_claim = claim_digest_hex(&deserialize_receipt(&chain.receipt).unwrap()).unwrap();
+
+    // The chain receipt is proven once and reused for both attestation proofs.
+    let first = prover
+        .prove_attestation_with_cert_chain(request.clone(), chain.receipt.clone())
+        .unwrap();
+    let second = prover.prove_attestation_with_cert_chain(request, chain.receipt).unwrap();
+
+    let verified = verify_attestation_proof(first.receipt, None).unwrap();
+    assert_eq!(verified.journal, verify_attestation_proof(second.receipt, None).unwrap().journal);
+    let journal = AttestationJournal::decode(&verified.journal).unwrap();
+    assert_eq!(hex::encode(journal.cert_chain_claim_digest), chain_claim);
+    assert_eq!(hex::encode(journal.public_key), PUBLIC_KEY_HEX);
+}
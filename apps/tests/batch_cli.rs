@@ -0,0 +1,97 @@
+use std::path::Path;
+
+use assert_cmd::Command;
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures");
+
+/// Copies the batch inputs and proves, in dev mode, the attestation receipt they point at.
+fn batch_dir() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    for name in ["first.json", "second.json", "malformed.json"] {
+        std::fs::copy(Path::new(FIXTURES).join("batch").join(name), dir.path().join(name)).unwrap();
+    }
+    Command::cargo_bin("attestation")
+        .unwrap()
+        .env("RISC0_DEV_MODE", "1")
+        .args(["prove", "--attestation"])
+        .arg(Path::new(FIXTURES).join("attestation.b64"))
+        .args(["--challenge", "ExYiYJ4jA3rGL9oQN8ihDP4seccnxwHSEU28u8aCQaU="])
+        .args(["--app-id", "LMRM26A744.xyz.elus.aegis.app-attester"])
+        .args(["--key-id", "HD8zVqwgesSBwPaYj5icRg1GI/b6SOoa4TqieTEBA0g="])
+        .args(["--timestamp", "1751800000", "--development", "--out-dir"])
+        .arg(dir.path().join("attestation"))
+        .assert()
+        .success();
+    dir
+}
+
+fn run_batch(dir: &Path, extra: &[&str]) -> (bool, serde_json::Value) {
+    let output = Command::cargo_bin("batch")
+        .unwrap()
+        .env("RISC0_DEV_MODE", "1")
+        .arg(dir)
+        .args(["--jobs", "2", "--format", "json"])
+        .args(extra)
+        .output()
+        .unwrap();
+    (output.status.success(), serde_json::from_slice(&output.stdout).unwrap())
+}
+
+#[test]
+fn failures_do_not_abort_the_batch() {
+    let dir = batch_dir();
+    let (success, report) = run_batch(dir.path(), &[]);
+
+    assert!(!success, "a failed input makes the run fail");
+    assert_eq!(report["proved"], 2);
+    assert_eq!(report["failed"], 1);
+    assert!(report["total_cycles"].as_u64().unwrap() > 0);
+    let malformed = &report["inputs"][1];
+    assert!(malformed["input"].as_str().unwrap().ends_with("malformed.json"));
+    assert!(malformed["reason"].as_str().unwrap().contains("malformed input"));
+    assert!(dir.path().join("first.receipt.bin").exists());
+    assert!(dir.path().join("second.receipt.bin").exists());
+    assert!(!dir.path().join("malformed.receipt.bin").exists());
+}
+
+#[test]
+fn missing_attestation_receipt_fails_the_input() {
+    let dir = batch_dir();
+    std::fs::remove_dir_all(dir.path().join("attestation")).unwrap();
+    let (success, report) = run_batch(dir.path(), &[]);
+
+    assert!(!success);
+    assert_eq!(report["failed"], 3);
+    assert!(report["inputs"][0]["reason"].as_str().unwrap().contains("receipt.bin"));
+}
+
+#[test]
+fn reruns_skip_proved_inputs_unless_forced() {
+    let dir = batch_dir();
+    run_batch(dir.path(), &[]);
+
+    let (_, report) = run_batch(dir.path(), &[]);
+    assert_eq!(report["skipped"], 2);
+    assert_eq!(report["proved"], 0);
+
+    let (_, report) = run_batch(dir.path(), &["--force"]);
+    assert_eq!(report["proved"], 2);
+}
+
+#[test]
+fn csv_report_has_one_row_per_input() {
+    let dir = batch_dir();
+    let output = Command::cargo_bin("batch")
+        .unwrap()
+        .env("RISC0_DEV_MODE", "1")
+        .arg(dir.path())
+        .args(["--format", "csv"])
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines[0], "input,status,cycles,proving_time_ms,receipt,reason");
+    assert_eq!(lines.len(), 4);
+    assert!(lines[2].contains(",failed,"));
+}
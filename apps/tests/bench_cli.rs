@@ -0,0 +1,72 @@
+use std::path::{Path, PathBuf};
+
+use assert_cmd::Command;
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures");
+
+/// Copies the first batch input next to a dev-mode attestation receipt and returns its path.
+fn assertion_input(dir: &Path) -> PathBuf {
+    let input = dir.join("first.json");
+    std::fs::copy(Path::new(FIXTURES).join("batch/first.json"), &input).unwrap();
+    Command::cargo_bin("attestation")
+        .unwrap()
+        .env("RISC0_DEV_MODE", "1")
+        .args(["prove", "--attestation"])
+        .arg(Path::new(FIXTURES).join("attestation.b64"))
+        .args(["--challenge", "ExYiYJ4jA3rGL9oQN8ihDP4seccnxwHSEU28u8aCQaU="])
+        .args(["--app-id", "LMRM26A744.xyz.elus.aegis.app-attester"])
+        .args(["--key-id", "HD8zVqwgesSBwPaYj5icRg1GI/b6SOoa4TqieTEBA0g="])
+        .args(["--timestamp", "1751800000", "--development", "--out-dir"])
+        .arg(dir.join("attestation"))
+        .assert()
+        .success();
+    input
+}
+
+#[test]
+fn json_rows_cover_the_requested_matrix() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = Command::cargo_bin("bench")
+        .unwrap()
+        .env("RISC0_DEV_MODE", "1")
+        .arg("--assertion-input")
+        .arg(assertion_input(dir.path()))
+        .args(["--po2", "16,18", "--hashfn", "sha-256", "--kind", "composite"])
+        .args(["--iterations", "2", "--warmup", "0", "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let rows: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(rows.len(), 2);
+    for (row, po2) in rows.iter().zip([16, 18]) {
+        assert_eq!(row["guest"], "assertion");
+        assert_eq!(row["po2"], po2);
+        assert_eq!(row["iterations"], 2);
+        assert!(row["user_cycles"].as_u64().unwrap() > 0);
+        assert!(row["total_cycles"].as_u64().unwrap() >= row["user_cycles"].as_u64().unwrap());
+        assert!(row["p95_ms"].as_u64().unwrap() >= row["median_ms"].as_u64().unwrap());
+    }
+}
+
+#[test]
+fn csv_header_is_stable() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = Command::cargo_bin("bench")
+        .unwrap()
+        .env("RISC0_DEV_MODE", "1")
+        .arg("--assertion-input")
+        .arg(assertion_input(dir.path()))
+        .args(["--po2", "16", "--hashfn", "sha-256", "--kind", "composite"])
+        .args(["--iterations", "1", "--warmup", "0", "--format", "csv"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        stdout.lines().next().unwrap(),
+        "guest,po2,hashfn,kind,segments,user_cycles,total_cycles,iterations,median_ms,p95_ms,receipt_bytes"
+    );
+    assert_eq!(stdout.lines().count(), 2);
+}
@@ -0,0 +1,53 @@
+#![cfg(all(feature = "capi", unix))]
+
+//! Compiles tests/capi/capi_test.c against the generated header and the cdylib, with the
+//! C compiler the `cc` crate finds, and runs it.
+
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+fn c_program_follows_the_documented_conventions() {
+    let manifest = Path::new(env!("CARGO_MANIFEST_DIR"));
+    // The cdylib is built next to the test binaries, in target/<profile>/deps.
+    let deps = env::current_exe().unwrap().parent().unwrap().to_path_buf();
+    assert!(
+        ["so", "dylib"].iter().any(|ext| deps.join(format!("libmopro_bindings.{ext}")).exists()),
+        "no libmopro_bindings cdylib in {}",
+        deps.display()
+    );
+    let out = tempfile::tempdir().unwrap();
+    let program = out.path().join("capi_test");
+
+    let compiler = cc::Build::new()
+        .cargo_metadata(false)
+        .opt_level(0)
+        .target(env!("CAPI_TARGET"))
+        .host(env!("CAPI_HOST"))
+        .out_dir(out.path())
+        .get_compiler();
+    let compiled = compiler
+        .to_command()
+        .arg(manifest.join("tests/capi/capi_test.c"))
+        .arg("-I")
+        .arg(manifest.join("include"))
+        .arg("-L")
+        .arg(&deps)
+        .arg(format!("-Wl,-rpath,{}", deps.display()))
+        .arg("-lmopro_bindings")
+        .arg("-o")
+        .arg(&program)
+        .output()
+        .unwrap();
+    assert!(compiled.status.success(), "cc failed:\n{}", String::from_utf8_lossy(&compiled.stderr));
+
+    let run = Command::new(&program).output().unwrap();
+    assert!(
+        run.status.success(),
+        "capi_test failed:\n{}{}",
+        String::from_utf8_lossy(&run.stdout),
+        String::from_utf8_lossy(&run.stderr)
+    );
+    assert_eq!(String::from_utf8_lossy(&run.stdout).trim(), "ok");
+}
@@ -0,0 +1,70 @@
+/* Uses the C interface the way a C or C++ host would; run by tests/capi.rs. */
+
+#include <stdio.h>
+#include <string.h>
+
+#include "mobiscale.h"
+
+#define CHECK(cond)                                                   \
+    do {                                                              \
+        if (!(cond)) {                                                \
+            fprintf(stderr, "%s:%d: %s\n", __FILE__, __LINE__, #cond); \
+            return 1;                                                 \
+        }                                                             \
+    } while (0)
+
+static int last_error_contains(const char *needle) {
+    MobiscaleBuffer message;
+    char text[512] = {0};
+    if (mobiscale_last_error(&message) != MOBISCALE_OK || message.len >= sizeof text) {
+        return 0;
+    }
+    if (message.len > 0) {
+        memcpy(text, message.data, message.len);
+    }
+    mobiscale_free_buffer(message);
+    return strstr(text, needle) != NULL;
+}
+
+int main(void) {
+    /* A result is the caller's until it hands it back to mobiscale_free_buffer. */
+    MobiscaleBuffer info;
+    CHECK(mobiscale_build_info(&info) == MOBISCALE_OK);
+    CHECK(info.data != NULL && info.len > 0 && info.data[0] == '{');
+    mobiscale_free_buffer(info);
+
+    /* Failures return the stable codes, here Risc0Error::InvalidReceipt, and empty the
+       out-parameter, which is then safe to free. */
+    const uint8_t garbage[] = {0xde, 0xad, 0xbe, 0xef};
+    uint8_t dummy = 0;
+    MobiscaleBuffer json = {&dummy, 1};
+    CHECK(mobiscale_verify_proof(MOBISCALE_GUEST_ASSERTION, garbage, sizeof garbage, &json) == 2004);
+    CHECK(json.data == NULL && json.len == 0);
+    mobiscale_free_buffer(json);
+    CHECK(last_error_contains("Invalid receipt"));
+
+    /* Misuse is reported rather than crashing. */
+    CHECK(mobiscale_verify_proof(42, garbage, sizeof garbage, &json) == MOBISCALE_ERR_INVALID_ARGUMENT);
+    CHECK(last_error_contains("unknown guest 42"));
+    CHECK(mobiscale_verify_proof(MOBISCALE_GUEST_ATTESTATION, NULL, 4, &json) == MOBISCALE_ERR_INVALID_ARGUMENT);
+    CHECK(mobiscale_verify_proof(MOBISCALE_GUEST_ATTESTATION, garbage, sizeof garbage, NULL)
+          == MOBISCALE_ERR_INVALID_ARGUMENT);
+    CHECK(mobiscale_last_error(NULL) == MOBISCALE_ERR_INVALID_ARGUMENT);
+
+    /* The attestation receipt is decoded before anything is proven. */
+    MobiscaleBuffer receipt;
+    CHECK(mobiscale_prove_assertion(garbage, sizeof garbage, garbage, sizeof garbage, "TEAM.app", garbage,
+                                    sizeof garbage, NULL, NULL, &receipt, NULL)
+          == 2004);
+    CHECK(receipt.data == NULL);
+    CHECK(mobiscale_prove_assertion(garbage, sizeof garbage, NULL, 0, NULL, garbage, sizeof garbage, NULL, NULL,
+                                    &receipt, NULL)
+          == MOBISCALE_ERR_INVALID_ARGUMENT);
+    CHECK(last_error_contains("app_id is null"));
+
+    MobiscaleBuffer empty = {NULL, 0};
+    mobiscale_free_buffer(empty);
+
+    puts("ok");
+    return 0;
+}
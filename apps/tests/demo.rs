@@ -0,0 +1,36 @@
+use assert_cmd::Command;
+
+/// Full pipeline over the recorded fixtures. Ignored by default because it runs every step
+/// end to end; run it with `cargo test -- --ignored`.
+#[test]
+#[ignore]
+fn demo_completes() {
+    Command::cargo_bin("demo")
+        .unwrap()
+        .env("RISC0_DEV_MODE", "1")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("[5/5] verify receipt"))
+        .stdout(predicates::str::contains("Demo completed successfully."));
+}
+
+#[test]
+#[ignore]
+fn demo_names_the_failing_step() {
+    let dir = tempfile::tempdir().unwrap();
+    let fixtures = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures");
+    for entry in std::fs::read_dir(fixtures).unwrap() {
+        let entry = entry.unwrap();
+        std::fs::copy(entry.path(), dir.path().join(entry.file_name())).unwrap();
+    }
+    std::fs::write(dir.path().join("challenge.b64"), "AAAA").unwrap();
+
+    Command::cargo_bin("demo")
+        .unwrap()
+        .env("RISC0_DEV_MODE", "1")
+        .arg("--fixtures")
+        .arg(dir.path())
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("step 1 (verify attestation) failed"));
+}
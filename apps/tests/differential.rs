@@ -0,0 +1,193 @@
+//! Runs every case in `fixtures/differential/` through both verification paths: appattest-rs
+//! on the host, as the server uses it, and the assertion guest in the executor. The two must
+//! accept the same cases, reject the same cases and, when they accept, report the same values.
+//!
+//! Each case is a JSON file; new ones are picked up without touching this file:
+//!
+//! ```json
+//! {
+//!   "assertion": "<base64 CBOR>",
+//!   "client_data": "<base64>",
+//!   "app_id": "<team id>.<bundle id>",
+//!   "previous_counter": "<optional: absent before the key's first assertion>",
+//!   "public_key": "<hex SEC1 key, optional: the recorded key>",
+//!   "expect": "accept" | "reject"
+//! }
+//! ```
+//!
+//! The guest is given a fake attestation receipt of `public_key` for the recorded app, so a
+//! case can name any key without an attestation object for it.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use appattest_rs::assertion::Assertion;
+use mopro_bindings::cli::{assertion_request, public_key_pem};
+use mopro_bindings::{AssertionJournal, AttestationJournal, GuestInput, Risc0Error, NO_ALLOWLIST, POLICY};
+use p256::ecdsa::Signature;
+use risc0_zkvm::{FakeReceipt, InnerReceipt, Receipt, ReceiptClaim};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+const CORPUS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/differential");
+const RECORDED_APP_ID: &str = "LMRM26A744.xyz.elus.aegis.app-attester";
+const RECORDED_PUBLIC_KEY_HEX: &str = "0485e322caa0f981bc18cd53574f1dc761c139d15030a36b1b249cc158c822c4506b1574b6b1c5b5bfaf8e2a1dfe3deaa87c8802d863c8aac23aa990560a8a56b4";
+
+#[derive(Deserialize)]
+struct Case {
+    assertion: String,
+    client_data: String,
+    app_id: String,
+    #[serde(default)]
+    previous_counter: Option<u32>,
+    public_key: Option<String>,
+    expect: Expect,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Expect {
+    Accept,
+    Reject,
+}
+
+/// What a verification path reports for an assertion it accepts.
+#[derive(Debug, PartialEq)]
+struct Verified {
+    signature_r: [u8; 32],
+    /// Low-S, the form the guest commits.
+    signature_s: [u8; 32],
+    public_key_x: [u8; 32],
+    public_key_y: [u8; 32],
+    counter: u32,
+}
+
+impl Case {
+    fn public_key_hex(&self) -> &str {
+        self.public_key.as_deref().unwrap_or(RECORDED_PUBLIC_KEY_HEX)
+    }
+}
+
+fn corpus() -> Vec<PathBuf> {
+    let mut paths: Vec<_> = fs::read_dir(CORPUS)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+    paths
+}
+
+fn field(bytes: &[u8]) -> [u8; 32] {
+    bytes.try_into().expect("32-byte field")
+}
+
+/// Verifies the case with appattest-rs, returning why it was rejected on failure.
+fn verify_natively(case: &Case) -> Result<Verified, String> {
+    let pem = public_key_pem(case.public_key_hex())?;
+    let assertion = Assertion::from_base64(&case.assertion).map_err(|e| e.to_string())?;
+    let counter = assertion.counter().map_err(|e| e.to_string())?;
+    let [r, s, x, y] = assertion
+        .verify(&case.client_data, &case.app_id, &pem, case.previous_counter, None)
+        .map_err(|e| e.to_string())?;
+    let signature = Signature::from_scalars(field(&r), field(&s)).map_err(|e| e.to_string())?;
+    let (r, s) = signature.normalize_s().unwrap_or(signature).split_bytes();
+    Ok(Verified {
+        signature_r: r.into(),
+        signature_s: s.into(),
+        public_key_x: field(&x),
+        public_key_y: field(&y),
+        counter,
+    })
+}
+
+fn attestation_receipt(public_key_hex: &str) -> Receipt {
+    let journal = AttestationJournal {
+        policy: POLICY,
+        public_key: hex::decode(public_key_hex).unwrap().try_into().unwrap(),
+        key_id: [0; 32],
+        rp_id_hash: Sha256::digest(RECORDED_APP_ID).into(),
+        environment: AttestationJournal::DEVELOPMENT,
+        timestamp: 1_751_800_000,
+        time_signer: [0; 32],
+        allowlist_entry: NO_ALLOWLIST,
+        cert_chain_image_id: [0; 32],
+        cert_chain_claim_digest: [0; 32],
+        input_digest: [0; 32],
+        policy_hash: Some([0; 32]),
+    }
+    .encode();
+    let claim = ReceiptClaim::ok(methods::ATTESTATION_REST_ID, journal.clone());
+    Receipt::new(InnerReceipt::Fake(FakeReceipt::new(claim)), journal)
+}
+
+/// Runs the case through the assertion guest, returning its abort reason on failure.
+///
+/// Panics on any error other than a guest abort: those mean the case never reached the
+/// guest, and the paths were not compared.
+fn verify_in_guest(case: &Case) -> Result<Verified, String> {
+    let receipt = bincode::serialize(&attestation_receipt(case.public_key_hex())).unwrap();
+    let request = assertion_request(
+        &case.assertion,
+        &case.client_data,
+        &case.app_id,
+        receipt,
+        case.previous_counter,
+        [0; 32],
+    )
+    .unwrap();
+    match GuestInput::try_from(&request).and_then(|input| input.execute()) {
+        Ok(session) => {
+            let journal = AssertionJournal::decode(&session.journal.bytes).unwrap();
+            assert_eq!(journal.client_data_hash, <[u8; 32]>::from(Sha256::digest(&request.client_data)));
+            Ok(Verified {
+                signature_r: journal.signature_r,
+                signature_s: journal.signature_s,
+                public_key_x: journal.public_key_x,
+                public_key_y: journal.public_key_y,
+                counter: journal.counter,
+            })
+        }
+        Err(Risc0Error::GuestRejected { code, reason }) => Err(format!("abort {code}: {reason}")),
+        Err(e) => panic!("the guest did not run: {e}"),
+    }
+}
+
+fn compare(path: &Path) -> Result<(), String> {
+    let case: Case = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+    let native = verify_natively(&case);
+    let guest = verify_in_guest(&case);
+    match (&native, &guest) {
+        (Ok(native), Ok(guest)) if native != guest => {
+            return Err(format!("values differ: host {native:?}, guest {guest:?}"))
+        }
+        (Ok(_), Err(reason)) => return Err(format!("host accepts, guest rejects: {reason}")),
+        (Err(reason), Ok(_)) => return Err(format!("guest accepts, host rejects: {reason}")),
+        _ => {}
+    }
+    let decision = if native.is_ok() { Expect::Accept } else { Expect::Reject };
+    if decision != case.expect {
+        return Err(format!("both paths {decision:?}, the case expects {:?}", case.expect));
+    }
+    Ok(())
+}
+
+#[test]
+fn host_and_guest_agree_on_the_corpus() {
+    let corpus = corpus();
+    assert!(!corpus.is_empty(), "no cases in {CORPUS}");
+    let failures: Vec<_> = corpus
+        .iter()
+        .filter_map(|path| compare(path).err().map(|e| format!("{}: {e}", path.display())))
+        .collect();
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn corpus_has_both_outcomes() {
+    let expects: Vec<Expect> = corpus()
+        .iter()
+        .map(|path| serde_json::from_str::<Case>(&fs::read_to_string(path).unwrap()).unwrap().expect)
+        .collect();
+    assert!(expects.contains(&Expect::Accept) && expects.contains(&Expect::Reject));
+}
@@ -0,0 +1,133 @@
+use std::path::{Path, PathBuf};
+
+use assert_cmd::Command;
+use risc0_zkvm::{FakeReceipt, InnerReceipt, Receipt, ReceiptClaim};
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures");
+const APP_ID: &str = "LMRM26A744.xyz.elus.aegis.app-attester";
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(FIXTURES).join(name)
+}
+
+fn inspect_json(receipt: &Path) -> serde_json::Value {
+    let output = Command::cargo_bin("inspect")
+        .unwrap()
+        .arg(receipt)
+        .arg("--json")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+/// Proves the fixture attestation in dev mode and returns the receipt path.
+fn attestation_receipt(dir: &Path) -> PathBuf {
+    Command::cargo_bin("attestation")
+        .unwrap()
+        .env("RISC0_DEV_MODE", "1")
+        .args(["prove", "--attestation"])
+        .arg(fixture("attestation.b64"))
+        .args(["--challenge", "ExYiYJ4jA3rGL9oQN8ihDP4seccnxwHSEU28u8aCQaU="])
+        .args([
+            "--app-id",
+            APP_ID,
+            "--key-id",
+            "HD8zVqwgesSBwPaYj5icRg1GI/b6SOoa4TqieTEBA0g=",
+        ])
+        .args(["--timestamp", "1751800000", "--development", "--out-dir"])
+        .arg(dir)
+        .assert()
+        .success();
+    dir.join("receipt.bin")
+}
+
+#[test]
+fn decodes_assertion_receipt() {
+    let dir = tempfile::tempdir().unwrap();
+    Command::cargo_bin("assertion")
+        .unwrap()
+        .env("RISC0_DEV_MODE", "1")
+        .arg("--assertion-file")
+        .arg(fixture("assertion.b64"))
+        .arg("--client-data")
+        .arg(fixture("client_data.b64"))
+        .args(["--app-id", APP_ID, "--attestation-receipt"])
+        .arg(attestation_receipt(&dir.path().join("attestation")))
+        .arg("--out-dir")
+        .arg(dir.path())
+        .assert()
+        .success();
+
+    let report = inspect_json(&dir.path().join("receipt.bin"));
+    assert_eq!(report["kind"], "fake");
+    assert_eq!(report["guest"], "assertion");
+    assert_eq!(report["layout"], "assertion");
+    assert_eq!(report["exit_code"], "Halted(0)");
+    assert_eq!(
+        report["journal"]["public_key_x"],
+        "85e322caa0f981bc18cd53574f1dc761c139d15030a36b1b249cc158c822c450"
+    );
+    assert_eq!(report["journal"]["version"], 6);
+    // appattest-rs shares the workspace version.
+    assert_eq!(report["journal"]["policy"]["appattest_version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(report["journal"]["policy"]["low_s"], true);
+    assert_eq!(report["journal"]["attestation_image_id"].as_str().unwrap().len(), 64);
+    assert_eq!(report["journal"]["counter"], 1);
+    assert_eq!(
+        report["journal"]["client_data_hash"],
+        "a3c1ab09ba1fa877e6bdf7a389c27352d071154956f0707e898a31185792be79"
+    );
+    assert_eq!(report["journal"]["challenge_hash"], "00".repeat(32));
+    assert_eq!(report["journal"]["session_nonce"], "00".repeat(32));
+    assert_eq!(report["claim_digest"].as_str().unwrap().len(), 64);
+}
+
+#[test]
+fn reports_attestation_receipt() {
+    let dir = tempfile::tempdir().unwrap();
+    let report = inspect_json(&attestation_receipt(dir.path()));
+    assert_eq!(report["guest"], "attestation");
+    assert_eq!(report["exit_code"], "Halted(0)");
+    assert_eq!(report["layout"], "attestation");
+    assert_eq!(report["journal"]["environment"], "development");
+    assert_eq!(report["journal"]["policy"]["development"], true);
+    assert_eq!(report["journal"]["timestamp"], 1751800000);
+    assert_eq!(report["journal_len"], report["journal_hex"].as_str().unwrap().len() / 2);
+}
+
+#[test]
+fn unknown_layout_falls_back_to_hex() {
+    let journal = b"not a known journal".to_vec();
+    let claim = ReceiptClaim::ok(methods::ASSERTION_ID, journal.clone());
+    let receipt = Receipt::new(InnerReceipt::Fake(FakeReceipt::new(claim)), journal.clone());
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("receipt.bin");
+    std::fs::write(&path, bincode::serialize(&receipt).unwrap()).unwrap();
+
+    let report = inspect_json(&path);
+    assert_eq!(report["layout"], "unknown");
+    assert_eq!(report["journal"], serde_json::Value::Null);
+    assert_eq!(report["journal_hex"], hex::encode(&journal));
+
+    Command::cargo_bin("inspect")
+        .unwrap()
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("00000000  6e 6f 74"));
+}
+
+#[test]
+fn corrupt_file_is_an_error() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("receipt.bin");
+    std::fs::write(&path, b"garbage").unwrap();
+
+    Command::cargo_bin("inspect")
+        .unwrap()
+        .arg(&path)
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("Invalid receipt"));
+}
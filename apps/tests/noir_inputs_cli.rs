@@ -0,0 +1,122 @@
+use std::path::{Path, PathBuf};
+
+use assert_cmd::Command;
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures");
+const APP_ID: &str = "LMRM26A744.xyz.elus.aegis.app-attester";
+/// `SHA256(nonce)` for the fixture assertion over `x = 15`.
+const MESSAGE_HASH: &str = "cf844b753a9264b4980e7cbb75b98d8d2ffb11531f2c8ebd522359fb7109c76d";
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(FIXTURES).join(name)
+}
+
+fn attestation_receipt(dir: &Path) -> PathBuf {
+    Command::cargo_bin("attestation")
+        .unwrap()
+        .env("RISC0_DEV_MODE", "1")
+        .args(["prove", "--attestation"])
+        .arg(fixture("attestation.b64"))
+        .args(["--challenge", "ExYiYJ4jA3rGL9oQN8ihDP4seccnxwHSEU28u8aCQaU="])
+        .args(["--app-id", APP_ID, "--key-id", "HD8zVqwgesSBwPaYj5icRg1GI/b6SOoa4TqieTEBA0g="])
+        .args(["--timestamp", "1751800000", "--development", "--out-dir"])
+        .arg(dir)
+        .assert()
+        .success();
+    dir.join("receipt.bin")
+}
+
+fn assertion_receipt(dir: &Path) -> PathBuf {
+    Command::cargo_bin("assertion")
+        .unwrap()
+        .env("RISC0_DEV_MODE", "1")
+        .arg("--assertion-file")
+        .arg(fixture("assertion.b64"))
+        .arg("--client-data")
+        .arg(fixture("client_data.b64"))
+        .args(["--app-id", APP_ID, "--attestation-receipt"])
+        .arg(attestation_receipt(&dir.join("attestation")))
+        .arg("--out-dir")
+        .arg(dir)
+        .assert()
+        .success();
+    dir.join("receipt.bin")
+}
+
+fn noir_inputs_cmd(ecdsa_dir: &Path) -> Command {
+    let mut cmd = Command::cargo_bin("noir_inputs").unwrap();
+    cmd.env("RISC0_DEV_MODE", "1")
+        .arg("--assertion")
+        .arg(fixture("assertion.b64"))
+        .arg("--client-data")
+        .arg(fixture("client_data.b64"))
+        .arg("--ecdsa-dir")
+        .arg(ecdsa_dir);
+    cmd
+}
+
+/// Reads a `[u8; 32]` input written as quoted hex bytes back into hex.
+fn field(toml: &toml::Table, name: &str) -> String {
+    let bytes: Vec<u8> = toml[name]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|v| u8::from_str_radix(v.as_str().unwrap().trim_start_matches("0x"), 16).unwrap())
+        .collect();
+    assert_eq!(bytes.len(), 32, "{name}");
+    hex::encode(bytes)
+}
+
+#[test]
+fn prover_toml_round_trips_the_journal() {
+    std::env::set_var("RISC0_DEV_MODE", "1");
+    let dir = tempfile::tempdir().unwrap();
+    let receipt = assertion_receipt(dir.path());
+    let journal = mopro_bindings::verify_assertion_proof(std::fs::read(&receipt).unwrap(), None).unwrap();
+
+    noir_inputs_cmd(dir.path()).arg("--receipt").arg(&receipt).assert().success();
+
+    let toml: toml::Table = std::fs::read_to_string(dir.path().join("Prover.toml")).unwrap().parse().unwrap();
+    assert_eq!(field(&toml, "sha256_hash"), MESSAGE_HASH);
+    assert_eq!(field(&toml, "r"), hex::encode(&journal.signature_r));
+    assert_eq!(field(&toml, "s"), hex::encode(&journal.signature_s));
+    assert_eq!(field(&toml, "public_key_x"), hex::encode(&journal.public_key_x));
+    assert_eq!(field(&toml, "public_key_y"), hex::encode(&journal.public_key_y));
+
+    // The same journal given as JSON produces the same file.
+    let json = serde_json::json!({
+        "signature_r": hex::encode(&journal.signature_r),
+        "signature_s": hex::encode(&journal.signature_s),
+        "public_key_x": hex::encode(&journal.public_key_x),
+        "public_key_y": hex::encode(&journal.public_key_y),
+    });
+    let json_path = dir.path().join("signature.json");
+    std::fs::write(&json_path, json.to_string()).unwrap();
+    let other = dir.path().join("from-json");
+    std::fs::create_dir(&other).unwrap();
+    noir_inputs_cmd(&other).arg("--signature-json").arg(&json_path).assert().success();
+    assert_eq!(
+        std::fs::read(other.join("Prover.toml")).unwrap(),
+        std::fs::read(dir.path().join("Prover.toml")).unwrap()
+    );
+}
+
+#[test]
+fn mismatched_client_data_is_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let receipt = assertion_receipt(dir.path());
+
+    let mut cmd = Command::cargo_bin("noir_inputs").unwrap();
+    cmd.env("RISC0_DEV_MODE", "1")
+        .arg("--receipt")
+        .arg(&receipt)
+        .arg("--assertion")
+        .arg(fixture("assertion.b64"))
+        .args(["--client-data", "eCA9IDE2"])
+        .arg("--ecdsa-dir")
+        .arg(dir.path())
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("does not verify"));
+    assert!(!dir.path().join("Prover.toml").exists());
+}
@@ -0,0 +1,106 @@
+use std::path::Path;
+use std::process::Command;
+
+use mopro_bindings::noir::verify_noir_proof;
+use mopro_bindings::{prove_assertion_noir, Risc0Error, SignatureData};
+
+/// The fixture assertion's signature and public key, with the `SHA256(nonce)` it signs, and
+/// a proof of it recorded with bb; see fixtures/README.md.
+const NOIR_FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/noir");
+
+/// Whether `tools` are installed; the tests here pass vacuously without them.
+fn available(tools: &[&str]) -> bool {
+    let available =
+        tools.iter().all(|tool| Command::new(tool).arg("--version").output().is_ok_and(|o| o.status.success()));
+    if !available {
+        eprintln!("skipping: one of {tools:?} is not on PATH");
+    }
+    available
+}
+
+fn fixture_json() -> serde_json::Value {
+    serde_json::from_str(&std::fs::read_to_string(Path::new(NOIR_FIXTURES).join("signature.json")).unwrap()).unwrap()
+}
+
+fn field(name: &str) -> Vec<u8> {
+    hex::decode(fixture_json()[name].as_str().unwrap()).unwrap()
+}
+
+fn signature() -> SignatureData {
+    SignatureData {
+        signature_r: field("signature_r"),
+        signature_s: field("signature_s"),
+        public_key_x: field("public_key_x"),
+        public_key_y: field("public_key_y"),
+        client_data_hash: Vec::new(),
+        challenge_hash: Vec::new(),
+        session_nonce: Vec::new(),
+        policy: None,
+    }
+}
+
+/// The public inputs of the fixture signature, with the message hash changed if `tamper`.
+fn public_inputs(tamper: bool) -> Vec<Vec<u8>> {
+    let mut message_hash = field("message_hash");
+    if tamper {
+        message_hash[0] ^= 0x01;
+    }
+    vec![message_hash, field("public_key_x"), field("public_key_y")]
+}
+
+/// The recorded proof and verification key, if they have been recorded.
+fn recorded_proof() -> Option<(Vec<u8>, Vec<u8>)> {
+    let read = |name: &str| std::fs::read(Path::new(NOIR_FIXTURES).join(name)).ok();
+    let recorded = read("proof").zip(read("vk"));
+    if recorded.is_none() {
+        eprintln!("skipping: no proof recorded in {NOIR_FIXTURES}");
+    }
+    recorded
+}
+
+#[test]
+fn recorded_proof_verifies_and_tampering_does_not() {
+    if !available(&["bb"]) {
+        return;
+    }
+    let Some((proof, vk)) = recorded_proof() else {
+        return;
+    };
+    assert_eq!(verify_noir_proof(proof.clone(), vk.clone(), public_inputs(false)), Ok(true));
+    assert_eq!(verify_noir_proof(proof.clone(), vk.clone(), public_inputs(true)), Ok(false));
+
+    let mut tampered = proof.clone();
+    let middle = tampered.len() / 2;
+    tampered[middle] ^= 0x01;
+    assert_eq!(verify_noir_proof(tampered, vk.clone(), public_inputs(false)), Ok(false));
+
+    // Not the circuit's layout: an error, not a failed proof.
+    let mut extra = public_inputs(false);
+    extra.push(vec![0; 32]);
+    assert!(matches!(verify_noir_proof(proof, vk, extra), Err(Risc0Error::VerificationFailed(_))));
+}
+
+#[test]
+#[ignore = "compiles and proves the ecdsa circuit; needs nargo and bb"]
+fn fixture_signature_proves_and_verifies() {
+    if !available(&["nargo", "bb"]) {
+        return;
+    }
+    let output = prove_assertion_noir(signature(), field("message_hash")).unwrap();
+    let verify =
+        |tamper| verify_noir_proof(output.proof.clone(), output.verification_key.clone(), public_inputs(tamper));
+    assert_eq!(verify(false), Ok(true));
+    assert_eq!(verify(true), Ok(false));
+}
+
+#[test]
+#[ignore = "compiles the ecdsa circuit; needs nargo and bb"]
+fn signature_over_another_hash_does_not_prove() {
+    if !available(&["nargo", "bb"]) {
+        return;
+    }
+    let mut message_hash = field("message_hash");
+    message_hash[0] ^= 0x01;
+    let error = prove_assertion_noir(signature(), message_hash).unwrap_err();
+    assert!(matches!(error, Risc0Error::ProveError(ref msg) if msg.contains("nargo execute")), "{error}");
+}
@@ -0,0 +1,155 @@
+#![cfg(feature = "publish")]
+
+use std::path::{Path, PathBuf};
+
+use alloy::network::TransactionBuilder;
+use alloy::node_bindings::Anvil;
+use alloy::primitives::{Address, Bytes};
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy::rpc::types::TransactionRequest;
+use assert_cmd::Command;
+use mopro_bindings::{AssertionJournal, HybridProofOutput, NoirProofOutput, NO_ALLOWLIST, POLICY};
+use risc0_zkvm::{FakeReceipt, InnerReceipt, Receipt, ReceiptClaim};
+use sha2::{Digest, Sha256};
+
+/// Init code of a contract whose runtime code is a single `STOP`: every call succeeds and the
+/// calldata stays inspectable in the transaction.
+const RECORDER_INITCODE: &str = "6001600c60003960016000f300";
+
+fn fake_receipt(dir: &Path) -> PathBuf {
+    let journal = vec![7u8; 12];
+    let claim = ReceiptClaim::ok(methods::ASSERTION_ID, journal.clone());
+    let receipt = Receipt::new(InnerReceipt::Fake(FakeReceipt::new(claim)), journal);
+    let path = dir.join("receipt.bin");
+    std::fs::write(&path, bincode::serialize(&receipt).unwrap()).unwrap();
+    path
+}
+
+/// A bundle of a fake receipt committing the fixture assertion's signature, which the
+/// consistency check rebuilds the signed message for.
+fn bundle(dir: &Path) -> HybridProofOutput {
+    let hex32 = |hex: &str| -> [u8; 32] { hex::decode(hex).unwrap().try_into().unwrap() };
+    let journal = AssertionJournal {
+        policy: POLICY,
+        rp_id_hash: Sha256::digest("LMRM26A744.xyz.elus.aegis.app-attester").into(),
+        public_key_x: hex32("85e322caa0f981bc18cd53574f1dc761c139d15030a36b1b249cc158c822c450"),
+        public_key_y: hex32("6b1574b6b1c5b5bfaf8e2a1dfe3deaa87c8802d863c8aac23aa990560a8a56b4"),
+        signature_r: hex32("38fb757e98a68de08beb869019ba0f671b91204cb7fdbab318a1921d0ddb77e8"),
+        signature_s: hex32("c98ff7da70a0e691a98095e10dca9b8bd0a9e6f10b37e175bda8c34dfc00c5b2"),
+        counter: 1,
+        client_data_hash: Sha256::digest("x = 15").into(),
+        attestation_image_id: [6; 32],
+        attestation_claim_digest: [7; 32],
+        challenge_hash: [0; 32],
+        allowlist_entry: NO_ALLOWLIST,
+        session_nonce: [0; 32],
+        input_digest: [0; 32],
+        policy_hash: Some([0; 32]),
+        cert_chain_image_id: Some([5; 32]),
+    }
+    .encode();
+    let claim = ReceiptClaim::ok(methods::ASSERTION_ID, journal.clone());
+    let receipt = Receipt::new(InnerReceipt::Fake(FakeReceipt::new(claim)), journal);
+    let noir = NoirProofOutput { proof: vec![1; 64], verification_key: vec![2; 64] };
+    let bundle = HybridProofOutput::new(bincode::serialize(&receipt).unwrap(), noir).unwrap();
+    std::fs::write(dir.join("bundle.bin"), bundle.to_bytes().unwrap()).unwrap();
+    bundle
+}
+
+fn dry_run_calldata(receipt: &Path, contract: Address) -> String {
+    let output = Command::cargo_bin("publish")
+        .unwrap()
+        .arg("--receipt")
+        .arg(receipt)
+        .args(["--contract", &contract.to_string(), "--dry-run", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    summary["calldata"].as_str().unwrap().to_string()
+}
+
+#[test]
+fn dry_run_prints_submit_calldata() {
+    let dir = tempfile::tempdir().unwrap();
+    let calldata = dry_run_calldata(&fake_receipt(dir.path()), Address::ZERO);
+
+    let bytes = hex::decode(calldata.trim_start_matches("0x")).unwrap();
+    // submit(bytes,bytes)
+    assert_eq!(&bytes[..4], &alloy::primitives::keccak256("submit(bytes,bytes)")[..4]);
+    // The seal starts with the fake-verifier selector; the journal is carried verbatim.
+    assert!(calldata.contains("ffffffff"));
+    assert!(calldata.contains(&hex::encode([7u8; 12])));
+}
+
+#[tokio::test]
+#[ignore = "needs anvil on PATH"]
+async fn sends_to_contract_on_anvil() {
+    let anvil = Anvil::new().spawn();
+    let provider = ProviderBuilder::new()
+        .wallet(alloy::signers::local::PrivateKeySigner::from(anvil.keys()[0].clone()))
+        .connect_http(anvil.endpoint_url());
+    let deploy = TransactionRequest::default().with_deploy_code(Bytes::from(hex::decode(RECORDER_INITCODE).unwrap()));
+    let contract = provider
+        .send_transaction(deploy)
+        .await
+        .unwrap()
+        .get_receipt()
+        .await
+        .unwrap()
+        .contract_address
+        .unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let receipt = fake_receipt(dir.path());
+    let output = Command::cargo_bin("publish")
+        .unwrap()
+        .arg("--receipt")
+        .arg(&receipt)
+        .args(["--contract", &contract.to_string(), "--rpc-url", &anvil.endpoint()])
+        .args(["--private-key", &hex::encode(anvil.keys()[0].to_bytes()), "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["status"], "success");
+
+    let tx_hash = summary["tx_hash"].as_str().unwrap().parse().unwrap();
+    let tx = provider.get_transaction_by_hash(tx_hash).await.unwrap().unwrap();
+    assert_eq!(tx.input().to_string(), dry_run_calldata(&receipt, contract));
+}
+
+#[test]
+fn bundle_sends_its_receipt() {
+    let dir = tempfile::tempdir().unwrap();
+    let bundle = bundle(dir.path());
+    std::fs::write(dir.path().join("receipt.bin"), &bundle.receipt).unwrap();
+
+    let output = Command::cargo_bin("publish")
+        .unwrap()
+        .arg("--bundle")
+        .arg(dir.path().join("bundle.bin"))
+        .args(["--contract", &Address::ZERO.to_string(), "--dry-run", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["calldata"], dry_run_calldata(&dir.path().join("receipt.bin"), Address::ZERO));
+}
+
+#[test]
+fn mismatched_bundle_is_refused() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut bundle = bundle(dir.path());
+    bundle.message_hash[0] ^= 0x01;
+    std::fs::write(dir.path().join("bundle.bin"), bundle.to_bytes().unwrap()).unwrap();
+
+    Command::cargo_bin("publish")
+        .unwrap()
+        .arg("--bundle")
+        .arg(dir.path().join("bundle.bin"))
+        .args(["--contract", &Address::ZERO.to_string(), "--dry-run"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("another sha256_hash than its receipt's journal"));
+}
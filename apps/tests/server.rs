@@ -0,0 +1,278 @@
+#![cfg(feature = "server")]
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use appattest_rs::error::AppAttestError;
+use appattest_rs::store::{ChallengeStore, InMemoryChallengeStore, InMemoryKeyStore};
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use base64::{engine::general_purpose, Engine};
+use http_body_util::BodyExt;
+use mopro_bindings::server::{router, upload_router, ServerConfig, ServerState, Uploads};
+use mopro_bindings::{AssertionJournal, NO_ALLOWLIST, POLICY};
+use risc0_zkvm::{FakeReceipt, InnerReceipt, Receipt, ReceiptClaim};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tower::ServiceExt;
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures");
+
+fn fixture(name: &str) -> String {
+    std::fs::read_to_string(format!("{FIXTURES}/{name}"))
+        .unwrap()
+        .trim()
+        .to_string()
+}
+
+/// Hands out the recorded challenge once, so the recorded attestation can be replayed.
+struct RecordedChallenge(Mutex<Option<String>>);
+
+impl ChallengeStore for RecordedChallenge {
+    fn issue(&self) -> Result<String, AppAttestError> {
+        Ok(fixture("challenge.b64"))
+    }
+
+    fn consume(&self, challenge: &str) -> bool {
+        let mut pending = self.0.lock().unwrap();
+        if pending.as_deref() == Some(challenge) {
+            *pending = None;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+fn state(challenges: Arc<dyn ChallengeStore>) -> ServerState {
+    ServerState {
+        keys: Arc::new(InMemoryKeyStore::new()),
+        challenges,
+        config: Arc::new(ServerConfig {
+            app_id: fixture("app_id.txt"),
+            development: true,
+            verification_time: Some(fixture("recorded_at.txt").parse().unwrap()),
+        }),
+    }
+}
+
+fn recorded_state() -> ServerState {
+    state(Arc::new(RecordedChallenge(Mutex::new(Some(fixture("challenge.b64"))))))
+}
+
+async fn post(app: &axum::Router, path: &str, body: Value) -> (StatusCode, Value) {
+    let request = Request::post(path)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    (status, serde_json::from_slice(&bytes).unwrap())
+}
+
+fn attest_body() -> Value {
+    json!({
+        "keyId": fixture("key_id.b64"),
+        "challenge": fixture("challenge.b64"),
+        "attestation": fixture("attestation.b64"),
+    })
+}
+
+fn assert_body() -> Value {
+    json!({
+        "keyId": fixture("key_id.b64"),
+        "assertion": fixture("assertion.b64"),
+        "clientData": fixture("client_data.b64"),
+    })
+}
+
+#[tokio::test]
+async fn issues_distinct_challenges() {
+    let app = router(state(Arc::new(InMemoryChallengeStore::new(Duration::from_secs(60)))));
+    let (status, first) = post(&app, "/challenge", json!({})).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, second) = post(&app, "/challenge", json!({})).await;
+    assert_ne!(first["challenge"], second["challenge"]);
+}
+
+#[tokio::test]
+async fn attest_then_assert() {
+    let app = router(recorded_state());
+
+    let (status, body) = post(&app, "/attest", attest_body()).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert!(body["publicKey"].as_str().unwrap().starts_with("0485e322"));
+
+    let (status, body) = post(&app, "/assert", assert_body()).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["counter"], 1);
+
+    // Replaying the same assertion must not pass the counter check again.
+    let (status, body) = post(&app, "/assert", assert_body()).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["code"], AppAttestError::InvalidCounter.code());
+}
+
+#[tokio::test]
+async fn challenges_cannot_be_reused() {
+    let app = router(recorded_state());
+    assert_eq!(post(&app, "/attest", attest_body()).await.0, StatusCode::OK);
+
+    let (status, body) = post(&app, "/attest", attest_body()).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["code"], AppAttestError::UnknownChallenge.code());
+}
+
+#[tokio::test]
+async fn assert_requires_an_attested_key() {
+    let app = router(recorded_state());
+    let (status, body) = post(&app, "/assert", assert_body()).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["error"]["code"], AppAttestError::UnknownKeyID.code());
+}
+
+#[tokio::test]
+async fn verify_proof_checks_the_image_id() {
+    std::env::set_var("RISC0_DEV_MODE", "1");
+    let journal = assertion_journal([0; 32]);
+    let receipt = assertion_receipt(&journal);
+    let app = router(recorded_state());
+
+    let (status, body) = post(&app, "/verify-proof", json!({ "receipt": receipt })).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["guest"], "assertion");
+    assert_eq!(body["journal"], hex::encode(journal.encode()));
+
+    // The key of an assertion comes from this build's attestation guest only.
+    let forged = assertion_receipt(&AssertionJournal { attestation_image_id: [0xee; 32], ..journal.clone() });
+    let (status, body) = post(&app, "/verify-proof", json!({ "receipt": forged })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["code"], 2005);
+    // And that guest's key from this build's certificate chain guest.
+    let forged = assertion_receipt(&AssertionJournal { cert_chain_image_id: Some([0xee; 32]), ..journal });
+    let (status, body) = post(&app, "/verify-proof", json!({ "receipt": forged })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["code"], 2005);
+
+    let (status, body) = post(
+        &app,
+        "/verify-proof",
+        json!({ "receipt": receipt, "guest": "attestation" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["code"], 2005);
+
+    let (status, body) = post(&app, "/verify-proof", json!({ "receipt": "AAAA" })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["code"], 2004);
+}
+
+/// An assertion journal committing `session_nonce`.
+fn assertion_journal(session_nonce: [u8; 32]) -> AssertionJournal {
+    AssertionJournal {
+        policy: POLICY,
+        rp_id_hash: [1; 32],
+        public_key_x: [2; 32],
+        public_key_y: [3; 32],
+        signature_r: [4; 32],
+        signature_s: [5; 32],
+        counter: 1,
+        client_data_hash: [6; 32],
+        attestation_image_id: risc0_zkvm::sha::Digest::from(methods::ATTESTATION_REST_ID).into(),
+        attestation_claim_digest: [8; 32],
+        challenge_hash: [0; 32],
+        allowlist_entry: NO_ALLOWLIST,
+        session_nonce,
+        input_digest: [0; 32],
+        policy_hash: Some([0; 32]),
+        cert_chain_image_id: Some(risc0_zkvm::sha::Digest::from(methods::CERT_CHAIN_ID).into()),
+    }
+}
+
+/// A fake assertion receipt of `journal`, base64 as in the request.
+fn assertion_receipt(journal: &AssertionJournal) -> String {
+    let journal = journal.encode();
+    let claim = ReceiptClaim::ok(methods::ASSERTION_ID, journal.clone());
+    let receipt = Receipt::new(InnerReceipt::Fake(FakeReceipt::new(claim)), journal);
+    general_purpose::STANDARD.encode(bincode::serialize(&receipt).unwrap())
+}
+
+#[tokio::test]
+async fn verify_proof_checks_the_session_nonce() {
+    std::env::set_var("RISC0_DEV_MODE", "1");
+    let app = router(state(Arc::new(InMemoryChallengeStore::new(Duration::from_secs(60)))));
+    let issue = || async { post(&app, "/challenge", json!({})).await.1["challenge"].as_str().unwrap().to_string() };
+    let bytes = |nonce: &str| -> [u8; 32] { general_purpose::STANDARD.decode(nonce).unwrap().try_into().unwrap() };
+
+    let nonce = issue().await;
+    let receipt = assertion_receipt(&assertion_journal(bytes(&nonce)));
+    let (status, body) = post(&app, "/verify-proof", json!({ "receipt": receipt, "sessionNonce": nonce })).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    // Each nonce backs a single verification.
+    let (status, body) = post(&app, "/verify-proof", json!({ "receipt": receipt, "sessionNonce": nonce })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["code"], AppAttestError::UnknownChallenge.code());
+
+    // A proof made for an earlier session does not pass for a fresh nonce.
+    let fresh = issue().await;
+    let (status, body) = post(&app, "/verify-proof", json!({ "receipt": receipt, "sessionNonce": fresh })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["code"], 2005);
+}
+
+type Answer = (StatusCode, Option<String>);
+
+/// `PUT /uploads/{id}` and the status and `Range` of the answer.
+async fn put_chunk(app: &axum::Router, id: &str, token: &str, range: &str, body: &[u8]) -> Answer {
+    let request = Request::put(format!("/uploads/{id}"))
+        .header("authorization", format!("Bearer {token}"))
+        .header("content-range", range)
+        .body(Body::from(body.to_vec()))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let range = response.headers().get("range").map(|value| value.to_str().unwrap().to_string());
+    (response.status(), range)
+}
+
+#[tokio::test]
+async fn uploads_arrive_in_chunks() {
+    let uploads = Arc::new(Uploads::default());
+    let app = upload_router(uploads.clone(), "token");
+    let bundle = b"a proof bundle".to_vec();
+    let id = hex::encode(Sha256::digest(&bundle));
+
+    let resume = |range: Option<&str>| (StatusCode::PERMANENT_REDIRECT, range.map(String::from));
+
+    assert_eq!(put_chunk(&app, &id, "token", "bytes */14", b"").await, resume(None));
+    assert_eq!(put_chunk(&app, &id, "token", "bytes 0-5/14", &bundle[..6]).await, resume(Some("bytes=0-5")));
+    // A chunk past what arrived is dropped, and one overlapping it only adds its new bytes.
+    assert_eq!(put_chunk(&app, &id, "token", "bytes 8-13/14", &bundle[8..]).await, resume(Some("bytes=0-5")));
+    assert_eq!(uploads.get(&id), None);
+    assert_eq!(put_chunk(&app, &id, "token", "bytes 3-13/14", &bundle[3..]).await.0, StatusCode::CREATED);
+    assert_eq!(uploads.get(&id), Some(bundle));
+    assert_eq!(put_chunk(&app, &id, "token", "bytes */14", b"").await.0, StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn uploads_are_checked() {
+    let app = upload_router(Arc::new(Uploads::default()), "token");
+    let id = hex::encode(Sha256::digest(b"abcd"));
+
+    assert_eq!(put_chunk(&app, &id, "other", "bytes */4", b"").await.0, StatusCode::UNAUTHORIZED);
+    assert_eq!(put_chunk(&app, "abcd", "token", "bytes */4", b"").await.0, StatusCode::BAD_REQUEST);
+    assert_eq!(put_chunk(&app, &id, "token", "bytes 0-3/4", b"ab").await.0, StatusCode::BAD_REQUEST);
+    assert_eq!(put_chunk(&app, &id, "token", "bytes 3-0/4", b"").await.0, StatusCode::BAD_REQUEST);
+    let too_large = format!("bytes */{}", mopro_bindings::server::MAX_UPLOAD_SIZE + 1);
+    assert_eq!(put_chunk(&app, &id, "token", &too_large, b"").await.0, StatusCode::PAYLOAD_TOO_LARGE);
+
+    // The total is fixed by the first request.
+    assert_eq!(put_chunk(&app, &id, "token", "bytes 0-1/4", b"ab").await.0, StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(put_chunk(&app, &id, "token", "bytes */5", b"").await.0, StatusCode::CONFLICT);
+
+    // Bytes that do not hash to the ID are thrown away.
+    assert_eq!(put_chunk(&app, &id, "token", "bytes 2-3/4", b"xx").await.0, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(put_chunk(&app, &id, "token", "bytes */4", b"").await, (StatusCode::PERMANENT_REDIRECT, None));
+}
@@ -0,0 +1,165 @@
+//! `ProofUploader` against `upload_router` over real sockets, with a proxy in between that
+//! cuts connections the way a mobile network does.
+
+#![cfg(all(feature = "server", feature = "uploader"))]
+
+use std::io::{Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use mopro_bindings::server::{upload_router, Uploads};
+use mopro_bindings::uploader::{ProofBundle, ProofUploader, UploadConfig, UploadError, UploadProgressListener};
+
+const TOKEN: &str = "upload token";
+
+/// Serves `app` on a free port from a runtime of its own, since the uploader blocks.
+fn serve(app: axum::Router) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.set_nonblocking(true).unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let listener = tokio::net::TcpListener::from_std(listener).unwrap();
+            axum::serve(listener, app).await.unwrap();
+        })
+    });
+    addr
+}
+
+/// Forwards connections to `upstream`, cutting each after the number of request bytes the
+/// next entry of `cuts` gives; connections past the end of `cuts` are left alone.
+fn proxy(upstream: SocketAddr, cuts: impl IntoIterator<Item = usize>) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let mut cuts = cuts.into_iter().collect::<Vec<_>>().into_iter();
+    thread::spawn(move || {
+        for client in listener.incoming() {
+            let client = client.unwrap();
+            let server = TcpStream::connect(upstream).unwrap();
+            let limit = cuts.next().unwrap_or(usize::MAX);
+            let (mut from_server, mut to_client) = (server.try_clone().unwrap(), client.try_clone().unwrap());
+            thread::spawn(move || {
+                let _ = std::io::copy(&mut from_server, &mut to_client);
+            });
+            thread::spawn(move || forward(client, server, limit));
+        }
+    });
+    addr
+}
+
+fn forward(mut client: TcpStream, mut server: TcpStream, mut limit: usize) {
+    let mut buf = [0; 4096];
+    loop {
+        let read = match client.read(&mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(read) => read,
+        };
+        let write = read.min(limit);
+        if server.write_all(&buf[..write]).is_err() || write < read {
+            break;
+        }
+        limit -= write;
+    }
+    let _ = client.shutdown(Shutdown::Both);
+    let _ = server.shutdown(Shutdown::Both);
+}
+
+/// Every `(sent, total)` it was told about.
+#[derive(Default)]
+struct Recorded(Mutex<Vec<(u64, u64)>>);
+
+impl UploadProgressListener for Recorded {
+    fn on_progress(&self, sent: u64, total: u64) {
+        self.0.lock().unwrap().push((sent, total));
+    }
+}
+
+fn bundle(len: usize) -> ProofBundle {
+    ProofBundle { receipt: (0..len).map(|i| (i * 7 % 251) as u8).collect() }
+}
+
+fn config() -> UploadConfig {
+    UploadConfig { chunk_size: 4096, max_attempts: 5, backoff_ms: 10, timeout_ms: 30_000 }
+}
+
+fn uploader(addr: SocketAddr, token: &str, config: UploadConfig) -> Arc<ProofUploader> {
+    ProofUploader::with_config(format!("http://{addr}/uploads"), token.into(), config).unwrap()
+}
+
+#[test]
+fn resumes_across_dropped_connections() {
+    let uploads = Arc::new(Uploads::default());
+    let server = serve(upload_router(uploads.clone(), TOKEN));
+    // Three connections cut partway through the third or so chunk, then a good one.
+    let addr = proxy(server, [10_000, 10_000, 10_000]);
+    let uploader = uploader(addr, TOKEN, config());
+    let listener = Arc::new(Recorded::default());
+    uploader.set_progress_listener(Some(listener.clone()));
+
+    let bundle = bundle(64 * 1024);
+    let result = uploader.upload(&bundle).unwrap();
+    assert_eq!((result.upload_id.as_str(), result.size), (bundle.upload_id().as_str(), 64 * 1024));
+    assert!(result.retries >= 3, "{result:?}");
+    assert_eq!(uploads.get(&bundle.upload_id()), Some(bundle.receipt));
+
+    let progress = listener.0.lock().unwrap();
+    assert!(progress.windows(2).all(|pair| pair[0].0 <= pair[1].0), "{progress:?}");
+    assert_eq!(progress.last(), Some(&(64 * 1024, 64 * 1024)));
+}
+
+#[test]
+fn resumes_a_partial_upload() {
+    let uploads = Arc::new(Uploads::default());
+    let server = serve(upload_router(uploads.clone(), TOKEN));
+    let bundle = bundle(32 * 1024);
+
+    // The first attempt loses its only connection after a few chunks.
+    let cut = uploader(proxy(server, [15_000]), TOKEN, UploadConfig { max_attempts: 1, ..config() });
+    assert!(matches!(cut.upload(&bundle), Err(UploadError::Failed { attempts: 1, .. })));
+    assert_eq!(uploads.get(&bundle.upload_id()), None);
+
+    // The next one, say after the app restarts, picks up where it stopped.
+    let uploader = uploader(server, TOKEN, config());
+    let listener = Arc::new(Recorded::default());
+    uploader.set_progress_listener(Some(listener.clone()));
+    assert_eq!(uploader.upload(&bundle).unwrap().retries, 0);
+    assert!(listener.0.lock().unwrap()[0].0 >= 4096);
+    assert_eq!(uploads.get(&bundle.upload_id()), Some(bundle.receipt));
+}
+
+#[test]
+fn a_wrong_token_is_not_retried() {
+    let server = serve(upload_router(Arc::new(Uploads::default()), TOKEN));
+    let uploader = uploader(server, "other token", config());
+    assert_eq!(uploader.upload(&bundle(100)), Err(UploadError::Unauthorized));
+}
+
+#[test]
+fn gives_up_after_max_attempts() {
+    let server = serve(upload_router(Arc::new(Uploads::default()), TOKEN));
+    let addr = proxy(server, [0; 100]);
+    let result = uploader(addr, TOKEN, config()).upload(&bundle(100));
+    assert!(matches!(result, Err(UploadError::Failed { attempts: 5, .. })), "{result:?}");
+}
+
+#[test]
+fn times_out_on_a_stalled_server() {
+    // Accepts connections and never answers.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        let mut held = Vec::new();
+        for stream in listener.incoming() {
+            held.push(stream);
+        }
+    });
+
+    let config = UploadConfig { timeout_ms: 500, ..config() };
+    let started = std::time::Instant::now();
+    let result = uploader(addr, TOKEN, config).upload(&bundle(100));
+    assert_eq!(result, Err(UploadError::TimedOut { sent: 0, total: 100 }));
+    assert!(started.elapsed() < Duration::from_secs(10));
+}
@@ -0,0 +1,159 @@
+use std::path::{Path, PathBuf};
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures");
+const APP_ID: &str = "LMRM26A744.xyz.elus.aegis.app-attester";
+
+/// Proves the fixture attestation in dev mode and returns the receipt path.
+fn attestation_receipt(dir: &Path) -> PathBuf {
+    Command::cargo_bin("attestation")
+        .unwrap()
+        .env("RISC0_DEV_MODE", "1")
+        .args(["prove", "--attestation"])
+        .arg(Path::new(FIXTURES).join("attestation.b64"))
+        .args(["--challenge", "ExYiYJ4jA3rGL9oQN8ihDP4seccnxwHSEU28u8aCQaU="])
+        .args(["--app-id", APP_ID, "--key-id", "HD8zVqwgesSBwPaYj5icRg1GI/b6SOoa4TqieTEBA0g="])
+        .args(["--timestamp", "1751800000", "--development", "--out-dir"])
+        .arg(dir)
+        .assert()
+        .success();
+    dir.join("receipt.bin")
+}
+
+/// Proves the fixture assertion in dev mode and returns the receipt path and journal.
+fn assertion_receipt(dir: &Path) -> (PathBuf, Vec<u8>) {
+    let manifest = dir.join("manifest.json");
+    Command::cargo_bin("assertion")
+        .unwrap()
+        .env("RISC0_DEV_MODE", "1")
+        .arg("--assertion-file")
+        .arg(Path::new(FIXTURES).join("assertion.b64"))
+        .arg("--client-data")
+        .arg(Path::new(FIXTURES).join("client_data.b64"))
+        .args(["--app-id", APP_ID, "--attestation-receipt"])
+        .arg(attestation_receipt(&dir.join("attestation")))
+        .arg("--out-dir")
+        .arg(dir)
+        .arg("--manifest")
+        .arg(&manifest)
+        .assert()
+        .success();
+
+    let manifest: serde_json::Value = serde_json::from_slice(&std::fs::read(manifest).unwrap()).unwrap();
+    let journal = hex::decode(manifest["journal_hex"].as_str().unwrap()).unwrap();
+    (dir.join("receipt.bin"), journal)
+}
+
+fn verify_cmd(receipt: &Path) -> Command {
+    let mut cmd = Command::cargo_bin("verify").unwrap();
+    cmd.env("RISC0_DEV_MODE", "1").arg("--receipt").arg(receipt);
+    cmd
+}
+
+#[test]
+fn valid_receipt_verifies() {
+    let dir = tempfile::tempdir().unwrap();
+    let (receipt, journal) = assertion_receipt(dir.path());
+
+    let output = verify_cmd(&receipt)
+        .args(["--kind", "assertion", "--json", "--expect-journal"])
+        .arg(hex::encode(&journal))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["verified"], true);
+    assert_eq!(summary["journal_hex"], hex::encode(&journal));
+    assert_eq!(summary["journal"]["public_key_x"].as_str().unwrap().len(), 64);
+
+    verify_cmd(&receipt).args(["--kind", "attestation"]).assert().failure();
+    verify_cmd(&receipt)
+        .args(["--kind", "assertion", "--expect-journal", "00"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("journal mismatch"));
+}
+
+#[test]
+fn session_nonce_must_match() {
+    let dir = tempfile::tempdir().unwrap();
+    // Proven without --session-nonce, so the journal commits zeros.
+    let (receipt, _) = assertion_receipt(dir.path());
+
+    verify_cmd(&receipt)
+        .args(["--kind", "assertion", "--session-nonce"])
+        .arg("00".repeat(32))
+        .assert()
+        .success();
+    verify_cmd(&receipt)
+        .args(["--kind", "assertion", "--session-nonce"])
+        .arg("01".repeat(32))
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("does not match"));
+}
+
+#[test]
+fn minimum_appattest_version_is_enforced() {
+    let dir = tempfile::tempdir().unwrap();
+    let receipt = attestation_receipt(dir.path());
+
+    // appattest-rs shares the workspace version.
+    verify_cmd(&receipt)
+        .args(["--kind", "attestation", "--min-appattest-version", env!("CARGO_PKG_VERSION")])
+        .assert()
+        .success();
+    verify_cmd(&receipt)
+        .args(["--kind", "attestation", "--min-appattest-version", "255.0.0"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("255.0.0 or later is required"));
+}
+
+#[test]
+fn tampered_receipt_is_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let (receipt, journal) = assertion_receipt(dir.path());
+
+    // Flip a bit in the serialized journal, which sits after the claim that commits to it.
+    let mut bytes = std::fs::read(&receipt).unwrap();
+    let start = bytes
+        .windows(journal.len())
+        .rposition(|w| w == journal.as_slice())
+        .expect("journal bytes in receipt");
+    bytes[start] ^= 0x01;
+    let tampered = dir.path().join("tampered.bin");
+    std::fs::write(&tampered, &bytes).unwrap();
+
+    verify_cmd(&tampered)
+        .args(["--kind", "assertion"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("error:"))
+        .stderr(predicates::str::contains("panicked").not());
+
+    std::fs::write(&tampered, b"not a receipt").unwrap();
+    verify_cmd(&tampered)
+        .args(["--kind", "assertion"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("Invalid receipt"));
+}
+
+#[test]
+fn stale_attestation_is_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let receipt = attestation_receipt(dir.path());
+
+    verify_cmd(&receipt)
+        .args(["--kind", "attestation", "--max-age", "3600", "--now", "1751803600"])
+        .assert()
+        .success();
+    verify_cmd(&receipt)
+        .args(["--kind", "attestation", "--max-age", "3600", "--now", "1751803601"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("older than 3600 s"));
+}
@@ -0,0 +1,116 @@
+//! What the appattest-rs fuzz targets run on each input. appattest-rs's `tests/corpus.rs`
+//! includes this file to run its checked-in corpus through the same code.
+//!
+//! Inputs are checked against the device recording in `apps/fixtures`, so that one keeping
+//! the recorded values gets past the first comparisons. Every function returns whatever the
+//! input; only a panic is a finding.
+
+use appattest_rs::assertion::Assertion;
+use appattest_rs::attestation::Attestation;
+use appattest_rs::cose::CoseKey;
+use appattest_rs::key::PublicKey;
+use appattest_rs::receipt::Receipt;
+use base64::{engine::general_purpose, Engine};
+use sha2::{Digest, Sha256};
+
+const APP_ID: &str = include_str!("../../apps/fixtures/app_id.txt");
+const CHALLENGE: &str = include_str!("../../apps/fixtures/challenge.b64");
+const KEY_ID: &str = include_str!("../../apps/fixtures/key_id.b64");
+const CLIENT_DATA: &str = include_str!("../../apps/fixtures/client_data.b64");
+const PUBLIC_KEY: &str = include_str!("../../apps/fixtures/public_key.pem");
+const RECORDED_AT: i64 = 1_751_800_000;
+
+/// Runs one input.
+pub type Target = fn(&[u8]);
+
+/// Each target by the name of its corpus directory.
+pub const TARGETS: &[(&str, Target)] = &[
+    ("attestation", attestation),
+    ("auth_data", auth_data),
+    ("assertion", assertion),
+    ("cose_key", cose_key),
+    ("receipt", receipt),
+];
+
+fn decoded(base64: &str) -> Vec<u8> {
+    general_purpose::STANDARD.decode(base64.trim()).unwrap()
+}
+
+fn app_id_hash() -> [u8; 32] {
+    Sha256::digest(APP_ID.trim()).into()
+}
+
+fn public_key() -> [u8; 65] {
+    PublicKey::from_pem(PUBLIC_KEY).unwrap().to_uncompressed()
+}
+
+/// An attestation object as the device sends it, in CBOR or, if it is text, in base64. Its
+/// authenticator data is checked against the recorded key, the chain's or not, so that
+/// inputs without a valid chain still reach those checks.
+pub fn attestation(data: &[u8]) {
+    if let Ok(text) = std::str::from_utf8(data) {
+        let _ = Attestation::from_base64(text);
+    }
+    let Ok(attestation) = Attestation::from_cbor(data) else {
+        return;
+    };
+    let _ = Receipt::from_ber(attestation.receipt());
+    let _ = attestation.verify_chain(&decoded(CHALLENGE), RECORDED_AT);
+    let _ = attestation.verify_auth_data(&app_id_hash(), &decoded(KEY_ID), &public_key());
+}
+
+/// Authenticator data, in an attestation with an empty statement.
+pub fn auth_data(data: &[u8]) {
+    let Ok(attestation) = Attestation::from_cbor(&attestation_with_auth_data(data)) else {
+        return;
+    };
+    let _ = attestation.verify_auth_data(&app_id_hash(), &decoded(KEY_ID), &public_key());
+}
+
+/// `{"fmt": "apple-appattest", "attStmt": {"x5c": [], "receipt": h''}, "authData": auth_data}`.
+fn attestation_with_auth_data(auth_data: &[u8]) -> Vec<u8> {
+    let mut cbor = vec![0xa3, 0x63];
+    cbor.extend(b"fmt");
+    cbor.push(0x6f);
+    cbor.extend(b"apple-appattest");
+    cbor.push(0x67);
+    cbor.extend(b"attStmt");
+    cbor.extend([0xa2, 0x63]);
+    cbor.extend(b"x5c");
+    cbor.extend([0x80, 0x67]);
+    cbor.extend(b"receipt");
+    cbor.extend([0x40, 0x68]);
+    cbor.extend(b"authData");
+    match auth_data.len() {
+        len @ 0..=23 => cbor.push(0x40 | len as u8),
+        len @ 24..=0xff => cbor.extend([0x58, len as u8]),
+        len => {
+            cbor.push(0x5b);
+            cbor.extend((len as u64).to_be_bytes());
+        }
+    }
+    cbor.extend(auth_data);
+    cbor
+}
+
+/// An assertion object, in CBOR or base64, checked against the recorded key.
+pub fn assertion(data: &[u8]) {
+    if let Ok(text) = std::str::from_utf8(data) {
+        let _ = Assertion::from_base64(text);
+    }
+    let Ok(assertion) = Assertion::from_cbor(data) else {
+        return;
+    };
+    let _ = assertion.counter();
+    let _ = assertion.verify_raw(&decoded(CLIENT_DATA), &app_id_hash(), &public_key(), None);
+}
+
+pub fn cose_key(data: &[u8]) {
+    let _ = CoseKey::from_cbor(data);
+}
+
+pub fn receipt(data: &[u8]) {
+    if let Ok(receipt) = Receipt::from_ber(data) {
+        let _ = receipt.needs_refresh(RECORDED_AT);
+    }
+}
@@ -0,0 +1,24 @@
+//! What the journal fuzz target runs on each input. guest-core's `tests/corpus.rs` includes
+//! this file to run its checked-in corpus through the same code.
+
+use guest_core::{AssertionJournal, AttestationJournal, CertChainJournal};
+
+/// Runs one input.
+pub type Target = fn(&[u8]);
+
+/// Each target by the name of its corpus directory.
+pub const TARGETS: &[(&str, Target)] = &[("journal", journal)];
+
+/// A journal of any of the guests. One that decodes must encode to bytes that decode to it
+/// again; not necessarily to `data`, since any nonzero `low_s` byte decodes to `true`.
+pub fn journal(data: &[u8]) {
+    if let Ok(journal) = AssertionJournal::decode(data) {
+        assert_eq!(AssertionJournal::decode(&journal.encode()), Ok(journal));
+    }
+    if let Ok(journal) = AttestationJournal::decode(data) {
+        assert_eq!(AttestationJournal::decode(&journal.encode()), Ok(journal));
+    }
+    if let Ok(journal) = CertChainJournal::decode(data) {
+        assert_eq!(CertChainJournal::decode(&journal.encode()), Ok(journal));
+    }
+}
@@ -0,0 +1,6 @@
+//! The harnesses of the fuzz targets in `fuzz_targets/`, one function per target. The crates
+//! they fuzz include them in their `tests/corpus.rs`, so that every input checked in under
+//! their `tests/corpus/` keeps running in `cargo test`.
+
+pub mod appattest;
+pub mod journal;
@@ -0,0 +1,46 @@
+//! The app IDs a guest build accepts.
+//!
+//! `methods/build.rs` reads `APP_ID_ALLOWLIST`, a comma-separated list of
+//! `<team id>.<bundle id>` app IDs, and compiles the guests with `RP_ID_HASH_ALLOWLIST`
+//! set to the lowercase hex `SHA256` of each, in the same order. The guests read it with
+//! `option_env!`, so the list is part of the ELF: every list has its own image IDs, and a
+//! verifier pinning an image ID pins the apps it accepts.
+
+/// Entry committed by a guest built without an allow-list, which accepts any app ID.
+pub const NO_ALLOWLIST: u32 = u32::MAX;
+
+/// Returns the index of `rp_id_hash` in `list`, the value of `RP_ID_HASH_ALLOWLIST`, or
+/// [`NO_ALLOWLIST`] if the guest was built without one. `None` means the app ID is not
+/// allowed.
+pub fn allowlist_entry(list: Option<&str>, rp_id_hash: &[u8; 32]) -> Option<u32> {
+    let list = match list.map(str::trim) {
+        None | Some("") => return Some(NO_ALLOWLIST),
+        Some(list) => list,
+    };
+    let hex: String = rp_id_hash.iter().map(|b| format!("{b:02x}")).collect();
+    list.split(',')
+        .position(|entry| entry.trim().eq_ignore_ascii_case(&hex))
+        .map(|index| index as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIRST: &str = "0101010101010101010101010101010101010101010101010101010101010101";
+    const SECOND: &str = "ABABABABABABABABABABABABABABABABABABABABABABABABABABABABABABABAB";
+
+    #[test]
+    fn matches_entries_by_position() {
+        let list = format!("{FIRST}, {SECOND}");
+        assert_eq!(allowlist_entry(Some(&list), &[0x01; 32]), Some(0));
+        assert_eq!(allowlist_entry(Some(&list), &[0xab; 32]), Some(1));
+        assert_eq!(allowlist_entry(Some(&list), &[0x02; 32]), None);
+    }
+
+    #[test]
+    fn no_list_accepts_any_app() {
+        assert_eq!(allowlist_entry(None, &[0x02; 32]), Some(NO_ALLOWLIST));
+        assert_eq!(allowlist_entry(Some(" "), &[0x02; 32]), Some(NO_ALLOWLIST));
+    }
+}
@@ -0,0 +1,175 @@
+//! What the assertion guest proves, minus reading its input and committing its journal.
+
+use appattest_rs::assertion::{client_data_challenge, Assertion};
+use appattest_rs::error::AppAttestError;
+use p256::ecdsa::Signature;
+use risc0_zkvm::sha::{Digest, Digestible, Impl, Sha256};
+use risc0_zkvm::ReceiptClaim;
+
+use crate::{
+    allowlist_entry, AssertionInput, AssertionJournal, AttestationJournal, VerificationPolicy, VerifyError, POLICY,
+};
+
+/// Verifies the assertion in `input` and returns the journal the guest commits for it.
+///
+/// `allowlist` is the guest's `RP_ID_HASH_ALLOWLIST`, see [`allowlist_entry`], and with
+/// [`POLICY`] makes the [`VerificationPolicy`] whose hash the journal commits. The
+/// attestation journal is trusted as given: the guest checks it with `env::verify` before
+/// calling this. Its `cert_chain_image_id` is carried over, since the assertion journal
+/// commits the attestation journal only as a claim digest.
+pub fn run(input: AssertionInput, allowlist: Option<&str>) -> Result<AssertionJournal, VerifyError> {
+    let policy_hash = VerificationPolicy::from_allowlist(allowlist)?.hash();
+    let allowlist_entry = allowlist_entry(allowlist, &input.app_id_hash).ok_or(VerifyError::NotAllowed)?;
+    let input_digest = input.canonical_digest();
+
+    let attestation = AttestationJournal::decode(&input.attestation_journal).map_err(VerifyError::AttestationJournal)?;
+    if attestation.rp_id_hash != input.app_id_hash {
+        return Err(VerifyError::AppIdMismatch);
+    }
+    let attestation_image_id = Digest::from(input.attestation_image_id);
+    let attestation_claim_digest = ReceiptClaim::ok(attestation_image_id, input.attestation_journal).digest();
+
+    let assertion = Assertion::from_cbor(&input.assertion).map_err(VerifyError::Malformed)?;
+    let counter = assertion.counter().map_err(VerifyError::Assertion)?;
+
+    let [signature_r, signature_s, public_key_x, public_key_y] = assertion
+        .verify_raw(&input.client_data, &input.app_id_hash, &attestation.public_key, input.previous_counter)
+        .map_err(VerifyError::Assertion)?;
+
+    let field = |bytes: Vec<u8>| -> [u8; 32] { bytes.try_into().expect("32-byte field") };
+    let hash = |bytes: &[u8]| -> [u8; 32] { Impl::hash_bytes(bytes).as_bytes().try_into().unwrap() };
+    // Binds the proof to the request the client data answered, when it names one.
+    let challenge_hash = client_data_challenge(&input.client_data).map_or([0; 32], |c| hash(c.as_bytes()));
+
+    // (r, s) and (r, n - s) both verify. Commit only the low-S form, so the host cannot
+    // choose which of the two the journal carries.
+    let signature = Signature::from_scalars(field(signature_r), field(signature_s))
+        .map_err(|_| VerifyError::Assertion(AppAttestError::InvalidSignature))?;
+    let signature = signature.normalize_s().unwrap_or(signature);
+    let (signature_r, signature_s) = signature.split_bytes();
+
+    Ok(AssertionJournal {
+        policy: POLICY,
+        rp_id_hash: input.app_id_hash,
+        public_key_x: field(public_key_x),
+        public_key_y: field(public_key_y),
+        signature_r: signature_r.into(),
+        signature_s: signature_s.into(),
+        counter,
+        client_data_hash: hash(&input.client_data),
+        attestation_image_id: attestation_image_id.into(),
+        attestation_claim_digest: attestation_claim_digest.into(),
+        challenge_hash,
+        allowlist_entry,
+        session_nonce: input.session_nonce,
+        input_digest,
+        policy_hash: Some(policy_hash),
+        cert_chain_image_id: Some(attestation.cert_chain_image_id),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose, Engine};
+
+    use super::*;
+    use crate::NO_ALLOWLIST;
+
+    const ASSERTION: &str = include_str!("../../apps/fixtures/assertion.b64");
+    const APP_ID: &str = "LMRM26A744.xyz.elus.aegis.app-attester";
+    const PUBLIC_KEY: &str = "0485e322caa0f981bc18cd53574f1dc761c139d15030a36b1b249cc158c822c4506b1574b6b1c5b5bfaf8e2a1dfe3deaa87c8802d863c8aac23aa990560a8a56b4";
+
+    fn sha256(bytes: &[u8]) -> [u8; 32] {
+        Impl::hash_bytes(bytes).as_bytes().try_into().unwrap()
+    }
+
+    fn recorded_input() -> AssertionInput {
+        let attestation_journal = AttestationJournal {
+            policy: POLICY,
+            public_key: hex::decode(PUBLIC_KEY).unwrap().try_into().unwrap(),
+            key_id: [0; 32],
+            rp_id_hash: sha256(APP_ID.as_bytes()),
+            environment: AttestationJournal::DEVELOPMENT,
+            timestamp: 1_751_800_000,
+            time_signer: [0; 32],
+            allowlist_entry: NO_ALLOWLIST,
+            cert_chain_image_id: [3; 32],
+            cert_chain_claim_digest: [0; 32],
+            input_digest: [0; 32],
+            policy_hash: Some([0; 32]),
+        }
+        .encode();
+        AssertionInput {
+            assertion: general_purpose::STANDARD.decode(ASSERTION.trim()).unwrap(),
+            client_data: b"x = 15".to_vec(),
+            app_id_hash: sha256(APP_ID.as_bytes()),
+            attestation_journal,
+            attestation_image_id: [1; 8],
+            previous_counter: None,
+            session_nonce: [2; 32],
+        }
+    }
+
+    #[test]
+    fn recorded_assertion_verifies() {
+        let journal = run(recorded_input(), None).unwrap();
+        assert_eq!(hex::encode([[4].as_slice(), &journal.public_key_x, &journal.public_key_y].concat()), PUBLIC_KEY);
+        assert_eq!(journal.counter, 1);
+        assert_eq!(journal.client_data_hash, sha256(b"x = 15"));
+        assert_eq!(journal.allowlist_entry, NO_ALLOWLIST);
+        assert_eq!(journal.session_nonce, [2; 32]);
+        assert_eq!(journal.policy, POLICY);
+        assert_eq!(journal.input_digest, recorded_input().canonical_digest());
+        assert_eq!(journal.policy_hash, Some(VerificationPolicy::for_app_ids::<&str>(&[]).hash()));
+        assert_eq!(journal.cert_chain_image_id, Some([3; 32]));
+    }
+
+    #[test]
+    fn bad_signature_is_rejected() {
+        let mut input = recorded_input();
+        // A byte inside `r`: the signature starts at 13, past the CBOR header.
+        input.assertion[13 + 10] ^= 0x01;
+        assert_eq!(run(input, None), Err(VerifyError::Assertion(AppAttestError::InvalidSignature)));
+    }
+
+    #[test]
+    fn other_app_id_is_rejected() {
+        let input = AssertionInput {
+            app_id_hash: sha256(b"TEAMID.com.example.other"),
+            ..recorded_input()
+        };
+        assert_eq!(run(input, None), Err(VerifyError::AppIdMismatch));
+
+        let allowlist = hex::encode(sha256(b"TEAMID.com.example.other"));
+        assert_eq!(run(recorded_input(), Some(&allowlist)), Err(VerifyError::NotAllowed));
+    }
+
+    #[test]
+    fn commits_the_hash_of_its_allow_list() {
+        let other = "TEAMID.com.example.other";
+        let allowlist = format!("{},{}", hex::encode(sha256(other.as_bytes())), hex::encode(sha256(APP_ID.as_bytes())));
+        let journal = run(recorded_input(), Some(&allowlist)).unwrap();
+        assert_eq!(journal.allowlist_entry, 1);
+        let expected = VerificationPolicy::for_app_ids(&[other, APP_ID]);
+        assert_eq!(journal.policy_hash, Some(expected.hash()));
+        assert_ne!(journal.policy_hash, run(recorded_input(), None).unwrap().policy_hash);
+
+        assert_eq!(run(recorded_input(), Some("not hex")), Err(VerifyError::BadAllowlist));
+    }
+
+    #[test]
+    fn stale_counter_is_rejected() {
+        let input = AssertionInput {
+            previous_counter: Some(1),
+            ..recorded_input()
+        };
+        assert_eq!(run(input, None), Err(VerifyError::Assertion(AppAttestError::InvalidCounter)));
+    }
+
+    #[test]
+    fn malformed_cbor_is_rejected() {
+        let mut input = recorded_input();
+        input.assertion.truncate(20);
+        assert!(matches!(run(input, None), Err(VerifyError::Malformed(_))));
+    }
+}
@@ -0,0 +1,144 @@
+//! What the attestation guest proves, minus reading its input and committing its journal.
+
+use appattest_rs::attestation::{Attestation, Environment};
+use appattest_rs::error::AppAttestError;
+use risc0_zkvm::sha::{Digest, Digestible, Impl, Sha256};
+use risc0_zkvm::ReceiptClaim;
+
+use crate::{
+    allowlist_entry, AttestationInput, AttestationJournal, CertChainJournal, VerificationPolicy, VerifyError, POLICY,
+};
+
+/// Verifies the attestation in `input` against its certificate chain journal and returns the
+/// journal the guest commits for it.
+///
+/// `allowlist` is the guest's `RP_ID_HASH_ALLOWLIST`, see [`allowlist_entry`], and with
+/// [`POLICY`] makes the [`VerificationPolicy`] whose hash the journal commits. The chain
+/// journal is trusted as given: the guest checks it with `env::verify` before calling this.
+pub fn run(input: AttestationInput, allowlist: Option<&str>) -> Result<AttestationJournal, VerifyError> {
+    let policy_hash = VerificationPolicy::from_allowlist(allowlist)?.hash();
+    let allowlist_entry = allowlist_entry(allowlist, &input.app_id_hash).ok_or(VerifyError::NotAllowed)?;
+
+    let chain = CertChainJournal::decode(&input.cert_chain_journal).map_err(VerifyError::CertChainJournal)?;
+    let input_digest = input.canonical_digest_over(&chain.input_digest);
+    let cert_chain_image_id = Digest::from(input.cert_chain_image_id);
+    let cert_chain_claim_digest = ReceiptClaim::ok(cert_chain_image_id, input.cert_chain_journal).digest();
+
+    let attestation = Attestation::from_cbor(&input.attestation).map_err(VerifyError::Malformed)?;
+    // The chain guest checked the nonce over its authenticator data; this must be the same.
+    if Impl::hash_bytes(attestation.auth_data()).as_bytes() != chain.auth_data_hash {
+        return Err(VerifyError::AuthDataMismatch);
+    }
+    let verified = attestation
+        .verify_auth_data(&input.app_id_hash, &input.key_id, &chain.public_key)
+        .map_err(VerifyError::Attestation)?;
+
+    Ok(AttestationJournal {
+        policy: POLICY,
+        public_key: chain.public_key,
+        key_id: input
+            .key_id
+            .try_into()
+            .map_err(|_| VerifyError::Attestation(AppAttestError::InvalidCredentialID))?,
+        rp_id_hash: verified.rp_id_hash,
+        environment: match verified.environment {
+            Environment::Production => AttestationJournal::PRODUCTION,
+            Environment::Development => AttestationJournal::DEVELOPMENT,
+        },
+        timestamp: chain.timestamp,
+        time_signer: chain.time_signer,
+        allowlist_entry,
+        cert_chain_image_id: cert_chain_image_id.into(),
+        cert_chain_claim_digest: cert_chain_claim_digest.into(),
+        input_digest,
+        policy_hash: Some(policy_hash),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose, Engine};
+
+    use super::*;
+    use crate::{cert_chain, CertChainInput};
+
+    const ATTESTATION: &str = include_str!("../../apps/fixtures/attestation.b64");
+    const CHALLENGE: &str = "ExYiYJ4jA3rGL9oQN8ihDP4seccnxwHSEU28u8aCQaU=";
+    const KEY_ID: &str = "HD8zVqwgesSBwPaYj5icRg1GI/b6SOoa4TqieTEBA0g=";
+    const APP_ID: &str = "LMRM26A744.xyz.elus.aegis.app-attester";
+    const RECORDED_AT: i64 = 1_751_800_000;
+    const CERT_CHAIN_IMAGE_ID: [u32; 8] = [3; 8];
+
+    fn decode(b64: &str) -> Vec<u8> {
+        general_purpose::STANDARD.decode(b64.trim()).unwrap()
+    }
+
+    /// The recorded attestation, with the journal its certificate chain guest commits.
+    fn recorded_input() -> AttestationInput {
+        let chain = cert_chain::run(CertChainInput {
+            attestation: decode(ATTESTATION),
+            challenge: decode(CHALLENGE),
+            timestamp: RECORDED_AT,
+            time_token: None,
+        })
+        .unwrap();
+        AttestationInput {
+            attestation: decode(ATTESTATION),
+            app_id_hash: Impl::hash_bytes(APP_ID.as_bytes()).as_bytes().try_into().unwrap(),
+            key_id: decode(KEY_ID),
+            cert_chain_journal: chain.encode(),
+            cert_chain_image_id: CERT_CHAIN_IMAGE_ID,
+        }
+    }
+
+    #[test]
+    fn recorded_attestation_verifies() {
+        let input = recorded_input();
+        let claim = ReceiptClaim::ok(Digest::from(CERT_CHAIN_IMAGE_ID), input.cert_chain_journal.clone()).digest();
+        let input_digest = input.canonical_digest().unwrap();
+        let journal = run(input, None).unwrap();
+        assert_eq!(journal.input_digest, input_digest);
+        assert_eq!(journal.public_key[..4], [0x04, 0x85, 0xe3, 0x22]);
+        assert_eq!(journal.environment, AttestationJournal::DEVELOPMENT);
+        assert_eq!(journal.timestamp, RECORDED_AT);
+        assert_eq!(journal.time_signer, [0; 32]);
+        assert_eq!(journal.policy, POLICY);
+        assert_eq!(journal.policy_hash, Some(VerificationPolicy::for_app_ids::<&str>(&[]).hash()));
+        assert_eq!(Digest::from(journal.cert_chain_image_id), Digest::from(CERT_CHAIN_IMAGE_ID));
+        assert_eq!(Digest::from(journal.cert_chain_claim_digest), claim);
+    }
+
+    #[test]
+    fn other_app_id_is_rejected() {
+        let input = AttestationInput {
+            app_id_hash: Impl::hash_bytes(b"TEAMID.com.example.other").as_bytes().try_into().unwrap(),
+            ..recorded_input()
+        };
+        assert_eq!(run(input, None), Err(VerifyError::Attestation(AppAttestError::InvalidAppID)));
+    }
+
+    #[test]
+    fn chain_of_another_object_is_rejected() {
+        let mut chain = CertChainJournal::decode(&recorded_input().cert_chain_journal).unwrap();
+        chain.auth_data_hash[0] ^= 0x01;
+        let input = AttestationInput {
+            cert_chain_journal: chain.encode(),
+            ..recorded_input()
+        };
+        assert_eq!(run(input, None), Err(VerifyError::AuthDataMismatch));
+    }
+
+    #[test]
+    fn key_id_must_hash_the_chain_key() {
+        let mut input = recorded_input();
+        input.key_id[0] ^= 0x01;
+        assert_eq!(run(input, None), Err(VerifyError::Attestation(AppAttestError::InvalidPublicKey)));
+    }
+
+    #[test]
+    fn undecodable_chain_journal_is_rejected() {
+        let mut input = recorded_input();
+        input.cert_chain_journal.truncate(10);
+        assert!(matches!(run(input, None), Err(VerifyError::CertChainJournal(_))));
+    }
+}
@@ -0,0 +1,127 @@
+//! What the certificate chain guest proves, minus reading its input and committing its journal.
+
+use appattest_rs::attestation::Attestation;
+use appattest_rs::error::AppAttestError;
+use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+use risc0_zkvm::sha::{Impl, Sha256};
+
+use crate::{CertChainInput, CertChainJournal, TimeToken, VerifyError, POLICY};
+
+/// Checks the certificate chain and nonce of the attestation in `input` and returns the
+/// journal the guest commits for it.
+///
+/// Nothing here depends on the app ID or key ID, which the attestation guest checks, so
+/// the receipt can be proven once per key and reused.
+pub fn run(input: CertChainInput) -> Result<CertChainJournal, VerifyError> {
+    // The one timestamp the chain is checked at and the journal commits; the host cannot
+    // have the guest check at one time and report another.
+    let timestamp = input.timestamp;
+    let input_digest = input.canonical_digest();
+    let time_signer = match &input.time_token {
+        Some(token) => verify_time_token(token, timestamp)?,
+        None => [0; 32],
+    };
+
+    let chain = Attestation::from_cbor(&input.attestation)
+        .map_err(VerifyError::Malformed)?
+        .verify_chain(&input.challenge, timestamp)
+        .map_err(VerifyError::Attestation)?;
+
+    Ok(CertChainJournal {
+        policy: POLICY,
+        public_key: chain
+            .public_key
+            .try_into()
+            .map_err(|_| VerifyError::Attestation(AppAttestError::InvalidPublicKey))?,
+        leaf_fingerprint: chain.leaf_fingerprint,
+        auth_data_hash: chain.auth_data_hash,
+        timestamp,
+        time_signer,
+        input_digest,
+    })
+}
+
+/// Checks the time source's signature over `timestamp` and returns `SHA256(signer)`.
+fn verify_time_token(token: &TimeToken, timestamp: i64) -> Result<[u8; 32], VerifyError> {
+    let key = VerifyingKey::from_sec1_bytes(&token.signer)
+        .map_err(|e| VerifyError::TimeToken(format!("invalid signer: {e:?}")))?;
+    let signature = Signature::from_der(&token.signature)
+        .map_err(|e| VerifyError::TimeToken(format!("invalid signature: {e:?}")))?;
+    key.verify(&timestamp.to_le_bytes(), &signature)
+        .map_err(|e| VerifyError::TimeToken(format!("{e:?}")))?;
+    Ok(Impl::hash_bytes(&token.signer).as_bytes().try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose, Engine};
+    use p256::ecdsa::{signature::Signer, SigningKey};
+
+    use super::*;
+
+    const ATTESTATION: &str = include_str!("../../apps/fixtures/attestation.b64");
+    const CHALLENGE: &str = "ExYiYJ4jA3rGL9oQN8ihDP4seccnxwHSEU28u8aCQaU=";
+    const RECORDED_AT: i64 = 1_751_800_000;
+
+    fn recorded_input() -> CertChainInput {
+        let decode = |b64: &str| general_purpose::STANDARD.decode(b64.trim()).unwrap();
+        CertChainInput {
+            attestation: decode(ATTESTATION),
+            challenge: decode(CHALLENGE),
+            timestamp: RECORDED_AT,
+            time_token: None,
+        }
+    }
+
+    fn time_token(timestamp: i64) -> TimeToken {
+        let key = SigningKey::from_slice(&[7; 32]).unwrap();
+        let signature: Signature = key.sign(&timestamp.to_le_bytes());
+        TimeToken {
+            signer: key.verifying_key().to_encoded_point(false).as_bytes().to_vec(),
+            signature: signature.to_der().as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn recorded_chain_verifies() {
+        let input = recorded_input();
+        let auth_data = Attestation::from_cbor(&input.attestation).unwrap().auth_data().to_vec();
+        let journal = run(input).unwrap();
+        assert_eq!(journal.public_key[..4], [0x04, 0x85, 0xe3, 0x22]);
+        assert_eq!(journal.auth_data_hash, Impl::hash_bytes(&auth_data).as_bytes());
+        assert_ne!(journal.leaf_fingerprint, [0; 32]);
+        assert_eq!(journal.timestamp, RECORDED_AT);
+        assert_eq!(journal.time_signer, [0; 32]);
+        assert_eq!(journal.policy, POLICY);
+        assert_eq!(journal.input_digest, recorded_input().canonical_digest());
+    }
+
+    #[test]
+    fn wrong_challenge_is_rejected() {
+        let mut input = recorded_input();
+        input.challenge[0] ^= 0x01;
+        assert_eq!(run(input), Err(VerifyError::Attestation(AppAttestError::InvalidNonce)));
+    }
+
+    #[test]
+    fn time_token_must_sign_the_checked_timestamp() {
+        let input = CertChainInput {
+            time_token: Some(time_token(RECORDED_AT)),
+            ..recorded_input()
+        };
+        assert_ne!(run(input).unwrap().time_signer, [0; 32]);
+
+        let input = CertChainInput {
+            time_token: Some(time_token(RECORDED_AT - 1)),
+            ..recorded_input()
+        };
+        assert!(matches!(run(input), Err(VerifyError::TimeToken(_))));
+    }
+
+    #[test]
+    fn malformed_cbor_is_rejected() {
+        let mut input = recorded_input();
+        input.attestation.truncate(64);
+        assert!(matches!(run(input), Err(VerifyError::Malformed(_))));
+    }
+}
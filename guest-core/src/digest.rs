@@ -0,0 +1,219 @@
+//! Canonical digests of the guests' inputs: one way to name "what was verified" that the
+//! host, the guests and anything keyed by an input (receipt caches, replay caches, audit
+//! logs, relayed bundles) agree on.
+//!
+//! A digest is `SHA256` of the input's encoding, which is a sequence of fields, each its
+//! length as 4 little-endian bytes followed by its bytes. The first field is a
+//! domain-separation tag naming the input and the encoding's version, so digests of
+//! different inputs or versions never collide; changing a field list means a new tag.
+//! Integers are encoded as their little-endian bytes, image IDs as their eight words.
+//!
+//! | tag                              | fields after the tag                                                                                                                                                           |
+//! |----------------------------------|--------------------------------------------------------------------------------------------------------------------------------------------------------------------------------|
+//! | `mobiscale/cert-chain-input/v1`  | `attestation`, `challenge`, `timestamp` (8), time token present (1), its `signer`, its `signature`                                                                             |
+//! | `mobiscale/attestation-input/v1` | the certificate chain input's digest (32), `attestation`, `app_id_hash` (32), `key_id`                                                                                         |
+//! | `mobiscale/assertion-input/v2`   | `assertion`, `client_data`, `app_id_hash` (32), `attestation_image_id` (32), `attestation_journal`, previous counter present (1), `previous_counter` (4), `session_nonce` (32) |
+//!
+//! An absent time token encodes as a `0` presence byte and two empty fields, and an absent
+//! previous counter as a `0` presence byte and an empty field; `v1` of the assertion input
+//! had no presence byte, as its previous counter could not be absent. The attestation guest
+//! does not see the challenge or the time token, so its digest covers them through the
+//! certificate chain input's digest, which it reads from the chain journal.
+
+use risc0_zkvm::sha::{Impl, Sha256};
+
+use crate::{AssertionInput, AttestationInput, CertChainInput, CertChainJournal, JournalError};
+
+/// Domain-separation tag of [`CertChainInput::canonical_digest`].
+pub const CERT_CHAIN_INPUT_TAG: &str = "mobiscale/cert-chain-input/v1";
+/// Domain-separation tag of [`AttestationInput::canonical_digest`].
+pub const ATTESTATION_INPUT_TAG: &str = "mobiscale/attestation-input/v1";
+/// Domain-separation tag of [`AssertionInput::canonical_digest`].
+pub const ASSERTION_INPUT_TAG: &str = "mobiscale/assertion-input/v2";
+
+/// The encoding of one input, field by field.
+struct Encoding(Vec<u8>);
+
+impl Encoding {
+    fn new(tag: &str) -> Self {
+        let mut encoding = Encoding(Vec::new());
+        encoding.field(tag.as_bytes());
+        encoding
+    }
+
+    fn field(&mut self, bytes: &[u8]) -> &mut Self {
+        let len = u32::try_from(bytes.len()).expect("inputs are smaller than 4 GiB");
+        self.0.extend_from_slice(&len.to_le_bytes());
+        self.0.extend_from_slice(bytes);
+        self
+    }
+
+    fn image_id(&mut self, image_id: &[u32; 8]) -> &mut Self {
+        let bytes: Vec<u8> = image_id.iter().flat_map(|word| word.to_le_bytes()).collect();
+        self.field(&bytes)
+    }
+
+    fn digest(&self) -> [u8; 32] {
+        Impl::hash_bytes(&self.0).as_bytes().try_into().unwrap()
+    }
+}
+
+impl CertChainInput {
+    /// The input's digest under [`CERT_CHAIN_INPUT_TAG`], as the certificate chain guest
+    /// commits it.
+    pub fn canonical_digest(&self) -> [u8; 32] {
+        let (signer, signature) = match &self.time_token {
+            Some(token) => (token.signer.as_slice(), token.signature.as_slice()),
+            None => (&[][..], &[][..]),
+        };
+        Encoding::new(CERT_CHAIN_INPUT_TAG)
+            .field(&self.attestation)
+            .field(&self.challenge)
+            .field(&self.timestamp.to_le_bytes())
+            .field(&[self.time_token.is_some() as u8])
+            .field(signer)
+            .field(signature)
+            .digest()
+    }
+}
+
+impl AttestationInput {
+    /// The input's digest under [`ATTESTATION_INPUT_TAG`], as the attestation guest commits
+    /// it. Fails if the certificate chain journal does not decode.
+    pub fn canonical_digest(&self) -> Result<[u8; 32], JournalError> {
+        let chain = CertChainJournal::decode(&self.cert_chain_journal)?;
+        Ok(self.canonical_digest_over(&chain.input_digest))
+    }
+
+    /// The digest given the certificate chain input's, for a host that has not proven the
+    /// chain yet. The certificate chain journal itself is not covered.
+    pub fn canonical_digest_over(&self, cert_chain_input_digest: &[u8; 32]) -> [u8; 32] {
+        Encoding::new(ATTESTATION_INPUT_TAG)
+            .field(cert_chain_input_digest)
+            .field(&self.attestation)
+            .field(&self.app_id_hash)
+            .field(&self.key_id)
+            .digest()
+    }
+}
+
+impl AssertionInput {
+    /// The input's digest under [`ASSERTION_INPUT_TAG`], as the assertion guest commits it.
+    pub fn canonical_digest(&self) -> [u8; 32] {
+        let previous_counter = self.previous_counter.map(u32::to_le_bytes);
+        Encoding::new(ASSERTION_INPUT_TAG)
+            .field(&self.assertion)
+            .field(&self.client_data)
+            .field(&self.app_id_hash)
+            .image_id(&self.attestation_image_id)
+            .field(&self.attestation_journal)
+            .field(&[self.previous_counter.is_some() as u8])
+            .field(previous_counter.as_ref().map_or(&[][..], |bytes| bytes.as_slice()))
+            .field(&self.session_nonce)
+            .digest()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{TimeToken, POLICY};
+
+    fn cert_chain_input() -> CertChainInput {
+        CertChainInput { attestation: vec![1, 2, 3], challenge: vec![4; 32], timestamp: 1_751_800_000, time_token: None }
+    }
+
+    fn assertion_input() -> AssertionInput {
+        AssertionInput {
+            assertion: vec![1, 2, 3],
+            client_data: b"x = 15".to_vec(),
+            app_id_hash: [2; 32],
+            attestation_journal: vec![3; 10],
+            attestation_image_id: [4; 8],
+            previous_counter: Some(5),
+            session_nonce: [6; 32],
+        }
+    }
+
+    #[test]
+    fn encoding_is_length_prefixed() {
+        let mut encoding = Encoding::new("t");
+        encoding.field(b"ab").field(b"").image_id(&[1; 8]);
+        let mut expected = vec![1, 0, 0, 0, b't', 2, 0, 0, 0, b'a', b'b', 0, 0, 0, 0, 32, 0, 0, 0];
+        expected.extend([1, 0, 0, 0].repeat(8));
+        assert_eq!(encoding.0, expected);
+    }
+
+    #[test]
+    fn digests_are_pinned() {
+        // Changing these changes every committed digest; use a new tag instead.
+        assert_eq!(
+            hex::encode(cert_chain_input().canonical_digest()),
+            "32fd365faf19ac0cffc3ef9c1e3247faf27cefeab33fdddffb9d778a499200cc"
+        );
+        assert_eq!(
+            hex::encode(assertion_input().canonical_digest()),
+            "40a6e4ce8af008fa662223cb5f830f11d1cb2d11b725c9339a21a05d7ac6f7ff"
+        );
+    }
+
+    #[test]
+    fn every_field_counts() {
+        let base = assertion_input().canonical_digest();
+        let changed = [
+            AssertionInput { assertion: vec![1, 2], ..assertion_input() },
+            AssertionInput { client_data: b"x = 16".to_vec(), ..assertion_input() },
+            AssertionInput { app_id_hash: [0; 32], ..assertion_input() },
+            AssertionInput { attestation_image_id: [0; 8], ..assertion_input() },
+            AssertionInput { attestation_journal: vec![3; 11], ..assertion_input() },
+            AssertionInput { previous_counter: Some(6), ..assertion_input() },
+            AssertionInput { previous_counter: None, ..assertion_input() },
+            AssertionInput { session_nonce: [0; 32], ..assertion_input() },
+        ];
+        for input in changed {
+            assert_ne!(input.canonical_digest(), base, "{input:?}");
+        }
+
+        // Moving a byte from one field to the next is a different input.
+        let shifted = AssertionInput { assertion: vec![1, 2], client_data: b"\x03x = 15".to_vec(), ..assertion_input() };
+        assert_ne!(shifted.canonical_digest(), base);
+
+        // No previous assertion is not a previous counter of 0.
+        assert_ne!(
+            AssertionInput { previous_counter: None, ..assertion_input() }.canonical_digest(),
+            AssertionInput { previous_counter: Some(0), ..assertion_input() }.canonical_digest()
+        );
+
+        // An empty time token is not the absent one.
+        let empty_token = CertChainInput {
+            time_token: Some(TimeToken { signer: Vec::new(), signature: Vec::new() }),
+            ..cert_chain_input()
+        };
+        assert_ne!(empty_token.canonical_digest(), cert_chain_input().canonical_digest());
+    }
+
+    #[test]
+    fn attestation_digest_covers_the_chain_input() {
+        let chain = |input_digest| CertChainJournal {
+            policy: POLICY,
+            public_key: [4; 65],
+            leaf_fingerprint: [5; 32],
+            auth_data_hash: [6; 32],
+            timestamp: 1_751_800_000,
+            time_signer: [0; 32],
+            input_digest,
+        };
+        let input = |input_digest| AttestationInput {
+            attestation: vec![1, 2, 3],
+            app_id_hash: [2; 32],
+            key_id: vec![3; 32],
+            cert_chain_journal: chain(input_digest).encode(),
+            cert_chain_image_id: [4; 8],
+        };
+        assert_eq!(input([7; 32]).canonical_digest(), Ok(input([8; 32]).canonical_digest_over(&[7; 32])));
+        assert_ne!(input([7; 32]).canonical_digest(), input([8; 32]).canonical_digest());
+
+        let undecodable = AttestationInput { cert_chain_journal: vec![1], ..input([7; 32]) };
+        assert!(undecodable.canonical_digest().is_err());
+    }
+}
@@ -0,0 +1,149 @@
+use core::fmt;
+
+use appattest_rs::error::AppAttestError;
+
+use crate::JournalError;
+
+/// Abort codes a guest reports when it refuses its input; see [`VerifyError::abort_message`].
+pub mod abort {
+    /// An input field or object does not decode.
+    pub const MALFORMED_INPUT: u8 = 10;
+    /// The assertion signature does not verify for the attested key.
+    pub const SIGNATURE_INVALID: u8 = 11;
+    /// The objects are bound to another app ID than the input names.
+    pub const APP_ID_MISMATCH: u8 = 12;
+    /// The assertion counter is not above the previous one.
+    pub const COUNTER_REPLAY: u8 = 13;
+    /// The app ID is not in the guest's built-in allow-list.
+    pub const APP_ID_NOT_ALLOWED: u8 = 14;
+    /// Apple's certificate chain, nonce, key ID or environment check failed.
+    pub const ATTESTATION_INVALID: u8 = 15;
+    /// The time token does not sign the checked timestamp.
+    pub const TIME_TOKEN_INVALID: u8 = 16;
+    /// The attestation receipt the assertion relies on cannot be verified.
+    pub const ATTESTATION_RECEIPT_INVALID: u8 = 17;
+    /// The certificate chain receipt the attestation relies on cannot be verified.
+    pub const CERT_CHAIN_RECEIPT_INVALID: u8 = 18;
+    /// The guest's built-in allow-list has an entry that is not a SHA-256 in hex.
+    pub const ALLOWLIST_INVALID: u8 = 19;
+}
+
+/// Start of every guest abort message: `guest abort <code>: <reason>`.
+const ABORT_PREFIX: &str = "guest abort ";
+
+/// Formats the panic message of a guest aborting with `code`.
+pub fn abort_message(code: u8, reason: impl fmt::Display) -> String {
+    format!("{ABORT_PREFIX}{code}: {reason}")
+}
+
+/// Finds a guest abort in the text of a host-side execution or proving error and returns
+/// its code and reason.
+pub fn parse_abort(error: &str) -> Option<(u8, String)> {
+    let rest = &error[error.find(ABORT_PREFIX)? + ABORT_PREFIX.len()..];
+    let (code, reason) = rest.split_once(": ")?;
+    Some((code.parse().ok()?, reason.lines().next().unwrap_or_default().to_string()))
+}
+
+/// Why a guest refused its input. The guests panic with its [`abort_message`](Self::abort_message).
+#[derive(Debug, PartialEq)]
+pub enum VerifyError {
+    /// The app ID is not in the guest's built-in allow-list.
+    NotAllowed,
+    /// The attestation journal handed to the assertion guest does not decode.
+    AttestationJournal(JournalError),
+    /// The certificate chain journal handed to the attestation guest does not decode.
+    CertChainJournal(JournalError),
+    /// The authenticator data is not the one the certificate chain guest checked the nonce over.
+    AuthDataMismatch,
+    /// The attestation vouches for a key of another app ID than the assertion names.
+    AppIdMismatch,
+    /// The attestation or assertion object does not decode.
+    Malformed(AppAttestError),
+    /// appattest-rs rejected the attestation object.
+    Attestation(AppAttestError),
+    /// appattest-rs rejected the assertion object.
+    Assertion(AppAttestError),
+    /// The time token does not sign the timestamp the chain is checked at.
+    TimeToken(String),
+    /// The built-in allow-list has an entry that is not 64 hex digits, so the guest's
+    /// [`crate::VerificationPolicy`] cannot be stated.
+    BadAllowlist,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::NotAllowed => write!(f, "App ID is not in this build's allow-list"),
+            VerifyError::AttestationJournal(e) => write!(f, "Failed to decode the attestation journal: {e}"),
+            VerifyError::CertChainJournal(e) => write!(f, "Failed to decode the certificate chain journal: {e}"),
+            VerifyError::AuthDataMismatch => write!(f, "Authenticator data differs from the checked chain's"),
+            VerifyError::AppIdMismatch => write!(f, "Attestation is for another app ID"),
+            VerifyError::Malformed(e) => write!(f, "Failed to decode the input: {e}"),
+            // Debug, so the variant names tests and logs match on stay visible.
+            VerifyError::Attestation(e) => write!(f, "Attestation verification failed: {e:?}"),
+            VerifyError::Assertion(e) => write!(f, "Assertion verification failed: {e:?}"),
+            VerifyError::TimeToken(e) => write!(f, "Time token verification failed: {e}"),
+            VerifyError::BadAllowlist => write!(f, "The built-in allow-list has an entry that is not a hex SHA-256"),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+impl VerifyError {
+    /// The [`abort`] code the guest exits with.
+    pub fn code(&self) -> u8 {
+        let app_attest = |e: &AppAttestError, otherwise| match e {
+            AppAttestError::InvalidSignature => abort::SIGNATURE_INVALID,
+            AppAttestError::InvalidCounter => abort::COUNTER_REPLAY,
+            AppAttestError::InvalidAppID | AppAttestError::InvalidAppIDHash => abort::APP_ID_MISMATCH,
+            _ => otherwise,
+        };
+        match self {
+            VerifyError::NotAllowed => abort::APP_ID_NOT_ALLOWED,
+            VerifyError::AttestationJournal(_) | VerifyError::CertChainJournal(_) | VerifyError::Malformed(_) => {
+                abort::MALFORMED_INPUT
+            }
+            VerifyError::AuthDataMismatch => abort::ATTESTATION_INVALID,
+            VerifyError::AppIdMismatch => abort::APP_ID_MISMATCH,
+            VerifyError::Attestation(e) => app_attest(e, abort::ATTESTATION_INVALID),
+            VerifyError::Assertion(e) => app_attest(e, abort::MALFORMED_INPUT),
+            VerifyError::TimeToken(_) => abort::TIME_TOKEN_INVALID,
+            VerifyError::BadAllowlist => abort::ALLOWLIST_INVALID,
+        }
+    }
+
+    /// The message the guest panics with, which the host parses back with [`parse_abort`].
+    pub fn abort_message(&self) -> String {
+        abort_message(self.code(), self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn abort_messages_round_trip_through_host_errors() {
+        let err = VerifyError::Assertion(AppAttestError::InvalidCounter);
+        let host = format!("Guest panicked: {}\nstack backtrace: ...", err.abort_message());
+        assert_eq!(
+            parse_abort(&host),
+            Some((abort::COUNTER_REPLAY, "Assertion verification failed: InvalidCounter".to_string()))
+        );
+        assert_eq!(parse_abort("Guest panicked: something else"), None);
+    }
+
+    #[test]
+    fn codes_follow_the_failed_check() {
+        let code = |e: VerifyError| e.code();
+        assert_eq!(code(VerifyError::Assertion(AppAttestError::InvalidSignature)), abort::SIGNATURE_INVALID);
+        assert_eq!(code(VerifyError::Assertion(AppAttestError::Message("cbor".into()))), abort::MALFORMED_INPUT);
+        assert_eq!(code(VerifyError::Attestation(AppAttestError::InvalidNonce)), abort::ATTESTATION_INVALID);
+        assert_eq!(code(VerifyError::Attestation(AppAttestError::InvalidAppID)), abort::APP_ID_MISMATCH);
+        assert_eq!(code(VerifyError::AppIdMismatch), abort::APP_ID_MISMATCH);
+        assert_eq!(code(VerifyError::AuthDataMismatch), abort::ATTESTATION_INVALID);
+        assert_eq!(code(VerifyError::CertChainJournal(JournalError::Empty)), abort::MALFORMED_INPUT);
+        assert_eq!(code(VerifyError::BadAllowlist), abort::ALLOWLIST_INVALID);
+    }
+}
@@ -0,0 +1,588 @@
+//! The journals the guests commit.
+//!
+//! Each layout starts with a version byte and has fixed offsets after it, so that a
+//! Solidity decoder can read fields by slicing. The guests commit the bytes with
+//! `env::commit_slice`, so a journal is exactly `LEN` bytes with no serde framing. Every
+//! layout puts the [`Policy`] descriptor right after the version byte; the attestation
+//! and assertion journals commit the hash of the whole
+//! [`VerificationPolicy`](crate::VerificationPolicy) after their other fields.
+//!
+//! `decode` reads the layout the guests commit now and nothing else, which is what a guest
+//! checking another guest's journal needs. Hosts read the previous layouts as well, through
+//! the verifier crate's `JournalCodec`, into the same structs.
+
+use core::fmt;
+
+use crate::Policy;
+
+/// Assertion journal version written by the current guest. Version 1 took the public key
+/// from the host instead of an attestation receipt, version 2 had no `challenge_hash`,
+/// version 3 no `allowlist_entry`, version 4 no `session_nonce`, version 5 no `policy`,
+/// version 6 no `input_digest`, version 7 no `policy_hash` and version 8 no
+/// `cert_chain_image_id`; none of them is produced any more.
+pub const ASSERTION_JOURNAL_V9: u8 = 9;
+
+/// The previous assertion journal version: version 9 up to `cert_chain_image_id`, which it
+/// lacks. No guest commits it any more, but hosts still read it.
+pub const ASSERTION_JOURNAL_V8: u8 = 8;
+
+/// The assertion journal version before [`ASSERTION_JOURNAL_V8`], which also lacks
+/// `policy_hash`. Hosts still read it.
+pub const ASSERTION_JOURNAL_V7: u8 = 7;
+
+/// Attestation journal version written by the current guest. Version 1 had no
+/// `time_signer`, version 2 no `allowlist_entry`, version 3 no `policy`, version 4 was
+/// committed by a single guest that also checked the certificate chain, version 5 had no
+/// `input_digest` and version 6 no `policy_hash`; none of them is produced any more.
+pub const ATTESTATION_JOURNAL_V7: u8 = 7;
+
+/// The previous attestation journal version: version 7 up to `policy_hash`, which it lacks.
+/// No guest commits it any more, but hosts still read it.
+pub const ATTESTATION_JOURNAL_V6: u8 = 6;
+
+/// Certificate chain journal version written by the current guest. Version 1 had no
+/// `input_digest` and is not produced any more.
+pub const CERT_CHAIN_JOURNAL_V2: u8 = 2;
+
+/// Decoded assertion journal.
+///
+/// | offset | length | field                      |
+/// |-------:|-------:|----------------------------|
+/// |      0 |      1 | version (`9`)              |
+/// |      1 |      6 | `policy`                   |
+/// |      7 |     32 | `rp_id_hash`               |
+/// |     39 |     32 | `public_key_x`             |
+/// |     71 |     32 | `public_key_y`             |
+/// |    103 |     32 | `signature_r`              |
+/// |    135 |     32 | `signature_s`              |
+/// |    167 |      4 | `counter`, LE              |
+/// |    171 |     32 | `client_data_hash`         |
+/// |    203 |     32 | `attestation_image_id`     |
+/// |    235 |     32 | `attestation_claim_digest` |
+/// |    267 |     32 | `challenge_hash`           |
+/// |    299 |      4 | `allowlist_entry`, LE      |
+/// |    303 |     32 | `session_nonce`            |
+/// |    335 |     32 | `input_digest`             |
+/// |    367 |     32 | `policy_hash`              |
+/// |    399 |     32 | `cert_chain_image_id`      |
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AssertionJournal {
+    /// What the guest that produced the journal checks.
+    pub policy: Policy,
+    /// `SHA256` of the app ID the assertion was checked against.
+    pub rp_id_hash: [u8; 32],
+    pub public_key_x: [u8; 32],
+    pub public_key_y: [u8; 32],
+    pub signature_r: [u8; 32],
+    /// Always the low-S form, `s <= n / 2`: a device signature with a high `s` is committed
+    /// as `(r, n - s)`, which verifies over the same message.
+    pub signature_s: [u8; 32],
+    /// Counter from the authenticator data.
+    pub counter: u32,
+    /// `SHA256` of the client data the assertion signs.
+    pub client_data_hash: [u8; 32],
+    /// Image ID of the attestation guest whose receipt vouched for the public key.
+    pub attestation_image_id: [u8; 32],
+    /// Digest of that receipt's claim. A verifier holding the attestation journal checks
+    /// it against `ReceiptClaim::ok(attestation_image_id, journal).digest()`.
+    pub attestation_claim_digest: [u8; 32],
+    /// `SHA256` of the `challenge` string when the client data is a JSON object carrying
+    /// one, all zeros otherwise. Lets a verifier check which request the assertion answered.
+    pub challenge_hash: [u8; 32],
+    /// Index of `rp_id_hash` in the guest's built-in allow-list, or
+    /// [`NO_ALLOWLIST`](crate::NO_ALLOWLIST) for a build without one.
+    pub allowlist_entry: u32,
+    /// Nonce the host passed in, committed verbatim. A verifier that issued one nonce per
+    /// session rejects a journal carrying any other, so an old proof cannot be replayed.
+    pub session_nonce: [u8; 32],
+    /// [`AssertionInput::canonical_digest`](crate::AssertionInput::canonical_digest) of the
+    /// input the guest verified.
+    pub input_digest: [u8; 32],
+    /// [`VerificationPolicy::hash`](crate::VerificationPolicy::hash) of the guest's policy.
+    /// The guests always commit one; `None` for a version 7 journal read by a host.
+    pub policy_hash: Option<[u8; 32]>,
+    /// The attestation journal's `cert_chain_image_id`: the certificate chain guest that
+    /// checked Apple's chain for the key. The guest always commits one; `None` for a version
+    /// 7 or 8 journal read by a host, which cannot show what checked the chain.
+    pub cert_chain_image_id: Option<[u8; 32]>,
+}
+
+/// Why a journal could not be decoded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum JournalError {
+    /// The journal is empty, so there is no version byte.
+    Empty,
+    /// The version byte names a layout this build does not know.
+    UnsupportedVersion(u8),
+    /// The journal is not the length its version requires.
+    Length { expected: usize, actual: usize },
+}
+
+impl fmt::Display for JournalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JournalError::Empty => write!(f, "journal is empty"),
+            JournalError::UnsupportedVersion(version) => write!(f, "unsupported journal version {version}"),
+            JournalError::Length { expected, actual } => {
+                write!(f, "journal is {actual} bytes, expected {expected}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for JournalError {}
+
+impl AssertionJournal {
+    /// Encoded length of a version 9 journal.
+    pub const LEN: usize = 1 + Policy::LEN + 32 * 5 + 4 + 32 * 4 + 4 + 32 + 32 + 32 + 32;
+
+    /// Encoded length of a version 8 journal.
+    pub const LEN_V8: usize = Self::LEN - 32;
+
+    /// Encoded length of a version 7 journal.
+    pub const LEN_V7: usize = Self::LEN_V8 - 32;
+
+    /// The version [`AssertionJournal::encode`] writes: 9, or the previous one whose fields
+    /// the journal ends with, 8 without a `cert_chain_image_id` and 7 without either.
+    pub fn version(&self) -> u8 {
+        match (self.policy_hash, self.cert_chain_image_id) {
+            (_, Some(_)) => ASSERTION_JOURNAL_V9,
+            (Some(_), None) => ASSERTION_JOURNAL_V8,
+            (None, None) => ASSERTION_JOURNAL_V7,
+        }
+    }
+
+    /// Encodes the journal as [`AssertionJournal::version`]. A version 9 journal without a
+    /// `policy_hash` commits zeros for it.
+    pub fn encode(&self) -> Vec<u8> {
+        let version = self.version();
+        let mut out = Vec::with_capacity(Self::LEN);
+        out.push(version);
+        out.extend_from_slice(&self.policy.encode());
+        out.extend_from_slice(&self.rp_id_hash);
+        out.extend_from_slice(&self.public_key_x);
+        out.extend_from_slice(&self.public_key_y);
+        out.extend_from_slice(&self.signature_r);
+        out.extend_from_slice(&self.signature_s);
+        out.extend_from_slice(&self.counter.to_le_bytes());
+        out.extend_from_slice(&self.client_data_hash);
+        out.extend_from_slice(&self.attestation_image_id);
+        out.extend_from_slice(&self.attestation_claim_digest);
+        out.extend_from_slice(&self.challenge_hash);
+        out.extend_from_slice(&self.allowlist_entry.to_le_bytes());
+        out.extend_from_slice(&self.session_nonce);
+        out.extend_from_slice(&self.input_digest);
+        if version != ASSERTION_JOURNAL_V7 {
+            out.extend_from_slice(&self.policy_hash.unwrap_or_default());
+        }
+        if let Some(cert_chain_image_id) = &self.cert_chain_image_id {
+            out.extend_from_slice(cert_chain_image_id);
+        }
+        out
+    }
+
+    /// Decodes a journal, rejecting unknown versions and any length but the exact one.
+    pub fn decode(bytes: &[u8]) -> Result<Self, JournalError> {
+        check_header(bytes, ASSERTION_JOURNAL_V9, Self::LEN)?;
+
+        let mut fields = Fields::new(bytes);
+        let policy = Policy::decode(&fields.take()?);
+        let rp_id_hash = fields.take()?;
+        let public_key_x = fields.take()?;
+        let public_key_y = fields.take()?;
+        let signature_r = fields.take()?;
+        let signature_s = fields.take()?;
+        let counter = u32::from_le_bytes(fields.take()?);
+        let client_data_hash = fields.take()?;
+        let attestation_image_id = fields.take()?;
+        let attestation_claim_digest = fields.take()?;
+        let challenge_hash = fields.take()?;
+        let allowlist_entry = u32::from_le_bytes(fields.take()?);
+        let session_nonce = fields.take()?;
+        let input_digest = fields.take()?;
+        let policy_hash = Some(fields.take()?);
+        let cert_chain_image_id = Some(fields.take()?);
+        Ok(Self {
+            policy,
+            rp_id_hash,
+            public_key_x,
+            public_key_y,
+            signature_r,
+            signature_s,
+            counter,
+            client_data_hash,
+            attestation_image_id,
+            attestation_claim_digest,
+            challenge_hash,
+            allowlist_entry,
+            session_nonce,
+            input_digest,
+            policy_hash,
+            cert_chain_image_id,
+        })
+    }
+}
+
+/// Decoded attestation journal.
+///
+/// | offset | length | field                                      |
+/// |-------:|-------:|--------------------------------------------|
+/// |      0 |      1 | version (`7`)                              |
+/// |      1 |      6 | `policy`                                   |
+/// |      7 |     65 | `public_key`, uncompressed SEC1            |
+/// |     72 |     32 | `key_id`                                   |
+/// |    104 |     32 | `rp_id_hash`                               |
+/// |    136 |      1 | `environment`: 0 production, 1 development |
+/// |    137 |      8 | `timestamp`, LE                            |
+/// |    145 |     32 | `time_signer`                              |
+/// |    177 |      4 | `allowlist_entry`, LE                      |
+/// |    181 |     32 | `cert_chain_image_id`                      |
+/// |    213 |     32 | `cert_chain_claim_digest`                  |
+/// |    245 |     32 | `input_digest`                             |
+/// |    277 |     32 | `policy_hash`                              |
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AttestationJournal {
+    /// What the guest that produced the journal checks.
+    pub policy: Policy,
+    pub public_key: [u8; 65],
+    /// `SHA256` of the public key, as returned by `generateKey`.
+    pub key_id: [u8; 32],
+    /// `SHA256` of the app ID the key was attested for.
+    pub rp_id_hash: [u8; 32],
+    pub environment: u8,
+    /// Unix time the certificate chain was checked at, as supplied by the host.
+    pub timestamp: i64,
+    /// `SHA256` of the key that signed `timestamp`, or zeros if the host sent no time token.
+    pub time_signer: [u8; 32],
+    /// Index of `rp_id_hash` in the guest's built-in allow-list, as in [`AssertionJournal`].
+    pub allowlist_entry: u32,
+    /// Image ID of the certificate chain guest whose receipt vouched for `public_key`.
+    pub cert_chain_image_id: [u8; 32],
+    /// Digest of that receipt's claim, checked against
+    /// `ReceiptClaim::ok(cert_chain_image_id, journal).digest()` like the assertion's
+    /// `attestation_claim_digest`.
+    pub cert_chain_claim_digest: [u8; 32],
+    /// [`AttestationInput::canonical_digest`](crate::AttestationInput::canonical_digest) of
+    /// the input the guest verified, which covers the certificate chain guest's input.
+    pub input_digest: [u8; 32],
+    /// [`VerificationPolicy::hash`](crate::VerificationPolicy::hash) of the guest's policy,
+    /// as in [`AssertionJournal`]; `None` for a version 6 journal.
+    pub policy_hash: Option<[u8; 32]>,
+}
+
+/// Decoded certificate chain journal, committed by the guest that checks Apple's chain
+/// and the nonce once per key.
+///
+/// | offset | length | field                           |
+/// |-------:|-------:|---------------------------------|
+/// |      0 |      1 | version (`2`)                   |
+/// |      1 |      6 | `policy`                        |
+/// |      7 |     65 | `public_key`, uncompressed SEC1 |
+/// |     72 |     32 | `leaf_fingerprint`              |
+/// |    104 |     32 | `auth_data_hash`                |
+/// |    136 |      8 | `timestamp`, LE                 |
+/// |    144 |     32 | `time_signer`                   |
+/// |    176 |     32 | `input_digest`                  |
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CertChainJournal {
+    /// What the guest that produced the journal checks.
+    pub policy: Policy,
+    /// Key the leaf certificate attests.
+    pub public_key: [u8; 65],
+    /// `SHA256` of the DER leaf certificate.
+    pub leaf_fingerprint: [u8; 32],
+    /// `SHA256` of the authenticator data the nonce was checked over. The attestation guest
+    /// checks the rest of the object against it.
+    pub auth_data_hash: [u8; 32],
+    /// Unix time the chain was checked at, as supplied by the host.
+    pub timestamp: i64,
+    /// `SHA256` of the key that signed `timestamp`, or zeros if the host sent no time token.
+    pub time_signer: [u8; 32],
+    /// [`CertChainInput::canonical_digest`](crate::CertChainInput::canonical_digest) of the
+    /// input the guest verified.
+    pub input_digest: [u8; 32],
+}
+
+impl AttestationJournal {
+    /// Encoded length of a version 7 journal.
+    pub const LEN: usize = 1 + Policy::LEN + 65 + 32 + 32 + 1 + 8 + 32 + 4 + 32 + 32 + 32 + 32;
+
+    /// Encoded length of a version 6 journal.
+    pub const LEN_V6: usize = Self::LEN - 32;
+
+    pub const PRODUCTION: u8 = 0;
+    pub const DEVELOPMENT: u8 = 1;
+
+    /// Encodes the journal as version 7, or as version 6 if it has no `policy_hash`.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(Self::LEN);
+        out.push(if self.policy_hash.is_some() { ATTESTATION_JOURNAL_V7 } else { ATTESTATION_JOURNAL_V6 });
+        out.extend_from_slice(&self.policy.encode());
+        out.extend_from_slice(&self.public_key);
+        out.extend_from_slice(&self.key_id);
+        out.extend_from_slice(&self.rp_id_hash);
+        out.push(self.environment);
+        out.extend_from_slice(&self.timestamp.to_le_bytes());
+        out.extend_from_slice(&self.time_signer);
+        out.extend_from_slice(&self.allowlist_entry.to_le_bytes());
+        out.extend_from_slice(&self.cert_chain_image_id);
+        out.extend_from_slice(&self.cert_chain_claim_digest);
+        out.extend_from_slice(&self.input_digest);
+        if let Some(policy_hash) = &self.policy_hash {
+            out.extend_from_slice(policy_hash);
+        }
+        out
+    }
+
+    /// Decodes a journal, rejecting unknown versions and any length but the exact one.
+    pub fn decode(bytes: &[u8]) -> Result<Self, JournalError> {
+        check_header(bytes, ATTESTATION_JOURNAL_V7, Self::LEN)?;
+
+        let mut fields = Fields::new(bytes);
+        Ok(Self {
+            policy: Policy::decode(&fields.take()?),
+            public_key: fields.take()?,
+            key_id: fields.take()?,
+            rp_id_hash: fields.take()?,
+            environment: u8::from_le_bytes(fields.take()?),
+            timestamp: i64::from_le_bytes(fields.take()?),
+            time_signer: fields.take()?,
+            allowlist_entry: u32::from_le_bytes(fields.take()?),
+            cert_chain_image_id: fields.take()?,
+            cert_chain_claim_digest: fields.take()?,
+            input_digest: fields.take()?,
+            policy_hash: Some(fields.take()?),
+        })
+    }
+}
+
+impl CertChainJournal {
+    /// Encoded length of a version 2 journal.
+    pub const LEN: usize = 1 + Policy::LEN + 65 + 32 + 32 + 8 + 32 + 32;
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(Self::LEN);
+        out.push(CERT_CHAIN_JOURNAL_V2);
+        out.extend_from_slice(&self.policy.encode());
+        out.extend_from_slice(&self.public_key);
+        out.extend_from_slice(&self.leaf_fingerprint);
+        out.extend_from_slice(&self.auth_data_hash);
+        out.extend_from_slice(&self.timestamp.to_le_bytes());
+        out.extend_from_slice(&self.time_signer);
+        out.extend_from_slice(&self.input_digest);
+        out
+    }
+
+    /// Decodes a journal, rejecting unknown versions and any length but the exact one.
+    pub fn decode(bytes: &[u8]) -> Result<Self, JournalError> {
+        check_header(bytes, CERT_CHAIN_JOURNAL_V2, Self::LEN)?;
+
+        let mut fields = Fields::new(bytes);
+        Ok(Self {
+            policy: Policy::decode(&fields.take()?),
+            public_key: fields.take()?,
+            leaf_fingerprint: fields.take()?,
+            auth_data_hash: fields.take()?,
+            timestamp: i64::from_le_bytes(fields.take()?),
+            time_signer: fields.take()?,
+            input_digest: fields.take()?,
+        })
+    }
+}
+
+fn check_header(bytes: &[u8], version: u8, len: usize) -> Result<(), JournalError> {
+    let found = *bytes.first().ok_or(JournalError::Empty)?;
+    if found != version {
+        return Err(JournalError::UnsupportedVersion(found));
+    }
+    if bytes.len() != len {
+        return Err(JournalError::Length {
+            expected: len,
+            actual: bytes.len(),
+        });
+    }
+    Ok(())
+}
+
+/// The fields of a journal whose header [`check_header`] accepted, in layout order.
+struct Fields<'a> {
+    bytes: &'a [u8],
+    len: usize,
+}
+
+impl<'a> Fields<'a> {
+    /// Starts after the version byte.
+    fn new(bytes: &'a [u8]) -> Self {
+        Fields { bytes: bytes.get(1..).unwrap_or_default(), len: bytes.len() }
+    }
+
+    /// The next `N` bytes. The header check leaves exactly enough, so the error only stands
+    /// in for a panic should a layout and its `LEN` disagree.
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], JournalError> {
+        let Some((field, rest)) = self.bytes.split_first_chunk() else {
+            return Err(JournalError::Length { expected: self.len - self.bytes.len() + N, actual: self.len });
+        };
+        self.bytes = rest;
+        Ok(*field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::POLICY;
+
+    fn sample() -> AssertionJournal {
+        AssertionJournal {
+            policy: POLICY,
+            rp_id_hash: [1; 32],
+            public_key_x: [2; 32],
+            public_key_y: [3; 32],
+            signature_r: [4; 32],
+            signature_s: [5; 32],
+            counter: 0x0102_0304,
+            client_data_hash: [6; 32],
+            attestation_image_id: [7; 32],
+            attestation_claim_digest: [8; 32],
+            challenge_hash: [9; 32],
+            allowlist_entry: 0x0a0b_0c0d,
+            session_nonce: [10; 32],
+            input_digest: [11; 32],
+            policy_hash: Some([12; 32]),
+            cert_chain_image_id: Some([13; 32]),
+        }
+    }
+
+    #[test]
+    fn layout_has_static_offsets() {
+        let bytes = sample().encode();
+        assert_eq!(bytes.len(), AssertionJournal::LEN);
+        assert_eq!(bytes[0], ASSERTION_JOURNAL_V9);
+        assert_eq!(bytes[1..7], POLICY.encode());
+        for (offset, value) in [
+            (7, 1),
+            (39, 2),
+            (71, 3),
+            (103, 4),
+            (135, 5),
+            (171, 6),
+            (203, 7),
+            (235, 8),
+            (267, 9),
+            (303, 10),
+            (335, 11),
+            (367, 12),
+            (399, 13),
+        ] {
+            assert!(bytes[offset..offset + 32].iter().all(|&b| b == value), "field at {offset}");
+        }
+        assert_eq!(bytes[167..171], [4, 3, 2, 1]);
+        assert_eq!(bytes[299..303], [0x0d, 0x0c, 0x0b, 0x0a]);
+    }
+
+    #[test]
+    fn round_trips() {
+        assert_eq!(AssertionJournal::decode(&sample().encode()), Ok(sample()));
+    }
+
+    #[test]
+    fn journals_without_the_last_fields_encode_the_previous_layouts() {
+        let v8 = AssertionJournal { cert_chain_image_id: None, ..sample() }.encode();
+        assert_eq!(v8.len(), AssertionJournal::LEN_V8);
+        assert_eq!(v8[0], ASSERTION_JOURNAL_V8);
+        assert_eq!(v8[1..], sample().encode()[1..AssertionJournal::LEN_V8]);
+
+        let v7 = AssertionJournal { policy_hash: None, cert_chain_image_id: None, ..sample() }.encode();
+        assert_eq!(v7.len(), AssertionJournal::LEN_V7);
+        assert_eq!(v7[0], ASSERTION_JOURNAL_V7);
+        assert_eq!(v7[1..], sample().encode()[1..AssertionJournal::LEN_V7]);
+        // Which only hosts read.
+        assert_eq!(AssertionJournal::decode(&v8), Err(JournalError::UnsupportedVersion(8)));
+        assert_eq!(AssertionJournal::decode(&v7), Err(JournalError::UnsupportedVersion(7)));
+
+        let no_policy_hash = AssertionJournal { policy_hash: None, ..sample() }.encode();
+        assert_eq!(no_policy_hash[367..399], [0; 32]);
+    }
+
+    #[test]
+    fn rejects_unknown_versions_and_lengths() {
+        let mut bytes = sample().encode();
+        assert_eq!(
+            AssertionJournal::decode(&bytes[..100]),
+            Err(JournalError::Length {
+                expected: AssertionJournal::LEN,
+                actual: 100
+            })
+        );
+        bytes[0] = 8;
+        assert_eq!(AssertionJournal::decode(&bytes), Err(JournalError::UnsupportedVersion(8)));
+        assert_eq!(AssertionJournal::decode(&[]), Err(JournalError::Empty));
+    }
+
+    #[test]
+    fn attestation_layout_round_trips() {
+        let journal = AttestationJournal {
+            policy: POLICY,
+            public_key: [4; 65],
+            key_id: [7; 32],
+            rp_id_hash: [8; 32],
+            environment: AttestationJournal::DEVELOPMENT,
+            timestamp: 1_751_800_000,
+            time_signer: [9; 32],
+            allowlist_entry: 2,
+            cert_chain_image_id: [10; 32],
+            cert_chain_claim_digest: [11; 32],
+            input_digest: [12; 32],
+            policy_hash: Some([13; 32]),
+        };
+        let bytes = journal.encode();
+        assert_eq!(bytes.len(), AttestationJournal::LEN);
+        assert_eq!(bytes[0], ATTESTATION_JOURNAL_V7);
+        assert_eq!(bytes[1..7], POLICY.encode());
+        assert_eq!(bytes[136], 1);
+        assert_eq!(bytes[137..145], 1_751_800_000i64.to_le_bytes());
+        assert!(bytes[145..177].iter().all(|&b| b == 9));
+        assert_eq!(bytes[177..181], [2, 0, 0, 0]);
+        assert!(bytes[181..213].iter().all(|&b| b == 10));
+        assert!(bytes[213..245].iter().all(|&b| b == 11));
+        assert!(bytes[245..277].iter().all(|&b| b == 12));
+        assert!(bytes[277..309].iter().all(|&b| b == 13));
+        assert_eq!(AttestationJournal::decode(&bytes), Ok(journal.clone()));
+        assert_eq!(
+            AttestationJournal::decode(&bytes[..181]),
+            Err(JournalError::Length {
+                expected: AttestationJournal::LEN,
+                actual: 181
+            })
+        );
+        let v6 = AttestationJournal { policy_hash: None, ..journal }.encode();
+        assert_eq!(v6.len(), AttestationJournal::LEN_V6);
+        assert_eq!(v6[1..], bytes[1..277]);
+        assert_eq!(AttestationJournal::decode(&v6), Err(JournalError::UnsupportedVersion(6)));
+    }
+
+    #[test]
+    fn cert_chain_layout_round_trips() {
+        let journal = CertChainJournal {
+            policy: POLICY,
+            public_key: [4; 65],
+            leaf_fingerprint: [5; 32],
+            auth_data_hash: [6; 32],
+            timestamp: 1_751_800_000,
+            time_signer: [7; 32],
+            input_digest: [8; 32],
+        };
+        let bytes = journal.encode();
+        assert_eq!(bytes.len(), CertChainJournal::LEN);
+        assert_eq!(bytes[0], CERT_CHAIN_JOURNAL_V2);
+        assert_eq!(bytes[1..7], POLICY.encode());
+        assert!(bytes[72..104].iter().all(|&b| b == 5));
+        assert!(bytes[104..136].iter().all(|&b| b == 6));
+        assert_eq!(bytes[136..144], 1_751_800_000i64.to_le_bytes());
+        assert!(bytes[144..176].iter().all(|&b| b == 7));
+        assert!(bytes[176..208].iter().all(|&b| b == 8));
+        assert_eq!(CertChainJournal::decode(&bytes), Ok(journal));
+        // An attestation journal is not a certificate chain journal.
+        assert!(CertChainJournal::decode(&[ATTESTATION_JOURNAL_V7; AttestationJournal::LEN]).is_err());
+    }
+}
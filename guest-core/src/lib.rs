@@ -0,0 +1,96 @@
+//! Inputs the host writes to the guests with `ExecutorEnvBuilder::write` and the guests
+//! read back with `env::read`, the journals the guests commit, and the verification the
+//! guests run in between.
+//!
+//! Both sides depend on this crate so the encodings cannot drift apart. Changing anything
+//! here changes the guest ELF, and with it the image ID. The guests' `main` only reads the
+//! input, calls [`cert_chain::run`], [`attestation::run`] or [`assertion::run`] and commits
+//! the journal, so the verification logic is tested natively with `cargo test -p guest-core`.
+//!
+//! An attestation is proven in two steps: the certificate chain guest checks Apple's chain
+//! and the nonce, the expensive part, and the attestation guest checks the rest of the
+//! object against the chain guest's journal, verified by composition. The chain receipt
+//! depends only on the attestation object, so a host can prove it once per key.
+
+use serde::{Deserialize, Serialize};
+
+mod allowlist;
+pub mod assertion;
+pub mod attestation;
+pub mod cert_chain;
+pub mod digest;
+mod error;
+mod journal;
+mod policy;
+
+pub use allowlist::{allowlist_entry, NO_ALLOWLIST};
+pub use digest::{ASSERTION_INPUT_TAG, ATTESTATION_INPUT_TAG, CERT_CHAIN_INPUT_TAG};
+pub use error::{abort, abort_message, parse_abort, VerifyError};
+pub use journal::{
+    AssertionJournal, AttestationJournal, CertChainJournal, JournalError, ASSERTION_JOURNAL_V7, ASSERTION_JOURNAL_V8,
+    ASSERTION_JOURNAL_V9, ATTESTATION_JOURNAL_V6, ATTESTATION_JOURNAL_V7, CERT_CHAIN_JOURNAL_V2,
+};
+pub use policy::{Policy, VerificationPolicy, POLICY};
+
+/// Everything the assertion guest needs to verify one assertion.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct AssertionInput {
+    /// CBOR assertion object, as produced by `DCAppAttestService.generateAssertion`.
+    pub assertion: Vec<u8>,
+    /// Client data the assertion signs.
+    pub client_data: Vec<u8>,
+    /// `SHA256` of the `<team id>.<bundle id>` app ID.
+    pub app_id_hash: [u8; 32],
+    /// Journal of the attestation receipt for the key. The guest verifies it with
+    /// `env::verify`, so the host must add that receipt as an assumption, and takes the
+    /// public key from it.
+    pub attestation_journal: Vec<u8>,
+    /// Image ID of the attestation guest the journal must come from.
+    pub attestation_image_id: [u32; 8],
+    /// Counter of the last accepted assertion for this key, `None` before the first one,
+    /// which may then have any counter.
+    pub previous_counter: Option<u32>,
+    /// Nonce the relying party issued for this session. Not verified, only committed.
+    pub session_nonce: [u8; 32],
+}
+
+/// Everything the certificate chain guest needs to check one attestation object's chain.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CertChainInput {
+    /// CBOR attestation object, as produced by `DCAppAttestService.attestKey`.
+    pub attestation: Vec<u8>,
+    /// Challenge the relying party issued for this attestation.
+    pub challenge: Vec<u8>,
+    /// Unix time the certificate chain is checked at; the guest commits it unchanged.
+    pub timestamp: i64,
+    /// Optional signature over `timestamp` by a time source the verifier trusts.
+    pub time_token: Option<TimeToken>,
+}
+
+/// Everything the attestation guest needs to verify the rest of one attestation object.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct AttestationInput {
+    /// The same CBOR attestation object the certificate chain guest checked.
+    pub attestation: Vec<u8>,
+    /// `SHA256` of the `<team id>.<bundle id>` app ID.
+    pub app_id_hash: [u8; 32],
+    /// Key identifier returned by `generateKey`.
+    pub key_id: Vec<u8>,
+    /// Journal of the certificate chain receipt for the object. The guest verifies it with
+    /// `env::verify`, so the host must add that receipt as an assumption.
+    pub cert_chain_journal: Vec<u8>,
+    /// Image ID of the certificate chain guest the journal must come from.
+    pub cert_chain_image_id: [u32; 8],
+}
+
+/// A time source's ECDSA P-256 signature over the little-endian bytes of a timestamp.
+///
+/// The guest only checks that the signature is valid for `signer`; the journal carries
+/// `SHA256(signer)` and deciding whether that signer is trusted is up to the verifier.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TimeToken {
+    /// Uncompressed SEC1 public key of the time source.
+    pub signer: Vec<u8>,
+    /// DER-encoded ECDSA signature over `timestamp.to_le_bytes()`, hashed with SHA-256.
+    pub signature: Vec<u8>,
+}
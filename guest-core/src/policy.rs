@@ -0,0 +1,304 @@
+//! The verification policy both guests commit right after the journal version byte.
+//!
+//! Upgrading appattest-rs or changing a check changes the image IDs, but an image ID does
+//! not say what changed. The descriptor does: a verifier can require, say, a minimum
+//! appattest-rs version without keeping a table of image IDs.
+//!
+//! The descriptor leaves out the app IDs a build accepts. [`VerificationPolicy`] is the
+//! whole policy, descriptor and allow-list, as data: the attestation and assertion guests
+//! commit the SHA-256 of its canonical CBOR as their journals' `policy_hash`, built from
+//! their compile-time constants, and a server builds the one it expects from its own
+//! configuration. If the two drift apart, the hashes differ and verification fails, rather
+//! than the server trusting checks the guest never made.
+//!
+//! The canonical CBOR is a map with text keys in the order of RFC 8949's deterministic
+//! encoding, integers and lengths in their shortest form:
+//!
+//! | key                | value                                              |
+//! |--------------------|----------------------------------------------------|
+//! | `lowS`             | `true` or `false`                                  |
+//! | `appIdHashes`      | array of 32-byte strings, in allow-list order      |
+//! | `environments`     | unsigned integer                                   |
+//! | `counterPolicy`    | unsigned integer                                   |
+//! | `appattestVersion` | array of three unsigned integers                   |
+
+use risc0_zkvm::sha::{Impl, Sha256};
+
+use crate::VerifyError;
+
+/// What the guests check, as committed in their journals.
+///
+/// | offset | length | field                                    |
+/// |-------:|-------:|------------------------------------------|
+/// |      0 |      3 | `appattest_version`: major, minor, patch |
+/// |      3 |      1 | `counter_policy`                         |
+/// |      4 |      1 | `low_s`: 0 or 1                          |
+/// |      5 |      1 | `environments`, a bit set                |
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Policy {
+    /// Version of the appattest-rs crate the guest verifies with.
+    pub appattest_version: (u8, u8, u8),
+    /// How an assertion counter is checked against the previous one.
+    pub counter_policy: u8,
+    /// Whether the assertion journal commits the signature in low-S form only.
+    pub low_s: bool,
+    /// Attestation environments the guest accepts: [`Policy::PRODUCTION`], [`Policy::DEVELOPMENT`]
+    /// or both.
+    pub environments: u8,
+}
+
+/// The policy of the guests in this build, shared with the host so the two cannot disagree.
+pub const POLICY: Policy = Policy {
+    appattest_version: appattest_rs::VERSION,
+    counter_policy: Policy::COUNTER_STRICTLY_INCREASING,
+    low_s: true,
+    environments: Policy::PRODUCTION | Policy::DEVELOPMENT,
+};
+
+impl Policy {
+    /// Encoded length of the descriptor.
+    pub const LEN: usize = 6;
+
+    /// The counter must be greater than the previous one, or than 0 on the first assertion.
+    pub const COUNTER_STRICTLY_INCREASING: u8 = 1;
+
+    pub const PRODUCTION: u8 = 1 << 0;
+    pub const DEVELOPMENT: u8 = 1 << 1;
+
+    pub fn encode(&self) -> [u8; Self::LEN] {
+        let (major, minor, patch) = self.appattest_version;
+        [major, minor, patch, self.counter_policy, self.low_s as u8, self.environments]
+    }
+
+    pub fn decode(bytes: &[u8; Self::LEN]) -> Self {
+        Self {
+            appattest_version: (bytes[0], bytes[1], bytes[2]),
+            counter_policy: bytes[3],
+            low_s: bytes[4] != 0,
+            environments: bytes[5],
+        }
+    }
+}
+
+/// Everything a guest build checks: the [`Policy`] descriptor and the app IDs it accepts.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerificationPolicy {
+    pub policy: Policy,
+    /// `SHA256` of each app ID the build accepts, in the order of its allow-list, whose
+    /// index the journals commit as `allowlist_entry`. Empty for a build that accepts any.
+    pub app_id_hashes: Vec<[u8; 32]>,
+}
+
+impl VerificationPolicy {
+    /// The policy of a guest of this build compiled with `allowlist`, its
+    /// `RP_ID_HASH_ALLOWLIST`, see [`crate::allowlist_entry`]. Fails with
+    /// [`VerifyError::BadAllowlist`] on an entry that is not 64 hex digits.
+    pub fn from_allowlist(allowlist: Option<&str>) -> Result<Self, VerifyError> {
+        let app_id_hashes = match allowlist.map(str::trim) {
+            None | Some("") => Vec::new(),
+            Some(list) => list
+                .split(',')
+                .map(|entry| parse_hash(entry.trim()))
+                .collect::<Option<_>>()
+                .ok_or(VerifyError::BadAllowlist)?,
+        };
+        Ok(VerificationPolicy { policy: POLICY, app_id_hashes })
+    }
+
+    /// The policy a server expects of guests of this build compiled with `APP_ID_ALLOWLIST`
+    /// set to `app_ids`, `<team id>.<bundle id>` in the same order; none for a build without
+    /// one.
+    pub fn for_app_ids<S: AsRef<str>>(app_ids: &[S]) -> Self {
+        let app_id_hashes = app_ids.iter().map(|app_id| sha256(app_id.as_ref().as_bytes())).collect();
+        VerificationPolicy { policy: POLICY, app_id_hashes }
+    }
+
+    /// The canonical CBOR encoding, see the module documentation.
+    pub fn to_canonical_cbor(&self) -> Vec<u8> {
+        let (major, minor, patch) = self.policy.appattest_version;
+        let mut version = Vec::new();
+        cbor_head(&mut version, ARRAY, 3);
+        for part in [major, minor, patch] {
+            cbor_head(&mut version, UNSIGNED, part.into());
+        }
+        let mut app_id_hashes = Vec::new();
+        cbor_head(&mut app_id_hashes, ARRAY, self.app_id_hashes.len() as u64);
+        for hash in &self.app_id_hashes {
+            cbor_head(&mut app_id_hashes, BYTES, 32);
+            app_id_hashes.extend_from_slice(hash);
+        }
+        let mut counter_policy = Vec::new();
+        cbor_head(&mut counter_policy, UNSIGNED, self.policy.counter_policy.into());
+        let mut environments = Vec::new();
+        cbor_head(&mut environments, UNSIGNED, self.policy.environments.into());
+        let low_s = vec![if self.policy.low_s { TRUE } else { FALSE }];
+
+        let mut entries: Vec<(Vec<u8>, Vec<u8>)> = [
+            ("appattestVersion", version),
+            ("appIdHashes", app_id_hashes),
+            ("counterPolicy", counter_policy),
+            ("environments", environments),
+            ("lowS", low_s),
+        ]
+        .into_iter()
+        .map(|(key, value)| {
+            let mut encoded = Vec::new();
+            cbor_head(&mut encoded, TEXT, key.len() as u64);
+            encoded.extend_from_slice(key.as_bytes());
+            (encoded, value)
+        })
+        .collect();
+        // Deterministic encoding orders a map by the bytes of its encoded keys.
+        entries.sort();
+
+        let mut out = Vec::new();
+        cbor_head(&mut out, MAP, entries.len() as u64);
+        for (key, value) in entries {
+            out.extend_from_slice(&key);
+            out.extend_from_slice(&value);
+        }
+        out
+    }
+
+    /// `SHA256` of [`VerificationPolicy::to_canonical_cbor`], the `policy_hash` the guests
+    /// commit.
+    pub fn hash(&self) -> [u8; 32] {
+        sha256(&self.to_canonical_cbor())
+    }
+}
+
+const UNSIGNED: u8 = 0;
+const BYTES: u8 = 2;
+const TEXT: u8 = 3;
+const ARRAY: u8 = 4;
+const MAP: u8 = 5;
+const FALSE: u8 = 0xf4;
+const TRUE: u8 = 0xf5;
+
+/// Appends the head of a CBOR item of `major` type with argument `value`, in its shortest
+/// form.
+fn cbor_head(out: &mut Vec<u8>, major: u8, value: u64) {
+    let major = major << 5;
+    match value {
+        0..=23 => out.push(major | value as u8),
+        24..=0xff => out.extend_from_slice(&[major | 24, value as u8]),
+        0x100..=0xffff => {
+            out.push(major | 25);
+            out.extend_from_slice(&(value as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(major | 26);
+            out.extend_from_slice(&(value as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(major | 27);
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+}
+
+fn parse_hash(hex: &str) -> Option<[u8; 32]> {
+    if hex.len() != 64 || !hex.bytes().all(|digit| digit.is_ascii_hexdigit()) {
+        return None;
+    }
+    let mut hash = [0; 32];
+    for (byte, digits) in hash.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(core::str::from_utf8(digits).ok()?, 16).ok()?;
+    }
+    Some(hash)
+}
+
+fn sha256(bytes: &[u8]) -> [u8; 32] {
+    Impl::hash_bytes(bytes).as_bytes().try_into().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn policy_names_the_linked_appattest_version() {
+        let version = env!("CARGO_PKG_VERSION");
+        let (major, minor, patch) = POLICY.appattest_version;
+        // appattest-rs shares the workspace version with this crate.
+        assert_eq!(format!("{major}.{minor}.{patch}"), version);
+        assert_eq!(POLICY.counter_policy, Policy::COUNTER_STRICTLY_INCREASING);
+        assert!(POLICY.low_s);
+        assert_eq!(POLICY.environments, 0b11);
+    }
+
+    #[test]
+    fn round_trips() {
+        assert_eq!(Policy::decode(&POLICY.encode()), POLICY);
+    }
+
+    fn sample() -> VerificationPolicy {
+        VerificationPolicy {
+            policy: Policy { appattest_version: (0, 2, 1), ..POLICY },
+            app_id_hashes: vec![[0xab; 32]],
+        }
+    }
+
+    #[test]
+    fn canonical_cbor_sorts_its_keys() {
+        let expected = [
+            &[0xa5, 0x64][..],
+            b"lowS",
+            &[0xf5, 0x6b],
+            b"appIdHashes",
+            &[0x81, 0x58, 0x20],
+            &[0xab; 32],
+            &[0x6c],
+            b"environments",
+            &[0x03, 0x6d],
+            b"counterPolicy",
+            &[0x01, 0x70],
+            b"appattestVersion",
+            &[0x83, 0x00, 0x02, 0x01],
+        ]
+        .concat();
+        assert_eq!(sample().to_canonical_cbor(), expected);
+        assert_eq!(sample().hash(), sha256(&expected));
+
+        // No allow-list is an empty array, and 24 entries take a one-byte length.
+        let open = VerificationPolicy { app_id_hashes: Vec::new(), ..sample() };
+        assert!(open.to_canonical_cbor().windows(12).any(|w| w == b"appIdHashes\x80"));
+        let long = VerificationPolicy { app_id_hashes: vec![[1; 32]; 24], ..sample() };
+        assert!(long.to_canonical_cbor().windows(15).any(|w| w == b"appIdHashes\x98\x18\x58\x20"));
+    }
+
+    #[test]
+    fn every_field_changes_the_hash() {
+        let hash = sample().hash();
+        let changed = [
+            VerificationPolicy { policy: Policy { appattest_version: (0, 2, 2), ..sample().policy }, ..sample() },
+            VerificationPolicy { policy: Policy { counter_policy: 2, ..sample().policy }, ..sample() },
+            VerificationPolicy { policy: Policy { low_s: false, ..sample().policy }, ..sample() },
+            VerificationPolicy { policy: Policy { environments: Policy::PRODUCTION, ..sample().policy }, ..sample() },
+            VerificationPolicy { app_id_hashes: Vec::new(), ..sample() },
+            VerificationPolicy { app_id_hashes: vec![[0xab; 32], [0xcd; 32]], ..sample() },
+        ];
+        for policy in changed {
+            assert_ne!(policy.hash(), hash, "{policy:?}");
+        }
+        // The allow-list's order is part of the policy: the journals commit indices into it.
+        let first = VerificationPolicy { app_id_hashes: vec![[1; 32], [2; 32]], ..sample() };
+        let second = VerificationPolicy { app_id_hashes: vec![[2; 32], [1; 32]], ..sample() };
+        assert_ne!(first.hash(), second.hash());
+    }
+
+    #[test]
+    fn servers_and_guests_build_the_same_policy() {
+        let app_ids = ["LMRM26A744.xyz.elus.aegis.app-attester", "TEAMID.com.example.other"];
+        let hex = |app_id: &str| sha256(app_id.as_bytes()).iter().map(|b| format!("{b:02X}")).collect::<String>();
+        let allowlist = format!("{}, {}", hex(app_ids[0]), hex(app_ids[1]));
+        assert_eq!(VerificationPolicy::from_allowlist(Some(&allowlist)), Ok(VerificationPolicy::for_app_ids(&app_ids)));
+        assert_eq!(VerificationPolicy::from_allowlist(None), Ok(VerificationPolicy::for_app_ids::<&str>(&[])));
+        assert!(VerificationPolicy::from_allowlist(Some(" ")).unwrap().app_id_hashes.is_empty());
+
+        let bad = ["abc".to_string(), format!("{}zz", &hex(app_ids[0])[..62]), format!("{},", hex(app_ids[0]))];
+        for bad in bad {
+            assert_eq!(VerificationPolicy::from_allowlist(Some(&bad)), Err(VerifyError::BadAllowlist), "{bad}");
+        }
+    }
+}
@@ -2,6 +2,7 @@ import Foundation
 
 // MARK: – Data models
 public struct AttestationResult: Sendable {
+    public let appID: String
     public let keyID: String
     public let attestation: Data
     public let challenge: Data       // 32-byte random
@@ -36,9 +37,11 @@ public struct AssertionResult: Sendable {
 // ---------------------------------------------------------------------
 public func generateDummyAttestation(challenge: Data) -> AttestationResult {
     let dummyKeyID = "dummy-key-id"
+    let dummyAppID = "TEAM.some.app"
     let dummyAttestation = Data("dummy-attestation".utf8)
 
     return AttestationResult(
+        appID: dummyAppID,
         keyID: dummyKeyID,
         attestation: dummyAttestation,
         challenge: challenge
@@ -32,11 +32,26 @@ public actor ProverService {
     /// 3. Flattens the signature + assertion hash into `[String]` inputs.
     /// 4. Produces a Noir proof (`Data`) via `generateNoirProof`.
     ///
+    /// - Parameters:
+    ///   - attestationReceipt: Receipt from `proveAttestationExt` for the asserting key.
+    ///   - previousCounter: Counter of the last assertion accepted for this key; nil before its first.
+    ///   - sessionNonce: 32-byte nonce the verifier issued for this session; zeros if none.
     /// - Returns: Raw Noir proof bytes.
-    public func proveAssertionExt(assertionResult: AssertionResult) async throws -> AssertionCompositeProof {
+    public func proveAssertionExt(assertionResult: AssertionResult,
+                                  attestationReceipt: Data,
+                                  previousCounter: UInt32? = nil,
+                                  sessionNonce: Data = Data(count: 32)) async throws -> AssertionCompositeProof {
 
         // RISC‑0 proof for the iOS‑level assertion (signature)
-        let assertionProof = try proveAssertion()
+        let request = AssertionRequest(
+            assertion: assertionResult.assertion,
+            clientData: assertionResult.payload,
+            appId: assertionResult.appID,
+            attestationReceipt: attestationReceipt,
+            previousCounter: previousCounter,
+            sessionNonce: sessionNonce
+        )
+        let assertionProof = try proveAssertion(request: request)
 
         // 1️⃣  Hash of the original payload (32 bytes) — must be the first Noir input
         let payloadHash = SHA256.hash(data: assertionResult.payload)
@@ -68,8 +83,16 @@ public actor ProverService {
     /// - Returns: `AttestationProof` (just the receipt bytes).
     public func proveAttestationExt(att: AttestationResult) throws -> AttestationExtProof {
 
-   
-        let proofOut = try proveAttestation()
+        // The guest checks the certificate chain at this time and commits it to the journal.
+        let request = AttestationRequest(
+            attestation: att.attestation,
+            challenge: att.challenge,
+            appId: att.appID,
+            keyId: Data(base64Encoded: att.keyID) ?? Data(),
+            timestamp: Int64(Date().timeIntervalSince1970),
+            timeToken: nil
+        )
+        let proofOut = try proveAttestation(request: request)
 
         return AttestationExtProof(risc0Receipt: proofOut.receipt)
     }
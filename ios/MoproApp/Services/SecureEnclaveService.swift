@@ -32,7 +32,8 @@ public actor SecureEnclaveService {
             clientDataHash: Data(clientHash)
         )
 
-        return AttestationResult(keyID: keyID,
+        return AttestationResult(appID: appID,
+                                 keyID: keyID,
                                  attestation: attestation,
                                  challenge: challenge)
     }
@@ -26,10 +26,10 @@ struct VerifiabilityService {
     }
 
     // MARK: – Assertion
-    func generateAssertion(payload: Data) async -> (AssertionResult, AssertionCompositeProof, Bool) {
+    func generateAssertion(payload: Data, attestationReceipt: Data) async -> (AssertionResult, AssertionCompositeProof, Bool) {
         let (asr, usedDummy) = await safeGenerateAssertion(payload: payload)
         do {
-            let comp = try await prover.proveAssertionExt(assertionResult: asr)
+            let comp = try await prover.proveAssertionExt(assertionResult: asr, attestationReceipt: attestationReceipt)
         return (asr, comp, usedDummy)
         } catch ProverError.circuitNotFound(let message) {
             print("[Error] " + message)
@@ -46,11 +46,15 @@ final class AppFlowViewModel: ObservableObject {
             appendLog("❌ No ML output available for assertion")
             return false 
         }
+        guard let attestationReceipt = attestation?.1.risc0Receipt else {
+            appendLog("❌ No attestation receipt available for assertion")
+            return false
+        }
 
         print("🔄 AppFlowViewModel: Running assertion with \(payload.count) bytes of data")
         
         do {
-            let (asr, comp, usedDummy) = await service.generateAssertion(payload: payload)
+            let (asr, comp, usedDummy) = await service.generateAssertion(payload: payload, attestationReceipt: attestationReceipt)
             if usedDummy { showWarning("⚠️ Dummy assertion used") }
 
             assertion = (asr, comp)
@@ -15,8 +15,34 @@
 use std::{collections::HashMap, env, path::PathBuf};
 
 use risc0_build::{embed_methods_with_options, DockerOptionsBuilder, GuestOptionsBuilder};
+use sha2::{Digest, Sha256};
+
+/// Comma-separated `<team id>.<bundle id>` app IDs the guests accept; unset accepts any.
+const APP_ID_ALLOWLIST: &str = "APP_ID_ALLOWLIST";
+/// What the guests are compiled with: the hex `SHA256` of each app ID, in order.
+const RP_ID_HASH_ALLOWLIST: &str = "RP_ID_HASH_ALLOWLIST";
+
+/// Hashes the allow-list for the guests, which read it with `option_env!`.
+fn rp_id_hash_allowlist() -> Option<String> {
+    let app_ids = env::var(APP_ID_ALLOWLIST).ok()?;
+    let hashes: Vec<String> = app_ids
+        .split(',')
+        .map(str::trim)
+        .filter(|app_id| !app_id.is_empty())
+        .map(|app_id| hex::encode(Sha256::digest(app_id)))
+        .collect();
+    (!hashes.is_empty()).then(|| hashes.join(","))
+}
 
 fn main() {
+    // The allow-list is compiled into the guests, so each list yields different image IDs.
+    // The guest build inherits this process's environment.
+    println!("cargo:rerun-if-env-changed={APP_ID_ALLOWLIST}");
+    let allowlist = rp_id_hash_allowlist();
+    match &allowlist {
+        Some(hashes) => env::set_var(RP_ID_HASH_ALLOWLIST, hashes),
+        None => env::remove_var(RP_ID_HASH_ALLOWLIST),
+    }
 
     // Builds can be made deterministic, and thereby reproducible, by using Docker to build the
     // guest. Check the RISC0_USE_DOCKER variable and use Docker to build the guest if set.
@@ -27,6 +53,7 @@ fn main() {
     if env::var("RISC0_USE_DOCKER").is_ok() {
         let docker_options = DockerOptionsBuilder::default()
             .root_dir(manifest_dir.join("../"))
+            .env(allowlist.iter().map(|hashes| (RP_ID_HASH_ALLOWLIST.to_string(), hashes.clone())).collect())
             .build()
             .unwrap();
         builder.use_docker(docker_options);
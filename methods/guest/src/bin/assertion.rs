@@ -12,34 +12,27 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use appattest_rs::assertion::Assertion;
-#[allow(unused_imports)]
+use guest_core::{abort, abort_message, assertion, AssertionInput};
 use risc0_zkvm::guest::env;
+use risc0_zkvm::sha::Digest;
 
+// Any failure panics, which fails the execution: a receipt for this guest exists only if
+// the assertion verified, and the journal is committed only then. The panic message carries
+// a guest_core::abort code for the host to report.
 fn main() {
-   let base64_client_data = "eCA9IDE1";
-    let app_id = "LMRM26A744.xyz.elus.aegis.app-attester"; // replace this with yours. E.g 9000738UU8.auth.iphone.com
-    let public_key = "-----BEGIN PUBLIC KEY-----
-MFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEheMiyqD5gbwYzVNXTx3HYcE50VAw
-o2sbJJzBWMgixFBrFXS2scW1v6+OKh3+PeqofIgC2GPIqsI6qZBWCopWtA==
------END PUBLIC KEY-----";
+    let input: AssertionInput = env::read();
 
-    let previous_counter = 0;
-    let base64_cbor_data = "omlzaWduYXR1cmVYRzBFAiA4+3V+mKaN4IvrhpAZug9nG5EgTLf9urMYoZIdDdt36AIhAMmP99pwoOaRqYCV4Q3Km4vQqebxCzfhdb2ow038AMWycWF1dGhlbnRpY2F0b3JEYXRhWCXXwWIjgKCprB/bVvaYf7bZmcJ35UnK1TNWcBhOwgdSS0AAAAAB";
+    // The key comes from an attestation receipt, not from the host: `env::verify` adds the
+    // attestation claim as an assumption that the final receipt can only discharge with a
+    // valid receipt for it.
+    env::verify(Digest::from(input.attestation_image_id), &input.attestation_journal)
+        .unwrap_or_else(|e| {
+            let reason = format!("Attestation receipt verification failed: {e:?}");
+            panic!("{}", abort_message(abort::ATTESTATION_RECEIPT_INVALID, reason))
+        });
 
-    // Convert from base64 CBOR to Assertion
-    let assertion_result = Assertion::from_base64(base64_cbor_data);
-    
-    match assertion_result {
-        Ok(assertion) => {
-            match assertion.verify(base64_client_data, app_id, public_key, previous_counter, Some(false)) {
-                Ok(result) => { 
-                    println!("Assertion verification successful!");
-                    env::commit(&result);
-                },
-                Err(e) => println!("Assertion verification failed: {:?}", e),
-            }
-        },
-        Err(e) => println!("Failed to decode and create assertion: {:?}", e),
-    }
+    // The allow-list is built in by methods/build.rs; see guest_core::allowlist.
+    let journal = assertion::run(input, option_env!("RP_ID_HASH_ALLOWLIST"))
+        .unwrap_or_else(|e| panic!("{}", e.abort_message()));
+    env::commit_slice(&journal.encode());
 }
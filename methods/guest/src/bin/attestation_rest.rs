@@ -0,0 +1,38 @@
+// Copyright 2023 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use guest_core::{abort, abort_message, attestation, AttestationInput};
+use risc0_zkvm::guest::env;
+use risc0_zkvm::sha::Digest;
+
+// Any failure panics, which fails the execution: a receipt for this guest exists only if
+// Apple attested the key, and the journal is committed only then. The panic message carries
+// a guest_core::abort code for the host to report.
+fn main() {
+    let input: AttestationInput = env::read();
+
+    // The key and the checked authenticator data come from a certificate chain receipt:
+    // `env::verify` adds its claim as an assumption that the final receipt can only
+    // discharge with a valid receipt for it.
+    env::verify(Digest::from(input.cert_chain_image_id), &input.cert_chain_journal)
+        .unwrap_or_else(|e| {
+            let reason = format!("Certificate chain receipt verification failed: {e:?}");
+            panic!("{}", abort_message(abort::CERT_CHAIN_RECEIPT_INVALID, reason))
+        });
+
+    // The allow-list is built in by methods/build.rs; see guest_core::allowlist.
+    let journal = attestation::run(input, option_env!("RP_ID_HASH_ALLOWLIST"))
+        .unwrap_or_else(|e| panic!("{}", e.abort_message()));
+    env::commit_slice(&journal.encode());
+}
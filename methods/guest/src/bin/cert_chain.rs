@@ -0,0 +1,25 @@
+// Copyright 2023 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use guest_core::{cert_chain, CertChainInput};
+use risc0_zkvm::guest::env;
+
+// Any failure panics, which fails the execution: a receipt for this guest exists only if
+// the object's certificate chain leads to Apple's root and certifies its nonce. The panic
+// message carries a guest_core::abort code for the host to report.
+fn main() {
+    let input: CertChainInput = env::read();
+    let journal = cert_chain::run(input).unwrap_or_else(|e| panic!("{}", e.abort_message()));
+    env::commit_slice(&journal.encode());
+}
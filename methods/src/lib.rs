@@ -17,37 +17,217 @@ include!(concat!(env!("OUT_DIR"), "/methods.rs"));
 
 #[cfg(test)]
 mod tests {
-    use alloy_primitives::U256;
-    use alloy_sol_types::SolValue;
-    use risc0_zkvm::{default_executor, ExecutorEnv};
+    use base64::{engine::general_purpose, Engine};
+    use guest_core::{AssertionInput, AssertionJournal, AttestationJournal, VerificationPolicy, POLICY};
+    use p256::ecdsa::{signature::Signer, Signature, SigningKey};
+    use risc0_zkvm::sha::Digestible;
+    use risc0_zkvm::{default_executor, ExecutorEnv, ReceiptClaim};
+    use sha2::{Digest, Sha256};
 
-    #[test]
-    fn proves_even_number() {
-        let even_number = U256::from(1304);
+    // The assertion recorded in apps/fixtures.
+    const ASSERTION: &str = "omlzaWduYXR1cmVYRzBFAiA4+3V+mKaN4IvrhpAZug9nG5EgTLf9urMYoZIdDdt36AIhAMmP99pwoOaRqYCV4Q3Km4vQqebxCzfhdb2ow038AMWycWF1dGhlbnRpY2F0b3JEYXRhWCXXwWIjgKCprB/bVvaYf7bZmcJ35UnK1TNWcBhOwgdSS0AAAAAB";
+    const APP_ID: &str = "LMRM26A744.xyz.elus.aegis.app-attester";
+    const PUBLIC_KEY: &str = "0485e322caa0f981bc18cd53574f1dc761c139d15030a36b1b249cc158c822c4506b1574b6b1c5b5bfaf8e2a1dfe3deaa87c8802d863c8aac23aa990560a8a56b4";
+
+    /// An attestation journal for `public_key`, as the attestation guest would commit it.
+    fn attestation_journal(public_key: &str) -> Vec<u8> {
+        AttestationJournal {
+            policy: POLICY,
+            public_key: hex::decode(public_key).unwrap().try_into().unwrap(),
+            key_id: Sha256::digest(hex::decode(public_key).unwrap()).into(),
+            rp_id_hash: Sha256::digest(APP_ID).into(),
+            environment: AttestationJournal::DEVELOPMENT,
+            timestamp: 1_751_800_000,
+            time_signer: [0; 32],
+            allowlist_entry: guest_core::NO_ALLOWLIST,
+            cert_chain_image_id: [0; 32],
+            cert_chain_claim_digest: [0; 32],
+            input_digest: [0; 32],
+            policy_hash: Some([0; 32]),
+        }
+        .encode()
+    }
+
+    fn recorded_input() -> AssertionInput {
+        AssertionInput {
+            assertion: general_purpose::STANDARD.decode(ASSERTION).unwrap(),
+            client_data: b"x = 15".to_vec(),
+            app_id_hash: Sha256::digest(APP_ID).into(),
+            attestation_journal: attestation_journal(PUBLIC_KEY),
+            attestation_image_id: super::ATTESTATION_REST_ID,
+            previous_counter: None,
+            session_nonce: [0; 32],
+        }
+    }
+
+    /// A first assertion over `client_data` by a key the test holds, with its attestation.
+    fn signed_input(client_data: &[u8]) -> AssertionInput {
+        let key = SigningKey::from_slice(&[7; 32]).unwrap();
+        let public_key = hex::encode(key.verifying_key().to_encoded_point(false).as_bytes());
+
+        // rpIdHash, flags, counter 1.
+        let authenticator_data = [Sha256::digest(APP_ID).as_slice(), &[0x40, 0, 0, 0, 1]].concat();
+        let nonce = Sha256::digest([authenticator_data.as_slice(), &Sha256::digest(client_data)].concat());
+        let signature: Signature = key.sign(&nonce);
+        let signature = signature.to_der();
+
+        // {"signature": bytes, "authenticatorData": bytes}
+        let mut assertion = vec![0xa2, 0x69];
+        assertion.extend_from_slice(b"signature");
+        assertion.extend_from_slice(&[0x58, signature.as_bytes().len() as u8]);
+        assertion.extend_from_slice(signature.as_bytes());
+        assertion.push(0x71);
+        assertion.extend_from_slice(b"authenticatorData");
+        assertion.extend_from_slice(&[0x58, authenticator_data.len() as u8]);
+        assertion.extend_from_slice(&authenticator_data);
+
+        AssertionInput {
+            assertion,
+            client_data: client_data.to_vec(),
+            attestation_journal: attestation_journal(&public_key),
+            ..recorded_input()
+        }
+    }
+
+    /// Replaces the DER signature of an assertion laid out like the recorded one.
+    fn with_signature(assertion: &[u8], signature: &Signature) -> Vec<u8> {
+        // {"signature": bytes(len), ...}: the length is at 12, the signature follows it.
+        let rest = &assertion[13 + assertion[12] as usize..];
+        let der = signature.to_der();
+        [&assertion[..12], &[der.as_bytes().len() as u8], der.as_bytes(), rest].concat()
+    }
 
+    fn execute(input: &AssertionInput) -> Vec<u8> {
+        // The executor only records the assumption; proving would need a receipt for it.
+        let claim = ReceiptClaim::ok(input.attestation_image_id, input.attestation_journal.clone());
         let env = ExecutorEnv::builder()
-            .write_slice(&even_number.abi_encode())
+            .write(input)
+            .unwrap()
+            .add_assumption(claim)
             .build()
             .unwrap();
 
         // NOTE: Use the executor to run tests without proving.
-        let session_info = default_executor().execute(env, super::IS_EVEN_ELF).unwrap();
+        default_executor()
+            .execute(env, super::ASSERTION_ELF)
+            .unwrap()
+            .journal
+            .bytes
+    }
+
+    #[test]
+    fn verifies_the_assertion_it_is_given() {
+        let journal = execute(&recorded_input());
 
-        let x = U256::abi_decode(&session_info.journal.bytes).unwrap();
-        assert_eq!(x, even_number);
+        let journal = AssertionJournal::decode(&journal).unwrap();
+        let key = [[4].as_slice(), &journal.public_key_x, &journal.public_key_y].concat();
+        assert_eq!(hex::encode(key), PUBLIC_KEY);
+        assert_eq!(journal.rp_id_hash, <[u8; 32]>::from(Sha256::digest(APP_ID)));
+        assert_eq!(journal.counter, 1);
+        assert_eq!(journal.client_data_hash, <[u8; 32]>::from(Sha256::digest(b"x = 15")));
+        let claim = ReceiptClaim::ok(super::ATTESTATION_REST_ID, attestation_journal(PUBLIC_KEY));
+        assert_eq!(journal.attestation_claim_digest, <[u8; 32]>::from(claim.digest()));
+        assert_eq!(journal.challenge_hash, [0; 32], "`x = 15` is not JSON client data");
     }
 
     #[test]
-    #[should_panic(expected = "number is not even")]
-    fn rejects_odd_number() {
-        let odd_number = U256::from(75);
+    fn commits_the_digest_the_host_computes() {
+        for input in [recorded_input(), signed_input(br#"{"challenge": "a"}"#)] {
+            let journal = AssertionJournal::decode(&execute(&input)).unwrap();
+            assert_eq!(journal.input_digest, input.canonical_digest());
+        }
+    }
 
-        let env = ExecutorEnv::builder()
-            .write_slice(&odd_number.abi_encode())
-            .build()
-            .unwrap();
+    #[test]
+    fn commits_the_policy_the_host_expects() {
+        // What a server configured with the same app IDs as build.rs expects.
+        let app_ids: Vec<&str> = option_env!("APP_ID_ALLOWLIST")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|app_id| !app_id.is_empty())
+            .collect();
+        let journal = AssertionJournal::decode(&execute(&recorded_input())).unwrap();
+        assert_eq!(journal.policy_hash, Some(VerificationPolicy::for_app_ids(&app_ids).hash()));
+    }
 
-        // NOTE: Use the executor to run tests without proving.
-        default_executor().execute(env, super::IS_EVEN_ELF).unwrap();
+    #[test]
+    fn commits_the_challenge_of_json_client_data() {
+        let journal = execute(&signed_input(br#"{"challenge": "c2VydmVy", "amount": 5}"#));
+
+        let journal = AssertionJournal::decode(&journal).unwrap();
+        assert_eq!(journal.challenge_hash, <[u8; 32]>::from(Sha256::digest("c2VydmVy")));
+    }
+
+    #[test]
+    fn different_client_data_commits_different_journals() {
+        let a = AssertionJournal::decode(&execute(&signed_input(br#"{"challenge": "a"}"#))).unwrap();
+        let b = AssertionJournal::decode(&execute(&signed_input(br#"{"challenge": "b"}"#))).unwrap();
+        assert_ne!(a.client_data_hash, b.client_data_hash);
+        assert_ne!(a.challenge_hash, b.challenge_hash);
+    }
+
+    #[test]
+    #[should_panic(expected = "InvalidSignature")]
+    fn rejects_a_signature_passed_off_as_covering_other_client_data() {
+        let input = AssertionInput {
+            client_data: br#"{"challenge": "b"}"#.to_vec(),
+            ..signed_input(br#"{"challenge": "a"}"#)
+        };
+        execute(&input);
+    }
+
+    #[test]
+    fn commits_the_session_nonce_verbatim() {
+        let with_nonce = |session_nonce| {
+            let input = AssertionInput {
+                session_nonce,
+                ..recorded_input()
+            };
+            AssertionJournal::decode(&execute(&input)).unwrap()
+        };
+
+        let (a, b) = (with_nonce([1; 32]), with_nonce([2; 32]));
+        assert_eq!(a.session_nonce, [1; 32]);
+        assert_eq!(b.session_nonce, [2; 32]);
+        assert_ne!(a.encode(), b.encode());
+    }
+
+    #[test]
+    fn commits_only_the_low_s_form() {
+        let recorded = recorded_input();
+        let signature = Signature::from_der(&recorded.assertion[13..13 + recorded.assertion[12] as usize]).unwrap();
+        let low_s = signature.normalize_s().expect("the recorded device signature is high-S");
+        let malleated = AssertionInput {
+            assertion: with_signature(&recorded.assertion, &low_s),
+            ..recorded_input()
+        };
+
+        let journal = execute(&recorded);
+        assert_eq!(execute(&malleated), journal, "both forms verify and commit the same journal");
+        let journal = AssertionJournal::decode(&journal).unwrap();
+        assert_eq!(journal.signature_s, <[u8; 32]>::from(low_s.s().to_bytes()));
+    }
+
+    #[test]
+    #[should_panic(expected = "InvalidSignature")]
+    fn rejects_an_assertion_by_another_attested_key() {
+        // The generator of P-256, a valid key that did not sign the recorded assertion.
+        let other = "046b17d1f2e12c4247f8bce6e563a440f277037d812deb33a0f4a13945d898c2964fe342e2fe1a7f9b8ee7eb4a7c0f9e162bce33576b315ececbb6406837bf51f5";
+        let input = AssertionInput {
+            attestation_journal: attestation_journal(other),
+            ..recorded_input()
+        };
+        execute(&input);
+    }
+
+    #[test]
+    #[should_panic(expected = "InvalidCounter")]
+    fn rejects_a_replayed_counter() {
+        let input = AssertionInput {
+            previous_counter: Some(1),
+            ..recorded_input()
+        };
+        execute(&input);
     }
 }
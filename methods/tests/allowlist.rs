@@ -0,0 +1,45 @@
+//! Builds the guests with two different app ID allow-lists. Ignored by default because it
+//! compiles the guests twice from scratch; run it with `cargo test -p methods -- --ignored`.
+
+use std::path::Path;
+use std::process::Command;
+
+/// Builds this crate into `target_dir` with `APP_ID_ALLOWLIST` set to `app_ids` and returns
+/// the generated `methods.rs`.
+fn build_with_allowlist(target_dir: &Path, app_ids: &str) -> String {
+    let status = Command::new(env!("CARGO"))
+        .args(["build", "-p", "methods"])
+        .env("CARGO_TARGET_DIR", target_dir)
+        .env("APP_ID_ALLOWLIST", app_ids)
+        .env_remove("RISC0_SKIP_BUILD")
+        .status()
+        .unwrap();
+    assert!(status.success(), "building with APP_ID_ALLOWLIST={app_ids} failed");
+
+    std::fs::read_dir(target_dir.join("debug/build"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path().join("out/methods.rs"))
+        .find(|path| path.is_file())
+        .map(|path| std::fs::read_to_string(path).unwrap())
+        .expect("generated methods.rs")
+}
+
+/// The `<NAME>_ID` constant line of a generated `methods.rs`.
+fn image_id<'a>(methods: &'a str, name: &str) -> &'a str {
+    let prefix = format!("pub const {name}_ID");
+    methods
+        .lines()
+        .find(|line| line.trim_start().starts_with(&prefix))
+        .expect("image ID constant")
+}
+
+#[test]
+#[ignore]
+fn allowlists_change_the_image_ids() {
+    let dir = tempfile::tempdir().unwrap();
+    let ours = build_with_allowlist(&dir.path().join("ours"), "LMRM26A744.xyz.elus.aegis.app-attester");
+    let other = build_with_allowlist(&dir.path().join("other"), "TEAMID.com.example.app");
+    for name in ["ASSERTION", "ATTESTATION_REST"] {
+        assert_ne!(image_id(&ours, name), image_id(&other, name), "{name} image ID");
+    }
+}
@@ -0,0 +1,152 @@
+//! Reads journals of the layout the guests commit now and of the ones before it, so that a
+//! verifier upgraded ahead of the provers still reads what the previous guest builds prove,
+//! e.g. while a `TrustedImages` registry trusts both builds, and one left behind fails on a
+//! newer journal with the versions it does read.
+//!
+//! Each [`JournalCodec`] maps the leading version byte to its decoder, and every decoder
+//! returns the latest struct: a field the journal's version did not have is `None` there,
+//! as `policy_hash` is for an assertion journal of version 7. `guest_core`'s `decode`
+//! functions read the current layout only, which is what the guests need.
+//!
+//! Reading a journal is not accepting it: an assertion journal older than version 9 does
+//! not name the certificate chain guest behind its key, so the assertion verifiers reject
+//! it.
+
+use guest_core::{
+    AssertionJournal, AttestationJournal, CertChainJournal, JournalError, ASSERTION_JOURNAL_V7, ASSERTION_JOURNAL_V8,
+    ASSERTION_JOURNAL_V9, ATTESTATION_JOURNAL_V6, ATTESTATION_JOURNAL_V7, CERT_CHAIN_JOURNAL_V2,
+};
+
+use crate::VerifierError;
+
+/// Decodes a journal of one version into the latest struct.
+type Decoder<J> = fn(&[u8]) -> Result<J, JournalError>;
+
+/// The decoders of one kind of journal, by version byte.
+pub struct JournalCodec<J: 'static> {
+    decoders: &'static [(u8, Decoder<J>)],
+}
+
+/// Assertion journals: versions 7, 8 and 9.
+pub const ASSERTION_JOURNALS: JournalCodec<AssertionJournal> = JournalCodec {
+    decoders: &[
+        (ASSERTION_JOURNAL_V7, assertion_v7),
+        (ASSERTION_JOURNAL_V8, assertion_v8),
+        (ASSERTION_JOURNAL_V9, AssertionJournal::decode),
+    ],
+};
+
+/// Attestation journals: versions 6 and 7.
+pub const ATTESTATION_JOURNALS: JournalCodec<AttestationJournal> = JournalCodec {
+    decoders: &[(ATTESTATION_JOURNAL_V6, attestation_v6), (ATTESTATION_JOURNAL_V7, AttestationJournal::decode)],
+};
+
+/// Certificate chain journals: version 2 only. Version 1 lacked the `input_digest` the
+/// attestation guest binds its own input to, and no trusted build commits it.
+pub const CERT_CHAIN_JOURNALS: JournalCodec<CertChainJournal> =
+    JournalCodec { decoders: &[(CERT_CHAIN_JOURNAL_V2, CertChainJournal::decode)] };
+
+impl<J> JournalCodec<J> {
+    /// The versions this build reads, oldest first; the last is the one the guest commits.
+    pub fn supported(&self) -> Vec<u8> {
+        self.decoders.iter().map(|(version, _)| *version).collect()
+    }
+
+    pub fn supports(&self, version: u8) -> bool {
+        self.decoders.iter().any(|(supported, _)| *supported == version)
+    }
+
+    /// Decodes `bytes` with the decoder of its version byte. Fails with
+    /// [`VerifierError::UnsupportedJournalVersion`] for a version this build does not read,
+    /// and with [`VerifierError::JournalError`] for an empty journal or one that is not its
+    /// version's length.
+    pub fn decode(&self, bytes: &[u8]) -> Result<J, VerifierError> {
+        let found = *bytes.first().ok_or(JournalError::Empty)?;
+        let (_, decoder) = self
+            .decoders
+            .iter()
+            .find(|(version, _)| *version == found)
+            .ok_or_else(|| VerifierError::UnsupportedJournalVersion { found, supported: self.supported() })?;
+        Ok(decoder(bytes)?)
+    }
+}
+
+/// Version 7: version 9 without `policy_hash` and `cert_chain_image_id`.
+fn assertion_v7(bytes: &[u8]) -> Result<AssertionJournal, JournalError> {
+    let latest = with_fields_appended(bytes, AssertionJournal::LEN_V7, 2, ASSERTION_JOURNAL_V9)?;
+    Ok(AssertionJournal { policy_hash: None, cert_chain_image_id: None, ..AssertionJournal::decode(&latest)? })
+}
+
+/// Version 8: version 9 without `cert_chain_image_id`.
+fn assertion_v8(bytes: &[u8]) -> Result<AssertionJournal, JournalError> {
+    let latest = with_fields_appended(bytes, AssertionJournal::LEN_V8, 1, ASSERTION_JOURNAL_V9)?;
+    Ok(AssertionJournal { cert_chain_image_id: None, ..AssertionJournal::decode(&latest)? })
+}
+
+/// Version 6: version 7 without `policy_hash`.
+fn attestation_v6(bytes: &[u8]) -> Result<AttestationJournal, JournalError> {
+    let latest = with_fields_appended(bytes, AttestationJournal::LEN_V6, 1, ATTESTATION_JOURNAL_V7)?;
+    Ok(AttestationJournal { policy_hash: None, ..AttestationJournal::decode(&latest)? })
+}
+
+/// `bytes`, which must be `len` long, as the latest version: its version byte and 32 zero
+/// bytes for each of the `fields` fields appended since, which the caller then sets to
+/// `None`.
+fn with_fields_appended(bytes: &[u8], len: usize, fields: usize, latest_version: u8) -> Result<Vec<u8>, JournalError> {
+    if bytes.len() != len {
+        return Err(JournalError::Length { expected: len, actual: bytes.len() });
+    }
+    let mut latest = bytes.to_vec();
+    latest[0] = latest_version;
+    latest.resize(len + 32 * fields, 0);
+    Ok(latest)
+}
+
+#[cfg(test)]
+mod tests {
+    use guest_core::POLICY;
+
+    use super::*;
+
+    fn attestation() -> AttestationJournal {
+        AttestationJournal {
+            policy: POLICY,
+            public_key: [4; 65],
+            key_id: [1; 32],
+            rp_id_hash: [2; 32],
+            environment: AttestationJournal::PRODUCTION,
+            timestamp: 1_751_800_000,
+            time_signer: [3; 32],
+            allowlist_entry: 1,
+            cert_chain_image_id: [5; 32],
+            cert_chain_claim_digest: [6; 32],
+            input_digest: [7; 32],
+            policy_hash: Some([8; 32]),
+        }
+    }
+
+    #[test]
+    fn previous_versions_decode_without_their_missing_fields() {
+        let v6 = AttestationJournal { policy_hash: None, ..attestation() };
+        assert_eq!(ATTESTATION_JOURNALS.decode(&v6.encode()), Ok(v6));
+        assert_eq!(ATTESTATION_JOURNALS.decode(&attestation().encode()), Ok(attestation()));
+        assert_eq!(ATTESTATION_JOURNALS.supported(), [6, 7]);
+        assert!(ATTESTATION_JOURNALS.supports(6) && !ATTESTATION_JOURNALS.supports(5));
+    }
+
+    #[test]
+    fn each_version_must_have_its_own_length() {
+        let mut bytes = attestation().encode();
+        bytes[0] = ATTESTATION_JOURNAL_V6;
+        assert_eq!(
+            ATTESTATION_JOURNALS.decode(&bytes),
+            Err(JournalError::Length { expected: AttestationJournal::LEN_V6, actual: AttestationJournal::LEN }.into())
+        );
+        assert_eq!(ATTESTATION_JOURNALS.decode(&[]), Err(JournalError::Empty.into()));
+        // A certificate chain journal is version 2, which no attestation journal is.
+        assert_eq!(
+            ATTESTATION_JOURNALS.decode(&[CERT_CHAIN_JOURNAL_V2; CertChainJournal::LEN]),
+            Err(VerifierError::UnsupportedJournalVersion { found: 2, supported: vec![6, 7] })
+        );
+    }
+}
@@ -0,0 +1,449 @@
+//! Verifies MobiScale receipts and decodes their journals, for services that check proofs
+//! but do not produce them.
+//!
+//! Unlike `apps`, this crate does not depend on `methods`, so building it compiles neither
+//! the guests nor the prover: risc0-zkvm is linked without `prove`. Image IDs are therefore
+//! passed in rather than linked in; take them from the prover's `get_build_info`, or the
+//! `--version-full` output of its binaries, as hex, and parse them with [`parse_image_id`].
+//!
+//! The journal layouts are `guest_core`'s and are re-exported here, as is
+//! [`VerificationPolicy`]: build one from the server's config and pass it to the
+//! `verify_*_receipt` functions to reject receipts of guests built with another policy.
+//! Errors carry the same
+//! stable codes and JSON shape as the matching `Risc0Error` variants of `apps`, which
+//! re-exports this crate's result types and verifies through it.
+//!
+//! Journals are decoded through [`codec`], which reads the previous layout of each journal
+//! as well as the current one.
+
+use risc0_zkvm::sha::Digest;
+use risc0_zkvm::Receipt;
+use serde::{Deserialize, Serialize};
+
+pub mod codec;
+
+pub use codec::{JournalCodec, ASSERTION_JOURNALS, ATTESTATION_JOURNALS, CERT_CHAIN_JOURNALS};
+pub use guest_core::{
+    AssertionInput, AssertionJournal, AttestationInput, AttestationJournal, CertChainInput, CertChainJournal,
+    JournalError, Policy, VerificationPolicy, ASSERTION_JOURNAL_V7, ASSERTION_JOURNAL_V8, ASSERTION_JOURNAL_V9,
+    ATTESTATION_JOURNAL_V6, ATTESTATION_JOURNAL_V7, CERT_CHAIN_JOURNAL_V2, NO_ALLOWLIST,
+};
+
+#[cfg(feature = "uniffi")]
+uniffi::setup_scaffolding!();
+
+/// Why a receipt was not accepted. In JSON, `{"kind": "<variant>", "detail": ...}`, like
+/// `Risc0Error`.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(tag = "kind", content = "detail")]
+pub enum VerifierError {
+    #[error("Failed to decode journal: {0}")]
+    JournalError(String),
+    #[error("Invalid receipt: {0}")]
+    InvalidReceipt(String),
+    #[error("Receipt verification failed: {0}")]
+    VerificationFailed(String),
+    /// The journal's version byte is none of the `supported` ones, e.g. a journal of a guest
+    /// newer than this build.
+    #[error("Unsupported journal version {found}, this build reads {supported:?}")]
+    UnsupportedJournalVersion { found: u8, supported: Vec<u8> },
+}
+
+impl VerifierError {
+    /// Stable numeric code, the one of the `Risc0Error` variant of the same name.
+    pub fn code(&self) -> u16 {
+        match self {
+            VerifierError::JournalError(_) => 2003,
+            VerifierError::InvalidReceipt(_) => 2004,
+            VerifierError::VerificationFailed(_) => 2005,
+            VerifierError::UnsupportedJournalVersion { .. } => 2011,
+        }
+    }
+}
+
+impl From<JournalError> for VerifierError {
+    fn from(error: JournalError) -> Self {
+        VerifierError::JournalError(error.to_string())
+    }
+}
+
+/// Signature, public key and commitments from a verified assertion journal.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "uniffi", derive(uniffi::Record))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct SignatureData {
+    #[serde(with = "appattest_rs::json::bytes")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+    pub signature_r: Vec<u8>,
+    #[serde(with = "appattest_rs::json::bytes")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+    pub signature_s: Vec<u8>,
+    #[serde(with = "appattest_rs::json::bytes")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+    pub public_key_x: Vec<u8>,
+    #[serde(with = "appattest_rs::json::bytes")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+    pub public_key_y: Vec<u8>,
+    /// `SHA256` of the client data the signature covers.
+    #[serde(with = "appattest_rs::json::bytes")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+    pub client_data_hash: Vec<u8>,
+    /// `SHA256` of the challenge in JSON client data, all zeros if it carries none.
+    #[serde(with = "appattest_rs::json::bytes")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+    pub challenge_hash: Vec<u8>,
+    /// Session nonce the proof was requested with; the verifier checks it against the one it
+    /// issued.
+    #[serde(with = "appattest_rs::json::bytes")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+    pub session_nonce: Vec<u8>,
+    /// Policy the guest committed. `None` only for signature data that did not come out of
+    /// a journal, such as hand-written JSON.
+    pub policy: Option<PolicyData>,
+}
+
+impl From<&AssertionJournal> for SignatureData {
+    fn from(journal: &AssertionJournal) -> Self {
+        Self {
+            signature_r: journal.signature_r.to_vec(),
+            signature_s: journal.signature_s.to_vec(),
+            public_key_x: journal.public_key_x.to_vec(),
+            public_key_y: journal.public_key_y.to_vec(),
+            client_data_hash: journal.client_data_hash.to_vec(),
+            challenge_hash: journal.challenge_hash.to_vec(),
+            session_nonce: journal.session_nonce.to_vec(),
+            policy: Some(journal.policy.into()),
+        }
+    }
+}
+
+/// The verification policy committed by a guest, as [`Policy`] in a form the bindings carry.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "uniffi", derive(uniffi::Record))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct PolicyData {
+    pub appattest_major: u8,
+    pub appattest_minor: u8,
+    pub appattest_patch: u8,
+    /// [`Policy::COUNTER_STRICTLY_INCREASING`] is the only policy so far.
+    pub counter_policy: u8,
+    pub low_s: bool,
+    /// Bit set of [`Policy::PRODUCTION`] and [`Policy::DEVELOPMENT`].
+    pub environments: u8,
+}
+
+impl From<Policy> for PolicyData {
+    fn from(policy: Policy) -> Self {
+        let (appattest_major, appattest_minor, appattest_patch) = policy.appattest_version;
+        Self {
+            appattest_major,
+            appattest_minor,
+            appattest_patch,
+            counter_policy: policy.counter_policy,
+            low_s: policy.low_s,
+            environments: policy.environments,
+        }
+    }
+}
+
+impl From<PolicyData> for Policy {
+    fn from(policy: PolicyData) -> Self {
+        Policy {
+            appattest_version: (policy.appattest_major, policy.appattest_minor, policy.appattest_patch),
+            counter_policy: policy.counter_policy,
+            low_s: policy.low_s,
+            environments: policy.environments,
+        }
+    }
+}
+
+/// A [`VerificationPolicy`] in a form the bindings carry.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "uniffi", derive(uniffi::Record))]
+pub struct VerificationPolicyData {
+    pub policy: PolicyData,
+    /// `SHA256` of each allowed app ID, in allow-list order; empty for a guest built without
+    /// an allow-list.
+    pub app_id_hashes: Vec<Vec<u8>>,
+}
+
+impl From<&VerificationPolicy> for VerificationPolicyData {
+    fn from(policy: &VerificationPolicy) -> Self {
+        Self {
+            policy: policy.policy.into(),
+            app_id_hashes: policy.app_id_hashes.iter().map(|hash| hash.to_vec()).collect(),
+        }
+    }
+}
+
+impl TryFrom<VerificationPolicyData> for VerificationPolicy {
+    type Error = VerifierError;
+
+    /// Fails for an app ID hash that is not 32 bytes.
+    fn try_from(data: VerificationPolicyData) -> Result<Self, VerifierError> {
+        let app_id_hashes = data
+            .app_id_hashes
+            .into_iter()
+            .map(|hash| {
+                <[u8; 32]>::try_from(hash).map_err(|hash| {
+                    VerifierError::VerificationFailed(format!("app ID hash is {} bytes, expected 32", hash.len()))
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(VerificationPolicy { policy: data.policy.into(), app_id_hashes })
+    }
+}
+
+/// A verified attestation receipt's journal and the policy it was produced under.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "uniffi", derive(uniffi::Record))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct VerifiedAttestation {
+    #[serde(with = "appattest_rs::json::bytes")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+    pub journal: Vec<u8>,
+    pub policy: PolicyData,
+}
+
+/// The input a guest was given, for [`verify_receipt_matches_inputs`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProvenInput {
+    CertChain(CertChainInput),
+    Attestation(AttestationInput),
+    Assertion(AssertionInput),
+}
+
+impl ProvenInput {
+    /// The `input_digest` the input's guest commits, see `guest_core::digest`. Fails for an
+    /// attestation input whose certificate chain journal does not decode.
+    pub fn canonical_digest(&self) -> Result<[u8; 32], VerifierError> {
+        match self {
+            ProvenInput::CertChain(input) => Ok(input.canonical_digest()),
+            ProvenInput::Attestation(input) => Ok(input.canonical_digest()?),
+            ProvenInput::Assertion(input) => Ok(input.canonical_digest()),
+        }
+    }
+}
+
+/// Parses an image ID given as 64 hex digits, with or without `0x`, as `get_build_info`
+/// prints it.
+pub fn parse_image_id(hex: &str) -> Result<Digest, VerifierError> {
+    hex::decode(hex.trim_start_matches("0x"))
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .map(Digest::from)
+        .ok_or_else(|| VerifierError::VerificationFailed(format!("invalid image ID {hex}")))
+}
+
+/// Decodes a bincode-serialized receipt as written by the prove functions.
+pub fn deserialize_receipt(bytes: &[u8]) -> Result<Receipt, VerifierError> {
+    bincode::deserialize(bytes).map_err(|e| VerifierError::InvalidReceipt(e.to_string()))
+}
+
+/// Decodes `bytes` and verifies the receipt against `expected_image_id`.
+pub fn verify_receipt(bytes: &[u8], expected_image_id: impl Into<Digest>) -> Result<Receipt, VerifierError> {
+    let receipt = deserialize_receipt(bytes)?;
+    receipt
+        .verify(expected_image_id)
+        .map_err(|e| VerifierError::VerificationFailed(e.to_string()))?;
+    Ok(receipt)
+}
+
+/// Decodes the journal committed by the assertion guest with [`ASSERTION_JOURNALS`],
+/// dispatching on its version byte.
+pub fn decode_assertion_journal(journal: &[u8]) -> Result<AssertionJournal, VerifierError> {
+    ASSERTION_JOURNALS.decode(journal)
+}
+
+/// Decodes the journal committed by the attestation guest with [`ATTESTATION_JOURNALS`],
+/// dispatching on its version byte.
+pub fn decode_attestation_journal(journal: &[u8]) -> Result<AttestationJournal, VerifierError> {
+    ATTESTATION_JOURNALS.decode(journal)
+}
+
+/// Decodes the journal committed by the certificate chain guest with
+/// [`CERT_CHAIN_JOURNALS`], dispatching on its version byte.
+pub fn decode_cert_chain_journal(journal: &[u8]) -> Result<CertChainJournal, VerifierError> {
+    CERT_CHAIN_JOURNALS.decode(journal)
+}
+
+/// Verifies an assertion receipt against `image_id`, checks that its journal relies on the
+/// attestation guest `attestation_image_id` over the certificate chain guest
+/// `cert_chain_image_id` and returns the signature data from it. With an `expected_policy`,
+/// also rejects a journal committing another policy, see [`check_policy_hash`].
+pub fn verify_assertion_receipt(
+    bytes: &[u8],
+    image_id: impl Into<Digest>,
+    attestation_image_id: impl Into<Digest>,
+    cert_chain_image_id: impl Into<Digest>,
+    expected_policy: Option<&VerificationPolicy>,
+) -> Result<SignatureData, VerifierError> {
+    let receipt = verify_receipt(bytes, image_id)?;
+    let journal = decode_assertion_journal(&receipt.journal.bytes)?;
+    check_attestation_image(&journal, attestation_image_id)?;
+    check_assertion_cert_chain_image(&journal, cert_chain_image_id)?;
+    if let Some(expected) = expected_policy {
+        check_policy_hash(journal.policy_hash.as_ref(), expected)?;
+    }
+    Ok(SignatureData::from(&journal))
+}
+
+/// Verifies an attestation receipt against `image_id`, checks that its journal relies on the
+/// certificate chain guest `cert_chain_image_id` and, with an `expected_policy`, that it
+/// commits that policy, and returns the journal and its policy.
+pub fn verify_attestation_receipt(
+    bytes: &[u8],
+    image_id: impl Into<Digest>,
+    cert_chain_image_id: impl Into<Digest>,
+    expected_policy: Option<&VerificationPolicy>,
+) -> Result<VerifiedAttestation, VerifierError> {
+    let receipt = verify_receipt(bytes, image_id)?;
+    let journal = decode_attestation_journal(&receipt.journal.bytes)?;
+    check_cert_chain_image(&journal, cert_chain_image_id)?;
+    if let Some(expected) = expected_policy {
+        check_policy_hash(journal.policy_hash.as_ref(), expected)?;
+    }
+    Ok(VerifiedAttestation { journal: receipt.journal.bytes, policy: journal.policy.into() })
+}
+
+/// Whether `receipt`'s journal commits the digest of `inputs`, i.e. whether the receipt
+/// answers a request carrying exactly these inputs rather than another one.
+///
+/// Only the journal is read: verify the receipt itself with [`verify_receipt`] as well. A
+/// journal that does not decode as that of `inputs`' guest does not match.
+pub fn verify_receipt_matches_inputs(receipt: &Receipt, inputs: &ProvenInput) -> bool {
+    let journal = &receipt.journal.bytes;
+    let committed = match inputs {
+        ProvenInput::CertChain(_) => decode_cert_chain_journal(journal).map(|journal| journal.input_digest),
+        ProvenInput::Attestation(_) => decode_attestation_journal(journal).map(|journal| journal.input_digest),
+        ProvenInput::Assertion(_) => decode_assertion_journal(journal).map(|journal| journal.input_digest),
+    };
+    matches!((committed, inputs.canonical_digest()), (Ok(committed), Ok(expected)) if committed == expected)
+}
+
+/// Rejects an attestation journal relying on a certificate chain receipt of another guest
+/// than `expected`.
+///
+/// The attestation guest verifies a chain receipt of whichever image ID the host names, so a
+/// verified attestation receipt alone does not show that Apple's chain was checked.
+pub fn check_cert_chain_image(journal: &AttestationJournal, expected: impl Into<Digest>) -> Result<(), VerifierError> {
+    let expected = expected.into();
+    if Digest::from(journal.cert_chain_image_id) != expected {
+        return Err(VerifierError::VerificationFailed(format!(
+            "attestation relies on certificate chain guest {}, expected {expected}",
+            hex::encode(journal.cert_chain_image_id)
+        )));
+    }
+    Ok(())
+}
+
+/// Rejects an assertion journal whose key comes from an attestation receipt of another guest
+/// than `expected`.
+///
+/// The assertion guest verifies an attestation receipt of whichever image ID the host names
+/// and checks the signature against the key in its journal, so a verified assertion receipt
+/// alone does not show that the key was attested.
+pub fn check_attestation_image(journal: &AssertionJournal, expected: impl Into<Digest>) -> Result<(), VerifierError> {
+    let expected = expected.into();
+    if Digest::from(journal.attestation_image_id) != expected {
+        return Err(VerifierError::VerificationFailed(format!(
+            "assertion relies on attestation guest {}, expected {expected}",
+            hex::encode(journal.attestation_image_id)
+        )));
+    }
+    Ok(())
+}
+
+/// Rejects an assertion journal whose key was certified by a certificate chain receipt of
+/// another guest than `expected`, or that does not name one because its version predates
+/// `cert_chain_image_id`.
+///
+/// The attestation guest behind the key verifies a chain receipt of whichever image ID its
+/// host names, like the assertion guest does with the attestation receipt, and the
+/// assertion journal commits the attestation journal only by its claim digest.
+pub fn check_assertion_cert_chain_image(
+    journal: &AssertionJournal,
+    expected: impl Into<Digest>,
+) -> Result<(), VerifierError> {
+    let expected = expected.into();
+    let Some(committed) = journal.cert_chain_image_id else {
+        return Err(VerifierError::VerificationFailed(format!(
+            "assertion journal predates certificate chain image IDs, expected certificate chain guest {expected}"
+        )));
+    };
+    if Digest::from(committed) != expected {
+        return Err(VerifierError::VerificationFailed(format!(
+            "assertion relies on certificate chain guest {}, expected {expected}",
+            hex::encode(committed)
+        )));
+    }
+    Ok(())
+}
+
+/// Rejects a journal whose committed `policy_hash` is not that of `expected`, or that has
+/// none because its version predates it.
+///
+/// The guest commits the hash of the policy it was built with, its `POLICY` and allow-list,
+/// so a mismatch means the receipt comes from a guest that verifies differently than the
+/// server is configured to, even if its image is trusted.
+pub fn check_policy_hash(committed: Option<&[u8; 32]>, expected: &VerificationPolicy) -> Result<(), VerifierError> {
+    let expected = expected.hash();
+    let Some(committed) = committed else {
+        return Err(VerifierError::VerificationFailed(format!(
+            "journal predates policy hashes, expected verification policy {}",
+            hex::encode(expected)
+        )));
+    };
+    if *committed != expected {
+        return Err(VerifierError::VerificationFailed(format!(
+            "journal commits verification policy {}, expected {}",
+            hex::encode(committed),
+            hex::encode(expected)
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_ids_parse_from_hex() {
+        let words = [1, 2, 3, 4, 5, 6, 7, 8];
+        let hex = hex::encode(Digest::from(words).as_bytes());
+        assert_eq!(parse_image_id(&hex), Ok(Digest::from(words)));
+        assert_eq!(parse_image_id(&format!("0x{hex}")), Ok(Digest::from(words)));
+        assert!(parse_image_id(&hex[2..]).is_err());
+        assert!(parse_image_id("zz").is_err());
+    }
+
+    #[test]
+    fn errors_keep_their_codes_and_json() {
+        let error = VerifierError::from(JournalError::Empty);
+        assert_eq!(error.code(), 2003);
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({"kind": "JournalError", "detail": "journal is empty"})
+        );
+        assert_eq!(VerifierError::InvalidReceipt(String::new()).code(), 2004);
+        assert_eq!(VerifierError::VerificationFailed(String::new()).code(), 2005);
+
+        let error = VerifierError::UnsupportedJournalVersion { found: 9, supported: vec![7, 8] };
+        assert_eq!(error.code(), 2011);
+        assert_eq!(error.to_string(), "Unsupported journal version 9, this build reads [7, 8]");
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({"kind": "UnsupportedJournalVersion", "detail": {"found": 9, "supported": [7, 8]}})
+        );
+    }
+
+    #[test]
+    fn garbage_is_an_invalid_receipt() {
+        assert!(matches!(verify_receipt(&[0xff; 16], [0; 8]), Err(VerifierError::InvalidReceipt(_))));
+    }
+}
@@ -0,0 +1,117 @@
+//! Decodes the journals in `fixtures/`, one of each version a [`JournalCodec`] reads, as
+//! committed by the guest builds that wrote them. Run the ignored `writes_the_fixtures` to
+//! rewrite the current versions' after their layout changes; the previous versions' are
+//! frozen, since no guest of this build commits them.
+
+use mobiscale_verifier::{
+    decode_assertion_journal, decode_attestation_journal, AssertionJournal, AttestationJournal, Policy, VerifierError,
+    ASSERTION_JOURNALS, ATTESTATION_JOURNALS, NO_ALLOWLIST,
+};
+
+const ASSERTION_V7: &[u8] = include_bytes!("../fixtures/assertion_journal_v7.bin");
+const ASSERTION_V8: &[u8] = include_bytes!("../fixtures/assertion_journal_v8.bin");
+const ASSERTION_V9: &[u8] = include_bytes!("../fixtures/assertion_journal_v9.bin");
+const ATTESTATION_V6: &[u8] = include_bytes!("../fixtures/attestation_journal_v6.bin");
+const ATTESTATION_V7: &[u8] = include_bytes!("../fixtures/attestation_journal_v7.bin");
+
+fn policy() -> Policy {
+    Policy {
+        appattest_version: (0, 2, 1),
+        counter_policy: Policy::COUNTER_STRICTLY_INCREASING,
+        low_s: true,
+        environments: Policy::PRODUCTION | Policy::DEVELOPMENT,
+    }
+}
+
+fn assertion() -> AssertionJournal {
+    AssertionJournal {
+        policy: policy(),
+        rp_id_hash: [1; 32],
+        public_key_x: [2; 32],
+        public_key_y: [3; 32],
+        signature_r: [4; 32],
+        signature_s: [5; 32],
+        counter: 1,
+        client_data_hash: [6; 32],
+        attestation_image_id: [7; 32],
+        attestation_claim_digest: [8; 32],
+        challenge_hash: [9; 32],
+        allowlist_entry: NO_ALLOWLIST,
+        session_nonce: [10; 32],
+        input_digest: [11; 32],
+        policy_hash: Some([12; 32]),
+        cert_chain_image_id: Some([13; 32]),
+    }
+}
+
+fn attestation() -> AttestationJournal {
+    let mut public_key = [1; 65];
+    public_key[0] = 4;
+    AttestationJournal {
+        policy: policy(),
+        public_key,
+        key_id: [2; 32],
+        rp_id_hash: [3; 32],
+        environment: AttestationJournal::PRODUCTION,
+        timestamp: 1_751_800_000,
+        time_signer: [4; 32],
+        allowlist_entry: 0,
+        cert_chain_image_id: [5; 32],
+        cert_chain_claim_digest: [6; 32],
+        input_digest: [7; 32],
+        policy_hash: Some([8; 32]),
+    }
+}
+
+#[test]
+fn checked_in_journals_of_each_version_decode() {
+    assert_eq!(ASSERTION_JOURNALS.decode(ASSERTION_V9), Ok(assertion()));
+    assert_eq!(
+        ASSERTION_JOURNALS.decode(ASSERTION_V8),
+        Ok(AssertionJournal { cert_chain_image_id: None, ..assertion() })
+    );
+    assert_eq!(
+        ASSERTION_JOURNALS.decode(ASSERTION_V7),
+        Ok(AssertionJournal { policy_hash: None, cert_chain_image_id: None, ..assertion() })
+    );
+    assert_eq!(ATTESTATION_JOURNALS.decode(ATTESTATION_V7), Ok(attestation()));
+    assert_eq!(
+        ATTESTATION_JOURNALS.decode(ATTESTATION_V6),
+        Ok(AttestationJournal { policy_hash: None, ..attestation() })
+    );
+
+    // The decode functions go through the codecs.
+    assert_eq!(decode_assertion_journal(ASSERTION_V7), ASSERTION_JOURNALS.decode(ASSERTION_V7));
+    assert_eq!(decode_attestation_journal(ATTESTATION_V6), ATTESTATION_JOURNALS.decode(ATTESTATION_V6));
+}
+
+#[test]
+fn previous_versions_reencode_as_checked_in() {
+    assert_eq!(ASSERTION_JOURNALS.decode(ASSERTION_V8).unwrap().encode(), ASSERTION_V8);
+    assert_eq!(ASSERTION_JOURNALS.decode(ASSERTION_V7).unwrap().encode(), ASSERTION_V7);
+    assert_eq!(ATTESTATION_JOURNALS.decode(ATTESTATION_V6).unwrap().encode(), ATTESTATION_V6);
+}
+
+#[test]
+fn unknown_versions_are_rejected_with_the_supported_ones() {
+    let mut v10 = ASSERTION_V9.to_vec();
+    v10[0] = 10;
+    assert_eq!(
+        decode_assertion_journal(&v10),
+        Err(VerifierError::UnsupportedJournalVersion { found: 10, supported: vec![7, 8, 9] })
+    );
+    let mut v9 = ATTESTATION_V7.to_vec();
+    v9[0] = 9;
+    assert_eq!(
+        decode_attestation_journal(&v9),
+        Err(VerifierError::UnsupportedJournalVersion { found: 9, supported: vec![6, 7] })
+    );
+}
+
+#[test]
+#[ignore = "rewrites the fixtures"]
+fn writes_the_fixtures() {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures");
+    std::fs::write(format!("{dir}/assertion_journal_v9.bin"), assertion().encode()).unwrap();
+    std::fs::write(format!("{dir}/attestation_journal_v7.bin"), attestation().encode()).unwrap();
+}
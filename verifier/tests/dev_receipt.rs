@@ -0,0 +1,87 @@
+//! Verifies `fixtures/dev_assertion_receipt.bin`, a fake (dev mode) receipt of the assertion
+//! journal below claimed by the made-up image [`IMAGE_ID`], relying on the made-up
+//! attestation image [`ATTESTATION_IMAGE_ID`] and certificate chain image
+//! [`CERT_CHAIN_IMAGE_ID`]. Run the ignored
+//! `writes_the_fixture` to rewrite it after a risc0-zkvm upgrade changes the receipt encoding.
+
+use mobiscale_verifier::{
+    decode_assertion_journal, deserialize_receipt, verify_assertion_receipt, verify_attestation_receipt,
+    verify_receipt, AssertionJournal, Policy, VerifierError, NO_ALLOWLIST,
+};
+use risc0_zkvm::{FakeReceipt, InnerReceipt, Receipt, ReceiptClaim};
+
+const IMAGE_ID: [u32; 8] = [1, 2, 3, 4, 5, 6, 7, 8];
+const ATTESTATION_IMAGE_ID: [u8; 32] = [7; 32];
+const CERT_CHAIN_IMAGE_ID: [u8; 32] = [13; 32];
+const FIXTURE: &[u8] = include_bytes!("../fixtures/dev_assertion_receipt.bin");
+
+fn journal() -> AssertionJournal {
+    AssertionJournal {
+        policy: Policy {
+            appattest_version: (0, 2, 1),
+            counter_policy: Policy::COUNTER_STRICTLY_INCREASING,
+            low_s: true,
+            environments: Policy::PRODUCTION | Policy::DEVELOPMENT,
+        },
+        rp_id_hash: [1; 32],
+        public_key_x: [2; 32],
+        public_key_y: [3; 32],
+        signature_r: [4; 32],
+        signature_s: [5; 32],
+        counter: 1,
+        client_data_hash: [6; 32],
+        attestation_image_id: ATTESTATION_IMAGE_ID,
+        attestation_claim_digest: [8; 32],
+        challenge_hash: [9; 32],
+        allowlist_entry: NO_ALLOWLIST,
+        session_nonce: [10; 32],
+        input_digest: [11; 32],
+        policy_hash: Some([12; 32]),
+        cert_chain_image_id: Some(CERT_CHAIN_IMAGE_ID),
+    }
+}
+
+#[test]
+fn checked_in_receipt_verifies() {
+    std::env::set_var("RISC0_DEV_MODE", "1");
+    let receipt = verify_receipt(FIXTURE, IMAGE_ID).unwrap();
+    assert_eq!(decode_assertion_journal(&receipt.journal.bytes).unwrap(), journal());
+
+    let signature =
+        verify_assertion_receipt(FIXTURE, IMAGE_ID, ATTESTATION_IMAGE_ID, CERT_CHAIN_IMAGE_ID, None).unwrap();
+    assert_eq!(signature.public_key_x, vec![2; 32]);
+    assert_eq!(signature.session_nonce, vec![10; 32]);
+    assert_eq!(signature.policy, Some(journal().policy.into()));
+}
+
+#[test]
+fn checked_in_receipt_is_bound_to_its_image_and_guest() {
+    std::env::set_var("RISC0_DEV_MODE", "1");
+    assert!(matches!(verify_receipt(FIXTURE, [8; 8]), Err(VerifierError::VerificationFailed(_))));
+    // Its key comes from an attestation receipt of another guest than the one trusted.
+    assert!(matches!(
+        verify_assertion_receipt(FIXTURE, IMAGE_ID, [8u8; 32], CERT_CHAIN_IMAGE_ID, None),
+        Err(VerifierError::VerificationFailed(msg)) if msg.contains(&hex::encode(ATTESTATION_IMAGE_ID))
+    ));
+    // And that attestation receipt relied on a certificate chain receipt of another guest.
+    assert!(matches!(
+        verify_assertion_receipt(FIXTURE, IMAGE_ID, ATTESTATION_IMAGE_ID, [8u8; 32], None),
+        Err(VerifierError::VerificationFailed(msg)) if msg.contains(&hex::encode(CERT_CHAIN_IMAGE_ID))
+    ));
+    // An assertion journal is not an attestation journal.
+    assert!(matches!(
+        verify_attestation_receipt(FIXTURE, IMAGE_ID, [0; 8], None),
+        Err(VerifierError::JournalError(_))
+    ));
+    assert!(matches!(deserialize_receipt(&FIXTURE[..100]), Err(VerifierError::InvalidReceipt(_))));
+}
+
+#[test]
+#[ignore = "rewrites the fixture"]
+fn writes_the_fixture() {
+    let journal = journal().encode();
+    let claim = ReceiptClaim::ok(IMAGE_ID, journal.clone());
+    let receipt = Receipt::new(InnerReceipt::Fake(FakeReceipt::new(claim)), journal);
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/dev_assertion_receipt.bin");
+    std::fs::write(path, bincode::serialize(&receipt).unwrap()).unwrap();
+}
@@ -0,0 +1,100 @@
+//! Matches receipts with the inputs of the request they were attached to, through the
+//! `input_digest` their journals commit.
+
+use mobiscale_verifier::{
+    verify_receipt_matches_inputs, AssertionInput, AssertionJournal, CertChainInput, CertChainJournal, Policy,
+    ProvenInput, NO_ALLOWLIST,
+};
+use risc0_zkvm::{FakeReceipt, InnerReceipt, Receipt, ReceiptClaim};
+
+fn receipt(journal: Vec<u8>) -> Receipt {
+    let claim = ReceiptClaim::ok([1, 2, 3, 4, 5, 6, 7, 8], journal.clone());
+    Receipt::new(InnerReceipt::Fake(FakeReceipt::new(claim)), journal)
+}
+
+fn assertion_input() -> AssertionInput {
+    AssertionInput {
+        assertion: vec![0xa2; 40],
+        client_data: br#"{"challenge":"c2Vzc2lvbg","action":"transfer"}"#.to_vec(),
+        app_id_hash: [1; 32],
+        attestation_journal: vec![2; 64],
+        attestation_image_id: [3; 8],
+        previous_counter: Some(4),
+        session_nonce: [5; 32],
+    }
+}
+
+fn policy() -> Policy {
+    Policy {
+        appattest_version: (0, 2, 1),
+        counter_policy: Policy::COUNTER_STRICTLY_INCREASING,
+        low_s: true,
+        environments: Policy::PRODUCTION | Policy::DEVELOPMENT,
+    }
+}
+
+fn assertion_receipt(input_digest: [u8; 32]) -> Receipt {
+    let journal = AssertionJournal {
+        policy: policy(),
+        rp_id_hash: [1; 32],
+        public_key_x: [2; 32],
+        public_key_y: [3; 32],
+        signature_r: [4; 32],
+        signature_s: [5; 32],
+        counter: 5,
+        client_data_hash: [6; 32],
+        attestation_image_id: [7; 32],
+        attestation_claim_digest: [8; 32],
+        challenge_hash: [9; 32],
+        allowlist_entry: NO_ALLOWLIST,
+        session_nonce: [5; 32],
+        input_digest,
+        policy_hash: Some([12; 32]),
+        cert_chain_image_id: Some([13; 32]),
+    };
+    receipt(journal.encode())
+}
+
+#[test]
+fn receipts_match_the_inputs_they_commit() {
+    let input = ProvenInput::Assertion(assertion_input());
+    let receipt = assertion_receipt(input.canonical_digest().unwrap());
+    assert!(verify_receipt_matches_inputs(&receipt, &input));
+}
+
+#[test]
+fn any_changed_byte_of_client_data_does_not_match() {
+    let receipt = assertion_receipt(ProvenInput::Assertion(assertion_input()).canonical_digest().unwrap());
+    let len = assertion_input().client_data.len();
+    for index in 0..len {
+        let mut changed = assertion_input();
+        changed.client_data[index] ^= 0x01;
+        assert!(!verify_receipt_matches_inputs(&receipt, &ProvenInput::Assertion(changed)), "byte {index}");
+    }
+}
+
+#[test]
+fn receipts_of_another_guest_do_not_match() {
+    let input = CertChainInput {
+        attestation: vec![1; 32],
+        challenge: vec![2; 32],
+        timestamp: 1_751_800_000,
+        time_token: None,
+    };
+    let journal = CertChainJournal {
+        policy: policy(),
+        public_key: [4; 65],
+        leaf_fingerprint: [0; 32],
+        auth_data_hash: [0; 32],
+        timestamp: input.timestamp,
+        time_signer: [0; 32],
+        input_digest: input.canonical_digest(),
+    };
+    let cert_chain_receipt = receipt(journal.encode());
+    assert!(verify_receipt_matches_inputs(&cert_chain_receipt, &ProvenInput::CertChain(input.clone())));
+
+    // The same digest in an assertion journal, and a journal that does not decode.
+    let digest = input.canonical_digest();
+    assert!(!verify_receipt_matches_inputs(&assertion_receipt(digest), &ProvenInput::CertChain(input.clone())));
+    assert!(!verify_receipt_matches_inputs(&receipt(vec![0xff; 8]), &ProvenInput::CertChain(input)));
+}
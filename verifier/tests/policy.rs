@@ -0,0 +1,121 @@
+//! Checks receipts against the [`VerificationPolicy`] the server expects, through the
+//! `policy_hash` their journals commit.
+
+use mobiscale_verifier::{
+    check_policy_hash, verify_assertion_receipt, verify_attestation_receipt, AssertionJournal, AttestationJournal,
+    Policy, PolicyData, VerificationPolicy, VerificationPolicyData, VerifierError, NO_ALLOWLIST,
+};
+use risc0_zkvm::{FakeReceipt, InnerReceipt, Receipt, ReceiptClaim};
+
+const IMAGE_ID: [u32; 8] = [1, 2, 3, 4, 5, 6, 7, 8];
+const CERT_CHAIN_IMAGE_ID: [u32; 8] = [2; 8];
+const ATTESTATION_IMAGE_ID: [u32; 8] = [3; 8];
+const APP_ID: &str = "LMRM26A744.xyz.elus.aegis.app-attester";
+
+fn receipt(journal: Vec<u8>) -> Vec<u8> {
+    let claim = ReceiptClaim::ok(IMAGE_ID, journal.clone());
+    bincode::serialize(&Receipt::new(InnerReceipt::Fake(FakeReceipt::new(claim)), journal)).unwrap()
+}
+
+fn guest_policy() -> VerificationPolicy {
+    VerificationPolicy::for_app_ids(&[APP_ID])
+}
+
+fn assertion_receipt(policy_hash: [u8; 32]) -> Vec<u8> {
+    let journal = AssertionJournal {
+        policy: guest_policy().policy,
+        rp_id_hash: [1; 32],
+        public_key_x: [2; 32],
+        public_key_y: [3; 32],
+        signature_r: [4; 32],
+        signature_s: [5; 32],
+        counter: 1,
+        client_data_hash: [6; 32],
+        attestation_image_id: risc0_zkvm::sha::Digest::from(ATTESTATION_IMAGE_ID).into(),
+        attestation_claim_digest: [8; 32],
+        challenge_hash: [9; 32],
+        allowlist_entry: 0,
+        session_nonce: [10; 32],
+        input_digest: [11; 32],
+        policy_hash: Some(policy_hash),
+        cert_chain_image_id: Some(risc0_zkvm::sha::Digest::from(CERT_CHAIN_IMAGE_ID).into()),
+    };
+    receipt(journal.encode())
+}
+
+fn attestation_receipt(policy_hash: [u8; 32]) -> Vec<u8> {
+    let journal = AttestationJournal {
+        policy: guest_policy().policy,
+        public_key: [4; 65],
+        key_id: [1; 32],
+        rp_id_hash: [2; 32],
+        environment: AttestationJournal::DEVELOPMENT,
+        timestamp: 1_751_800_000,
+        time_signer: [0; 32],
+        allowlist_entry: NO_ALLOWLIST,
+        cert_chain_image_id: risc0_zkvm::sha::Digest::from(CERT_CHAIN_IMAGE_ID).into(),
+        cert_chain_claim_digest: [3; 32],
+        input_digest: [5; 32],
+        policy_hash: Some(policy_hash),
+    };
+    receipt(journal.encode())
+}
+
+#[test]
+fn receipts_committing_the_expected_policy_verify() {
+    std::env::set_var("RISC0_DEV_MODE", "1");
+    // The server builds its policy from its config, not from the guest's constants.
+    let expected = VerificationPolicy::for_app_ids(&[APP_ID.to_string()]);
+    let committed = guest_policy().hash();
+
+    let assertion = assertion_receipt(committed);
+    let verified =
+        verify_assertion_receipt(&assertion, IMAGE_ID, ATTESTATION_IMAGE_ID, CERT_CHAIN_IMAGE_ID, Some(&expected));
+    assert!(verified.is_ok());
+    let attestation = attestation_receipt(committed);
+    assert!(verify_attestation_receipt(&attestation, IMAGE_ID, CERT_CHAIN_IMAGE_ID, Some(&expected)).is_ok());
+}
+
+#[test]
+fn receipts_committing_another_policy_are_rejected() {
+    std::env::set_var("RISC0_DEV_MODE", "1");
+    let committed = guest_policy().hash();
+    let other_app = VerificationPolicy::for_app_ids(&[APP_ID, "TEAMID.com.example.other"]);
+    let mut high_s = guest_policy();
+    high_s.policy.low_s = false;
+
+    for expected in [other_app, high_s] {
+        let assertion = assertion_receipt(committed);
+        assert!(matches!(
+            verify_assertion_receipt(&assertion, IMAGE_ID, ATTESTATION_IMAGE_ID, CERT_CHAIN_IMAGE_ID, Some(&expected)),
+            Err(VerifierError::VerificationFailed(msg)) if msg.contains(&hex::encode(committed))
+        ));
+        let attestation = attestation_receipt(committed);
+        assert!(matches!(
+            verify_attestation_receipt(&attestation, IMAGE_ID, CERT_CHAIN_IMAGE_ID, Some(&expected)),
+            Err(VerifierError::VerificationFailed(_))
+        ));
+    }
+    // Without an expected policy the hash is not looked at.
+    let assertion = assertion_receipt([0; 32]);
+    assert!(verify_assertion_receipt(&assertion, IMAGE_ID, ATTESTATION_IMAGE_ID, CERT_CHAIN_IMAGE_ID, None).is_ok());
+}
+
+#[test]
+fn policies_cross_the_bindings_unchanged() {
+    let policy = guest_policy();
+    let data = VerificationPolicyData::from(&policy);
+    assert_eq!(data.app_id_hashes.len(), 1);
+    assert_eq!(VerificationPolicy::try_from(data.clone()), Ok(policy.clone()));
+    assert!(check_policy_hash(Some(&policy.hash()), &VerificationPolicy::try_from(data.clone()).unwrap()).is_ok());
+
+    let truncated = VerificationPolicyData {
+        app_id_hashes: vec![vec![0; 31]],
+        ..data
+    };
+    assert_eq!(
+        VerificationPolicy::try_from(truncated),
+        Err(VerifierError::VerificationFailed("app ID hash is 31 bytes, expected 32".into()))
+    );
+    assert_eq!(Policy::from(PolicyData::from(policy.policy)), guest_policy().policy);
+}